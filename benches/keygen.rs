@@ -0,0 +1,35 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use diamond_io::{
+    bgg::sampler::BGGPublicKeySampler,
+    poly::{
+        dcrt::{DCRTPolyHashSampler, DCRTPolyParams, DCRTPolyUniformSampler},
+        sampler::PolyUniformSampler,
+    },
+};
+use keccak_asm::Keccak256;
+
+fn bench_keygen(c: &mut Criterion) {
+    let params = DCRTPolyParams::new(4, 2, 17, 1);
+    let ell = 128;
+    let d = 1;
+    let key: [u8; 32] = rand::random();
+    let tag = b"bench_keygen";
+    let reveal_plaintexts = vec![true; ell];
+    let sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+    let uniform_sampler = DCRTPolyUniformSampler::new();
+
+    c.bench_with_input(BenchmarkId::new("Keygen hash", ell), &ell, |b, _| {
+        b.iter(|| {
+            let _ = sampler.sample(&params, tag, &reveal_plaintexts);
+        })
+    });
+
+    c.bench_with_input(BenchmarkId::new("Keygen uniform NTT", ell), &ell, |b, _| {
+        b.iter(|| {
+            let _ = sampler.sample_uniform(&params, &uniform_sampler, &reveal_plaintexts);
+        })
+    });
+}
+
+criterion_group!(benches, bench_keygen);
+criterion_main!(benches);
@@ -0,0 +1,48 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use diamond_io::poly::{
+    native::{NativePoly, NativePolyMatrix, NativePolyParams, NativePolyUniformSampler},
+    sampler::{DistType, PolyUniformSampler},
+    PolyMatrix,
+};
+use num_bigint::BigUint;
+
+/// The product with one schoolbook multiplication per pair of entries.
+fn naive_mul(a: &NativePolyMatrix, b: &NativePolyMatrix) -> Vec<Vec<NativePoly>> {
+    (0..a.row_size())
+        .map(|i| {
+            (0..b.col_size())
+                .map(|j| {
+                    (0..a.col_size())
+                        .map(|k| a.entry(i, k) * b.entry(k, j))
+                        .reduce(|acc, prod| acc + prod)
+                        .unwrap()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn bench_native_matrix_mul(c: &mut Criterion) {
+    let sampler = NativePolyUniformSampler::new();
+    for ring_dimension in [64, 256] {
+        let params = NativePolyParams::new(ring_dimension, (BigUint::from(1u8) << 110) - 1u8, 10);
+        let size = 4;
+        let lhs = sampler.sample_uniform(&params, size, size, DistType::FinRingDist);
+        let rhs = sampler.sample_uniform(&params, size, size, DistType::FinRingDist);
+
+        c.bench_with_input(BenchmarkId::new("NTT Multiplication", ring_dimension), &size, |b, _| {
+            b.iter(|| {
+                let _ = lhs.clone() * &rhs;
+            })
+        });
+
+        c.bench_with_input(BenchmarkId::new("Naive Multiplication", ring_dimension), &size, |b, _| {
+            b.iter(|| {
+                let _ = naive_mul(&lhs, &rhs);
+            })
+        });
+    }
+}
+
+criterion_group!(benches, bench_native_matrix_mul);
+criterion_main!(benches);
@@ -0,0 +1,82 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use diamond_io::poly::{
+    dcrt::{DCRTPolyHashSampler, DCRTPolyMatrix, DCRTPolyParams, DCRTPolyPrfSampler},
+    sampler::{DistType, PolyHashSampler},
+    PolyMatrix,
+};
+use keccak_asm::Keccak256;
+
+/// The hashed matrix built the way it was before tiling: one flat vector of every entry,
+/// generated serially and then sliced into rows.
+fn flat_sample_hash(
+    sampler: &DCRTPolyHashSampler<Keccak256>,
+    params: &DCRTPolyParams,
+    key: [u8; 32],
+    tag: &[u8],
+    nrow: usize,
+    ncol: usize,
+    dist: DistType,
+) -> DCRTPolyMatrix {
+    let entries = (0..nrow * ncol)
+        .map(|idx| sampler.sample_hash_entry(params, key, tag, idx / ncol, idx % ncol, dist))
+        .collect::<Vec<_>>();
+    let rows = entries.chunks(ncol).map(|row| row.to_vec()).collect();
+    DCRTPolyMatrix::from_poly_vec(params, rows)
+}
+
+fn bench_hash_vs_prf(c: &mut Criterion) {
+    let params = DCRTPolyParams::new(4, 2, 17, 1);
+    let (nrow, ncol) = (100, 300);
+    let key: [u8; 32] = rand::random();
+    let tag = b"bench_sampler";
+    let hash_sampler = DCRTPolyHashSampler::<Keccak256>::new();
+    let prf_sampler = DCRTPolyPrfSampler::new();
+
+    for dist in [DistType::FinRingDist, DistType::BitDist] {
+        c.bench_with_input(
+            BenchmarkId::new("Hash sampler", format!("{:?}", dist)),
+            &dist,
+            |b, _| {
+                b.iter(|| {
+                    let _ = hash_sampler.sample_hash(&params, key, tag, nrow, ncol, dist);
+                })
+            },
+        );
+
+        c.bench_with_input(
+            BenchmarkId::new("PRF sampler", format!("{:?}", dist)),
+            &dist,
+            |b, _| {
+                b.iter(|| {
+                    let _ = prf_sampler.sample_hash(&params, key, tag, nrow, ncol, dist);
+                })
+            },
+        );
+    }
+}
+
+fn bench_hash_tiles(c: &mut Criterion) {
+    let params = DCRTPolyParams::new(4, 2, 17, 1);
+    let key: [u8; 32] = rand::random();
+    let tag = b"bench_tiles";
+    let sampler = DCRTPolyHashSampler::<Keccak256>::new();
+    let dist = DistType::FinRingDist;
+
+    for (nrow, ncol) in [(10, 30), (100, 300)] {
+        let size = format!("{}x{}", nrow, ncol);
+        c.bench_with_input(BenchmarkId::new("Hash sampler tiled", &size), &size, |b, _| {
+            b.iter(|| {
+                let _ = sampler.sample_hash(&params, key, tag, nrow, ncol, dist);
+            })
+        });
+
+        c.bench_with_input(BenchmarkId::new("Hash sampler flat", &size), &size, |b, _| {
+            b.iter(|| {
+                let _ = flat_sample_hash(&sampler, &params, key, tag, nrow, ncol, dist);
+            })
+        });
+    }
+}
+
+criterion_group!(benches, bench_hash_vs_prf, bench_hash_tiles);
+criterion_main!(benches);
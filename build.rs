@@ -1,17 +1,19 @@
 fn main() {
     println!("cargo::rerun-if-changed=src/main.rs");
 
-    // linking openFHE
-    println!("cargo::rustc-link-arg=-L/usr/local/lib");
-    println!("cargo::rustc-link-arg=-lOPENFHEpke");
-    println!("cargo::rustc-link-arg=-lOPENFHEbinfhe");
-    println!("cargo::rustc-link-arg=-lOPENFHEcore");
+    // linking openFHE, unless the crate is built without the DCRT backend
+    if std::env::var_os("CARGO_FEATURE_OPENFHE").is_some() {
+        println!("cargo::rustc-link-arg=-L/usr/local/lib");
+        println!("cargo::rustc-link-arg=-lOPENFHEpke");
+        println!("cargo::rustc-link-arg=-lOPENFHEbinfhe");
+        println!("cargo::rustc-link-arg=-lOPENFHEcore");
 
-    // linking OpenMP
-    println!("cargo::rustc-link-arg=-fopenmp");
+        // linking OpenMP
+        println!("cargo::rustc-link-arg=-fopenmp");
 
-    // necessary to avoid LD_LIBRARY_PATH
-    println!("cargo::rustc-link-arg=-Wl,-rpath,/usr/local/lib");
+        // necessary to avoid LD_LIBRARY_PATH
+        println!("cargo::rustc-link-arg=-Wl,-rpath,/usr/local/lib");
+    }
 
     // Only execute the following code when the "bgm" feature is enabled
     #[cfg(feature = "bgm")]
@@ -0,0 +1,165 @@
+//! Predicts the sizes and evaluation times of an obfuscation and recommends parameters for an
+//! input length `ell`, a multiplicative depth and a security level. Polynomial operation
+//! timings are calibrated by a micro-benchmark once per parameter set and persisted, so later
+//! runs on the same machine are instant.
+use clap::Parser;
+use diamond_io::{
+    io::estimate::{ObfuscationShape, PolyOpTimings},
+    poly::{PolyParams, dcrt::DCRTPolyParams},
+    security::{AuditConfig, audit},
+    utils::create_random_poly,
+};
+use std::{collections::BTreeMap, fs, path::PathBuf, time::Duration};
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Number of input bits
+    #[arg(long)]
+    ell: usize,
+
+    /// Multiplicative depth of the obfuscated circuit
+    #[arg(long)]
+    depth: usize,
+
+    /// Targeted bits of security
+    #[arg(long, default_value_t = 128.0)]
+    security: f64,
+
+    #[arg(long, default_value_t = 1)]
+    d: usize,
+
+    #[arg(long, default_value_t = 51)]
+    crt_bits: usize,
+
+    #[arg(long, default_value_t = 17)]
+    base_bits: u32,
+
+    #[arg(long, default_value_t = 8)]
+    level_width: usize,
+
+    #[arg(long, default_value_t = 4.578)]
+    encoding_sigma: f64,
+
+    /// Number of packed output polynomials
+    #[arg(long, default_value_t = 1)]
+    outputs: usize,
+
+    /// Where micro-benchmark results are persisted
+    #[arg(long, default_value = "diamond-calc-calibration.json")]
+    calibration: PathBuf,
+}
+
+/// The smallest ring dimension, and for it the fewest CRT towers, whose error budget covers
+/// the depth and whose estimated security reaches the target.
+fn recommend(args: &Args) -> Option<DCRTPolyParams> {
+    let config = AuditConfig {
+        secret_size: args.d,
+        error_sigma: args.encoding_sigma,
+        flooding_sigma: None,
+        circuit_depth: args.depth,
+    };
+    for log_n in 10..=17 {
+        let n = 1u32 << log_n;
+        if (n as usize) < args.level_width {
+            continue;
+        }
+        for crt_depth in 1..=64 {
+            // Skip the combinations OpenFHE cannot generate a modulus for
+            let Ok(params) = DCRTPolyParams::try_new(n, crt_depth, args.crt_bits, args.base_bits)
+            else {
+                continue;
+            };
+            let report = audit(&params, &config);
+            if report.correctness_slack_bits < 10.0 {
+                continue;
+            }
+            // More towers only lower the security, so try the next ring dimension
+            if report.security_bits >= args.security {
+                return Some(params);
+            }
+            break;
+        }
+    }
+    None
+}
+
+fn calibrate(args: &Args, params: &DCRTPolyParams) -> PolyOpTimings {
+    let key = format!(
+        "n={} crt_depth={} crt_bits={}",
+        params.ring_dimension(),
+        params.crt_depth(),
+        params.crt_bits()
+    );
+    let mut cache: BTreeMap<String, PolyOpTimings> = fs::read_to_string(&args.calibration)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    if let Some(timings) = cache.get(&key) {
+        return *timings;
+    }
+    let timings =
+        PolyOpTimings::measure(&create_random_poly(params), &create_random_poly(params), 20);
+    cache.insert(key, timings);
+    fs::write(&args.calibration, serde_json::to_string_pretty(&cache).unwrap())
+        .expect("failed to persist the calibration");
+    timings
+}
+
+fn mib(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+fn main() {
+    let args = Args::parse();
+    let Some(params) = recommend(&args) else {
+        eprintln!(
+            "no ring dimension up to 2^17 reaches {} bits of security at depth {}",
+            args.security, args.depth
+        );
+        std::process::exit(1);
+    };
+    let shape = ObfuscationShape {
+        ring_dimension: params.ring_dimension() as usize,
+        crt_depth: params.crt_depth(),
+        crt_bits: params.crt_bits(),
+        base_bits: params.base_bits(),
+        d: args.d,
+        input_size: args.ell,
+        level_width: args.level_width,
+        packed_output_size: args.outputs,
+    };
+    let report = audit(
+        &params,
+        &AuditConfig {
+            secret_size: args.d,
+            error_sigma: args.encoding_sigma,
+            flooding_sigma: None,
+            circuit_depth: args.depth,
+        },
+    );
+    println!("recommended parameters");
+    println!("  ring_dimension = {}", shape.ring_dimension);
+    println!("  crt_depth = {}", shape.crt_depth);
+    println!("  crt_bits = {}", shape.crt_bits);
+    println!("  base_bits = {}", shape.base_bits);
+    println!("  log q = {}, about {:.0} bits of security", report.log_q, report.security_bits);
+
+    let sizes = shape.estimate_sizes();
+    println!("sizes");
+    println!("  polynomial: {} bytes", sizes.poly_bytes);
+    println!("  ciphertext: {:.2} MiB", mib(sizes.ciphertext_bytes));
+    println!("  preimages: {:.2} MiB", mib(sizes.preimage_bytes));
+    println!("  obfuscation: {:.2} MiB", mib(sizes.obfuscation_bytes));
+
+    let timings = calibrate(&args, &params);
+    let mul_gate = timings.mul_gate(&shape);
+    println!("timings (calibrated in {})", args.calibration.display());
+    println!("  ring add: {:?}, ring mul: {:?}", timings.add, timings.mul);
+    println!("  add gate: {:?}", timings.add_gate(&shape));
+    println!("  mul gate: {:?}", mul_gate);
+    println!("  depth-{} mul chain: {:?}", args.depth, mul_gate * args.depth as u32);
+    if mul_gate > Duration::from_secs(60) {
+        println!("  multiplications take minutes; consider a larger base_bits");
+    }
+}
@@ -0,0 +1,203 @@
+//! Generates everything an obfuscator needs ahead of time: ring parameters for a security
+//! preset, the hash-sampler key the public matrices are expanded from, and either the BGG public
+//! keys of `ell` attributes or, with `--trapdoor`, a trapdoor and its public matrix.
+//!
+//! The output directory holds `params.json` in the format of [`SerializableObfuscationParams`],
+//! `hash_key.bin` (the 32 raw key bytes) and either `pubkeys.keys` in the key cache format or
+//! the `trapdoor_a`, `trapdoor_r` and `trapdoor_e` matrix files of the initial `b_star`
+//! trapdoor, which `dio run-bench --keys` passes to the obfuscator.
+use clap::{Parser, ValueEnum};
+use diamond_io::{
+    bgg::{key_cache::write_pubkeys, sampler::BGGPublicKeySampler},
+    io::{
+        serde::SerializableObfuscationParams,
+        utils::{packed_input_size, reveal_plaintexts, sample_public_key_by_id},
+    },
+    migrate::OBFUSCATION_PARAMS_VERSION,
+    poly::{
+        PolyMatrix, PolyParams,
+        dcrt::{DCRTPolyHashSampler, DCRTPolyParams, DCRTPolyTrapdoorSampler},
+        rng::OsRngSource,
+        sampler::PolyTrapdoorSampler,
+    },
+    security::{AuditConfig, AuditReport, audit},
+};
+use keccak_asm::Keccak256;
+use num_bigint::BigUint;
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::PathBuf,
+};
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Preset {
+    /// No security target, the smallest ring meeting the depth; for tests only
+    Test,
+    /// 80 bits of estimated security
+    Low,
+    /// 100 bits of estimated security
+    Medium,
+    /// 128 bits of estimated security
+    High,
+}
+
+impl Preset {
+    fn security_bits(self) -> f64 {
+        match self {
+            Preset::Test => 0.0,
+            Preset::Low => 80.0,
+            Preset::Medium => 100.0,
+            Preset::High => 128.0,
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Directory the parameters and keys are written to
+    #[arg(short, long)]
+    out: PathBuf,
+
+    #[arg(long, value_enum, default_value_t = Preset::High)]
+    preset: Preset,
+
+    /// Number of input bits
+    #[arg(long)]
+    ell: usize,
+
+    /// Multiplicative depth of the obfuscated circuit
+    #[arg(long)]
+    depth: usize,
+
+    /// Number of input bits consumed per level
+    #[arg(long, default_value_t = 1)]
+    level_width: usize,
+
+    #[arg(long, default_value_t = 1)]
+    d: usize,
+
+    /// Modulus of the output ciphertexts
+    #[arg(long)]
+    switched_modulus: BigUint,
+
+    /// Serialized public circuit the parameters are for
+    #[arg(long)]
+    public_circuit: PathBuf,
+
+    #[arg(long, default_value_t = 51)]
+    crt_bits: usize,
+
+    #[arg(long, default_value_t = 17)]
+    base_bits: u32,
+
+    #[arg(long, default_value_t = 4.578)]
+    encoding_sigma: f64,
+
+    #[arg(long, default_value_t = 4.578)]
+    hardcoded_key_sigma: f64,
+
+    #[arg(long, default_value_t = 4.578)]
+    p_sigma: f64,
+
+    /// Sample a trapdoor and its public matrix instead of hash-derived public keys
+    #[arg(long)]
+    trapdoor: bool,
+
+    #[arg(long, default_value_t = 4.578)]
+    trapdoor_sigma: f64,
+}
+
+/// The smallest ring dimension, and for it the fewest CRT towers, whose error budget covers
+/// the depth and whose estimated security reaches the preset.
+fn select_params(args: &Args) -> Option<(DCRTPolyParams, AuditReport)> {
+    let config = AuditConfig {
+        secret_size: args.d,
+        error_sigma: args.encoding_sigma,
+        flooding_sigma: None,
+        circuit_depth: args.depth,
+    };
+    for log_n in 10..=17 {
+        for crt_depth in 1..=64 {
+            // Skip the combinations OpenFHE cannot generate a modulus for
+            let Ok(params) =
+                DCRTPolyParams::try_new(1 << log_n, crt_depth, args.crt_bits, args.base_bits)
+            else {
+                continue;
+            };
+            let report = audit(&params, &config);
+            if report.correctness_slack_bits < 10.0 {
+                continue;
+            }
+            // More towers only lower the security, so try the next ring dimension
+            if report.security_bits >= args.preset.security_bits() {
+                return Some((params, report));
+            }
+            break;
+        }
+    }
+    None
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let Some((params, report)) = select_params(&args) else {
+        eprintln!(
+            "no ring dimension up to 2^17 reaches the {:?} preset at depth {}",
+            args.preset, args.depth
+        );
+        std::process::exit(1);
+    };
+    fs::create_dir_all(&args.out).expect("failed to create the output directory");
+
+    let obf_params = SerializableObfuscationParams {
+        version: OBFUSCATION_PARAMS_VERSION,
+        ring_dimension: params.ring_dimension(),
+        crt_depth: params.crt_depth(),
+        crt_bits: params.crt_bits(),
+        base_bits: params.base_bits(),
+        switched_modulus: args.switched_modulus.clone(),
+        input_size: args.ell,
+        level_width: args.level_width,
+        public_circuit_path: args.public_circuit.clone(),
+        d: args.d,
+        encoding_sigma: args.encoding_sigma,
+        hardcoded_key_sigma: args.hardcoded_key_sigma,
+        p_sigma: args.p_sigma,
+        trapdoor_sigma: args.trapdoor_sigma,
+    };
+    fs::write(args.out.join("params.json"), obf_params.to_json_str())
+        .expect("failed to write params.json");
+
+    let bgg_sampler =
+        BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::from_source(&OsRngSource, args.d);
+    fs::write(args.out.join("hash_key.bin"), bgg_sampler.hash_key())
+        .expect("failed to write hash_key.bin");
+
+    if args.trapdoor {
+        let trapdoor_sampler = DCRTPolyTrapdoorSampler::new(&params, args.trapdoor_sigma);
+        // The initial `b_star` has two blocks of `d + 1` rows
+        let (trapdoor, public_matrix) = trapdoor_sampler.trapdoor(&params, 2 * (args.d + 1));
+        public_matrix.write_to_files(&args.out, "trapdoor_a").await;
+        trapdoor.r.write_to_files(&args.out, "trapdoor_r").await;
+        trapdoor.e.write_to_files(&args.out, "trapdoor_e").await;
+    } else {
+        // The keys of the first input, as the obfuscator derives them from the same hash key
+        let packed_input_size = packed_input_size(args.ell, params.ring_dimension() as usize);
+        let reveal_plaintexts = reveal_plaintexts(packed_input_size);
+        let pubkeys = sample_public_key_by_id(&bgg_sampler, &params, 0, &reveal_plaintexts);
+        let file = File::create(args.out.join("pubkeys.keys")).expect("failed to create pubkeys");
+        write_pubkeys(&mut BufWriter::new(file), &pubkeys).expect("failed to write pubkeys");
+    }
+
+    println!("wrote keys to {}", args.out.display());
+    println!("  preset = {:?}", args.preset);
+    println!("  ring_dimension = {}", obf_params.ring_dimension);
+    println!("  crt_depth = {}", obf_params.crt_depth);
+    println!("  log q = {}, about {:.0} bits of security", report.log_q, report.security_bits);
+    if args.trapdoor {
+        println!("  the trapdoor files are secret; keep them off shared storage");
+    }
+}
@@ -34,6 +34,9 @@ pub struct RunBenchConfig {
     pub p_sigma: f64,
     #[serde(default = "default_trapdoor_sigma")]
     pub trapdoor_sigma: Option<f64>,
+    /// number of nonzero coefficients of ternary secrets, binary secrets if unset
+    #[serde(default)]
+    pub secret_hamming_weight: Option<usize>,
     /// polynomial ring dimension
     pub ring_dimension: u32,
     /// size of the tower
@@ -5,17 +5,23 @@ use config::{RunBenchConfig, SimBenchNormConfig};
 use diamond_io::utils::calculate_tmp_size;
 use diamond_io::{
     io::{
-        Obfuscation, obf::obfuscate, params::ObfuscationParams, utils::build_final_digits_circuit,
+        Obfuscation,
+        obf::{ObfuscationKeys, obfuscate, obfuscate_with_keys},
+        params::{KeygenConfig, ObfuscationParams},
+        serde::SerializableObfuscationParams,
+        utils::build_final_digits_circuit,
     },
     poly::{
-        Poly, PolyElem, PolyParams,
+        Poly, PolyElem, PolyMatrix, PolyParams,
         dcrt::{
             DCRTPoly, DCRTPolyHashSampler, DCRTPolyMatrix, DCRTPolyParams, DCRTPolyTrapdoorSampler,
-            DCRTPolyUniformSampler, FinRingElem,
+            DCRTPolyUniformSampler, FinRingElem, sampler::trapdoor::DCRTTrapdoor,
         },
+        dims::trapdoor_width,
+        rng::default_source,
         sampler::{DistType, PolyUniformSampler},
     },
-    utils::{calculate_directory_size, init_tracing},
+    utils::{calculate_directory_size, init_tracing, ThreadPoolConfig},
 };
 use num_traits::identities::One;
 
@@ -59,6 +65,10 @@ enum Commands {
 
         #[arg(long)]
         mul_num: usize,
+
+        /// Directory written by `diamond-keygen --trapdoor`, whose keys replace fresh ones
+        #[arg(long)]
+        keys: Option<PathBuf>,
     },
     SimBenchNorm {
         #[arg(short, long)]
@@ -80,7 +90,7 @@ async fn main() {
     init_tracing();
     let command = Args::parse().command;
     match command {
-        Commands::RunBench { config, obf_dir, verify, add_num, mul_num } => {
+        Commands::RunBench { config, obf_dir, verify, add_num, mul_num, keys } => {
             let contents = fs::read_to_string(&config).unwrap();
             let dio_config: RunBenchConfig = toml::from_str(&contents).unwrap();
             let dir = Path::new(&obf_dir);
@@ -114,19 +124,50 @@ async fn main() {
                 hardcoded_key_sigma: dio_config.hardcoded_key_sigma,
                 p_sigma: dio_config.p_sigma,
                 trapdoor_sigma: dio_config.trapdoor_sigma.unwrap_or_default(),
+                keygen: dio_config
+                    .secret_hamming_weight
+                    .map_or_else(KeygenConfig::default, KeygenConfig::ternary),
+                key_cache: None,
             };
             let sampler_uniform = DCRTPolyUniformSampler::new();
-            let mut rng = rand::rng();
             let hardcoded_key = sampler_uniform.sample_poly(&params, &DistType::BitDist);
-            obfuscate::<
-                DCRTPolyMatrix,
-                DCRTPolyUniformSampler,
-                DCRTPolyHashSampler<Keccak256>,
-                DCRTPolyTrapdoorSampler,
-                _,
-                _,
-            >(obf_params.clone(), hardcoded_key.clone(), &mut rng, &obf_dir)
-            .await;
+            let pool = ThreadPoolConfig::global();
+            match keys {
+                Some(keys_dir) => {
+                    let keys = read_keys(&keys_dir, &params, &dio_config);
+                    obfuscate_with_keys::<
+                        DCRTPolyMatrix,
+                        DCRTPolyUniformSampler,
+                        DCRTPolyHashSampler<Keccak256>,
+                        DCRTPolyTrapdoorSampler,
+                        _,
+                    >(
+                        obf_params.clone(),
+                        hardcoded_key.clone(),
+                        keys,
+                        default_source(),
+                        &obf_dir,
+                        &pool,
+                    )
+                    .await
+                }
+                None => {
+                    obfuscate::<
+                        DCRTPolyMatrix,
+                        DCRTPolyUniformSampler,
+                        DCRTPolyHashSampler<Keccak256>,
+                        DCRTPolyTrapdoorSampler,
+                        _,
+                    >(
+                        obf_params.clone(),
+                        hardcoded_key.clone(),
+                        default_source(),
+                        &obf_dir,
+                        &pool,
+                    )
+                    .await
+                }
+            }
             let obfuscation_time = start_time.elapsed();
             info!("Time to obfuscate: {:?}", obfuscation_time);
 
@@ -226,3 +267,29 @@ async fn main() {
         }
     }
 }
+
+/// Reads the keys `diamond-keygen --trapdoor` wrote to `dir`, which must have been generated for
+/// the ring parameters, `d` and input size of `config`.
+fn read_keys(
+    dir: &Path,
+    params: &DCRTPolyParams,
+    config: &RunBenchConfig,
+) -> ObfuscationKeys<DCRTTrapdoor, DCRTPolyMatrix> {
+    let contents = fs::read_to_string(dir.join("params.json")).unwrap();
+    let keygen_params = SerializableObfuscationParams::from_json_str(&contents);
+    let keygen_ring = keygen_params.ring_params().unwrap();
+    assert!(keygen_ring == *params, "keys generated for other ring parameters");
+    assert_eq!(keygen_params.d, config.d, "keys generated for another d");
+    assert_eq!(keygen_params.input_size, config.input_size, "keys generated for other inputs");
+
+    let hash_key = fs::read(dir.join("hash_key.bin")).unwrap();
+    let hash_key = hash_key.try_into().expect("hash_key.bin does not hold 32 bytes");
+    let size = 2 * (config.d + 1);
+    let log_base_q = params.modulus_digits();
+    let read = |id: &str, ncol: usize| DCRTPolyMatrix::read_from_files(params, size, ncol, dir, id);
+    let r = read("trapdoor_r", size * log_base_q);
+    let e = read("trapdoor_e", size * log_base_q);
+    let trapdoor = DCRTTrapdoor { r, e };
+    let public_matrix = read("trapdoor_a", trapdoor_width(size, log_base_q));
+    ObfuscationKeys { hash_key, initial_trapdoor: Some((trapdoor, public_matrix)) }
+}
@@ -0,0 +1,31 @@
+//! Decodes arbitrary bytes as coefficients and checks that whatever is accepted re-encodes to
+//! the same bytes, i.e. that the canonical encoding is a bijection on valid inputs.
+#![no_main]
+
+use diamond_io::poly::{
+    canonical::{coeff_width, decode_coeffs, encode_coeffs},
+    dcrt::FinRingElem,
+};
+use libfuzzer_sys::fuzz_target;
+use num_bigint::BigUint;
+use std::sync::Arc;
+
+fuzz_target!(|data: &[u8]| {
+    // The first byte picks the modulus width and the next ones the modulus itself
+    let Some((&width, data)) = data.split_first() else { return };
+    let width = (width % 16) as usize + 1;
+    if data.len() < width {
+        return;
+    }
+    let (modulus, data) = data.split_at(width);
+    let modulus = BigUint::from_bytes_le(modulus);
+    if modulus < BigUint::from(2u8) {
+        return;
+    }
+    let width = coeff_width(&modulus);
+    let modulus = Arc::new(modulus);
+    let len = data.len() / width;
+    if let Ok(coeffs) = decode_coeffs::<FinRingElem>(&modulus, len, data) {
+        assert_eq!(encode_coeffs(&coeffs), data);
+    }
+});
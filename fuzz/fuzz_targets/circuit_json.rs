@@ -0,0 +1,16 @@
+//! Parses arbitrary strings as serialized circuits and checks that malformed circuits are
+//! rejected with an error, and that every accepted circuit round-trips through its
+//! serialization.
+#![no_main]
+
+use diamond_io::bgg::circuit::serde::SerializablePolyCircuit;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(json) = std::str::from_utf8(data) else { return };
+    let Ok(serializable) = SerializablePolyCircuit::try_from_json_str(json) else { return };
+    let Ok(circuit) = serializable.try_to_circuit() else { return };
+    let json = SerializablePolyCircuit::from_circuit(&circuit).to_json_str();
+    let reparsed = SerializablePolyCircuit::try_from_json_str(&json).unwrap();
+    assert_eq!(reparsed.try_to_circuit().unwrap(), circuit);
+});
@@ -0,0 +1,37 @@
+//! Reads arbitrary bytes as encoding shards, evaluation key streams and attribute envelopes,
+//! which must fail with an error rather than panic or allocate the lengths they claim, and
+//! checks that whatever is accepted re-serializes to the same value.
+#![no_main]
+
+use diamond_io::{
+    bgg::{
+        envelope::{read_envelope, write_envelope},
+        eval_key::EvalKeyReader,
+        shard::{read_shard, write_shard},
+    },
+    poly::native::{NativePolyMatrix, NativePolyParams},
+};
+use libfuzzer_sys::fuzz_target;
+use num_bigint::BigUint;
+use std::io::Cursor;
+
+fuzz_target!(|data: &[u8]| {
+    let params = NativePolyParams::new(4, (BigUint::from(1u8) << 61) - 1u8, 8);
+
+    if let Ok(shard) = read_shard::<_, NativePolyMatrix>(&mut Cursor::new(data), &params) {
+        let mut bytes = Vec::new();
+        write_shard(&mut bytes, &shard).unwrap();
+        let reread = read_shard::<_, NativePolyMatrix>(&mut Cursor::new(bytes), &params).unwrap();
+        assert_eq!(reread, shard);
+    }
+
+    if let Ok(mut reader) = EvalKeyReader::new(Cursor::new(data)) {
+        while let Ok(Some(_)) = reader.read_key::<NativePolyMatrix>(&params) {}
+    }
+
+    if let Ok(envelope) = read_envelope(&mut Cursor::new(data)) {
+        let mut bytes = Vec::new();
+        write_envelope(&mut bytes, &envelope).unwrap();
+        assert_eq!(read_envelope(&mut Cursor::new(bytes)).unwrap(), envelope);
+    }
+});
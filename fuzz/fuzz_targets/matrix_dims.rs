@@ -0,0 +1,55 @@
+//! Builds small matrices of arbitrary shapes and checks the shape algebra of slicing,
+//! concatenation and transposition against the entries it should preserve.
+#![no_main]
+
+use diamond_io::poly::{
+    dcrt::FinRingElem,
+    native::{NativePoly, NativePolyMatrix, NativePolyParams},
+    Poly, PolyMatrix, PolyParams,
+};
+use libfuzzer_sys::fuzz_target;
+use num_bigint::BigUint;
+
+fuzz_target!(|data: &[u8]| {
+    // The first four bytes pick the shape and the split points, the rest fills the entries
+    let Some((&[nrow, ncol, row_split, col_split], data)) = data.split_first_chunk::<4>() else {
+        return;
+    };
+    let (nrow, ncol) = (nrow as usize % 6 + 1, ncol as usize % 6 + 1);
+    let (row_split, col_split) = (row_split as usize % (nrow + 1), col_split as usize % (ncol + 1));
+    let params = NativePolyParams::new(4, (BigUint::from(1u8) << 61) - 1u8, 8);
+    let modulus = params.modulus();
+    let entries = (0..nrow)
+        .map(|i| {
+            (0..ncol)
+                .map(|j| {
+                    let value = data.get(i * ncol + j).copied().unwrap_or(0);
+                    NativePoly::from_const(&params, &FinRingElem::new(value, modulus.clone()))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let matrix = NativePolyMatrix::from_poly_vec(&params, entries);
+    assert_eq!(matrix.size(), (nrow, ncol));
+
+    // Splitting at any point and concatenating the parts gives the matrix back
+    if row_split > 0 && row_split < nrow {
+        let top = matrix.slice_rows(0, row_split);
+        let bottom = matrix.slice_rows(row_split, nrow);
+        assert_eq!(top.row_size() + bottom.row_size(), nrow);
+        assert_eq!(top.concat_rows(&[&bottom]), matrix);
+    }
+    if col_split > 0 && col_split < ncol {
+        let left = matrix.slice_columns(0, col_split);
+        let right = matrix.slice_columns(col_split, ncol);
+        assert_eq!(left.concat_columns(&[&right]), matrix);
+    }
+
+    // Transposition swaps the shape and is an involution
+    let transposed = matrix.transpose();
+    assert_eq!(transposed.size(), (ncol, nrow));
+    assert_eq!(transposed.transpose(), matrix);
+    let product = matrix.clone() * &transposed;
+    assert_eq!(product.size(), (nrow, nrow));
+    assert_eq!(product.transpose(), product);
+});
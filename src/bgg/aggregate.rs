@@ -0,0 +1,260 @@
+use super::{BggEncoding, BggPublicKey};
+use crate::poly::{Poly, PolyElem, PolyMatrix, PolyParams};
+
+/// Error returned when encodings cannot be aggregated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AggregateError {
+    Empty,
+    /// The encoding at `index` was evaluated under a different public key than the first one.
+    PublicKeyMismatch {
+        index: usize,
+    },
+    /// Summing `width` errors of `error_bits` bits may exceed `q/4`.
+    NoiseBudget {
+        width: usize,
+        max_width: usize,
+    },
+    /// [`aggregate_vote`] was given a different number of weights than of votes.
+    WeightCount {
+        votes: usize,
+        weights: usize,
+    },
+}
+
+impl std::fmt::Display for AggregateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "no encodings to aggregate"),
+            Self::PublicKeyMismatch { index } => {
+                write!(f, "encoding {} has a different public key", index)
+            }
+            Self::NoiseBudget { width, max_width } => write!(
+                f,
+                "cannot aggregate {} encodings, at most {} fit the noise budget",
+                width, max_width
+            ),
+            Self::WeightCount { votes, weights } => {
+                write!(f, "{} votes but {} weights", votes, weights)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AggregateError {}
+
+/// The largest number of encodings whose errors, each below `2^error_bits`, can be summed while
+/// the total error stays below `q/4`, i.e. `2^(log q - 3 - error_bits)`.
+pub fn max_width<P: PolyParams>(params: &P, error_bits: usize) -> usize {
+    let budget_bits = params.modulus_bits().saturating_sub(3 + error_bits);
+    1usize.checked_shl(budget_bits as u32).unwrap_or(usize::MAX)
+}
+
+/// Homomorphically sums encodings from independent ciphertexts under the same secret, each
+/// evaluated with the same circuit and public keys, e.g. to count how many users satisfy a
+/// policy with a 0/1-valued circuit. The result encodes the sum of the plaintexts under the sum
+/// of the public keys, which the key side recomputes as `encodings.len()` times the evaluated
+/// public key. `error_bits` bounds the bits of the error of each input.
+pub fn aggregate_sum<M: PolyMatrix>(
+    params: &<M::P as Poly>::Params,
+    encodings: &[BggEncoding<M>],
+    error_bits: usize,
+) -> Result<BggEncoding<M>, AggregateError> {
+    check_budget(params, encodings, encodings.len(), error_bits)?;
+    Ok(encodings[1..].iter().fold(encodings[0].clone(), |sum, enc| sum + enc))
+}
+
+/// Homomorphically tallies weighted votes, e.g. weighted by stake: the encodings of 0/1-valued
+/// outputs of the same circuit and public keys, each scaled by the public weight of its voter.
+/// The result encodes `sum_i w_i x_i` under `sum_i w_i` times the evaluated public key, so
+/// comparing the decrypted tally with half the total weight decides a weighted majority. An
+/// error scaled by `w` grows like the sum of `w` errors, so the noise budget is that of
+/// [`aggregate_sum`] over `sum_i w_i` encodings.
+pub fn aggregate_vote<M: PolyMatrix>(
+    params: &<M::P as Poly>::Params,
+    votes: &[BggEncoding<M>],
+    weights: &[usize],
+    error_bits: usize,
+) -> Result<BggEncoding<M>, AggregateError> {
+    if votes.len() != weights.len() {
+        return Err(AggregateError::WeightCount { votes: votes.len(), weights: weights.len() });
+    }
+    let total_weight =
+        weights.iter().try_fold(0usize, |acc, &w| acc.checked_add(w)).unwrap_or(usize::MAX);
+    check_budget(params, votes, total_weight, error_bits)?;
+    let scaled = votes.iter().zip(weights).map(|(vote, &weight)| scale(params, vote, weight));
+    Ok(scaled.reduce(|sum, vote| sum + &vote).expect("votes are not empty"))
+}
+
+/// Checks that `encodings` share the public key of the first one and that `width` errors fit
+/// the noise budget.
+fn check_budget<M: PolyMatrix>(
+    params: &<M::P as Poly>::Params,
+    encodings: &[BggEncoding<M>],
+    width: usize,
+    error_bits: usize,
+) -> Result<(), AggregateError> {
+    let first = encodings.first().ok_or(AggregateError::Empty)?;
+    if let Some(index) = encodings.iter().position(|enc| enc.pubkey != first.pubkey) {
+        return Err(AggregateError::PublicKeyMismatch { index });
+    }
+    let max_width = max_width(params, error_bits);
+    if width > max_width {
+        return Err(AggregateError::NoiseBudget { width, max_width });
+    }
+    Ok(())
+}
+
+/// The encoding of `weight * x` under `weight * A` from the encoding of `x` under `A`.
+fn scale<M: PolyMatrix>(
+    params: &<M::P as Poly>::Params,
+    encoding: &BggEncoding<M>,
+    weight: usize,
+) -> BggEncoding<M> {
+    let weight = <M::P as Poly>::Elem::constant(&params.modulus(), weight as u64);
+    let weight = M::P::from_const(params, &weight);
+    let pubkey = &encoding.pubkey;
+    let pubkey = BggPublicKey::new(pubkey.matrix.clone() * &weight, pubkey.reveal_plaintext);
+    let plaintext = encoding.plaintext.as_ref().map(|plaintext| plaintext.clone() * &weight);
+    BggEncoding::new(encoding.vector.clone() * &weight, pubkey, plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::{
+            circuit::PolyCircuit,
+            sampler::{BGGEncodingSampler, BGGPublicKeySampler},
+        },
+        poly::{
+            dcrt::{
+                DCRTPoly, DCRTPolyHashSampler, DCRTPolyMatrix, DCRTPolyParams,
+                DCRTPolyUniformSampler, FinRingElem,
+            },
+            sampler::PolyUniformSampler,
+        },
+        utils::{create_bit_random_poly, create_random_poly},
+    };
+    use keccak_asm::Keccak256;
+
+    #[test]
+    fn test_aggregate_sum() {
+        let key: [u8; 32] = rand::random();
+        let params = DCRTPolyParams::default();
+        let d = 3;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let pubkeys = bgg_sampler.sample(&params, b"aggregate", &[true, true]);
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+
+        // The policy x1 * x2 evaluated for several users
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        let mul_gate = circuit.mul_gate(inputs[0], inputs[1]);
+        circuit.output(vec![mul_gate]);
+        let num_users = 4;
+        let outputs = (0..num_users)
+            .map(|_| {
+                let plaintexts = vec![create_bit_random_poly(&params); 2];
+                let encodings = bgg_sampler.sample(&params, &pubkeys, &plaintexts);
+                circuit.eval(&params, &encodings[0], &encodings[1..]).pop().unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        // The sum encodes the sum of the outputs under num_users times the output public key
+        let sum = aggregate_sum(&params, &outputs, 0).unwrap();
+        let expected_plaintext = outputs
+            .iter()
+            .fold(DCRTPoly::const_zero(&params), |acc, enc| acc + enc.plaintext.as_ref().unwrap());
+        assert_eq!(sum.plaintext, Some(expected_plaintext.clone()));
+        let width = FinRingElem::new(num_users as u64, params.modulus());
+        let expected_matrix =
+            outputs[0].pubkey.matrix.clone() * DCRTPoly::from_const(&params, &width);
+        assert_eq!(sum.pubkey.matrix, expected_matrix);
+        let gadget = DCRTPolyMatrix::gadget_matrix(&params, d + 1);
+        assert_eq!(
+            sum.vector,
+            bgg_sampler.secret_vec.clone() * (expected_matrix - (gadget * expected_plaintext))
+        );
+    }
+
+    #[test]
+    fn test_aggregate_vote() {
+        let key: [u8; 32] = rand::random();
+        let params = DCRTPolyParams::default();
+        let d = 3;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let pubkeys = bgg_sampler.sample(&params, b"vote", &[true]);
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+
+        // Three voters of weights 1, 2 and 4 voting yes, no and yes
+        let (zero, one) = (DCRTPoly::const_zero(&params), DCRTPoly::const_one(&params));
+        let votes = [&one, &zero, &one]
+            .iter()
+            .map(|&vote| bgg_sampler.sample(&params, &pubkeys, &[vote.clone()]).pop().unwrap())
+            .collect::<Vec<_>>();
+        let weights = [1, 2, 4];
+        let tally = aggregate_vote(&params, &votes, &weights, 0).unwrap();
+
+        // The tally is 5 out of a total weight of 7 under 7 times the public key
+        let constant =
+            |value: u64| DCRTPoly::from_const(&params, &FinRingElem::new(value, params.modulus()));
+        assert_eq!(tally.plaintext, Some(constant(5)));
+        let expected_matrix = votes[0].pubkey.matrix.clone() * constant(7);
+        assert_eq!(tally.pubkey.matrix, expected_matrix);
+        let gadget = DCRTPolyMatrix::gadget_matrix(&params, d + 1);
+        assert_eq!(
+            tally.vector,
+            bgg_sampler.secret_vec.clone() * (expected_matrix - (gadget * constant(5)))
+        );
+
+        // Unit weights tally like a sum, and the weights must match the votes
+        let sum = aggregate_sum(&params, &votes, 0).unwrap();
+        assert_eq!(aggregate_vote(&params, &votes, &[1; 3], 0).unwrap(), sum);
+        assert_eq!(
+            aggregate_vote(&params, &votes, &weights[..2], 0).unwrap_err(),
+            AggregateError::WeightCount { votes: 3, weights: 2 }
+        );
+
+        // The noise budget counts the total weight: 2^(34 - 3 - 30) = 2 < 7
+        assert_eq!(
+            aggregate_vote(&params, &votes, &weights, 30).unwrap_err(),
+            AggregateError::NoiseBudget { width: 7, max_width: 2 }
+        );
+    }
+
+    #[test]
+    fn test_aggregate_errors() {
+        let key: [u8; 32] = rand::random();
+        let params = DCRTPolyParams::default();
+        let d = 2;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let pubkeys = bgg_sampler.sample(&params, b"aggregate", &[true, true]);
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let plaintexts = vec![create_random_poly(&params); 2];
+        let encodings = bgg_sampler.sample(&params, &pubkeys, &plaintexts);
+
+        assert_eq!(
+            aggregate_sum::<DCRTPolyMatrix>(&params, &[], 0).unwrap_err(),
+            AggregateError::Empty
+        );
+        assert_eq!(
+            aggregate_sum(&params, &encodings[1..], 0).unwrap_err(),
+            AggregateError::PublicKeyMismatch { index: 1 }
+        );
+
+        // A 34-bit modulus leaves room for 2^(34 - 3 - 30) = 2 errors of 30 bits
+        assert_eq!(max_width(&params, 30), 2);
+        let same = vec![encodings[1].clone(); 3];
+        assert_eq!(
+            aggregate_sum(&params, &same, 30).unwrap_err(),
+            AggregateError::NoiseBudget { width: 3, max_width: 2 }
+        );
+        assert!(aggregate_sum(&params, &same[..2], 30).is_ok());
+    }
+}
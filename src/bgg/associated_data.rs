@@ -0,0 +1,204 @@
+//! Associated data of a ciphertext: the policy it was produced for, when it expires and the
+//! tenant it belongs to.
+//!
+//! The data is bound twice. The public keys of the attributes can be sampled under
+//! [`AssociatedData::bind_tag`], so that encodings only evaluate correctly under the keys of
+//! their own context, and the keys of [`EncodedAttributes::mac`] and of the attribute envelope
+//! are derived from it, so that a ciphertext relayed with other associated data, or none, fails
+//! to authenticate. Neither authenticates once the expiry has passed. The associated data is
+//! stored in the encoding stream by
+//! [`crate::bgg::encoding_stream::write_encoded_attributes`].
+use super::{
+    epoch::bind_epoch,
+    eval_key::{read_bytes, read_u64, write_u64},
+    EncodedAttributes,
+};
+use crate::poly::PolyMatrix;
+use digest::Digest;
+use std::{
+    io::{self, Read},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const AD_DOMAIN: &[u8; 4] = b"DIOD";
+
+/// The current Unix time in seconds, which expiries are checked against.
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct AssociatedData {
+    pub policy_id: Vec<u8>,
+    /// Unix time in seconds after which the ciphertext is rejected, if any.
+    pub expiry: Option<u64>,
+    pub tenant_id: Vec<u8>,
+}
+
+impl AssociatedData {
+    pub fn new(policy_id: &[u8]) -> Self {
+        Self { policy_id: policy_id.to_vec(), ..Default::default() }
+    }
+
+    pub fn with_expiry(mut self, expiry: u64) -> Self {
+        self.expiry = Some(expiry);
+        self
+    }
+
+    pub fn with_tenant(mut self, tenant_id: &[u8]) -> Self {
+        self.tenant_id = tenant_id.to_vec();
+        self
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expiry.is_some_and(|expiry| now > expiry)
+    }
+
+    /// The magic `DIOD`, the policy id and the tenant id prefixed by their lengths as `u64`
+    /// little-endian, and the expiry as a presence byte followed by a `u64`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = AD_DOMAIN.to_vec();
+        for field in [&self.policy_id, &self.tenant_id] {
+            write_u64(&mut bytes, field.len() as u64).expect("writing to a vector cannot fail");
+            bytes.extend_from_slice(field);
+        }
+        bytes.push(self.expiry.is_some() as u8);
+        bytes.extend_from_slice(&self.expiry.unwrap_or(0).to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(mut bytes: &[u8]) -> io::Result<Self> {
+        let reader = &mut bytes;
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != AD_DOMAIN {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not associated data"));
+        }
+        let len = read_u64(reader)?;
+        let policy_id = read_bytes(reader, len)?;
+        let len = read_u64(reader)?;
+        let tenant_id = read_bytes(reader, len)?;
+        let mut present = [0u8; 1];
+        reader.read_exact(&mut present)?;
+        let expiry = read_u64(reader)?;
+        let expiry = match present[0] {
+            0 => None,
+            1 => Some(expiry),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid expiry flag")),
+        };
+        if !reader.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "trailing bytes"));
+        }
+        Ok(Self { policy_id, expiry, tenant_id })
+    }
+
+    /// The tag to sample the public keys of a ciphertext with this data under, `tag` followed
+    /// by [`Self::to_bytes`], which is prefix-free.
+    pub fn bind_tag(&self, tag: &[u8]) -> Vec<u8> {
+        [tag, &self.to_bytes()].concat()
+    }
+
+    /// Derives `H(DIOD || len || key || data)` from `key`, where `len` is the length of the key
+    /// as a `u64` little-endian.
+    pub fn bind_key<H: Digest>(&self, key: &[u8]) -> Vec<u8> {
+        let mut hasher = H::new();
+        hasher.update(AD_DOMAIN);
+        hasher.update((key.len() as u64).to_le_bytes());
+        hasher.update(key);
+        hasher.update(self.to_bytes());
+        hasher.finalize().to_vec()
+    }
+}
+
+impl<M: PolyMatrix> EncodedAttributes<M> {
+    /// Whether the associated data of these encodings expired before the Unix time `now`.
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.associated_data().is_some_and(|data| data.is_expired(now))
+    }
+
+    /// The key bound to the associated data and the epoch of these encodings, or `key` itself
+    /// without associated data at epoch 0.
+    pub(crate) fn bound_key<H: Digest>(&self, key: &[u8]) -> Vec<u8> {
+        let key = match self.associated_data() {
+            Some(data) => data.bind_key::<H>(key),
+            None => key.to_vec(),
+        };
+        match self.epoch() {
+            0 => key,
+            epoch => bind_epoch::<H>(&key, epoch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::encoding_stream::{write_encoded_attributes, EncodingStreamReader},
+        poly::dcrt::DCRTPolyMatrix,
+        utils::TestBggSetup,
+    };
+    use keccak_asm::Keccak256;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_associated_data_bytes() {
+        let data = AssociatedData::new(b"policy").with_expiry(1_700_000_000).with_tenant(b"acme");
+        assert_eq!(AssociatedData::from_bytes(&data.to_bytes()).unwrap(), data);
+        let data = AssociatedData::new(b"");
+        assert_eq!(AssociatedData::from_bytes(&data.to_bytes()).unwrap(), data);
+        assert!(AssociatedData::from_bytes(&data.to_bytes()[1..]).is_err());
+        assert!(AssociatedData::new(b"p").with_expiry(10).is_expired(11));
+        assert!(!AssociatedData::new(b"p").is_expired(u64::MAX));
+
+        // Moving bytes between the fields changes the encoding
+        let a = AssociatedData::new(b"ab").with_tenant(b"c");
+        let b = AssociatedData::new(b"a").with_tenant(b"bc");
+        assert_ne!(a.bind_tag(b"keys"), b.bind_tag(b"keys"));
+    }
+
+    #[test]
+    fn test_associated_data_binding() {
+        let setup = TestBggSetup::new(2);
+        let data = AssociatedData::new(b"policy-1").with_expiry(1_700_000_000).with_tenant(b"a");
+        let (pubkeys, encodings) = setup.sample(&data.bind_tag(b"ad"), &[true, true]);
+        let other_pubkeys = setup.pubkey_sampler.sample(&setup.params, b"ad", &[true, true]);
+        assert_ne!(pubkeys, other_pubkeys);
+
+        let bound = EncodedAttributes::with_associated_data(encodings.clone(), data.clone());
+        assert_eq!(bound.associated_data(), Some(&data));
+
+        // The tag does not verify once the associated data is replaced or stripped
+        let mac_key = b"relay key";
+        let now = 1_600_000_000;
+        let tag = bound.mac::<Keccak256>(mac_key);
+        assert!(bound.verify_mac_at::<Keccak256>(mac_key, &tag, now));
+        let replayed = EncodedAttributes::with_associated_data(
+            encodings.clone(),
+            AssociatedData { policy_id: b"policy-2".to_vec(), ..data.clone() },
+        );
+        assert!(!replayed.verify_mac_at::<Keccak256>(mac_key, &tag, now));
+        let stripped = EncodedAttributes::from_slots(encodings);
+        assert!(!stripped.verify_mac_at::<Keccak256>(mac_key, &tag, now));
+
+        // Neither does the attribute envelope open
+        let envelope = bound.seal_attributes::<Keccak256>(b"aux", b"attributes");
+        let opened = bound.open_attributes_at::<Keccak256>(b"aux", &envelope, now);
+        assert_eq!(opened.unwrap(), b"attributes");
+        assert!(stripped.open_attributes_at::<Keccak256>(b"aux", &envelope, now).is_err());
+
+        // Nothing authenticates after the expiry, which has already passed
+        assert!(!bound.verify_mac_at::<Keccak256>(mac_key, &tag, 1_700_000_001));
+        assert!(!bound.verify_mac::<Keccak256>(mac_key, &tag));
+        assert!(bound.open_attributes::<Keccak256>(b"aux", &envelope).is_err());
+
+        // The associated data is restored from the encoding stream
+        let mut cursor = Cursor::new(Vec::new());
+        write_encoded_attributes(&mut cursor, &bound).unwrap();
+        cursor.set_position(0);
+        let mut reader = EncodingStreamReader::new(cursor).unwrap();
+        assert_eq!(reader.associated_data(), Some(&data));
+        let read = reader.read_attributes::<DCRTPolyMatrix>(&setup.params).unwrap();
+        assert!(read.verify_mac_at::<Keccak256>(mac_key, &tag, now));
+    }
+}
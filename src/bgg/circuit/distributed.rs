@@ -0,0 +1,491 @@
+//! Evaluation of circuits too large for one machine across worker processes.
+//!
+//! The driver walks the circuit level by level. The gates of a level only read wires of earlier
+//! levels, so they are cut into batches of [`GateTask`]s that are independent of each other. One
+//! thread per worker pulls batches from a shared queue until the level is drained, so fast
+//! workers take over the batches slow ones have not reached yet. A request holds the tasks of a
+//! batch and the values of the wires they read that the worker does not hold yet, and the
+//! response the values of the evaluated gates. Every other wire is referenced by its id.
+//!
+//! Workers keep the wires they received or evaluated until the driver releases them. Once the
+//! last gate reading a wire is evaluated, the driver drops its own copy and tells the workers
+//! holding it to drop theirs with their next request, so neither side keeps the whole circuit.
+//!
+//! Requests and responses are opaque bytes moved by a user-supplied [`Transport`], which can be
+//! a socket, a message queue or, in tests, a function call. Workers answer requests with
+//! [`Worker::handle`].
+use super::{serde::SerializablePolyGateType, Evaluable, PolyCircuit, PolyGateType};
+use crate::{
+    bgg::{
+        encoding_stream::{read_record, write_record},
+        eval_key::{read_bytes, read_matrix, read_u64, write_matrix, write_u64},
+        BggEncoding, BggPublicKey,
+    },
+    poly::{Poly, PolyMatrix},
+    utils::ThreadPoolConfig,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io::{self, Read, Write},
+    sync::Mutex,
+};
+
+/// A wire value that can be sent to a worker.
+pub trait WireCodec: Evaluable {
+    fn write_wire(&self, writer: &mut dyn Write) -> io::Result<()>;
+    fn read_wire(params: &Self::Params, reader: &mut dyn Read) -> io::Result<Self>;
+}
+
+impl<M: PolyMatrix> WireCodec for BggPublicKey<M> {
+    fn write_wire(&self, writer: &mut dyn Write) -> io::Result<()> {
+        write_matrix(writer, &self.matrix)?;
+        writer.write_all(&[self.reveal_plaintext as u8])
+    }
+
+    fn read_wire(params: &<M::P as Poly>::Params, reader: &mut dyn Read) -> io::Result<Self> {
+        let matrix = read_matrix(reader, params)?;
+        let mut reveal_plaintext = [0u8; 1];
+        reader.read_exact(&mut reveal_plaintext)?;
+        Ok(Self::new(matrix, reveal_plaintext[0] != 0))
+    }
+}
+
+impl<M: PolyMatrix> WireCodec for BggEncoding<M> {
+    fn write_wire(&self, writer: &mut dyn Write) -> io::Result<()> {
+        write_record(writer, self)
+    }
+
+    fn read_wire(params: &<M::P as Poly>::Params, reader: &mut dyn Read) -> io::Result<Self> {
+        read_record(reader, params)
+    }
+}
+
+/// One gate evaluated on a worker.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GateTask {
+    pub gate_id: usize,
+    pub gate_type: SerializablePolyGateType,
+    /// The ids of the wires the gate reads, followed by 0, the constant one, for gates with a
+    /// constant and automorphisms.
+    pub input_gates: Vec<usize>,
+}
+
+impl GateTask {
+    fn reads_one(&self) -> bool {
+        matches!(
+            self.gate_type,
+            SerializablePolyGateType::Const { .. } |
+                SerializablePolyGateType::AddConst { .. } |
+                SerializablePolyGateType::Automorphism { .. }
+        )
+    }
+}
+
+/// Moves requests to workers and their responses back.
+pub trait Transport: Sync {
+    fn num_workers(&self) -> usize;
+    /// Sends `request` to `worker` and waits for its response. Calls for different workers are
+    /// made concurrently.
+    fn round_trip(&self, worker: usize, request: Vec<u8>) -> io::Result<Vec<u8>>;
+}
+
+fn invalid_data(message: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn invalid_input(message: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message.to_string())
+}
+
+/// Writes the tasks as length-prefixed JSON, followed by the number and the `u64` little-endian
+/// ids of the wires the worker may drop, and then the number of wires and the id and value of
+/// every wire read by a task that is not in `held`. Those are added to `held` and `released` is
+/// emptied.
+fn write_request<E: WireCodec>(
+    tasks: &[GateTask],
+    wires: &HashMap<usize, E>,
+    held: &mut HashSet<usize>,
+    released: &mut Vec<usize>,
+) -> io::Result<Vec<u8>> {
+    let mut request = Vec::new();
+    let json = serde_json::to_vec(tasks).map_err(invalid_data)?;
+    write_u64(&mut request, json.len() as u64)?;
+    request.write_all(&json)?;
+    write_u64(&mut request, released.len() as u64)?;
+    for id in released.drain(..) {
+        write_u64(&mut request, id as u64)?;
+    }
+    let mut ids = tasks
+        .iter()
+        .flat_map(|task| task.input_gates.iter().copied())
+        .filter(|id| !held.contains(id))
+        .collect::<Vec<_>>();
+    ids.sort_unstable();
+    ids.dedup();
+    write_u64(&mut request, ids.len() as u64)?;
+    for id in ids {
+        write_u64(&mut request, id as u64)?;
+        wires[&id].write_wire(&mut request)?;
+        held.insert(id);
+    }
+    Ok(request)
+}
+
+/// Drops the driver's copy of a wire and schedules it to be dropped by the workers holding it.
+fn release<E>(
+    id: usize,
+    wires: &mut HashMap<usize, E>,
+    held: &mut [HashSet<usize>],
+    released: &mut [Vec<usize>],
+) {
+    wires.remove(&id);
+    for (held, released) in held.iter_mut().zip(released.iter_mut()) {
+        if held.remove(&id) {
+            released.push(id);
+        }
+    }
+}
+
+/// Reads the number of wires followed by the id and value of every wire.
+fn read_wires<E: WireCodec>(
+    params: &E::Params,
+    reader: &mut &[u8],
+) -> io::Result<Vec<(usize, E)>> {
+    let count = read_u64(reader)?;
+    let mut wires = Vec::new();
+    for _ in 0..count {
+        let id = read_u64(reader)? as usize;
+        wires.push((id, E::read_wire(params, reader)?));
+    }
+    Ok(wires)
+}
+
+/// The state of one worker: the wires it received or evaluated and has not been told to drop.
+pub struct Worker<E: WireCodec> {
+    params: E::Params,
+    wires: HashMap<usize, E>,
+    pool: ThreadPoolConfig,
+}
+
+impl<E: WireCodec> Worker<E> {
+    /// A worker evaluating its gates in `pool`.
+    pub fn new(params: E::Params, pool: ThreadPoolConfig) -> Self {
+        Self { params, wires: HashMap::new(), pool }
+    }
+
+    /// The number of wires the worker holds.
+    pub fn num_wires(&self) -> usize {
+        self.wires.len()
+    }
+
+    /// Evaluates the tasks of a request written by the driver and returns the values of their
+    /// gates, which the worker keeps for later requests. This is what a worker runs for every
+    /// request it receives.
+    pub fn handle(&mut self, request: &[u8]) -> io::Result<Vec<u8>> {
+        let mut reader = request;
+        let len = read_u64(&mut reader)?;
+        let tasks: Vec<GateTask> =
+            serde_json::from_slice(&read_bytes(&mut reader, len)?).map_err(invalid_data)?;
+        for _ in 0..read_u64(&mut reader)? {
+            self.wires.remove(&(read_u64(&mut reader)? as usize));
+        }
+        let received = read_wires::<E>(&self.params, &mut reader)?;
+        self.wires.extend(received);
+
+        let mut response = Vec::new();
+        write_u64(&mut response, tasks.len() as u64)?;
+        for task in tasks.iter() {
+            let result = self.pool.install(|| self.evaluate(task))?;
+            write_u64(&mut response, task.gate_id as u64)?;
+            result.write_wire(&mut response)?;
+            self.wires.insert(task.gate_id, result);
+        }
+        Ok(response)
+    }
+
+    fn evaluate(&self, task: &GateTask) -> io::Result<E> {
+        let num_input =
+            task.gate_type.num_input().and_then(|n| n.checked_add(usize::from(task.reads_one())));
+        if num_input != Some(task.input_gates.len()) {
+            let message = format!("gate {} has the wrong number of inputs", task.gate_id);
+            return Err(invalid_data(message));
+        }
+        let inputs = task
+            .input_gates
+            .iter()
+            .map(|id| {
+                self.wires.get(id).ok_or_else(|| invalid_data(format!("wire {} missing", id)))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let params = &self.params;
+        let result = match &task.gate_type {
+            SerializablePolyGateType::Const { digits } => {
+                E::from_digits(params, inputs[0], digits)
+            }
+            SerializablePolyGateType::Add => inputs[0].clone() + inputs[1],
+            SerializablePolyGateType::Sub => inputs[0].clone() - inputs[1],
+            SerializablePolyGateType::Mul => inputs[0].clone() * inputs[1],
+            SerializablePolyGateType::Rotate { shift } => inputs[0].rotate(params, *shift),
+            SerializablePolyGateType::AddConst { digits } => {
+                inputs[0].add_const(params, inputs[1], digits)
+            }
+            SerializablePolyGateType::MulConst { digits } => inputs[0].mul_const(params, digits),
+            SerializablePolyGateType::MulScalar { scalar } => inputs[0].mul_scalar(params, scalar),
+            SerializablePolyGateType::Automorphism { k } => {
+                inputs[0].apply_automorphism(params, inputs[3], [inputs[1], inputs[2]], *k)
+            }
+            SerializablePolyGateType::InnerProduct { len } => {
+                let inputs = inputs.into_iter().cloned().collect::<Vec<_>>();
+                let (lhs, rhs) = inputs.split_at(*len);
+                E::eval_inner_product(params, lhs, rhs)
+            }
+            SerializablePolyGateType::Input | SerializablePolyGateType::Call { .. } => {
+                return Err(invalid_data(format!("gate {} cannot be evaluated", task.gate_id)));
+            }
+        };
+        Ok(result)
+    }
+}
+
+impl PolyCircuit {
+    fn gate_task(&self, gate_id: usize) -> io::Result<GateTask> {
+        let gate = &self.gates[&gate_id];
+        if let PolyGateType::Call { .. } = gate.gate_type {
+            return Err(invalid_input("call gates cannot be evaluated remotely"));
+        }
+        let mut task = GateTask {
+            gate_id,
+            gate_type: SerializablePolyGateType::from(&gate.gate_type),
+            input_gates: gate.input_gates.clone(),
+        };
+        if task.reads_one() {
+            task.input_gates.push(0);
+        }
+        Ok(task)
+    }
+
+    /// Evaluates like [`Self::eval`] on the workers behind `transport`, sending at most
+    /// `batch_size` gates per request, and encodes and decodes the wires in `pool`. Fails with
+    /// the first error of the transport or a worker, if a response does not hold the gates of its
+    /// request, or if the batch size, the number of workers or the number of inputs is invalid.
+    pub fn eval_distributed<E: WireCodec, T: Transport>(
+        &self,
+        params: &E::Params,
+        one: &E,
+        inputs: &[E],
+        transport: &T,
+        batch_size: usize,
+        pool: &ThreadPoolConfig,
+    ) -> io::Result<Vec<E>> {
+        if batch_size == 0 {
+            return Err(invalid_input("batch size must be positive"));
+        }
+        let num_workers = transport.num_workers();
+        if num_workers == 0 {
+            return Err(invalid_input("the transport has no workers"));
+        }
+        if self.num_input() != inputs.len() {
+            let message = format!("expected {} inputs, got {}", self.num_input(), inputs.len());
+            return Err(invalid_input(message));
+        }
+        let mut wires = HashMap::from([(0, one.clone())]);
+        wires.extend(inputs.iter().cloned().enumerate().map(|(idx, input)| (idx + 1, input)));
+        let levels = self
+            .compute_levels()
+            .into_iter()
+            .map(|level| {
+                level
+                    .into_iter()
+                    .filter(|gate_id| !wires.contains_key(gate_id))
+                    .map(|gate_id| self.gate_task(gate_id))
+                    .collect::<io::Result<Vec<_>>>()
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        // Gates left to read each wire. The outputs count once more, so they are never dropped
+        let mut readers = HashMap::<usize, usize>::new();
+        let read_ids = levels.iter().flatten().flat_map(|task| task.input_gates.iter());
+        for id in read_ids.chain(self.output_ids.iter()) {
+            *readers.entry(*id).or_default() += 1;
+        }
+        wires.retain(|id, _| readers.contains_key(id));
+        // The wires each worker holds and those it may drop with its next request
+        let mut held = vec![HashSet::new(); num_workers];
+        let mut released = vec![Vec::new(); num_workers];
+
+        for tasks in levels.iter() {
+            let queue = Mutex::new(tasks.chunks(batch_size).collect::<VecDeque<_>>());
+            let (queue, wires_ref) = (&queue, &wires);
+            let results = std::thread::scope(|scope| {
+                let workers = held
+                    .iter_mut()
+                    .zip(released.iter_mut())
+                    .enumerate()
+                    .map(|(worker, (held, released))| {
+                        scope.spawn(move || {
+                            let mut results = Vec::new();
+                            loop {
+                                let Some(batch) = queue.lock().unwrap().pop_front() else {
+                                    return Ok(results);
+                                };
+                                let request = pool
+                                    .install(|| write_request(batch, wires_ref, held, released))?;
+                                let response = transport.round_trip(worker, request)?;
+                                let evaluated = pool.install(|| {
+                                    read_wires::<E>(params, &mut response.as_slice())
+                                })?;
+                                let ids = evaluated.iter().map(|(id, _)| *id);
+                                if !ids.eq(batch.iter().map(|task| task.gate_id)) {
+                                    return Err(invalid_data("response does not match request"));
+                                }
+                                held.extend(batch.iter().map(|task| task.gate_id));
+                                results.extend(evaluated);
+                            }
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                workers
+                    .into_iter()
+                    .map(|worker| worker.join().expect("worker thread panicked"))
+                    .collect::<io::Result<Vec<_>>>()
+            })?;
+            wires.extend(results.into_iter().flatten());
+
+            // Drop the wires nothing reads and those whose last reader was just evaluated
+            for task in tasks.iter() {
+                if !readers.contains_key(&task.gate_id) {
+                    release(task.gate_id, &mut wires, &mut held, &mut released);
+                }
+                for id in task.input_gates.iter() {
+                    let left = readers.get_mut(id).expect("reader count missing");
+                    *left -= 1;
+                    if *left == 0 {
+                        release(*id, &mut wires, &mut held, &mut released);
+                    }
+                }
+            }
+        }
+        Ok(self.output_ids.iter().map(|id| wires[id].clone()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::sampler::BGGPublicKeySampler,
+        poly::dcrt::{DCRTPolyHashSampler, DCRTPolyMatrix, DCRTPolyParams},
+    };
+    use keccak_asm::Keccak256;
+
+    type Key = BggPublicKey<DCRTPolyMatrix>;
+
+    /// Runs the workers in this process and records the size of the requests each of them
+    /// answered.
+    struct LocalTransport {
+        workers: Vec<Mutex<Worker<Key>>>,
+        requests: Mutex<Vec<Vec<usize>>>,
+    }
+
+    impl LocalTransport {
+        fn new(params: &DCRTPolyParams, num_workers: usize) -> Self {
+            let workers = (0..num_workers)
+                .map(|_| Mutex::new(Worker::new(params.clone(), ThreadPoolConfig::global())));
+            Self { workers: workers.collect(), requests: Mutex::new(vec![vec![]; num_workers]) }
+        }
+    }
+
+    impl Transport for LocalTransport {
+        fn num_workers(&self) -> usize {
+            self.workers.len()
+        }
+
+        fn round_trip(&self, worker: usize, request: Vec<u8>) -> io::Result<Vec<u8>> {
+            self.requests.lock().unwrap()[worker].push(request.len());
+            self.workers[worker].lock().unwrap().handle(&request)
+        }
+    }
+
+    #[test]
+    fn test_eval_distributed() {
+        let params = DCRTPolyParams::default();
+        let key: [u8; 32] = rand::random();
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, 2);
+        let pubkeys = bgg_sampler.sample(&params, b"distributed", &[true; 3]);
+
+        // Every gate type but calls, over several levels
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(3);
+        let mul_gate = circuit.mul_gate(inputs[0], inputs[1]);
+        let add_gate = circuit.add_gate(inputs[1], inputs[2]);
+        let const_gate = circuit.const_digits_poly(&[2, 1]);
+        let sub_gate = circuit.sub_gate(mul_gate, const_gate);
+        let add_const_gate = circuit.add_const_gate(add_gate, &[3]);
+        let mul_const_gate = circuit.mul_const_gate(sub_gate, &[5]);
+        let rotate_gate = circuit.rotate_gate(add_const_gate, 1);
+        let product = circuit.mul_gate(mul_const_gate, rotate_gate);
+        circuit.output(vec![product, add_gate]);
+        let expected = circuit.eval(&params, &pubkeys[0], &pubkeys[1..]);
+
+        // Several workers share the batches and agree with the local evaluation
+        let transport = LocalTransport::new(&params, 3);
+        let pool = ThreadPoolConfig::with_num_threads(2).unwrap();
+        let outputs = circuit
+            .eval_distributed(&params, &pubkeys[0], &pubkeys[1..], &transport, 1, &pool)
+            .unwrap();
+        assert_eq!(outputs, expected);
+        let requests = transport.requests.lock().unwrap().iter().map(Vec::len).sum::<usize>();
+        assert_eq!(requests, circuit.num_gates() - 4);
+
+        // Invalid arguments are errors rather than panics
+        let (one, inputs) = (&pubkeys[0], &pubkeys[1..]);
+        let result = circuit.eval_distributed(&params, one, &inputs[..2], &transport, 1, &pool);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        let result = circuit.eval_distributed(&params, one, inputs, &transport, 0, &pool);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+        // Truncated requests are rejected by workers
+        let wires = pubkeys.iter().cloned().enumerate().collect::<HashMap<_, _>>();
+        let task = circuit.gate_task(mul_gate).unwrap();
+        let request = write_request(&[task], &wires, &mut HashSet::new(), &mut vec![]).unwrap();
+        let truncated = &request[..request.len() / 2];
+        let mut worker = Worker::<Key>::new(params.clone(), ThreadPoolConfig::global());
+        assert!(worker.handle(truncated).is_err());
+    }
+
+    #[test]
+    fn test_eval_distributed_references() {
+        let params = DCRTPolyParams::default();
+        let key: [u8; 32] = rand::random();
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, 2);
+        let pubkeys = bgg_sampler.sample(&params, b"distributed", &[true; 2]);
+
+        // A chain of multiplications, one gate per level
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        let mut product = circuit.mul_gate(inputs[0], inputs[1]);
+        for _ in 0..3 {
+            product = circuit.mul_gate(product, inputs[0]);
+        }
+        circuit.output(vec![product]);
+        let expected = circuit.eval(&params, &pubkeys[0], &pubkeys[1..]);
+
+        let transport = LocalTransport::new(&params, 1);
+        let pool = ThreadPoolConfig::global();
+        let outputs = circuit
+            .eval_distributed(&params, &pubkeys[0], &pubkeys[1..], &transport, 1, &pool)
+            .unwrap();
+        assert_eq!(outputs, expected);
+
+        // Only the first request carries wire values, the later ones reference the wires the
+        // worker already holds
+        let requests = transport.requests.lock().unwrap()[0].clone();
+        assert_eq!(requests.len(), 4);
+        assert!(requests[1..].iter().all(|&len| len * 4 < requests[0]));
+
+        // The worker dropped the wires released before the last request, keeping the first
+        // input, the last product and the product it read
+        assert_eq!(transport.workers[0].lock().unwrap().num_wires(), 3);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::poly::{Poly, PolyElem, PolyParams};
+use num_bigint::BigUint;
 use rayon::prelude::*;
 use std::{
     fmt::Debug,
@@ -18,12 +19,49 @@ pub trait Evaluable:
     + for<'a> Mul<&'a Self, Output = Self>
 {
     type Params: Debug + Clone + Send + Sync;
+    /// Data derived from a right-hand multiplication input that can be reused by every
+    /// multiplication with the same right input.
+    type Prepared: Send + Sync;
     fn rotate(&self, params: &Self::Params, shift: usize) -> Self;
     fn from_digits(params: &Self::Params, one: &Self, digits: &[u32]) -> Self;
+    /// Computes `self + c` for the public constant `c` with coefficients `digits`.
+    fn add_const(&self, params: &Self::Params, one: &Self, digits: &[u32]) -> Self {
+        self.clone() + Self::from_digits(params, one, digits)
+    }
+    /// Computes `c * self` for the public constant `c` with coefficients `digits`.
+    fn mul_const(&self, params: &Self::Params, digits: &[u32]) -> Self;
+    /// Computes `scalar * self` for a public integer `scalar`, which may be as large as the
+    /// modulus.
+    fn mul_scalar(&self, params: &Self::Params, scalar: &BigUint) -> Self;
+    /// Applies the automorphism `X -> X^k` for an odd `k`, given the encoding `one` of the
+    /// constant one and the key-switching hints `hints` of `k`, see
+    /// [`crate::bgg::sampler::BGGEncodingSampler::sample_automorphism_hints`].
+    fn apply_automorphism(
+        &self,
+        params: &Self::Params,
+        one: &Self,
+        hints: [&Self; 2],
+        k: usize,
+    ) -> Self;
+    /// Computes `Σ lhs[i] * rhs[i]` for inputs of the same nonzero length.
+    fn eval_inner_product(_: &Self::Params, lhs: &[Self], rhs: &[Self]) -> Self {
+        assert_eq!(lhs.len(), rhs.len(), "inner product inputs must have the same length");
+        lhs.iter()
+            .zip(rhs.iter())
+            .map(|(left, right)| left.clone() * right)
+            .reduce(|acc, product| acc + product)
+            .expect("inner product of empty inputs")
+    }
+    fn prepare(&self, params: &Self::Params) -> Self::Prepared;
+    /// Computes `self * other` given `prepared = other.prepare(params)`.
+    fn mul_prepared(self, other: &Self, _prepared: &Self::Prepared) -> Self {
+        self * other
+    }
 }
 
 impl<P: Poly> Evaluable for P {
     type Params = P::Params;
+    type Prepared = ();
 
     fn rotate(&self, params: &Self::Params, shift: usize) -> Self {
         let mut coeffs = self.coeffs();
@@ -38,4 +76,19 @@ impl<P: Poly> Evaluable for P {
             .collect();
         Self::from_coeffs(params, &coeffs)
     }
+
+    fn mul_const(&self, params: &Self::Params, digits: &[u32]) -> Self {
+        self.clone() * Self::from_digits(params, &Self::const_one(params), digits)
+    }
+
+    fn mul_scalar(&self, params: &Self::Params, scalar: &BigUint) -> Self {
+        let scalar = <P::Elem as PolyElem>::from_bytes(&params.modulus(), &scalar.to_bytes_le());
+        self.clone() * Self::from_const(params, &scalar)
+    }
+
+    fn apply_automorphism(&self, params: &Self::Params, _: &Self, _: [&Self; 2], k: usize) -> Self {
+        Poly::automorphism(self, params, k)
+    }
+
+    fn prepare(&self, _: &Self::Params) {}
 }
@@ -0,0 +1,258 @@
+use super::Evaluable;
+use num_bigint::BigUint;
+use std::{
+    collections::{HashMap, HashSet},
+    ops::{Add, Mul, Sub},
+    sync::Arc,
+};
+
+#[derive(Debug)]
+enum ExprNode<E: Evaluable> {
+    Leaf(E),
+    /// Signed sum of terms, where `true` marks a subtracted term. The first term is never
+    /// subtracted.
+    Sum(Vec<(bool, EvalExpr<E>)>),
+    Mul(EvalExpr<E>, EvalExpr<E>),
+    Rotate(EvalExpr<E>, usize),
+    FromDigits(EvalExpr<E>, Vec<u32>),
+    MulConst(EvalExpr<E>, Vec<u32>),
+    MulScalar(EvalExpr<E>, BigUint),
+    /// The input, the constant one and the two hints of the automorphism `X -> X^k`.
+    Automorphism([EvalExpr<E>; 4], usize),
+}
+
+/// A lazily evaluated expression over an [`Evaluable`] type.
+///
+/// Gate operations only build the expression graph; nothing is computed until [`Self::force`]
+/// is called. Chains of additions and subtractions are collapsed into a single signed sum, and
+/// subexpressions shared by several nodes are evaluated only once. Since `EvalExpr` is itself
+/// `Evaluable`, evaluating a [`super::PolyCircuit`] over it returns the outputs as expressions.
+#[derive(Debug, Clone)]
+pub struct EvalExpr<E: Evaluable>(Arc<ExprNode<E>>);
+
+impl<E: Evaluable> EvalExpr<E> {
+    pub fn leaf(value: E) -> Self {
+        Self(Arc::new(ExprNode::Leaf(value)))
+    }
+
+    fn terms(&self, negate: bool) -> Vec<(bool, Self)> {
+        match self.0.as_ref() {
+            ExprNode::Sum(terms) => {
+                terms.iter().map(|(neg, term)| (*neg ^ negate, term.clone())).collect()
+            }
+            _ => vec![(negate, self.clone())],
+        }
+    }
+
+    fn sum(&self, other: &Self, negate: bool) -> Self {
+        let mut terms = self.terms(false);
+        terms.extend(other.terms(negate));
+        Self(Arc::new(ExprNode::Sum(terms)))
+    }
+
+    /// Returns the number of nodes in the expression graph, counting shared nodes once.
+    pub fn num_nodes(&self) -> usize {
+        let mut visited = HashSet::new();
+        self.count_nodes(&mut visited);
+        visited.len()
+    }
+
+    fn count_nodes(&self, visited: &mut HashSet<usize>) {
+        if !visited.insert(self.id()) {
+            return;
+        }
+        match self.0.as_ref() {
+            ExprNode::Leaf(_) => {}
+            ExprNode::Sum(terms) => terms.iter().for_each(|(_, term)| term.count_nodes(visited)),
+            ExprNode::Mul(left, right) => {
+                left.count_nodes(visited);
+                right.count_nodes(visited);
+            }
+            ExprNode::Rotate(input, _) |
+            ExprNode::FromDigits(input, _) |
+            ExprNode::MulConst(input, _) |
+            ExprNode::MulScalar(input, _) => input.count_nodes(visited),
+            ExprNode::Automorphism(inputs, _) => {
+                inputs.iter().for_each(|input| input.count_nodes(visited))
+            }
+        }
+    }
+
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.0) as usize
+    }
+
+    /// Materializes the expression.
+    pub fn force(&self, params: &E::Params) -> E {
+        let mut memo = HashMap::new();
+        self.force_with_memo(params, &mut memo)
+    }
+
+    fn force_with_memo(&self, params: &E::Params, memo: &mut HashMap<usize, E>) -> E {
+        if let Some(value) = memo.get(&self.id()) {
+            return value.clone();
+        }
+        let value = match self.0.as_ref() {
+            ExprNode::Leaf(value) => value.clone(),
+            ExprNode::Sum(terms) => {
+                let (first_neg, first) = &terms[0];
+                debug_assert!(!first_neg, "the first term of a sum must not be subtracted");
+                let mut acc = first.force_with_memo(params, memo);
+                for (neg, term) in terms[1..].iter() {
+                    let value = term.force_with_memo(params, memo);
+                    acc = if *neg { acc - value } else { acc + value };
+                }
+                acc
+            }
+            ExprNode::Mul(left, right) => {
+                left.force_with_memo(params, memo) * right.force_with_memo(params, memo)
+            }
+            ExprNode::Rotate(input, shift) => {
+                input.force_with_memo(params, memo).rotate(params, *shift)
+            }
+            ExprNode::FromDigits(one, digits) => {
+                E::from_digits(params, &one.force_with_memo(params, memo), digits)
+            }
+            ExprNode::MulConst(input, digits) => {
+                input.force_with_memo(params, memo).mul_const(params, digits)
+            }
+            ExprNode::MulScalar(input, scalar) => {
+                input.force_with_memo(params, memo).mul_scalar(params, scalar)
+            }
+            ExprNode::Automorphism(inputs, k) => {
+                let [input, one, h0, h1] =
+                    inputs.clone().map(|input| input.force_with_memo(params, memo));
+                input.apply_automorphism(params, &one, [&h0, &h1], *k)
+            }
+        };
+        memo.insert(self.id(), value.clone());
+        value
+    }
+}
+
+impl<E: Evaluable> Add for EvalExpr<E> {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        self.sum(&other, false)
+    }
+}
+
+impl<E: Evaluable> Add<&Self> for EvalExpr<E> {
+    type Output = Self;
+    fn add(self, other: &Self) -> Self {
+        self.sum(other, false)
+    }
+}
+
+impl<E: Evaluable> Sub for EvalExpr<E> {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        self.sum(&other, true)
+    }
+}
+
+impl<E: Evaluable> Sub<&Self> for EvalExpr<E> {
+    type Output = Self;
+    fn sub(self, other: &Self) -> Self {
+        self.sum(other, true)
+    }
+}
+
+impl<E: Evaluable> Mul for EvalExpr<E> {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        Self(Arc::new(ExprNode::Mul(self, other)))
+    }
+}
+
+impl<E: Evaluable> Mul<&Self> for EvalExpr<E> {
+    type Output = Self;
+    fn mul(self, other: &Self) -> Self {
+        Self(Arc::new(ExprNode::Mul(self, other.clone())))
+    }
+}
+
+impl<E: Evaluable> Evaluable for EvalExpr<E> {
+    type Params = E::Params;
+    type Prepared = ();
+
+    fn rotate(&self, _: &Self::Params, shift: usize) -> Self {
+        Self(Arc::new(ExprNode::Rotate(self.clone(), shift)))
+    }
+
+    fn from_digits(_: &Self::Params, one: &Self, digits: &[u32]) -> Self {
+        Self(Arc::new(ExprNode::FromDigits(one.clone(), digits.to_vec())))
+    }
+
+    fn mul_const(&self, _: &Self::Params, digits: &[u32]) -> Self {
+        Self(Arc::new(ExprNode::MulConst(self.clone(), digits.to_vec())))
+    }
+
+    fn mul_scalar(&self, _: &Self::Params, scalar: &BigUint) -> Self {
+        Self(Arc::new(ExprNode::MulScalar(self.clone(), scalar.clone())))
+    }
+
+    fn apply_automorphism(
+        &self,
+        _: &Self::Params,
+        one: &Self,
+        hints: [&Self; 2],
+        k: usize,
+    ) -> Self {
+        let inputs = [self.clone(), one.clone(), hints[0].clone(), hints[1].clone()];
+        Self(Arc::new(ExprNode::Automorphism(inputs, k)))
+    }
+
+    fn prepare(&self, _: &Self::Params) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::circuit::PolyCircuit,
+        poly::{
+            dcrt::{DCRTPoly, DCRTPolyParams},
+            Poly,
+        },
+        utils::create_random_poly,
+    };
+
+    #[test]
+    fn test_eval_expr_collapses_sums() {
+        let params = DCRTPolyParams::default();
+        let polys = (0..4).map(|_| create_random_poly(&params)).collect::<Vec<_>>();
+        let leaves = polys.iter().cloned().map(EvalExpr::leaf).collect::<Vec<_>>();
+
+        // ((a + b) - (c - d)) is a single sum node over four leaves
+        let expr = (leaves[0].clone() + &leaves[1]) - (leaves[2].clone() - &leaves[3]);
+        assert_eq!(expr.num_nodes(), 5);
+        let expected = polys[0].clone() + &polys[1] - &polys[2] + &polys[3];
+        assert_eq!(expr.force(&params), expected);
+    }
+
+    #[test]
+    fn test_eval_expr_circuit() {
+        let params = DCRTPolyParams::default();
+        let polys = (0..3).map(|_| create_random_poly(&params)).collect::<Vec<_>>();
+
+        // (x1 + x2) * x3 rotated, plus a constant and the same product reused
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(3);
+        let add = circuit.add_gate(inputs[0], inputs[1]);
+        let mul = circuit.mul_gate(add, inputs[2]);
+        let rotated = circuit.rotate_gate(mul, 1);
+        let digits = circuit.const_digits_poly(&[1, 0, 1]);
+        let out1 = circuit.add_gate(rotated, digits);
+        let out2 = circuit.sub_gate(out1, mul);
+        circuit.output(vec![out1, out2]);
+
+        let one = DCRTPoly::const_one(&params);
+        let expected = circuit.eval(&params, &one, &polys);
+
+        let leaves = polys.iter().cloned().map(EvalExpr::leaf).collect::<Vec<_>>();
+        let outputs = circuit.eval(&params, &EvalExpr::leaf(one), &leaves);
+        let forced = outputs.iter().map(|expr| expr.force(&params)).collect::<Vec<_>>();
+        assert_eq!(forced, expected);
+    }
+}
@@ -1,3 +1,5 @@
+use num_bigint::BigUint;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PolyGate {
     pub gate_id: usize,
@@ -19,6 +21,18 @@ pub enum PolyGateType {
     Sub,
     Mul,
     Rotate { shift: usize },
+    /// Adds the public constant with coefficients `digits` to the input.
+    AddConst { digits: Vec<u32> },
+    /// Multiplies the input by the public constant with coefficients `digits`.
+    MulConst { digits: Vec<u32> },
+    /// Multiplies the input by the public integer `scalar`, which may be as large as the modulus.
+    MulScalar { scalar: BigUint },
+    /// Applies the automorphism `X -> X^k` to the first input, with the key-switching hints of
+    /// `k` as the other two.
+    Automorphism { k: usize },
+    /// Computes `Σ x_i * y_i` for the first `len` inputs `x_i` and the last `len` inputs `y_i`
+    /// in one pass.
+    InnerProduct { len: usize },
     Call { circuit_id: usize, num_input: usize, output_id: usize },
 }
 
@@ -26,9 +40,31 @@ impl PolyGateType {
     pub fn num_input(&self) -> usize {
         match self {
             PolyGateType::Input | PolyGateType::Const { .. } => 0,
-            PolyGateType::Rotate { .. } => 1,
+            PolyGateType::Rotate { .. } |
+            PolyGateType::AddConst { .. } |
+            PolyGateType::MulConst { .. } |
+            PolyGateType::MulScalar { .. } => 1,
             PolyGateType::Add | PolyGateType::Sub | PolyGateType::Mul => 2,
+            PolyGateType::Automorphism { .. } => 3,
+            PolyGateType::InnerProduct { len } => 2 * len,
             PolyGateType::Call { num_input, .. } => *num_input,
         }
     }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PolyGateType::Input => "input",
+            PolyGateType::Const { .. } => "const",
+            PolyGateType::Add => "add",
+            PolyGateType::Sub => "sub",
+            PolyGateType::Mul => "mul",
+            PolyGateType::Rotate { .. } => "rotate",
+            PolyGateType::AddConst { .. } => "add_const",
+            PolyGateType::MulConst { .. } => "mul_const",
+            PolyGateType::MulScalar { .. } => "mul_scalar",
+            PolyGateType::Automorphism { .. } => "automorphism",
+            PolyGateType::InnerProduct { .. } => "inner_product",
+            PolyGateType::Call { .. } => "call",
+        }
+    }
 }
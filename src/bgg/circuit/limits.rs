@@ -0,0 +1,244 @@
+use super::{Evaluable, PolyCircuit, PolyGateType};
+use crate::utils::ThreadPoolConfig;
+use memory_stats::memory_stats;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+/// Limits enforced by [`PolyCircuit::eval_with_options`] and
+/// [`PolyCircuit::eval_streaming_with_options`] before every gate, e.g. when evaluating untrusted
+/// circuits in a service. The default is unlimited.
+#[derive(Debug, Clone, Default)]
+pub struct EvalOptions {
+    /// The maximum number of gates to evaluate, not counting inputs.
+    pub max_gates: Option<usize>,
+    /// The maximum physical memory of the whole process in bytes. Evaluations with this limit
+    /// abort on platforms where the memory use is unavailable.
+    pub max_memory_bytes: Option<usize>,
+    pub deadline: Option<Instant>,
+    /// Aborts the evaluation once set, e.g. from another thread.
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+impl EvalOptions {
+    pub(super) fn check(&self, evaluated: usize) -> Option<EvalLimit> {
+        if let Some(max_gates) = self.max_gates {
+            if evaluated >= max_gates {
+                return Some(EvalLimit::Gates(max_gates));
+            }
+        }
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                return Some(EvalLimit::Deadline);
+            }
+        }
+        if let Some(cancel) = &self.cancel {
+            if cancel.load(Ordering::Relaxed) {
+                return Some(EvalLimit::Cancelled);
+            }
+        }
+        if let Some(max_memory_bytes) = self.max_memory_bytes {
+            let Some(usage) = memory_stats() else {
+                return Some(EvalLimit::MemoryUnavailable);
+            };
+            let used = usage.physical_mem;
+            if used > max_memory_bytes {
+                return Some(EvalLimit::Memory { used, max_memory_bytes });
+            }
+        }
+        None
+    }
+}
+
+/// The limit that aborted an evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvalLimit {
+    Gates(usize),
+    Memory {
+        used: usize,
+        max_memory_bytes: usize,
+    },
+    /// A memory limit was set, but the memory use cannot be read on this platform.
+    MemoryUnavailable,
+    Deadline,
+    Cancelled,
+}
+
+/// Error returned when an evaluation is aborted, with the progress made so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvalAborted {
+    pub limit: EvalLimit,
+    /// The number of gates evaluated before the evaluation was aborted, not counting inputs.
+    pub evaluated_gates: usize,
+    pub total_gates: usize,
+}
+
+impl std::fmt::Display for EvalAborted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self.limit {
+            EvalLimit::Gates(max_gates) => format!("gate limit of {} reached", max_gates),
+            EvalLimit::Memory { used, max_memory_bytes } => {
+                format!("memory use of {} bytes exceeds {} bytes", used, max_memory_bytes)
+            }
+            EvalLimit::MemoryUnavailable => "memory use is unavailable".to_string(),
+            EvalLimit::Deadline => "deadline passed".to_string(),
+            EvalLimit::Cancelled => "cancelled".to_string(),
+        };
+        write!(
+            f,
+            "evaluation aborted after {} of {} gates: {}",
+            self.evaluated_gates, self.total_gates, reason
+        )
+    }
+}
+
+impl std::error::Error for EvalAborted {}
+
+impl PolyCircuit {
+    /// Evaluates the circuit level by level like [`Self::eval`], checking `options` before every
+    /// gate.
+    pub fn eval_with_options<E: Evaluable>(
+        &self,
+        params: &E::Params,
+        one: &E,
+        inputs: &[E],
+        options: &EvalOptions,
+    ) -> Result<Vec<E>, EvalAborted> {
+        let (wires, _) = self.eval_wires_with_options(params, one, inputs, options)?;
+        Ok(self.output_ids.iter().map(|id| wires[id].clone()).collect())
+    }
+
+    /// Evaluates the circuit one gate at a time like [`Self::eval_streaming`], checking `options`
+    /// before every gate.
+    pub fn eval_streaming_with_options<E: Evaluable>(
+        &self,
+        params: &E::Params,
+        one: &E,
+        inputs: &[E],
+        options: &EvalOptions,
+    ) -> Result<Vec<E>, EvalAborted> {
+        let total_gates = self
+            .topological_order()
+            .iter()
+            .filter(|&&gate_id| self.gates[&gate_id].gate_type != PolyGateType::Input)
+            .count();
+        self.eval_streaming_with(
+            params,
+            one,
+            |idx| Ok(inputs[idx].clone()),
+            |evaluated, _| match options.check(evaluated) {
+                Some(limit) => Err(EvalAborted { limit, evaluated_gates: evaluated, total_gates }),
+                None => Ok(()),
+            },
+            |_| {},
+            &ThreadPoolConfig::global(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        poly::{
+            dcrt::{DCRTPoly, DCRTPolyParams},
+            Poly,
+        },
+        utils::create_random_poly,
+    };
+    use std::time::Duration;
+
+    fn square_chain(depth: usize) -> PolyCircuit {
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(1);
+        let mut gate = inputs[0];
+        for _ in 0..depth {
+            gate = circuit.mul_gate(gate, gate);
+        }
+        circuit.output(vec![gate]);
+        circuit
+    }
+
+    /// Evaluates with both [`PolyCircuit::eval_with_options`] and
+    /// [`PolyCircuit::eval_streaming_with_options`], which must agree.
+    fn eval_both(
+        circuit: &PolyCircuit,
+        inputs: &[DCRTPoly],
+        options: &EvalOptions,
+    ) -> Result<Vec<DCRTPoly>, EvalAborted> {
+        let params = DCRTPolyParams::default();
+        let one = DCRTPoly::const_one(&params);
+        let levels = circuit.eval_with_options(&params, &one, inputs, options);
+        let streaming = circuit.eval_streaming_with_options(&params, &one, inputs, options);
+        assert_eq!(levels, streaming);
+        levels
+    }
+
+    #[test]
+    fn test_eval_with_options_unlimited() {
+        let params = DCRTPolyParams::default();
+        let one = DCRTPoly::const_one(&params);
+        let circuit = square_chain(3);
+        let inputs = vec![create_random_poly(&params)];
+        let result = eval_both(&circuit, &inputs, &EvalOptions::default());
+        assert_eq!(result.unwrap(), circuit.eval(&params, &one, &inputs));
+    }
+
+    #[test]
+    fn test_eval_with_options_limits() {
+        let params = DCRTPolyParams::default();
+        let circuit = square_chain(5);
+        let inputs = vec![create_random_poly(&params)];
+
+        // The gate limit stops the evaluation after max_gates gates
+        let options = EvalOptions { max_gates: Some(3), ..Default::default() };
+        let err = eval_both(&circuit, &inputs, &options).unwrap_err();
+        assert_eq!(
+            err,
+            EvalAborted { limit: EvalLimit::Gates(3), evaluated_gates: 3, total_gates: 5 }
+        );
+        let options = EvalOptions { max_gates: Some(5), ..Default::default() };
+        assert!(eval_both(&circuit, &inputs, &options).is_ok());
+
+        // A passed deadline stops the evaluation before the first gate
+        let deadline = Instant::now() - Duration::from_secs(1);
+        let options = EvalOptions { deadline: Some(deadline), ..Default::default() };
+        let err = eval_both(&circuit, &inputs, &options).unwrap_err();
+        assert_eq!(err.limit, EvalLimit::Deadline);
+        assert_eq!(err.evaluated_gates, 0);
+
+        // So does a cancelled flag
+        let cancel = Arc::new(AtomicBool::new(true));
+        let options = EvalOptions { cancel: Some(cancel), ..Default::default() };
+        let err = eval_both(&circuit, &inputs, &options).unwrap_err();
+        assert_eq!(err.limit, EvalLimit::Cancelled);
+    }
+
+    #[test]
+    fn test_eval_with_options_memory() {
+        let params = DCRTPolyParams::default();
+        let circuit = square_chain(5);
+        let inputs = vec![create_random_poly(&params)];
+
+        // A memory limit below the current usage stops the evaluation, and so does any memory
+        // limit where the usage cannot be read
+        let options = EvalOptions { max_memory_bytes: Some(1), ..Default::default() };
+        let err = eval_both(&circuit, &inputs, &options).unwrap_err();
+        assert_eq!(err.evaluated_gates, 0);
+        match memory_stats() {
+            Some(_) => assert!(matches!(err.limit, EvalLimit::Memory { max_memory_bytes: 1, .. })),
+            None => assert_eq!(err.limit, EvalLimit::MemoryUnavailable),
+        }
+
+        // A limit above the current usage lets the evaluation finish
+        if let Some(usage) = memory_stats() {
+            let max_memory_bytes = Some(usage.physical_mem * 4);
+            let options = EvalOptions { max_memory_bytes, ..Default::default() };
+            assert!(eval_both(&circuit, &inputs, &options).is_ok());
+        }
+    }
+}
@@ -1,18 +1,41 @@
+pub mod distributed;
 pub mod eval;
+pub mod expr;
 pub mod gate;
+pub mod limits;
+pub mod output;
+pub mod partial;
+pub mod planner;
+pub mod policy;
 pub mod serde;
+pub mod soundness;
+pub mod stats;
 pub mod utils;
 use dashmap::DashMap;
 pub use eval::*;
+pub use expr::EvalExpr;
 pub use gate::{PolyGate, PolyGateType};
+pub use limits::{EvalAborted, EvalLimit, EvalOptions};
+use num_bigint::BigUint;
+pub use output::{OutputDecoding, OutputInfo};
+pub use planner::NoisePlan;
+pub use policy::{compile_policy, PolicyError};
+pub use stats::{EvalStats, GateStats};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use std::{
     collections::{BTreeMap, HashMap, HashSet},
     fmt::Debug,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
 };
 pub use utils::*;
 
-use crate::utils::debug_mem;
+use crate::{
+    poly::arena::{ArenaStats, PolyArena},
+    utils::{debug_mem, parallelism_config, ThreadPoolConfig},
+};
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct PolyCircuit {
     gates: BTreeMap<usize, PolyGate>,
@@ -46,6 +69,11 @@ impl PolyCircuit {
         self.gates.len()
     }
 
+    /// Iterate over the gates in ascending order of gate id
+    pub fn gates(&self) -> impl Iterator<Item = &PolyGate> {
+        self.gates.values()
+    }
+
     pub fn input(&mut self, num_input: usize) -> Vec<usize> {
         #[cfg(debug_assertions)]
         assert_eq!(self.num_input, 0);
@@ -72,7 +100,9 @@ impl PolyCircuit {
         self.not_gate(0)
     }
 
-    /// index 0 have value 1
+    /// Gate 0, the constant-one wire, which evaluates to the `one` argument of [`Self::eval`]
+    /// (e.g. [`crate::bgg::AttributeSlots::constant_one_row`]). Constant terms such as the `+ 1` of
+    /// `x1 * x2 + 1` are added with this gate or [`Self::add_const_gate`].
     pub fn const_one_gate(&mut self) -> usize {
         0
     }
@@ -144,10 +174,43 @@ impl PolyCircuit {
         self.new_gate_generic(vec![left_input, right_input], PolyGateType::Mul)
     }
 
+    /// Computes `Σ lhs[i] * rhs[i]` over two lists of gates of the same length with a single
+    /// gate, which keys and encodings evaluate in one fused pass.
+    pub fn inner_product_gate(&mut self, lhs: &[usize], rhs: &[usize]) -> usize {
+        assert_eq!(lhs.len(), rhs.len());
+        assert!(!lhs.is_empty());
+        let inputs = lhs.iter().chain(rhs.iter()).copied().collect();
+        self.new_gate_generic(inputs, PolyGateType::InnerProduct { len: lhs.len() })
+    }
+
     pub fn rotate_gate(&mut self, input: usize, shift: usize) -> usize {
         self.new_gate_generic(vec![input], PolyGateType::Rotate { shift })
     }
 
+    /// Computes `x + c` for the public constant `c` with coefficients `digits`.
+    pub fn add_const_gate(&mut self, input: usize, digits: &[u32]) -> usize {
+        self.new_gate_generic(vec![input], PolyGateType::AddConst { digits: digits.to_vec() })
+    }
+
+    /// Computes `c * x` for the public constant `c` with coefficients `digits`, which is cheaper
+    /// than a [`Self::mul_gate`] with a constant gate.
+    pub fn mul_const_gate(&mut self, input: usize, digits: &[u32]) -> usize {
+        self.new_gate_generic(vec![input], PolyGateType::MulConst { digits: digits.to_vec() })
+    }
+
+    /// Computes `scalar * x` for a public integer `scalar` as large as the modulus, e.g. the
+    /// inverse of a power of two, through a gadget decomposition.
+    pub fn mul_scalar_gate(&mut self, input: usize, scalar: &BigUint) -> usize {
+        self.new_gate_generic(vec![input], PolyGateType::MulScalar { scalar: scalar.clone() })
+    }
+
+    /// Applies the automorphism `X -> X^k` for an odd `k` to `input`, given the input gates
+    /// `hints` of its key-switching hints. Over encodings, the plaintext of `input` must be known.
+    pub fn automorphism_gate(&mut self, input: usize, k: usize, hints: [usize; 2]) -> usize {
+        assert!(k % 2 == 1, "automorphism index must be odd, got {}", k);
+        self.new_gate_generic(vec![input, hints[0], hints[1]], PolyGateType::Automorphism { k })
+    }
+
     pub fn const_digits_poly(&mut self, digits: &[u32]) -> usize {
         self.new_gate_generic(vec![], PolyGateType::Const { digits: digits.to_vec() })
     }
@@ -167,6 +230,34 @@ impl PolyCircuit {
         gate_id
     }
 
+    /// Returns a circuit computing the same outputs from only the inputs that the outputs depend
+    /// on, together with the (0-based) indices of those inputs in this circuit. Gates that no
+    /// output depends on are dropped. The indices are empty if the outputs are constants.
+    pub fn prune_inputs(&self) -> (Self, Vec<usize>) {
+        let reachable = self.topological_order().into_iter().collect::<HashSet<_>>();
+        let used_inputs = (1..=self.num_input)
+            .filter(|id| reachable.contains(id))
+            .map(|id| id - 1)
+            .collect::<Vec<_>>();
+        let mut pruned = Self::new();
+        let new_inputs = pruned.input(used_inputs.len());
+        let mut gate_map = HashMap::from([(0, 0)]);
+        for (&old_input, &new_input) in used_inputs.iter().zip(new_inputs.iter()) {
+            gate_map.insert(old_input + 1, new_input);
+        }
+        // Gate ids are assigned in topological order, so inputs are mapped before their users.
+        for gate in self.gates.values() {
+            if gate.gate_type == PolyGateType::Input || !reachable.contains(&gate.gate_id) {
+                continue;
+            }
+            let inputs = gate.input_gates.iter().map(|id| gate_map[id]).collect();
+            let new_id = pruned.new_gate_generic(inputs, gate.gate_type.clone());
+            gate_map.insert(gate.gate_id, new_id);
+        }
+        pruned.output(self.output_ids.iter().map(|id| gate_map[id]).collect());
+        (pruned, used_inputs)
+    }
+
     /// Computes a topological order (as a vector of gate IDs) for all gates that
     /// are needed to evaluate the outputs. This is done via a DFS from each output gate.
     fn topological_order(&self) -> Vec<usize> {
@@ -230,7 +321,53 @@ impl PolyCircuit {
     }
 
     /// Evaluate the circuit using an iterative approach over a precomputed topological order.
+    /// The gates are evaluated in the current rayon pool, see [`Self::eval_in`] to pick one.
     pub fn eval<E: Evaluable>(&self, params: &E::Params, one: &E, inputs: &[E]) -> Vec<E> {
+        let wires = self.eval_wires(params, one, inputs);
+        let outputs = self
+            .output_ids
+            .par_iter()
+            .map(|&id| wires.get(&id).expect("output missing").clone())
+            .collect();
+        debug_mem("Outputs are collected");
+        outputs
+    }
+
+    /// Evaluate the circuit like [`Self::eval`] with the gates evaluated in `pool`. The outputs
+    /// do not depend on the pool.
+    pub fn eval_in<E: Evaluable>(
+        &self,
+        params: &E::Params,
+        one: &E,
+        inputs: &[E],
+        pool: &ThreadPoolConfig,
+    ) -> Vec<E> {
+        pool.install(|| self.eval(params, one, inputs))
+    }
+
+    /// Evaluate the circuit like [`Self::eval`], but return the values of all wires keyed by gate
+    /// id, including the constant one at id 0 and the inputs.
+    pub fn eval_wires<E: Evaluable>(
+        &self,
+        params: &E::Params,
+        one: &E,
+        inputs: &[E],
+    ) -> BTreeMap<usize, E> {
+        self.eval_wires_with_options(params, one, inputs, &EvalOptions::default())
+            .expect("an unlimited evaluation is never aborted")
+            .0
+    }
+
+    /// Like [`Self::eval_wires`], checking `options` before every gate. The gates of a level
+    /// that already started are finished before the evaluation is aborted. Also returns the
+    /// statistics of the arenas holding the gate inputs.
+    fn eval_wires_with_options<E: Evaluable>(
+        &self,
+        params: &E::Params,
+        one: &E,
+        inputs: &[E],
+        options: &EvalOptions,
+    ) -> Result<(BTreeMap<usize, E>, ArenaStats), EvalAborted> {
         #[cfg(debug_assertions)]
         {
             assert_eq!(self.num_input(), inputs.len());
@@ -248,77 +385,243 @@ impl PolyCircuit {
         }
         debug_mem("Input wires are set");
 
+        // Wires that are the right input of several Mul gates are prepared once and shared.
+        let mut mul_fan_out: HashMap<usize, usize> = HashMap::new();
+        for gate in self.gates.values() {
+            if let PolyGateType::Mul = gate.gate_type {
+                *mul_fan_out.entry(gate.input_gates[1]).or_default() += 1;
+            }
+        }
+        let prepared: DashMap<usize, Arc<E::Prepared>> = DashMap::new();
+        // Mul gates left per shared wire, whose prepared operand is freed after the last one
+        let pending: HashMap<usize, AtomicUsize> = mul_fan_out
+            .iter()
+            .filter(|(_, &count)| count > 1)
+            .map(|(&id, &count)| (id, AtomicUsize::new(count)))
+            .collect();
+
+        let total_gates = levels.iter().flatten().filter(|id| !wires.contains_key(id)).count();
+        // Gates are counted when they start, so that parallel gates never exceed the gate limit
+        let started = AtomicUsize::new(0);
+        let evaluated = AtomicUsize::new(0);
+        let aborted: Mutex<Option<EvalLimit>> = Mutex::new(None);
+
+        let arenas = GateArenas::new();
+        let parallel_gates = parallelism_config().gates;
         for level in levels.iter() {
             debug_mem("New level started");
             // All gates in the same level can be processed in parallel.
-            level.par_iter().for_each(|&gate_id| {
+            let eval_gate = |&gate_id: &usize| {
                 debug_mem(format!("Gate id {} started", gate_id));
                 if wires.contains_key(&gate_id) {
                     debug_mem(format!("Gate id {} already evaluated", gate_id));
                     return;
                 }
+                if let Some(limit) = options.check(started.fetch_add(1, Ordering::AcqRel)) {
+                    aborted.lock().unwrap().get_or_insert(limit);
+                    return;
+                }
                 let gate = self.gates.get(&gate_id).expect("gate not found").clone();
                 debug_mem("Get gate");
-                let result = match &gate.gate_type {
-                    PolyGateType::Input => {
-                        panic!("Input gate {:?} should already be preloaded", gate);
+                // The inputs are cloned into the thread's arena, which is reset for every gate
+                let result = arenas.with(|arena| {
+                    let start = arena.alloc_extend(gate.input_gates.iter().map(|id| {
+                        wires.get(id).unwrap_or_else(|| panic!("wire {} missing", id)).clone()
+                    }));
+                    let inputs = arena.slice(start, gate.input_gates.len());
+                    match &gate.gate_type {
+                        PolyGateType::Input => {
+                            panic!("Input gate {:?} should already be preloaded", gate);
+                        }
+                        PolyGateType::Const { digits } => E::from_digits(params, one, digits),
+                        PolyGateType::Add => {
+                            debug_mem("Add gate start");
+                            let result = inputs[0].clone() + &inputs[1];
+                            debug_mem("Add gate end");
+                            result
+                        }
+                        PolyGateType::Sub => {
+                            debug_mem("Sub gate start");
+                            let result = inputs[0].clone() - &inputs[1];
+                            debug_mem("Sub gate end");
+                            result
+                        }
+                        PolyGateType::Mul => {
+                            debug_mem("Mul gate start");
+                            let (left, right) = (&inputs[0], &inputs[1]);
+                            let right_id = gate.input_gates[1];
+                            let result = if let Some(remaining) = pending.get(&right_id) {
+                                let operand = prepared
+                                    .entry(right_id)
+                                    .or_insert_with(|| Arc::new(right.prepare(params)))
+                                    .clone();
+                                if remaining.fetch_sub(1, Ordering::AcqRel) == 1 {
+                                    prepared.remove(&right_id);
+                                }
+                                left.clone().mul_prepared(right, &operand)
+                            } else {
+                                left.clone() * right
+                            };
+                            debug_mem("Mul gate end");
+                            result
+                        }
+                        PolyGateType::Rotate { shift } => {
+                            debug_mem("Rotate gate start");
+                            let result = inputs[0].rotate(params, *shift);
+                            debug_mem("Rotate gate end");
+                            result
+                        }
+                        PolyGateType::AddConst { digits } => {
+                            inputs[0].add_const(params, one, digits)
+                        }
+                        PolyGateType::MulConst { digits } => inputs[0].mul_const(params, digits),
+                        PolyGateType::MulScalar { scalar } => inputs[0].mul_scalar(params, scalar),
+                        PolyGateType::Automorphism { k } => {
+                            inputs[0].apply_automorphism(params, one, [&inputs[1], &inputs[2]], *k)
+                        }
+                        PolyGateType::InnerProduct { len } => {
+                            debug_mem("InnerProduct gate start");
+                            let (lhs, rhs) = inputs.split_at(*len);
+                            let result = E::eval_inner_product(params, lhs, rhs);
+                            debug_mem("InnerProduct gate end");
+                            result
+                        }
+                        PolyGateType::Call { .. } => {
+                            panic!("no more call gate type during evaluation");
+                        }
                     }
-                    PolyGateType::Const { digits } => E::from_digits(params, one, digits),
-                    PolyGateType::Add => {
-                        debug_mem("Add gate start");
-                        let left =
-                            wires.get(&gate.input_gates[0]).expect("wire missing for Add").clone();
-                        let right =
-                            wires.get(&gate.input_gates[1]).expect("wire missing for Add").clone();
-                        let result = left + right;
-                        debug_mem("Add gate end");
-                        result
-                    }
-                    PolyGateType::Sub => {
-                        debug_mem("Sub gate start");
-                        let left =
-                            wires.get(&gate.input_gates[0]).expect("wire missing for Sub").clone();
-                        let right =
-                            wires.get(&gate.input_gates[1]).expect("wire missing for Sub").clone();
-                        let result = left - right;
-                        debug_mem("Sub gate end");
-                        result
-                    }
-                    PolyGateType::Mul => {
-                        debug_mem("Mul gate start");
-                        let left =
-                            wires.get(&gate.input_gates[0]).expect("wire missing for Mul").clone();
-                        let right =
-                            wires.get(&gate.input_gates[1]).expect("wire missing for Mul").clone();
-                        let result = left * right;
-                        debug_mem("Mul gate end");
-                        result
-                    }
-                    PolyGateType::Rotate { shift } => {
-                        debug_mem("Rotate gate start");
-                        let input =
-                            wires.get(&gate.input_gates[0]).expect("wire missing for Rotate");
-                        let result = input.rotate(params, *shift);
-                        debug_mem("Rotate gate end");
-                        result
-                    }
-                    PolyGateType::Call { .. } => {
-                        panic!("no more call gate type during evaluation");
-                    }
-                };
+                });
                 wires.insert(gate_id, result);
+                evaluated.fetch_add(1, Ordering::AcqRel);
                 debug_mem(format!("Gate id {} finished", gate_id));
-            });
+            };
+            if parallel_gates {
+                level.par_iter().for_each(eval_gate);
+            } else {
+                level.iter().for_each(eval_gate);
+            }
             debug_mem("Evaluated gate in parallel");
+            if let Some(limit) = aborted.lock().unwrap().take() {
+                let evaluated_gates = evaluated.load(Ordering::Acquire);
+                return Err(EvalAborted { limit, evaluated_gates, total_gates });
+            }
         }
+        Ok((wires.into_iter().collect(), arenas.stats()))
+    }
 
-        let outputs = self
-            .output_ids
-            .par_iter()
-            .map(|&id| wires.get(&id).expect("output missing").clone())
-            .collect();
-        debug_mem("Outputs are collected");
-        outputs
+    /// Evaluate the circuit one gate at a time in topological order, loading the input with
+    /// (0-based) index `i` through `load_input(i)` only when the first gate using it is reached
+    /// and dropping every wire after its last use. Peak memory is thus proportional to the
+    /// circuit width rather than the number of inputs. The gates are evaluated in `pool`.
+    pub fn eval_streaming<E: Evaluable, Error>(
+        &self,
+        params: &E::Params,
+        one: &E,
+        load_input: impl FnMut(usize) -> Result<E, Error>,
+        pool: &ThreadPoolConfig,
+    ) -> Result<Vec<E>, Error> {
+        self.eval_streaming_with(params, one, load_input, |_, _| Ok(()), |_| {}, pool)
+    }
+
+    /// Like [`Self::eval_streaming`], but calls `before_gate(evaluated, gate_type)` before and
+    /// `after_gate(gate_type)` after every gate other than an input, where `evaluated` counts
+    /// the gates evaluated so far, and aborts with the error of `before_gate`.
+    fn eval_streaming_with<E: Evaluable, Error>(
+        &self,
+        params: &E::Params,
+        one: &E,
+        load_input: impl FnMut(usize) -> Result<E, Error>,
+        before_gate: impl FnMut(usize, &PolyGateType) -> Result<(), Error>,
+        after_gate: impl FnMut(&PolyGateType),
+        pool: &ThreadPoolConfig,
+    ) -> Result<Vec<E>, Error> {
+        let order = self.topological_order();
+        self.eval_streaming_in(order, params, one, load_input, before_gate, after_gate, pool)
+    }
+
+    /// Like [`Self::eval_streaming_with`], but evaluates the gates in `order`, which must list
+    /// every gate the outputs depend on after its inputs.
+    fn eval_streaming_in<E: Evaluable, Error>(
+        &self,
+        order: Vec<usize>,
+        params: &E::Params,
+        one: &E,
+        mut load_input: impl FnMut(usize) -> Result<E, Error>,
+        mut before_gate: impl FnMut(usize, &PolyGateType) -> Result<(), Error>,
+        mut after_gate: impl FnMut(&PolyGateType),
+        pool: &ThreadPoolConfig,
+    ) -> Result<Vec<E>, Error> {
+        let mut remaining_uses: HashMap<usize, usize> = HashMap::new();
+        for gate_id in order.iter() {
+            for input in self.gates[gate_id].input_gates.iter() {
+                *remaining_uses.entry(*input).or_default() += 1;
+            }
+        }
+        for output in self.output_ids.iter() {
+            *remaining_uses.entry(*output).or_default() += 1;
+        }
+
+        let mut wires: HashMap<usize, E> = HashMap::new();
+        let mut take = |wires: &mut HashMap<usize, E>, gate_id: usize| -> E {
+            let uses = remaining_uses.get_mut(&gate_id).expect("wire use missing");
+            *uses -= 1;
+            if *uses == 0 {
+                wires.remove(&gate_id).expect("wire missing")
+            } else {
+                wires[&gate_id].clone()
+            }
+        };
+        let mut evaluated = 0;
+        for gate_id in order {
+            let gate = &self.gates[&gate_id];
+            if gate.gate_type != PolyGateType::Input {
+                before_gate(evaluated, &gate.gate_type)?;
+                evaluated += 1;
+            }
+            let result = match &gate.gate_type {
+                PolyGateType::Input if gate_id == 0 => one.clone(),
+                PolyGateType::Input => load_input(gate_id - 1)?,
+                PolyGateType::Call { .. } => {
+                    panic!("no more call gate type during evaluation");
+                }
+                gate_type => {
+                    let inputs = gate.input_gates.iter().map(|&id| take(&mut wires, id));
+                    let mut inputs = inputs.collect::<Vec<_>>().into_iter();
+                    pool.install(move || {
+                        let mut input = || inputs.next().expect("gate input missing");
+                        match gate_type {
+                            PolyGateType::Const { digits } => E::from_digits(params, one, digits),
+                            PolyGateType::Add => input() + input(),
+                            PolyGateType::Sub => input() - input(),
+                            PolyGateType::Mul => input() * input(),
+                            PolyGateType::Rotate { shift } => input().rotate(params, *shift),
+                            PolyGateType::AddConst { digits } => {
+                                input().add_const(params, one, digits)
+                            }
+                            PolyGateType::MulConst { digits } => input().mul_const(params, digits),
+                            PolyGateType::MulScalar { scalar } => {
+                                input().mul_scalar(params, scalar)
+                            }
+                            PolyGateType::Automorphism { k } => {
+                                let [x, h0, h1] = [input(), input(), input()];
+                                x.apply_automorphism(params, one, [&h0, &h1], *k)
+                            }
+                            PolyGateType::InnerProduct { len } => {
+                                let inputs = (0..2 * len).map(|_| input()).collect::<Vec<_>>();
+                                let (lhs, rhs) = inputs.split_at(*len);
+                                E::eval_inner_product(params, lhs, rhs)
+                            }
+                            PolyGateType::Input | PolyGateType::Call { .. } => unreachable!(),
+                        }
+                    })
+                }
+            };
+            if gate.gate_type != PolyGateType::Input {
+                after_gate(&gate.gate_type);
+            }
+            wires.insert(gate_id, result);
+        }
+        Ok(self.output_ids.iter().map(|&id| take(&mut wires, id)).collect())
     }
 
     pub fn register_sub_circuit(&mut self, sub_circuit: Self) -> usize {
@@ -389,6 +692,47 @@ impl PolyCircuit {
     }
 }
 
+/// One arena per rayon worker thread plus one for the calling thread, reused by every gate that
+/// thread evaluates. A gate started while its thread is still inside another one, because rayon
+/// stole it during a nested parallel operation, gets a fresh arena instead.
+struct GateArenas<E> {
+    arenas: Vec<Mutex<PolyArena<E>>>,
+    spilled: Mutex<ArenaStats>,
+}
+
+impl<E> GateArenas<E> {
+    fn new() -> Self {
+        let arenas = (0..=rayon::current_num_threads()).map(|_| Mutex::default()).collect();
+        Self { arenas, spilled: Mutex::default() }
+    }
+
+    /// Runs `f` on the reset arena of the current thread.
+    fn with<R>(&self, f: impl FnOnce(&mut PolyArena<E>) -> R) -> R {
+        let last = self.arenas.len() - 1;
+        let idx = rayon::current_thread_index().map_or(last, |idx| idx.min(last));
+        match self.arenas[idx].try_lock() {
+            Ok(mut arena) => {
+                arena.reset();
+                f(&mut arena)
+            }
+            Err(_) => {
+                let mut arena = PolyArena::new();
+                let result = f(&mut arena);
+                self.spilled.lock().unwrap().merge(arena.stats());
+                result
+            }
+        }
+    }
+
+    fn stats(self) -> ArenaStats {
+        let mut stats = self.spilled.into_inner().unwrap();
+        for arena in self.arenas {
+            stats.merge(arena.into_inner().unwrap().stats());
+        }
+        stats
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -399,6 +743,7 @@ mod tests {
                 DCRTPolyMatrix, FinRingElem,
             },
             enc::rlwe_encrypt,
+            rng::ChaChaSource,
             sampler::{DistType, PolyUniformSampler},
             Poly, PolyMatrix, PolyParams,
         },
@@ -580,6 +925,91 @@ mod tests {
         assert_eq!(result[0], expected);
     }
 
+    #[test]
+    fn test_prune_inputs() {
+        // Create parameters for testing
+        let params = DCRTPolyParams::default();
+        let polys = (0..4).map(|_| create_random_poly(&params)).collect::<Vec<_>>();
+
+        // Only x2 and x4 reach the output; x1 * x3 is dead
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(4);
+        let _dead = circuit.mul_gate(inputs[0], inputs[2]);
+        let mul = circuit.mul_gate(inputs[3], inputs[1]);
+        let digits = circuit.const_digits_poly(&[1, 1]);
+        let add = circuit.add_gate(mul, digits);
+        circuit.output(vec![add, inputs[1]]);
+
+        let (pruned, used_inputs) = circuit.prune_inputs();
+        assert_eq!(used_inputs, vec![1, 3]);
+        assert_eq!(pruned.num_input(), 2);
+        assert_eq!(pruned.num_output(), 2);
+
+        let one = DCRTPoly::const_one(&params);
+        let expected = circuit.eval(&params, &one, &polys);
+        let pruned_inputs = used_inputs.iter().map(|&i| polys[i].clone()).collect::<Vec<_>>();
+        assert_eq!(pruned.eval(&params, &one, &pruned_inputs), expected);
+
+        // A constant output uses no input at all
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        let _dead = circuit.add_gate(inputs[0], inputs[1]);
+        let digits = circuit.const_digits_poly(&[1, 1]);
+        circuit.output(vec![digits]);
+        let (pruned, used_inputs) = circuit.prune_inputs();
+        assert!(used_inputs.is_empty());
+        assert_eq!(pruned.num_input(), 0);
+        let expected = circuit.eval(&params, &one, &polys[..2]);
+        assert_eq!(pruned.eval(&params, &one, &[]), expected);
+    }
+
+    #[test]
+    fn test_eval_streaming() {
+        // Create parameters for testing
+        let params = DCRTPolyParams::default();
+        let polys = (0..5).map(|_| create_random_poly(&params)).collect::<Vec<_>>();
+
+        // (x1 + x2) * x4 - x2, with x3 and x5 unused
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(5);
+        let add = circuit.add_gate(inputs[0], inputs[1]);
+        let mul = circuit.mul_gate(add, inputs[3]);
+        let sub = circuit.sub_gate(mul, inputs[1]);
+        let square = circuit.mul_gate(sub, sub);
+        circuit.output(vec![sub, square]);
+
+        let one = DCRTPoly::const_one(&params);
+        let expected = circuit.eval(&params, &one, &polys);
+
+        // Inputs are loaded once each and only when needed
+        let mut loaded = vec![];
+        let result = circuit
+            .eval_streaming(
+                &params,
+                &one,
+                |idx| {
+                    loaded.push(idx);
+                    Ok::<_, ()>(polys[idx].clone())
+                },
+                &ThreadPoolConfig::global(),
+            )
+            .unwrap();
+        assert_eq!(result, expected);
+        loaded.sort();
+        assert_eq!(loaded, vec![0, 1, 3]);
+
+        // Errors of the loader are propagated
+        let pool = ThreadPoolConfig::global();
+        let err = circuit.eval_streaming(&params, &one, |idx| Err::<DCRTPoly, _>(idx), &pool);
+        assert!(err.is_err());
+
+        // The gates are evaluated in the given pool
+        let pool = ThreadPoolConfig::with_num_threads(2).unwrap();
+        let load_input = |idx: usize| Ok::<_, ()>(polys[idx].clone());
+        let result = circuit.eval_streaming(&params, &one, load_input, &pool).unwrap();
+        assert_eq!(result, expected);
+    }
+
     #[test]
     fn test_eval_multiple_outputs() {
         // Create parameters for testing
@@ -674,6 +1104,20 @@ mod tests {
         assert_eq!(result[0], expected);
     }
 
+    #[test]
+    fn test_inner_product_gate() {
+        let params = DCRTPolyParams::default();
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(4);
+        let ip = circuit.inner_product_gate(&inputs[0..2], &inputs[2..4]);
+        circuit.output(vec![ip]);
+        let polys = (0..4).map(|_| create_random_poly(&params)).collect::<Vec<_>>();
+        let result = circuit.eval(&params, &DCRTPoly::const_one(&params), &polys);
+        let expected = polys[0].clone() * &polys[2] + polys[1].clone() * &polys[3];
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0], expected);
+    }
+
     #[test]
     fn test_boolean_gate_and() {
         let params = DCRTPolyParams::default();
@@ -1049,4 +1493,71 @@ mod tests {
         assert_eq!(result.len(), 1);
         assert_eq!(result[0], expected);
     }
+
+    #[test]
+    fn test_eval_arena_reuse() {
+        // Create parameters for testing
+        let params = DCRTPolyParams::default();
+        let poly1 = create_random_poly(&params);
+        let poly2 = create_random_poly(&params);
+
+        // A chain of 200 Add gates, each with two inputs
+        let num_gates = 200;
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        let mut gate = inputs[0];
+        for _ in 0..num_gates {
+            gate = circuit.add_gate(gate, inputs[1]);
+        }
+        circuit.output(vec![gate]);
+
+        // Evaluate the circuit and check the result
+        let one = DCRTPoly::const_one(&params);
+        let values = [poly1.clone(), poly2.clone()];
+        let options = EvalOptions::default();
+        let (wires, stats) =
+            circuit.eval_wires_with_options(&params, &one, &values, &options).unwrap();
+        let expected = (0..num_gates).fold(poly1, |acc, _| acc + &poly2);
+        assert_eq!(wires[&gate], expected);
+
+        // Every gate cloned its two inputs into an arena, but the arenas only grew once per
+        // thread instead of allocating a buffer for every gate
+        assert_eq!(stats.resets, num_gates);
+        assert_eq!(stats.allocations, 2 * num_gates);
+        assert!(stats.growths <= rayon::current_num_threads() + 1);
+    }
+
+    #[test]
+    fn test_eval_deterministic_across_pools() {
+        let params = DCRTPolyParams::default();
+        let one = DCRTPoly::const_one(&params);
+
+        // A wide circuit whose levels hold many gates
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(8);
+        let mut outputs = vec![];
+        for i in 0..8 {
+            let product = circuit.mul_gate(inputs[i], inputs[(i + 1) % 8]);
+            let sum = circuit.add_gate(product, inputs[(i + 3) % 8]);
+            outputs.push(circuit.mul_gate(sum, inputs[(i + 5) % 8]));
+        }
+        circuit.output(outputs);
+
+        // Inputs sampled from one seed and the outputs do not depend on the pool nor its number
+        // of threads
+        let sample_and_eval = |pool: &ThreadPoolConfig| {
+            let source = Arc::new(ChaChaSource::for_test(3));
+            let sampler = DCRTPolyUniformSampler::with_source(source);
+            let polys = pool
+                .install(|| sampler.sample_uniform(&params, 1, 8, DistType::FinRingDist))
+                .get_row(0);
+            (circuit.eval_in(&params, &one, &polys, pool), polys)
+        };
+        let expected = sample_and_eval(&ThreadPoolConfig::global());
+        for num_threads in [1, 2, 4] {
+            let pool = ThreadPoolConfig::with_num_threads(num_threads).unwrap();
+            assert_eq!(pool.current_num_threads(), num_threads);
+            assert_eq!(sample_and_eval(&pool), expected);
+        }
+    }
 }
@@ -0,0 +1,125 @@
+use super::{Evaluable, PolyCircuit};
+use crate::poly::Poly;
+
+/// How the bits of an output are extracted once its plaintext polynomial is recovered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputDecoding {
+    /// One bit per coefficient, for outputs packing several bits into one polynomial.
+    #[default]
+    Coefficients,
+    /// Only the bit of the constant coefficient, for boolean outputs.
+    ConstantTerm,
+}
+
+impl OutputDecoding {
+    /// Rounds the coefficients selected by this decoding to bits like
+    /// [`Poly::extract_bits_with_threshold`].
+    pub fn decode<P: Poly>(&self, params: &P::Params, poly: &P) -> Vec<bool> {
+        let mut bits = poly.extract_bits_with_threshold(params);
+        if *self == Self::ConstantTerm {
+            bits.truncate(1);
+        }
+        bits
+    }
+}
+
+/// Metadata needed to decode one output of a circuit independently of the other outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputInfo {
+    /// Position of the output in the order given to [`PolyCircuit::output`].
+    pub index: usize,
+    pub gate_id: usize,
+    /// Length of the longest path from an input to the output gate.
+    pub depth: usize,
+    pub decoding: OutputDecoding,
+}
+
+impl PolyCircuit {
+    /// Returns the metadata of every output, decoding the `i`-th output with `decodings[i]`.
+    pub fn output_info(&self, decodings: &[OutputDecoding]) -> Vec<OutputInfo> {
+        assert_eq!(decodings.len(), self.num_output(), "one decoding per output is required");
+        let levels = self.compute_levels();
+        self.output_ids
+            .iter()
+            .zip(decodings.iter())
+            .enumerate()
+            .map(|(index, (&gate_id, &decoding))| {
+                let depth = levels
+                    .iter()
+                    .position(|level| level.contains(&gate_id))
+                    .expect("output gate not found");
+                OutputInfo { index, gate_id, depth, decoding }
+            })
+            .collect()
+    }
+
+    /// Evaluates every output of the circuit in a single pass like [`Self::eval`], returning the
+    /// outputs in output order together with their [`OutputInfo`].
+    pub fn eval_outputs<E: Evaluable>(
+        &self,
+        params: &E::Params,
+        one: &E,
+        inputs: &[E],
+        decodings: &[OutputDecoding],
+    ) -> (Vec<E>, Vec<OutputInfo>) {
+        let info = self.output_info(decodings);
+        (self.eval(params, one, inputs), info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::{
+        dcrt::{DCRTPoly, DCRTPolyParams, FinRingElem},
+        PolyElem, PolyParams,
+    };
+
+    #[test]
+    fn test_eval_outputs() {
+        let params = DCRTPolyParams::default();
+        let modulus = params.modulus();
+        let half_q = FinRingElem::half_q(&modulus);
+        let zero = FinRingElem::zero(&modulus);
+        let encode = |bits: [bool; 4]| {
+            let coeffs = bits
+                .iter()
+                .map(|&bit| if bit { half_q.clone() } else { zero.clone() })
+                .collect::<Vec<_>>();
+            DCRTPoly::from_coeffs(&params, &coeffs)
+        };
+
+        // Three outputs of different depths: x1 + x2, x1 and (x1 + x2) * x2
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        let add_gate = circuit.add_gate(inputs[0], inputs[1]);
+        let mul_gate = circuit.mul_gate(add_gate, inputs[1]);
+        circuit.output(vec![add_gate, inputs[0], mul_gate]);
+
+        // Evaluate once and decode the first two outputs separately
+        let x1 = encode([true, false, true, false]);
+        let x2 = encode([true, true, false, false]);
+        let decodings =
+            [OutputDecoding::Coefficients, OutputDecoding::ConstantTerm, OutputDecoding::default()];
+        let (outputs, info) =
+            circuit.eval_outputs(&params, &DCRTPoly::const_one(&params), &[x1, x2], &decodings);
+        assert_eq!(outputs.len(), 3);
+        assert_eq!(
+            info.iter().map(|info| (info.index, info.gate_id, info.depth)).collect::<Vec<_>>(),
+            vec![(0, add_gate, 1), (1, inputs[0], 0), (2, mul_gate, 2)]
+        );
+
+        // Adding two encodings of one wraps around to about zero, so the sum decodes to XOR
+        assert_eq!(info[0].decoding.decode(&params, &outputs[0]), vec![false, true, true, false]);
+        assert_eq!(info[1].decoding.decode(&params, &outputs[1]), vec![true]);
+    }
+
+    #[test]
+    #[should_panic(expected = "one decoding per output is required")]
+    fn test_output_info_wrong_length() {
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        circuit.output(inputs);
+        circuit.output_info(&[OutputDecoding::ConstantTerm]);
+    }
+}
@@ -0,0 +1,211 @@
+//! Partial evaluation of circuits over attributes whose values are public.
+use super::{PolyCircuit, PolyGateType};
+use std::collections::{HashMap, HashSet};
+
+/// A wire of the simplified circuit: either an integer constant folded in, or a gate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Wire {
+    Const(i64),
+    Gate(usize),
+}
+
+/// Builds the simplified circuit, creating every constant gate at most once.
+struct Folder {
+    circuit: PolyCircuit,
+    constants: HashMap<i64, usize>,
+}
+
+impl Folder {
+    fn gate(&mut self, wire: Wire) -> usize {
+        let c = match wire {
+            Wire::Gate(id) => return id,
+            Wire::Const(1) => return 0,
+            Wire::Const(c) => c,
+        };
+        if let Some(&id) = self.constants.get(&c) {
+            return id;
+        }
+        let id = match c {
+            0 => self.circuit.const_zero_gate(),
+            c if c > 0 && c <= u32::MAX as i64 => self.circuit.const_digits_poly(&[c as u32]),
+            c => {
+                let zero = self.gate(Wire::Const(0));
+                let magnitude = self.gate(Wire::Const(c.checked_neg().expect("constant overflow")));
+                self.circuit.sub_gate(zero, magnitude)
+            }
+        };
+        self.constants.insert(c, id);
+        id
+    }
+
+    fn binary(&mut self, gate_type: PolyGateType, left: Wire, right: Wire) -> usize {
+        let inputs = vec![self.gate(left), self.gate(right)];
+        self.circuit.new_gate_generic(inputs, gate_type)
+    }
+
+    fn unary(&mut self, gate_type: PolyGateType, input: Wire) -> usize {
+        let inputs = vec![self.gate(input)];
+        self.circuit.new_gate_generic(inputs, gate_type)
+    }
+}
+
+/// Returns the constant of `digits` if only its constant coefficient can be non-zero.
+fn scalar(digits: &[u32]) -> Option<i64> {
+    match digits {
+        [] => Some(0),
+        [c, rest @ ..] if rest.iter().all(|&d| d == 0) => Some(*c as i64),
+        _ => None,
+    }
+}
+
+impl PolyCircuit {
+    /// Folds the public values of the attributes in `known`, given as (0-based attribute index,
+    /// bit) pairs, into the circuit. Gates whose inputs are all known are computed as integer
+    /// constants, and additions of zero, multiplications by zero or one and multiplications by
+    /// other constants (as [`Self::mul_const_gate`]) are simplified, so fewer multiplications are
+    /// evaluated homomorphically.
+    ///
+    /// Returns the simplified circuit over the remaining attributes together with their indices
+    /// in this circuit. At least one attribute must remain unknown.
+    pub fn partial_evaluate(&self, known: &[(usize, bool)]) -> (Self, Vec<usize>) {
+        let mut known_bits = HashMap::new();
+        for &(idx, bit) in known {
+            assert!(idx < self.num_input, "attribute index {} out of range", idx);
+            assert!(known_bits.insert(idx, bit).is_none(), "attribute {} is given twice", idx);
+        }
+        let remaining =
+            (0..self.num_input).filter(|idx| !known_bits.contains_key(idx)).collect::<Vec<_>>();
+        assert!(!remaining.is_empty(), "every attribute is known");
+
+        let mut folder = Folder { circuit: Self::new(), constants: HashMap::new() };
+        folder.circuit.sub_circuits = self.sub_circuits.clone();
+        let new_inputs = folder.circuit.input(remaining.len());
+        let mut wires = HashMap::from([(0, Wire::Const(1))]);
+        for (idx, bit) in known_bits {
+            wires.insert(idx + 1, Wire::Const(bit as i64));
+        }
+        for (&idx, &new_input) in remaining.iter().zip(new_inputs.iter()) {
+            wires.insert(idx + 1, Wire::Gate(new_input));
+        }
+
+        // Gate ids are assigned in topological order, so inputs are folded before their users.
+        let reachable = self.topological_order().into_iter().collect::<HashSet<_>>();
+        for gate in self.gates.values() {
+            if gate.gate_type == PolyGateType::Input || !reachable.contains(&gate.gate_id) {
+                continue;
+            }
+            let inputs = gate.input_gates.iter().map(|id| wires[id]).collect::<Vec<_>>();
+            let gate_type = &gate.gate_type;
+            let wire = match (gate_type, inputs.as_slice()) {
+                (PolyGateType::Const { digits }, []) => match scalar(digits) {
+                    Some(c) => Wire::Const(c),
+                    None => Wire::Gate(folder.circuit.new_gate_generic(vec![], gate_type.clone())),
+                },
+                (PolyGateType::Add, &[left, right]) => match (left, right) {
+                    (Wire::Const(a), Wire::Const(b)) if a.checked_add(b).is_some() => {
+                        Wire::Const(a + b)
+                    }
+                    (Wire::Const(0), other) | (other, Wire::Const(0)) => other,
+                    _ => Wire::Gate(folder.binary(gate_type.clone(), left, right)),
+                },
+                (PolyGateType::Sub, &[left, right]) => match (left, right) {
+                    (Wire::Const(a), Wire::Const(b)) if a.checked_sub(b).is_some() => {
+                        Wire::Const(a - b)
+                    }
+                    (other, Wire::Const(0)) => other,
+                    _ => Wire::Gate(folder.binary(gate_type.clone(), left, right)),
+                },
+                (PolyGateType::Mul, &[left, right]) => match (left, right) {
+                    (Wire::Const(a), Wire::Const(b)) if a.checked_mul(b).is_some() => {
+                        Wire::Const(a * b)
+                    }
+                    (Wire::Const(0), _) | (_, Wire::Const(0)) => Wire::Const(0),
+                    (Wire::Const(1), other) | (other, Wire::Const(1)) => other,
+                    (Wire::Const(c), Wire::Gate(id)) | (Wire::Gate(id), Wire::Const(c))
+                        if c > 0 && c <= u32::MAX as i64 =>
+                    {
+                        Wire::Gate(folder.circuit.mul_const_gate(id, &[c as u32]))
+                    }
+                    _ => Wire::Gate(folder.binary(gate_type.clone(), left, right)),
+                },
+                (PolyGateType::Rotate { .. }, &[Wire::Const(0)]) => Wire::Const(0),
+                // Automorphisms fix integers
+                (PolyGateType::Automorphism { .. }, &[Wire::Const(c), _, _]) => Wire::Const(c),
+                (PolyGateType::MulScalar { .. }, &[Wire::Const(0)]) => Wire::Const(0),
+                (PolyGateType::AddConst { digits }, &[Wire::Const(a)]) => {
+                    match scalar(digits).and_then(|c| a.checked_add(c)) {
+                        Some(sum) => Wire::Const(sum),
+                        None => Wire::Gate(folder.unary(gate_type.clone(), Wire::Const(a))),
+                    }
+                }
+                (PolyGateType::MulConst { .. }, &[Wire::Const(0)]) => Wire::Const(0),
+                (PolyGateType::MulConst { digits }, &[Wire::Const(a)]) => {
+                    match scalar(digits).and_then(|c| a.checked_mul(c)) {
+                        Some(product) => Wire::Const(product),
+                        None => Wire::Gate(folder.unary(gate_type.clone(), Wire::Const(a))),
+                    }
+                }
+                _ => {
+                    let inputs = inputs.iter().map(|&wire| folder.gate(wire)).collect();
+                    Wire::Gate(folder.circuit.new_gate_generic(inputs, gate_type.clone()))
+                }
+            };
+            wires.insert(gate.gate_id, wire);
+        }
+        let outputs = self.output_ids.iter().map(|id| folder.gate(wires[id])).collect();
+        folder.circuit.output(outputs);
+        (folder.circuit, remaining)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        poly::{
+            dcrt::{DCRTPoly, DCRTPolyParams},
+            Poly,
+        },
+        utils::create_bit_poly,
+    };
+
+    fn count_muls(circuit: &PolyCircuit) -> usize {
+        circuit.gates().filter(|gate| gate.gate_type == PolyGateType::Mul).count()
+    }
+
+    #[test]
+    fn test_partial_evaluate() {
+        let params = DCRTPolyParams::default();
+
+        // (x1 AND x2) OR (x3 XOR x4), with x1 and x3 public
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(4);
+        let and = circuit.and_gate(inputs[0], inputs[1]);
+        let xor = circuit.xor_gate(inputs[2], inputs[3]);
+        let or = circuit.or_gate(and, xor);
+        circuit.output(vec![or, and]);
+
+        let one = DCRTPoly::const_one(&params);
+        for x1 in [false, true] {
+            for x3 in [false, true] {
+                let (partial, remaining) = circuit.partial_evaluate(&[(0, x1), (2, x3)]);
+                assert_eq!(remaining, vec![1, 3]);
+                assert!(count_muls(&partial) < count_muls(&circuit));
+
+                // The simplified circuit agrees with the full one on every remaining attribute
+                for x2 in [false, true] {
+                    for x4 in [false, true] {
+                        let bits = [x1, x2, x3, x4];
+                        let full =
+                            bits.iter().map(|&b| create_bit_poly(&params, b)).collect::<Vec<_>>();
+                        let rest = [create_bit_poly(&params, x2), create_bit_poly(&params, x4)];
+                        assert_eq!(
+                            partial.eval(&params, &one, &rest),
+                            circuit.eval(&params, &one, &full)
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! Plans the evaluation of a circuit for low noise growth.
+//!
+//! The noise of a BGG+ multiplication grows with the noise of its left operand times the
+//! gadget norm, so a left-deep chain `((x1 * x2) * x3) * ...` of `k` factors multiplies the
+//! noise of `x1` by that factor `k - 1` times, while a balanced tree of the same factors only
+//! `ceil(log2 k)` times. [`PolyCircuit::plan_noise`] rebuilds every product whose intermediate
+//! results are used only once as a tree of minimal multiplicative depth, predicts the noise of
+//! both circuits with [`PolyCircuit::simulate_bgg_norm`] and keeps the rewritten one only if it
+//! does better.
+//!
+//! A rewritten circuit computes the same plaintexts but different public keys, so the keys and
+//! the encodings of an evaluation must both go through [`NoisePlan::circuit`].
+use super::{Evaluable, PolyCircuit, PolyGateType};
+use crate::{bgg::norm_simulator::NormBounds, utils::ThreadPoolConfig};
+use num_bigint::BigUint;
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+    convert::Infallible,
+};
+
+/// A circuit planned by [`PolyCircuit::plan_noise`] together with the order to evaluate its
+/// gates in and its predicted noise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoisePlan {
+    circuit: PolyCircuit,
+    schedule: Vec<usize>,
+    noise: NormBounds,
+    original_noise: NormBounds,
+}
+
+impl NoisePlan {
+    /// The circuit to evaluate, which is the original one if rebalancing did not lower the noise.
+    pub fn circuit(&self) -> &PolyCircuit {
+        &self.circuit
+    }
+
+    /// The gate ids of [`Self::circuit`] level by level, every gate after its inputs.
+    pub fn schedule(&self) -> &[usize] {
+        &self.schedule
+    }
+
+    /// The predicted noise of the outputs of [`Self::circuit`].
+    pub fn noise(&self) -> &NormBounds {
+        &self.noise
+    }
+
+    /// The predicted noise of the outputs of the circuit the plan was made for.
+    pub fn original_noise(&self) -> &NormBounds {
+        &self.original_noise
+    }
+
+    /// Whether the plan rewrote the circuit.
+    pub fn is_rebalanced(&self) -> bool {
+        self.noise != self.original_noise
+    }
+
+    pub fn eval<E: Evaluable>(&self, params: &E::Params, one: &E, inputs: &[E]) -> Vec<E> {
+        let load_input = |idx: usize| Ok(inputs[idx].clone());
+        let pool = ThreadPoolConfig::global();
+        match self.eval_streaming::<E, Infallible>(params, one, load_input, &pool) {
+            Ok(outputs) => outputs,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Evaluates [`Self::circuit`] like [`PolyCircuit::eval_streaming`], in the order of
+    /// [`Self::schedule`].
+    pub fn eval_streaming<E: Evaluable, Error>(
+        &self,
+        params: &E::Params,
+        one: &E,
+        load_input: impl FnMut(usize) -> Result<E, Error>,
+        pool: &ThreadPoolConfig,
+    ) -> Result<Vec<E>, Error> {
+        self.circuit.eval_streaming_in(
+            self.schedule.clone(),
+            params,
+            one,
+            load_input,
+            |_, _| Ok(()),
+            |_| {},
+            pool,
+        )
+    }
+}
+
+impl PolyCircuit {
+    /// Plans the evaluation of the circuit for the least noise, predicting it as
+    /// [`Self::simulate_bgg_norm`] does for the same arguments.
+    pub fn plan_noise(
+        &self,
+        dim: u32,
+        base_bits: u32,
+        packed_input_norms: Vec<BigUint>,
+    ) -> NoisePlan {
+        let original_noise = self.simulate_bgg_norm(dim, base_bits, packed_input_norms.clone());
+        let rebalanced = self.rebalance_muls();
+        let noise = rebalanced.simulate_bgg_norm(dim, base_bits, packed_input_norms);
+        let (circuit, noise) = if noise.max_bits() < original_noise.max_bits() {
+            (rebalanced, noise)
+        } else {
+            (self.clone(), original_noise.clone())
+        };
+        let schedule = circuit.compute_levels().into_iter().flatten().collect();
+        NoisePlan { circuit, schedule, noise, original_noise }
+    }
+
+    /// Returns an equivalent circuit in which every product of factors whose intermediate
+    /// results have no other use is computed by a tree of minimal multiplicative depth.
+    fn rebalance_muls(&self) -> Self {
+        let mut uses: HashMap<usize, usize> = HashMap::new();
+        let mut mul_users: HashMap<usize, usize> = HashMap::new();
+        for gate in self.gates.values() {
+            for input in gate.input_gates.iter() {
+                *uses.entry(*input).or_default() += 1;
+                if gate.gate_type == PolyGateType::Mul {
+                    *mul_users.entry(*input).or_default() += 1;
+                }
+            }
+        }
+        for output in self.output_ids.iter() {
+            *uses.entry(*output).or_default() += 1;
+        }
+        // Products used once, by another product, are merged into the tree of their user.
+        let merged = self
+            .gates
+            .values()
+            .filter(|gate| {
+                gate.gate_type == PolyGateType::Mul &&
+                    uses.get(&gate.gate_id) == Some(&1) &&
+                    mul_users.get(&gate.gate_id) == Some(&1)
+            })
+            .map(|gate| gate.gate_id)
+            .collect::<HashSet<_>>();
+
+        let mut rebalanced = Self::new();
+        rebalanced.sub_circuits = self.sub_circuits.clone();
+        let new_inputs = rebalanced.input(self.num_input);
+        let mut gate_map = HashMap::from([(0, 0)]);
+        let mut depths = HashMap::from([(0, 0)]);
+        for (old_input, new_input) in (1..=self.num_input).zip(new_inputs) {
+            gate_map.insert(old_input, new_input);
+            depths.insert(new_input, 0);
+        }
+        // Gate ids are assigned in topological order, so inputs are mapped before their users.
+        for gate in self.gates.values() {
+            if gate.gate_type == PolyGateType::Input || merged.contains(&gate.gate_id) {
+                continue;
+            }
+            let new_id = if gate.gate_type == PolyGateType::Mul {
+                let factors = self
+                    .mul_factors(gate.gate_id, &merged)
+                    .into_iter()
+                    .map(|factor| gate_map[&factor])
+                    .collect::<Vec<_>>();
+                rebalanced.balanced_product(&factors, &mut depths)
+            } else {
+                let inputs = gate.input_gates.iter().map(|id| gate_map[id]).collect::<Vec<_>>();
+                let mut depth = inputs.iter().map(|id| depths[id]).max().unwrap_or(0);
+                if let PolyGateType::InnerProduct { .. } = gate.gate_type {
+                    depth += 1;
+                }
+                let new_id = rebalanced.new_gate_generic(inputs, gate.gate_type.clone());
+                depths.insert(new_id, depth);
+                new_id
+            };
+            gate_map.insert(gate.gate_id, new_id);
+        }
+        rebalanced.output(self.output_ids.iter().map(|id| gate_map[id]).collect());
+        rebalanced
+    }
+
+    /// The factors of the product computed by the Mul gate `gate_id`, descending into the
+    /// `merged` products, from left to right.
+    fn mul_factors(&self, gate_id: usize, merged: &HashSet<usize>) -> Vec<usize> {
+        let mut factors = vec![];
+        let mut stack = vec![gate_id];
+        while let Some(id) = stack.pop() {
+            if id != gate_id && !merged.contains(&id) {
+                factors.push(id);
+                continue;
+            }
+            let gate = &self.gates[&id];
+            stack.push(gate.input_gates[1]);
+            stack.push(gate.input_gates[0]);
+        }
+        factors
+    }
+
+    /// Multiplies `factors` by always multiplying the two shallowest partial products, which
+    /// gives the least multiplicative depth, and returns the id of the product.
+    fn balanced_product(&mut self, factors: &[usize], depths: &mut HashMap<usize, usize>) -> usize {
+        // The shallower operand goes on the left, whose noise is scaled by the gadget norm,
+        // and ties are broken by position.
+        let mut heap = factors
+            .iter()
+            .enumerate()
+            .map(|(position, &id)| Reverse((depths[&id], position, id)))
+            .collect::<BinaryHeap<_>>();
+        let mut position = factors.len();
+        loop {
+            let Reverse((left_depth, _, left)) = heap.pop().expect("product of no factors");
+            let Some(Reverse((right_depth, _, right))) = heap.pop() else {
+                return left;
+            };
+            let product = self.mul_gate(left, right);
+            let depth = left_depth.max(right_depth) + 1;
+            depths.insert(product, depth);
+            heap.push(Reverse((depth, position, product)));
+            position += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        poly::{
+            dcrt::{DCRTPoly, DCRTPolyParams},
+            Poly,
+        },
+        utils::create_random_poly,
+    };
+
+    #[test]
+    fn test_plan_noise_balances_mul_chain() {
+        let params = DCRTPolyParams::default();
+
+        // x1 * ... * x8 as a chain of depth 7, plus a product shared by two outputs
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(8);
+        let chain = inputs[1..].iter().fold(inputs[0], |acc, &input| circuit.mul_gate(acc, input));
+        let shared = circuit.mul_gate(inputs[0], inputs[1]);
+        let reused = circuit.add_gate(shared, inputs[2]);
+        let extended = circuit.mul_gate(shared, inputs[3]);
+        circuit.output(vec![chain, reused, extended]);
+
+        let plan = circuit.plan_noise(16, 1, vec![BigUint::from(1u32); 8]);
+        assert!(plan.is_rebalanced());
+        assert!(plan.noise().max_bits() < plan.original_noise().max_bits());
+        let noise = plan.circuit().simulate_bgg_norm(16, 1, vec![BigUint::from(1u32); 8]);
+        assert_eq!(&noise, plan.noise());
+
+        // 7 multiplications of depth 3 replace the chain, the shared product is kept
+        let muls = |circuit: &PolyCircuit| {
+            circuit.gates().filter(|gate| gate.gate_type == PolyGateType::Mul).count()
+        };
+        assert_eq!(muls(plan.circuit()), muls(&circuit));
+        let levels = plan.circuit().compute_levels();
+        assert_eq!(levels.len(), 4);
+        assert_eq!(plan.schedule().len(), levels.iter().map(Vec::len).sum::<usize>());
+
+        // The plan evaluates to the same plaintexts in its schedule
+        let one = DCRTPoly::const_one(&params);
+        let polys = (0..8).map(|_| create_random_poly(&params)).collect::<Vec<_>>();
+        assert_eq!(plan.eval(&params, &one, &polys), circuit.eval(&params, &one, &polys));
+        assert_eq!(plan.circuit().eval(&params, &one, &polys), circuit.eval(&params, &one, &polys));
+    }
+
+    #[test]
+    fn test_plan_noise_keeps_balanced_circuit() {
+        // (x1 * x2) * (x3 * x4) cannot be improved
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(4);
+        let left = circuit.mul_gate(inputs[0], inputs[1]);
+        let right = circuit.mul_gate(inputs[2], inputs[3]);
+        let product = circuit.mul_gate(left, right);
+        circuit.output(vec![product]);
+
+        let plan = circuit.plan_noise(16, 1, vec![BigUint::from(1u32); 4]);
+        assert!(!plan.is_rebalanced());
+        assert_eq!(plan.circuit(), &circuit);
+    }
+}
@@ -0,0 +1,278 @@
+//! Compiles boolean policies over named attributes, e.g. `(age_over_18 AND country_us) OR admin`,
+//! into circuits with one input per attribute and a single 0/1 output.
+//!
+//! The grammar is `expr := term (OR term)*`, `term := factor (AND factor)*` and
+//! `factor := NOT factor | '(' expr ')' | name`, where the keywords are case-insensitive and
+//! names consist of ASCII letters, digits and underscores.
+use super::PolyCircuit;
+use std::collections::HashMap;
+
+/// The maximum nesting depth of parentheses and `NOT`s, which bounds the recursion of the parser.
+pub const MAX_POLICY_DEPTH: usize = 256;
+
+/// Error returned when a policy cannot be compiled. Positions are byte offsets into the policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyError {
+    UnknownAttribute { name: String, position: usize },
+    DuplicateAttribute(String),
+    UnexpectedToken { found: String, position: usize },
+    UnexpectedEnd,
+    TooDeep { position: usize },
+}
+
+impl std::fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownAttribute { name, position } => {
+                write!(f, "unknown attribute `{}` at position {}", name, position)
+            }
+            Self::DuplicateAttribute(name) => write!(f, "attribute `{}` is listed twice", name),
+            Self::UnexpectedToken { found, position } => {
+                write!(f, "unexpected `{}` at position {}", found, position)
+            }
+            Self::UnexpectedEnd => write!(f, "unexpected end of policy"),
+            Self::TooDeep { position } => write!(
+                f,
+                "policy nested deeper than {} levels at position {}",
+                MAX_POLICY_DEPTH, position
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PolicyError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token<'a> {
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+    Name(&'a str),
+}
+
+fn tokenize(policy: &str) -> Result<Vec<(usize, Token<'_>)>, PolicyError> {
+    let mut tokens = Vec::new();
+    let bytes = policy.as_bytes();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let c = bytes[pos];
+        if c.is_ascii_whitespace() {
+            pos += 1;
+        } else if c == b'(' || c == b')' {
+            tokens.push((pos, if c == b'(' { Token::Open } else { Token::Close }));
+            pos += 1;
+        } else if c.is_ascii_alphanumeric() || c == b'_' {
+            let start = pos;
+            while pos < bytes.len() && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'_') {
+                pos += 1;
+            }
+            let word = &policy[start..pos];
+            let token = match word.to_ascii_uppercase().as_str() {
+                "AND" => Token::And,
+                "OR" => Token::Or,
+                "NOT" => Token::Not,
+                _ => Token::Name(word),
+            };
+            tokens.push((start, token));
+        } else {
+            let found = policy[pos..].chars().next().unwrap().to_string();
+            return Err(PolicyError::UnexpectedToken { found, position: pos });
+        }
+    }
+    Ok(tokens)
+}
+
+fn describe(token: &Token<'_>) -> String {
+    match token {
+        Token::And => "AND".to_string(),
+        Token::Or => "OR".to_string(),
+        Token::Not => "NOT".to_string(),
+        Token::Open => "(".to_string(),
+        Token::Close => ")".to_string(),
+        Token::Name(name) => name.to_string(),
+    }
+}
+
+struct Parser<'a, 'c> {
+    tokens: Vec<(usize, Token<'a>)>,
+    pos: usize,
+    depth: usize,
+    inputs: HashMap<&'a str, usize>,
+    circuit: &'c mut PolyCircuit,
+}
+
+impl<'a> Parser<'a, '_> {
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    /// Enters one more level of nesting at `position`, failing beyond [`MAX_POLICY_DEPTH`].
+    fn enter(&mut self, position: usize) -> Result<(), PolicyError> {
+        if self.depth == MAX_POLICY_DEPTH {
+            return Err(PolicyError::TooDeep { position });
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn expr(&mut self) -> Result<usize, PolicyError> {
+        let mut gate = self.term()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            let right = self.term()?;
+            gate = self.circuit.or_gate(gate, right);
+        }
+        Ok(gate)
+    }
+
+    fn term(&mut self) -> Result<usize, PolicyError> {
+        let mut gate = self.factor()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            let right = self.factor()?;
+            gate = self.circuit.and_gate(gate, right);
+        }
+        Ok(gate)
+    }
+
+    fn factor(&mut self) -> Result<usize, PolicyError> {
+        let (position, token) =
+            self.tokens.get(self.pos).cloned().ok_or(PolicyError::UnexpectedEnd)?;
+        self.pos += 1;
+        match token {
+            Token::Not => {
+                self.enter(position)?;
+                let input = self.factor()?;
+                self.depth -= 1;
+                Ok(self.circuit.not_gate(input))
+            }
+            Token::Open => {
+                self.enter(position)?;
+                let gate = self.expr()?;
+                self.depth -= 1;
+                match self.tokens.get(self.pos) {
+                    Some((_, Token::Close)) => {
+                        self.pos += 1;
+                        Ok(gate)
+                    }
+                    Some((position, token)) => Err(PolicyError::UnexpectedToken {
+                        found: describe(token),
+                        position: *position,
+                    }),
+                    None => Err(PolicyError::UnexpectedEnd),
+                }
+            }
+            Token::Name(name) => self.inputs.get(name).copied().ok_or_else(|| {
+                PolicyError::UnknownAttribute { name: name.to_string(), position }
+            }),
+            token => Err(PolicyError::UnexpectedToken { found: describe(&token), position }),
+        }
+    }
+}
+
+/// Compiles `policy` into a circuit whose `i`-th input is the attribute `attributes[i]`, each
+/// of which must be 0 or 1.
+pub fn compile_policy(policy: &str, attributes: &[&str]) -> Result<PolyCircuit, PolicyError> {
+    let mut circuit = PolyCircuit::new();
+    let input_gates = circuit.input(attributes.len());
+    let mut inputs = HashMap::new();
+    for (&name, &gate) in attributes.iter().zip(input_gates.iter()) {
+        if inputs.insert(name, gate).is_some() {
+            return Err(PolicyError::DuplicateAttribute(name.to_string()));
+        }
+    }
+    let tokens = tokenize(policy)?;
+    let mut parser = Parser { tokens, pos: 0, depth: 0, inputs, circuit: &mut circuit };
+    let output = parser.expr()?;
+    if let Some((position, token)) = parser.tokens.get(parser.pos) {
+        return Err(PolicyError::UnexpectedToken { found: describe(token), position: *position });
+    }
+    circuit.output(vec![output]);
+    Ok(circuit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::{
+        dcrt::{DCRTPoly, DCRTPolyParams},
+        Poly,
+    };
+
+    #[test]
+    fn test_compile_policy() {
+        let params = DCRTPolyParams::default();
+        let attributes = ["age_over_18", "country_us", "admin"];
+        let circuit = compile_policy("(age_over_18 AND country_us) OR admin", &attributes).unwrap();
+        assert_eq!(circuit.num_input(), 3);
+
+        // The circuit agrees with the policy on every assignment
+        let one = DCRTPoly::const_one(&params);
+        let zero = DCRTPoly::const_zero(&params);
+        for bits in 0..8u8 {
+            let values = (0..3).map(|i| (bits >> i) & 1 == 1).collect::<Vec<_>>();
+            let inputs = values
+                .iter()
+                .map(|&v| if v { one.clone() } else { zero.clone() })
+                .collect::<Vec<_>>();
+            let expected = (values[0] && values[1]) || values[2];
+            let output = circuit.eval(&params, &one, &inputs);
+            assert_eq!(output, vec![if expected { one.clone() } else { zero.clone() }]);
+        }
+
+        // NOT binds tighter than AND, which binds tighter than OR
+        let circuit =
+            compile_policy("not admin and country_us or age_over_18", &attributes).unwrap();
+        let inputs = vec![zero.clone(), one.clone(), zero.clone()];
+        assert_eq!(circuit.eval(&params, &one, &inputs), vec![one.clone()]);
+    }
+
+    #[test]
+    fn test_compile_policy_errors() {
+        let attributes = ["a", "b"];
+        assert_eq!(
+            compile_policy("a AND c", &attributes).unwrap_err(),
+            PolicyError::UnknownAttribute { name: "c".to_string(), position: 6 }
+        );
+        assert_eq!(compile_policy("a AND", &attributes).unwrap_err(), PolicyError::UnexpectedEnd);
+        assert_eq!(compile_policy("(a OR b", &attributes).unwrap_err(), PolicyError::UnexpectedEnd);
+        assert_eq!(
+            compile_policy("a b", &attributes).unwrap_err(),
+            PolicyError::UnexpectedToken { found: "b".to_string(), position: 2 }
+        );
+        assert_eq!(
+            compile_policy("a & b", &attributes).unwrap_err(),
+            PolicyError::UnexpectedToken { found: "&".to_string(), position: 2 }
+        );
+        assert_eq!(
+            compile_policy("a", &["a", "a"]).unwrap_err(),
+            PolicyError::DuplicateAttribute("a".to_string())
+        );
+    }
+
+    #[test]
+    fn test_compile_policy_depth() {
+        let attributes = ["a"];
+
+        // Nesting up to the limit compiles
+        let nested = format!("{}a{}", "(".repeat(MAX_POLICY_DEPTH), ")".repeat(MAX_POLICY_DEPTH));
+        assert!(compile_policy(&nested, &attributes).is_ok());
+        let negated = format!("{}a", "NOT ".repeat(MAX_POLICY_DEPTH));
+        assert!(compile_policy(&negated, &attributes).is_ok());
+
+        // One level more is rejected at the offending token instead of overflowing the stack
+        let too_deep = format!("{}a", "(".repeat(MAX_POLICY_DEPTH + 1));
+        assert_eq!(
+            compile_policy(&too_deep, &attributes).unwrap_err(),
+            PolicyError::TooDeep { position: MAX_POLICY_DEPTH }
+        );
+        // The 128th `(NOT ` would open the 257th level with its `NOT`
+        let too_negated = format!("NOT {}a", "(NOT ".repeat(10_000));
+        assert_eq!(
+            compile_policy(&too_negated, &attributes).unwrap_err(),
+            PolicyError::TooDeep { position: 5 + 5 * 127 }
+        );
+    }
+}
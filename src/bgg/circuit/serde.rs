@@ -1,4 +1,6 @@
 use super::{PolyCircuit, PolyGateType};
+use crate::migrate::{circuit_migrator, MigrationError, CIRCUIT_VERSION};
+use num_bigint::BigUint;
 use serde::{Deserialize, Serialize};
 use serde_json;
 use std::collections::BTreeMap;
@@ -11,18 +13,54 @@ pub enum SerializablePolyGateType {
     Sub,
     Mul,
     Rotate { shift: usize },
+    AddConst { digits: Vec<u32> },
+    MulConst { digits: Vec<u32> },
+    MulScalar { scalar: BigUint },
+    Automorphism { k: usize },
+    InnerProduct { len: usize },
     Call { circuit_id: usize, num_input: usize, output_id: usize },
 }
 
 impl SerializablePolyGateType {
-    pub fn num_input(&self) -> usize {
-        match self {
+    /// The number of inputs of the gate, or `None` if it overflows `usize`, which only an
+    /// untrusted inner product length can.
+    pub fn num_input(&self) -> Option<usize> {
+        let num_input = match self {
             SerializablePolyGateType::Input | SerializablePolyGateType::Const { .. } => 0,
-            SerializablePolyGateType::Rotate { .. } => 1,
+            SerializablePolyGateType::Rotate { .. } |
+            SerializablePolyGateType::AddConst { .. } |
+            SerializablePolyGateType::MulConst { .. } |
+            SerializablePolyGateType::MulScalar { .. } => 1,
             SerializablePolyGateType::Add |
             SerializablePolyGateType::Sub |
             SerializablePolyGateType::Mul => 2,
+            SerializablePolyGateType::Automorphism { .. } => 3,
+            SerializablePolyGateType::InnerProduct { len } => return len.checked_mul(2),
             SerializablePolyGateType::Call { num_input, .. } => *num_input,
+        };
+        Some(num_input)
+    }
+}
+
+impl From<&PolyGateType> for SerializablePolyGateType {
+    fn from(gate_type: &PolyGateType) -> Self {
+        match gate_type {
+            PolyGateType::Input => Self::Input,
+            PolyGateType::Const { digits } => Self::Const { digits: digits.clone() },
+            PolyGateType::Add => Self::Add,
+            PolyGateType::Sub => Self::Sub,
+            PolyGateType::Mul => Self::Mul,
+            PolyGateType::Rotate { shift } => Self::Rotate { shift: *shift },
+            PolyGateType::AddConst { digits } => Self::AddConst { digits: digits.clone() },
+            PolyGateType::MulConst { digits } => Self::MulConst { digits: digits.clone() },
+            PolyGateType::MulScalar { scalar } => Self::MulScalar { scalar: scalar.clone() },
+            PolyGateType::Automorphism { k } => Self::Automorphism { k: *k },
+            PolyGateType::InnerProduct { len } => Self::InnerProduct { len: *len },
+            PolyGateType::Call { circuit_id, num_input, output_id } => Self::Call {
+                circuit_id: *circuit_id,
+                num_input: *num_input,
+                output_id: *output_id,
+            },
         }
     }
 }
@@ -46,6 +84,9 @@ impl SerializablePolyGate {
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SerializablePolyCircuit {
+    /// Format version, 0 for circuits serialized before versioning was introduced.
+    #[serde(default)]
+    version: u32,
     gates: BTreeMap<usize, SerializablePolyGate>,
     sub_circuits: BTreeMap<usize, Self>,
     output_ids: Vec<usize>,
@@ -59,31 +100,17 @@ impl SerializablePolyCircuit {
         output_ids: Vec<usize>,
         num_input: usize,
     ) -> Self {
-        Self { gates, sub_circuits, output_ids, num_input }
+        Self { version: CIRCUIT_VERSION, gates, sub_circuits, output_ids, num_input }
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
     }
 
     pub fn from_circuit(circuit: &PolyCircuit) -> Self {
         let mut gates = BTreeMap::new();
         for (gate_id, gate) in circuit.gates.iter() {
-            let gate_type = match &gate.gate_type {
-                PolyGateType::Input => SerializablePolyGateType::Input,
-                PolyGateType::Const { digits } => {
-                    SerializablePolyGateType::Const { digits: digits.clone() }
-                }
-                PolyGateType::Add => SerializablePolyGateType::Add,
-                PolyGateType::Sub => SerializablePolyGateType::Sub,
-                PolyGateType::Mul => SerializablePolyGateType::Mul,
-                PolyGateType::Rotate { shift } => {
-                    SerializablePolyGateType::Rotate { shift: *shift }
-                }
-                PolyGateType::Call { circuit_id, num_input, output_id } => {
-                    SerializablePolyGateType::Call {
-                        circuit_id: *circuit_id,
-                        num_input: *num_input,
-                        output_id: *output_id,
-                    }
-                }
-            };
+            let gate_type = SerializablePolyGateType::from(&gate.gate_type);
             let serializable_gate =
                 SerializablePolyGate::new(*gate_id, gate_type, gate.input_gates.clone());
             gates.insert(*gate_id, serializable_gate);
@@ -98,19 +125,55 @@ impl SerializablePolyCircuit {
     }
 
     pub fn to_circuit(&self) -> PolyCircuit {
+        self.try_to_circuit().expect("Failed to rebuild PolyCircuit")
+    }
+
+    /// Like [`Self::to_circuit`], returning a malformed circuit as an error instead of panicking:
+    /// every gate must have the arity of its type and read only gates defined before it, calls
+    /// must name a registered sub-circuit with a matching number of inputs, and outputs must
+    /// exist.
+    pub fn try_to_circuit(&self) -> Result<PolyCircuit, MigrationError> {
+        let invalid = |msg: String| Err(MigrationError::Invalid(msg));
+        let is_input = |gate_id| {
+            self.gates.get(&gate_id).is_some_and(|gate: &SerializablePolyGate| {
+                gate.gate_type == SerializablePolyGateType::Input
+            })
+        };
+        if !(0..=self.num_input).all(is_input) {
+            return invalid(format!("the circuit does not start with {} inputs", self.num_input));
+        }
         let mut circuit = PolyCircuit::new();
         circuit.input(self.num_input);
-        for (_, serializable_sub_circuit) in self.sub_circuits.iter() {
-            let sub_circuit = serializable_sub_circuit.to_circuit();
+        for (expected, (circuit_id, serializable_sub_circuit)) in
+            self.sub_circuits.iter().enumerate()
+        {
+            if *circuit_id != expected {
+                return invalid(format!("sub-circuit {} is missing", expected));
+            }
+            let sub_circuit = serializable_sub_circuit.try_to_circuit()?;
             circuit.register_sub_circuit(sub_circuit);
         }
 
         // Process gates in ascending order of their usize keys
         let mut gate_idx = 0;
         while gate_idx < self.gates.len() {
-            let serializable_gate = &self.gates[&gate_idx];
+            let Some(serializable_gate) = self.gates.get(&gate_idx) else {
+                return invalid(format!("gate {} is missing", gate_idx));
+            };
+            let inputs = &serializable_gate.input_gates;
+            if serializable_gate.gate_id != gate_idx ||
+                Some(inputs.len()) != serializable_gate.gate_type.num_input()
+            {
+                return invalid(format!("gate {} is malformed", gate_idx));
+            }
+            if let Some(input) = inputs.iter().find(|id| !circuit.gates.contains_key(id)) {
+                return invalid(format!("gate {} reads undefined gate {}", gate_idx, input));
+            }
             match &serializable_gate.gate_type {
                 SerializablePolyGateType::Input => {
+                    if gate_idx > self.num_input {
+                        return invalid(format!("gate {} is an input after the inputs", gate_idx));
+                    }
                     gate_idx += 1;
                 }
                 SerializablePolyGateType::Const { digits } => {
@@ -118,43 +181,79 @@ impl SerializablePolyCircuit {
                     gate_idx += 1;
                 }
                 SerializablePolyGateType::Add => {
-                    circuit.add_gate(
-                        serializable_gate.input_gates[0],
-                        serializable_gate.input_gates[1],
-                    );
+                    circuit.add_gate(inputs[0], inputs[1]);
                     gate_idx += 1;
                 }
                 SerializablePolyGateType::Sub => {
-                    circuit.sub_gate(
-                        serializable_gate.input_gates[0],
-                        serializable_gate.input_gates[1],
-                    );
+                    circuit.sub_gate(inputs[0], inputs[1]);
                     gate_idx += 1;
                 }
                 SerializablePolyGateType::Mul => {
-                    circuit.mul_gate(
-                        serializable_gate.input_gates[0],
-                        serializable_gate.input_gates[1],
-                    );
+                    circuit.mul_gate(inputs[0], inputs[1]);
                     gate_idx += 1;
                 }
                 SerializablePolyGateType::Rotate { shift } => {
-                    circuit.rotate_gate(serializable_gate.input_gates[0], *shift);
+                    circuit.rotate_gate(inputs[0], *shift);
+                    gate_idx += 1;
+                }
+                SerializablePolyGateType::AddConst { digits } => {
+                    circuit.add_const_gate(inputs[0], digits);
+                    gate_idx += 1;
+                }
+                SerializablePolyGateType::MulConst { digits } => {
+                    circuit.mul_const_gate(inputs[0], digits);
+                    gate_idx += 1;
+                }
+                SerializablePolyGateType::MulScalar { scalar } => {
+                    circuit.mul_scalar_gate(inputs[0], scalar);
+                    gate_idx += 1;
+                }
+                SerializablePolyGateType::Automorphism { k } => {
+                    if k % 2 == 0 {
+                        return invalid(format!("gate {} has an even automorphism index", gate_idx));
+                    }
+                    circuit.automorphism_gate(inputs[0], *k, [inputs[1], inputs[2]]);
+                    gate_idx += 1;
+                }
+                SerializablePolyGateType::InnerProduct { len } => {
+                    if *len == 0 {
+                        return invalid(format!("gate {} has an empty inner product", gate_idx));
+                    }
+                    let (lhs, rhs) = inputs.split_at(*len);
+                    circuit.inner_product_gate(lhs, rhs);
                     gate_idx += 1;
                 }
                 SerializablePolyGateType::Call { circuit_id, .. } => {
-                    let output_size = circuit.sub_circuits[circuit_id].num_output();
-                    circuit.call_sub_circuit(*circuit_id, &serializable_gate.input_gates);
+                    let Some(sub_circuit) = circuit.sub_circuits.get(circuit_id) else {
+                        return invalid(format!("gate {} calls unknown circuit", gate_idx));
+                    };
+                    let output_size = sub_circuit.num_output();
+                    if sub_circuit.num_input() != inputs.len() || output_size == 0 {
+                        return invalid(format!("gate {} calls circuit {}", gate_idx, circuit_id));
+                    }
+                    circuit.call_sub_circuit(*circuit_id, inputs);
                     gate_idx += output_size;
                 }
             };
         }
+        if let Some(output) = self.output_ids.iter().find(|id| !circuit.gates.contains_key(id)) {
+            return invalid(format!("output {} is not a gate", output));
+        }
         circuit.output(self.output_ids.clone());
-        circuit
+        Ok(circuit)
     }
 
+    /// Deserializes a circuit, upgrading it to the current version first if it is older.
     pub fn from_json_str(json_str: &str) -> Self {
-        serde_json::from_str(json_str).expect("Failed to deserialize SerializablePolyCircuit")
+        Self::try_from_json_str(json_str).expect("Failed to deserialize SerializablePolyCircuit")
+    }
+
+    /// Like [`Self::from_json_str`], returning malformed input as an error.
+    pub fn try_from_json_str(json_str: &str) -> Result<Self, MigrationError> {
+        let value = serde_json::from_str(json_str)
+            .map_err(|err| MigrationError::Invalid(err.to_string()))?;
+        let value = circuit_migrator().migrate(value)?;
+        serde_json::from_value(value).map_err(|err| MigrationError::Invalid(err.to_string()))
     }
 
     pub fn to_json_str(&self) -> String {
@@ -196,8 +295,12 @@ mod tests {
         // Use the sub-circuit outputs
         let combined_gate = original_circuit.add_gate(sub_gate, sub_outputs[0]);
 
+        // Apply public constants
+        let mul_const_gate = original_circuit.mul_const_gate(combined_gate, &[2, 1]);
+        let add_const_gate = original_circuit.add_const_gate(mul_const_gate, &[0, 3]);
+
         // Set the output
-        original_circuit.output(vec![combined_gate, mul_gate, sub_outputs[1]]);
+        original_circuit.output(vec![add_const_gate, mul_gate, sub_outputs[1]]);
 
         // Convert to SerializablePolyCircuit
         let serializable_circuit = SerializablePolyCircuit::from_circuit(&original_circuit);
@@ -255,4 +358,31 @@ mod tests {
         // This works because PolyCircuit implements the Eq trait
         assert_eq!(roundtrip_circuit, original_circuit);
     }
+
+    #[test]
+    fn test_try_to_circuit_rejects_malformed() {
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        let add_gate = circuit.add_gate(inputs[0], inputs[1]);
+        circuit.output(vec![add_gate]);
+        let valid = SerializablePolyCircuit::from_circuit(&circuit);
+        assert_eq!(valid.try_to_circuit().unwrap(), circuit);
+
+        // A gate reading a later gate, a wrong arity and a dangling output are errors
+        let mut forward = valid.clone();
+        forward.gates.get_mut(&add_gate).unwrap().input_gates[1] = add_gate;
+        let mut arity = valid.clone();
+        arity.gates.get_mut(&add_gate).unwrap().input_gates.pop();
+        let mut output = valid.clone();
+        output.output_ids = vec![add_gate + 1];
+        let mut missing_input = valid.clone();
+        missing_input.num_input = 5;
+        // An inner product whose number of inputs overflows
+        let mut overflow = valid.clone();
+        overflow.gates.get_mut(&add_gate).unwrap().gate_type =
+            SerializablePolyGateType::InnerProduct { len: usize::MAX / 2 + 1 };
+        for malformed in [forward, arity, output, missing_input, overflow] {
+            assert!(matches!(malformed.try_to_circuit(), Err(MigrationError::Invalid(_))));
+        }
+    }
 }
@@ -0,0 +1,138 @@
+//! Per-gate checks of the encoding invariant `vector = (s, -1) * (A - x * G) + e` in debug
+//! builds, so that math errors during development fail at the first wrong gate instead of
+//! surfacing as a wrong output.
+use super::PolyCircuit;
+use crate::{
+    bgg::BggEncoding,
+    poly::{plaintext::modulus_biguint, Poly, PolyElem, PolyMatrix},
+};
+use num_bigint::BigUint;
+
+/// Knows the secrets of the encodings and the largest error coefficient the checks accept.
+#[derive(Debug, Clone)]
+pub struct SoundnessChecker<M: PolyMatrix> {
+    secret_vec: M,
+    error_bound: BigUint,
+}
+
+impl<M: PolyMatrix> SoundnessChecker<M> {
+    /// `secrets` are the `d` secret polynomials given to the encoding sampler. Encodings sampled
+    /// with `gauss_sigma = 0` satisfy the invariant exactly, i.e. with an `error_bound` of 0.
+    pub fn new(params: &<M::P as Poly>::Params, secrets: &[M::P], error_bound: BigUint) -> Self {
+        let mut secrets = secrets.to_vec();
+        secrets.push(M::P::const_minus_one(params));
+        Self { secret_vec: M::from_poly_vec_row(params, secrets), error_bound }
+    }
+
+    /// Panics with the column and coefficient index of the first error coefficient of `encoding`
+    /// larger than the bound. Encodings with a hidden plaintext cannot be checked and pass.
+    pub fn check(
+        &self,
+        params: &<M::P as Poly>::Params,
+        gate_id: usize,
+        encoding: &BggEncoding<M>,
+    ) {
+        let Some(plaintext) = &encoding.plaintext else {
+            return;
+        };
+        let matrix = &encoding.pubkey.matrix;
+        let gadget = M::gadget_matrix(params, matrix.row_size());
+        let expected = self.secret_vec.clone() * &(matrix.clone() - gadget * plaintext);
+        let error = encoding.vector.clone() - expected;
+        let q = modulus_biguint::<M::P>(params);
+        for column in 0..error.col_size() {
+            for (coeff, value) in error.entry(0, column).coeffs().iter().enumerate() {
+                let value = value.to_biguint();
+                let centered = if value > &(&q >> 1) { &q - value } else { value.clone() };
+                if centered > self.error_bound {
+                    panic!(
+                        "gate {} violates the encoding invariant at column {}, coefficient {}: \
+                         error {} exceeds {}",
+                        gate_id, column, coeff, centered, self.error_bound
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl PolyCircuit {
+    /// Evaluates like [`Self::eval`] and, in debug builds, checks every wire with `checker` in
+    /// topological order, so the first gate breaking the invariant is the one reported. Release
+    /// builds skip the checks.
+    pub fn eval_checked<M: PolyMatrix>(
+        &self,
+        params: &<M::P as Poly>::Params,
+        one: &BggEncoding<M>,
+        inputs: &[BggEncoding<M>],
+        checker: &SoundnessChecker<M>,
+    ) -> Vec<BggEncoding<M>> {
+        if !cfg!(debug_assertions) {
+            return self.eval(params, one, inputs);
+        }
+        let wires = self.eval_wires(params, one, inputs);
+        for gate_id in self.topological_order() {
+            checker.check(params, gate_id, &wires[&gate_id]);
+        }
+        self.output_ids.iter().map(|id| wires[id].clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::sampler::{BGGEncodingSampler, BGGPublicKeySampler},
+        poly::dcrt::{DCRTPolyHashSampler, DCRTPolyMatrix, DCRTPolyParams, DCRTPolyUniformSampler},
+        utils::{create_bit_random_poly, create_random_poly},
+    };
+    use keccak_asm::Keccak256;
+
+    type Setup =
+        (DCRTPolyParams, SoundnessChecker<DCRTPolyMatrix>, Vec<BggEncoding<DCRTPolyMatrix>>);
+
+    fn setup() -> Setup {
+        let params = DCRTPolyParams::default();
+        let key: [u8; 32] = rand::random();
+        let d = 2;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let pubkeys = bgg_sampler.sample(&params, b"soundness", &[true, true]);
+        let secrets = (0..d).map(|_| create_bit_random_poly(&params)).collect::<Vec<_>>();
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let plaintexts = vec![create_random_poly(&params), create_random_poly(&params)];
+        let encodings = bgg_sampler.sample(&params, &pubkeys, &plaintexts);
+        let checker = SoundnessChecker::new(&params, &secrets, BigUint::ZERO);
+        (params, checker, encodings)
+    }
+
+    fn circuit() -> PolyCircuit {
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        let mul_gate = circuit.mul_gate(inputs[0], inputs[1]);
+        let add_gate = circuit.add_gate(mul_gate, inputs[0]);
+        circuit.output(vec![add_gate]);
+        circuit
+    }
+
+    #[test]
+    fn test_eval_checked() {
+        let (params, checker, encodings) = setup();
+        let circuit = circuit();
+        let outputs = circuit.eval_checked(&params, &encodings[0], &encodings[1..], &checker);
+        assert_eq!(outputs, circuit.eval(&params, &encodings[0], &encodings[1..]));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "gate 1 violates the encoding invariant")]
+    fn test_eval_checked_reports_gate() {
+        let (params, checker, mut encodings) = setup();
+
+        // A wrong plaintext on the first input is caught at its own wire
+        let plaintext = encodings[1].plaintext.clone().unwrap();
+        encodings[1].plaintext =
+            Some(plaintext + <DCRTPolyMatrix as PolyMatrix>::P::const_one(&params));
+        circuit().eval_checked(&params, &encodings[0], &encodings[1..], &checker);
+    }
+}
@@ -0,0 +1,155 @@
+use super::{Evaluable, PolyCircuit};
+use crate::{counters::OpCounts, utils::ThreadPoolConfig};
+use std::{
+    cell::Cell,
+    collections::BTreeMap,
+    convert::Infallible,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+/// The operations spent on the gates of one type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GateStats {
+    pub count: usize,
+    pub poly_muls: u64,
+    pub ntts: u64,
+    /// Zero unless [`crate::counters::CountingAllocator`] is installed.
+    pub allocated_bytes: u64,
+    pub wall_time: Duration,
+}
+
+impl GateStats {
+    fn add(&mut self, other: &Self) {
+        self.count += other.count;
+        self.poly_muls += other.poly_muls;
+        self.ntts += other.ntts;
+        self.allocated_bytes += other.allocated_bytes;
+        self.wall_time += other.wall_time;
+    }
+}
+
+/// Statistics collected by [`PolyCircuit::eval_with_stats`], keyed by the name of the gate type.
+/// A call gate includes the gates of its sub-circuit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EvalStats {
+    pub per_gate_type: BTreeMap<&'static str, GateStats>,
+}
+
+impl EvalStats {
+    pub fn total(&self) -> GateStats {
+        let mut total = GateStats::default();
+        for stats in self.per_gate_type.values() {
+            total.add(stats);
+        }
+        total
+    }
+
+    /// Formats the wall time per gate type in microseconds as folded stacks, one
+    /// `eval;<gate type> <microseconds>` line per type, e.g. for `flamegraph.pl` or `inferno`.
+    pub fn folded_stacks(&self) -> String {
+        self.per_gate_type
+            .iter()
+            .map(|(name, stats)| format!("eval;{} {}\n", name, stats.wall_time.as_micros()))
+            .collect()
+    }
+
+    pub fn write_folded<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(self.folded_stacks().as_bytes())
+    }
+}
+
+impl PolyCircuit {
+    /// Evaluates the circuit one gate at a time like [`Self::eval_streaming`], measuring the
+    /// operations of every gate. The counters are process-wide, so other evaluations running
+    /// concurrently are included, and only the wall time is measured without the `counters`
+    /// feature.
+    pub fn eval_with_stats<E: Evaluable>(
+        &self,
+        params: &E::Params,
+        one: &E,
+        inputs: &[E],
+    ) -> (Vec<E>, EvalStats) {
+        let mut stats = EvalStats::default();
+        let start = Cell::new(None);
+        let outputs = self.eval_streaming_with::<E, Infallible>(
+            params,
+            one,
+            |idx| Ok(inputs[idx].clone()),
+            |_, _| {
+                start.set(Some((Instant::now(), OpCounts::now())));
+                Ok(())
+            },
+            |gate_type| {
+                let (started, counts) = start.take().expect("gate was not started");
+                let ops = OpCounts::now().since(&counts);
+                let entry = stats.per_gate_type.entry(gate_type.name()).or_default();
+                entry.add(&GateStats {
+                    count: 1,
+                    poly_muls: ops.poly_muls,
+                    ntts: ops.ntts,
+                    allocated_bytes: ops.allocated_bytes,
+                    wall_time: started.elapsed(),
+                });
+            },
+            &ThreadPoolConfig::global(),
+        );
+        let outputs = match outputs {
+            Ok(outputs) => outputs,
+            Err(never) => match never {},
+        };
+        (outputs, stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        poly::{
+            dcrt::{DCRTPoly, DCRTPolyParams},
+            Poly,
+        },
+        utils::create_random_poly,
+    };
+
+    #[test]
+    fn test_eval_with_stats() {
+        let params = DCRTPolyParams::default();
+        let one = DCRTPoly::const_one(&params);
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        let add_gate = circuit.add_gate(inputs[0], inputs[1]);
+        let mul_gate = circuit.mul_gate(add_gate, inputs[1]);
+        let square_gate = circuit.mul_gate(mul_gate, mul_gate);
+        circuit.output(vec![square_gate]);
+
+        // The outputs match a plain evaluation
+        let inputs = vec![create_random_poly(&params), create_random_poly(&params)];
+        let (outputs, stats) = circuit.eval_with_stats(&params, &one, &inputs);
+        assert_eq!(outputs, circuit.eval(&params, &one, &inputs));
+
+        // Every gate is counted under its type and each mul gate multiplies at least once
+        assert_eq!(stats.per_gate_type.len(), 2);
+        assert_eq!(stats.per_gate_type["add"].count, 1);
+        assert_eq!(stats.per_gate_type["mul"].count, 2);
+        if cfg!(feature = "counters") {
+            assert!(stats.per_gate_type["mul"].poly_muls >= 2);
+        }
+        assert_eq!(stats.total().count, 3);
+    }
+
+    #[test]
+    fn test_eval_stats_folded_stacks() {
+        let mut stats = EvalStats::default();
+        let add = GateStats { count: 2, wall_time: Duration::from_micros(9), ..Default::default() };
+        let mul = GateStats { count: 1, wall_time: Duration::from_millis(2), ..Default::default() };
+        stats.per_gate_type.insert("add", add);
+        stats.per_gate_type.insert("mul", mul);
+        assert_eq!(stats.folded_stacks(), "eval;add 9\neval;mul 2000\n");
+        let mut out = Vec::new();
+        stats.write_folded(&mut out).unwrap();
+        assert_eq!(out, stats.folded_stacks().into_bytes());
+        assert_eq!(stats.total().wall_time, Duration::from_micros(2009));
+    }
+}
@@ -1,9 +1,44 @@
-use super::{circuit::Evaluable, BggPublicKey};
-use crate::poly::{Poly, PolyMatrix};
+use super::{
+    associated_data::AssociatedData,
+    circuit::{Evaluable, PolyCircuit},
+    digits_to_int::DigitsToInt,
+    gates::{AttrSideEval, KeySideEval, StandardGates},
+    public_key::{project_slots, PreparedOperand},
+    sampler::BGGPublicKeySampler,
+    slots::{check_inner_product_ranges, AttributeSlots},
+    BggPublicKey,
+};
+use crate::poly::{
+    plaintext::modulus_biguint, sampler::PolyHashSampler, sampling::uniform_mod_q, Poly, PolyElem,
+    PolyMatrix, PolyParams,
+};
+use num_bigint::BigUint;
+use rand::RngCore;
 use rayon::prelude::*;
-use std::ops::{Add, Mul, Sub};
+use std::ops::{Add, Mul, Range, Sub};
 
-#[derive(Debug, Clone)]
+/// Error returned by [`BggEncoding::flood`] when the modulus is too small for the noise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FloodError {
+    pub smudging_bits: usize,
+    pub modulus_bits: usize,
+}
+
+impl std::fmt::Display for FloodError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-bit smudging noise needs a modulus of at least {} bits, got {}",
+            self.smudging_bits,
+            self.smudging_bits + 3,
+            self.modulus_bits
+        )
+    }
+}
+
+impl std::error::Error for FloodError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BggEncoding<M: PolyMatrix> {
     pub vector: M,
     pub pubkey: BggPublicKey<M>,
@@ -23,6 +58,107 @@ impl<M: PolyMatrix> BggEncoding<M> {
         self.vector.concat_columns(&others.par_iter().map(|x| &x.vector).collect::<Vec<_>>()[..])
     }
 
+    /// Restricts the encodings of all attributes to the attributes at `indices`, remapping them
+    /// like [`BggPublicKey::project`].
+    pub fn project(encodings: &[Self], indices: &[usize]) -> Vec<Self> {
+        project_slots(encodings, indices)
+    }
+
+    /// Adds uniform smudging noise in `[-2^smudging_bits, 2^smudging_bits)` to every coefficient
+    /// of the vector, so that a published evaluated encoding can be simulated without the
+    /// error it accumulated, as long as `smudging_bits` exceeds that error's bits by the
+    /// statistical security parameter. Fails unless `q > 2^(smudging_bits + 2)`, which keeps
+    /// the flooded error below `q/4`.
+    pub fn flood<R: RngCore + ?Sized>(
+        self,
+        params: &<M::P as Poly>::Params,
+        smudging_bits: usize,
+        rng: &mut R,
+    ) -> Result<Self, FloodError> {
+        let modulus_bits = params.modulus_bits();
+        if smudging_bits + 3 > modulus_bits {
+            return Err(FloodError { smudging_bits, modulus_bits });
+        }
+        let q = modulus_biguint::<M::P>(params);
+        let bound = BigUint::from(1u8) << smudging_bits;
+        let n = params.ring_dimension() as usize;
+        let noise = (0..self.vector.row_size())
+            .map(|_| {
+                (0..self.vector.col_size())
+                    .map(|_| {
+                        let coeffs = uniform_mod_q(rng, &(&bound << 1), n)
+                            .into_iter()
+                            .map(|value| {
+                                // Shift [0, 2 * bound) to [-bound, bound) modulo q
+                                let value = (value + &q - &bound) % &q;
+                                <M::P as Poly>::Elem::from_bytes(
+                                    &params.modulus(),
+                                    &value.to_bytes_le(),
+                                )
+                            })
+                            .collect::<Vec<_>>();
+                        M::P::from_coeffs(params, &coeffs)
+                    })
+                    .collect()
+            })
+            .collect();
+        let vector = self.vector + M::from_poly_vec(params, noise);
+        Ok(Self { vector, pubkey: self.pubkey, plaintext: self.plaintext })
+    }
+
+    /// Keeps only the combination `vector * G^-1(u)` of the columns, `u` being the last unit
+    /// vector of dimension `d + 1`, which is all the final zero test needs. The result encodes
+    /// the same plaintext under the `(d + 1) x 1` public key [`BggPublicKey::compress`], with
+    /// an error at most `m` times the base larger, and is `m` times smaller to send back to the
+    /// decryptor.
+    pub fn compress(&self, params: &<M::P as Poly>::Params) -> Self {
+        self.power_of_base(params, 0)
+    }
+
+    /// Computes the encoding of `Σ lhs[i] * rhs[i]` in one pass, multiplying the concatenated
+    /// left vectors by the stacked decompositions of the right public keys only once.
+    /// The plaintexts of all left-hand inputs must be known.
+    pub fn inner_product(lhs: &[Self], rhs: &[Self]) -> Self {
+        assert_eq!(lhs.len(), rhs.len(), "inner product inputs must have the same length");
+        assert!(!lhs.is_empty(), "inner product of empty inputs");
+        let decomposed =
+            rhs.par_iter().map(|enc| enc.pubkey.matrix.decompose()).collect::<Vec<_>>();
+        let decomposed = decomposed[0].concat_rows(&decomposed[1..].iter().collect::<Vec<_>>());
+        let lhs_vector = lhs[0].concat_vector(&lhs[1..]);
+        let lhs_matrix = lhs[0]
+            .pubkey
+            .matrix
+            .concat_columns(&lhs[1..].iter().map(|enc| &enc.pubkey.matrix).collect::<Vec<_>>());
+        let second_term = lhs
+            .par_iter()
+            .zip(rhs.par_iter())
+            .map(|(l, r)| {
+                let plaintext = l
+                    .plaintext
+                    .as_ref()
+                    .expect("Unknown plaintext for the left-hand input of multiplication");
+                r.vector.clone() * plaintext
+            })
+            .reduce_with(|a, b| a + b)
+            .expect("inner product of empty inputs");
+        let vector = lhs_vector * &decomposed + second_term;
+        let plaintext = lhs
+            .iter()
+            .zip(rhs.iter())
+            .map(|(l, r)| match (l.plaintext.as_ref(), r.plaintext.as_ref()) {
+                (Some(a), Some(b)) => Some(a.clone() * b),
+                _ => None,
+            })
+            .reduce(|acc, p| match (acc, p) {
+                (Some(a), Some(b)) => Some(a + b),
+                _ => None,
+            })
+            .flatten();
+        let reveal_plaintext = lhs.iter().chain(rhs.iter()).all(|enc| enc.pubkey.reveal_plaintext);
+        let pubkey = BggPublicKey { matrix: lhs_matrix * decomposed, reveal_plaintext };
+        Self { vector, pubkey, plaintext }
+    }
+
     /// Writes the encoding with id to files under the given directory.
     pub async fn write_to_files<P: AsRef<std::path::Path> + Send + Sync>(
         &self,
@@ -76,6 +212,144 @@ impl<M: PolyMatrix> BggEncoding<M> {
     }
 }
 
+/// The encodings of a ciphertext in [`AttributeSlots`], together with the associated data and the
+/// epoch they were sampled under.
+#[derive(Debug, Clone)]
+pub struct EncodedAttributes<M: PolyMatrix> {
+    slots: AttributeSlots<BggEncoding<M>>,
+    associated_data: Option<AssociatedData>,
+    epoch: u64,
+}
+
+impl<M: PolyMatrix> EncodedAttributes<M> {
+    /// Wraps encodings whose first slot encodes the constant one, as returned by
+    /// [`crate::bgg::sampler::BGGEncodingSampler::sample`].
+    pub fn from_slots(slots: Vec<BggEncoding<M>>) -> Self {
+        Self { slots: AttributeSlots::from_slots(slots), associated_data: None, epoch: 0 }
+    }
+
+    /// Like [`Self::from_slots`], binding `associated_data` into the keys of [`Self::mac`] and of
+    /// the attribute envelope.
+    pub fn with_associated_data(
+        slots: Vec<BggEncoding<M>>,
+        associated_data: AssociatedData,
+    ) -> Self {
+        Self { associated_data: Some(associated_data), ..Self::from_slots(slots) }
+    }
+
+    pub fn associated_data(&self) -> Option<&AssociatedData> {
+        self.associated_data.as_ref()
+    }
+
+    /// The encodings in their slots, as evaluated under the matching [`AttributeKeys`].
+    ///
+    /// [`AttributeKeys`]: crate::bgg::slots::AttributeKeys
+    pub fn as_slots(&self) -> &AttributeSlots<BggEncoding<M>> {
+        &self.slots
+    }
+
+    /// The encoding of the constant one, for gates such as constants that need it.
+    pub fn constant_one_row(&self) -> &BggEncoding<M> {
+        self.slots.constant_one_row()
+    }
+
+    pub fn attributes(&self) -> &[BggEncoding<M>] {
+        self.slots.attributes()
+    }
+
+    /// The encoding of the constant one followed by the encodings of the attributes.
+    pub fn slots(&self) -> &[BggEncoding<M>] {
+        self.slots.slots()
+    }
+
+    /// The encoding of the bias term with coefficients `digits`, under the key
+    /// [`AttributeKeys::m_eval_bias`] returns for the same digits.
+    ///
+    /// [`AttributeKeys::m_eval_bias`]: crate::bgg::slots::AttributeKeys::m_eval_bias
+    pub fn eval_bias(&self, params: &<M::P as Poly>::Params, digits: &[u32]) -> BggEncoding<M> {
+        StandardGates.const_encoding(params, self.constant_one_row(), digits)
+    }
+
+    /// The encoding of `Σ x_i * y_i` for the attributes `x` at `range_a` and `y` at `range_b`,
+    /// under the key [`AttributeKeys::m_eval_inner_product`] returns for the same ranges.
+    ///
+    /// [`AttributeKeys::m_eval_inner_product`]:
+    ///     crate::bgg::slots::AttributeKeys::m_eval_inner_product
+    pub fn eval_inner_product(
+        &self,
+        range_a: Range<usize>,
+        range_b: Range<usize>,
+    ) -> BggEncoding<M> {
+        check_inner_product_ranges(&range_a, &range_b);
+        let attributes = self.attributes();
+        BggEncoding::inner_product(&attributes[range_a], &attributes[range_b])
+    }
+
+    /// The number of attributes, not counting the constant one.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn into_slots(self) -> Vec<BggEncoding<M>> {
+        self.slots.into_slots()
+    }
+
+    /// Evaluates `circuit` with one input per attribute.
+    pub fn eval(
+        &self,
+        params: &<M::P as Poly>::Params,
+        circuit: &PolyCircuit,
+    ) -> Vec<BggEncoding<M>> {
+        self.slots.eval(params, circuit)
+    }
+}
+
+/// BGG+ encodings without their hash-derived public keys, which the receiver expands again from
+/// the tag they were sampled under, so that only the tag, the vectors and the revealed plaintexts
+/// are transmitted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressedEncodings<M: PolyMatrix> {
+    pub tag: Vec<u8>,
+    pub reveal_plaintexts: Vec<bool>,
+    pub vectors: Vec<M>,
+    pub plaintexts: Vec<Option<M::P>>,
+}
+
+impl<M: PolyMatrix> CompressedEncodings<M> {
+    /// Drops the public keys of `encodings`, which must be the keys
+    /// [`BGGPublicKeySampler::sample`] returns for `tag`, the first slot included.
+    pub fn compress(tag: &[u8], encodings: &[BggEncoding<M>]) -> Self {
+        assert!(!encodings.is_empty(), "the constant-one slot is missing");
+        let reveal_plaintexts =
+            encodings[1..].iter().map(|encoding| encoding.pubkey.reveal_plaintext).collect();
+        let vectors = encodings.iter().map(|encoding| encoding.vector.clone()).collect();
+        let plaintexts = encodings.iter().map(|encoding| encoding.plaintext.clone()).collect();
+        Self { tag: tag.to_vec(), reveal_plaintexts, vectors, plaintexts }
+    }
+
+    /// Re-derives the public keys from the tag with `sampler`, which must use the hash key and
+    /// epoch of the sender, and reattaches them to the vectors.
+    pub fn expand<K: AsRef<[u8]>, S: PolyHashSampler<K, M = M>>(
+        &self,
+        params: &<M::P as Poly>::Params,
+        sampler: &BGGPublicKeySampler<K, S>,
+    ) -> Vec<BggEncoding<M>> {
+        let pubkeys = sampler.sample(params, &self.tag, &self.reveal_plaintexts);
+        assert_eq!(pubkeys.len(), self.vectors.len(), "one vector per public key is required");
+        pubkeys
+            .into_iter()
+            .zip(self.vectors.iter().zip(self.plaintexts.iter()))
+            .map(|(pubkey, (vector, plaintext))| {
+                BggEncoding::new(vector.clone(), pubkey, plaintext.clone())
+            })
+            .collect()
+    }
+}
+
 impl<M: PolyMatrix> Add for BggEncoding<M> {
     type Output = Self;
     fn add(self, other: Self) -> Self {
@@ -86,13 +360,7 @@ impl<M: PolyMatrix> Add for BggEncoding<M> {
 impl<M: PolyMatrix> Add<&Self> for BggEncoding<M> {
     type Output = Self;
     fn add(self, other: &Self) -> Self {
-        let vector = self.vector + &other.vector;
-        let pubkey = self.pubkey + &other.pubkey;
-        let plaintext = match (self.plaintext, other.plaintext.as_ref()) {
-            (Some(a), Some(b)) => Some(a + b),
-            _ => None,
-        };
-        Self { vector, pubkey, plaintext }
+        StandardGates.add_encodings(self, other)
     }
 }
 
@@ -106,13 +374,7 @@ impl<M: PolyMatrix> Sub for BggEncoding<M> {
 impl<M: PolyMatrix> Sub<&Self> for BggEncoding<M> {
     type Output = Self;
     fn sub(self, other: &Self) -> Self {
-        let vector = self.vector - &other.vector;
-        let pubkey = self.pubkey - &other.pubkey;
-        let plaintext = match (self.plaintext, other.plaintext.as_ref()) {
-            (Some(a), Some(b)) => Some(a - b),
-            _ => None,
-        };
-        Self { vector, pubkey, plaintext }
+        StandardGates.sub_encodings(self, other)
     }
 }
 
@@ -126,67 +388,83 @@ impl<M: PolyMatrix> Mul for BggEncoding<M> {
 impl<M: PolyMatrix> Mul<&Self> for BggEncoding<M> {
     type Output = Self;
     fn mul(self, other: &Self) -> Self {
-        if self.plaintext.is_none() {
-            panic!("Unknown plaintext for the left-hand input of multiplication");
-        }
-        let decomposed_b = other.pubkey.matrix.decompose();
-        let first_term = self.vector * decomposed_b.clone();
-        let second_term = other.vector.clone() * self.plaintext.as_ref().unwrap();
-        let new_vector = first_term + second_term;
-        let new_plaintext = match (self.plaintext, other.plaintext.as_ref()) {
-            (Some(a), Some(b)) => Some(a * b),
-            _ => None,
-        };
-
-        let new_pubkey = BggPublicKey {
-            matrix: self.pubkey.matrix * decomposed_b,
-            reveal_plaintext: self.pubkey.reveal_plaintext & other.pubkey.reveal_plaintext,
-        };
-        Self { vector: new_vector, pubkey: new_pubkey, plaintext: new_plaintext }
+        let prepared = PreparedOperand::new(&other.pubkey);
+        self.mul_prepared(other, &prepared)
     }
 }
 
 impl<M: PolyMatrix> Evaluable for BggEncoding<M> {
     type Params = <M::P as Poly>::Params;
+    type Prepared = PreparedOperand<M>;
     fn rotate(&self, params: &Self::Params, shift: usize) -> Self {
-        let rotate_poly = <M::P>::const_rotate_poly(params, shift);
-        let vector = self.vector.clone() * &rotate_poly;
-        let pubkey = self.pubkey.rotate(params, shift);
-        let plaintext = self.plaintext.clone().map(|plaintext| plaintext * rotate_poly);
-        Self { vector, pubkey, plaintext }
+        StandardGates.rotate_encoding(params, self, shift)
     }
 
     fn from_digits(params: &Self::Params, one: &Self, digits: &[u32]) -> Self {
-        let const_poly =
-            <M::P as Evaluable>::from_digits(params, &<M::P>::const_one(params), digits);
-        let vector = one.vector.clone() * &const_poly;
-        let pubkey = BggPublicKey::from_digits(params, &one.pubkey, digits);
-        let plaintext = one.plaintext.clone().map(|plaintext| plaintext * const_poly);
-        Self { vector, pubkey, plaintext }
+        StandardGates.const_encoding(params, one, digits)
+    }
+
+    /// Multiplies the vector by the constant directly, so the error grows by the norm of the
+    /// constant rather than by a gadget decomposition.
+    fn mul_const(&self, params: &Self::Params, digits: &[u32]) -> Self {
+        StandardGates.mul_const_encoding(params, self, digits)
+    }
+
+    fn mul_scalar(&self, params: &Self::Params, scalar: &BigUint) -> Self {
+        StandardGates.mul_scalar_encoding(params, self, scalar)
+    }
+
+    fn apply_automorphism(
+        &self,
+        params: &Self::Params,
+        one: &Self,
+        hints: [&Self; 2],
+        k: usize,
+    ) -> Self {
+        StandardGates.automorphism_encoding(params, one, self, hints, k)
+    }
+
+    /// Multiplies the concatenated left-hand inputs by the stacked decompositions of the
+    /// right-hand ones once, see [`Self::inner_product`].
+    fn eval_inner_product(_: &Self::Params, lhs: &[Self], rhs: &[Self]) -> Self {
+        Self::inner_product(lhs, rhs)
+    }
+
+    fn prepare(&self, _: &Self::Params) -> Self::Prepared {
+        StandardGates.prepare_key(&self.pubkey)
+    }
+
+    fn mul_prepared(self, other: &Self, prepared: &Self::Prepared) -> Self {
+        StandardGates.mul_encodings(self, other, prepared)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::{CompressedEncodings, EncodedAttributes, FloodError, PreparedOperand};
     use crate::{
+        assert_matrix_close,
         bgg::{
-            circuit::PolyCircuit,
+            circuit::{Evaluable, PolyCircuit},
             sampler::{BGGEncodingSampler, BGGPublicKeySampler},
-            BggEncoding,
+            selftest::dual_eval,
+            slots::AttributeKeys,
+            BggEncoding, BggPublicKey,
         },
         poly::{
             dcrt::{
                 matrix::base::BaseMatrix,
                 params::DCRTPolyParams,
                 sampler::{hash::DCRTPolyHashSampler, uniform::DCRTPolyUniformSampler},
-                DCRTPoly,
+                DCRTPoly, DCRTPolyMatrix,
             },
             sampler::PolyUniformSampler,
-            PolyParams,
+            Poly, PolyMatrix, PolyParams,
         },
         utils::{create_bit_random_poly, create_random_poly},
     };
     use keccak_asm::Keccak256;
+    use num_bigint::BigUint;
     use rand::Rng;
     use serial_test::serial;
     use std::{fs, path::Path};
@@ -342,6 +620,326 @@ mod tests {
         assert_eq!(result[0].plaintext.as_ref().unwrap(), expected.plaintext.as_ref().unwrap());
     }
 
+    #[test]
+    fn test_encoding_mul_fan_out() {
+        // Create parameters for testing
+        let params = DCRTPolyParams::default();
+
+        // Create samplers
+        let key: [u8; 32] = rand::random();
+        let d = 3;
+        let bgg_pubkey_sampler =
+            BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+
+        // Generate random tag for sampling
+        let tag: u64 = rand::random();
+        let tag_bytes = tag.to_le_bytes();
+
+        // Create random public keys
+        let reveal_plaintexts = [true; 3];
+        let pubkeys = bgg_pubkey_sampler.sample(&params, &tag_bytes, &reveal_plaintexts);
+
+        // Create secret and plaintexts
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let plaintexts = (0..3).map(|_| create_random_poly(&params)).collect::<Vec<_>>();
+
+        // Create encoding sampler and encodings
+        let bgg_encoding_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let encodings = bgg_encoding_sampler.sample(&params, &pubkeys, &plaintexts);
+
+        // Multiplying by a prepared operand is the same as multiplying directly
+        let prepared = encodings[3].prepare(&params);
+        let direct = encodings[1].clone() * &encodings[3];
+        let reused = encodings[1].clone().mul_prepared(&encodings[3], &prepared);
+        assert_eq!(reused.vector, direct.vector);
+        assert_eq!(reused.pubkey, direct.pubkey);
+        assert_eq!(reused.plaintext, direct.plaintext);
+
+        // So is decomposing it in blocks of columns that do not divide its width
+        let blocked = PreparedOperand::blocked(&encodings[3].pubkey, 7);
+        assert!(blocked.decomposed().is_none());
+        let reused = encodings[1].clone().mul_prepared(&encodings[3], &blocked);
+        assert_eq!(reused.vector, direct.vector);
+        assert_eq!(reused.pubkey, direct.pubkey);
+
+        // A circuit multiplying two inputs by the same third input
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(3);
+        let mul_gate1 = circuit.mul_gate(inputs[0], inputs[2]);
+        let mul_gate2 = circuit.mul_gate(inputs[1], inputs[2]);
+        circuit.output(vec![mul_gate1, mul_gate2]);
+
+        // Evaluate the circuit
+        let result = circuit.eval(&params, &encodings[0], &encodings[1..]);
+
+        // Expected result
+        let expected1 = encodings[1].clone() * &encodings[3];
+        let expected2 = encodings[2].clone() * &encodings[3];
+
+        // Verify the result
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].vector, expected1.vector);
+        assert_eq!(result[0].pubkey, expected1.pubkey);
+        assert_eq!(result[1].vector, expected2.vector);
+        assert_eq!(result[1].pubkey, expected2.pubkey);
+        assert_eq!(result[1].plaintext, expected2.plaintext);
+    }
+
+    #[test]
+    fn test_encoding_flood() {
+        // Create parameters for testing
+        let params = DCRTPolyParams::default();
+
+        // Create samplers
+        let key: [u8; 32] = rand::random();
+        let d = 3;
+        let bgg_pubkey_sampler =
+            BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+
+        // Create random public keys and encodings without errors
+        let pubkeys = bgg_pubkey_sampler.sample(&params, b"flood", &[true]);
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let plaintexts = vec![create_random_poly(&params)];
+        let bgg_encoding_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let encodings = bgg_encoding_sampler.sample(&params, &pubkeys, &plaintexts);
+
+        // The added noise is within [-2^8, 2^8) for every coefficient
+        let mut rng = rand::rng();
+        let smudging_bits = 8;
+        let flooded = encodings[1].clone().flood(&params, smudging_bits, &mut rng).unwrap();
+        assert_eq!(flooded.pubkey, encodings[1].pubkey);
+        assert_eq!(flooded.plaintext, encodings[1].plaintext);
+        let bound = BigUint::from(1u8) << smudging_bits;
+        assert_matrix_close!(flooded.vector, encodings[1].vector, bound);
+
+        // The noise must stay below q/4
+        let modulus_bits = params.modulus_bits();
+        assert_eq!(
+            encodings[1].clone().flood(&params, modulus_bits - 2, &mut rng).unwrap_err(),
+            FloodError { smudging_bits: modulus_bits - 2, modulus_bits }
+        );
+    }
+
+    #[test]
+    fn test_encoding_automorphism() {
+        // Create parameters for testing
+        let params = DCRTPolyParams::default();
+
+        // Create samplers
+        let key: [u8; 32] = rand::random();
+        let d = 3;
+        let bgg_pubkey_sampler =
+            BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+
+        // Create random public keys and encodings without errors
+        let pubkeys = bgg_pubkey_sampler.sample(&params, b"automorphism", &[true]);
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let plaintexts = vec![create_random_poly(&params)];
+        let bgg_encoding_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let encodings = bgg_encoding_sampler.sample(&params, &pubkeys, &plaintexts);
+
+        // Applying X -> X^k to s * (A - x * G) gives an encoding of σ(x) under σ(A) and σ(s)
+        let k = 3;
+        let vector = encodings[1].vector.automorphism(&params, k);
+        let matrix = encodings[1].pubkey.matrix.automorphism(&params, k);
+        let plaintext = plaintexts[0].automorphism(&params, k);
+        let secret_vec = bgg_encoding_sampler.secret_vec.automorphism(&params, k);
+        let gadget = DCRTPolyMatrix::gadget_matrix(&params, d + 1);
+        assert_eq!(gadget.automorphism(&params, k), gadget);
+        assert_eq!(vector, secret_vec * (matrix - (gadget * plaintext)));
+    }
+
+    #[test]
+    fn test_encoding_const_gates() {
+        // Create parameters for testing
+        let params = DCRTPolyParams::default();
+
+        // Create samplers
+        let key: [u8; 32] = rand::random();
+        let d = 3;
+        let bgg_pubkey_sampler =
+            BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+
+        // Create random public keys and encodings without errors
+        let pubkeys = bgg_pubkey_sampler.sample(&params, b"const_gates", &[true]);
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let plaintexts = vec![create_random_poly(&params)];
+        let bgg_encoding_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let encodings = bgg_encoding_sampler.sample(&params, &pubkeys, &plaintexts);
+
+        // The affine function c1 * x + c2 for public constants c1 and c2
+        let c1 = [3, 0, 1, 0];
+        let c2 = [1, 2, 0, 1];
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(1);
+        let mul_const = circuit.mul_const_gate(inputs[0], &c1);
+        let add_const = circuit.add_const_gate(mul_const, &c2);
+        circuit.output(vec![add_const]);
+
+        // The result encodes c1 * x + c2
+        let result = circuit.eval(&params, &encodings[0], &encodings[1..]).pop().unwrap();
+        let one = DCRTPoly::const_one(&params);
+        let expected_plaintext = plaintexts[0].clone() * DCRTPoly::from_digits(&params, &one, &c1) +
+            DCRTPoly::from_digits(&params, &one, &c2);
+        assert_eq!(result.plaintext, Some(expected_plaintext));
+
+        // Check the BGG identity of the key side and the attribute side at every gate
+        assert_eq!(
+            dual_eval(&params, &circuit, &bgg_encoding_sampler, &pubkeys, &encodings, &plaintexts),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_encoding_inner_product() {
+        // Create parameters for testing
+        let params = DCRTPolyParams::default();
+
+        // Create samplers
+        let key: [u8; 32] = rand::random();
+        let d = 3;
+        let bgg_pubkey_sampler =
+            BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+
+        // Generate random tag for sampling
+        let tag: u64 = rand::random();
+        let tag_bytes = tag.to_le_bytes();
+
+        // Create random public keys for two attribute segments of length 2
+        let reveal_plaintexts = [true; 4];
+        let pubkeys = bgg_pubkey_sampler.sample(&params, &tag_bytes, &reveal_plaintexts);
+
+        // Create secret and plaintexts
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let plaintexts = (0..4).map(|_| create_random_poly(&params)).collect::<Vec<_>>();
+
+        // Create encoding sampler and encodings
+        let bgg_encoding_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let encodings = bgg_encoding_sampler.sample(&params, &pubkeys, &plaintexts);
+        let lhs = &encodings[1..3];
+        let rhs = &encodings[3..5];
+
+        // Evaluate the fused inner product
+        let result = BggEncoding::inner_product(lhs, rhs);
+
+        // Expected result: lhs[0] * rhs[0] + lhs[1] * rhs[1]
+        let expected = lhs[0].clone() * rhs[0].clone() + lhs[1].clone() * rhs[1].clone();
+
+        // Verify the result
+        assert_eq!(result.vector, expected.vector);
+        assert_eq!(result.pubkey.matrix, expected.pubkey.matrix);
+        assert_eq!(result.plaintext.as_ref().unwrap(), expected.plaintext.as_ref().unwrap());
+
+        // The key-side inner product must agree with the evaluated public key
+        let pubkey = BggPublicKey::inner_product(&pubkeys[1..3], &pubkeys[3..5]);
+        assert_eq!(pubkey.matrix, expected.pubkey.matrix);
+
+        // The inner-product gate and the attribute ranges evaluate to the same encoding
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(4);
+        let ip = circuit.inner_product_gate(&inputs[0..2], &inputs[2..4]);
+        circuit.output(vec![ip]);
+        let evaluated = circuit.eval(&params, &encodings[0], &encodings[1..]);
+        assert_eq!(evaluated[0].vector, result.vector);
+        let attributes = EncodedAttributes::from_slots(encodings.clone());
+        assert_eq!(attributes.eval_inner_product(0..2, 2..4).vector, result.vector);
+        let keys = AttributeKeys::from_slots(pubkeys.clone());
+        assert_eq!(keys.m_eval_inner_product(0..2, 2..4).matrix, expected.pubkey.matrix);
+    }
+
+    #[test]
+    fn test_compressed_encodings() {
+        // Create parameters for testing
+        let params = DCRTPolyParams::default();
+
+        // Create samplers
+        let key: [u8; 32] = rand::random();
+        let d = 3;
+        let bgg_pubkey_sampler =
+            BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+
+        // Encode two attributes under a fresh tag, revealing only the first one
+        let tag: u64 = rand::random();
+        let tag_bytes = tag.to_le_bytes();
+        let pubkeys = bgg_pubkey_sampler.sample(&params, &tag_bytes, &[true, false]);
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let plaintexts = (0..2).map(|_| create_random_poly(&params)).collect::<Vec<_>>();
+        let bgg_encoding_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let encodings = bgg_encoding_sampler.sample(&params, &pubkeys, &plaintexts);
+
+        // Only the tag, the vectors and the revealed plaintexts are kept
+        let compressed = CompressedEncodings::compress(&tag_bytes, &encodings);
+        assert_eq!(compressed.reveal_plaintexts, vec![true, false]);
+        assert_eq!(compressed.plaintexts[2], None);
+
+        // The receiver re-derives the same encodings from the hash key
+        let receiver = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        assert_eq!(compressed.expand(&params, &receiver), encodings);
+    }
+
+    #[test]
+    fn test_encoding_project() {
+        // Create parameters for testing
+        let params = DCRTPolyParams::default();
+
+        // Create samplers
+        let key: [u8; 32] = rand::random();
+        let d = 3;
+        let bgg_pubkey_sampler =
+            BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+
+        // Generate random tag for sampling
+        let tag: u64 = rand::random();
+        let tag_bytes = tag.to_le_bytes();
+
+        // Create public keys and encodings for a universe of 5 attributes
+        let reveal_plaintexts = [true; 5];
+        let pubkeys = bgg_pubkey_sampler.sample(&params, &tag_bytes, &reveal_plaintexts);
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let plaintexts = (0..5).map(|_| create_random_poly(&params)).collect::<Vec<_>>();
+        let bgg_encoding_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let encodings = bgg_encoding_sampler.sample(&params, &pubkeys, &plaintexts);
+
+        // Project to attributes 3 and 1, in that order
+        let indices = [3, 1];
+        let projected_pubkeys = BggPublicKey::project(&pubkeys, &indices);
+        let projected_encodings = BggEncoding::project(&encodings, &indices);
+        assert_eq!(projected_pubkeys.len(), 3);
+        assert_eq!(projected_pubkeys[0], pubkeys[0]);
+        assert_eq!(projected_pubkeys[1], pubkeys[4]);
+        assert_eq!(projected_pubkeys[2], pubkeys[2]);
+
+        // A policy x3 * x1 + x3 over the projected attributes
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        let mul_gate = circuit.mul_gate(inputs[0], inputs[1]);
+        let add_gate = circuit.add_gate(mul_gate, inputs[0]);
+        circuit.output(vec![add_gate]);
+        let result = circuit.eval(&params, &projected_encodings[0], &projected_encodings[1..]);
+
+        // The same policy evaluated over the full universe
+        let mut full_circuit = PolyCircuit::new();
+        let full_inputs = full_circuit.input(5);
+        let mul_gate = full_circuit.mul_gate(full_inputs[3], full_inputs[1]);
+        let add_gate = full_circuit.add_gate(mul_gate, full_inputs[3]);
+        full_circuit.output(vec![add_gate]);
+        let expected = full_circuit.eval(&params, &encodings[0], &encodings[1..]);
+
+        // Verify the result
+        assert_eq!(result[0].vector, expected[0].vector);
+        assert_eq!(result[0].pubkey, expected[0].pubkey);
+        assert_eq!(result[0].plaintext, expected[0].plaintext);
+        let pubkey_result = circuit.eval(&params, &projected_pubkeys[0], &projected_pubkeys[1..]);
+        assert_eq!(pubkey_result[0], expected[0].pubkey);
+    }
+
     #[test]
     fn test_encoding_circuit_operations() {
         // Create parameters for testing
@@ -373,10 +971,6 @@ mod tests {
         // Create encoding sampler and encodings
         let bgg_encoding_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
         let encodings = bgg_encoding_sampler.sample(&params, &pubkeys, &plaintexts);
-        let enc_one = encodings[0].clone();
-        let enc1 = encodings[1].clone();
-        let enc2 = encodings[2].clone();
-        let enc3 = encodings[3].clone();
 
         // Create a circuit: ((enc1 + enc2)^2) - enc3
         let mut circuit = PolyCircuit::new();
@@ -393,18 +987,11 @@ mod tests {
 
         circuit.output(vec![sub_gate]);
 
-        // Evaluate the circuit
-        let result = circuit.eval(&params, &enc_one, &[enc1.clone(), enc2.clone(), enc3.clone()]);
-
-        // Expected result: ((enc1 + enc2)^2) - enc3
-        let expected =
-            ((enc1.clone() + enc2.clone()) * (enc1.clone() + enc2.clone())) - enc3.clone();
-
-        // Verify the result
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].vector, expected.vector);
-        assert_eq!(result[0].pubkey.matrix, expected.pubkey.matrix);
-        assert_eq!(result[0].plaintext.as_ref().unwrap(), expected.plaintext.as_ref().unwrap());
+        // Check the BGG identity of the key side and the attribute side at every gate
+        assert_eq!(
+            dual_eval(&params, &circuit, &bgg_encoding_sampler, &pubkeys, &encodings, &plaintexts),
+            Ok(())
+        );
     }
 
     #[test]
@@ -439,11 +1026,6 @@ mod tests {
         // Create encoding sampler and encodings
         let bgg_encoding_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
         let encodings = bgg_encoding_sampler.sample(&params, &pubkeys, &plaintexts);
-        let enc_one = encodings[0].clone();
-        let enc1 = encodings[1].clone();
-        let enc2 = encodings[2].clone();
-        let enc3 = encodings[3].clone();
-        let enc4 = encodings[4].clone();
 
         // Create a complex circuit with depth = 4
         // Circuit structure:
@@ -471,26 +1053,11 @@ mod tests {
 
         circuit.output(vec![f]);
 
-        // Evaluate the circuit
-        let result = circuit.eval(
-            &params,
-            &enc_one,
-            &[enc1.clone(), enc2.clone(), enc3.clone(), enc4.clone()],
+        // Check the BGG identity of the key side and the attribute side at every gate
+        assert_eq!(
+            dual_eval(&params, &circuit, &bgg_encoding_sampler, &pubkeys, &encodings, &plaintexts),
+            Ok(())
         );
-
-        // Expected result: (((enc1 + enc2) * (enc3 * enc4)) + (enc1 - enc3)) * scalar
-        let sum1 = enc1.clone() + enc2.clone();
-        let prod1 = enc3.clone() * enc4.clone();
-        let prod2 = sum1.clone() * prod1;
-        let diff = enc1.clone() - enc3.clone();
-        let sum2 = prod2 + diff;
-        let expected = sum2.clone() * sum2;
-
-        // Verify the result
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].vector, expected.vector);
-        assert_eq!(result[0].pubkey.matrix, expected.pubkey.matrix);
-        assert_eq!(result[0].plaintext.as_ref().unwrap(), expected.plaintext.as_ref().unwrap());
     }
 
     #[test]
@@ -740,4 +1307,33 @@ mod tests {
         // Clean up the test directory
         std::fs::remove_dir_all(test_dir).unwrap();
     }
+
+    #[test]
+    fn test_encoding_compress() {
+        let params = DCRTPolyParams::default();
+        let key: [u8; 32] = rand::random();
+        let d = 2;
+        let bgg_pubkey_sampler =
+            BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let pubkeys = bgg_pubkey_sampler.sample(&params, b"compress", &[true]);
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let plaintext = create_random_poly(&params);
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let bgg_encoding_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let encodings = bgg_encoding_sampler.sample(&params, &pubkeys, &[plaintext.clone()]);
+
+        // A single column is left, under the compressed public key
+        let compressed = encodings[1].compress(&params);
+        let m = (d + 1) * params.modulus_digits();
+        assert_eq!(encodings[1].vector.size(), (1, m));
+        assert_eq!(compressed.vector.size(), (1, 1));
+        assert_eq!(compressed.pubkey, pubkeys[1].compress(&params));
+        assert_eq!(compressed.plaintext, Some(plaintext.clone()));
+
+        // Without error the column is s * (A * G^-1(u) - x * u)
+        let unit_vector = DCRTPolyMatrix::unit_column_vector(&params, d + 1, d);
+        let expected = bgg_encoding_sampler.secret_vec.clone() *
+            (compressed.pubkey.matrix.clone() - unit_vector * plaintext);
+        assert_eq!(compressed.vector, expected);
+    }
 }
@@ -0,0 +1,322 @@
+use super::{
+    circuit::{NoisePlan, PolyCircuit},
+    epoch::EpochPolicy,
+    eval_key::{
+        read_bytes, read_header, read_matrix, read_poly, read_u64, write_header, write_matrix,
+        write_poly, write_u64,
+    },
+    matrix_hasher::MatrixHasher,
+    AssociatedData, BggEncoding, BggPublicKey, EncodedAttributes,
+};
+use crate::{
+    migrate::ENCODING_STREAM_VERSION,
+    poly::{Poly, PolyMatrix},
+    utils::ThreadPoolConfig,
+};
+use digest::Digest;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+pub(crate) const ENCODING_MAGIC: &[u8; 4] = b"DIOE";
+
+/// Writes encodings (including the constant-one encoding in slot 0) so that each one can be
+/// read back by index with [`EncodingStreamReader`].
+///
+/// The layout is the magic `DIOE` and the format version as a `u32`, the key epoch, the
+/// associated data as a presence byte followed by its length and [`AssociatedData::to_bytes`],
+/// the number of encodings and a table of their byte offsets relative to the end of the
+/// associated data (all `u64` little-endian), followed by the records. Each record holds the
+/// vector and public key matrices, the reveal flag and a presence flag followed by the plaintext.
+pub fn write_encoding_stream<W: Write + Seek, M: PolyMatrix>(
+    writer: &mut W,
+    encodings: &[BggEncoding<M>],
+) -> io::Result<()> {
+    write_stream(writer, encodings, 0, None)
+}
+
+/// Like [`write_encoding_stream`], storing the epoch and the associated data of `attributes`
+/// next to their encodings, so that [`EncodingStreamReader::read_attributes`] restores them.
+pub fn write_encoded_attributes<W: Write + Seek, M: PolyMatrix>(
+    writer: &mut W,
+    attributes: &EncodedAttributes<M>,
+) -> io::Result<()> {
+    write_stream(writer, attributes.slots(), attributes.epoch(), attributes.associated_data())
+}
+
+fn write_stream<W: Write + Seek, M: PolyMatrix>(
+    writer: &mut W,
+    encodings: &[BggEncoding<M>],
+    epoch: u64,
+    associated_data: Option<&AssociatedData>,
+) -> io::Result<()> {
+    write_header(writer, ENCODING_MAGIC, ENCODING_STREAM_VERSION)?;
+    write_u64(writer, epoch)?;
+    match associated_data {
+        Some(data) => {
+            let bytes = data.to_bytes();
+            writer.write_all(&[1])?;
+            write_u64(writer, bytes.len() as u64)?;
+            writer.write_all(&bytes)?;
+        }
+        None => writer.write_all(&[0])?,
+    }
+    let start = writer.stream_position()?;
+    write_u64(writer, encodings.len() as u64)?;
+    let table_start = writer.stream_position()?;
+    writer.seek(SeekFrom::Current(8 * encodings.len() as i64))?;
+    let mut offsets = Vec::with_capacity(encodings.len());
+    for encoding in encodings {
+        offsets.push(writer.stream_position()? - start);
+        write_record(writer, encoding)?;
+    }
+    let end = writer.stream_position()?;
+    writer.seek(SeekFrom::Start(table_start))?;
+    for offset in offsets {
+        write_u64(writer, offset)?;
+    }
+    writer.seek(SeekFrom::Start(end))?;
+    writer.flush()
+}
+
+/// Writes the vector and public key matrices, the reveal flag and a presence flag followed by the
+/// plaintext of one encoding.
+pub(crate) fn write_record<W: Write + ?Sized, M: PolyMatrix>(
+    writer: &mut W,
+    encoding: &BggEncoding<M>,
+) -> io::Result<()> {
+    write_matrix(writer, &encoding.vector)?;
+    write_matrix(writer, &encoding.pubkey.matrix)?;
+    writer.write_all(&[encoding.pubkey.reveal_plaintext as u8])?;
+    match &encoding.plaintext {
+        Some(plaintext) => {
+            writer.write_all(&[1])?;
+            write_poly(writer, plaintext)
+        }
+        None => writer.write_all(&[0]),
+    }
+}
+
+/// Hashes the number of encodings and the record of every encoding.
+pub(crate) fn encodings_digest<H: Digest, M: PolyMatrix>(encodings: &[BggEncoding<M>]) -> Vec<u8> {
+    let mut hasher = MatrixHasher::<H>::new();
+    hasher.update((encodings.len() as u64).to_le_bytes());
+    for encoding in encodings {
+        write_record(&mut hasher, encoding).expect("hashing cannot fail");
+    }
+    hasher.finalize()
+}
+
+/// Reads one encoding written by [`write_record`].
+pub(crate) fn read_record<R: Read + ?Sized, M: PolyMatrix>(
+    reader: &mut R,
+    params: &<M::P as Poly>::Params,
+) -> io::Result<BggEncoding<M>> {
+    let vector = read_matrix(reader, params)?;
+    let matrix = read_matrix(reader, params)?;
+    let mut flags = [0u8; 2];
+    reader.read_exact(&mut flags)?;
+    let plaintext = match flags[1] {
+        0 => None,
+        _ => Some(read_poly(reader, params)?),
+    };
+    Ok(BggEncoding::new(vector, BggPublicKey::new(matrix, flags[0] != 0), plaintext))
+}
+
+/// Reads encodings written by [`write_encoding_stream`] on demand by index.
+#[derive(Debug)]
+pub struct EncodingStreamReader<R: Read + Seek> {
+    reader: R,
+    start: u64,
+    offsets: Vec<u64>,
+    epoch: u64,
+    associated_data: Option<AssociatedData>,
+}
+
+impl<R: Read + Seek> EncodingStreamReader<R> {
+    /// Reads the header, the epoch, the associated data and the offset table at the current
+    /// position of `reader`. Streams of older versions must be upgraded with
+    /// [`crate::migrate::migrate_encoding_stream`] first.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        read_header(&mut reader, ENCODING_MAGIC, ENCODING_STREAM_VERSION)?;
+        let epoch = read_u64(&mut reader)?;
+        let mut present = [0u8; 1];
+        reader.read_exact(&mut present)?;
+        let associated_data = match present[0] {
+            0 => None,
+            1 => {
+                let len = read_u64(&mut reader)?;
+                Some(AssociatedData::from_bytes(&read_bytes(&mut reader, len)?)?)
+            }
+            _ => {
+                let message = "invalid associated data flag";
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+            }
+        };
+        let start = reader.stream_position()?;
+        let len = read_u64(&mut reader)? as usize;
+        let offsets = (0..len).map(|_| read_u64(&mut reader)).collect::<io::Result<Vec<_>>>()?;
+        Ok(Self { reader, start, offsets, epoch, associated_data })
+    }
+
+    /// The key epoch the encodings were made for, 0 unless written by
+    /// [`write_encoded_attributes`].
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// The associated data written by [`write_encoded_attributes`], if any.
+    pub fn associated_data(&self) -> Option<&AssociatedData> {
+        self.associated_data.as_ref()
+    }
+
+    /// Reads every encoding, together with the epoch and the associated data stored next to them.
+    pub fn read_attributes<M: PolyMatrix>(
+        &mut self,
+        params: &<M::P as Poly>::Params,
+    ) -> io::Result<EncodedAttributes<M>> {
+        if self.is_empty() {
+            let message = "the constant-one slot is missing";
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+        let slots = (0..self.len()).map(|idx| self.read(params, idx)).collect::<io::Result<_>>()?;
+        let attributes = match self.associated_data.clone() {
+            Some(data) => EncodedAttributes::with_associated_data(slots, data),
+            None => EncodedAttributes::from_slots(slots),
+        };
+        Ok(attributes.with_epoch(self.epoch))
+    }
+
+    /// Number of encodings, including the constant-one encoding.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Reads the encoding in slot `idx`, where slot 0 is the constant-one encoding.
+    pub fn read<M: PolyMatrix>(
+        &mut self,
+        params: &<M::P as Poly>::Params,
+        idx: usize,
+    ) -> io::Result<BggEncoding<M>> {
+        let offset = *self.offsets.get(idx).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("no encoding at index {}", idx))
+        })?;
+        let position = self.start.checked_add(offset).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("invalid offset of slot {}", idx))
+        })?;
+        self.reader.seek(SeekFrom::Start(position))?;
+        read_record(&mut self.reader, params)
+    }
+
+    /// Evaluates `circuit` over the stored encodings with [`PolyCircuit::eval_streaming`],
+    /// reading each attribute encoding only when a gate first needs it and evaluating the gates
+    /// in `pool`. Fails before reading any encoding if `policy` rejects their epoch.
+    pub fn eval_circuit<M: PolyMatrix>(
+        &mut self,
+        params: &<M::P as Poly>::Params,
+        circuit: &PolyCircuit,
+        policy: EpochPolicy,
+        pool: &ThreadPoolConfig,
+    ) -> io::Result<Vec<BggEncoding<M>>> {
+        policy.check(self.epoch)?;
+        if self.len() != circuit.num_input() + 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} encodings for a circuit of {} inputs", self.len(), circuit.num_input()),
+            ));
+        }
+        let one = self.read(params, 0)?;
+        circuit.eval_streaming(params, &one, |idx| self.read(params, idx + 1), pool)
+    }
+
+    /// Evaluates the circuit of `plan` like [`Self::eval_circuit`], in the order of its
+    /// schedule. The public keys of the outputs must come from the same planned circuit.
+    pub fn eval_plan<M: PolyMatrix>(
+        &mut self,
+        params: &<M::P as Poly>::Params,
+        plan: &NoisePlan,
+        policy: EpochPolicy,
+        pool: &ThreadPoolConfig,
+    ) -> io::Result<Vec<BggEncoding<M>>> {
+        policy.check(self.epoch)?;
+        let num_input = plan.circuit().num_input();
+        if self.len() != num_input + 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} encodings for a circuit of {} inputs", self.len(), num_input),
+            ));
+        }
+        let one = self.read(params, 0)?;
+        plan.eval_streaming(params, &one, |idx| self.read(params, idx + 1), pool)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::sampler::{BGGEncodingSampler, BGGPublicKeySampler},
+        poly::dcrt::{DCRTPolyHashSampler, DCRTPolyMatrix, DCRTPolyParams, DCRTPolyUniformSampler},
+        utils::{create_bit_random_poly, create_random_poly},
+    };
+    use keccak_asm::Keccak256;
+    use num_bigint::BigUint;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_encoding_stream_eval() {
+        // Create parameters for testing
+        let params = DCRTPolyParams::default();
+
+        // Create samplers
+        let key: [u8; 32] = rand::random();
+        let d = 2;
+        let bgg_pubkey_sampler =
+            BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+
+        // Create public keys, hiding the plaintext of the third attribute
+        let tag: u64 = rand::random();
+        let reveal_plaintexts = [true, true, false, true];
+        let pubkeys = bgg_pubkey_sampler.sample(&params, &tag.to_le_bytes(), &reveal_plaintexts);
+
+        // Create secret, plaintexts and encodings
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let plaintexts = (0..4).map(|_| create_random_poly(&params)).collect::<Vec<_>>();
+        let bgg_encoding_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let encodings = bgg_encoding_sampler.sample(&params, &pubkeys, &plaintexts);
+
+        // Write the encodings and read a single one back
+        let mut cursor = Cursor::new(Vec::new());
+        write_encoding_stream(&mut cursor, &encodings).unwrap();
+        cursor.set_position(0);
+        let mut reader = EncodingStreamReader::new(cursor).unwrap();
+        assert_eq!(reader.len(), 5);
+        let third = reader.read::<DCRTPolyMatrix>(&params, 3).unwrap();
+        assert_eq!(third.vector, encodings[3].vector);
+        assert_eq!(third.pubkey, encodings[3].pubkey);
+        assert_eq!(third.plaintext, None);
+
+        // x1 * x2 + x3, evaluated over the stream and in memory
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(4);
+        let mul_gate = circuit.mul_gate(inputs[0], inputs[1]);
+        let add_gate = circuit.add_gate(mul_gate, inputs[2]);
+        circuit.output(vec![add_gate]);
+        let (policy, pool) = (EpochPolicy::default(), ThreadPoolConfig::global());
+        let result =
+            reader.eval_circuit::<DCRTPolyMatrix>(&params, &circuit, policy, &pool).unwrap();
+        let expected = circuit.eval(&params, &encodings[0], &encodings[1..]);
+        assert_eq!(result[0].vector, expected[0].vector);
+        assert_eq!(result[0].pubkey, expected[0].pubkey);
+        assert_eq!(result[0].plaintext, expected[0].plaintext);
+
+        // The same circuit through its noise plan
+        let plan = circuit.plan_noise(16, 1, vec![BigUint::from(1u32); 4]);
+        let result = reader.eval_plan::<DCRTPolyMatrix>(&params, &plan, policy, &pool).unwrap();
+        assert_eq!(result[0].vector, expected[0].vector);
+        assert_eq!(result[0].plaintext, expected[0].plaintext);
+    }
+}
@@ -0,0 +1,171 @@
+//! Encrypts the raw attribute vector stored next to its encodings with ChaCha20-Poly1305, so that
+//! applications keeping both do not need their own envelope format.
+//!
+//! The key of the envelope is `H(DIOA || len || aux_key || digest)`, where `len` is the length of
+//! the auxiliary key as a `u64` little-endian and `digest` the digest of the encodings, which is
+//! also bound as associated data. An envelope therefore only opens next to the encodings it was
+//! sealed with. Encodings with [`super::AssociatedData`] replace the auxiliary key by its
+//! [`super::AssociatedData::bind_key`].
+use super::{
+    associated_data::unix_now,
+    encoding_stream::encodings_digest,
+    eval_key::{read_bytes, read_u64, write_u64},
+    EncodedAttributes,
+};
+use crate::poly::{
+    rng::{default_source, CryptoRngSource},
+    PolyMatrix,
+};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use digest::Digest;
+use std::io::{self, Read, Write};
+
+const KEY_DOMAIN: &[u8; 4] = b"DIOA";
+pub const NONCE_SIZE: usize = 12;
+
+/// The attribute vector encrypted under a key derived from an auxiliary key and the encodings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeEnvelope {
+    pub nonce: [u8; NONCE_SIZE],
+    /// The encrypted attributes followed by the 16-byte tag.
+    pub ciphertext: Vec<u8>,
+}
+
+/// Error returned when an envelope does not open, because the auxiliary key or the encodings
+/// differ from those it was sealed with, the envelope was modified or the associated data of the
+/// encodings has expired.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvelopeError;
+
+impl std::fmt::Display for EnvelopeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "attribute envelope failed to authenticate")
+    }
+}
+
+impl std::error::Error for EnvelopeError {}
+
+impl<M: PolyMatrix> EncodedAttributes<M> {
+    fn envelope_cipher<H: Digest>(&self, aux_key: &[u8]) -> (ChaCha20Poly1305, Vec<u8>) {
+        let slots = std::iter::once(self.constant_one_row())
+            .chain(self.attributes())
+            .cloned()
+            .collect::<Vec<_>>();
+        let digest = encodings_digest::<H, M>(&slots);
+        let aux_key = self.bound_key::<H>(aux_key);
+        let mut hasher = H::new();
+        hasher.update(KEY_DOMAIN);
+        hasher.update((aux_key.len() as u64).to_le_bytes());
+        hasher.update(&aux_key);
+        hasher.update(&digest);
+        let key = hasher.finalize();
+        assert!(key.len() >= 32, "the hash must have at least 256 bits of output");
+        (ChaCha20Poly1305::new(Key::from_slice(&key[..32])), digest)
+    }
+
+    /// Encrypts `attributes` under a key derived from `aux_key` and these encodings, with a
+    /// random nonce.
+    pub fn seal_attributes<H: Digest>(
+        &self,
+        aux_key: &[u8],
+        attributes: &[u8],
+    ) -> AttributeEnvelope {
+        self.seal_attributes_with::<H>(aux_key, attributes, &*default_source())
+    }
+
+    /// Like [`Self::seal_attributes`], drawing the nonce from `source`.
+    pub fn seal_attributes_with<H: Digest>(
+        &self,
+        aux_key: &[u8],
+        attributes: &[u8],
+        source: &dyn CryptoRngSource,
+    ) -> AttributeEnvelope {
+        let (cipher, digest) = self.envelope_cipher::<H>(aux_key);
+        let mut nonce = [0u8; NONCE_SIZE];
+        source.fill_bytes(&mut nonce);
+        let payload = Payload { msg: attributes, aad: &digest };
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), payload)
+            .expect("encryption of an in-memory buffer cannot fail");
+        AttributeEnvelope { nonce, ciphertext }
+    }
+
+    pub fn open_attributes<H: Digest>(
+        &self,
+        aux_key: &[u8],
+        envelope: &AttributeEnvelope,
+    ) -> Result<Vec<u8>, EnvelopeError> {
+        self.open_attributes_at::<H>(aux_key, envelope, unix_now())
+    }
+
+    /// Like [`Self::open_attributes`], checking the expiry against the Unix time `now`.
+    pub fn open_attributes_at<H: Digest>(
+        &self,
+        aux_key: &[u8],
+        envelope: &AttributeEnvelope,
+        now: u64,
+    ) -> Result<Vec<u8>, EnvelopeError> {
+        if self.is_expired(now) {
+            return Err(EnvelopeError);
+        }
+        let (cipher, digest) = self.envelope_cipher::<H>(aux_key);
+        let payload = Payload { msg: &envelope.ciphertext, aad: &digest };
+        cipher.decrypt(Nonce::from_slice(&envelope.nonce), payload).map_err(|_| EnvelopeError)
+    }
+}
+
+/// Writes the nonce followed by the length of the ciphertext as a `u64` little-endian and the
+/// ciphertext.
+pub fn write_envelope<W: Write>(writer: &mut W, envelope: &AttributeEnvelope) -> io::Result<()> {
+    writer.write_all(&envelope.nonce)?;
+    write_u64(writer, envelope.ciphertext.len() as u64)?;
+    writer.write_all(&envelope.ciphertext)?;
+    writer.flush()
+}
+
+pub fn read_envelope<R: Read>(reader: &mut R) -> io::Result<AttributeEnvelope> {
+    let mut nonce = [0u8; NONCE_SIZE];
+    reader.read_exact(&mut nonce)?;
+    let len = read_u64(reader)?;
+    let ciphertext = read_bytes(reader, len)?;
+    Ok(AttributeEnvelope { nonce, ciphertext })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::TestBggSetup;
+    use keccak_asm::Keccak256;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_attribute_envelope() {
+        let (_, encodings) = TestBggSetup::new(2).sample(b"envelope", &[true, true]);
+        let attributes = EncodedAttributes::from_slots(encodings.clone());
+
+        // The envelope round-trips through its serialization and opens with the same key
+        let aux_key = b"storage key";
+        let raw = b"age_over_18=1;country_us=0".to_vec();
+        let envelope = attributes.seal_attributes::<Keccak256>(aux_key, &raw);
+        let mut cursor = Cursor::new(Vec::new());
+        write_envelope(&mut cursor, &envelope).unwrap();
+        cursor.set_position(0);
+        let read = read_envelope(&mut cursor).unwrap();
+        assert_eq!(read, envelope);
+        assert_eq!(attributes.open_attributes::<Keccak256>(aux_key, &read), Ok(raw));
+
+        // Another key, other encodings or a modified envelope are rejected
+        let err = Err(EnvelopeError);
+        assert_eq!(attributes.open_attributes::<Keccak256>(b"other key", &envelope), err);
+        let mut swapped = encodings.clone();
+        swapped.swap(1, 2);
+        let swapped = EncodedAttributes::from_slots(swapped);
+        assert_eq!(swapped.open_attributes::<Keccak256>(aux_key, &envelope), err);
+        let mut modified = envelope.clone();
+        modified.ciphertext[0] ^= 1;
+        assert_eq!(attributes.open_attributes::<Keccak256>(aux_key, &modified), err);
+    }
+}
@@ -0,0 +1,187 @@
+//! Key epochs, for deployments that refresh their keys periodically.
+//!
+//! The public keys of epoch `e > 0` are expanded from `H(DIO_EPOCH || hash_key || e)` instead of
+//! the hash key itself, where `H` is the hash of the sampler, so advancing the epoch of a
+//! [`super::sampler::BGGPublicKeySampler`] re-derives every hash-expanded matrix, including the
+//! [`crate::io::utils::PublicSampledData`] and the [`super::revocation::RevocationToken`]s
+//! sampled from it, and encodings of earlier epochs no longer evaluate correctly under the new
+//! keys.
+//!
+//! Encodings carry the epoch they were made for, which is bound into their MAC and envelope keys
+//! and stored in the encoding stream. Evaluators reject stale ciphertexts with an
+//! [`EpochPolicy`] before evaluating them.
+use super::EncodedAttributes;
+use crate::poly::PolyMatrix;
+use digest::Digest;
+use std::{fmt, io};
+
+const EPOCH_DOMAIN: &[u8; 9] = b"DIO_EPOCH";
+
+/// The hash key public keys of `epoch` are expanded from, `hash_key` itself at epoch 0.
+pub fn epoch_hash_key<H: Digest>(hash_key: [u8; 32], epoch: u64) -> [u8; 32] {
+    if epoch == 0 {
+        return hash_key;
+    }
+    let mut hasher = H::new();
+    hasher.update(EPOCH_DOMAIN);
+    hasher.update(hash_key);
+    hasher.update(epoch.to_le_bytes());
+    let digest = hasher.finalize();
+    assert!(digest.len() >= 32, "the hash must have at least 256 bits of output");
+    digest[..32].try_into().unwrap()
+}
+
+/// Derives `H(DIO_EPOCH || len || key || epoch)` from `key`, where `len` is the length of the
+/// key as a `u64` little-endian.
+pub(crate) fn bind_epoch<H: Digest>(key: &[u8], epoch: u64) -> Vec<u8> {
+    let mut hasher = H::new();
+    hasher.update(EPOCH_DOMAIN);
+    hasher.update((key.len() as u64).to_le_bytes());
+    hasher.update(key);
+    hasher.update(epoch.to_le_bytes());
+    hasher.finalize().to_vec()
+}
+
+/// Why encodings are rejected by an [`EpochPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EpochError {
+    Stale { epoch: u64, current: u64, max_age: u64 },
+    Future { epoch: u64, current: u64 },
+}
+
+impl fmt::Display for EpochError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stale { epoch, current, max_age } => write!(
+                f,
+                "encodings of epoch {} are more than {} epochs older than epoch {}",
+                epoch, max_age, current
+            ),
+            Self::Future { epoch, current } => {
+                write!(f, "encodings of epoch {} are ahead of epoch {}", epoch, current)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EpochError {}
+
+impl From<EpochError> for io::Error {
+    fn from(err: EpochError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// The epochs an evaluator accepts encodings of: the `current` epoch and the `max_age` epochs
+/// before it. The default only accepts epoch 0, for deployments that never advance it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EpochPolicy {
+    pub current: u64,
+    pub max_age: u64,
+}
+
+impl EpochPolicy {
+    pub fn new(current: u64, max_age: u64) -> Self {
+        Self { current, max_age }
+    }
+
+    pub fn check(&self, epoch: u64) -> Result<(), EpochError> {
+        let (current, max_age) = (self.current, self.max_age);
+        if epoch > current {
+            return Err(EpochError::Future { epoch, current });
+        }
+        if current - epoch > max_age {
+            return Err(EpochError::Stale { epoch, current, max_age });
+        }
+        Ok(())
+    }
+}
+
+impl<M: PolyMatrix> EncodedAttributes<M> {
+    /// Marks the encodings as made under the public keys of `epoch`.
+    pub fn with_epoch(mut self, epoch: u64) -> Self {
+        self.epoch = epoch;
+        self
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Accepts encodings of the `current` epoch and of the `max_age` epochs before it.
+    pub fn check_epoch(&self, current: u64, max_age: u64) -> Result<(), EpochError> {
+        EpochPolicy::new(current, max_age).check(self.epoch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::{
+            circuit::PolyCircuit,
+            encoding_stream::{write_encoded_attributes, EncodingStreamReader},
+            sampler::{BGGEncodingSampler, BGGPublicKeySampler},
+        },
+        poly::dcrt::{DCRTPolyHashSampler, DCRTPolyMatrix, DCRTPolyParams, DCRTPolyUniformSampler},
+        utils::{create_bit_random_poly, create_random_poly, ThreadPoolConfig},
+    };
+    use keccak_asm::Keccak256;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_key_epochs() {
+        let params = DCRTPolyParams::default();
+        let key: [u8; 32] = rand::random();
+        let d = 2;
+
+        // Epoch 0 samples the same keys as before, later epochs re-derive them
+        let mut bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let initial = bgg_sampler.sample(&params, b"epoch", &[true]);
+        assert_eq!(epoch_hash_key::<Keccak256>(key, 0), key);
+        bgg_sampler.advance_epoch();
+        assert_eq!(bgg_sampler.epoch(), 1);
+        let pubkeys = bgg_sampler.sample(&params, b"epoch", &[true]);
+        assert_ne!(pubkeys, initial);
+        let reloaded = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        assert_eq!(reloaded.at_epoch(1).sample(&params, b"epoch", &[true]), pubkeys);
+
+        // Encodings of epoch 1 are accepted until they are more than max_age epochs old
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let encodings = bgg_sampler.sample(&params, &pubkeys, &[create_random_poly(&params)]);
+        let attributes = EncodedAttributes::from_slots(encodings.clone()).with_epoch(1);
+        assert_eq!(attributes.check_epoch(1, 0), Ok(()));
+        assert_eq!(attributes.check_epoch(3, 2), Ok(()));
+        assert_eq!(
+            attributes.check_epoch(3, 1),
+            Err(EpochError::Stale { epoch: 1, current: 3, max_age: 1 })
+        );
+        assert_eq!(attributes.check_epoch(0, 5), Err(EpochError::Future { epoch: 1, current: 0 }));
+
+        // Relabeling the epoch breaks the tag
+        let mac_key = b"relay key";
+        let tag = attributes.mac::<Keccak256>(mac_key);
+        assert!(attributes.verify_mac::<Keccak256>(mac_key, &tag));
+        let relabeled = EncodedAttributes::from_slots(encodings).with_epoch(2);
+        assert!(!relabeled.verify_mac::<Keccak256>(mac_key, &tag));
+
+        // The epoch is stored in the encoding stream, whose evaluation rejects stale encodings
+        let mut cursor = Cursor::new(Vec::new());
+        write_encoded_attributes(&mut cursor, &attributes).unwrap();
+        cursor.set_position(0);
+        let mut reader = EncodingStreamReader::new(cursor).unwrap();
+        assert_eq!(reader.epoch(), 1);
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(1);
+        circuit.output(inputs);
+        let pool = ThreadPoolConfig::global();
+        let policy = EpochPolicy::new(3, 1);
+        let stale = reader.eval_circuit::<DCRTPolyMatrix>(&params, &circuit, policy, &pool);
+        assert_eq!(stale.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let policy = EpochPolicy::new(2, 1);
+        let fresh = reader.eval_circuit::<DCRTPolyMatrix>(&params, &circuit, policy, &pool);
+        assert_eq!(fresh.unwrap()[0].vector, attributes.attributes()[0].vector);
+    }
+}
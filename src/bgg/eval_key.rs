@@ -0,0 +1,295 @@
+use super::{
+    circuit::{PolyCircuit, PolyGateType},
+    AttributeKeys,
+};
+use crate::{
+    migrate::EVAL_KEY_STREAM_VERSION,
+    poly::{Poly, PolyMatrix},
+    utils::block_size,
+};
+use std::io::{self, Read, Write};
+
+pub(crate) const EVAL_KEY_MAGIC: &[u8; 4] = b"DIOK";
+const KEY_TAG: u8 = 1;
+const END_TAG: u8 = 0;
+
+/// Streams evaluation keys, i.e. the decomposed right-hand public key matrices `G^-1(A_r)` of
+/// the multiplication gates, stacked for inner-product gates, to any [`Write`] one row block at
+/// a time.
+///
+/// The stream starts with the magic `DIOK` and the format version as a `u32`. Each key is
+/// written as a tag byte, the gate id, the number of rows and columns (all `u64`
+/// little-endian), followed by every entry as a `u32` length prefix and its compact bytes.
+/// [`Self::finish`] writes an end marker.
+#[derive(Debug)]
+pub struct EvalKeyWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> EvalKeyWriter<W> {
+    /// Writes the stream header and returns the writer.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        write_header(&mut writer, EVAL_KEY_MAGIC, EVAL_KEY_STREAM_VERSION)?;
+        Ok(Self { writer })
+    }
+
+    /// Writes the evaluation key of `gate_id`, materializing at most `block_size()` rows of
+    /// serialized entries at a time.
+    pub fn write_key<M: PolyMatrix>(&mut self, gate_id: usize, matrix: &M) -> io::Result<()> {
+        self.writer.write_all(&[KEY_TAG])?;
+        write_u64(&mut self.writer, gate_id as u64)?;
+        write_matrix(&mut self.writer, matrix)
+    }
+
+    /// Evaluates the circuit over `pubkeys` and writes the evaluation key of every
+    /// multiplication and inner-product gate. Returns the number of keys written.
+    pub fn write_circuit_keys<M: PolyMatrix>(
+        &mut self,
+        params: &<M::P as Poly>::Params,
+        circuit: &PolyCircuit,
+        pubkeys: &AttributeKeys<M>,
+    ) -> io::Result<usize> {
+        let wires = circuit.eval_wires(params, pubkeys.constant_one_row(), pubkeys.attributes());
+        let mut count = 0;
+        for gate in circuit.gates() {
+            let right_ids = match gate.gate_type {
+                PolyGateType::Mul => &gate.input_gates[1..],
+                PolyGateType::InnerProduct { len } => &gate.input_gates[len..],
+                _ => continue,
+            };
+            let decomposed =
+                right_ids.iter().map(|id| wires[id].matrix.decompose()).collect::<Vec<_>>();
+            let key = decomposed[0].concat_rows(&decomposed[1..].iter().collect::<Vec<_>>());
+            self.write_key(gate.gate_id, &key)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Writes the end marker, flushes and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.write_all(&[END_TAG])?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads evaluation keys written by [`EvalKeyWriter`] one key at a time.
+#[derive(Debug)]
+pub struct EvalKeyReader<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> EvalKeyReader<R> {
+    /// Reads and checks the stream header. Streams of older versions must be upgraded with
+    /// [`crate::migrate::migrate_eval_key_stream`] first.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        read_header(&mut reader, EVAL_KEY_MAGIC, EVAL_KEY_STREAM_VERSION)?;
+        Ok(Self { reader })
+    }
+
+    /// Reads the next key as `(gate_id, matrix)`, or `None` once the end marker is reached.
+    pub fn read_key<M: PolyMatrix>(
+        &mut self,
+        params: &<M::P as Poly>::Params,
+    ) -> io::Result<Option<(usize, M)>> {
+        let mut tag = [0u8; 1];
+        self.reader.read_exact(&mut tag)?;
+        match tag[0] {
+            END_TAG => return Ok(None),
+            KEY_TAG => {}
+            tag => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unknown eval key tag {}", tag),
+                ))
+            }
+        }
+        let gate_id = read_u64(&mut self.reader)? as usize;
+        let matrix = read_matrix(&mut self.reader, params)?;
+        Ok(Some((gate_id, matrix)))
+    }
+}
+
+pub(crate) fn write_header<W: Write + ?Sized>(
+    writer: &mut W,
+    magic: &[u8; 4],
+    version: u32,
+) -> io::Result<()> {
+    writer.write_all(magic)?;
+    writer.write_all(&version.to_le_bytes())
+}
+
+/// Reads a header written by [`write_header`] and checks that it has the expected version.
+pub(crate) fn read_header<R: Read + ?Sized>(
+    reader: &mut R,
+    magic: &[u8; 4],
+    version: u32,
+) -> io::Result<()> {
+    let mut header = [0u8; 8];
+    reader.read_exact(&mut header)?;
+    if &header[..4] != magic {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "missing stream header, the stream may need to be migrated",
+        ));
+    }
+    let found = u32::from_le_bytes(header[4..].try_into().unwrap());
+    if found != version {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported stream version {}, expected {}", found, version),
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn write_u64<W: Write + ?Sized>(writer: &mut W, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub(crate) fn read_u64<R: Read + ?Sized>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Reads `len` bytes without allocating them up front, so that a corrupted length fails with
+/// an unexpected end of file instead of a huge allocation.
+pub(crate) fn read_bytes<R: Read + ?Sized>(reader: &mut R, len: u64) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    (&mut *reader).take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+/// Writes the number of rows and columns followed by every entry as a `u32` length prefix and
+/// its compact bytes, serializing at most `block_size()` rows at a time.
+pub(crate) fn write_matrix<W: Write + ?Sized, M: PolyMatrix>(
+    writer: &mut W,
+    matrix: &M,
+) -> io::Result<()> {
+    let (nrow, ncol) = matrix.size();
+    write_u64(writer, nrow as u64)?;
+    write_u64(writer, ncol as u64)?;
+    let block_size = block_size();
+    for start in (0..nrow).step_by(block_size) {
+        let end = (start + block_size).min(nrow);
+        let block = matrix.slice_rows(start, end);
+        let mut bytes = Vec::new();
+        for i in 0..end - start {
+            for poly in block.get_row(i) {
+                write_poly(&mut bytes, &poly)?;
+            }
+        }
+        writer.write_all(&bytes)?;
+    }
+    Ok(())
+}
+
+/// Reads a matrix written by [`write_matrix`].
+pub(crate) fn read_matrix<R: Read + ?Sized, M: PolyMatrix>(
+    reader: &mut R,
+    params: &<M::P as Poly>::Params,
+) -> io::Result<M> {
+    let nrow = read_u64(reader)? as usize;
+    let ncol = read_u64(reader)? as usize;
+    if nrow == 0 || ncol == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "empty matrix"));
+    }
+    // The dimensions are untrusted, so rows are only allocated once they are read
+    let mut rows = Vec::new();
+    for _ in 0..nrow {
+        let row = (0..ncol).map(|_| read_poly(reader, params)).collect::<io::Result<Vec<_>>>()?;
+        rows.push(row);
+    }
+    Ok(M::from_poly_vec(params, rows))
+}
+
+pub(crate) fn write_poly<W: Write + ?Sized, P: Poly>(writer: &mut W, poly: &P) -> io::Result<()> {
+    let entry = poly.to_compact_bytes();
+    writer.write_all(&(entry.len() as u32).to_le_bytes())?;
+    writer.write_all(&entry)
+}
+
+pub(crate) fn read_poly<R: Read + ?Sized, P: Poly>(
+    reader: &mut R,
+    params: &P::Params,
+) -> io::Result<P> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    let entry = read_bytes(reader, u32::from_le_bytes(len) as u64)?;
+    P::try_from_compact_bytes(params, &entry)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed polynomial"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::sampler::BGGPublicKeySampler,
+        poly::dcrt::{DCRTPoly, DCRTPolyHashSampler, DCRTPolyMatrix, DCRTPolyParams},
+        utils::create_random_poly,
+    };
+    use keccak_asm::Keccak256;
+
+    #[test]
+    fn test_eval_key_write_read() {
+        // Create parameters for testing
+        let params = DCRTPolyParams::default();
+
+        // Create random public keys
+        let key: [u8; 32] = rand::random();
+        let d = 2;
+        let bgg_pubkey_sampler =
+            BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let tag: u64 = rand::random();
+        let reveal_plaintexts = [true; 3];
+        let pubkeys =
+            bgg_pubkey_sampler.sample_attributes(&params, &tag.to_le_bytes(), &reveal_plaintexts);
+
+        // Create a circuit with two multiplication gates: (x1 * x2) * x3
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(3);
+        let mul1 = circuit.mul_gate(inputs[0], inputs[1]);
+        let mul2 = circuit.mul_gate(mul1, inputs[2]);
+        circuit.output(vec![mul2]);
+
+        // Stream the evaluation keys into a buffer
+        let mut writer = EvalKeyWriter::new(Vec::new()).unwrap();
+        let count = writer.write_circuit_keys(&params, &circuit, &pubkeys).unwrap();
+        assert_eq!(count, 2);
+        let bytes = writer.finish().unwrap();
+
+        // Read them back one by one
+        let mut reader = EvalKeyReader::new(bytes.as_slice()).unwrap();
+        let (gate_id, matrix) = reader.read_key::<DCRTPolyMatrix>(&params).unwrap().unwrap();
+        assert_eq!(gate_id, mul1);
+        assert_eq!(matrix, pubkeys.attributes()[1].matrix.decompose());
+        let (gate_id, matrix) = reader.read_key::<DCRTPolyMatrix>(&params).unwrap().unwrap();
+        assert_eq!(gate_id, mul2);
+        assert_eq!(matrix, pubkeys.attributes()[2].matrix.decompose());
+        assert!(reader.read_key::<DCRTPolyMatrix>(&params).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_poly_rejects_malformed() {
+        let params = DCRTPolyParams::default();
+        let poly = create_random_poly(&params);
+        let mut bytes = Vec::new();
+        write_poly(&mut bytes, &poly).unwrap();
+        assert_eq!(read_poly::<_, DCRTPoly>(&mut bytes.as_slice(), &params).unwrap(), poly);
+
+        // A length beyond the input, a truncated entry and a wrong coefficient size are errors
+        let mut long = bytes.clone();
+        long[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let truncated = bytes[..bytes.len() - 1].to_vec();
+        let mut resized = bytes.clone();
+        resized[4] = resized[4].wrapping_add(1);
+        for malformed in [long, truncated, resized] {
+            assert!(read_poly::<_, DCRTPoly>(&mut malformed.as_slice(), &params).is_err());
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use super::{matrix_hasher::MatrixHasher, BggPublicKey};
+use crate::{migrate::FINGERPRINT_VERSION, poly::PolyMatrix};
+use digest::Digest;
+use std::marker::PhantomData;
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+const ROOT_PREFIX: u8 = 2;
+
+/// Hashes the [`FINGERPRINT_VERSION`], the reveal flag and the public key matrix with
+/// [`MatrixHasher`], which walks its entries in row-major order. Version 0 fingerprints hashed
+/// the entries without their lengths, so Merkle roots computed over them must be recomputed.
+pub fn pubkey_fingerprint<H: Digest, M: PolyMatrix>(pubkey: &BggPublicKey<M>) -> Vec<u8> {
+    let mut hasher = MatrixHasher::<H>::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(FINGERPRINT_VERSION.to_le_bytes());
+    hasher.update([pubkey.reveal_plaintext as u8]);
+    hasher.update_matrix(&pubkey.matrix);
+    hasher.finalize()
+}
+
+fn hash_node<H: Digest>(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut hasher = H::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().to_vec()
+}
+
+/// Binds the number of leaves into the root, so that a tree cannot be reinterpreted as one
+/// with more leaves by pairing its last node with itself.
+fn hash_root<H: Digest>(leaf_count: usize, top: &[u8]) -> Vec<u8> {
+    let mut hasher = H::new();
+    hasher.update([ROOT_PREFIX]);
+    hasher.update((leaf_count as u64).to_le_bytes());
+    hasher.update(top);
+    hasher.finalize().to_vec()
+}
+
+/// The number of siblings on the path of every leaf of a tree with `leaf_count` leaves.
+fn tree_depth(leaf_count: usize) -> usize {
+    leaf_count.next_power_of_two().trailing_zeros() as usize
+}
+
+/// An authentication path of one public key in a [`PubKeyMerkleTree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub index: usize,
+    pub siblings: Vec<Vec<u8>>,
+}
+
+/// A Merkle tree over the fingerprints of a list of public keys, so that evaluators fetching
+/// only some of the keys can authenticate them against a trusted root.
+/// A node without a sibling is paired with itself, and the root commits to the number of keys.
+#[derive(Debug, Clone)]
+pub struct PubKeyMerkleTree<H: Digest> {
+    levels: Vec<Vec<Vec<u8>>>,
+    root: Vec<u8>,
+    _h: PhantomData<H>,
+}
+
+impl<H: Digest> PubKeyMerkleTree<H> {
+    pub fn new<M: PolyMatrix>(pubkeys: &[BggPublicKey<M>]) -> Self {
+        assert!(!pubkeys.is_empty(), "no public keys to commit");
+        let leaves = pubkeys.iter().map(pubkey_fingerprint::<H, M>).collect::<Vec<_>>();
+        let mut levels = vec![leaves];
+        while levels.last().unwrap().len() > 1 {
+            let level = levels.last().unwrap();
+            let next = level
+                .chunks(2)
+                .map(|pair| hash_node::<H>(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+                .collect();
+            levels.push(next);
+        }
+        let root = hash_root::<H>(pubkeys.len(), &levels.last().unwrap()[0]);
+        Self { levels, root, _h: PhantomData }
+    }
+
+    /// The number of committed public keys, which verifiers need along with the root.
+    pub fn leaf_count(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Returns the fingerprint of each public key.
+    pub fn fingerprints(&self) -> &[Vec<u8>] {
+        &self.levels[0]
+    }
+
+    pub fn root(&self) -> &[u8] {
+        &self.root
+    }
+
+    pub fn proof(&self, index: usize) -> MerkleProof {
+        assert!(index < self.levels[0].len(), "index out of range");
+        let mut siblings = Vec::with_capacity(self.levels.len() - 1);
+        let mut idx = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = level.get(idx ^ 1).unwrap_or(&level[idx]);
+            siblings.push(sibling.clone());
+            idx /= 2;
+        }
+        MerkleProof { index, siblings }
+    }
+}
+
+/// Verifies that `pubkey` is the `proof.index`-th of the `leaf_count` keys committed to by
+/// `root`. Indices past the last key are rejected even if the path would hash to the root.
+pub fn verify_row<H: Digest, M: PolyMatrix>(
+    root: &[u8],
+    leaf_count: usize,
+    pubkey: &BggPublicKey<M>,
+    proof: &MerkleProof,
+) -> bool {
+    if proof.index >= leaf_count || proof.siblings.len() != tree_depth(leaf_count) {
+        return false;
+    }
+    let mut node = pubkey_fingerprint::<H, M>(pubkey);
+    let mut idx = proof.index;
+    for sibling in proof.siblings.iter() {
+        node = if idx % 2 == 0 {
+            hash_node::<H>(&node, sibling)
+        } else {
+            hash_node::<H>(sibling, &node)
+        };
+        idx /= 2;
+    }
+    hash_root::<H>(leaf_count, &node) == root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::sampler::BGGPublicKeySampler,
+        poly::dcrt::{DCRTPolyHashSampler, DCRTPolyParams},
+    };
+    use keccak_asm::Keccak256;
+
+    #[test]
+    fn test_pubkey_merkle_tree() {
+        // Create parameters for testing
+        let params = DCRTPolyParams::default();
+
+        // Sample 1 + 4 public keys
+        let key: [u8; 32] = rand::random();
+        let d = 3;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let tag: u64 = rand::random();
+        let tag_bytes = tag.to_le_bytes();
+        let pubkeys = bgg_sampler.sample(&params, &tag_bytes, &[true; 4]);
+
+        // Every key verifies against the root
+        let tree = PubKeyMerkleTree::<Keccak256>::new(&pubkeys);
+        assert_eq!(tree.fingerprints().len(), pubkeys.len());
+        let count = tree.leaf_count();
+        for (i, pubkey) in pubkeys.iter().enumerate() {
+            let proof = tree.proof(i);
+            assert!(verify_row::<Keccak256, _>(tree.root(), count, pubkey, &proof));
+        }
+
+        // A key at the wrong index or a modified key is rejected
+        let proof = tree.proof(1);
+        assert!(!verify_row::<Keccak256, _>(tree.root(), count, &pubkeys[2], &proof));
+        let tampered = pubkeys[1].clone() + &pubkeys[2];
+        assert!(!verify_row::<Keccak256, _>(tree.root(), count, &tampered, &proof));
+        assert!(!verify_row::<Keccak256, _>(tree.root(), count + 1, &pubkeys[1], &proof));
+    }
+
+    #[test]
+    fn test_pubkey_merkle_tree_rejects_out_of_range_index() {
+        let params = DCRTPolyParams::default();
+        let key: [u8; 32] = rand::random();
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, 2);
+        let pubkeys = bgg_sampler.sample(&params, b"merkle", &[true; 2]);
+
+        // With 3 keys the last one is paired with itself, so its path also hashes to the
+        // top node at index 3 and at any index with the same low bits
+        let tree = PubKeyMerkleTree::<Keccak256>::new(&pubkeys);
+        assert_eq!(tree.leaf_count(), 3);
+        let proof = tree.proof(2);
+        assert!(verify_row::<Keccak256, _>(tree.root(), 3, &pubkeys[2], &proof));
+        for index in [3, 6, 7, 10] {
+            let forged = MerkleProof { index, siblings: proof.siblings.clone() };
+            assert!(!verify_row::<Keccak256, _>(tree.root(), 3, &pubkeys[2], &forged));
+        }
+    }
+}
@@ -0,0 +1,642 @@
+//! Gate algorithms over BGG+ public keys and encodings, split into the key side, which only
+//! depends on the public keys, and the attribute side, which also depends on the attributes.
+//!
+//! Every method has the standard algorithm as its default, used by [`StandardGates`] and by the
+//! [`Evaluable`] implementations of [`BggPublicKey`] and [`BggEncoding`]. Another gate family
+//! overrides the methods it changes and is evaluated through [`Gated`], e.g. with
+//! [`PolyCircuit::eval_encodings_with`], without touching the callers of [`PolyCircuit::eval`].
+use super::{
+    circuit::{Evaluable, PolyCircuit},
+    public_key::PreparedOperand,
+    BggEncoding, BggPublicKey,
+};
+use crate::{
+    poly::{gadget::GadgetVector, Poly, PolyMatrix},
+    utils::debug_mem,
+};
+use num_bigint::BigUint;
+use std::{
+    fmt::Debug,
+    ops::{Add, Mul, Sub},
+};
+
+/// Gates as functions of the public keys of their inputs.
+pub trait KeySideEval<M: PolyMatrix> {
+    fn add_keys(&self, lhs: BggPublicKey<M>, rhs: &BggPublicKey<M>) -> BggPublicKey<M> {
+        let reveal_plaintext = lhs.reveal_plaintext & rhs.reveal_plaintext;
+        BggPublicKey { matrix: lhs.matrix + &rhs.matrix, reveal_plaintext }
+    }
+
+    fn sub_keys(&self, lhs: BggPublicKey<M>, rhs: &BggPublicKey<M>) -> BggPublicKey<M> {
+        let reveal_plaintext = lhs.reveal_plaintext & rhs.reveal_plaintext;
+        BggPublicKey { matrix: lhs.matrix - &rhs.matrix, reveal_plaintext }
+    }
+
+    fn rotate_key(
+        &self,
+        params: &<M::P as Poly>::Params,
+        key: &BggPublicKey<M>,
+        shift: usize,
+    ) -> BggPublicKey<M> {
+        debug_mem(format!("BGGPublicKey::rotate {:?}, {:?}", key.matrix.size(), shift));
+        let rotate_poly = <M::P>::const_rotate_poly(params, shift);
+        let matrix = key.matrix.clone() * rotate_poly;
+        debug_mem("BGGPublicKey::rotate matrix multiplied");
+        BggPublicKey { matrix, reveal_plaintext: key.reveal_plaintext }
+    }
+
+    /// The public key of the constant with coefficients `digits`, given the key of one.
+    fn const_key(
+        &self,
+        params: &<M::P as Poly>::Params,
+        one: &BggPublicKey<M>,
+        digits: &[u32],
+    ) -> BggPublicKey<M> {
+        debug_mem(format!("BGGPublicKey::from_digits {:?}, {:?}", one.matrix.size(), digits.len()));
+        let matrix = one.matrix.clone() * const_poly::<M>(params, digits);
+        debug_mem("BGGPublicKey::from_digits matrix multiplied");
+        BggPublicKey { matrix, reveal_plaintext: one.reveal_plaintext }
+    }
+
+    fn mul_const_key(
+        &self,
+        params: &<M::P as Poly>::Params,
+        key: &BggPublicKey<M>,
+        digits: &[u32],
+    ) -> BggPublicKey<M> {
+        let matrix = key.matrix.clone() * const_poly::<M>(params, digits);
+        BggPublicKey { matrix, reveal_plaintext: key.reveal_plaintext }
+    }
+
+    /// Computes `A * G^-1(scalar * G)`.
+    fn mul_scalar_key(
+        &self,
+        params: &<M::P as Poly>::Params,
+        key: &BggPublicKey<M>,
+        scalar: &BigUint,
+    ) -> BggPublicKey<M> {
+        let matrix = key.matrix.clone() * scalar_gadget::<M>(params, key.matrix.row_size(), scalar);
+        BggPublicKey { matrix, reveal_plaintext: key.reveal_plaintext }
+    }
+
+    /// Computes `P * G^-1(σ(A) * G^-1(Q))` for the automorphism `σ: X -> X^k`, given the keys
+    /// `[Q, P]` of its hints.
+    fn automorphism_key(
+        &self,
+        params: &<M::P as Poly>::Params,
+        key: &BggPublicKey<M>,
+        hint_keys: [&BggPublicKey<M>; 2],
+        k: usize,
+    ) -> BggPublicKey<M> {
+        let switched = key.matrix.automorphism(params, k) * hint_keys[0].matrix.decompose();
+        let matrix = hint_keys[1].matrix.clone() * switched.decompose();
+        BggPublicKey { matrix, reveal_plaintext: key.reveal_plaintext }
+    }
+
+    /// Prepares the public key of a right multiplication input, shared by every multiplication
+    /// with it.
+    fn prepare_key(&self, key: &BggPublicKey<M>) -> PreparedOperand<M> {
+        PreparedOperand::new(key)
+    }
+
+    /// Computes `lhs * G^-1(rhs)` given `prepared = self.prepare_key(rhs)`.
+    fn mul_keys(
+        &self,
+        lhs: BggPublicKey<M>,
+        rhs: &BggPublicKey<M>,
+        prepared: &PreparedOperand<M>,
+    ) -> BggPublicKey<M> {
+        let matrix = prepared.left_mul(&lhs.matrix);
+        debug_mem("BGGPublicKey::mul matrix multiplied");
+        let reveal_plaintext = lhs.reveal_plaintext & rhs.reveal_plaintext;
+        BggPublicKey { matrix, reveal_plaintext }
+    }
+}
+
+/// Gates as functions of the encodings of their inputs, i.e. of the public keys and the
+/// attributes. The public keys of the outputs are those computed on the key side.
+pub trait AttrSideEval<M: PolyMatrix>: KeySideEval<M> {
+    fn add_encodings(&self, lhs: BggEncoding<M>, rhs: &BggEncoding<M>) -> BggEncoding<M> {
+        let vector = lhs.vector + &rhs.vector;
+        let pubkey = self.add_keys(lhs.pubkey, &rhs.pubkey);
+        let plaintext = match (lhs.plaintext, rhs.plaintext.as_ref()) {
+            (Some(a), Some(b)) => Some(a + b),
+            _ => None,
+        };
+        BggEncoding { vector, pubkey, plaintext }
+    }
+
+    fn sub_encodings(&self, lhs: BggEncoding<M>, rhs: &BggEncoding<M>) -> BggEncoding<M> {
+        let vector = lhs.vector - &rhs.vector;
+        let pubkey = self.sub_keys(lhs.pubkey, &rhs.pubkey);
+        let plaintext = match (lhs.plaintext, rhs.plaintext.as_ref()) {
+            (Some(a), Some(b)) => Some(a - b),
+            _ => None,
+        };
+        BggEncoding { vector, pubkey, plaintext }
+    }
+
+    fn rotate_encoding(
+        &self,
+        params: &<M::P as Poly>::Params,
+        encoding: &BggEncoding<M>,
+        shift: usize,
+    ) -> BggEncoding<M> {
+        let rotate_poly = <M::P>::const_rotate_poly(params, shift);
+        let vector = encoding.vector.clone() * &rotate_poly;
+        let pubkey = self.rotate_key(params, &encoding.pubkey, shift);
+        let plaintext = encoding.plaintext.clone().map(|plaintext| plaintext * rotate_poly);
+        BggEncoding { vector, pubkey, plaintext }
+    }
+
+    /// The encoding of the constant with coefficients `digits`, given the encoding of one.
+    fn const_encoding(
+        &self,
+        params: &<M::P as Poly>::Params,
+        one: &BggEncoding<M>,
+        digits: &[u32],
+    ) -> BggEncoding<M> {
+        let const_poly = const_poly::<M>(params, digits);
+        let vector = one.vector.clone() * &const_poly;
+        let pubkey = self.const_key(params, &one.pubkey, digits);
+        let plaintext = one.plaintext.clone().map(|plaintext| plaintext * const_poly);
+        BggEncoding { vector, pubkey, plaintext }
+    }
+
+    /// Multiplies the vector by the constant directly, so the error grows by the norm of the
+    /// constant rather than by a gadget decomposition.
+    fn mul_const_encoding(
+        &self,
+        params: &<M::P as Poly>::Params,
+        encoding: &BggEncoding<M>,
+        digits: &[u32],
+    ) -> BggEncoding<M> {
+        let const_poly = const_poly::<M>(params, digits);
+        let vector = encoding.vector.clone() * &const_poly;
+        let pubkey = self.mul_const_key(params, &encoding.pubkey, digits);
+        let plaintext = encoding.plaintext.clone().map(|plaintext| plaintext * const_poly);
+        BggEncoding { vector, pubkey, plaintext }
+    }
+
+    /// Multiplies the vector by `G^-1(scalar * G)`, so the error grows by the gadget dimension
+    /// however large the scalar is.
+    fn mul_scalar_encoding(
+        &self,
+        params: &<M::P as Poly>::Params,
+        encoding: &BggEncoding<M>,
+        scalar: &BigUint,
+    ) -> BggEncoding<M> {
+        let rows = encoding.pubkey.matrix.row_size();
+        let vector = encoding.vector.clone() * scalar_gadget::<M>(params, rows, scalar);
+        let pubkey = self.mul_scalar_key(params, &encoding.pubkey, scalar);
+        let plaintext = encoding
+            .plaintext
+            .as_ref()
+            .map(|plaintext| <M::P as Evaluable>::mul_scalar(plaintext, params, scalar));
+        BggEncoding { vector, pubkey, plaintext }
+    }
+
+    /// Computes the encoding of `σ(x)` for the automorphism `σ: X -> X^k` from the encoding
+    /// `one` of the constant one and the hints `[h_0, h_1]` under the keys `[Q, P]`, as
+    /// `σ(c) * G^-1(Q) + σ(x) * (c_one - h_0) + h_1 * G^-1(σ(A) * G^-1(Q))`. The plaintext of
+    /// `encoding` must be known.
+    fn automorphism_encoding(
+        &self,
+        params: &<M::P as Poly>::Params,
+        one: &BggEncoding<M>,
+        encoding: &BggEncoding<M>,
+        hints: [&BggEncoding<M>; 2],
+        k: usize,
+    ) -> BggEncoding<M> {
+        let Some(plaintext) = &encoding.plaintext else {
+            panic!("Unknown plaintext for the input of an automorphism");
+        };
+        let plaintext = plaintext.automorphism(params, k);
+        let q_digits = hints[0].pubkey.matrix.decompose();
+        let switched = encoding.pubkey.matrix.automorphism(params, k) * &q_digits;
+        let vector = encoding.vector.automorphism(params, k) * q_digits +
+            (one.vector.clone() - &hints[0].vector) * &plaintext +
+            hints[1].vector.clone() * switched.decompose();
+        let hint_keys = [&hints[0].pubkey, &hints[1].pubkey];
+        let pubkey = self.automorphism_key(params, &encoding.pubkey, hint_keys, k);
+        BggEncoding { vector, pubkey, plaintext: Some(plaintext) }
+    }
+
+    /// Computes the encoding of `lhs * rhs` given `prepared = self.prepare_key(&rhs.pubkey)`.
+    /// The plaintext of `lhs` must be known.
+    fn mul_encodings(
+        &self,
+        lhs: BggEncoding<M>,
+        rhs: &BggEncoding<M>,
+        prepared: &PreparedOperand<M>,
+    ) -> BggEncoding<M> {
+        let Some(lhs_plaintext) = lhs.plaintext else {
+            panic!("Unknown plaintext for the left-hand input of multiplication");
+        };
+        let first_term = prepared.left_mul(&lhs.vector);
+        let second_term = rhs.vector.clone() * &lhs_plaintext;
+        let plaintext = rhs.plaintext.as_ref().map(|b| lhs_plaintext * b);
+        let pubkey = self.mul_keys(lhs.pubkey, &rhs.pubkey, prepared);
+        BggEncoding { vector: first_term + second_term, pubkey, plaintext }
+    }
+}
+
+/// A gate family that can evaluate circuits over both public keys and encodings.
+pub trait GateSet<M: PolyMatrix>: AttrSideEval<M> + Debug + Clone + Send + Sync {}
+
+impl<M: PolyMatrix, G: AttrSideEval<M> + Debug + Clone + Send + Sync> GateSet<M> for G {}
+
+/// The standard BGG+ gates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StandardGates;
+
+impl<M: PolyMatrix> KeySideEval<M> for StandardGates {}
+
+impl<M: PolyMatrix> AttrSideEval<M> for StandardGates {}
+
+/// The standard gates for public keys and encodings built from the gadget matrix of `G`
+/// instead of the powers of the base. Only the decompositions of multiplication inputs and of
+/// scalars depend on the gadget, which are always computed whole. Automorphisms need a gadget
+/// they fix, so they are only supported for the powers of the base.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GadgetGates<G>(pub G);
+
+impl<M: PolyMatrix, G: GadgetVector<M>> KeySideEval<M> for GadgetGates<G> {
+    fn mul_scalar_key(
+        &self,
+        params: &<M::P as Poly>::Params,
+        key: &BggPublicKey<M>,
+        scalar: &BigUint,
+    ) -> BggPublicKey<M> {
+        let scalar = <M::P as Evaluable>::mul_scalar(&<M::P>::const_one(params), params, scalar);
+        let scaled = self.0.matrix(params, key.matrix.row_size()) * scalar;
+        let matrix = key.matrix.clone() * self.0.decompose(&scaled);
+        BggPublicKey { matrix, reveal_plaintext: key.reveal_plaintext }
+    }
+
+    fn prepare_key(&self, key: &BggPublicKey<M>) -> PreparedOperand<M> {
+        PreparedOperand::Decomposed(self.0.decompose(&key.matrix))
+    }
+}
+
+impl<M: PolyMatrix, G: GadgetVector<M>> AttrSideEval<M> for GadgetGates<G> {
+    fn mul_scalar_encoding(
+        &self,
+        params: &<M::P as Poly>::Params,
+        encoding: &BggEncoding<M>,
+        scalar: &BigUint,
+    ) -> BggEncoding<M> {
+        let scalar_poly =
+            <M::P as Evaluable>::mul_scalar(&<M::P>::const_one(params), params, scalar);
+        let scaled = self.0.matrix(params, encoding.pubkey.matrix.row_size()) * &scalar_poly;
+        let vector = encoding.vector.clone() * self.0.decompose(&scaled);
+        let pubkey = self.mul_scalar_key(params, &encoding.pubkey, scalar);
+        let plaintext = encoding.plaintext.clone().map(|plaintext| plaintext * scalar_poly);
+        BggEncoding { vector, pubkey, plaintext }
+    }
+}
+
+fn const_poly<M: PolyMatrix>(params: &<M::P as Poly>::Params, digits: &[u32]) -> M::P {
+    <M::P as Evaluable>::from_digits(params, &<M::P>::const_one(params), digits)
+}
+
+/// `G^-1(scalar * G)` for the gadget matrix `G` with `rows` rows.
+fn scalar_gadget<M: PolyMatrix>(
+    params: &<M::P as Poly>::Params,
+    rows: usize,
+    scalar: &BigUint,
+) -> M {
+    let scalar = <M::P as Evaluable>::mul_scalar(&<M::P>::const_one(params), params, scalar);
+    (M::gadget_matrix(params, rows) * scalar).decompose()
+}
+
+/// A public key or an encoding evaluated with the gates of `G`.
+#[derive(Debug, Clone)]
+pub struct Gated<G, T> {
+    pub gates: G,
+    pub value: T,
+}
+
+impl<G, T> Gated<G, T> {
+    fn wrap(gates: &G, values: &[T]) -> Vec<Self>
+    where
+        G: Clone,
+        T: Clone,
+    {
+        values.iter().map(|value| Self { gates: gates.clone(), value: value.clone() }).collect()
+    }
+}
+
+macro_rules! impl_gated_ops {
+    ($value:ident, $bound:ident, $add:ident, $sub:ident, $mul:ident, $rhs:ident => $key:expr) => {
+        impl<M: PolyMatrix, G: $bound<M>> Add for Gated<G, $value<M>> {
+            type Output = Self;
+            fn add(self, other: Self) -> Self {
+                self + &other
+            }
+        }
+
+        impl<M: PolyMatrix, G: $bound<M>> Add<&Self> for Gated<G, $value<M>> {
+            type Output = Self;
+            fn add(self, other: &Self) -> Self {
+                let value = self.gates.$add(self.value, &other.value);
+                Self { gates: self.gates, value }
+            }
+        }
+
+        impl<M: PolyMatrix, G: $bound<M>> Sub for Gated<G, $value<M>> {
+            type Output = Self;
+            fn sub(self, other: Self) -> Self {
+                self - &other
+            }
+        }
+
+        impl<M: PolyMatrix, G: $bound<M>> Sub<&Self> for Gated<G, $value<M>> {
+            type Output = Self;
+            fn sub(self, other: &Self) -> Self {
+                let value = self.gates.$sub(self.value, &other.value);
+                Self { gates: self.gates, value }
+            }
+        }
+
+        impl<M: PolyMatrix, G: $bound<M>> Mul for Gated<G, $value<M>> {
+            type Output = Self;
+            fn mul(self, other: Self) -> Self {
+                self * &other
+            }
+        }
+
+        impl<M: PolyMatrix, G: $bound<M>> Mul<&Self> for Gated<G, $value<M>> {
+            type Output = Self;
+            fn mul(self, other: &Self) -> Self {
+                let $rhs = &other.value;
+                let prepared = self.gates.prepare_key($key);
+                let value = self.gates.$mul(self.value, &other.value, &prepared);
+                Self { gates: self.gates, value }
+            }
+        }
+    };
+}
+
+impl_gated_ops!(BggPublicKey, KeySideEval, add_keys, sub_keys, mul_keys, key => key);
+impl_gated_ops!(
+    BggEncoding,
+    AttrSideEval,
+    add_encodings,
+    sub_encodings,
+    mul_encodings,
+    encoding => &encoding.pubkey
+);
+
+impl<M, G> Evaluable for Gated<G, BggPublicKey<M>>
+where
+    M: PolyMatrix,
+    G: KeySideEval<M> + Debug + Clone + Send + Sync,
+{
+    type Params = <M::P as Poly>::Params;
+    type Prepared = PreparedOperand<M>;
+
+    fn rotate(&self, params: &Self::Params, shift: usize) -> Self {
+        let value = self.gates.rotate_key(params, &self.value, shift);
+        Self { gates: self.gates.clone(), value }
+    }
+
+    fn from_digits(params: &Self::Params, one: &Self, digits: &[u32]) -> Self {
+        let value = one.gates.const_key(params, &one.value, digits);
+        Self { gates: one.gates.clone(), value }
+    }
+
+    fn mul_const(&self, params: &Self::Params, digits: &[u32]) -> Self {
+        let value = self.gates.mul_const_key(params, &self.value, digits);
+        Self { gates: self.gates.clone(), value }
+    }
+
+    fn mul_scalar(&self, params: &Self::Params, scalar: &BigUint) -> Self {
+        let value = self.gates.mul_scalar_key(params, &self.value, scalar);
+        Self { gates: self.gates.clone(), value }
+    }
+
+    fn apply_automorphism(
+        &self,
+        params: &Self::Params,
+        _: &Self,
+        hints: [&Self; 2],
+        k: usize,
+    ) -> Self {
+        let hint_keys = [&hints[0].value, &hints[1].value];
+        let value = self.gates.automorphism_key(params, &self.value, hint_keys, k);
+        Self { gates: self.gates.clone(), value }
+    }
+
+    fn prepare(&self, _: &Self::Params) -> Self::Prepared {
+        self.gates.prepare_key(&self.value)
+    }
+
+    fn mul_prepared(self, other: &Self, prepared: &Self::Prepared) -> Self {
+        let value = self.gates.mul_keys(self.value, &other.value, prepared);
+        Self { gates: self.gates, value }
+    }
+}
+
+impl<M, G> Evaluable for Gated<G, BggEncoding<M>>
+where
+    M: PolyMatrix,
+    G: GateSet<M>,
+{
+    type Params = <M::P as Poly>::Params;
+    type Prepared = PreparedOperand<M>;
+
+    fn rotate(&self, params: &Self::Params, shift: usize) -> Self {
+        let value = self.gates.rotate_encoding(params, &self.value, shift);
+        Self { gates: self.gates.clone(), value }
+    }
+
+    fn from_digits(params: &Self::Params, one: &Self, digits: &[u32]) -> Self {
+        let value = one.gates.const_encoding(params, &one.value, digits);
+        Self { gates: one.gates.clone(), value }
+    }
+
+    fn mul_const(&self, params: &Self::Params, digits: &[u32]) -> Self {
+        let value = self.gates.mul_const_encoding(params, &self.value, digits);
+        Self { gates: self.gates.clone(), value }
+    }
+
+    fn mul_scalar(&self, params: &Self::Params, scalar: &BigUint) -> Self {
+        let value = self.gates.mul_scalar_encoding(params, &self.value, scalar);
+        Self { gates: self.gates.clone(), value }
+    }
+
+    fn apply_automorphism(
+        &self,
+        params: &Self::Params,
+        one: &Self,
+        hints: [&Self; 2],
+        k: usize,
+    ) -> Self {
+        let hints = [&hints[0].value, &hints[1].value];
+        let value = self.gates.automorphism_encoding(params, &one.value, &self.value, hints, k);
+        Self { gates: self.gates.clone(), value }
+    }
+
+    fn prepare(&self, _: &Self::Params) -> Self::Prepared {
+        self.gates.prepare_key(&self.value.pubkey)
+    }
+
+    fn mul_prepared(self, other: &Self, prepared: &Self::Prepared) -> Self {
+        let value = self.gates.mul_encodings(self.value, &other.value, prepared);
+        Self { gates: self.gates, value }
+    }
+}
+
+impl PolyCircuit {
+    /// Evaluates the circuit over public keys with the key-side gates of `gates`.
+    pub fn eval_keys_with<M, G>(
+        &self,
+        params: &<M::P as Poly>::Params,
+        gates: &G,
+        one: &BggPublicKey<M>,
+        inputs: &[BggPublicKey<M>],
+    ) -> Vec<BggPublicKey<M>>
+    where
+        M: PolyMatrix,
+        G: KeySideEval<M> + Debug + Clone + Send + Sync,
+    {
+        let one = Gated { gates: gates.clone(), value: one.clone() };
+        let outputs = self.eval(params, &one, &Gated::wrap(gates, inputs));
+        outputs.into_iter().map(|output| output.value).collect()
+    }
+
+    /// Evaluates the circuit over encodings with the gates of `gates`.
+    pub fn eval_encodings_with<M: PolyMatrix, G: GateSet<M>>(
+        &self,
+        params: &<M::P as Poly>::Params,
+        gates: &G,
+        one: &BggEncoding<M>,
+        inputs: &[BggEncoding<M>],
+    ) -> Vec<BggEncoding<M>> {
+        let one = Gated { gates: gates.clone(), value: one.clone() };
+        let outputs = self.eval(params, &one, &Gated::wrap(gates, inputs));
+        outputs.into_iter().map(|output| output.value).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::sampler::{BGGEncodingSampler, BGGPublicKeySampler},
+        poly::{
+            dcrt::{DCRTPolyHashSampler, DCRTPolyMatrix, DCRTPolyParams, DCRTPolyUniformSampler},
+            gadget::TwistedGadget,
+        },
+        utils::{create_bit_random_poly, create_random_poly},
+    };
+    use keccak_asm::Keccak256;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    /// Decomposes right multiplication inputs one column at a time and counts them and the
+    /// multiplied keys.
+    #[derive(Debug, Clone, Default)]
+    struct ColumnGates {
+        prepared: Arc<AtomicUsize>,
+        multiplied: Arc<AtomicUsize>,
+    }
+
+    impl<M: PolyMatrix> KeySideEval<M> for ColumnGates {
+        fn prepare_key(&self, key: &BggPublicKey<M>) -> PreparedOperand<M> {
+            self.prepared.fetch_add(1, Ordering::Relaxed);
+            PreparedOperand::blocked(key, 1)
+        }
+
+        fn mul_keys(
+            &self,
+            lhs: BggPublicKey<M>,
+            rhs: &BggPublicKey<M>,
+            prepared: &PreparedOperand<M>,
+        ) -> BggPublicKey<M> {
+            self.multiplied.fetch_add(1, Ordering::Relaxed);
+            let matrix = prepared.left_mul(&lhs.matrix);
+            BggPublicKey { matrix, reveal_plaintext: lhs.reveal_plaintext & rhs.reveal_plaintext }
+        }
+    }
+
+    impl<M: PolyMatrix> AttrSideEval<M> for ColumnGates {}
+
+    #[test]
+    fn test_eval_with_gate_set() {
+        let params = DCRTPolyParams::default();
+        let key: [u8; 32] = rand::random();
+        let d = 2;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let pubkeys = bgg_sampler.sample(&params, b"gates", &[true, true]);
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let plaintexts = vec![create_random_poly(&params), create_random_poly(&params)];
+        let encodings = bgg_sampler.sample(&params, &pubkeys, &plaintexts);
+
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        let mul_gate = circuit.mul_gate(inputs[0], inputs[1]);
+        let rotate_gate = circuit.rotate_gate(mul_gate, 1);
+        let sub_gate = circuit.sub_gate(rotate_gate, inputs[1]);
+        circuit.output(vec![sub_gate]);
+
+        // The standard gates agree with the plain evaluation
+        let expected_keys = circuit.eval(&params, &pubkeys[0], &pubkeys[1..]);
+        let keys = circuit.eval_keys_with(&params, &StandardGates, &pubkeys[0], &pubkeys[1..]);
+        assert_eq!(keys, expected_keys);
+        let expected = circuit.eval(&params, &encodings[0], &encodings[1..]);
+        let outputs =
+            circuit.eval_encodings_with(&params, &StandardGates, &encodings[0], &encodings[1..]);
+        assert_eq!(outputs, expected);
+
+        // Another family plugs in its own multiplication operands with the same results
+        let gates = ColumnGates::default();
+        let keys = circuit.eval_keys_with(&params, &gates, &pubkeys[0], &pubkeys[1..]);
+        assert_eq!(keys, expected_keys);
+        let outputs = circuit.eval_encodings_with(&params, &gates, &encodings[0], &encodings[1..]);
+        assert_eq!(outputs, expected);
+        assert_eq!(gates.prepared.load(Ordering::Relaxed), 2);
+        // The keys of encodings are derived on the key side
+        assert_eq!(gates.multiplied.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_eval_with_twisted_gadget() {
+        let params = DCRTPolyParams::default();
+        let key: [u8; 32] = rand::random();
+        let d = 2;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let pubkeys = bgg_sampler.sample(&params, b"twisted", &[true, true]);
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let plaintexts = vec![create_random_poly(&params), create_random_poly(&params)];
+
+        // Encodings s * (A - x * X * G) under the gadget twisted by X
+        let gadget = TwistedGadget::monomial(&params, 1);
+        let encodings = bgg_sampler.sample_with_gadget(&params, &pubkeys, &plaintexts, &gadget);
+
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        let mul_gate = circuit.mul_gate(inputs[0], inputs[1]);
+        let add_gate = circuit.add_gate(mul_gate, inputs[0]);
+        circuit.output(vec![add_gate]);
+
+        // The outputs are encodings of the product under the same gadget
+        let gates = GadgetGates(gadget.clone());
+        let keys = circuit.eval_keys_with(&params, &gates, &pubkeys[0], &pubkeys[1..]);
+        let output =
+            circuit.eval_encodings_with(&params, &gates, &encodings[0], &encodings[1..]).remove(0);
+        let expected_plaintext = plaintexts[0].clone() * &plaintexts[1] + &plaintexts[0];
+        assert_eq!(output.plaintext, Some(expected_plaintext.clone()));
+        assert_eq!(output.pubkey, keys[0]);
+        let g: DCRTPolyMatrix = gadget.matrix(&params, d + 1);
+        let secret_vec = bgg_sampler.secret_vec;
+        assert_eq!(output.vector, secret_vec * (keys[0].matrix.clone() - g * expected_plaintext));
+    }
+}
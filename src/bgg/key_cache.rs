@@ -0,0 +1,188 @@
+//! A persistent cache of key-side evaluations, i.e. the output public keys of a circuit
+//! evaluated over public keys without any attribute. They only depend on the circuit and the
+//! public keys, so every evaluation of the same policy under the same keys can reuse them.
+use super::{
+    circuit::{serde::SerializablePolyCircuit, PolyCircuit},
+    eval_key::{read_header, read_matrix, read_u64, write_header, write_matrix, write_u64},
+    fingerprint::pubkey_fingerprint,
+    AttributeKeys, BggPublicKey,
+};
+use crate::{
+    migrate::KEY_CACHE_VERSION,
+    poly::{plaintext::modulus_biguint, Poly, PolyMatrix, PolyParams},
+};
+use digest::Digest;
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+pub(crate) const KEY_CACHE_MAGIC: &[u8; 4] = b"DIOP";
+
+/// Hashes the serialized circuit, including its sub-circuits.
+pub fn circuit_digest<H: Digest>(circuit: &PolyCircuit) -> Vec<u8> {
+    let json = SerializablePolyCircuit::from_circuit(circuit).to_json_str();
+    H::digest(json.as_bytes()).to_vec()
+}
+
+/// The cache key of `circuit` evaluated over `pubkeys` as a hex string, hashing the ring
+/// dimension, the modulus and base of `params`, the circuit digest and the fingerprint of every
+/// public key.
+pub fn cache_key<H: Digest, M: PolyMatrix>(
+    params: &<M::P as Poly>::Params,
+    circuit: &PolyCircuit,
+    pubkeys: &AttributeKeys<M>,
+) -> String {
+    let mut hasher = H::new();
+    let modulus = modulus_biguint::<M::P>(params).to_bytes_le();
+    hasher.update((params.ring_dimension() as u64).to_le_bytes());
+    hasher.update((modulus.len() as u64).to_le_bytes());
+    hasher.update(&modulus);
+    hasher.update((params.base_bits() as u64).to_le_bytes());
+    hasher.update(circuit_digest::<H>(circuit));
+    hasher.update((pubkeys.slots().len() as u64).to_le_bytes());
+    for pubkey in pubkeys.slots() {
+        hasher.update(pubkey_fingerprint::<H, M>(pubkey));
+    }
+    hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Stores the output public keys of key-side evaluations as one file per cache key in a
+/// directory.
+#[derive(Debug, Clone)]
+pub struct KeySideCache {
+    dir: PathBuf,
+}
+
+impl KeySideCache {
+    /// Uses `dir`, which is created on the first write.
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self { dir: dir.as_ref().to_path_buf() }
+    }
+
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.keys", key))
+    }
+
+    /// Returns the outputs of `circuit` evaluated over `pubkeys`, reading them from the cache or
+    /// evaluating and storing them. Fails if the circuit does not take one input per attribute.
+    pub fn get_or_eval<H: Digest, M: PolyMatrix>(
+        &self,
+        params: &<M::P as Poly>::Params,
+        circuit: &PolyCircuit,
+        pubkeys: &AttributeKeys<M>,
+    ) -> io::Result<Vec<BggPublicKey<M>>> {
+        if circuit.num_input() != pubkeys.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("circuit takes {} inputs, got {} keys", circuit.num_input(), pubkeys.len()),
+            ));
+        }
+        let path = self.path(&cache_key::<H, M>(params, circuit, pubkeys));
+        match File::open(&path) {
+            Ok(file) => return read_pubkeys(&mut BufReader::new(file), params),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+        let outputs = pubkeys.eval(params, circuit);
+        fs::create_dir_all(&self.dir)?;
+        // Write to a temporary file first so that concurrent readers never see a partial file
+        let tmp_path = path.with_extension(format!("tmp{}", std::process::id()));
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        write_pubkeys(&mut writer, &outputs)?;
+        writer.into_inner().map_err(|err| err.into_error())?.sync_all()?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(outputs)
+    }
+}
+
+/// Writes the magic `DIOP`, the format version as a `u32` and the number of keys as a `u64`,
+/// followed by the reveal flag and the matrix of every key.
+pub fn write_pubkeys<W: Write, M: PolyMatrix>(
+    writer: &mut W,
+    pubkeys: &[BggPublicKey<M>],
+) -> io::Result<()> {
+    write_header(writer, KEY_CACHE_MAGIC, KEY_CACHE_VERSION)?;
+    write_u64(writer, pubkeys.len() as u64)?;
+    for pubkey in pubkeys {
+        writer.write_all(&[pubkey.reveal_plaintext as u8])?;
+        write_matrix(writer, &pubkey.matrix)?;
+    }
+    writer.flush()
+}
+
+/// Reads public keys written by [`write_pubkeys`].
+pub fn read_pubkeys<R: Read, M: PolyMatrix>(
+    reader: &mut R,
+    params: &<M::P as Poly>::Params,
+) -> io::Result<Vec<BggPublicKey<M>>> {
+    read_header(reader, KEY_CACHE_MAGIC, KEY_CACHE_VERSION)?;
+    let len = read_u64(reader)? as usize;
+    (0..len)
+        .map(|_| {
+            let mut reveal = [0u8; 1];
+            reader.read_exact(&mut reveal)?;
+            Ok(BggPublicKey::new(read_matrix(reader, params)?, reveal[0] != 0))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::sampler::BGGPublicKeySampler,
+        poly::dcrt::{DCRTPolyHashSampler, DCRTPolyMatrix, DCRTPolyParams},
+    };
+    use keccak_asm::Keccak256;
+
+    #[test]
+    fn test_key_side_cache() {
+        let params = DCRTPolyParams::default();
+        let key: [u8; 32] = rand::random();
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, 2);
+        let pubkeys = bgg_sampler.sample_attributes(&params, b"key cache", &[true, true]);
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        let mul_gate = circuit.mul_gate(inputs[0], inputs[1]);
+        circuit.output(vec![mul_gate]);
+        let dir = std::env::temp_dir().join(format!("diamond-key-cache-{}", rand::random::<u64>()));
+        let cache = KeySideCache::new(&dir);
+
+        // The first call evaluates and stores the outputs
+        let key = cache_key::<Keccak256, DCRTPolyMatrix>(&params, &circuit, &pubkeys);
+        assert!(!cache.path(&key).exists());
+        let outputs = cache.get_or_eval::<Keccak256, _>(&params, &circuit, &pubkeys).unwrap();
+        assert_eq!(outputs, pubkeys.eval(&params, &circuit));
+        assert!(cache.path(&key).exists());
+
+        // The second call reads them back
+        let cached = cache.get_or_eval::<Keccak256, _>(&params, &circuit, &pubkeys).unwrap();
+        assert_eq!(cached, outputs);
+
+        // Another circuit or other public keys have another key
+        let mut other = PolyCircuit::new();
+        let inputs = other.input(2);
+        let add_gate = other.add_gate(inputs[0], inputs[1]);
+        other.output(vec![add_gate]);
+        assert_ne!(cache_key::<Keccak256, DCRTPolyMatrix>(&params, &other, &pubkeys), key);
+        let other_pubkeys = bgg_sampler.sample_attributes(&params, b"other", &[true, true]);
+        assert_ne!(cache_key::<Keccak256, DCRTPolyMatrix>(&params, &circuit, &other_pubkeys), key);
+
+        // Other ring parameters have another key
+        for other_params in [DCRTPolyParams::new(8, 2, 17, 1), DCRTPolyParams::new(4, 3, 17, 1)] {
+            let other_key =
+                cache_key::<Keccak256, DCRTPolyMatrix>(&other_params, &circuit, &pubkeys);
+            assert_ne!(other_key, key);
+        }
+
+        // A circuit with another number of inputs is rejected
+        let mut wide = PolyCircuit::new();
+        let inputs = wide.input(3);
+        wide.output(inputs);
+        let err = cache.get_or_eval::<Keccak256, _>(&params, &wide, &pubkeys).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,130 @@
+//! Keyed hashes over encodings, so that ciphertexts relayed through untrusted storage can be
+//! checked before an expensive evaluation starts.
+//!
+//! The tag of a byte stream `m` under `key` is `H(1 || len || key || H(0 || len || key || m))`,
+//! where `len` is the length of the key as a `u64` little-endian. The outer hash has a fixed-size
+//! input, so the tag is not extendable even for Merkle-Damgård hashes.
+use super::{
+    associated_data::unix_now, encoding_stream::write_record, matrix_hasher::MatrixHasher,
+    EncodedAttributes,
+};
+use crate::poly::PolyMatrix;
+use digest::Digest;
+use std::io::{self, Read, Write};
+use subtle::ConstantTimeEq;
+
+const INNER_PREFIX: u8 = 0;
+const OUTER_PREFIX: u8 = 1;
+
+/// Computes the tag of the bytes written to it without buffering them.
+pub struct MacWriter<H: Digest> {
+    inner: MatrixHasher<H>,
+    key: Vec<u8>,
+}
+
+impl<H: Digest> MacWriter<H> {
+    pub fn new(key: &[u8]) -> Self {
+        let mut inner = MatrixHasher::<H>::new();
+        inner.update([INNER_PREFIX]);
+        inner.update((key.len() as u64).to_le_bytes());
+        inner.update(key);
+        Self { inner, key: key.to_vec() }
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        let mut outer = H::new();
+        outer.update([OUTER_PREFIX]);
+        outer.update((self.key.len() as u64).to_le_bytes());
+        outer.update(&self.key);
+        outer.update(self.inner.finalize());
+        outer.finalize().to_vec()
+    }
+}
+
+impl<H: Digest> Write for MacWriter<H> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Computes the tag of everything `reader` yields, e.g. a file written by
+/// [`crate::bgg::encoding_stream::write_encoding_stream`].
+pub fn mac_reader<H: Digest, R: Read>(key: &[u8], mut reader: R) -> io::Result<Vec<u8>> {
+    let mut writer = MacWriter::<H>::new(key);
+    io::copy(&mut reader, &mut writer)?;
+    Ok(writer.finalize())
+}
+
+/// Compares two tags in constant time.
+pub fn tags_eq(a: &[u8], b: &[u8]) -> bool {
+    bool::from(a.ct_eq(b))
+}
+
+impl<M: PolyMatrix> EncodedAttributes<M> {
+    /// The tag of the number of slots followed by the record of every slot, including the
+    /// constant one, in the layout of the encoding stream. With associated data the tag is keyed
+    /// by [`super::AssociatedData::bind_key`].
+    pub fn mac<H: Digest>(&self, key: &[u8]) -> Vec<u8> {
+        let mut writer = MacWriter::<H>::new(&self.bound_key::<H>(key));
+        let slots = std::iter::once(self.constant_one_row()).chain(self.attributes());
+        writer.inner.update((self.len() as u64 + 1).to_le_bytes());
+        for encoding in slots {
+            write_record(&mut writer, encoding).expect("hashing cannot fail");
+        }
+        writer.finalize()
+    }
+
+    /// Checks `tag` and that the associated data, if any, has not expired.
+    pub fn verify_mac<H: Digest>(&self, key: &[u8], tag: &[u8]) -> bool {
+        self.verify_mac_at::<H>(key, tag, unix_now())
+    }
+
+    /// Like [`Self::verify_mac`], checking the expiry against the Unix time `now`.
+    pub fn verify_mac_at<H: Digest>(&self, key: &[u8], tag: &[u8], now: u64) -> bool {
+        !self.is_expired(now) && tags_eq(&self.mac::<H>(key), tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bgg::encoding_stream::write_encoding_stream, utils::TestBggSetup};
+    use keccak_asm::Keccak256;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_encoded_attributes_mac() {
+        let (_, encodings) = TestBggSetup::new(2).sample(b"mac", &[true, false]);
+        let attributes = EncodedAttributes::from_slots(encodings.clone());
+
+        // The tag verifies under the same key only
+        let mac_key = b"relay key";
+        let tag = attributes.mac::<Keccak256>(mac_key);
+        assert!(attributes.verify_mac::<Keccak256>(mac_key, &tag));
+        assert!(!attributes.verify_mac::<Keccak256>(b"other key", &tag));
+        assert!(!attributes.verify_mac::<Keccak256>(mac_key, &tag[1..]));
+
+        // Swapping the vectors of two slots is detected
+        let mut tampered = encodings.clone();
+        let vector = tampered[1].vector.clone();
+        tampered[1].vector = tampered[2].vector.clone();
+        tampered[2].vector = vector;
+        let tampered = EncodedAttributes::from_slots(tampered);
+        assert!(!tampered.verify_mac::<Keccak256>(mac_key, &tag));
+
+        // A serialized stream is checked before it is parsed
+        let mut cursor = Cursor::new(Vec::new());
+        write_encoding_stream(&mut cursor, &encodings).unwrap();
+        let mut bytes = cursor.into_inner();
+        let tag = mac_reader::<Keccak256, _>(mac_key, bytes.as_slice()).unwrap();
+        assert!(tags_eq(&mac_reader::<Keccak256, _>(mac_key, bytes.as_slice()).unwrap(), &tag));
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert!(!tags_eq(&mac_reader::<Keccak256, _>(mac_key, bytes.as_slice()).unwrap(), &tag));
+    }
+}
@@ -0,0 +1,94 @@
+//! Incremental hashing of matrices, so that fingerprints and tags of public matrices of several
+//! gigabytes never hold more than one entry in serialized form.
+use super::eval_key::{write_poly, write_u64};
+use crate::poly::{Poly, PolyMatrix};
+use digest::Digest;
+use std::io::{self, Write};
+
+/// Streams matrices into a digest entry by entry, in the canonical layout of the serialized
+/// formats: the row and column counts as `u64` little-endian, then every entry in row-major
+/// order as its compact bytes prefixed by their length as a `u32`. A matrix thus hashes like the
+/// bytes [`super::eval_key`] writes for it.
+#[derive(Debug, Clone, Default)]
+pub struct MatrixHasher<H: Digest> {
+    hasher: H,
+}
+
+impl<H: Digest> MatrixHasher<H> {
+    pub fn new() -> Self {
+        Self { hasher: H::new() }
+    }
+
+    pub fn update(&mut self, data: impl AsRef<[u8]>) {
+        self.hasher.update(data);
+    }
+
+    pub fn update_poly<P: Poly>(&mut self, poly: &P) {
+        write_poly(self, poly).expect("hashing cannot fail");
+    }
+
+    /// Walks the rows of `matrix` one at a time, hashing every entry as soon as it is read.
+    pub fn update_matrix<M: PolyMatrix>(&mut self, matrix: &M) {
+        let (nrow, ncol) = matrix.size();
+        write_u64(self, nrow as u64).expect("hashing cannot fail");
+        write_u64(self, ncol as u64).expect("hashing cannot fail");
+        for i in 0..nrow {
+            for poly in matrix.get_row(i) {
+                self.update_poly(&poly);
+            }
+        }
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        self.hasher.finalize().to_vec()
+    }
+}
+
+impl<H: Digest> Write for MatrixHasher<H> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.hasher.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The digest of `matrix` alone, see [`MatrixHasher`].
+pub fn matrix_digest<H: Digest, M: PolyMatrix>(matrix: &M) -> Vec<u8> {
+    let mut hasher = MatrixHasher::<H>::new();
+    hasher.update_matrix(matrix);
+    hasher.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::eval_key::write_matrix,
+        poly::{
+            dcrt::{DCRTPolyMatrix, DCRTPolyParams, DCRTPolyUniformSampler},
+            sampler::{DistType, PolyUniformSampler},
+        },
+    };
+    use keccak_asm::Keccak256;
+
+    #[test]
+    fn test_matrix_hasher() {
+        let params = DCRTPolyParams::default();
+        let sampler = DCRTPolyUniformSampler::new();
+        let matrix = sampler.sample_uniform(&params, 3, 4, DistType::FinRingDist);
+
+        // The streamed digest equals the digest of the serialized matrix
+        let mut bytes = Vec::new();
+        write_matrix(&mut bytes, &matrix).unwrap();
+        assert_eq!(matrix_digest::<Keccak256, _>(&matrix), Keccak256::digest(&bytes).to_vec());
+
+        // The shape is hashed along with the entries
+        let row = matrix.slice_rows(0, 1);
+        let entries = (0..4).map(|j| vec![row.entry(0, j)]).collect::<Vec<_>>();
+        let column = DCRTPolyMatrix::from_poly_vec(&params, entries);
+        assert_ne!(matrix_digest::<Keccak256, _>(&row), matrix_digest::<Keccak256, _>(&column));
+    }
+}
@@ -1,11 +1,29 @@
+pub mod aggregate;
+pub mod associated_data;
 pub mod circuit;
 pub mod digits_to_int;
 pub mod encoding;
+pub mod encoding_stream;
+pub mod envelope;
+pub mod epoch;
+pub mod eval_key;
+pub mod fingerprint;
+pub mod gates;
+pub mod key_cache;
+pub mod mac;
+pub mod matrix_hasher;
 pub mod norm_simulator;
+pub mod packed;
 pub mod public_key;
+pub mod revocation;
 pub mod sampler;
+pub mod selftest;
+pub mod shard;
+pub mod slots;
 // pub mod serde;
 
+pub use associated_data::AssociatedData;
 pub use digits_to_int::DigitsToInt;
-pub use encoding::BggEncoding;
+pub use encoding::{BggEncoding, CompressedEncodings, EncodedAttributes};
 pub use public_key::BggPublicKey;
+pub use slots::{AttributeKeys, AttributeSlots};
@@ -51,6 +51,32 @@ impl NormBounds {
             .collect();
         Self { h_norms }
     }
+
+    /// The bit length of the largest coefficient of any output's norm polynomial.
+    pub fn max_bits(&self) -> u64 {
+        self.h_norms
+            .iter()
+            .flatten()
+            .map(|coeff| coeff.parse::<BigUint>().expect("invalid norm coefficient").bits())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The largest output error bound for fresh encoding errors of norm at most `fresh_error`
+    /// and public keys of `m` columns, i.e. every norm polynomial evaluated at `m`, times
+    /// `fresh_error`.
+    pub fn error_bound(&self, m: usize, fresh_error: &BigUint) -> BigUint {
+        let m = BigUint::from(m);
+        self.h_norms
+            .iter()
+            .map(|h_norm| {
+                h_norm.iter().rev().fold(BigUint::zero(), |acc, coeff| {
+                    acc * &m + coeff.parse::<BigUint>().expect("invalid norm coefficient")
+                }) * fresh_error
+            })
+            .max()
+            .unwrap_or_default()
+    }
 }
 
 // Note: h_norm and plaintext_norm computed here can be larger than the modulus `q`.
@@ -102,6 +128,7 @@ impl_binop_with_refs!(NormSimulator => Mul::mul(self, rhs: &NormSimulator) -> No
 
 impl Evaluable for NormSimulator {
     type Params = ();
+    type Prepared = ();
     fn rotate(&self, _: &Self::Params, _: usize) -> Self {
         self.clone()
     }
@@ -113,6 +140,42 @@ impl Evaluable for NormSimulator {
         let plaintext_norm = one.plaintext_norm.clone() * BigUint::from(*digit_max);
         Self { h_norm, plaintext_norm, dim_sqrt: one.dim_sqrt, base: one.base }
     }
+
+    fn mul_const(&self, _: &Self::Params, digits: &[u32]) -> Self {
+        // A product with a constant of digit norm `c` grows both norms by up to `c * sqrt(n)`
+        let digit_max = digits.iter().copied().max().unwrap_or(0);
+        let factor = BigUint::from(digit_max) * BigUint::from(self.dim_sqrt);
+        let h_norm = self.h_norm.clone() * factor.clone();
+        let plaintext_norm = self.plaintext_norm.clone() * factor;
+        Self { h_norm, plaintext_norm, dim_sqrt: self.dim_sqrt, base: self.base }
+    }
+
+    fn mul_scalar(&self, _: &Self::Params, scalar: &BigUint) -> Self {
+        // The error is multiplied by a decomposition like the left input of a multiplication
+        let h_norm = self.h_norm.right_rotate(self.dim_sqrt as u64 * (self.base as u64 - 1));
+        let plaintext_norm = &self.plaintext_norm * scalar;
+        Self { h_norm, plaintext_norm, dim_sqrt: self.dim_sqrt, base: self.base }
+    }
+
+    fn apply_automorphism(
+        &self,
+        _: &Self::Params,
+        one: &Self,
+        hints: [&Self; 2],
+        _: usize,
+    ) -> Self {
+        // Automorphisms keep norms, so the error is that of the input and the second hint times
+        // decompositions, plus the errors of one and the first hint times the plaintext
+        let scale = self.dim_sqrt as u64 * (self.base as u64 - 1);
+        let factor = &self.plaintext_norm * BigUint::from(self.dim_sqrt);
+        let h_norm = self.h_norm.right_rotate(scale) +
+            hints[1].h_norm.right_rotate(scale) +
+            (&one.h_norm + &hints[0].h_norm) * factor;
+        let plaintext_norm = self.plaintext_norm.clone();
+        Self { h_norm, plaintext_norm, dim_sqrt: self.dim_sqrt, base: self.base }
+    }
+
+    fn prepare(&self, _: &Self::Params) {}
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -237,6 +300,21 @@ mod tests {
         assert_eq!(result.dim_sqrt.pow(2), 16);
     }
 
+    #[test]
+    fn test_error_simulator_mul_const() {
+        let sim = create_test_error_simulator(16, vec![10u32], 5);
+
+        // Both norms grow by the largest digit times dim_sqrt
+        let result = sim.mul_const(&(), &[1, 3, 2]);
+        assert_eq!(result.h_norm.0[0], BigUint::from(120u32)); // 10 * 3 * 4
+        assert_eq!(result.plaintext_norm, BigUint::from(60u32)); // 5 * 3 * 4
+
+        // An empty constant is zero
+        let result = sim.mul_const(&(), &[]);
+        assert_eq!(result.h_norm.0[0], BigUint::ZERO);
+        assert_eq!(result.plaintext_norm, BigUint::ZERO);
+    }
+
     #[test]
     fn test_simulate_bgg_norm() {
         // Create a simple circuit: (input1 + input2) * input3
@@ -265,4 +343,16 @@ mod tests {
         assert_eq!(norms.h_norms.len(), 1);
         assert_eq!(norms, expected);
     }
+
+    #[test]
+    fn test_norm_bounds_error_bound() {
+        // The norm polynomials 3 + 2m and 1 + m^2 evaluated at m = 4
+        let simulators = [
+            create_test_error_simulator(16, vec![3, 2], 1),
+            create_test_error_simulator(16, vec![1, 0, 1], 1),
+        ];
+        let norms = NormBounds::from_norm_simulators(&simulators);
+        assert_eq!(norms.error_bound(4, &BigUint::one()), BigUint::from(17u32));
+        assert_eq!(norms.error_bound(4, &BigUint::from(5u32)), BigUint::from(85u32));
+    }
 }
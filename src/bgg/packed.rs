@@ -0,0 +1,274 @@
+//! Packs up to `n` binary attributes into the coefficients of one polynomial, so that a
+//! ciphertext of `ell` attributes has `ceil(ell / n)` attribute encodings instead of `ell`.
+//!
+//! Slot `i` of a packed row is the coefficient of `X^i`. Additions, subtractions, negations and
+//! multiplications by integer constants act slot by slot, and a rotation by `X^k` moves every
+//! slot `k` positions up as long as no slot is moved past `X^(n - 1)`, where the ring wraps it
+//! around with a flipped sign.
+//!
+//! The automorphisms `X -> X^k` move slot `i` to slot `i * k mod 2n`, negated if that is `n` or
+//! more. Over encodings they also switch the secret back with the hints of
+//! [`BGGEncodingSampler::sample_automorphism_hints`], which the circuit takes as extra inputs.
+//! Multiplying two packed rows convolves their slots instead, so ANDs unpack the slots first:
+//! the trace `z + σ(z)` over the automorphisms of [`unpack_automorphisms`] cancels every slot
+//! but the constant one, which is doubled at each step and scaled back by `n^-1 mod q`.
+//!
+//! [`BGGEncodingSampler::sample_automorphism_hints`]:
+//! crate::bgg::sampler::BGGEncodingSampler::sample_automorphism_hints
+use crate::{
+    bgg::circuit::PolyCircuit,
+    poly::{plaintext::modulus_biguint, Poly, PolyElem, PolyParams},
+};
+use num_bigint::BigUint;
+
+/// Packs `bits` into the first `bits.len()` coefficients of a polynomial.
+pub fn pack_bits<P: Poly>(params: &P::Params, bits: &[bool]) -> P {
+    let n = params.ring_dimension() as usize;
+    assert!(bits.len() <= n, "cannot pack {} bits into a ring of dimension {}", bits.len(), n);
+    let modulus = params.modulus();
+    let mut coeffs = vec![P::Elem::zero(&modulus); n];
+    for (coeff, &bit) in coeffs.iter_mut().zip(bits) {
+        if bit {
+            *coeff = P::Elem::one(&modulus);
+        }
+    }
+    P::from_coeffs(params, &coeffs)
+}
+
+/// Reads back the first `len` slots of a packed row, or `None` if one of them is not a bit.
+pub fn unpack_bits<P: Poly>(poly: &P, len: usize) -> Option<Vec<bool>> {
+    let coeffs = poly.coeffs();
+    let n = coeffs.len();
+    assert!(len <= n, "cannot unpack {} slots of a ring of dimension {}", len, n);
+    let one = BigUint::from(1u8);
+    coeffs[..len]
+        .iter()
+        .map(|coeff| match coeff.to_biguint() {
+            value if value == &BigUint::ZERO => Some(false),
+            value if value == &one => Some(true),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Packs `bits` into rows of `n` slots, the last row holding the remaining bits.
+pub fn pack_attributes<P: Poly>(params: &P::Params, bits: &[bool]) -> Vec<P> {
+    let n = params.ring_dimension() as usize;
+    bits.chunks(n).map(|chunk| pack_bits(params, chunk)).collect()
+}
+
+/// The automorphism indices `n + 1, n / 2 + 1, ..., 3` of the trace in
+/// [`PolyCircuit::packed_unpack_gate`], in the order it applies them.
+pub fn unpack_automorphisms(n: usize) -> Vec<usize> {
+    assert!(n >= 2 && n.is_power_of_two(), "ring dimension {} is not a power of two", n);
+    (0..n.trailing_zeros()).map(|i| (n >> i) + 1).collect()
+}
+
+impl PolyCircuit {
+    /// Computes the NOT of the first `len` slots of a packed row as `(1, ..., 1) - x`. Slots
+    /// past `len` become their negation.
+    pub fn packed_not_gate(&mut self, input: usize, len: usize) -> usize {
+        let ones = self.const_digits_poly(&vec![1; len]);
+        self.sub_gate(ones, input)
+    }
+
+    /// Moves slot `i` of a packed row to slot `i + shift`. The top `shift` slots must be unused,
+    /// since slots moved past the ring dimension wrap around differently on plaintexts and
+    /// encodings.
+    pub fn packed_shift_gate(&mut self, input: usize, shift: usize) -> usize {
+        self.rotate_gate(input, shift)
+    }
+
+    /// Adds the packed rows `inputs` slot by slot, e.g. to count how many of the attributes in
+    /// the same slot of several rows are set.
+    pub fn packed_sum_gate(&mut self, inputs: &[usize]) -> usize {
+        assert!(!inputs.is_empty(), "sum of no packed rows");
+        inputs[1..].iter().fold(inputs[0], |acc, &input| self.add_gate(acc, input))
+    }
+
+    /// Moves slot `i` of a packed row to slot `i * k mod 2n`, negated if that is `n` or more,
+    /// given the input gates `hints` of the hints of `k`.
+    pub fn packed_automorphism_gate(&mut self, input: usize, k: usize, hints: [usize; 2]) -> usize {
+        self.automorphism_gate(input, k, hints)
+    }
+
+    /// Extracts slot `slot` of a packed row as a constant polynomial, given the input gates
+    /// `hints` of the hints of every index of [`unpack_automorphisms`], in the same order.
+    pub fn packed_unpack_gate<P: Poly>(
+        &mut self,
+        params: &P::Params,
+        input: usize,
+        slot: usize,
+        hints: &[[usize; 2]],
+    ) -> usize {
+        let n = params.ring_dimension() as usize;
+        let indices = unpack_automorphisms(n);
+        assert!(slot < n, "slot {} out of range for ring dimension {}", slot, n);
+        assert_eq!(hints.len(), indices.len(), "one pair of hints per automorphism is required");
+        // The constant coefficient of X^(n - slot) * x is -x_slot
+        let mut acc = if slot == 0 {
+            input
+        } else {
+            let mut digits = vec![0; n - slot + 1];
+            digits[n - slot] = 1;
+            self.mul_const_gate(input, &digits)
+        };
+        for (&k, &hints) in indices.iter().zip(hints) {
+            let conjugate = self.automorphism_gate(acc, k, hints);
+            acc = self.add_gate(acc, conjugate);
+        }
+        let q = modulus_biguint::<P>(params);
+        let half = (&q + 1u8) / 2u8;
+        let n_inv = half.modpow(&BigUint::from(indices.len()), &q);
+        let scalar = if slot == 0 { n_inv } else { &q - n_inv };
+        self.mul_scalar_gate(acc, &scalar)
+    }
+
+    /// Computes the AND of slot `slot` of two packed rows as a constant polynomial, unpacking
+    /// both with [`Self::packed_unpack_gate`]. [`Self::packed_shift_gate`] moves it back to a
+    /// slot.
+    pub fn packed_and_gate<P: Poly>(
+        &mut self,
+        params: &P::Params,
+        left: usize,
+        right: usize,
+        slot: usize,
+        hints: &[[usize; 2]],
+    ) -> usize {
+        let left = self.packed_unpack_gate::<P>(params, left, slot, hints);
+        let right = self.packed_unpack_gate::<P>(params, right, slot, hints);
+        self.mul_gate(left, right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::{
+            circuit::Evaluable,
+            sampler::{BGGEncodingSampler, BGGPublicKeySampler},
+        },
+        poly::{
+            dcrt::{
+                DCRTPoly, DCRTPolyHashSampler, DCRTPolyMatrix, DCRTPolyParams,
+                DCRTPolyUniformSampler,
+            },
+            PolyMatrix,
+        },
+        utils::create_bit_random_poly,
+    };
+    use keccak_asm::Keccak256;
+
+    #[test]
+    fn test_pack_attributes() {
+        let params = DCRTPolyParams::default();
+        let n = params.ring_dimension() as usize;
+        let bits = (0..n + 3).map(|i| i % 3 == 0).collect::<Vec<_>>();
+
+        // n + 3 attributes fit in two rows
+        let rows = pack_attributes::<DCRTPoly>(&params, &bits);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(unpack_bits(&rows[0], n).unwrap(), bits[..n]);
+        assert_eq!(unpack_bits(&rows[1], 3).unwrap(), bits[n..]);
+        assert_eq!(unpack_bits(&(rows[0].clone() + &rows[0]), n), None);
+    }
+
+    #[test]
+    fn test_packed_gates() {
+        let params = DCRTPolyParams::default();
+        let len = 6;
+        let x = [true, false, true, true, false, false];
+        let y = [false, false, true, false, true, false];
+
+        // NOT(x) + (y shifted up by one slot), on plaintexts and on encodings
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        let not_x = circuit.packed_not_gate(inputs[0], len);
+        let shifted = circuit.packed_shift_gate(inputs[1], 1);
+        let sum = circuit.packed_sum_gate(&[not_x, shifted]);
+        circuit.output(vec![sum]);
+
+        let plaintexts = vec![pack_bits::<DCRTPoly>(&params, &x), pack_bits(&params, &y)];
+        let one = DCRTPoly::const_one(&params);
+        let output = circuit.eval(&params, &one, &plaintexts).remove(0);
+        let expected =
+            (0..len).map(|i| !x[i] as u32 + (i > 0 && y[i - 1]) as u32).collect::<Vec<_>>();
+        let expected = <DCRTPoly as Evaluable>::from_digits(&params, &one, &expected);
+        assert_eq!(output, expected);
+
+        let key: [u8; 32] = rand::random();
+        let d = 2;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let pubkeys = bgg_sampler.sample(&params, b"packed", &[true; 2]);
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let encodings = bgg_sampler.sample(&params, &pubkeys, &plaintexts);
+        let output = circuit.eval(&params, &encodings[0], &encodings[1..]).remove(0);
+        assert_eq!(output.plaintext, Some(expected));
+    }
+
+    #[test]
+    fn test_packed_automorphisms() {
+        let params = DCRTPolyParams::default();
+        let n = params.ring_dimension() as usize;
+        let x = [true, false, true, true];
+        let y = [false, true, true, true];
+        let indices = unpack_automorphisms(n);
+        let num_hints = 2 * (indices.len() + 1);
+
+        // AND of slot 2 of x and y, and x with its slots permuted by X -> X^(2n - 1)
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2 + num_hints);
+        let hints = inputs[2..].chunks(2).map(|pair| [pair[0], pair[1]]).collect::<Vec<_>>();
+        let and = circuit.packed_and_gate::<DCRTPoly>(
+            &params,
+            inputs[0],
+            inputs[1],
+            2,
+            &hints[..indices.len()],
+        );
+        let permuted = circuit.packed_automorphism_gate(inputs[0], 2 * n - 1, hints[indices.len()]);
+        circuit.output(vec![and, permuted]);
+
+        // The hints are ignored on plaintexts
+        let rows = vec![pack_bits::<DCRTPoly>(&params, &x), pack_bits(&params, &y)];
+        let zeros = vec![DCRTPoly::const_zero(&params); num_hints];
+        let one = DCRTPoly::const_one(&params);
+        let outputs = circuit.eval(&params, &one, &[rows.clone(), zeros].concat());
+        let expected = vec![
+            <DCRTPoly as Evaluable>::from_digits(&params, &one, &[(x[2] && y[2]) as u32]),
+            rows[0].automorphism(&params, 2 * n - 1),
+        ];
+        assert_eq!(outputs, expected);
+
+        let key: [u8; 32] = rand::random();
+        let d = 2;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let pubkeys = bgg_sampler.sample(&params, b"packed", &[true; 2]);
+        let hint_keys = bgg_sampler.sample(&params, b"hints", &vec![false; num_hints]);
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let mut encodings = bgg_sampler.sample(&params, &pubkeys, &rows);
+        let mut keys = pubkeys.clone();
+        for (pair, &k) in hint_keys[1..].chunks(2).zip(indices.iter().chain([&(2 * n - 1)])) {
+            let hints = [&pair[0], &pair[1]];
+            encodings.extend(bgg_sampler.sample_automorphism_hints(&params, &pubkeys[0], hints, k));
+            keys.extend_from_slice(pair);
+        }
+
+        // Without errors, the outputs are exact encodings of the expected plaintexts under the
+        // keys evaluated on the key side
+        let outputs = circuit.eval(&params, &encodings[0], &encodings[1..]);
+        let output_keys = circuit.eval(&params, &keys[0], &keys[1..]);
+        let gadget = DCRTPolyMatrix::gadget_matrix(&params, d + 1);
+        for ((output, key), expected) in outputs.into_iter().zip(output_keys).zip(expected) {
+            assert_eq!(output.pubkey, key);
+            assert_eq!(output.plaintext, Some(expected.clone()));
+            let secret_vec = bgg_sampler.secret_vec.clone();
+            assert_eq!(output.vector, secret_vec * (key.matrix - gadget.clone() * expected));
+        }
+    }
+}
@@ -1,8 +1,13 @@
-use super::circuit::Evaluable;
+use super::{
+    circuit::Evaluable,
+    digits_to_int::DigitsToInt,
+    gates::{KeySideEval, StandardGates},
+};
 use crate::{
     poly::{Poly, PolyMatrix},
-    utils::debug_mem,
+    utils::{debug_mem, mul_block_size},
 };
+use num_bigint::BigUint;
 use rayon::prelude::*;
 use std::ops::{Add, Mul, Sub};
 
@@ -21,6 +26,30 @@ impl<M: PolyMatrix> BggPublicKey<M> {
         self.matrix.concat_columns(&others.par_iter().map(|x| &x.matrix).collect::<Vec<_>>()[..])
     }
 
+    /// The public key `A * G^-1(u)` of [`super::BggEncoding::compress`].
+    pub fn compress(&self, params: &<M::P as Poly>::Params) -> Self {
+        self.power_of_base(params, 0)
+    }
+
+    /// Computes the public key of `Σ lhs[i] * rhs[i]` with a single matrix multiplication
+    /// `[A_1 | ... | A_k] * [G^-1(B_1); ...; G^-1(B_k)]`.
+    pub fn inner_product(lhs: &[Self], rhs: &[Self]) -> Self {
+        assert_eq!(lhs.len(), rhs.len(), "inner product inputs must have the same length");
+        assert!(!lhs.is_empty(), "inner product of empty inputs");
+        let lhs_matrix = lhs[0].concat_matrix(&lhs[1..]);
+        let decomposed = rhs.par_iter().map(|pk| pk.matrix.decompose()).collect::<Vec<_>>();
+        let rhs_matrix = decomposed[0].concat_rows(&decomposed[1..].iter().collect::<Vec<_>>());
+        let reveal_plaintext = lhs.iter().chain(rhs.iter()).all(|pk| pk.reveal_plaintext);
+        Self { matrix: lhs_matrix * rhs_matrix, reveal_plaintext }
+    }
+
+    /// Restricts the public keys of all attributes to the attributes at `indices`, keeping the
+    /// constant-one key in slot 0. The `k`-th attribute of the output is the `indices[k]`-th
+    /// attribute of the input, which is `pubkeys[1 + indices[k]]`.
+    pub fn project(pubkeys: &[Self], indices: &[usize]) -> Vec<Self> {
+        project_slots(pubkeys, indices)
+    }
+
     /// Writes the public key with id to files under the given directory.
     pub async fn write_to_files<P: AsRef<std::path::Path> + Send + Sync>(
         &self,
@@ -44,6 +73,21 @@ impl<M: PolyMatrix> BggPublicKey<M> {
     }
 }
 
+/// Selects slot 0 followed by the attribute slots at `indices` (shifted by one for slot 0).
+pub(crate) fn project_slots<T: Clone>(slots: &[T], indices: &[usize]) -> Vec<T> {
+    assert!(!slots.is_empty(), "the constant-one slot is missing");
+    let mut seen = vec![false; slots.len() - 1];
+    let mut projected = Vec::with_capacity(indices.len() + 1);
+    projected.push(slots[0].clone());
+    for &idx in indices {
+        assert!(idx < seen.len(), "attribute index {} out of range", idx);
+        assert!(!seen[idx], "attribute index {} is projected twice", idx);
+        seen[idx] = true;
+        projected.push(slots[idx + 1].clone());
+    }
+    projected
+}
+
 impl<M: PolyMatrix> Add for BggPublicKey<M> {
     type Output = Self;
     fn add(self, other: Self) -> Self {
@@ -54,8 +98,7 @@ impl<M: PolyMatrix> Add for BggPublicKey<M> {
 impl<M: PolyMatrix> Add<&Self> for BggPublicKey<M> {
     type Output = Self;
     fn add(self, other: &Self) -> Self {
-        let reveal_plaintext = self.reveal_plaintext & other.reveal_plaintext;
-        Self { matrix: self.matrix + &other.matrix, reveal_plaintext }
+        StandardGates.add_keys(self, other)
     }
 }
 
@@ -69,8 +112,7 @@ impl<M: PolyMatrix> Sub for BggPublicKey<M> {
 impl<M: PolyMatrix> Sub<&Self> for BggPublicKey<M> {
     type Output = Self;
     fn sub(self, other: &Self) -> Self {
-        let reveal_plaintext = self.reveal_plaintext & other.reveal_plaintext;
-        Self { matrix: self.matrix - &other.matrix, reveal_plaintext }
+        StandardGates.sub_keys(self, other)
     }
 }
 
@@ -85,35 +127,109 @@ impl<M: PolyMatrix> Mul<&Self> for BggPublicKey<M> {
     type Output = Self;
     fn mul(self, other: &Self) -> Self {
         debug_mem(format!("BGGPublicKey::mul {:?}, {:?}", self.matrix.size(), other.matrix.size()));
-        let decomposed = other.matrix.decompose();
+        let prepared = PreparedOperand::new(other);
         debug_mem("BGGPublicKey::mul decomposed");
-        let matrix = self.matrix * decomposed;
-        debug_mem("BGGPublicKey::mul matrix multiplied");
-        let reveal_plaintext = self.reveal_plaintext & other.reveal_plaintext;
-        debug_mem("BGGPublicKey::mul reveal_plaintext");
-        Self { matrix, reveal_plaintext }
+        self.mul_prepared(other, &prepared)
+    }
+}
+
+/// The gadget decomposition `G^-1(B)` of a public key `B` used as the right input of
+/// multiplications, computed once and shared by every gate multiplying by `B`. The entries
+/// are kept in the evaluation (NTT) form the matrix backend stores them in.
+///
+/// The decomposition of an `m`-column key has `m * log q` rows, which dominates the memory of a
+/// multiplication for realistic parameters. A blocked operand keeps only `B` instead and
+/// decomposes `block_size` of its columns at a time while multiplying.
+#[derive(Debug, Clone)]
+pub enum PreparedOperand<M: PolyMatrix> {
+    Decomposed(M),
+    Blocked { matrix: M, block_size: usize },
+}
+
+impl<M: PolyMatrix> PreparedOperand<M> {
+    /// Decomposes `pubkey` in blocks of [`mul_block_size`] columns if it is set, or entirely.
+    pub fn new(pubkey: &BggPublicKey<M>) -> Self {
+        match mul_block_size() {
+            Some(block_size) => Self::blocked(pubkey, block_size),
+            None => Self::Decomposed(pubkey.matrix.decompose()),
+        }
+    }
+
+    pub fn blocked(pubkey: &BggPublicKey<M>, block_size: usize) -> Self {
+        assert!(block_size > 0, "block size must be positive");
+        Self::Blocked { matrix: pubkey.matrix.clone(), block_size }
+    }
+
+    /// Returns the whole decomposition unless it is computed in blocks.
+    pub fn decomposed(&self) -> Option<&M> {
+        match self {
+            Self::Decomposed(decomposed) => Some(decomposed),
+            Self::Blocked { .. } => None,
+        }
+    }
+
+    /// Computes `lhs * G^-1(B)`. Column `j` of `G^-1(B)` only depends on column `j` of `B`, so
+    /// in blocks every product of `lhs` with the decomposition of a slice of columns is a slice
+    /// of the result.
+    pub fn left_mul(&self, lhs: &M) -> M {
+        match self {
+            Self::Decomposed(decomposed) => lhs.clone() * decomposed,
+            Self::Blocked { matrix, block_size } => {
+                let ncol = matrix.col_size();
+                let blocks = (0..ncol)
+                    .step_by(*block_size)
+                    .map(|start| {
+                        let end = (start + block_size).min(ncol);
+                        lhs.clone() * &matrix.slice_columns(start, end).decompose()
+                    })
+                    .collect::<Vec<_>>();
+                blocks[0].concat_columns(&blocks[1..].iter().collect::<Vec<_>>())
+            }
+        }
     }
 }
 
 impl<M: PolyMatrix> Evaluable for BggPublicKey<M> {
     type Params = <M::P as Poly>::Params;
+    type Prepared = PreparedOperand<M>;
     fn rotate(&self, params: &Self::Params, shift: usize) -> Self {
-        debug_mem(format!("BGGPublicKey::rotate {:?}, {:?}", self.matrix.size(), shift));
-        let rotate_poly = <M::P>::const_rotate_poly(params, shift);
-        debug_mem("BGGPublicKey::rotate rotate_poly");
-        let matrix = self.matrix.clone() * rotate_poly;
-        debug_mem("BGGPublicKey::rotate matrix multiplied");
-        Self { matrix, reveal_plaintext: self.reveal_plaintext }
+        StandardGates.rotate_key(params, self, shift)
     }
 
     fn from_digits(params: &Self::Params, one: &Self, digits: &[u32]) -> Self {
-        debug_mem(format!("BGGPublicKey::from_digits {:?}, {:?}", one.matrix.size(), digits.len()));
-        let const_poly =
-            <M::P as Evaluable>::from_digits(params, &<M::P>::const_one(params), digits);
-        debug_mem("BGGPublicKey::from_digits const_poly");
-        let matrix = one.matrix.clone() * const_poly;
-        debug_mem("BGGPublicKey::from_digits matrix multiplied");
-        Self { matrix, reveal_plaintext: one.reveal_plaintext }
+        StandardGates.const_key(params, one, digits)
+    }
+
+    fn mul_const(&self, params: &Self::Params, digits: &[u32]) -> Self {
+        StandardGates.mul_const_key(params, self, digits)
+    }
+
+    fn mul_scalar(&self, params: &Self::Params, scalar: &BigUint) -> Self {
+        StandardGates.mul_scalar_key(params, self, scalar)
+    }
+
+    fn apply_automorphism(
+        &self,
+        params: &Self::Params,
+        _: &Self,
+        hints: [&Self; 2],
+        k: usize,
+    ) -> Self {
+        StandardGates.automorphism_key(params, self, hints, k)
+    }
+
+    /// Multiplies the concatenated left-hand inputs by the stacked decompositions of the
+    /// right-hand ones once, see [`Self::inner_product`].
+    fn eval_inner_product(_: &Self::Params, lhs: &[Self], rhs: &[Self]) -> Self {
+        Self::inner_product(lhs, rhs)
+    }
+
+    fn prepare(&self, _: &Self::Params) -> Self::Prepared {
+        StandardGates.prepare_key(self)
+    }
+
+    fn mul_prepared(self, other: &Self, prepared: &Self::Prepared) -> Self {
+        StandardGates.mul_keys(self, other, prepared)
     }
 }
 
@@ -0,0 +1,195 @@
+use super::{
+    sampler::{BGGEncodingSampler, BGGPublicKeySampler},
+    BggEncoding, BggPublicKey,
+};
+use crate::poly::{
+    sampler::{DistType, PolyHashSampler, PolyUniformSampler},
+    Poly, PolyMatrix, PolyParams,
+};
+use rayon::prelude::*;
+use std::fmt;
+
+const TAG_REVOCATION_PREFIX: &[u8] = b"BGG_REVOCATION:";
+
+/// Why a [`RevocationToken`] cannot be applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevocationError {
+    /// A revoked slot is not among the given public keys or encodings.
+    SlotOutOfRange { idx: usize, len: usize },
+}
+
+impl fmt::Display for RevocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SlotOutOfRange { idx, len } => {
+                write!(f, "revoked slot {} is out of range for {} slots", idx, len)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RevocationError {}
+
+/// An update token published by the key authority to revoke attributes.
+///
+/// Applying the token adds a hash-derived uniform matrix `D_i` to the public key `A_i` of every
+/// revoked slot `i`. The `D_i` are derived from the tag of the public keys, so a token only
+/// applies to the keys sampled for that tag. Encodings created for the old key no longer satisfy
+/// `c_i = s * (A_i - x_i * G) + e` for those slots, so any evaluation touching them fails, while
+/// the other slots stay valid. A holder of the secret can refresh a stored encoding by adding
+/// `s * D_i`.
+///
+/// Tokens belong to a key epoch of [`super::epoch`]: the `D_i` are derived from the epoch key of
+/// the public key sampler, and advancing its epoch re-derives every public key, which supersedes
+/// the tokens of earlier epochs. Successive revocations within an epoch are numbered by `round`.
+#[derive(Debug, Clone)]
+pub struct RevocationToken<M: PolyMatrix> {
+    /// The key epoch whose public keys the token re-randomizes.
+    pub epoch: u64,
+    /// The tag the revoked public keys were sampled for.
+    pub tag: Vec<u8>,
+    pub round: u64,
+    /// Indices of the revoked slots. Index 0 is the constant 1 slot and cannot be revoked.
+    pub revoked: Vec<usize>,
+    pub deltas: Vec<M>,
+}
+
+impl<M: PolyMatrix> RevocationToken<M> {
+    /// Derives the token of the given round for the revoked slots of the public keys sampled for
+    /// `tag` from the epoch key of `key_sampler`.
+    pub fn sample<S: PolyHashSampler<[u8; 32], M = M>>(
+        params: &<M::P as Poly>::Params,
+        key_sampler: &BGGPublicKeySampler<[u8; 32], S>,
+        tag: &[u8],
+        round: u64,
+        revoked: &[usize],
+    ) -> Self {
+        assert!(!revoked.contains(&0), "the constant 1 slot cannot be revoked");
+        let sampler = S::new();
+        let hash_key = key_sampler.epoch_key();
+        let secret_vec_size = key_sampler.d + 1;
+        let columns = secret_vec_size * params.modulus_digits();
+        let deltas = revoked
+            .par_iter()
+            .map(|&idx| {
+                let mut delta_tag = TAG_REVOCATION_PREFIX.to_vec();
+                delta_tag.extend_from_slice(&(tag.len() as u64).to_le_bytes());
+                delta_tag.extend_from_slice(tag);
+                delta_tag.extend_from_slice(&round.to_le_bytes());
+                delta_tag.extend_from_slice(&(idx as u64).to_le_bytes());
+                sampler.sample_hash(
+                    params,
+                    hash_key,
+                    delta_tag,
+                    secret_vec_size,
+                    columns,
+                    DistType::FinRingDist,
+                )
+            })
+            .collect();
+        Self {
+            epoch: key_sampler.epoch(),
+            tag: tag.to_vec(),
+            round,
+            revoked: revoked.to_vec(),
+            deltas,
+        }
+    }
+
+    /// Checks that every revoked slot is one of `len` slots.
+    fn check_range(&self, len: usize) -> Result<(), RevocationError> {
+        match self.revoked.iter().find(|&&idx| idx >= len) {
+            Some(&idx) => Err(RevocationError::SlotOutOfRange { idx, len }),
+            None => Ok(()),
+        }
+    }
+
+    /// Re-randomizes the revoked public keys in place. Nothing is changed if a revoked slot is
+    /// out of range.
+    pub fn apply_to_pubkeys(&self, pubkeys: &mut [BggPublicKey<M>]) -> Result<(), RevocationError> {
+        self.check_range(pubkeys.len())?;
+        for (&idx, delta) in self.revoked.iter().zip(self.deltas.iter()) {
+            pubkeys[idx].matrix = pubkeys[idx].matrix.clone() + delta;
+        }
+        Ok(())
+    }
+
+    /// Updates stored encodings so that they match the re-randomized public keys.
+    /// Requires the encoding sampler holding the secret the encodings were created with. Nothing
+    /// is changed if a revoked slot is out of range.
+    pub fn apply_to_encodings<S: PolyUniformSampler<M = M>>(
+        &self,
+        encoding_sampler: &BGGEncodingSampler<S>,
+        encodings: &mut [BggEncoding<M>],
+    ) -> Result<(), RevocationError> {
+        self.check_range(encodings.len())?;
+        let secret_vec = &encoding_sampler.secret_vec;
+        for (&idx, delta) in self.revoked.iter().zip(self.deltas.iter()) {
+            let encoding = &mut encodings[idx];
+            encoding.vector = encoding.vector.clone() + secret_vec.clone() * delta;
+            encoding.pubkey.matrix = encoding.pubkey.matrix.clone() + delta;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::TestBggSetup;
+
+    #[test]
+    fn test_revocation_token() {
+        // Create public keys and encodings for two attributes
+        let setup = TestBggSetup::new(3);
+        let tag: u64 = rand::random();
+        let tag_bytes = tag.to_le_bytes();
+        let (mut pubkeys, mut encodings) = setup.sample(&tag_bytes, &[true; 2]);
+        let (params, key_sampler) = (&setup.params, &setup.pubkey_sampler);
+        let old_encodings = encodings.clone();
+
+        // Revoke the second attribute
+        let token = RevocationToken::sample(params, key_sampler, &tag_bytes, 1, &[2]);
+        assert_eq!(token.epoch, 0);
+        token.apply_to_pubkeys(&mut pubkeys).unwrap();
+        assert_eq!(pubkeys[1], old_encodings[1].pubkey);
+        assert_ne!(pubkeys[2], old_encodings[2].pubkey);
+
+        // Only the revoked encoding is shifted by s * D
+        token.apply_to_encodings(&setup.encoding_sampler, &mut encodings).unwrap();
+        assert_eq!(encodings[1].vector, old_encodings[1].vector);
+        assert_eq!(
+            encodings[2].vector.clone() - &old_encodings[2].vector,
+            setup.encoding_sampler.secret_vec.clone() * &token.deltas[0]
+        );
+        for (enc, pubkey) in encodings.iter().zip(pubkeys.iter()) {
+            assert_eq!(&enc.pubkey, pubkey);
+        }
+
+        // The same round of the next key epoch is derived from another key
+        let next_sampler = key_sampler.at_epoch(1);
+        let next = RevocationToken::sample(params, &next_sampler, &tag_bytes, 1, &[2]);
+        assert_eq!(next.epoch, 1);
+        assert_ne!(next.deltas, token.deltas);
+
+        // The same round for the keys of another tag is derived from another tag
+        let other_tag = tag.wrapping_add(1).to_le_bytes();
+        let other = RevocationToken::sample(params, key_sampler, &other_tag, 1, &[2]);
+        assert_ne!(other.deltas, token.deltas);
+
+        // A token revoking a slot beyond the keys is rejected without changing any of them
+        let pubkeys_before = pubkeys.clone();
+        let out_of_range = RevocationToken::sample(params, key_sampler, &tag_bytes, 2, &[1, 3]);
+        assert_eq!(
+            out_of_range.apply_to_pubkeys(&mut pubkeys),
+            Err(RevocationError::SlotOutOfRange { idx: 3, len: 3 })
+        );
+        assert_eq!(pubkeys, pubkeys_before);
+        let encodings_before = encodings.clone();
+        assert_eq!(
+            out_of_range.apply_to_encodings(&setup.encoding_sampler, &mut encodings),
+            Err(RevocationError::SlotOutOfRange { idx: 3, len: 3 })
+        );
+        assert_eq!(encodings, encodings_before);
+    }
+}
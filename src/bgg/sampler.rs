@@ -1,19 +1,79 @@
-use super::{BggEncoding, BggPublicKey};
+use super::{
+    circuit::PolyCircuit, epoch::epoch_hash_key, AttributeKeys, BggEncoding, BggPublicKey,
+    EncodedAttributes,
+};
 use crate::{
     parallel_iter,
     poly::{
+        gadget::{GadgetVector, PowersOfBase},
+        norms::centered_abs,
+        plaintext::modulus_biguint,
+        rng::CryptoRngSource,
         sampler::{DistType, PolyHashSampler, PolyUniformSampler},
-        Poly, PolyMatrix, PolyParams,
+        Poly, PolyElem, PolyMatrix, PolyParams,
     },
     utils::debug_mem,
 };
 use rayon::prelude::*;
 use std::marker::PhantomData;
 
+/// How [`BGGEncodingSampler::sample_with_mode`] encodes the attributes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EncodingMode {
+    /// Plain BGG+ encodings: the plaintexts of the public keys with `reveal_plaintext` set are
+    /// attached to the encodings.
+    Plain,
+    /// Weakly attribute-hiding encodings. No attribute plaintext is attached to the encodings
+    /// regardless of the `reveal_plaintext` flags, and every attribute encoding is flooded with
+    /// extra Gaussian noise of standard deviation `flooding_sigma`, so that the encoding errors,
+    /// and hence the noise of evaluated encodings, do not depend on the attributes.
+    ///
+    /// What still leaks: the number of attributes, their public keys, and anything the evaluator
+    /// can compute without the plaintexts. Circuits over hidden attributes are restricted to
+    /// additions, subtractions, rotations, constants, and multiplications whose left input is
+    /// known; decrypting an output still reveals whether the predicate is satisfied.
+    AttributeHiding { flooding_sigma: f64 },
+}
+
+/// The attribute-independent part of BGG+ encodings under a secret `s`: the masks
+/// `s * A_i + e_i` of every public key `A_i` and `s * G`. The encoding of `x_i` is
+/// `s * A_i + e_i - x_i * s * G`, so the same randomness can encode alternative plaintexts.
+#[derive(Debug, Clone)]
+pub struct EncodingRandomness<M: PolyMatrix> {
+    pub(crate) masks: Vec<M>,
+    pub(crate) secret_gadget: M,
+    pub public_keys: Vec<BggPublicKey<M>>,
+}
+
+impl<M: PolyMatrix> EncodingRandomness<M> {
+    /// Encodes the constant one followed by `plaintexts` in the first `1 + plaintexts.len()`
+    /// slots.
+    pub fn encode(
+        &self,
+        params: &<M::P as Poly>::Params,
+        plaintexts: &[M::P],
+    ) -> Vec<BggEncoding<M>> {
+        // first slot is allocated to the constant 1 polynomial plaintext
+        assert_eq!(plaintexts.len() + 1, self.masks.len(), "one public key per slot is required");
+        let plaintexts = [&[M::P::const_one(params)], plaintexts].concat();
+        parallel_iter!(plaintexts)
+            .enumerate()
+            .map(|(idx, plaintext)| {
+                let vector = self.masks[idx].clone() - self.secret_gadget.clone() * &plaintext;
+                debug_mem("before constructing BggEncoding");
+                let pubkey = self.public_keys[idx].clone();
+                let plaintext = if pubkey.reveal_plaintext { Some(plaintext) } else { None };
+                BggEncoding { vector, pubkey, plaintext }
+            })
+            .collect()
+    }
+}
+
 /// A sampler of a public key A in the BGG+ RLWE encoding scheme
 #[derive(Clone)]
 pub struct BGGPublicKeySampler<K: AsRef<[u8]>, S: PolyHashSampler<K>> {
     hash_key: [u8; 32],
+    epoch: u64,
     pub d: usize,
     _k: PhantomData<K>,
     _s: PhantomData<S>,
@@ -30,7 +90,41 @@ where
     /// # Returns
     /// A new public key sampler
     pub fn new(hash_key: [u8; 32], d: usize) -> Self {
-        Self { hash_key, d, _k: PhantomData, _s: PhantomData }
+        Self { hash_key, epoch: 0, d, _k: PhantomData, _s: PhantomData }
+    }
+
+    /// Creates a sampler with a hash key drawn from `source`.
+    pub fn from_source(source: &dyn CryptoRngSource, d: usize) -> Self {
+        let mut hash_key = [0u8; 32];
+        source.fill_bytes(&mut hash_key);
+        Self::new(hash_key, d)
+    }
+
+    /// The key the public matrices of every epoch are expanded from, which evaluators need to
+    /// re-derive them.
+    pub fn hash_key(&self) -> [u8; 32] {
+        self.hash_key
+    }
+
+    /// The epoch the public keys are sampled for, 0 unless advanced.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// The key the hash-expanded matrices of the current epoch are derived from.
+    pub fn epoch_key(&self) -> [u8; 32] {
+        epoch_hash_key::<S::Hash>(self.hash_key, self.epoch)
+    }
+
+    /// Samples public keys of the next epoch from now on, under a hash key derived from the
+    /// hash key and the epoch, so that encodings of earlier epochs no longer evaluate correctly.
+    pub fn advance_epoch(&mut self) {
+        self.epoch = self.epoch.checked_add(1).expect("epoch overflow");
+    }
+
+    pub fn at_epoch(mut self, epoch: u64) -> Self {
+        self.epoch = epoch;
+        self
     }
 
     /// Sample a public key matrix
@@ -53,7 +147,7 @@ where
         let packed_input_size = 1 + reveal_plaintexts.len(); // first slot is allocated to the constant 1 polynomial plaintext
         let all_matrix = sampler.sample_hash(
             params,
-            self.hash_key,
+            self.epoch_key(),
             tag,
             secret_vec_size,
             columns * packed_input_size,
@@ -69,6 +163,52 @@ where
             })
             .collect()
     }
+
+    /// Samples the public keys like [`Self::sample`], with the constant-one slot kept apart from
+    /// the attributes.
+    pub fn sample_attributes(
+        &self,
+        params: &<<<S as PolyHashSampler<K>>::M as PolyMatrix>::P as Poly>::Params,
+        tag: &[u8],
+        reveal_plaintexts: &[bool],
+    ) -> AttributeKeys<<S as PolyHashSampler<K>>::M> {
+        AttributeKeys::from_slots(self.sample(params, tag, reveal_plaintexts))
+    }
+
+    /// Sample public key matrices of the same shape as [`Self::sample`] from a uniform sampler
+    /// instead of the hash sampler.
+    ///
+    /// A uniform polynomial in the evaluation (NTT) domain is uniform in the coefficient domain,
+    /// so backends that sample `FinRingDist` directly in the evaluation domain (as
+    /// `DCRTPolyUniformSampler` does) skip the forward NTT that the coefficient-domain hash
+    /// output needs before its first multiplication. The keys are not derived from the hash key
+    /// and must be stored or transmitted instead of being re-sampled from the tag.
+    pub fn sample_uniform<U: PolyUniformSampler<M = <S as PolyHashSampler<K>>::M>>(
+        &self,
+        params: &<<<S as PolyHashSampler<K>>::M as PolyMatrix>::P as Poly>::Params,
+        uniform_sampler: &U,
+        reveal_plaintexts: &[bool],
+    ) -> Vec<BggPublicKey<<S as PolyHashSampler<K>>::M>> {
+        let log_base_q = params.modulus_digits();
+        let secret_vec_size = self.d + 1;
+        let columns = secret_vec_size * log_base_q;
+        let packed_input_size = 1 + reveal_plaintexts.len(); // first slot is allocated to the constant 1 polynomial plaintext
+        let all_matrix = uniform_sampler.sample_uniform(
+            params,
+            secret_vec_size,
+            columns * packed_input_size,
+            DistType::FinRingDist,
+        );
+        parallel_iter!(0..packed_input_size)
+            .map(|idx| {
+                let reveal_plaintext = if idx == 0 { true } else { reveal_plaintexts[idx - 1] };
+                BggPublicKey::new(
+                    all_matrix.slice_columns(columns * idx, columns * (idx + 1)),
+                    reveal_plaintext,
+                )
+            })
+            .collect()
+    }
 }
 
 /// A sampler of an encoding in the BGG+ RLWE encoding scheme
@@ -115,60 +255,275 @@ where
         public_keys: &[BggPublicKey<S::M>],
         plaintexts: &[<S::M as PolyMatrix>::P],
     ) -> Vec<BggEncoding<S::M>> {
+        self.sample_randomness(params, public_keys).encode(params, plaintexts)
+    }
+
+    /// Samples the encodings of `plaintexts` like [`Self::sample`], with the constant-one slot
+    /// kept apart from the attributes. `public_keys` are the keys returned by
+    /// [`BGGPublicKeySampler::sample_attributes`].
+    pub fn sample_attributes(
+        &self,
+        params: &<<<S as PolyUniformSampler>::M as PolyMatrix>::P as Poly>::Params,
+        public_keys: &AttributeKeys<S::M>,
+        plaintexts: &[<S::M as PolyMatrix>::P],
+    ) -> EncodedAttributes<S::M> {
+        EncodedAttributes::from_slots(self.sample(params, public_keys.slots(), plaintexts))
+    }
+
+    /// Samples the attribute-independent part of the encodings under `public_keys`, from which
+    /// [`EncodingRandomness::encode`] derives the encodings of any plaintexts.
+    pub fn sample_randomness(
+        &self,
+        params: &<<<S as PolyUniformSampler>::M as PolyMatrix>::P as Poly>::Params,
+        public_keys: &[BggPublicKey<S::M>],
+    ) -> EncodingRandomness<S::M> {
+        self.sample_randomness_with_gadget(params, public_keys, &PowersOfBase)
+    }
+
+    /// Samples the encodings of `plaintexts` like [`Self::sample`] with respect to the gadget
+    /// matrix of `gadget`, i.e. `s * (A - x * G)`, for evaluation with [`GadgetGates`].
+    ///
+    /// [`GadgetGates`]: crate::bgg::gates::GadgetGates
+    pub fn sample_with_gadget<G: GadgetVector<S::M>>(
+        &self,
+        params: &<<<S as PolyUniformSampler>::M as PolyMatrix>::P as Poly>::Params,
+        public_keys: &[BggPublicKey<S::M>],
+        plaintexts: &[<S::M as PolyMatrix>::P],
+        gadget: &G,
+    ) -> Vec<BggEncoding<S::M>> {
+        self.sample_randomness_with_gadget(params, public_keys, gadget).encode(params, plaintexts)
+    }
+
+    /// Like [`Self::sample_randomness`] for the gadget matrix of `gadget`, which must be as wide
+    /// as the public keys.
+    pub fn sample_randomness_with_gadget<G: GadgetVector<S::M>>(
+        &self,
+        params: &<<<S as PolyUniformSampler>::M as PolyMatrix>::P as Poly>::Params,
+        public_keys: &[BggPublicKey<S::M>],
+        gadget: &G,
+    ) -> EncodingRandomness<S::M> {
         let secret_vec = &self.secret_vec;
-        let log_base_q = params.modulus_digits();
-        let packed_input_size = 1 + plaintexts.len(); // first slot is allocated to the constant 1 polynomial plaintext
-        let plaintexts: Vec<<S::M as PolyMatrix>::P> =
-            [&[<<S as PolyUniformSampler>::M as PolyMatrix>::P::const_one(params)], plaintexts]
-                .concat();
         let secret_vec_size = self.secret_vec.col_size();
-        let columns = secret_vec_size * log_base_q * packed_input_size;
+        let m = secret_vec_size * gadget.len(params);
+        assert_eq!(public_keys[0].matrix.col_size(), m, "the gadget does not fit the public keys");
         let error: S::M = self.error_sampler.sample_uniform(
             params,
             1,
-            columns,
+            m * public_keys.len(),
             DistType::GaussDist { sigma: self.gauss_sigma },
         );
         let all_public_key_matrix: S::M = public_keys[0]
             .matrix
             .concat_columns(&public_keys[1..].par_iter().map(|pk| &pk.matrix).collect::<Vec<_>>());
-        let first_term = secret_vec.clone() * all_public_key_matrix;
+        let all_masks = secret_vec.clone() * all_public_key_matrix + error;
+        let masks = parallel_iter!(0..public_keys.len())
+            .map(|idx| all_masks.slice_columns(m * idx, m * (idx + 1)))
+            .collect();
+        let gadget = gadget.matrix(params, secret_vec_size);
+        EncodingRandomness {
+            masks,
+            secret_gadget: secret_vec.clone() * gadget,
+            public_keys: public_keys.to_vec(),
+        }
+    }
 
+    /// Samples one set of encodings per plaintext vector of `variants`, all sharing the secret,
+    /// the public keys and the errors, so that the sets differ only in the slots whose
+    /// plaintexts differ, e.g. the `x_i = 0` and `x_i = 1` branches of an input bit.
+    ///
+    /// # Security
+    ///
+    /// The difference of two sets is `s * G * (x - x')` in every slot where they differ, free of
+    /// any error, and `G` is public with a known trapdoor, so it reveals the secret `s`. At most
+    /// one set may reach an evaluator in the clear; the others must stay hidden, e.g. behind the
+    /// preimages of the obfuscation that only open one branch per input bit.
+    pub fn sample_correlated(
+        &self,
+        params: &<<<S as PolyUniformSampler>::M as PolyMatrix>::P as Poly>::Params,
+        public_keys: &[BggPublicKey<S::M>],
+        variants: &[Vec<<S::M as PolyMatrix>::P>],
+    ) -> Vec<EncodedAttributes<S::M>> {
+        let randomness = self.sample_randomness(params, public_keys);
+        variants
+            .iter()
+            .map(|plaintexts| EncodedAttributes::from_slots(randomness.encode(params, plaintexts)))
+            .collect()
+    }
+
+    /// Re-encodes the `idx`-th attribute (slot `idx + 1` of `encodings`) to `plaintext` under the
+    /// same secret with a fresh error, leaving the other encodings untouched.
+    pub fn update_attribute(
+        &self,
+        params: &<<<S as PolyUniformSampler>::M as PolyMatrix>::P as Poly>::Params,
+        encodings: &mut [BggEncoding<S::M>],
+        idx: usize,
+        plaintext: <S::M as PolyMatrix>::P,
+    ) {
+        let slot = idx + 1;
+        assert!(slot < encodings.len(), "attribute index {} out of range", idx);
+        let pubkey = encodings[slot].pubkey.clone();
+        let secret_vec_size = self.secret_vec.col_size();
+        let error: S::M = self.error_sampler.sample_uniform(
+            params,
+            1,
+            secret_vec_size * params.modulus_digits(),
+            DistType::GaussDist { sigma: self.gauss_sigma },
+        );
         let gadget = S::M::gadget_matrix(params, secret_vec_size);
-        let encoded_polys_vec = S::M::from_poly_vec_row(params, plaintexts.to_vec());
-        let second_term = encoded_polys_vec.tensor(&(secret_vec.clone() * gadget));
-        let all_vector = first_term - second_term + error;
+        let vector = self.secret_vec.clone() * &pubkey.matrix -
+            (self.secret_vec.clone() * gadget) * &plaintext +
+            error;
+        let plaintext = if pubkey.reveal_plaintext { Some(plaintext) } else { None };
+        encodings[slot] = BggEncoding { vector, pubkey, plaintext };
+    }
 
-        let m = secret_vec_size * log_base_q;
-        parallel_iter!(plaintexts)
-            .enumerate()
-            .map(|(idx, plaintext)| {
-                let vector = all_vector.slice_columns(m * idx, m * (idx + 1));
-                debug_mem("before constructing BggEncoding");
-                BggEncoding {
-                    vector,
-                    pubkey: public_keys[idx].clone(),
-                    plaintext: if public_keys[idx].reveal_plaintext {
-                        Some(plaintext.clone())
-                    } else {
-                        None
-                    },
-                }
+    /// Samples the key-switching hints of the automorphism `σ: X -> X^k` for automorphism gates,
+    /// `h_0 = s * A_one - σ(s) * Q + e_0` and `h_1 = s * P - σ(s) * G + e_1` for the keys
+    /// `hint_keys = [Q, P]` and the key `A_one` of the constant one. They are evaluated as two
+    /// more inputs whose plaintexts are unknown.
+    ///
+    /// # Security
+    ///
+    /// Like the rotation keys of FHE schemes, the hints encrypt the secret under itself and are
+    /// only safe under a circular security assumption. `Q` and `P` must be uniform, e.g. sampled
+    /// with [`BGGPublicKeySampler::sample`], and encode nothing else.
+    pub fn sample_automorphism_hints(
+        &self,
+        params: &<<<S as PolyUniformSampler>::M as PolyMatrix>::P as Poly>::Params,
+        one: &BggPublicKey<S::M>,
+        hint_keys: [&BggPublicKey<S::M>; 2],
+        k: usize,
+    ) -> [BggEncoding<S::M>; 2] {
+        let secret_vec_size = self.secret_vec.col_size();
+        let switched_secret = self.secret_vec.automorphism(params, k);
+        let gadget = S::M::gadget_matrix(params, secret_vec_size);
+        let masks = [&one.matrix, &hint_keys[1].matrix];
+        let targets = [&hint_keys[0].matrix, &gadget];
+        [0, 1].map(|i| {
+            let error: S::M = self.error_sampler.sample_uniform(
+                params,
+                1,
+                secret_vec_size * params.modulus_digits(),
+                DistType::GaussDist { sigma: self.gauss_sigma },
+            );
+            let vector =
+                self.secret_vec.clone() * masks[i] - switched_secret.clone() * targets[i] + error;
+            BggEncoding { vector, pubkey: hint_keys[i].clone(), plaintext: None }
+        })
+    }
+
+    /// Samples the encodings like [`Self::sample`] in the given [`EncodingMode`]. The constant
+    /// one slot is never hidden.
+    pub fn sample_with_mode(
+        &self,
+        params: &<<<S as PolyUniformSampler>::M as PolyMatrix>::P as Poly>::Params,
+        public_keys: &[BggPublicKey<S::M>],
+        plaintexts: &[<S::M as PolyMatrix>::P],
+        mode: EncodingMode,
+    ) -> Vec<BggEncoding<S::M>> {
+        let flooding_sigma = match mode {
+            EncodingMode::Plain => return self.sample(params, public_keys, plaintexts),
+            EncodingMode::AttributeHiding { flooding_sigma } => flooding_sigma,
+        };
+        let mut encodings = self.sample(params, public_keys, plaintexts);
+        let ncol = encodings[0].vector.col_size();
+        for encoding in encodings.iter_mut().skip(1) {
+            let flooding: S::M = self.error_sampler.sample_uniform(
+                params,
+                1,
+                ncol,
+                DistType::GaussDist { sigma: flooding_sigma },
+            );
+            encoding.vector = encoding.vector.clone() + flooding;
+            encoding.pubkey.reveal_plaintext = false;
+            encoding.plaintext = None;
+        }
+        encodings
+    }
+
+    /// Recovers a plaintext with 0/1 coefficients from an encoding, e.g. an evaluated output,
+    /// with the secret. Column `k` of the last gadget block of `vector - (s, -1) * A` holds
+    /// `x * g_k + e`, so every coefficient is rounded to whichever of `0` and `g_k` is closer,
+    /// for the gadget entry `g_k` of the largest magnitude.
+    pub fn decode_bits(
+        &self,
+        params: &<<<S as PolyUniformSampler>::M as PolyMatrix>::P as Poly>::Params,
+        encoding: &BggEncoding<S::M>,
+    ) -> Vec<bool> {
+        let q = modulus_biguint::<<S::M as PolyMatrix>::P>(params);
+        let gadget = S::M::gadget_matrix(params, 1);
+        let (digit, scale) = (0..gadget.col_size())
+            .map(|k| (k, gadget.entry(0, k).coeffs()[0].to_biguint().clone()))
+            .max_by_key(|(_, g)| centered_abs(g, &q))
+            .expect("gadget vector is non-empty");
+        let noisy = encoding.vector.clone() - self.secret_vec.clone() * &encoding.pubkey.matrix;
+        let column = (self.secret_vec.col_size() - 1) * gadget.col_size() + digit;
+        noisy
+            .entry(0, column)
+            .coeffs()
+            .iter()
+            .map(|coeff| {
+                let c = coeff.to_biguint();
+                let shifted = (c + &q - &scale) % &q;
+                centered_abs(&shifted, &q) < centered_abs(c, &q)
             })
             .collect()
     }
+
+    /// Encodes the plaintexts and evaluates `circuit` over the encodings in one step, sampling
+    /// only the encodings of the attributes the circuit outputs depend on instead of all
+    /// `1 + plaintexts.len()` of them. Returns the same output encodings as sampling all
+    /// encodings and evaluating the circuit over them, up to the sampled errors.
+    pub fn evaluate_predicate(
+        &self,
+        params: &<<<S as PolyUniformSampler>::M as PolyMatrix>::P as Poly>::Params,
+        public_keys: &[BggPublicKey<S::M>],
+        plaintexts: &[<S::M as PolyMatrix>::P],
+        circuit: &PolyCircuit,
+    ) -> Vec<BggEncoding<S::M>> {
+        assert_eq!(public_keys.len(), plaintexts.len() + 1);
+        let (pruned, used_inputs) = circuit.prune_inputs();
+        debug_mem(format!("evaluate_predicate uses {} inputs", used_inputs.len()));
+        let public_keys = BggPublicKey::project(public_keys, &used_inputs);
+        let plaintexts = used_inputs.iter().map(|&idx| plaintexts[idx].clone()).collect::<Vec<_>>();
+        let public_keys = AttributeKeys::from_slots(public_keys);
+        self.sample_attributes(params, &public_keys, &plaintexts).eval(params, &pruned)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
-        poly::dcrt::{
-            DCRTPoly, DCRTPolyHashSampler, DCRTPolyMatrix, DCRTPolyParams, DCRTPolyUniformSampler,
+        assert_matrix_close,
+        poly::{
+            dcrt::{
+                DCRTPoly, DCRTPolyHashSampler, DCRTPolyMatrix, DCRTPolyParams,
+                DCRTPolyUniformSampler,
+            },
+            rng::ChaChaSource,
         },
         utils::{create_bit_random_poly, create_random_poly},
     };
     use keccak_asm::Keccak256;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_bgg_pub_key_sampler_from_source() {
+        let params = DCRTPolyParams::default();
+
+        // Samplers keyed from the same seeded source expand the same public keys
+        let sampler = |seed| {
+            BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::from_source(
+                &ChaChaSource::for_test(seed),
+                2,
+            )
+        };
+        assert_eq!(sampler(1).hash_key(), sampler(1).hash_key());
+        assert_ne!(sampler(1).hash_key(), sampler(2).hash_key());
+        let pubkeys = sampler(1).sample(&params, b"source", &[true]);
+        assert_eq!(pubkeys, sampler(1).sample(&params, b"source", &[true]));
+    }
 
     #[test]
     fn test_bgg_pub_key_sampling() {
@@ -185,6 +540,27 @@ mod tests {
         assert_eq!(sampled_pub_keys.len(), packed_input_size + 1);
     }
 
+    #[test]
+    fn test_bgg_pub_key_sampling_uniform() {
+        let key: [u8; 32] = rand::random();
+        let tag: u64 = rand::random();
+        let params = DCRTPolyParams::default();
+        let d = 3;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let reveal_plaintexts = vec![true, false];
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let uniform_pub_keys =
+            bgg_sampler.sample_uniform(&params, &uniform_sampler, &reveal_plaintexts);
+        let hash_pub_keys = bgg_sampler.sample(&params, &tag.to_le_bytes(), &reveal_plaintexts);
+
+        // Both paths produce keys of the same shape and reveal flags
+        assert_eq!(uniform_pub_keys.len(), hash_pub_keys.len());
+        for (uniform, hash) in uniform_pub_keys.iter().zip(hash_pub_keys.iter()) {
+            assert_eq!(uniform.matrix.size(), hash.matrix.size());
+            assert_eq!(uniform.reveal_plaintext, hash.reveal_plaintext);
+        }
+    }
+
     #[test]
     fn test_bgg_pub_key_addition() {
         let key: [u8; 32] = rand::random();
@@ -337,4 +713,278 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_bgg_evaluate_predicate() {
+        let key: [u8; 32] = rand::random();
+        let tag: u64 = rand::random();
+        let tag_bytes = tag.to_le_bytes();
+        let params = DCRTPolyParams::default();
+        let d = 3;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let reveal_plaintexts = vec![true; 6];
+        let sampled_pub_keys = bgg_sampler.sample(&params, &tag_bytes, &reveal_plaintexts);
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let plaintexts = (0..6).map(|_| create_random_poly(&params)).collect::<Vec<_>>();
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+
+        // A sparse policy over attributes 1 and 4 out of 6
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(6);
+        let mul_gate = circuit.mul_gate(inputs[4], inputs[1]);
+        let sub_gate = circuit.sub_gate(mul_gate, inputs[1]);
+        circuit.output(vec![sub_gate]);
+
+        let result =
+            bgg_sampler.evaluate_predicate(&params, &sampled_pub_keys, &plaintexts, &circuit);
+        let bgg_encodings = bgg_sampler.sample(&params, &sampled_pub_keys, &plaintexts);
+        let expected = circuit.eval(&params, &bgg_encodings[0], &bgg_encodings[1..]);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].vector, expected[0].vector);
+        assert_eq!(result[0].pubkey, expected[0].pubkey);
+        assert_eq!(result[0].plaintext, expected[0].plaintext);
+
+        // A constant policy encodes no attribute at all
+        let mut circuit = PolyCircuit::new();
+        circuit.input(6);
+        let digits = circuit.const_digits_poly(&[1, 0, 1]);
+        circuit.output(vec![digits]);
+        let result =
+            bgg_sampler.evaluate_predicate(&params, &sampled_pub_keys, &plaintexts, &circuit);
+        let expected = circuit.eval(&params, &bgg_encodings[0], &bgg_encodings[1..]);
+        assert_eq!(result[0].vector, expected[0].vector);
+        assert_eq!(result[0].pubkey, expected[0].pubkey);
+    }
+
+    #[test]
+    fn test_bgg_sample_correlated() {
+        let key: [u8; 32] = rand::random();
+        let tag: u64 = rand::random();
+        let tag_bytes = tag.to_le_bytes();
+        let params = DCRTPolyParams::default();
+        let d = 3;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let sampled_pub_keys = bgg_sampler.sample(&params, &tag_bytes, &[true; 3]);
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 3.0);
+
+        // Two variants differing only in the second attribute
+        let zero = DCRTPoly::const_zero(&params);
+        let one = DCRTPoly::const_one(&params);
+        let first = create_random_poly(&params);
+        let variants = vec![
+            vec![first.clone(), zero.clone(), first.clone()],
+            vec![first.clone(), one.clone(), first],
+        ];
+        let encodings = bgg_sampler.sample_correlated(&params, &sampled_pub_keys, &variants);
+        assert_eq!(encodings.len(), 2);
+        let (x0, x1) = (encodings[0].clone().into_slots(), encodings[1].clone().into_slots());
+
+        // The shared slots are identical and the differing slot is offset by (x0 - x1) * s * G
+        for slot in [0, 1, 3] {
+            assert_eq!(x0[slot], x1[slot]);
+        }
+        let g = DCRTPolyMatrix::gadget_matrix(&params, d + 1);
+        assert_eq!(x0[2].vector.clone() - x1[2].vector.clone(), bgg_sampler.secret_vec.clone() * g);
+        assert_eq!(x0[2].plaintext, Some(zero));
+        assert_eq!(x1[2].plaintext, Some(one));
+    }
+
+    #[test]
+    fn test_bgg_update_attribute() {
+        let key: [u8; 32] = rand::random();
+        let tag: u64 = rand::random();
+        let tag_bytes = tag.to_le_bytes();
+        let params = DCRTPolyParams::default();
+        let d = 3;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let reveal_plaintexts = vec![true, false, true];
+        let sampled_pub_keys = bgg_sampler.sample(&params, &tag_bytes, &reveal_plaintexts);
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let mut plaintexts = (0..3).map(|_| create_random_poly(&params)).collect::<Vec<_>>();
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let mut bgg_encodings = bgg_sampler.sample(&params, &sampled_pub_keys, &plaintexts);
+        let before = bgg_encodings.clone();
+
+        // Flip the revealed and the hidden attribute one at a time
+        for idx in [0, 1] {
+            plaintexts[idx] = create_random_poly(&params);
+            bgg_sampler.update_attribute(&params, &mut bgg_encodings, idx, plaintexts[idx].clone());
+        }
+
+        // The updated encodings match a full re-encoding and the others are untouched
+        let expected = bgg_sampler.sample(&params, &sampled_pub_keys, &plaintexts);
+        for (updated, expected) in bgg_encodings.iter().zip(expected.iter()) {
+            assert_eq!(updated.vector, expected.vector);
+            assert_eq!(updated.pubkey, expected.pubkey);
+            assert_eq!(updated.plaintext, expected.plaintext);
+        }
+        assert_eq!(bgg_encodings[0].vector, before[0].vector);
+        assert_eq!(bgg_encodings[3].vector, before[3].vector);
+        assert_eq!(bgg_encodings[2].plaintext, None);
+    }
+
+    #[test]
+    fn test_bgg_sample_attributes() {
+        let key: [u8; 32] = rand::random();
+        let params = DCRTPolyParams::default();
+        let d = 3;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let sampled_pub_keys = bgg_sampler.sample_attributes(&params, b"attributes", &[true; 2]);
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let plaintexts = (0..2).map(|_| create_random_poly(&params)).collect::<Vec<_>>();
+        let encodings = bgg_sampler.sample_attributes(&params, &sampled_pub_keys, &plaintexts);
+
+        // Only the attributes are exposed as attributes
+        assert_eq!(encodings.len(), 2);
+        assert_eq!(encodings.constant_one_row().plaintext, Some(DCRTPoly::const_one(&params)));
+        assert_eq!(encodings.attributes()[1].plaintext, Some(plaintexts[1].clone()));
+
+        // A circuit adding a constant to x1 * x2 is evaluated without indexing the slots
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        let mul_gate = circuit.mul_gate(inputs[0], inputs[1]);
+        let add_const = circuit.add_const_gate(mul_gate, &[1]);
+        circuit.output(vec![add_const]);
+        let result = encodings.eval(&params, &circuit).pop().unwrap();
+        let expected = plaintexts[0].clone() * &plaintexts[1] + DCRTPoly::const_one(&params);
+        assert_eq!(result.plaintext, Some(expected));
+        assert_eq!(result.pubkey, sampled_pub_keys.eval(&params, &circuit).pop().unwrap());
+        assert_eq!(encodings.into_slots().len(), 3);
+    }
+
+    #[test]
+    fn test_bgg_encoding_randomness_reuse() {
+        let key: [u8; 32] = rand::random();
+        let params = DCRTPolyParams::default();
+        let d = 3;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let sampled_pub_keys = bgg_sampler.sample(&params, b"randomness", &[true, false]);
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 3.0);
+        let randomness = bgg_sampler.sample_randomness(&params, &sampled_pub_keys);
+
+        // Encode two attribute vectors with the same secret and errors
+        let plaintexts = (0..2).map(|_| create_random_poly(&params)).collect::<Vec<_>>();
+        let alternatives = (0..2).map(|_| create_random_poly(&params)).collect::<Vec<_>>();
+        let encodings = randomness.encode(&params, &plaintexts);
+        let alternative_encodings = randomness.encode(&params, &alternatives);
+        assert_eq!(encodings.len(), 3);
+        assert_eq!(encodings[0].vector, alternative_encodings[0].vector);
+        assert_eq!(encodings[1].plaintext, Some(plaintexts[0].clone()));
+        assert_eq!(encodings[2].plaintext, None);
+
+        // The encodings differ exactly by (x' - x) * s * G
+        let gadget = DCRTPolyMatrix::gadget_matrix(&params, d + 1);
+        let secret_gadget = bgg_sampler.secret_vec.clone() * gadget;
+        for idx in 0..2 {
+            let diff = alternatives[idx].clone() - &plaintexts[idx];
+            assert_eq!(
+                encodings[idx + 1].vector.clone() - &alternative_encodings[idx + 1].vector,
+                secret_gadget.clone() * diff
+            );
+        }
+    }
+
+    #[test]
+    fn test_bgg_sample_attribute_hiding() {
+        let key: [u8; 32] = rand::random();
+        let tag: u64 = rand::random();
+        let tag_bytes = tag.to_le_bytes();
+        let params = DCRTPolyParams::default();
+        let d = 3;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let reveal_plaintexts = vec![true; 3];
+        let sampled_pub_keys = bgg_sampler.sample(&params, &tag_bytes, &reveal_plaintexts);
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let plaintexts = (0..3).map(|_| create_random_poly(&params)).collect::<Vec<_>>();
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+
+        // Without flooding noise the hidden encodings are the plain ones minus the plaintexts
+        let plain = bgg_sampler.sample_with_mode(
+            &params,
+            &sampled_pub_keys,
+            &plaintexts,
+            EncodingMode::Plain,
+        );
+        let hidden = bgg_sampler.sample_with_mode(
+            &params,
+            &sampled_pub_keys,
+            &plaintexts,
+            EncodingMode::AttributeHiding { flooding_sigma: 0.0 },
+        );
+        assert_eq!(hidden.len(), plain.len());
+        assert_eq!(hidden[0].plaintext, Some(DCRTPoly::const_one(&params)));
+        assert!(hidden[0].pubkey.reveal_plaintext);
+        for (hidden, plain) in hidden.iter().zip(plain.iter()).skip(1) {
+            assert_eq!(hidden.vector, plain.vector);
+            assert_eq!(hidden.pubkey.matrix, plain.pubkey.matrix);
+            assert!(!hidden.pubkey.reveal_plaintext);
+            assert_eq!(hidden.plaintext, None);
+        }
+
+        // Linear circuits still evaluate over the hidden attributes
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(3);
+        let add_gate = circuit.add_gate(inputs[0], inputs[2]);
+        let sub_gate = circuit.sub_gate(add_gate, inputs[1]);
+        circuit.output(vec![sub_gate]);
+        let result = circuit.eval(&params, &hidden[0], &hidden[1..]);
+        let expected = circuit.eval(&params, &plain[0], &plain[1..]);
+        assert_eq!(result[0].vector, expected[0].vector);
+        assert_eq!(result[0].pubkey.matrix, expected[0].pubkey.matrix);
+        assert_eq!(result[0].plaintext, None);
+
+        // With flooding noise only the attribute slots move, by a noise bounded by 10 sigma
+        let flooding_sigma = 4.0;
+        let flooded = bgg_sampler.sample_with_mode(
+            &params,
+            &sampled_pub_keys,
+            &plaintexts,
+            EncodingMode::AttributeHiding { flooding_sigma },
+        );
+        assert_eq!(flooded[0].vector, plain[0].vector);
+        let bound = BigUint::from(10 * flooding_sigma as u32);
+        for (flooded, plain) in flooded.iter().zip(plain.iter()).skip(1) {
+            assert_matrix_close!(flooded.vector, plain.vector, bound);
+            assert!(!flooded.pubkey.reveal_plaintext);
+            assert_eq!(flooded.plaintext, None);
+        }
+        assert!(flooded.iter().zip(plain.iter()).skip(1).any(|(f, p)| f.vector != p.vector));
+    }
+
+    #[test]
+    fn test_bgg_decode_bits() {
+        let key: [u8; 32] = rand::random();
+        let params = DCRTPolyParams::default();
+        let d = 3;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let pubkeys = bgg_sampler.sample(&params, b"decode", &[true, true]);
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 3.0);
+        let plaintexts = vec![create_bit_random_poly(&params), DCRTPoly::const_one(&params)];
+        let encodings = bgg_sampler.sample(&params, &pubkeys, &plaintexts);
+
+        // Fresh encodings decode to their plaintexts
+        for (encoding, plaintext) in encodings[1..].iter().zip(plaintexts.iter()) {
+            assert_eq!(bgg_sampler.decode_bits(&params, encoding), plaintext.to_bool_vec());
+        }
+
+        // So does the output of a multiplication by the constant one
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        let mul_gate = circuit.mul_gate(inputs[0], inputs[1]);
+        circuit.output(vec![mul_gate]);
+        let output = circuit.eval(&params, &encodings[0], &encodings[1..]).pop().unwrap();
+        let expected = plaintexts[0].clone() * &plaintexts[1];
+        assert_eq!(bgg_sampler.decode_bits(&params, &output), expected.to_bool_vec());
+    }
 }
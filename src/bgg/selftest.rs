@@ -0,0 +1,112 @@
+use super::{circuit::PolyCircuit, sampler::BGGEncodingSampler, BggEncoding, BggPublicKey};
+use crate::poly::{sampler::PolyUniformSampler, Poly, PolyMatrix};
+
+/// Evaluates `circuit` side by side over the public keys, the encodings and the plaintexts, and
+/// checks the BGG homomorphism at every gate: the encoding must carry the key-side public key
+/// `A` and (if revealed) the plaintext `x` of that gate. When the encodings were sampled without
+/// error (`gauss_sigma == 0.0`), the vector is also checked to be exactly `s·A - x·s·G`.
+///
+/// `pubkeys` and `encodings` include the constant-one slot, while `plaintexts` does not, as in
+/// [`BGGEncodingSampler::sample`]. Returns the id of the first gate violating the identity.
+pub fn dual_eval<S: PolyUniformSampler>(
+    params: &<<S::M as PolyMatrix>::P as Poly>::Params,
+    circuit: &PolyCircuit,
+    encoding_sampler: &BGGEncodingSampler<S>,
+    pubkeys: &[BggPublicKey<S::M>],
+    encodings: &[BggEncoding<S::M>],
+    plaintexts: &[<S::M as PolyMatrix>::P],
+) -> Result<(), usize> {
+    assert_eq!(pubkeys.len(), plaintexts.len() + 1);
+    assert_eq!(encodings.len(), plaintexts.len() + 1);
+    let one = <S::M as PolyMatrix>::P::const_one(params);
+    let pubkey_wires = circuit.eval_wires(params, &pubkeys[0], &pubkeys[1..]);
+    let encoding_wires = circuit.eval_wires(params, &encodings[0], &encodings[1..]);
+    let plaintext_wires = circuit.eval_wires(params, &one, plaintexts);
+
+    let secret_vec = &encoding_sampler.secret_vec;
+    let check_vector = encoding_sampler.gauss_sigma == 0.0;
+    let secret_gadget = check_vector
+        .then(|| secret_vec.clone() * S::M::gadget_matrix(params, secret_vec.col_size()));
+
+    // Gate ids are assigned in topological order, so the first failure is the smallest id.
+    for (gate_id, pubkey) in pubkey_wires.iter() {
+        let encoding = &encoding_wires[gate_id];
+        let plaintext = &plaintext_wires[gate_id];
+        if encoding.pubkey.matrix != pubkey.matrix {
+            return Err(*gate_id);
+        }
+        if encoding.plaintext.as_ref().is_some_and(|revealed| revealed != plaintext) {
+            return Err(*gate_id);
+        }
+        if let Some(secret_gadget) = secret_gadget.as_ref() {
+            let expected = secret_vec.clone() * &pubkey.matrix - secret_gadget.clone() * plaintext;
+            if encoding.vector != expected {
+                return Err(*gate_id);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::sampler::BGGPublicKeySampler,
+        poly::dcrt::{DCRTPolyHashSampler, DCRTPolyParams, DCRTPolyUniformSampler},
+        utils::{create_bit_random_poly, create_random_poly},
+    };
+    use keccak_asm::Keccak256;
+
+    #[test]
+    fn test_dual_eval() {
+        // Create parameters for testing
+        let params = DCRTPolyParams::default();
+
+        // Create samplers
+        let key: [u8; 32] = rand::random();
+        let d = 3;
+        let bgg_pubkey_sampler =
+            BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+
+        // Create random public keys
+        let tag: u64 = rand::random();
+        let tag_bytes = tag.to_le_bytes();
+        let reveal_plaintexts = [true; 3];
+        let pubkeys = bgg_pubkey_sampler.sample(&params, &tag_bytes, &reveal_plaintexts);
+
+        // Create secret, plaintexts and encodings without error
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let plaintexts = vec![
+            create_random_poly(&params),
+            create_random_poly(&params),
+            create_random_poly(&params),
+        ];
+        let bgg_encoding_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let mut encodings = bgg_encoding_sampler.sample(&params, &pubkeys, &plaintexts);
+
+        // Create a circuit: ((x1 + x2) * x3 - x1) * 5
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(3);
+        let add_gate = circuit.add_gate(inputs[0], inputs[1]);
+        let mul_gate = circuit.mul_gate(add_gate, inputs[2]);
+        let sub_gate = circuit.sub_gate(mul_gate, inputs[0]);
+        let const_gate = circuit.const_digits_poly(&[1, 0, 1]);
+        let out_gate = circuit.mul_gate(sub_gate, const_gate);
+        circuit.output(vec![out_gate]);
+
+        // Honest encodings satisfy the identity at every gate
+        assert_eq!(
+            dual_eval(&params, &circuit, &bgg_encoding_sampler, &pubkeys, &encodings, &plaintexts),
+            Ok(())
+        );
+
+        // Tampering with the second input is reported at its input gate
+        encodings[2].vector = encodings[2].vector.clone() + encodings[1].vector.clone();
+        assert_eq!(
+            dual_eval(&params, &circuit, &bgg_encoding_sampler, &pubkeys, &encodings, &plaintexts),
+            Err(inputs[1])
+        );
+    }
+}
@@ -0,0 +1,273 @@
+//! Splits the encodings of a ciphertext into shards that are serialized independently, so that
+//! large ciphertexts can be distributed through chunked object storage and evaluators fetch only
+//! the slots they need.
+//!
+//! Every shard carries the digest of the parent ciphertext, its index among the shards and the
+//! range of slots it holds, so that [`EncodedAttributes::reassemble`] can check that the shards
+//! are complete and belong together.
+use super::{
+    encoding_stream::{encodings_digest, read_record, write_record},
+    eval_key::{read_bytes, read_header, read_u64, write_header, write_u64},
+    BggEncoding, EncodedAttributes,
+};
+use crate::{
+    migrate::SHARD_VERSION,
+    poly::{Poly, PolyMatrix},
+};
+use digest::Digest;
+use std::{
+    io::{self, Read, Write},
+    ops::Range,
+};
+
+pub(crate) const SHARD_MAGIC: &[u8; 4] = b"DIOS";
+
+/// Links a shard back to its parent ciphertext.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardMetadata {
+    /// Digest of the number of slots and the records of every slot of the parent.
+    pub parent: Vec<u8>,
+    pub index: usize,
+    pub num_shards: usize,
+    /// The slots of the parent held by the shard, where slot 0 is the constant one.
+    pub slots: Range<usize>,
+    pub total_slots: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodingShard<M: PolyMatrix> {
+    pub metadata: ShardMetadata,
+    pub encodings: Vec<BggEncoding<M>>,
+}
+
+/// Error returned when shards cannot be reassembled into their parent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShardError {
+    NoShards,
+    /// The shard does not have the parent, shard count or slot count of the first shard.
+    ForeignShard { index: usize },
+    DuplicateShard { index: usize },
+    MissingShard { index: usize },
+    /// The slots of the shard do not follow those of the previous shard.
+    SlotGap { index: usize },
+    /// The reassembled encodings do not hash to the parent digest.
+    DigestMismatch,
+}
+
+impl std::fmt::Display for ShardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoShards => write!(f, "no shards to reassemble"),
+            Self::ForeignShard { index } => {
+                write!(f, "shard {} belongs to another ciphertext", index)
+            }
+            Self::DuplicateShard { index } => write!(f, "shard {} is given twice", index),
+            Self::MissingShard { index } => write!(f, "shard {} is missing", index),
+            Self::SlotGap { index } => {
+                write!(f, "shard {} does not continue the slots of the previous shard", index)
+            }
+            Self::DigestMismatch => write!(f, "reassembled encodings do not match the parent"),
+        }
+    }
+}
+
+impl std::error::Error for ShardError {}
+
+impl<M: PolyMatrix> EncodedAttributes<M> {
+    /// Splits the slots, including the constant one, into `chunks` shards of consecutive slots
+    /// whose sizes differ by at most one.
+    pub fn split_rows<H: Digest>(&self, chunks: usize) -> Vec<EncodingShard<M>> {
+        let slots = std::iter::once(self.constant_one_row())
+            .chain(self.attributes())
+            .cloned()
+            .collect::<Vec<_>>();
+        let total_slots = slots.len();
+        assert!(
+            chunks > 0 && chunks <= total_slots,
+            "cannot split {} slots into {} shards",
+            total_slots,
+            chunks
+        );
+        let parent = encodings_digest::<H, M>(&slots);
+        let (size, rest) = (total_slots / chunks, total_slots % chunks);
+        let mut start = 0;
+        (0..chunks)
+            .map(|index| {
+                let end = start + size + usize::from(index < rest);
+                let metadata = ShardMetadata {
+                    parent: parent.clone(),
+                    index,
+                    num_shards: chunks,
+                    slots: start..end,
+                    total_slots,
+                };
+                let encodings = slots[start..end].to_vec();
+                start = end;
+                EncodingShard { metadata, encodings }
+            })
+            .collect()
+    }
+
+    /// Reassembles the ciphertext from all of its shards in any order, checking that they share
+    /// the parent, cover every slot once and hash to the parent digest.
+    pub fn reassemble<H: Digest>(mut shards: Vec<EncodingShard<M>>) -> Result<Self, ShardError> {
+        let first = shards.first().ok_or(ShardError::NoShards)?.metadata.clone();
+        for shard in shards.iter() {
+            let metadata = &shard.metadata;
+            if metadata.parent != first.parent ||
+                metadata.num_shards != first.num_shards ||
+                metadata.total_slots != first.total_slots ||
+                metadata.slots.len() != shard.encodings.len()
+            {
+                return Err(ShardError::ForeignShard { index: metadata.index });
+            }
+        }
+        shards.sort_by_key(|shard| shard.metadata.index);
+        let mut next_slot = 0;
+        for (expected, shard) in shards.iter().enumerate() {
+            let index = shard.metadata.index;
+            if index < expected {
+                return Err(ShardError::DuplicateShard { index });
+            }
+            if index > expected {
+                return Err(ShardError::MissingShard { index: expected });
+            }
+            if shard.metadata.slots.start != next_slot {
+                return Err(ShardError::SlotGap { index });
+            }
+            next_slot = shard.metadata.slots.end;
+        }
+        if shards.len() != first.num_shards {
+            return Err(ShardError::MissingShard { index: shards.len() });
+        }
+        if next_slot != first.total_slots {
+            return Err(ShardError::SlotGap { index: first.num_shards - 1 });
+        }
+        let slots = shards.into_iter().flat_map(|shard| shard.encodings).collect::<Vec<_>>();
+        if encodings_digest::<H, M>(&slots) != first.parent {
+            return Err(ShardError::DigestMismatch);
+        }
+        Ok(Self::from_slots(slots))
+    }
+}
+
+/// Writes the magic `DIOS` and the format version as a `u32`, followed by the length-prefixed
+/// parent digest, the shard index, the number of shards, the first and end slots and the total
+/// number of slots (all `u64` little-endian) and the record of every slot.
+pub fn write_shard<W: Write, M: PolyMatrix>(
+    writer: &mut W,
+    shard: &EncodingShard<M>,
+) -> io::Result<()> {
+    let metadata = &shard.metadata;
+    write_header(writer, SHARD_MAGIC, SHARD_VERSION)?;
+    write_u64(writer, metadata.parent.len() as u64)?;
+    writer.write_all(&metadata.parent)?;
+    for value in [
+        metadata.index,
+        metadata.num_shards,
+        metadata.slots.start,
+        metadata.slots.end,
+        metadata.total_slots,
+    ] {
+        write_u64(writer, value as u64)?;
+    }
+    for encoding in shard.encodings.iter() {
+        write_record(writer, encoding)?;
+    }
+    writer.flush()
+}
+
+pub fn read_shard<R: Read, M: PolyMatrix>(
+    reader: &mut R,
+    params: &<M::P as Poly>::Params,
+) -> io::Result<EncodingShard<M>> {
+    read_header(reader, SHARD_MAGIC, SHARD_VERSION)?;
+    let len = read_u64(reader)?;
+    let parent = read_bytes(reader, len)?;
+    let mut values = [0usize; 5];
+    for value in values.iter_mut() {
+        *value = read_u64(reader)? as usize;
+    }
+    let [index, num_shards, start, end, total_slots] = values;
+    if start > end || end > total_slots {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid slot range"));
+    }
+    let encodings =
+        (start..end).map(|_| read_record(reader, params)).collect::<io::Result<Vec<_>>>()?;
+    let metadata = ShardMetadata { parent, index, num_shards, slots: start..end, total_slots };
+    Ok(EncodingShard { metadata, encodings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::sampler::{BGGEncodingSampler, BGGPublicKeySampler},
+        poly::dcrt::{DCRTPolyHashSampler, DCRTPolyMatrix, DCRTPolyParams, DCRTPolyUniformSampler},
+        utils::{create_bit_random_poly, create_random_poly},
+    };
+    use keccak_asm::Keccak256;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_split_and_reassemble() {
+        let params = DCRTPolyParams::default();
+        let key: [u8; 32] = rand::random();
+        let d = 2;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let pubkeys = bgg_sampler.sample(&params, b"shard", &[true; 4]);
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let plaintexts = (0..4).map(|_| create_random_poly(&params)).collect::<Vec<_>>();
+        let encodings = bgg_sampler.sample(&params, &pubkeys, &plaintexts);
+        let attributes = EncodedAttributes::from_slots(encodings.clone());
+
+        // Five slots are split into shards of 2, 2 and 1 slots
+        let shards = attributes.split_rows::<Keccak256>(3);
+        let ranges = shards.iter().map(|shard| shard.metadata.slots.clone()).collect::<Vec<_>>();
+        assert_eq!(ranges, vec![0..2, 2..4, 4..5]);
+
+        // Every shard round-trips on its own and the shards reassemble in any order
+        let mut read_shards = shards
+            .iter()
+            .map(|shard| {
+                let mut cursor = Cursor::new(Vec::new());
+                write_shard(&mut cursor, shard).unwrap();
+                cursor.set_position(0);
+                read_shard::<_, DCRTPolyMatrix>(&mut cursor, &params).unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(read_shards, shards);
+        read_shards.reverse();
+        let reassembled = EncodedAttributes::reassemble::<Keccak256>(read_shards).unwrap();
+        assert_eq!(reassembled.into_slots(), encodings);
+
+        // Missing, duplicated and tampered shards are rejected
+        let missing = vec![shards[0].clone(), shards[2].clone()];
+        assert_eq!(
+            EncodedAttributes::reassemble::<Keccak256>(missing).unwrap_err(),
+            ShardError::MissingShard { index: 1 }
+        );
+        let duplicated = vec![shards[0].clone(), shards[1].clone(), shards[1].clone()];
+        assert_eq!(
+            EncodedAttributes::reassemble::<Keccak256>(duplicated).unwrap_err(),
+            ShardError::DuplicateShard { index: 1 }
+        );
+        let mut tampered = shards.clone();
+        tampered[1].encodings.swap(0, 1);
+        assert_eq!(
+            EncodedAttributes::reassemble::<Keccak256>(tampered).unwrap_err(),
+            ShardError::DigestMismatch
+        );
+
+        // Shards of another ciphertext do not mix
+        let other = EncodedAttributes::from_slots(encodings[..4].to_vec());
+        let other = other.split_rows::<Keccak256>(2);
+        let mixed = vec![shards[0].clone(), other[1].clone(), shards[2].clone()];
+        assert!(matches!(
+            EncodedAttributes::reassemble::<Keccak256>(mixed).unwrap_err(),
+            ShardError::ForeignShard { .. }
+        ));
+    }
+}
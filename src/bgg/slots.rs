@@ -0,0 +1,137 @@
+//! The slots of a ciphertext on either side of the evaluation: the slot of the constant one
+//! followed by one slot per attribute. [`crate::bgg::EncodedAttributes`] keeps encodings in these
+//! slots and [`AttributeKeys`] keeps public keys in them, so that neither callers on the key side
+//! nor on the attribute side manage the constant-one slot by hand.
+
+use super::{
+    circuit::{Evaluable, PolyCircuit},
+    gates::{KeySideEval, StandardGates},
+    BggPublicKey,
+};
+use crate::poly::{Poly, PolyMatrix};
+use std::ops::Range;
+
+/// The constant-one slot followed by the attribute slots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeSlots<T> {
+    slots: Vec<T>,
+}
+
+/// The public keys of a ciphertext, as returned by
+/// [`crate::bgg::sampler::BGGPublicKeySampler::sample_attributes`].
+pub type AttributeKeys<M> = AttributeSlots<BggPublicKey<M>>;
+
+impl<T> AttributeSlots<T> {
+    /// Wraps slots whose first slot holds the constant one, as returned by
+    /// [`crate::bgg::sampler::BGGPublicKeySampler::sample`] and
+    /// [`crate::bgg::sampler::BGGEncodingSampler::sample`].
+    pub fn from_slots(slots: Vec<T>) -> Self {
+        assert!(!slots.is_empty(), "the constant-one slot is missing");
+        Self { slots }
+    }
+
+    /// The slot of the constant one, for gates such as constants that need it.
+    pub fn constant_one_row(&self) -> &T {
+        &self.slots[0]
+    }
+
+    pub fn attributes(&self) -> &[T] {
+        &self.slots[1..]
+    }
+
+    /// The slot of the constant one followed by the attribute slots.
+    pub fn slots(&self) -> &[T] {
+        &self.slots
+    }
+
+    /// The number of attributes, not counting the constant one.
+    pub fn len(&self) -> usize {
+        self.slots.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn into_slots(self) -> Vec<T> {
+        self.slots
+    }
+}
+
+impl<T: Evaluable> AttributeSlots<T> {
+    /// Evaluates `circuit` with one input per attribute.
+    pub fn eval(&self, params: &T::Params, circuit: &PolyCircuit) -> Vec<T> {
+        circuit.eval(params, self.constant_one_row(), self.attributes())
+    }
+}
+
+impl<M: PolyMatrix> AttributeKeys<M> {
+    /// The public key of the bias term with coefficients `digits`, which is what a constant gate
+    /// evaluates to. Adding it to the key of `f(x)` gives the key of `f(x) + c`.
+    pub fn m_eval_bias(&self, params: &<M::P as Poly>::Params, digits: &[u32]) -> BggPublicKey<M> {
+        StandardGates.const_key(params, self.constant_one_row(), digits)
+    }
+
+    /// The public key of `Σ x_i * y_i` for the attributes `x` at `range_a` and `y` at `range_b`,
+    /// which must be disjoint ranges of the same nonzero length, computed in one fused pass.
+    pub fn m_eval_inner_product(
+        &self,
+        range_a: Range<usize>,
+        range_b: Range<usize>,
+    ) -> BggPublicKey<M> {
+        check_inner_product_ranges(&range_a, &range_b);
+        let attributes = self.attributes();
+        BggPublicKey::inner_product(&attributes[range_a], &attributes[range_b])
+    }
+}
+
+/// Asserts that `range_a` and `range_b` are disjoint ranges of the same nonzero length.
+pub(crate) fn check_inner_product_ranges(range_a: &Range<usize>, range_b: &Range<usize>) {
+    assert_eq!(range_a.len(), range_b.len(), "inner product ranges must have the same length");
+    assert!(!range_a.is_empty(), "inner product of empty ranges");
+    assert!(
+        range_a.end <= range_b.start || range_b.end <= range_a.start,
+        "inner product ranges {:?} and {:?} overlap",
+        range_a,
+        range_b
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::sampler::BGGPublicKeySampler,
+        poly::dcrt::{params::DCRTPolyParams, DCRTPolyHashSampler},
+    };
+    use keccak_asm::Keccak256;
+
+    #[test]
+    fn test_attribute_keys_bias() {
+        let params = DCRTPolyParams::default();
+        let key: [u8; 32] = rand::random();
+        let d = 3;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let pubkeys = bgg_sampler.sample_attributes(&params, b"bias", &[true; 2]);
+        let slots = bgg_sampler.sample(&params, b"bias", &[true; 2]);
+        assert_eq!(pubkeys.len(), 2);
+        assert_eq!(pubkeys.constant_one_row(), &slots[0]);
+        assert_eq!(pubkeys.attributes(), &slots[1..]);
+
+        // f(x) = x1 * x2 + 1 through the constant-one gate
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        let mul_gate = circuit.mul_gate(inputs[0], inputs[1]);
+        let one_gate = circuit.const_one_gate();
+        let add_gate = circuit.add_gate(mul_gate, one_gate);
+        circuit.output(vec![add_gate]);
+        let result = pubkeys.eval(&params, &circuit);
+
+        // The bias of 1 is the constant-one key itself, added to the key of x1 * x2
+        let bias = pubkeys.m_eval_bias(&params, &[1]);
+        assert_eq!(&bias, pubkeys.constant_one_row());
+        let attributes = pubkeys.attributes();
+        let expected = attributes[0].clone() * &attributes[1] + bias;
+        assert_eq!(result[0], expected);
+    }
+}
@@ -0,0 +1,398 @@
+//! A minimal C ABI for the evaluation path, so that services written in other languages can
+//! sample public keys, encode attributes, evaluate circuits and decode outputs.
+//!
+//! Every object is an opaque pointer owned by the caller and released with its `_free`
+//! function. Polynomials cross the boundary in the canonical encoding of
+//! [`crate::poly::canonical`], [`diamond_poly_bytes`] bytes each, and circuits as the JSON of
+//! [`SerializablePolyCircuit`]. Invalid arguments make constructors return null and other
+//! functions return `-1`; the header is generated with `just capi`. Public keys and encodings
+//! carry their key epoch, and evaluations reject encodings outside the accepted epochs.
+//!
+//! Panics are caught at the boundary and reported the same way, since unwinding into C aborts
+//! the host process; this requires building with `panic = "unwind"`, which the release profile
+//! does not use.
+use crate::{
+    bgg::{
+        circuit::{serde::SerializablePolyCircuit, PolyCircuit},
+        epoch::EpochPolicy,
+        sampler::{BGGEncodingSampler, BGGPublicKeySampler},
+        BggEncoding, BggPublicKey,
+    },
+    poly::{
+        canonical::decode_poly,
+        dcrt::{
+            DCRTPoly, DCRTPolyHashSampler, DCRTPolyMatrix, DCRTPolyParams, DCRTPolyUniformSampler,
+        },
+        sampler::PolyUniformSampler,
+        PolyMatrix, PolyParams,
+    },
+};
+use keccak_asm::Keccak256;
+use std::{
+    ffi::{c_char, CStr},
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+pub struct DiamondParams(DCRTPolyParams);
+
+/// The public keys of the constant one and of every attribute, sampled for a key epoch.
+pub struct DiamondPubkeys {
+    pubkeys: Vec<BggPublicKey<DCRTPolyMatrix>>,
+    epoch: u64,
+}
+
+/// The secret of the encoder, needed to encode attributes and decode outputs.
+pub struct DiamondEncoder(BGGEncodingSampler<DCRTPolyUniformSampler>);
+
+/// Encodings and the key epoch of the public keys they were made for.
+pub struct DiamondEncodings {
+    encodings: Vec<BggEncoding<DCRTPolyMatrix>>,
+    epoch: u64,
+}
+
+pub struct DiamondCircuit(PolyCircuit);
+
+/// Runs the body of an exported function, mapping a panic to `None`.
+fn catch<T>(body: impl FnOnce() -> Option<T>) -> Option<T> {
+    panic::catch_unwind(AssertUnwindSafe(body)).ok().flatten()
+}
+
+/// Like [`catch`], boxing the result for the caller or returning null.
+fn catch_ptr<T>(body: impl FnOnce() -> Option<T>) -> *mut T {
+    catch(body).map_or(ptr::null_mut(), |value| Box::into_raw(Box::new(value)))
+}
+
+/// Like [`catch`] for functions returning a status, `-1` on failure.
+fn catch_status(body: impl FnOnce() -> Option<()>) -> i32 {
+    catch(body).map_or(-1, |()| 0)
+}
+
+unsafe fn bytes<'a>(ptr: *const u8, len: usize) -> Option<&'a [u8]> {
+    if ptr.is_null() {
+        return (len == 0).then_some(&[][..]);
+    }
+    Some(slice::from_raw_parts(ptr, len))
+}
+
+fn decode_polys(params: &DCRTPolyParams, bytes: &[u8]) -> Option<Vec<DCRTPoly>> {
+    let poly_bytes = canonical_poly_len(params);
+    if bytes.len() % poly_bytes != 0 {
+        return None;
+    }
+    bytes.chunks_exact(poly_bytes).map(|chunk| decode_poly(params, chunk).ok()).collect()
+}
+
+fn canonical_poly_len(params: &DCRTPolyParams) -> usize {
+    params.ring_dimension() as usize * params.modulus_bits().div_ceil(8)
+}
+
+/// Creates parameters like [`DCRTPolyParams::try_new`], or returns null if they are invalid.
+#[no_mangle]
+pub extern "C" fn diamond_params_new(
+    ring_dimension: u32,
+    crt_depth: usize,
+    crt_bits: usize,
+    base_bits: u32,
+) -> *mut DiamondParams {
+    catch_ptr(|| {
+        DCRTPolyParams::try_new(ring_dimension, crt_depth, crt_bits, base_bits)
+            .ok()
+            .map(DiamondParams)
+    })
+}
+
+/// The number of bytes of one canonically encoded polynomial.
+///
+/// # Safety
+/// `params` must come from [`diamond_params_new`].
+#[no_mangle]
+pub unsafe extern "C" fn diamond_poly_bytes(params: *const DiamondParams) -> usize {
+    catch(|| params.as_ref().map(|params| canonical_poly_len(&params.0))).unwrap_or(0)
+}
+
+/// # Safety
+/// `params` must come from [`diamond_params_new`] or be null, and is not used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn diamond_params_free(params: *mut DiamondParams) {
+    catch(|| (!params.is_null()).then(|| drop(Box::from_raw(params))));
+}
+
+/// Samples the public keys of `num_inputs` attributes with secret dimension `d` from the
+/// 32-byte `hash_key` and the tag, for the key epoch `epoch`.
+///
+/// # Safety
+/// `params` must come from [`diamond_params_new`], `hash_key` must point to 32 bytes and `tag`
+/// to `tag_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn diamond_pubkey_new(
+    params: *const DiamondParams,
+    hash_key: *const u8,
+    tag: *const u8,
+    tag_len: usize,
+    d: usize,
+    num_inputs: usize,
+    epoch: u64,
+) -> *mut DiamondPubkeys {
+    catch_ptr(|| {
+        let params = params.as_ref()?;
+        let hash_key = bytes(hash_key, 32)?.try_into().ok()?;
+        let tag = bytes(tag, tag_len)?;
+        if d == 0 {
+            return None;
+        }
+        let sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(hash_key, d)
+            .at_epoch(epoch);
+        let pubkeys = sampler.sample(&params.0, tag, &vec![true; num_inputs]);
+        Some(DiamondPubkeys { pubkeys, epoch })
+    })
+}
+
+/// # Safety
+/// `pubkeys` must come from [`diamond_pubkey_new`] or be null, and is not used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn diamond_pubkeys_free(pubkeys: *mut DiamondPubkeys) {
+    catch(|| (!pubkeys.is_null()).then(|| drop(Box::from_raw(pubkeys))));
+}
+
+/// Creates an encoder from `d` secret polynomials and the standard deviation of the errors.
+///
+/// # Safety
+/// `params` must come from [`diamond_params_new`] and `secrets` point to `secrets_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn diamond_encoder_new(
+    params: *const DiamondParams,
+    secrets: *const u8,
+    secrets_len: usize,
+    gauss_sigma: f64,
+) -> *mut DiamondEncoder {
+    catch_ptr(|| {
+        let params = params.as_ref()?;
+        let secrets = decode_polys(&params.0, bytes(secrets, secrets_len)?)
+            .filter(|secrets| !secrets.is_empty())?;
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        Some(DiamondEncoder(BGGEncodingSampler::new(
+            &params.0,
+            &secrets,
+            uniform_sampler,
+            gauss_sigma,
+        )))
+    })
+}
+
+/// # Safety
+/// `encoder` must come from [`diamond_encoder_new`] or be null, and is not used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn diamond_encoder_free(encoder: *mut DiamondEncoder) {
+    catch(|| (!encoder.is_null()).then(|| drop(Box::from_raw(encoder))));
+}
+
+/// Encodes one plaintext polynomial per attribute of `pubkeys`. The result starts with the
+/// encoding of the constant one and has the epoch of `pubkeys`.
+///
+/// # Safety
+/// The objects must come from their constructors and `plaintexts` point to `plaintexts_len`
+/// bytes.
+#[no_mangle]
+pub unsafe extern "C" fn diamond_encode(
+    params: *const DiamondParams,
+    encoder: *const DiamondEncoder,
+    pubkeys: *const DiamondPubkeys,
+    plaintexts: *const u8,
+    plaintexts_len: usize,
+) -> *mut DiamondEncodings {
+    catch_ptr(|| {
+        let (params, encoder, pubkeys) = (params.as_ref()?, encoder.as_ref()?, pubkeys.as_ref()?);
+        let plaintexts = decode_polys(&params.0, bytes(plaintexts, plaintexts_len)?)?;
+        let d = pubkeys.pubkeys.first()?.matrix.row_size() - 1;
+        if plaintexts.len() + 1 != pubkeys.pubkeys.len() || encoder.0.secret_vec.col_size() != d + 1
+        {
+            return None;
+        }
+        let encodings = encoder.0.sample(&params.0, &pubkeys.pubkeys, &plaintexts);
+        Some(DiamondEncodings { encodings, epoch: pubkeys.epoch })
+    })
+}
+
+/// # Safety
+/// `encodings` must come from [`diamond_encode`] or [`diamond_eval_circuit`].
+#[no_mangle]
+pub unsafe extern "C" fn diamond_encodings_len(encodings: *const DiamondEncodings) -> usize {
+    catch(|| encodings.as_ref().map(|encodings| encodings.encodings.len())).unwrap_or(0)
+}
+
+/// # Safety
+/// `encodings` must come from [`diamond_encode`] or [`diamond_eval_circuit`] or be null, and is
+/// not used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn diamond_encodings_free(encodings: *mut DiamondEncodings) {
+    catch(|| (!encodings.is_null()).then(|| drop(Box::from_raw(encodings))));
+}
+
+/// Parses a circuit from the NUL-terminated JSON of [`SerializablePolyCircuit`].
+///
+/// # Safety
+/// `json` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn diamond_circuit_from_json(json: *const c_char) -> *mut DiamondCircuit {
+    catch_ptr(|| {
+        if json.is_null() {
+            return None;
+        }
+        let json = CStr::from_ptr(json).to_str().ok()?;
+        let circuit = SerializablePolyCircuit::try_from_json_str(json).ok()?;
+        Some(DiamondCircuit(circuit.try_to_circuit().ok()?))
+    })
+}
+
+/// # Safety
+/// `circuit` must come from [`diamond_circuit_from_json`] or be null, and is not used
+/// afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn diamond_circuit_free(circuit: *mut DiamondCircuit) {
+    catch(|| (!circuit.is_null()).then(|| drop(Box::from_raw(circuit))));
+}
+
+/// Evaluates the circuit over encodings from [`diamond_encode`], returning one encoding per
+/// output, or null if the encodings are not of the epoch `current_epoch` or of the `max_age`
+/// epochs before it.
+///
+/// # Safety
+/// The objects must come from their constructors.
+#[no_mangle]
+pub unsafe extern "C" fn diamond_eval_circuit(
+    params: *const DiamondParams,
+    circuit: *const DiamondCircuit,
+    encodings: *const DiamondEncodings,
+    current_epoch: u64,
+    max_age: u64,
+) -> *mut DiamondEncodings {
+    catch_ptr(|| {
+        let (params, circuit) = (params.as_ref()?, circuit.as_ref()?);
+        let encodings = encodings.as_ref()?;
+        EpochPolicy::new(current_epoch, max_age).check(encodings.epoch).ok()?;
+        let (one, inputs) = encodings.encodings.split_first()?;
+        if circuit.0.num_input() != inputs.len() || circuit.0.num_output() == 0 {
+            return None;
+        }
+        let outputs = circuit.0.eval(&params.0, one, inputs);
+        Some(DiamondEncodings { encodings: outputs, epoch: encodings.epoch })
+    })
+}
+
+/// Decodes the 0/1 coefficients of encoding `index` with the secret of `encoder`, writing one
+/// byte per coefficient to `out`, which must hold the ring dimension many bytes. Returns `0` on
+/// success and `-1` on invalid arguments.
+///
+/// # Safety
+/// The objects must come from their constructors and `out` point to `out_len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn diamond_decode(
+    params: *const DiamondParams,
+    encoder: *const DiamondEncoder,
+    encodings: *const DiamondEncodings,
+    index: usize,
+    out: *mut u8,
+    out_len: usize,
+) -> i32 {
+    catch_status(|| {
+        let (params, encoder) = (params.as_ref()?, encoder.as_ref()?);
+        let encoding = encodings.as_ref()?.encodings.get(index)?;
+        let n = params.0.ring_dimension() as usize;
+        if out.is_null() || out_len < n {
+            return None;
+        }
+        let bits = encoder.0.decode_bits(&params.0, encoding);
+        let out = slice::from_raw_parts_mut(out, out_len);
+        for (byte, bit) in out.iter_mut().zip(bits) {
+            *byte = bit as u8;
+        }
+        Some(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{poly::canonical::encode_poly, utils::create_bit_random_poly};
+    use std::{ffi::CString, ptr};
+
+    #[test]
+    fn test_capi_eval_path() {
+        unsafe {
+            let params = diamond_params_new(4, 2, 17, 1);
+            assert!(!params.is_null());
+            let rust_params = &(*params).0;
+            let poly_bytes = diamond_poly_bytes(params);
+
+            // Two attributes under a secret of dimension 2
+            let hash_key = [7u8; 32];
+            let tag = b"capi";
+            let pubkeys = diamond_pubkey_new(params, hash_key.as_ptr(), tag.as_ptr(), 4, 2, 2, 1);
+            let secrets = [create_bit_random_poly(rust_params), create_bit_random_poly(rust_params)]
+                .iter()
+                .flat_map(encode_poly)
+                .collect::<Vec<_>>();
+            let encoder = diamond_encoder_new(params, secrets.as_ptr(), secrets.len(), 0.0);
+            let x = create_bit_random_poly(rust_params);
+            let plaintexts =
+                [encode_poly(&x), encode_poly(&DCRTPoly::const_one(rust_params))].concat();
+            assert_eq!(plaintexts.len(), 2 * poly_bytes);
+            let encodings =
+                diamond_encode(params, encoder, pubkeys, plaintexts.as_ptr(), plaintexts.len());
+            assert_eq!(diamond_encodings_len(encodings), 3);
+
+            // x * 1 evaluates and decodes to x
+            let mut circuit = PolyCircuit::new();
+            let inputs = circuit.input(2);
+            let mul_gate = circuit.mul_gate(inputs[0], inputs[1]);
+            circuit.output(vec![mul_gate]);
+            let json = CString::new(SerializablePolyCircuit::from_circuit(&circuit).to_json_str())
+                .unwrap();
+            let circuit = diamond_circuit_from_json(json.as_ptr());
+            let outputs = diamond_eval_circuit(params, circuit, encodings, 1, 0);
+            assert_eq!(diamond_encodings_len(outputs), 1);
+            let mut bits = vec![0u8; 4];
+            assert_eq!(diamond_decode(params, encoder, outputs, 0, bits.as_mut_ptr(), 4), 0);
+            let expected = x.to_bool_vec().into_iter().map(u8::from).collect::<Vec<_>>();
+            assert_eq!(bits, expected);
+
+            // Invalid arguments are rejected
+            assert!(diamond_params_new(3, 2, 17, 1).is_null());
+            assert!(diamond_encode(params, encoder, pubkeys, plaintexts.as_ptr(), 1).is_null());
+            assert!(diamond_circuit_from_json(c"{".as_ptr()).is_null());
+            assert_eq!(diamond_decode(params, encoder, outputs, 1, bits.as_mut_ptr(), 4), -1);
+            assert_eq!(diamond_decode(params, encoder, outputs, 0, ptr::null_mut(), 4), -1);
+
+            // Encodings of another epoch are rejected
+            assert!(diamond_eval_circuit(params, circuit, encodings, 2, 0).is_null());
+            assert!(diamond_eval_circuit(params, circuit, encodings, 0, 5).is_null());
+
+            // Encodings of mismatched dimensions make the evaluation panic, which is caught
+            let other_pubkeys =
+                diamond_pubkey_new(params, hash_key.as_ptr(), tag.as_ptr(), 4, 3, 2, 1);
+            let slots = &(*encodings).encodings;
+            let mixed = DiamondEncodings {
+                encodings: vec![
+                    slots[0].clone(),
+                    slots[1].clone(),
+                    BggEncoding::new(
+                        slots[2].vector.clone(),
+                        (*other_pubkeys).pubkeys[2].clone(),
+                        None,
+                    ),
+                ],
+                epoch: 1,
+            };
+            assert!(diamond_eval_circuit(params, circuit, &mixed, 1, 0).is_null());
+            diamond_pubkeys_free(other_pubkeys);
+
+            diamond_encodings_free(outputs);
+            diamond_circuit_free(circuit);
+            diamond_encodings_free(encodings);
+            diamond_encoder_free(encoder);
+            diamond_pubkeys_free(pubkeys);
+            diamond_params_free(params);
+        }
+    }
+}
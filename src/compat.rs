@@ -0,0 +1,166 @@
+//! Consistency checks between the polynomial backend and a reference implementation over plain
+//! coefficients, so that divergence between backends is caught early.
+//!
+//! The reference path recomputes the encoding vectors `(s, -1) * (A - x * G)` with schoolbook
+//! negacyclic multiplication over big integers and compares them bit-exactly with the
+//! coefficients of the backend encodings after CRT interpolation. The encodings must be sampled
+//! without error, i.e. with `gauss_sigma = 0`.
+use crate::{
+    bgg::BggEncoding,
+    poly::{plaintext::modulus_biguint, Poly, PolyElem, PolyMatrix, PolyParams},
+};
+use num_bigint::BigUint;
+
+/// The first coefficient at which the backend and the reference encodings differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub slot: usize,
+    pub column: usize,
+    pub coeff: usize,
+    pub backend: BigUint,
+    pub reference: BigUint,
+}
+
+fn to_coeffs<P: Poly>(poly: &P) -> Vec<BigUint> {
+    poly.coeffs().iter().map(|coeff| coeff.to_biguint().clone()).collect()
+}
+
+/// Multiplies two polynomials of `Z_q[x]/(x^n + 1)` given by their coefficients.
+pub fn negacyclic_mul(q: &BigUint, a: &[BigUint], b: &[BigUint]) -> Vec<BigUint> {
+    let n = a.len();
+    assert_eq!(b.len(), n, "operands must have the same ring dimension");
+    let mut result = vec![BigUint::ZERO; n];
+    for i in 0..n {
+        for j in 0..n {
+            let product = &a[i] * &b[j] % q;
+            let k = i + j;
+            if k < n {
+                result[k] = (&result[k] + product) % q;
+            } else {
+                result[k - n] = (&result[k - n] + q - product) % q;
+            }
+        }
+    }
+    result
+}
+
+/// Computes the coefficients of the encoding vector of `plaintext` under `pubkey`, where
+/// `secrets` are the `d` secret polynomials given to the encoding sampler.
+pub fn reference_encode<M: PolyMatrix>(
+    params: &<M::P as Poly>::Params,
+    secrets: &[M::P],
+    pubkey: &M,
+    plaintext: &M::P,
+) -> Vec<Vec<BigUint>> {
+    let q = modulus_biguint::<M::P>(params);
+    let n = params.ring_dimension() as usize;
+    let log_base_q = params.modulus_digits();
+    let base = BigUint::from(1u8) << params.base_bits();
+    let mut secret_vec = secrets.iter().map(to_coeffs).collect::<Vec<_>>();
+    let mut minus_one = vec![BigUint::ZERO; n];
+    minus_one[0] = &q - 1u8;
+    secret_vec.push(minus_one);
+    let x = to_coeffs(plaintext);
+    let (nrow, ncol) = pubkey.size();
+    assert_eq!(nrow, secret_vec.len(), "the public key must have d + 1 rows");
+    (0..ncol)
+        .map(|j| {
+            let mut sum = vec![BigUint::ZERO; n];
+            for (r, s) in secret_vec.iter().enumerate() {
+                // A[r][j] - x * G[r][j], where G[r][r * L + k] = base^k
+                let mut entry = to_coeffs(&pubkey.entry(r, j));
+                if j / log_base_q == r {
+                    let digit = base.pow((j % log_base_q) as u32) % &q;
+                    for (e, xi) in entry.iter_mut().zip(x.iter()) {
+                        *e = (&*e + &q - xi * &digit % &q) % &q;
+                    }
+                }
+                for (acc, term) in sum.iter_mut().zip(negacyclic_mul(&q, s, &entry)) {
+                    *acc = (&*acc + term) % &q;
+                }
+            }
+            sum
+        })
+        .collect()
+}
+
+/// Returns the first coefficient at which `encodings` differ from the reference encodings of the
+/// constant one followed by `plaintexts`.
+pub fn first_mismatch<M: PolyMatrix>(
+    params: &<M::P as Poly>::Params,
+    secrets: &[M::P],
+    plaintexts: &[M::P],
+    encodings: &[BggEncoding<M>],
+) -> Option<Mismatch> {
+    assert_eq!(encodings.len(), plaintexts.len() + 1, "one encoding per plaintext and the one");
+    let one = M::P::const_one(params);
+    let plaintexts = std::iter::once(&one).chain(plaintexts.iter());
+    for (slot, (encoding, plaintext)) in encodings.iter().zip(plaintexts).enumerate() {
+        let reference = reference_encode(params, secrets, &encoding.pubkey.matrix, plaintext);
+        for (column, expected) in reference.into_iter().enumerate() {
+            let backend = to_coeffs(&encoding.vector.entry(0, column));
+            for (coeff, (b, r)) in backend.into_iter().zip(expected).enumerate() {
+                if b != r {
+                    return Some(Mismatch { slot, column, coeff, backend: b, reference: r });
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Panics with the first mismatch between the backend and the reference encodings.
+pub fn assert_encodings_match<M: PolyMatrix>(
+    params: &<M::P as Poly>::Params,
+    secrets: &[M::P],
+    plaintexts: &[M::P],
+    encodings: &[BggEncoding<M>],
+) {
+    if let Some(mismatch) = first_mismatch(params, secrets, plaintexts, encodings) {
+        panic!("backend encodings diverge from the reference: {:?}", mismatch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::sampler::{BGGEncodingSampler, BGGPublicKeySampler},
+        poly::dcrt::{DCRTPoly, DCRTPolyHashSampler, DCRTPolyParams, DCRTPolyUniformSampler},
+        utils::{create_bit_random_poly, create_random_poly},
+    };
+    use keccak_asm::Keccak256;
+
+    #[test]
+    fn test_negacyclic_mul() {
+        // x * x^3 = x^4 = -1 in Z_17[x]/(x^4 + 1)
+        let q = BigUint::from(17u8);
+        let x = [0u8, 1, 0, 0].map(BigUint::from);
+        let x3 = [0u8, 0, 0, 1].map(BigUint::from);
+        assert_eq!(negacyclic_mul(&q, &x, &x3), [16u8, 0, 0, 0].map(BigUint::from));
+    }
+
+    #[test]
+    fn test_backend_matches_reference() {
+        let params = DCRTPolyParams::default();
+        let key: [u8; 32] = rand::random();
+        let d = 2;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let pubkeys = bgg_sampler.sample(&params, b"compat", &[true, false]);
+        let secrets = (0..d).map(|_| create_bit_random_poly(&params)).collect::<Vec<_>>();
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let plaintexts = vec![create_random_poly(&params), create_random_poly(&params)];
+        let encodings = bgg_sampler.sample(&params, &pubkeys, &plaintexts);
+
+        // The sampled encodings agree with the reference path
+        assert_encodings_match(&params, &secrets, &plaintexts, &encodings);
+
+        // A different plaintext is reported at the slot that encodes it
+        let shifted = plaintexts[1].clone() + DCRTPoly::const_one(&params);
+        let wrong = vec![plaintexts[0].clone(), shifted];
+        let mismatch = first_mismatch(&params, &secrets, &wrong, &encodings).unwrap();
+        assert_eq!(mismatch.slot, 2);
+        assert_ne!(mismatch.backend, mismatch.reference);
+    }
+}
@@ -0,0 +1,212 @@
+//! A corpus of canonical boolean circuits with their expected outputs on seeded attribute
+//! vectors, and a runner that every polynomial backend must pass.
+//!
+//! The expected outputs are computed over plain booleans when the corpus is built, independently
+//! of the circuit evaluation, so a backend passes only if evaluating the circuits over its
+//! polynomials agrees with them bit for bit.
+use crate::{bgg::circuit::PolyCircuit, poly::Poly};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+/// Seed of the attribute vectors and of the random circuit, fixed so that every backend is run
+/// on the same corpus.
+pub const CORPUS_SEED: u64 = 0x6469_616d_6f6e_6421;
+/// Number of attribute vectors per circuit.
+pub const VECTORS_PER_CASE: usize = 8;
+
+/// A circuit of the corpus with attribute vectors and the outputs expected for each of them.
+#[derive(Debug, Clone)]
+pub struct ConformanceCase {
+    pub name: &'static str,
+    pub circuit: PolyCircuit,
+    pub vectors: Vec<(Vec<bool>, Vec<bool>)>,
+}
+
+/// The first output on which a backend disagrees with the corpus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConformanceFailure {
+    pub case: &'static str,
+    pub vector: usize,
+    pub output: usize,
+}
+
+impl std::fmt::Display for ConformanceFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: output {} differs on vector {}", self.case, self.output, self.vector)
+    }
+}
+
+impl std::error::Error for ConformanceFailure {}
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+impl Op {
+    fn gate(self, circuit: &mut PolyCircuit, left: usize, right: usize) -> usize {
+        match self {
+            Self::And => circuit.and_gate(left, right),
+            Self::Or => circuit.or_gate(left, right),
+            Self::Xor => circuit.xor_gate(left, right),
+            Self::Not => circuit.not_gate(left),
+        }
+    }
+
+    fn apply(self, left: bool, right: bool) -> bool {
+        match self {
+            Self::And => left && right,
+            Self::Or => left || right,
+            Self::Xor => left ^ right,
+            Self::Not => !left,
+        }
+    }
+}
+
+fn case<F: Fn(&[bool]) -> Vec<bool>>(
+    name: &'static str,
+    circuit: PolyCircuit,
+    rng: &mut ChaCha20Rng,
+    reference: F,
+) -> ConformanceCase {
+    let vectors = (0..VECTORS_PER_CASE)
+        .map(|_| {
+            let attributes = (0..circuit.num_input()).map(|_| rng.random()).collect::<Vec<_>>();
+            let expected = reference(&attributes);
+            (attributes, expected)
+        })
+        .collect();
+    ConformanceCase { name, circuit, vectors }
+}
+
+/// ANDs 8 attributes pairwise in a balanced tree.
+fn and_tree() -> PolyCircuit {
+    let mut circuit = PolyCircuit::new();
+    let mut level = circuit.input(8);
+    while level.len() > 1 {
+        level = level.chunks(2).map(|pair| circuit.and_gate(pair[0], pair[1])).collect();
+    }
+    circuit.output(level);
+    circuit
+}
+
+/// XORs 8 attributes in a chain.
+fn parity() -> PolyCircuit {
+    let mut circuit = PolyCircuit::new();
+    let inputs = circuit.input(8);
+    let output = inputs[1..].iter().fold(inputs[0], |acc, &input| circuit.xor_gate(acc, input));
+    circuit.output(vec![output]);
+    circuit
+}
+
+/// Outputs `a > b` for 4-bit integers given little-endian as the attributes `a_0..a_3` followed
+/// by `b_0..b_3`, scanning from the least significant bit.
+fn comparator() -> PolyCircuit {
+    let mut circuit = PolyCircuit::new();
+    let inputs = circuit.input(8);
+    let (a, b) = inputs.split_at(4);
+    let mut greater = circuit.const_zero_gate();
+    for i in 0..4 {
+        // greater = (a_i AND NOT b_i) OR (NOT (a_i XOR b_i) AND greater)
+        let not_b = circuit.not_gate(b[i]);
+        let bit_greater = circuit.and_gate(a[i], not_b);
+        let equal = circuit.xnor_gate(a[i], b[i]);
+        let carried = circuit.and_gate(equal, greater);
+        greater = circuit.or_gate(bit_greater, carried);
+    }
+    circuit.output(vec![greater]);
+    circuit
+}
+
+/// Three levels of 4 gates drawn from `rng`, each over two gates of the previous level, with the
+/// last level as outputs.
+fn random_layers(rng: &mut ChaCha20Rng) -> (PolyCircuit, Vec<Vec<(Op, usize, usize)>>) {
+    let ops = [Op::And, Op::Or, Op::Xor, Op::Not];
+    let mut circuit = PolyCircuit::new();
+    let mut level = circuit.input(6);
+    let mut layers = Vec::new();
+    for _ in 0..3 {
+        let layer = (0..4)
+            .map(|_| {
+                let op = ops[rng.random_range(0..ops.len())];
+                (op, rng.random_range(0..level.len()), rng.random_range(0..level.len()))
+            })
+            .collect::<Vec<_>>();
+        level = layer.iter().map(|&(op, l, r)| op.gate(&mut circuit, level[l], level[r])).collect();
+        layers.push(layer);
+    }
+    circuit.output(level);
+    (circuit, layers)
+}
+
+/// Builds the corpus: an AND-tree, a parity, a comparator and a 3-level random circuit.
+pub fn corpus() -> Vec<ConformanceCase> {
+    let mut rng = ChaCha20Rng::seed_from_u64(CORPUS_SEED);
+    let (random_circuit, layers) = random_layers(&mut rng);
+    vec![
+        case("and_tree", and_tree(), &mut rng, |x| vec![x.iter().all(|&b| b)]),
+        case("parity", parity(), &mut rng, |x| vec![x.iter().fold(false, |acc, &b| acc ^ b)]),
+        case("comparator", comparator(), &mut rng, |x| {
+            let value =
+                |bits: &[bool]| bits.iter().rev().fold(0u8, |acc, &b| (acc << 1) | b as u8);
+            vec![value(&x[..4]) > value(&x[4..])]
+        }),
+        case("random_3_levels", random_circuit, &mut rng, |x| {
+            layers.iter().fold(x.to_vec(), |level, layer| {
+                layer.iter().map(|&(op, l, r)| op.apply(level[l], level[r])).collect()
+            })
+        }),
+    ]
+}
+
+/// Evaluates every circuit of the corpus over the constant polynomials 0 and 1 of the backend
+/// `P` and returns the first output that differs from the expected one. Panics if a circuit
+/// returns another number of outputs than expected.
+pub fn run_conformance<P: Poly>(params: &P::Params) -> Result<(), ConformanceFailure> {
+    let one = P::const_one(params);
+    let zero = P::const_zero(params);
+    let to_poly = |bit: bool| if bit { one.clone() } else { zero.clone() };
+    for case in corpus() {
+        for (vector, (attributes, expected)) in case.vectors.iter().enumerate() {
+            let inputs = attributes.iter().map(|&bit| to_poly(bit)).collect::<Vec<_>>();
+            let outputs = case.circuit.eval(params, &one, &inputs);
+            assert_eq!(outputs.len(), expected.len(), "wrong number of outputs in {}", case.name);
+            for (output, (actual, &bit)) in outputs.iter().zip(expected).enumerate() {
+                if *actual != to_poly(bit) {
+                    return Err(ConformanceFailure { case: case.name, vector, output });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::{
+        dcrt::{DCRTPoly, DCRTPolyParams},
+        native::{NativePoly, NativePolyParams},
+    };
+
+    #[test]
+    fn test_corpus_is_deterministic() {
+        let first = corpus();
+        let second = corpus();
+        assert_eq!(first.len(), 4);
+        for (a, b) in first.iter().zip(second.iter()) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.vectors, b.vectors);
+            assert_eq!(a.circuit.num_gates(), b.circuit.num_gates());
+        }
+    }
+
+    #[test]
+    fn test_backends_conform() {
+        assert_eq!(run_conformance::<DCRTPoly>(&DCRTPolyParams::default()), Ok(()));
+        assert_eq!(run_conformance::<NativePoly>(&NativePolyParams::default()), Ok(()));
+    }
+}
@@ -0,0 +1,142 @@
+//! Process-wide counters of expensive operations, read by
+//! [`crate::bgg::circuit::PolyCircuit::eval_with_stats`]. Backends record their operations with
+//! [`record_poly_mul`] and [`record_ntt`]; allocations are only counted when a binary installs
+//! [`CountingAllocator`] as its global allocator. The counters are shared by all threads, so
+//! concurrent evaluations are attributed to each other.
+//!
+//! The operations are only counted with the `counters` feature, since every thread updating the
+//! same atomics on each multiplication and allocation contends on one cache line. Without it the
+//! `record_*` functions do nothing and the counts stay zero.
+use memory_stats::memory_stats;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+static POLY_MULS: AtomicU64 = AtomicU64::new(0);
+static NTTS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+static LIVE_FFI_POLYS: AtomicU64 = AtomicU64::new(0);
+static PEAK_FFI_POLYS: AtomicU64 = AtomicU64::new(0);
+
+pub fn record_poly_mul() {
+    if cfg!(feature = "counters") {
+        POLY_MULS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Records a transform between the coefficient and evaluation forms.
+pub fn record_ntt() {
+    if cfg!(feature = "counters") {
+        NTTS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Records a polynomial allocated on the C++ side.
+pub fn record_ffi_alloc() {
+    if cfg!(feature = "counters") {
+        let live = LIVE_FFI_POLYS.fetch_add(1, Ordering::Relaxed) + 1;
+        PEAK_FFI_POLYS.fetch_max(live, Ordering::Relaxed);
+    }
+}
+
+/// Records that a polynomial allocated on the C++ side was freed.
+pub fn record_ffi_free() {
+    if cfg!(feature = "counters") {
+        LIVE_FFI_POLYS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Forwards to [`System`] and counts the allocated bytes, e.g.
+/// `#[global_allocator] static ALLOC: CountingAllocator = CountingAllocator;`.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED_BYTES.fetch_add(new_size.saturating_sub(layout.size()) as u64, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// A snapshot of the counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpCounts {
+    pub poly_muls: u64,
+    pub ntts: u64,
+    /// Zero unless [`CountingAllocator`] is installed.
+    pub allocated_bytes: u64,
+}
+
+impl OpCounts {
+    pub fn now() -> Self {
+        Self {
+            poly_muls: POLY_MULS.load(Ordering::Relaxed),
+            ntts: NTTS.load(Ordering::Relaxed),
+            allocated_bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The operations counted since the snapshot `earlier`.
+    pub fn since(&self, earlier: &Self) -> Self {
+        Self {
+            poly_muls: self.poly_muls - earlier.poly_muls,
+            ntts: self.ntts - earlier.ntts,
+            allocated_bytes: self.allocated_bytes - earlier.allocated_bytes,
+        }
+    }
+}
+
+/// Tracks the polynomials held on the C++ side and the physical memory of the process from the
+/// moment it is started, e.g. around each request of a long-running service to find requests
+/// that leave C++ temporaries behind. The C++ polynomials are only tracked with the `counters`
+/// feature.
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryWatermark {
+    start_ffi_polys: u64,
+    start_physical_mem: usize,
+}
+
+/// Memory usage reported by a [`MemoryWatermark`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+    pub live_ffi_polys: u64,
+    /// The most C++ polynomials alive at once since the last watermark was started.
+    pub peak_ffi_polys: u64,
+    /// C++ polynomials allocated since the watermark was started and still alive, negative if
+    /// more were freed.
+    pub retained_ffi_polys: i64,
+    /// Zero if the platform does not report the memory usage.
+    pub physical_mem: usize,
+    pub physical_mem_growth: isize,
+}
+
+impl MemoryWatermark {
+    /// Starts a watermark, resetting the peak to the current number of C++ polynomials.
+    pub fn start() -> Self {
+        let live = LIVE_FFI_POLYS.load(Ordering::Relaxed);
+        PEAK_FFI_POLYS.store(live, Ordering::Relaxed);
+        let start_physical_mem = memory_stats().map_or(0, |usage| usage.physical_mem);
+        Self { start_ffi_polys: live, start_physical_mem }
+    }
+
+    pub fn report(&self) -> MemoryReport {
+        let live_ffi_polys = LIVE_FFI_POLYS.load(Ordering::Relaxed);
+        let physical_mem = memory_stats().map_or(0, |usage| usage.physical_mem);
+        MemoryReport {
+            live_ffi_polys,
+            peak_ffi_polys: PEAK_FFI_POLYS.load(Ordering::Relaxed),
+            retained_ffi_polys: live_ffi_polys as i64 - self.start_ffi_polys as i64,
+            physical_mem,
+            physical_mem_growth: physical_mem as isize - self.start_physical_mem as isize,
+        }
+    }
+}
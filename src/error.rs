@@ -0,0 +1,22 @@
+/// Errors surfaced by the crate instead of aborting the process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiamondError {
+    /// An OpenFHE call failed, e.g. returned a null pointer or a malformed value.
+    Ffi { call: &'static str, context: String },
+    /// The operands of a ring operation belong to different rings, see
+    /// [`crate::poly::Poly::operand_mismatch`].
+    OperandMismatch { op: &'static str, reason: String },
+}
+
+impl std::fmt::Display for DiamondError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ffi { call, context } => write!(f, "OpenFHE call {} failed: {}", call, context),
+            Self::OperandMismatch { op, reason } => {
+                write!(f, "operands of {} do not match: {}", op, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DiamondError {}
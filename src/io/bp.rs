@@ -0,0 +1,410 @@
+use crate::poly::{
+    norms::matrix_inf_norm,
+    plaintext::modulus_biguint,
+    sampler::{DistType, PolyUniformSampler, TrapdoorProvider},
+    Poly, PolyMatrix,
+};
+
+/// Width of Barrington branching programs.
+pub const BP_WIDTH: usize = 5;
+
+/// A permutation of `{0, .., 4}` where `p[i]` is the image of `i`.
+pub type Perm = [usize; BP_WIDTH];
+
+pub const IDENTITY_PERM: Perm = [0, 1, 2, 3, 4];
+
+/// Composes two permutations as "first `a`, then `b`".
+pub fn compose(a: &Perm, b: &Perm) -> Perm {
+    let mut out = [0; BP_WIDTH];
+    for i in 0..BP_WIDTH {
+        out[i] = b[a[i]];
+    }
+    out
+}
+
+pub fn inverse(p: &Perm) -> Perm {
+    let mut out = [0; BP_WIDTH];
+    for i in 0..BP_WIDTH {
+        out[p[i]] = i;
+    }
+    out
+}
+
+/// Returns `θ^-1 ∘ p ∘ θ`.
+fn conjugate(p: &Perm, theta: &Perm) -> Perm {
+    compose(&compose(&inverse(theta), p), theta)
+}
+
+fn is_five_cycle(p: &Perm) -> bool {
+    let mut cur = 0;
+    for step in 1..=BP_WIDTH {
+        cur = p[cur];
+        if cur == 0 {
+            return step == BP_WIDTH;
+        }
+    }
+    false
+}
+
+fn all_perms() -> Vec<Perm> {
+    let mut perms = vec![];
+    let mut stack = vec![vec![]];
+    while let Some(prefix) = stack.pop() {
+        if prefix.len() == BP_WIDTH {
+            let mut p = [0; BP_WIDTH];
+            p.copy_from_slice(&prefix);
+            perms.push(p);
+            continue;
+        }
+        for i in 0..BP_WIDTH {
+            if !prefix.contains(&i) {
+                let mut next = prefix.clone();
+                next.push(i);
+                stack.push(next);
+            }
+        }
+    }
+    perms
+}
+
+/// A boolean formula over input bits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BoolFormula {
+    Input(usize),
+    Not(Box<BoolFormula>),
+    And(Box<BoolFormula>, Box<BoolFormula>),
+    Or(Box<BoolFormula>, Box<BoolFormula>),
+}
+
+impl BoolFormula {
+    pub fn eval(&self, inputs: &[bool]) -> bool {
+        match self {
+            BoolFormula::Input(idx) => inputs[*idx],
+            BoolFormula::Not(f) => !f.eval(inputs),
+            BoolFormula::And(f, g) => f.eval(inputs) && g.eval(inputs),
+            BoolFormula::Or(f, g) => f.eval(inputs) || g.eval(inputs),
+        }
+    }
+}
+
+/// One level of a branching program reading the input bit `input_idx`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BpInstruction {
+    pub input_idx: usize,
+    pub perm_zero: Perm,
+    pub perm_one: Perm,
+}
+
+/// A width-5 permutation branching program.
+/// The product of the selected permutations equals `target` if the formula is satisfied and the
+/// identity otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchingProgram {
+    pub instructions: Vec<BpInstruction>,
+    pub target: Perm,
+}
+
+impl BranchingProgram {
+    /// Converts a formula of depth `d` into a branching program of length at most `4^d` via
+    /// Barrington's theorem.
+    pub fn from_formula(formula: &BoolFormula) -> Self {
+        let target = [1, 2, 3, 4, 0];
+        let ctx = BarringtonContext::new();
+        let instructions = ctx.build(formula, &target);
+        Self { instructions, target }
+    }
+
+    /// Number of levels of the program, i.e. the length of the encoding chain.
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    /// Returns the input bit read at each level.
+    pub fn levels(&self) -> Vec<usize> {
+        self.instructions.iter().map(|inst| inst.input_idx).collect()
+    }
+
+    pub fn eval(&self, inputs: &[bool]) -> bool {
+        let product = self.instructions.iter().fold(IDENTITY_PERM, |acc, inst| {
+            let perm = if inputs[inst.input_idx] { &inst.perm_one } else { &inst.perm_zero };
+            compose(&acc, perm)
+        });
+        debug_assert!(product == IDENTITY_PERM || product == self.target);
+        product == self.target
+    }
+
+    /// Returns the pair of 5x5 permutation matrices `(M_0, M_1)` of each level, where the `(i,
+    /// p[i])` entry is one, so that multiplying the selected matrices in level order follows
+    /// [`compose`].
+    pub fn to_matrices<M: PolyMatrix>(&self, params: &<M::P as Poly>::Params) -> Vec<(M, M)> {
+        self.instructions
+            .iter()
+            .map(|inst| (perm_matrix(params, &inst.perm_zero), perm_matrix(params, &inst.perm_one)))
+            .collect()
+    }
+}
+
+/// The 5x5 matrix of `p`, whose `(i, p[i])` entries are one.
+fn perm_matrix<M: PolyMatrix>(params: &<M::P as Poly>::Params, p: &Perm) -> M {
+    let zero = <M::P as Poly>::const_zero(params);
+    let one = <M::P as Poly>::const_one(params);
+    let rows = (0..BP_WIDTH)
+        .map(|i| {
+            (0..BP_WIDTH).map(|j| if p[i] == j { one.clone() } else { zero.clone() }).collect()
+        })
+        .collect();
+    M::from_poly_vec(params, rows)
+}
+
+/// A branching program encoded in a GGH15 chain `A_0, ..., A_L` of matrices with 5 rows.
+///
+/// Level `i` is encoded by the preimages `D_{i,b}` with
+/// `A_{i-1} * D_{i,b} = (M_{i,b} ⊗ s_{i,b}) * A_i + E_{i,b}` for the permutation matrices
+/// `M_{i,b}` of the level, small secrets `s_{i,b}` and errors `E_{i,b}`. A dummy program of
+/// identity permutations is encoded under the same secrets, so that the two products differ by
+/// more than the error only when the program accepts.
+#[derive(Debug, Clone)]
+pub struct Ggh15Program<M: PolyMatrix> {
+    pub a_0: M,
+    /// The input bit read at each level.
+    pub levels: Vec<usize>,
+    pub functional: Vec<(M, M)>,
+    pub dummy: Vec<(M, M)>,
+}
+
+impl<M: PolyMatrix> Ggh15Program<M> {
+    /// Encodes `bp` with a trapdoor of every matrix of the chain but the last one, drawing the
+    /// secrets and errors from a Gaussian of standard deviation `error_sigma`.
+    pub fn encode<SU, TP>(
+        params: &<M::P as Poly>::Params,
+        bp: &BranchingProgram,
+        uniform_sampler: &SU,
+        trapdoor_provider: &TP,
+        error_sigma: f64,
+    ) -> Self
+    where
+        SU: PolyUniformSampler<M = M>,
+        TP: TrapdoorProvider<M = M>,
+    {
+        assert!(!bp.is_empty(), "empty branching program");
+        let chain =
+            (0..bp.len()).map(|_| trapdoor_provider.trapdoor(params, BP_WIDTH)).collect::<Vec<_>>();
+        let ncol = chain[0].1.col_size();
+        let a_last = uniform_sampler.sample_uniform(params, BP_WIDTH, ncol, DistType::FinRingDist);
+        let dist = DistType::GaussDist { sigma: error_sigma };
+        let mut functional = Vec::with_capacity(bp.len());
+        let mut dummy = Vec::with_capacity(bp.len());
+        for (level, inst) in bp.instructions.iter().enumerate() {
+            let (trapdoor, a_prev) = &chain[level];
+            let a_next = chain.get(level + 1).map_or(&a_last, |(_, a)| a);
+            let encode_bit = |perm: &Perm| {
+                let secret = uniform_sampler.sample_poly(params, &dist);
+                let preimage = |left: M| {
+                    let error = uniform_sampler.sample_uniform(params, BP_WIDTH, ncol, dist);
+                    let target = left * &secret * a_next + error;
+                    trapdoor_provider.preimage(params, trapdoor, a_prev, &target)
+                };
+                let identity = M::identity(params, BP_WIDTH, None);
+                (preimage(perm_matrix(params, perm)), preimage(identity))
+            };
+            let (functional_zero, dummy_zero) = encode_bit(&inst.perm_zero);
+            let (functional_one, dummy_one) = encode_bit(&inst.perm_one);
+            functional.push((functional_zero, functional_one));
+            dummy.push((dummy_zero, dummy_one));
+        }
+        let a_0 = chain.into_iter().next().expect("empty chain").1;
+        Self { a_0, levels: bp.levels(), functional, dummy }
+    }
+
+    /// Evaluates the program on `inputs`: it accepts iff `A_0 * (Π D_{i,x} - Π D'_{i,x})` has a
+    /// coefficient beyond `q / 4`.
+    pub fn eval(&self, params: &<M::P as Poly>::Params, inputs: &[bool]) -> bool {
+        let product = |preimages: &[(M, M)]| {
+            self.levels.iter().zip(preimages.iter()).fold(self.a_0.clone(), |acc, (&idx, pair)| {
+                acc * if inputs[idx] { &pair.1 } else { &pair.0 }
+            })
+        };
+        let diff = product(&self.functional) - product(&self.dummy);
+        matrix_inf_norm(params, &diff) > modulus_biguint::<M::P>(params) / 4u32
+    }
+}
+
+/// Fixed 5-cycles `α`, `β` whose commutator `γ` is again a 5-cycle.
+struct BarringtonContext {
+    alpha: Perm,
+    beta: Perm,
+    gamma: Perm,
+    perms: Vec<Perm>,
+}
+
+impl BarringtonContext {
+    fn new() -> Self {
+        let perms = all_perms();
+        let alpha = [1, 2, 3, 4, 0];
+        let (beta, gamma) = perms
+            .iter()
+            .filter(|beta| is_five_cycle(beta))
+            .map(|beta| {
+                let gamma =
+                    compose(&compose(&compose(&alpha, beta), &inverse(&alpha)), &inverse(beta));
+                (*beta, gamma)
+            })
+            .find(|(_, gamma)| is_five_cycle(gamma))
+            .expect("a 5-cycle commutator must exist");
+        Self { alpha, beta, gamma, perms }
+    }
+
+    /// Returns `θ` such that `θ^-1 ∘ γ ∘ θ = target`.
+    fn conjugator(&self, target: &Perm) -> Perm {
+        *self
+            .perms
+            .iter()
+            .find(|theta| conjugate(&self.gamma, theta) == *target)
+            .expect("all 5-cycles are conjugate")
+    }
+
+    fn build(&self, formula: &BoolFormula, target: &Perm) -> Vec<BpInstruction> {
+        match formula {
+            BoolFormula::Input(idx) => {
+                vec![BpInstruction { input_idx: *idx, perm_zero: IDENTITY_PERM, perm_one: *target }]
+            }
+            BoolFormula::Not(f) => {
+                // P_f(target^-1) followed by target
+                let mut insts = self.build(f, &inverse(target));
+                let last = insts.last_mut().expect("empty branching program");
+                last.perm_zero = compose(&last.perm_zero, target);
+                last.perm_one = compose(&last.perm_one, target);
+                insts
+            }
+            BoolFormula::And(f, g) => {
+                let theta = self.conjugator(target);
+                let mut insts = self.build(f, &conjugate(&self.alpha, &theta));
+                insts.extend(self.build(g, &conjugate(&self.beta, &theta)));
+                insts.extend(self.build(f, &conjugate(&inverse(&self.alpha), &theta)));
+                insts.extend(self.build(g, &conjugate(&inverse(&self.beta), &theta)));
+                insts
+            }
+            BoolFormula::Or(f, g) => {
+                // f OR g = NOT(NOT f AND NOT g)
+                let formula = BoolFormula::Not(Box::new(BoolFormula::And(
+                    Box::new(BoolFormula::Not(f.clone())),
+                    Box::new(BoolFormula::Not(g.clone())),
+                )));
+                self.build(&formula, target)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::{
+        dcrt::{DCRTPolyMatrix, DCRTPolyParams, DCRTPolyTrapdoorSampler, DCRTPolyUniformSampler},
+        sampler::PolyTrapdoorSampler,
+    };
+
+    const SIGMA: f64 = 4.578;
+
+    fn input(idx: usize) -> Box<BoolFormula> {
+        Box::new(BoolFormula::Input(idx))
+    }
+
+    fn check_all_inputs(formula: &BoolFormula, num_input: usize) {
+        let bp = BranchingProgram::from_formula(formula);
+        for x in 0..(1 << num_input) {
+            let inputs = (0..num_input).map(|i| (x >> i) & 1 == 1).collect::<Vec<_>>();
+            assert_eq!(bp.eval(&inputs), formula.eval(&inputs), "inputs {:?}", inputs);
+        }
+    }
+
+    #[test]
+    fn test_bp_basic_gates() {
+        check_all_inputs(&BoolFormula::Input(0), 1);
+        check_all_inputs(&BoolFormula::Not(input(0)), 1);
+        check_all_inputs(&BoolFormula::And(input(0), input(1)), 2);
+        check_all_inputs(&BoolFormula::Or(input(0), input(1)), 2);
+    }
+
+    #[test]
+    fn test_bp_nested_formula() {
+        // (x0 AND NOT x1) OR (x2 AND x0)
+        let formula = BoolFormula::Or(
+            Box::new(BoolFormula::And(input(0), Box::new(BoolFormula::Not(input(1))))),
+            Box::new(BoolFormula::And(input(2), input(0))),
+        );
+        check_all_inputs(&formula, 3);
+    }
+
+    #[test]
+    fn test_bp_length_and_levels() {
+        let formula = BoolFormula::And(input(0), input(1));
+        let bp = BranchingProgram::from_formula(&formula);
+        assert_eq!(bp.len(), 4);
+        assert_eq!(bp.levels(), vec![0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn test_bp_to_matrices() {
+        let params = DCRTPolyParams::default();
+        let formula = BoolFormula::And(input(0), input(1));
+        let bp = BranchingProgram::from_formula(&formula);
+        let matrices = bp.to_matrices::<DCRTPolyMatrix>(&params);
+        assert_eq!(matrices.len(), bp.len());
+
+        // The product of the selected matrices is the permutation matrix of the product
+        let inputs = [true, true];
+        let product = matrices
+            .iter()
+            .zip(bp.levels())
+            .map(|((m0, m1), idx)| if inputs[idx] { m1.clone() } else { m0.clone() })
+            .reduce(|acc, m| acc * m)
+            .unwrap();
+        let single = BranchingProgram {
+            instructions: vec![BpInstruction {
+                input_idx: 0,
+                perm_zero: bp.target,
+                perm_one: bp.target,
+            }],
+            target: bp.target,
+        };
+        let target_matrix = single.to_matrices::<DCRTPolyMatrix>(&params).remove(0).0;
+        assert_eq!(product, target_matrix);
+    }
+
+    fn check_ggh15(params: &DCRTPolyParams, formula: &BoolFormula, num_input: usize) {
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let trapdoor_sampler = DCRTPolyTrapdoorSampler::new(params, SIGMA);
+        let bp = BranchingProgram::from_formula(formula);
+        let program = Ggh15Program::<DCRTPolyMatrix>::encode(
+            params,
+            &bp,
+            &uniform_sampler,
+            &trapdoor_sampler,
+            SIGMA,
+        );
+        assert_eq!(program.functional.len(), bp.len());
+        for x in 0..(1 << num_input) {
+            let inputs = (0..num_input).map(|i| (x >> i) & 1 == 1).collect::<Vec<_>>();
+            assert_eq!(program.eval(params, &inputs), formula.eval(&inputs), "inputs {:?}", inputs);
+        }
+    }
+
+    #[test]
+    fn test_ggh15_single_level() {
+        let params = DCRTPolyParams::default();
+        check_ggh15(&params, &BoolFormula::Input(0), 1);
+        check_ggh15(&params, &BoolFormula::Not(input(0)), 1);
+    }
+
+    #[test]
+    fn test_ggh15_and() {
+        // The error grows by a preimage per level, so the four levels need a larger modulus
+        let params = DCRTPolyParams::new(4, 8, 51, 17);
+        check_ggh15(&params, &BoolFormula::And(input(0), input(1)), 2);
+    }
+}
@@ -0,0 +1,150 @@
+//! Estimates of the artifact sizes and evaluation costs of an obfuscation from its parameters,
+//! without sampling anything, for sizing a deployment before running [`super::obf::obfuscate`].
+use crate::poly::{
+    dims::{gadget_len, trapdoor_width},
+    Poly,
+};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// The parameters that determine the sizes of an obfuscation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObfuscationShape {
+    pub ring_dimension: usize,
+    pub crt_depth: usize,
+    pub crt_bits: usize,
+    pub base_bits: u32,
+    pub d: usize,
+    pub input_size: usize,
+    pub level_width: usize,
+    /// Number of packed output polynomials.
+    pub packed_output_size: usize,
+}
+
+/// Sizes in bytes, with every coefficient stored in `ceil(log q / 8)` bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeEstimate {
+    pub poly_bytes: u64,
+    /// The vectors of the initial encodings the evaluator starts from.
+    pub ciphertext_bytes: u64,
+    /// The preimages of every level and the final preimage.
+    pub preimage_bytes: u64,
+    /// Everything [`super::obf::obfuscate`] writes. The public keys are derived from the hash
+    /// key and not stored.
+    pub obfuscation_bytes: u64,
+}
+
+impl ObfuscationShape {
+    pub fn gadget_len(&self) -> usize {
+        gadget_len(self.crt_depth, self.crt_bits, self.base_bits)
+    }
+
+    /// The number of columns `(d + 1) * gadget_len` of a BGG+ public key and encoding.
+    pub fn encoding_width(&self) -> usize {
+        (self.d + 1) * self.gadget_len()
+    }
+
+    pub fn estimate_sizes(&self) -> SizeEstimate {
+        let coeff_bytes = (self.crt_depth * self.crt_bits).div_ceil(8);
+        let poly_bytes = (self.ring_dimension * coeff_bytes) as u64;
+        let width = self.encoding_width() as u64;
+        let trapdoor_width = trapdoor_width(2 * (self.d + 1), self.gadget_len()) as u64;
+        let packed_input_size = (self.input_size.div_ceil(self.ring_dimension) + 1) as u64;
+        let num_levels = (self.input_size / self.level_width) as u64;
+        let level_size = 1u64 << self.level_width;
+
+        // The constant one and every packed input
+        let ciphertext_polys = (packed_input_size + 1) * width;
+        // The m and n preimages are square, the k preimage maps to all encodings of a level
+        let per_num_polys = 2 * trapdoor_width * trapdoor_width +
+            trapdoor_width * (packed_input_size + 1) * width;
+        let preimage_polys = num_levels * level_size * per_num_polys +
+            trapdoor_width * self.packed_output_size as u64;
+        // b is one polynomial and p_init one row of the trapdoor width
+        let obfuscation_polys = preimage_polys + ciphertext_polys + 1 + trapdoor_width;
+        SizeEstimate {
+            poly_bytes,
+            ciphertext_bytes: ciphertext_polys * poly_bytes,
+            preimage_bytes: preimage_polys * poly_bytes,
+            obfuscation_bytes: obfuscation_polys * poly_bytes,
+        }
+    }
+}
+
+/// Time of one ring addition and multiplication, measured with [`Self::measure`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PolyOpTimings {
+    pub add: Duration,
+    pub mul: Duration,
+}
+
+impl PolyOpTimings {
+    /// Averages `reps` additions and multiplications of `a` and `b`.
+    pub fn measure<P: Poly>(a: &P, b: &P, reps: u32) -> Self {
+        assert!(reps > 0, "at least one repetition is required");
+        let start = Instant::now();
+        for _ in 0..reps {
+            std::hint::black_box(a.clone() + b);
+        }
+        let add = start.elapsed() / reps;
+        let start = Instant::now();
+        for _ in 0..reps {
+            std::hint::black_box(a.clone() * b);
+        }
+        let mul = start.elapsed() / reps;
+        Self { add, mul }
+    }
+
+    /// An addition gate over encodings adds the `d + 1` public key rows and the vector.
+    pub fn add_gate(&self, shape: &ObfuscationShape) -> Duration {
+        self.add * ((shape.d + 2) * shape.encoding_width()) as u32
+    }
+
+    /// A multiplication gate over encodings multiplies the `d + 2` stacked rows by the
+    /// `m x m` decomposition of the right public key and adds the right vector times the left
+    /// plaintext, ignoring the cost of the decomposition itself.
+    pub fn mul_gate(&self, shape: &ObfuscationShape) -> Duration {
+        let m = shape.encoding_width() as u32;
+        let products = (shape.d as u32 + 2) * m * m;
+        self.mul * (products + m) + self.add * products
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_shape() -> ObfuscationShape {
+        ObfuscationShape {
+            ring_dimension: 4,
+            crt_depth: 2,
+            crt_bits: 17,
+            base_bits: 1,
+            d: 1,
+            input_size: 8,
+            level_width: 2,
+            packed_output_size: 1,
+        }
+    }
+
+    #[test]
+    fn test_estimate_sizes() {
+        // 34 gadget digits, encodings of width 68, trapdoors of width 4 * 36 = 144, 3 packed
+        // inputs and 4 levels of 4 numbers each
+        let sizes = small_shape().estimate_sizes();
+        assert_eq!(sizes.poly_bytes, 20);
+        assert_eq!(sizes.ciphertext_bytes, 4 * 68 * 20);
+        let preimage_polys = 16 * (2 * 144 * 144 + 144 * 4 * 68) + 144;
+        assert_eq!(sizes.preimage_bytes, preimage_polys * 20);
+        assert_eq!(sizes.obfuscation_bytes, (preimage_polys + 4 * 68 + 1 + 144) * 20);
+    }
+
+    #[test]
+    fn test_gate_timings() {
+        let timings = PolyOpTimings { add: Duration::from_nanos(1), mul: Duration::from_nanos(10) };
+        let shape = small_shape();
+        assert_eq!(timings.add_gate(&shape), Duration::from_nanos(3 * 68));
+        let products = 3 * 68 * 68;
+        assert_eq!(timings.mul_gate(&shape), Duration::from_nanos(10 * (products + 68) + products));
+    }
+}
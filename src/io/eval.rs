@@ -4,9 +4,13 @@ use super::bgm::Player;
 use super::{params::ObfuscationParams, Obfuscation};
 use crate::{
     bgg::{sampler::BGGPublicKeySampler, BggEncoding, DigitsToInt},
-    io::utils::{build_final_digits_circuit, sample_public_key_by_id, PublicSampledData},
+    io::utils::{
+        build_final_digits_circuit, reveal_plaintexts, sample_public_key_by_id, PublicSampledData,
+    },
+    migrate::check_obfuscation_version,
     parallel_iter,
     poly::{
+        dims::trapdoor_width,
         sampler::{PolyHashSampler, PolyTrapdoorSampler},
         Poly, PolyElem, PolyMatrix, PolyParams,
     },
@@ -25,6 +29,7 @@ where
         dir_path: P,
     ) -> Self {
         let dir_path = dir_path.as_ref().to_path_buf();
+        check_obfuscation_version(&dir_path).unwrap_or_else(|err| panic!("{}", err));
         let b = M::read_from_files(&obf_params.params, 1, 1, &dir_path, "b");
 
         let dim = obf_params.params.ring_dimension() as usize;
@@ -50,7 +55,7 @@ where
             })
             .collect::<Vec<_>>();
 
-        let m_b = (2 * d1) * (2 + log_base_q);
+        let m_b = trapdoor_width(2 * d1, log_base_q);
         let p_init = M::read_from_files(&obf_params.params, 1, m_b, &dir_path, "p_init");
 
         let level_size = (1u64 << obf_params.level_width) as usize;
@@ -186,7 +191,7 @@ where
 
     pub fn eval<SH, ST>(&self, obf_params: ObfuscationParams<M>, inputs: &[bool]) -> Vec<bool>
     where
-        SH: PolyHashSampler<[u8; 32], M = M>,
+        SH: PolyHashSampler<[u8; 32], M = M> + Sync,
         ST: PolyTrapdoorSampler<M = M>,
     {
         #[cfg(feature = "bgm")]
@@ -200,7 +205,7 @@ where
         let d1 = d + 1;
         assert_eq!(inputs.len(), obf_params.input_size);
         let bgg_pubkey_sampler = BGGPublicKeySampler::<_, SH>::new(self.hash_key, d);
-        let public_data = PublicSampledData::<SH>::sample(&obf_params, self.hash_key);
+        let public_data = PublicSampledData::sample(&obf_params, &bgg_pubkey_sampler);
         let params = obf_params.params;
         log_mem("Sampled public data");
         let packed_input_size = public_data.packed_input_size;
@@ -215,10 +220,7 @@ where
         assert!(inputs.len() % level_width == 0);
         let depth = obf_params.input_size / level_width;
 
-        #[cfg(feature = "debug")]
-        let reveal_plaintexts = [vec![true; packed_input_size - 1], vec![true; 1]].concat();
-        #[cfg(not(feature = "debug"))]
-        let reveal_plaintexts = [vec![true; packed_input_size - 1], vec![false; 1]].concat();
+        let reveal_plaintexts = reveal_plaintexts(packed_input_size);
         let pub_key_init =
             sample_public_key_by_id(&bgg_pubkey_sampler, &params, 0, &reveal_plaintexts);
         log_mem("Sampled pub_key_init");
@@ -3,7 +3,10 @@ pub mod bgm;
 
 use crate::{bgg::BggEncoding, poly::PolyMatrix};
 
+pub mod bp;
+pub mod estimate;
 pub mod eval;
+#[cfg(feature = "keygen")]
 pub mod obf;
 pub mod params;
 pub mod serde;
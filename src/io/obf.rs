@@ -4,38 +4,152 @@ use super::bgm::Player;
 use crate::{
     bgg::{
         sampler::{BGGEncodingSampler, BGGPublicKeySampler},
-        BggEncoding, BggPublicKey, DigitsToInt,
+        AttributeKeys, BggEncoding, BggPublicKey, DigitsToInt,
     },
     io::{
         params::ObfuscationParams,
-        utils::{build_final_digits_circuit, sample_public_key_by_id, PublicSampledData},
+        utils::{
+            build_final_digits_circuit, reveal_plaintexts, sample_public_key_by_id,
+            PublicSampledData,
+        },
     },
+    migrate::write_obfuscation_version,
     poly::{
-        enc::rlwe_encrypt,
-        sampler::{DistType, PolyHashSampler, PolyTrapdoorSampler, PolyUniformSampler},
+        dims::trapdoor_width,
+        enc::{lwe_sample, rlwe_encrypt},
+        rng::CryptoRngSource,
+        sampler::{
+            DistType, PolyHashSampler, PolyTrapdoorSampler, PolyUniformSampler, TrapdoorProvider,
+        },
         Poly, PolyElem, PolyMatrix, PolyParams,
     },
-    utils::log_mem,
+    utils::{log_mem, ThreadPoolConfig},
 };
 use futures::future::join_all;
 use itertools::Itertools;
-use rand::{Rng, RngCore};
 use rayon::{iter::ParallelIterator, slice::ParallelSlice};
-use std::{path::Path, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use tokio::runtime::Handle;
 
-pub async fn obfuscate<M, SU, SH, ST, R, P>(
+/// Key material generated ahead of time, e.g. by `diamond-keygen`, for
+/// [`obfuscate_with_keys`].
+pub struct ObfuscationKeys<T, M> {
+    /// The key the public matrices and the BGG+ public keys are expanded from.
+    pub hash_key: [u8; 32],
+    /// The trapdoor of the first `b_star` and its public matrix of `2 * (d + 1)` rows, sampled
+    /// by the obfuscator if absent.
+    pub initial_trapdoor: Option<(T, M)>,
+}
+
+/// Obfuscates with the hash key, secrets, errors and trapdoors all drawn from `source`,
+/// sampling and evaluating in `pool`.
+pub async fn obfuscate<M, SU, SH, ST, P>(
+    obf_params: ObfuscationParams<M>,
+    hardcoded_key: M::P,
+    source: Arc<dyn CryptoRngSource>,
+    dir_path: P,
+    pool: &ThreadPoolConfig,
+) where
+    M: PolyMatrix + 'static,
+    SU: PolyUniformSampler<M = M>,
+    SH: PolyHashSampler<[u8; 32], M = M> + Sync,
+    ST: PolyTrapdoorSampler<M = M> + Sync,
+    ST::Trapdoor: Send,
+    ObfuscationParams<M>: Send,
+    P: AsRef<Path>,
+{
+    let mut hash_key = [0u8; 32];
+    source.fill_bytes(&mut hash_key);
+    let keys = ObfuscationKeys { hash_key, initial_trapdoor: None };
+    obfuscate_with_keys::<M, SU, SH, ST, P>(obf_params, hardcoded_key, keys, source, dir_path, pool)
+        .await
+}
+
+/// [`obfuscate`] with pre-generated key material.
+pub async fn obfuscate_with_keys<M, SU, SH, ST, P>(
+    obf_params: ObfuscationParams<M>,
+    hardcoded_key: M::P,
+    keys: ObfuscationKeys<ST::Trapdoor, M>,
+    source: Arc<dyn CryptoRngSource>,
+    dir_path: P,
+    pool: &ThreadPoolConfig,
+) where
+    M: PolyMatrix + 'static,
+    SU: PolyUniformSampler<M = M>,
+    SH: PolyHashSampler<[u8; 32], M = M> + Sync,
+    ST: PolyTrapdoorSampler<M = M> + Sync,
+    ST::Trapdoor: Send,
+    ObfuscationParams<M>: Send,
+    P: AsRef<Path>,
+{
+    let sampler_trapdoor =
+        ST::new(&obf_params.params, obf_params.trapdoor_sigma).with_source(source.clone());
+    obfuscate_with_provider::<M, SU, SH, ST, P>(
+        obf_params,
+        hardcoded_key,
+        keys,
+        &sampler_trapdoor,
+        source,
+        dir_path,
+        pool,
+    )
+    .await
+}
+
+/// [`obfuscate_with_keys`] with the trapdoors and preimages of `trapdoor_provider`, e.g. a
+/// [`crate::poly::dcrt::sampler::trapdoor::provider::SharedTrapdoorProvider`] so that no single
+/// party learns the trapdoors. The secrets and errors are drawn from `source`.
+pub async fn obfuscate_with_provider<M, SU, SH, TP, P>(
     obf_params: ObfuscationParams<M>,
     hardcoded_key: M::P,
-    rng: &mut R,
+    keys: ObfuscationKeys<TP::Handle, M>,
+    trapdoor_provider: &TP,
+    source: Arc<dyn CryptoRngSource>,
     dir_path: P,
+    pool: &ThreadPoolConfig,
 ) where
     M: PolyMatrix + 'static,
     SU: PolyUniformSampler<M = M>,
-    SH: PolyHashSampler<[u8; 32], M = M>,
-    ST: PolyTrapdoorSampler<M = M>,
-    R: RngCore,
+    SH: PolyHashSampler<[u8; 32], M = M> + Sync,
+    TP: TrapdoorProvider<M = M> + Sync,
+    TP::Handle: Send,
+    ObfuscationParams<M>: Send,
     P: AsRef<Path>,
+{
+    // The files are written by blocking tasks of the runtime, spawned from the pool
+    let runtime = Handle::current();
+    let dir_path = dir_path.as_ref().to_path_buf();
+    let handles = pool.install(|| {
+        let _runtime = runtime.enter();
+        sample_obfuscation::<M, SU, SH, TP>(
+            obf_params,
+            hardcoded_key,
+            keys,
+            trapdoor_provider,
+            source,
+            dir_path,
+        )
+    });
+    join_all(handles).await;
+}
+
+/// Samples and evaluates the parts of the obfuscation, returning the tasks storing them.
+fn sample_obfuscation<M, SU, SH, TP>(
+    obf_params: ObfuscationParams<M>,
+    hardcoded_key: M::P,
+    keys: ObfuscationKeys<TP::Handle, M>,
+    trapdoor_provider: &TP,
+    source: Arc<dyn CryptoRngSource>,
+    dir_path: PathBuf,
+) -> Vec<tokio::task::JoinHandle<()>>
+where
+    M: PolyMatrix + 'static,
+    SU: PolyUniformSampler<M = M>,
+    SH: PolyHashSampler<[u8; 32], M = M> + Sync,
+    TP: TrapdoorProvider<M = M>,
 {
     #[cfg(feature = "bgm")]
     let player = Player::new();
@@ -43,7 +157,6 @@ pub async fn obfuscate<M, SU, SH, ST, R, P>(
     player.play_music("bgm/obf_bgm1.mp3");
 
     let mut handles: Vec<tokio::task::JoinHandle<()>> = Vec::new();
-    let dir_path = dir_path.as_ref().to_path_buf();
     if !dir_path.exists() {
         std::fs::create_dir_all(&dir_path).expect("Failed to create directory");
     }
@@ -52,18 +165,14 @@ pub async fn obfuscate<M, SU, SH, ST, R, P>(
     let dim = obf_params.params.ring_dimension() as usize;
     let log_base_q = obf_params.params.modulus_digits();
     let d = obf_params.d;
-    let hash_key = rng.random::<[u8; 32]>();
-    let sampler_uniform = SU::new();
-    let sampler_trapdoor = ST::new(&obf_params.params, obf_params.trapdoor_sigma);
+    let hash_key = keys.hash_key;
+    let sampler_uniform = SU::with_source(source);
     let bgg_pubkey_sampler = BGGPublicKeySampler::<_, SH>::new(hash_key, d);
-    let public_data = PublicSampledData::<SH>::sample(&obf_params, hash_key);
+    let public_data = PublicSampledData::sample(&obf_params, &bgg_pubkey_sampler);
     log_mem("Sampled public data");
     let packed_input_size = public_data.packed_input_size;
     assert_eq!(public_circuit.num_input(), (2 * log_base_q) + (packed_input_size - 1));
-    #[cfg(feature = "debug")]
-    let reveal_plaintexts = [vec![true; packed_input_size - 1], vec![true; 1]].concat();
-    #[cfg(not(feature = "debug"))]
-    let reveal_plaintexts = [vec![true; packed_input_size - 1], vec![false; 1]].concat();
+    let reveal_plaintexts = reveal_plaintexts(packed_input_size);
 
     let pub_key_init =
         sample_public_key_by_id(&bgg_pubkey_sampler, &obf_params.params, 0, &reveal_plaintexts);
@@ -72,7 +181,7 @@ pub async fn obfuscate<M, SU, SH, ST, R, P>(
     let params = Arc::new(obf_params.params);
     let packed_input_size = public_data.packed_input_size;
     let packed_output_size = public_data.packed_output_size;
-    let s_bars = sampler_uniform.sample_uniform(&params, 1, d, DistType::BitDist).get_row(0);
+    let s_bars = obf_params.keygen.sample_secrets(&sampler_uniform, &params, d);
     log_mem("Sampled s_bars");
     let t_bar_matrix = sampler_uniform.sample_uniform(&params, 1, 1, DistType::FinRingDist);
     log_mem("Sampled t_bar_matrix");
@@ -92,14 +201,6 @@ pub async fn obfuscate<M, SU, SH, ST, R, P>(
         obf_params.hardcoded_key_sigma,
     );
     log_mem("Generated RLWE ciphertext {a, b}");
-    let m_b = (2 * (d + 1)) * (2 + log_base_q);
-    let p_init_error = sampler_uniform.sample_uniform(
-        &params,
-        1,
-        m_b,
-        DistType::GaussDist { sigma: obf_params.p_sigma },
-    );
-
     let bgg_encode_sampler = BGGEncodingSampler::new(
         params.as_ref(),
         &s_bars,
@@ -135,14 +236,26 @@ pub async fn obfuscate<M, SU, SH, ST, R, P>(
         ));
     }
 
-    let (mut b_star_trapdoor_cur, mut b_star_cur) = sampler_trapdoor.trapdoor(&params, 2 * (d + 1));
+    let (mut b_star_trapdoor_cur, mut b_star_cur) = match keys.initial_trapdoor {
+        Some((trapdoor, public_matrix)) => {
+            assert_eq!(public_matrix.row_size(), 2 * (d + 1), "initial trapdoor of the wrong size");
+            (trapdoor, public_matrix)
+        }
+        None => trapdoor_provider.trapdoor(&params, 2 * (d + 1)),
+    };
     log_mem("b star trapdoor init sampled");
 
     let p_init = {
         let s_connect = s_init.concat_columns(&[s_init]);
-        let s_b = s_connect * &b_star_cur;
-        s_b + p_init_error
+        lwe_sample(
+            params.as_ref(),
+            &bgg_encode_sampler.error_sampler,
+            &s_connect,
+            &b_star_cur,
+            DistType::GaussDist { sigma: obf_params.p_sigma },
+        )
     };
+    debug_assert_eq!(p_init.col_size(), trapdoor_width(2 * (d + 1), log_base_q));
     log_mem("Computed p_init");
     handles.push(store_and_drop_matrix(p_init, &dir_path, "p_init"));
 
@@ -181,7 +294,8 @@ pub async fn obfuscate<M, SU, SH, ST, R, P>(
     let mut pub_key_cur = pub_key_init;
 
     for level in 0..depth {
-        let (b_star_trapdoor_level, b_star_level) = sampler_trapdoor.trapdoor(&params, 2 * (d + 1));
+        let (b_star_trapdoor_level, b_star_level) =
+            trapdoor_provider.trapdoor(&params, 2 * (d + 1));
         log_mem("Sampled b_star trapdoor for level");
 
         let pub_key_level =
@@ -211,7 +325,7 @@ pub async fn obfuscate<M, SU, SH, ST, R, P>(
             }
 
             let (b_num_trapdoor_level, b_num_level) =
-                sampler_trapdoor.trapdoor(&params, 2 * (d + 1));
+                trapdoor_provider.trapdoor(&params, 2 * (d + 1));
             log_mem("Sampled b trapdoor for level and num");
 
             #[cfg(feature = "debug")]
@@ -221,7 +335,7 @@ pub async fn obfuscate<M, SU, SH, ST, R, P>(
                 &format!("b_{}_{num}", level + 1),
             ));
 
-            let m_preimage_num = sampler_trapdoor.preimage(
+            let m_preimage_num = trapdoor_provider.preimage(
                 &params,
                 &b_star_trapdoor_cur,
                 &b_star_cur,
@@ -236,7 +350,7 @@ pub async fn obfuscate<M, SU, SH, ST, R, P>(
 
             // m_preimages[level].push(m_preimage_num);
 
-            let n_preimage_num = sampler_trapdoor.preimage(
+            let n_preimage_num = trapdoor_provider.preimage(
                 &params,
                 &b_num_trapdoor_level,
                 &b_num_level,
@@ -285,7 +399,7 @@ pub async fn obfuscate<M, SU, SH, ST, R, P>(
             let k_target = top.concat_rows(&[&bottom]);
             log_mem("Computed k_target");
             let k_preimage_num =
-                sampler_trapdoor.preimage(&params, &b_num_trapdoor_level, &b_num_level, &k_target);
+                trapdoor_provider.preimage(&params, &b_num_trapdoor_level, &b_num_level, &k_target);
             log_mem("Computed k_preimage_num");
             handles.push(store_and_drop_matrix(
                 k_preimage_num,
@@ -311,7 +425,13 @@ pub async fn obfuscate<M, SU, SH, ST, R, P>(
             public_circuit.clone(),
         );
         log_mem("Computed final_circuit");
-        let eval_outputs = final_circuit.eval(params.as_ref(), &pub_key_cur[0], &pub_key_cur[1..]);
+        let pub_keys = AttributeKeys::from_slots(pub_key_cur);
+        let eval_outputs = match &obf_params.key_cache {
+            Some(cache) => cache
+                .get_or_eval::<SH::Hash, M>(params.as_ref(), &final_circuit, &pub_keys)
+                .expect("Failed to evaluate through the key-side cache"),
+            None => pub_keys.eval(params.as_ref(), &final_circuit),
+        };
         log_mem("Evaluated outputs");
         debug_assert_eq!(eval_outputs.len(), log_base_q * packed_output_size);
         let output_ints = eval_outputs
@@ -328,7 +448,7 @@ pub async fn obfuscate<M, SU, SH, ST, R, P>(
     };
     log_mem("Computed final_preimage_target");
 
-    let final_preimage = sampler_trapdoor.preimage(
+    let final_preimage = trapdoor_provider.preimage(
         &params,
         &b_star_trapdoor_cur,
         &b_star_cur,
@@ -340,10 +460,11 @@ pub async fn obfuscate<M, SU, SH, ST, R, P>(
     let store_hash_key = tokio::task::spawn_blocking(move || {
         let path = dir_path_clone.join("hash_key");
         std::fs::write(&path, &hash_key).expect("Failed to write hash_key file");
+        write_obfuscation_version(&dir_path_clone).expect("Failed to write version file");
         log_mem("Stored hash_key");
     });
     handles.push(store_hash_key);
-    join_all(handles).await;
+    handles
 }
 
 fn store_and_drop_matrix<M: PolyMatrix + 'static>(
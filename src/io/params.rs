@@ -1,8 +1,46 @@
 use crate::{
-    bgg::circuit::PolyCircuit,
-    poly::{Poly, PolyMatrix, PolyParams},
+    bgg::{circuit::PolyCircuit, key_cache::KeySideCache},
+    poly::{
+        sampler::{DistType, PolyUniformSampler},
+        Poly, PolyMatrix, PolyParams,
+    },
 };
 
+/// How the obfuscator samples its secrets.
+#[derive(Debug, Clone, Copy)]
+pub struct KeygenConfig {
+    /// The distribution of the coefficients of the BGG+ secret polynomials.
+    pub secret_dist: DistType,
+}
+
+impl Default for KeygenConfig {
+    /// Binary secrets.
+    fn default() -> Self {
+        Self { secret_dist: DistType::BitDist }
+    }
+}
+
+impl KeygenConfig {
+    /// Ternary secrets with exactly `hamming_weight` nonzero coefficients.
+    pub fn ternary(hamming_weight: usize) -> Self {
+        Self { secret_dist: DistType::TernaryDist { hamming_weight } }
+    }
+
+    /// Samples the `d` secret polynomials.
+    pub fn sample_secrets<SU: PolyUniformSampler>(
+        &self,
+        sampler: &SU,
+        params: &<<SU::M as PolyMatrix>::P as Poly>::Params,
+        d: usize,
+    ) -> Vec<<SU::M as PolyMatrix>::P> {
+        if let DistType::TernaryDist { hamming_weight } = self.secret_dist {
+            let n = params.ring_dimension() as usize;
+            assert!(hamming_weight <= n, "hamming weight {} exceeds {}", hamming_weight, n);
+        }
+        sampler.sample_uniform(params, 1, d, self.secret_dist).get_row(0)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ObfuscationParams<M: PolyMatrix> {
     pub params: <<M as PolyMatrix>::P as Poly>::Params,
@@ -15,4 +53,8 @@ pub struct ObfuscationParams<M: PolyMatrix> {
     pub hardcoded_key_sigma: f64,
     pub p_sigma: f64,
     pub trapdoor_sigma: f64,
+    pub keygen: KeygenConfig,
+    /// Caches the key-side evaluation of the final circuit, which only depends on the circuit
+    /// and the public keys, e.g. when obfuscating again with the same keys.
+    pub key_cache: Option<KeySideCache>,
 }
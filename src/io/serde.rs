@@ -1,5 +1,8 @@
 use std::{path::PathBuf, str::FromStr};
 
+#[cfg(feature = "openfhe")]
+use crate::poly::dcrt::{DCRTPolyParams, ParamsError};
+use crate::migrate::{obfuscation_params_migrator, MigrationError, OBFUSCATION_PARAMS_VERSION};
 use num_bigint::BigUint;
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
@@ -18,8 +21,24 @@ where
     BigUint::from_str(&s).map_err(de::Error::custom)
 }
 
+/// Writes the current version, whatever the parameters were read with.
+fn current_version<S>(_: &u32, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_u32(OBFUSCATION_PARAMS_VERSION)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SerializableObfuscationParams {
+    /// Format version, 0 for parameters serialized before versioning was introduced. Always
+    /// serialized as [`OBFUSCATION_PARAMS_VERSION`].
+    #[serde(default, serialize_with = "current_version")]
+    pub version: u32,
+    pub ring_dimension: u32,
+    pub crt_depth: usize,
+    pub crt_bits: usize,
+    pub base_bits: u32,
     #[serde(serialize_with = "biguint_to_string", deserialize_with = "biguint_from_string")]
     pub switched_modulus: BigUint,
     pub input_size: usize,
@@ -31,3 +50,28 @@ pub struct SerializableObfuscationParams {
     pub p_sigma: f64,
     pub trapdoor_sigma: f64,
 }
+
+impl SerializableObfuscationParams {
+    /// Deserializes parameters, upgrading them to the current version first if they are older.
+    pub fn from_json_str(json_str: &str) -> Self {
+        Self::try_from_json_str(json_str).expect("Failed to deserialize obfuscation params")
+    }
+
+    /// Like [`Self::from_json_str`], returning malformed input as an error.
+    pub fn try_from_json_str(json_str: &str) -> Result<Self, MigrationError> {
+        let value = serde_json::from_str(json_str)
+            .map_err(|err| MigrationError::Invalid(err.to_string()))?;
+        let value = obfuscation_params_migrator().migrate(value)?;
+        serde_json::from_value(value).map_err(|err| MigrationError::Invalid(err.to_string()))
+    }
+
+    pub fn to_json_str(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Failed to serialize obfuscation params")
+    }
+
+    /// The ring parameters, checked like [`DCRTPolyParams::try_new`].
+    #[cfg(feature = "openfhe")]
+    pub fn ring_params(&self) -> Result<DCRTPolyParams, ParamsError> {
+        DCRTPolyParams::try_new(self.ring_dimension, self.crt_depth, self.crt_bits, self.base_bits)
+    }
+}
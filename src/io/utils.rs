@@ -4,15 +4,20 @@ use crate::{
         sampler::*,
         BggPublicKey,
     },
-    poly::{sampler::*, Poly, PolyMatrix, PolyParams},
+    poly::{sampler::*, tag::Tag, Poly, PolyMatrix, PolyParams},
 };
 use std::marker::PhantomData;
 
 use super::params::ObfuscationParams;
 
-const TAG_A_RLWE_BAR: &[u8] = b"A_RLWE_BAR";
-const TAG_A_PRF: &[u8] = b"A_PRF:";
-pub const TAG_BGG_PUBKEY_INPUT_PREFIX: &[u8] = b"BGG_PUBKEY_INPUT:";
+const TAG_A_RLWE_BAR: Tag<'static> = Tag::Custom(b"A_RLWE_BAR");
+const TAG_A_PRF: Tag<'static> = Tag::Custom(b"A_PRF");
+const TAG_BGG_PUBKEY_INPUT_PREFIX: &[u8] = b"BGG_PUBKEY_INPUT:";
+
+/// The tag of the public keys of the `id`-th input.
+pub fn bgg_pubkey_input_tag(id: usize) -> Vec<u8> {
+    Tag::Custom(&[TAG_BGG_PUBKEY_INPUT_PREFIX, &(id as u64).to_le_bytes()].concat()).encode()
+}
 
 pub fn sample_public_key_by_id<K: AsRef<[u8]>, S>(
     sampler: &BGGPublicKeySampler<K, S>,
@@ -23,11 +28,21 @@ pub fn sample_public_key_by_id<K: AsRef<[u8]>, S>(
 where
     S: PolyHashSampler<K>,
 {
-    sampler.sample(
-        params,
-        &[TAG_BGG_PUBKEY_INPUT_PREFIX, &(id as u64).to_le_bytes()].concat(),
-        reveal_plaintexts,
-    )
+    sampler.sample(params, &bgg_pubkey_input_tag(id), reveal_plaintexts)
+}
+
+/// The number of packed inputs: the polynomials holding `input_size` bits, plus the one holding
+/// the RLWE key.
+pub fn packed_input_size(input_size: usize, ring_dimension: usize) -> usize {
+    input_size.div_ceil(ring_dimension) + 1
+}
+
+/// Whether the plaintexts of the packed inputs are revealed to the evaluator: those of the input
+/// bits are, the RLWE key is not, except in debug builds.
+pub fn reveal_plaintexts(packed_input_size: usize) -> Vec<bool> {
+    let mut reveal = vec![true; packed_input_size];
+    reveal[packed_input_size - 1] = cfg!(feature = "debug");
+    reveal
 }
 
 #[derive(Debug, Clone)]
@@ -41,41 +56,37 @@ pub struct PublicSampledData<S: PolyHashSampler<[u8; 32]>> {
     _s: PhantomData<S>,
 }
 
-impl<S: PolyHashSampler<[u8; 32]>> PublicSampledData<S> {
-    pub fn sample(obf_params: &ObfuscationParams<S::M>, hash_key: [u8; 32]) -> Self {
+impl<S: PolyHashSampler<[u8; 32]> + Sync> PublicSampledData<S> {
+    /// Expands the public matrices from the key of the current epoch of `bgg_pubkey_sampler`, so
+    /// that they are re-derived with its public keys when the epoch advances.
+    pub fn sample(
+        obf_params: &ObfuscationParams<S::M>,
+        bgg_pubkey_sampler: &BGGPublicKeySampler<[u8; 32], S>,
+    ) -> Self {
+        let hash_key = bgg_pubkey_sampler.epoch_key();
         let hash_sampler = S::new();
         let params = &obf_params.params;
         let d = obf_params.d;
         let level_size = (1u64 << obf_params.level_width) as usize;
-        let mut rs = Vec::with_capacity(level_size);
-        let mut rgs = Vec::with_capacity(level_size);
         let one = S::M::identity(params, 1, None);
         let gadget_d_plus_1 = S::M::gadget_matrix(params, d + 1);
-        for i in 0..level_size {
-            let tag = format!("R_{}", i).into_bytes();
-            let r_i_bar = hash_sampler.sample_hash(params, hash_key, &tag, d, d, DistType::BitDist);
-            let r_i = r_i_bar.concat_diag(&[&one]);
-            let rg = r_i.clone() * &gadget_d_plus_1;
-            rs.push(r_i);
-            rgs.push(rg);
-        }
+        let r_specs =
+            (0..level_size).map(|i| (Tag::Level(i as u32).encode(), d, d)).collect::<Vec<_>>();
+        let r_bars = hash_sampler.sample_hash_many(params, hash_key, &r_specs, DistType::BitDist);
+        let rs = r_bars.iter().map(|r_i_bar| r_i_bar.concat_diag(&[&one])).collect::<Vec<_>>();
+        let rgs = rs.iter().map(|r_i| r_i.clone() * &gadget_d_plus_1).collect::<Vec<_>>();
 
         let log_base_q = params.modulus_digits();
         let dim = params.ring_dimension() as usize;
         // input bits, poly of the RLWE key
-        let packed_input_size = obf_params.input_size.div_ceil(dim) + 1;
+        let packed_input_size = packed_input_size(obf_params.input_size, dim);
         let packed_output_size = obf_params.public_circuit.num_output() / (2 * log_base_q);
-        let a_rlwe_bar =
-            hash_sampler.sample_hash(params, hash_key, TAG_A_RLWE_BAR, 1, 1, DistType::FinRingDist);
-
-        let a_prf_raw = hash_sampler.sample_hash(
-            params,
-            hash_key,
-            TAG_A_PRF,
-            d + 1,
-            packed_output_size,
-            DistType::FinRingDist,
-        );
+        let specs =
+            [(TAG_A_RLWE_BAR.encode(), 1, 1), (TAG_A_PRF.encode(), d + 1, packed_output_size)];
+        let mut matrices =
+            hash_sampler.sample_hash_many(params, hash_key, &specs, DistType::FinRingDist);
+        let a_prf_raw = matrices.pop().unwrap();
+        let a_rlwe_bar = matrices.pop().unwrap();
         let a_prf = a_prf_raw.modulus_switch(&obf_params.switched_modulus);
         Self { rs, a_rlwe_bar, rgs, a_prf, packed_input_size, packed_output_size, _s: PhantomData }
     }
@@ -152,6 +163,17 @@ mod test {
         },
     };
 
+    #[test]
+    fn test_public_sampling_tags() {
+        // The tags of the public matrices and of the input public keys are pairwise distinct
+        let mut tags = (0..4).map(|i| Tag::Level(i).encode()).collect::<Vec<_>>();
+        tags.extend((0..4).map(bgg_pubkey_input_tag));
+        tags.push(TAG_A_RLWE_BAR.encode());
+        tags.push(TAG_A_PRF.encode());
+        let distinct = tags.iter().collect::<std::collections::HashSet<_>>();
+        assert_eq!(distinct.len(), tags.len());
+    }
+
     #[test]
     fn test_build_final_step_circuit() {
         // 1. Set up parameters
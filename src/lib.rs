@@ -1,8 +1,25 @@
 #![allow(clippy::needless_range_loop)]
 #![allow(clippy::too_many_arguments)]
 
+#[cfg(all(feature = "eval-only", feature = "openfhe"))]
+compile_error!(
+    "the `eval-only` feature excludes OpenFHE and cannot be combined with `openfhe`, `keygen` or \
+     `capi`; disable the default features"
+);
+
 pub mod bgg;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod compat;
+pub mod conformance;
+pub mod counters;
+pub mod error;
 pub mod io;
+pub mod migrate;
 pub mod poly;
+#[cfg(all(any(test, feature = "proptest"), feature = "openfhe"))]
+pub mod proptest_utils;
+pub mod security;
+#[cfg(feature = "keygen")]
 pub mod test_utils;
 pub mod utils;
@@ -0,0 +1,407 @@
+//! Format versions of the serialized artifacts and upgrades from older versions.
+//!
+//! JSON artifacts carry a `version` field (missing means version 0) and are upgraded one version
+//! at a time by the hooks registered in a [`JsonMigrator`]. Binary streams start with a magic and
+//! a version; streams written before versioning have no header and are upgraded by prepending it.
+
+use crate::bgg::{encoding_stream::ENCODING_MAGIC, eval_key::EVAL_KEY_MAGIC};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+pub const CIRCUIT_VERSION: u32 = 1;
+pub const OBFUSCATION_PARAMS_VERSION: u32 = 2;
+pub const EVAL_KEY_STREAM_VERSION: u32 = 1;
+pub const ENCODING_STREAM_VERSION: u32 = 3;
+pub const KEY_CACHE_VERSION: u32 = 1;
+pub const SHARD_VERSION: u32 = 1;
+pub const OBFUSCATION_VERSION: u32 = 3;
+pub const FINGERPRINT_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum MigrationError {
+    /// The artifact was written by a newer version of the library.
+    UnsupportedVersion { artifact: &'static str, version: u32, current: u32 },
+    /// No hook upgrades the artifact from this version.
+    MissingHook { artifact: &'static str, version: u32 },
+    Invalid(String),
+    Io(io::Error),
+}
+
+impl std::fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedVersion { artifact, version, current } => write!(
+                f,
+                "{} version {} is newer than the supported version {}",
+                artifact, version, current
+            ),
+            Self::MissingHook { artifact, version } => {
+                write!(f, "no migration of {} from version {}", artifact, version)
+            }
+            Self::Invalid(msg) => write!(f, "invalid artifact: {}", msg),
+            Self::Io(err) => write!(f, "io error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl From<io::Error> for MigrationError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Upgrades a JSON artifact from the version given as key to the next one.
+pub type JsonHook = fn(Value) -> Result<Value, MigrationError>;
+
+/// Upgrades JSON artifacts of one kind to the current version by chaining hooks.
+#[derive(Debug, Clone)]
+pub struct JsonMigrator {
+    artifact: &'static str,
+    current: u32,
+    hooks: BTreeMap<u32, JsonHook>,
+}
+
+impl JsonMigrator {
+    pub fn new(artifact: &'static str, current: u32) -> Self {
+        Self { artifact, current, hooks: BTreeMap::new() }
+    }
+
+    /// Registers the hook upgrading version `from` to `from + 1`.
+    pub fn register(mut self, from: u32, hook: JsonHook) -> Self {
+        self.hooks.insert(from, hook);
+        self
+    }
+
+    pub fn migrate(&self, mut value: Value) -> Result<Value, MigrationError> {
+        let mut version = value.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+        if version > self.current {
+            return Err(MigrationError::UnsupportedVersion {
+                artifact: self.artifact,
+                version,
+                current: self.current,
+            });
+        }
+        while version < self.current {
+            let hook = self
+                .hooks
+                .get(&version)
+                .ok_or(MigrationError::MissingHook { artifact: self.artifact, version })?;
+            value = hook(value)?;
+            version += 1;
+        }
+        set_version(&mut value, self.current)?;
+        Ok(value)
+    }
+}
+
+fn set_version(value: &mut Value, version: u32) -> Result<(), MigrationError> {
+    value
+        .as_object_mut()
+        .ok_or_else(|| MigrationError::Invalid("expected a JSON object".to_string()))?
+        .insert("version".to_string(), version.into());
+    Ok(())
+}
+
+/// Version 1 only adds the version field, which is also set on every sub-circuit.
+fn upgrade_circuit_v0(mut value: Value) -> Result<Value, MigrationError> {
+    if let Some(sub_circuits) = value.get_mut("sub_circuits").and_then(Value::as_object_mut) {
+        for sub_circuit in sub_circuits.values_mut() {
+            *sub_circuit = upgrade_circuit_v0(sub_circuit.take())?;
+        }
+    }
+    set_version(&mut value, 1)?;
+    Ok(value)
+}
+
+/// Migrator for [`crate::bgg::circuit::serde::SerializablePolyCircuit`].
+pub fn circuit_migrator() -> JsonMigrator {
+    JsonMigrator::new("circuit", CIRCUIT_VERSION).register(0, upgrade_circuit_v0)
+}
+
+/// Version 2 adds the ring parameters. They cannot be recovered, so older parameters only
+/// upgrade if they were added by hand.
+fn upgrade_obfuscation_params_v1(value: Value) -> Result<Value, MigrationError> {
+    for field in ["ring_dimension", "crt_depth", "crt_bits", "base_bits"] {
+        if value.get(field).is_none() {
+            return Err(MigrationError::Invalid(format!("obfuscation params lack {}", field)));
+        }
+    }
+    Ok(value)
+}
+
+/// Migrator for [`crate::io::serde::SerializableObfuscationParams`].
+pub fn obfuscation_params_migrator() -> JsonMigrator {
+    JsonMigrator::new("obfuscation params", OBFUSCATION_PARAMS_VERSION)
+        .register(0, Ok)
+        .register(1, upgrade_obfuscation_params_v1)
+}
+
+/// Copies a binary stream to `writer`, replacing its header by the current one. Unversioned
+/// streams are the version 1 layout without a header, and `inserted[v]` holds the bytes that
+/// upgrade a stream from version `v` to `v + 1` when written right after the header. Returns the
+/// version the stream had.
+fn migrate_stream<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    artifact: &'static str,
+    magic: &[u8; 4],
+    current: u32,
+    inserted: &[&[u8]],
+) -> Result<u32, MigrationError> {
+    let mut head = [0u8; 8];
+    let mut len = 0;
+    while len < head.len() {
+        match reader.read(&mut head[len..])? {
+            0 => break,
+            n => len += n,
+        }
+    }
+    let version = if len == head.len() && &head[..4] == magic {
+        u32::from_le_bytes(head[4..].try_into().unwrap())
+    } else {
+        0
+    };
+    if version > current {
+        return Err(MigrationError::UnsupportedVersion { artifact, version, current });
+    }
+    if version == current {
+        writer.write_all(&head[..len])?;
+    } else {
+        let upgrades = inserted
+            .get(version as usize..current as usize)
+            .ok_or(MigrationError::MissingHook { artifact, version })?;
+        writer.write_all(magic)?;
+        writer.write_all(&current.to_le_bytes())?;
+        // Later versions insert their bytes closer to the header
+        for bytes in upgrades.iter().rev() {
+            writer.write_all(bytes)?;
+        }
+        if version == 0 {
+            writer.write_all(&head[..len])?;
+        }
+    }
+    io::copy(reader, writer)?;
+    writer.flush()?;
+    Ok(version)
+}
+
+/// Upgrades a stream of [`crate::bgg::eval_key::EvalKeyWriter`] to the current version.
+pub fn migrate_eval_key_stream<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+) -> Result<u32, MigrationError> {
+    let (magic, current) = (EVAL_KEY_MAGIC, EVAL_KEY_STREAM_VERSION);
+    migrate_stream(reader, writer, "eval key stream", magic, current, &[&[]])
+}
+
+/// Upgrades a stream of [`crate::bgg::encoding_stream::write_encoding_stream`] to the current
+/// version. Version 2 adds the associated data, absent in older streams, and version 3 the key
+/// epoch, 0 in older streams.
+pub fn migrate_encoding_stream<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+) -> Result<u32, MigrationError> {
+    let (magic, current) = (ENCODING_MAGIC, ENCODING_STREAM_VERSION);
+    let inserted: &[&[u8]] = &[&[], &[0], &0u64.to_le_bytes()];
+    migrate_stream(reader, writer, "encoding stream", magic, current, inserted)
+}
+
+/// Writes the current version to the `version` file of the obfuscation in `dir`.
+pub fn write_obfuscation_version(dir: &Path) -> io::Result<()> {
+    fs::write(dir.join("version"), OBFUSCATION_VERSION.to_le_bytes())
+}
+
+/// Checks the `version` file of the obfuscation in `dir`. Obfuscations without it are version 0,
+/// whose public matrices were derived with plain byte-string tags instead of
+/// [`crate::poly::tag::Tag`]s, version 1 derived them without
+/// [`crate::poly::sampler::many_tag`] and version 2 read uniform coefficients from the hash bits
+/// instead of [`crate::poly::sampling::uniform_mod_q`]. None can be upgraded; they must be
+/// obfuscated again.
+pub fn check_obfuscation_version(dir: &Path) -> Result<(), MigrationError> {
+    let (artifact, current) = ("obfuscation", OBFUSCATION_VERSION);
+    let version = match fs::read(dir.join("version")) {
+        Ok(bytes) => u32::from_le_bytes(bytes.as_slice().try_into().map_err(|_| {
+            MigrationError::Invalid("the obfuscation version is not a u32".to_string())
+        })?),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => 0,
+        Err(err) => return Err(err.into()),
+    };
+    if version > current {
+        return Err(MigrationError::UnsupportedVersion { artifact, version, current });
+    }
+    if version < current {
+        return Err(MigrationError::MissingHook { artifact, version });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::{
+            circuit::{serde::SerializablePolyCircuit, PolyCircuit},
+            encoding_stream::{write_encoding_stream, EncodingStreamReader},
+            eval_key::{EvalKeyReader, EvalKeyWriter},
+        },
+        io::serde::SerializableObfuscationParams,
+        poly::{
+            dcrt::{DCRTPolyMatrix, DCRTPolyParams},
+            PolyMatrix,
+        },
+    };
+    use std::io::Cursor;
+
+    #[test]
+    fn test_migrate_circuit_v0() {
+        // A circuit with a sub-circuit serialized before versioning
+        let mut sub_circuit = PolyCircuit::new();
+        let sub_inputs = sub_circuit.input(2);
+        let sub_add_gate = sub_circuit.add_gate(sub_inputs[0], sub_inputs[1]);
+        sub_circuit.output(vec![sub_add_gate]);
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        let sub_circuit_id = circuit.register_sub_circuit(sub_circuit);
+        let outputs = circuit.call_sub_circuit(sub_circuit_id, &inputs);
+        circuit.output(outputs);
+        let json = SerializablePolyCircuit::from_circuit(&circuit).to_json_str();
+        let mut value: Value = serde_json::from_str(&json).unwrap();
+        value.as_object_mut().unwrap().remove("version");
+        value["sub_circuits"]["0"].as_object_mut().unwrap().remove("version");
+
+        // Migration sets the version everywhere and the circuit still loads
+        let migrated = circuit_migrator().migrate(value.clone()).unwrap();
+        assert_eq!(migrated["version"], CIRCUIT_VERSION);
+        assert_eq!(migrated["sub_circuits"]["0"]["version"], CIRCUIT_VERSION);
+        let loaded = SerializablePolyCircuit::from_json_str(&value.to_string());
+        assert_eq!(loaded.version(), CIRCUIT_VERSION);
+        assert_eq!(loaded.to_circuit(), circuit);
+
+        // Artifacts from a newer version are rejected
+        value["version"] = (CIRCUIT_VERSION + 1).into();
+        assert!(matches!(
+            circuit_migrator().migrate(value),
+            Err(MigrationError::UnsupportedVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn test_migrate_obfuscation_params_v1() {
+        let mut value = serde_json::json!({
+            "version": 1,
+            "switched_modulus": "17",
+            "input_size": 4,
+            "level_width": 1,
+            "public_circuit_path": "circuit.json",
+            "d": 1,
+            "encoding_sigma": 0.0,
+            "hardcoded_key_sigma": 0.0,
+            "p_sigma": 0.0,
+            "trapdoor_sigma": 4.578,
+        });
+
+        // Version 1 lacks the ring parameters, which cannot be made up
+        assert!(matches!(
+            obfuscation_params_migrator().migrate(value.clone()),
+            Err(MigrationError::Invalid(_))
+        ));
+
+        // With the ring parameters added by hand, the parameters load
+        let object = value.as_object_mut().unwrap();
+        object.insert("ring_dimension".to_string(), 4.into());
+        object.insert("crt_depth".to_string(), 2.into());
+        object.insert("crt_bits".to_string(), 17.into());
+        object.insert("base_bits".to_string(), 1.into());
+        let params = SerializableObfuscationParams::from_json_str(&value.to_string());
+        assert_eq!(params.version, OBFUSCATION_PARAMS_VERSION);
+        assert!(params.ring_params().unwrap() == DCRTPolyParams::default());
+
+        // The current version is written whatever the field holds
+        let json = SerializableObfuscationParams { version: 0, ..params }.to_json_str();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["version"], OBFUSCATION_PARAMS_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_eval_key_stream_v0() {
+        let params = DCRTPolyParams::default();
+        let matrix = DCRTPolyMatrix::identity(&params, 2, None);
+        let mut writer = EvalKeyWriter::new(Vec::new()).unwrap();
+        writer.write_key(7, &matrix).unwrap();
+        let current = writer.finish().unwrap();
+
+        // An unversioned stream is the current one without its header
+        let legacy = current[8..].to_vec();
+        assert!(EvalKeyReader::new(legacy.as_slice()).is_err());
+        let mut migrated = Vec::new();
+        let version = migrate_eval_key_stream(&mut legacy.as_slice(), &mut migrated).unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(migrated, current);
+
+        // Current streams are copied unchanged
+        let mut copied = Vec::new();
+        let version = migrate_eval_key_stream(&mut current.as_slice(), &mut copied).unwrap();
+        assert_eq!(version, EVAL_KEY_STREAM_VERSION);
+        assert_eq!(copied, current);
+
+        let mut reader = EvalKeyReader::new(migrated.as_slice()).unwrap();
+        let (gate_id, read) = reader.read_key::<DCRTPolyMatrix>(&params).unwrap().unwrap();
+        assert_eq!(gate_id, 7);
+        assert_eq!(read, matrix);
+    }
+
+    #[test]
+    fn test_migrate_encoding_stream_v1() {
+        let mut cursor = Cursor::new(Vec::new());
+        write_encoding_stream::<_, DCRTPolyMatrix>(&mut cursor, &[]).unwrap();
+        let current = cursor.into_inner();
+
+        // Version 2 streams lack the epoch after the header, version 1 streams also the
+        // associated data flag and unversioned streams also the header
+        let body = current[17..].to_vec();
+        let v1 = [&ENCODING_MAGIC[..], &1u32.to_le_bytes()[..], &body[..]].concat();
+        let v2 = [&ENCODING_MAGIC[..], &2u32.to_le_bytes()[..], &[0], &body[..]].concat();
+        for (legacy, expected_version) in [(v2, 2), (v1, 1), (body, 0)] {
+            let mut migrated = Vec::new();
+            let version = migrate_encoding_stream(&mut legacy.as_slice(), &mut migrated).unwrap();
+            assert_eq!(version, expected_version);
+            assert_eq!(migrated, current);
+        }
+        let reader = EncodingStreamReader::new(Cursor::new(current)).unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(reader.epoch(), 0);
+        assert_eq!(reader.associated_data(), None);
+    }
+
+    #[test]
+    fn test_check_obfuscation_version() {
+        let dir =
+            std::env::temp_dir().join(format!("diamond-obf-version-{}", rand::random::<u64>()));
+        fs::create_dir_all(&dir).unwrap();
+
+        // Obfuscations without a version file use the old tags
+        assert!(matches!(
+            check_obfuscation_version(&dir),
+            Err(MigrationError::MissingHook { version: 0, .. })
+        ));
+
+        // The written version is accepted
+        write_obfuscation_version(&dir).unwrap();
+        check_obfuscation_version(&dir).unwrap();
+
+        // Newer versions are rejected
+        fs::write(dir.join("version"), (OBFUSCATION_VERSION + 1).to_le_bytes()).unwrap();
+        assert!(matches!(
+            check_obfuscation_version(&dir),
+            Err(MigrationError::UnsupportedVersion { .. })
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
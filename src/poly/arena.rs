@@ -0,0 +1,194 @@
+//! A bump arena for the temporary polynomials of one step of a computation, such as the inputs
+//! of a circuit gate or the digits of one matrix row in a gadget decomposition.
+//!
+//! The arena is reset after every step and keeps its buffer, so a long computation only
+//! allocates when a step needs more temporaries than any step before it.
+
+/// Index of a value allocated in a [`PolyArena`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ArenaIdx(usize);
+
+/// Allocation statistics of a [`PolyArena`], used to measure allocator churn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ArenaStats {
+    /// Number of values allocated since the arena was created.
+    pub allocations: usize,
+    /// Number of times the arena was reset.
+    pub resets: usize,
+    /// Number of times the backing buffer was allocated or grown.
+    pub growths: usize,
+    /// Largest number of values alive at the same time.
+    pub high_water: usize,
+}
+
+impl ArenaStats {
+    /// Adds the statistics of another arena, e.g. of another worker thread.
+    pub fn merge(&mut self, other: ArenaStats) {
+        self.allocations += other.allocations;
+        self.resets += other.resets;
+        self.growths += other.growths;
+        self.high_water = self.high_water.max(other.high_water);
+    }
+}
+
+/// A bump arena for temporary values, freed all at once by [`Self::reset`].
+#[derive(Debug)]
+pub struct PolyArena<T> {
+    values: Vec<T>,
+    stats: ArenaStats,
+}
+
+impl<T> Default for PolyArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> PolyArena<T> {
+    pub fn new() -> Self {
+        Self { values: Vec::new(), stats: ArenaStats::default() }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        let growths = usize::from(capacity > 0);
+        Self {
+            values: Vec::with_capacity(capacity),
+            stats: ArenaStats { growths, ..ArenaStats::default() },
+        }
+    }
+
+    pub fn alloc(&mut self, value: T) -> ArenaIdx {
+        if self.values.len() == self.values.capacity() {
+            self.stats.growths += 1;
+        }
+        self.values.push(value);
+        self.stats.allocations += 1;
+        self.stats.high_water = self.stats.high_water.max(self.values.len());
+        ArenaIdx(self.values.len() - 1)
+    }
+
+    /// Allocates every value of `values` and returns the index of the first one; the rest follow
+    /// contiguously and can be read back with [`Self::slice`].
+    pub fn alloc_extend<I: IntoIterator<Item = T>>(&mut self, values: I) -> ArenaIdx {
+        let start = self.values.len();
+        for value in values {
+            self.alloc(value);
+        }
+        ArenaIdx(start)
+    }
+
+    pub fn get(&self, idx: ArenaIdx) -> &T {
+        &self.values[idx.0]
+    }
+
+    /// Returns `len` contiguous values starting at `start`.
+    pub fn slice(&self, start: ArenaIdx, len: usize) -> &[T] {
+        &self.values[start.0..start.0 + len]
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.values.capacity()
+    }
+
+    pub fn stats(&self) -> ArenaStats {
+        self.stats
+    }
+
+    /// Frees all values at once while keeping the allocated capacity. Indices returned before
+    /// the reset must not be used afterwards.
+    pub fn reset(&mut self) {
+        self.values.clear();
+        self.stats.resets += 1;
+    }
+}
+
+/// The rows of `G^-1(matrix)` for a gadget of `len` digits, where `row(i)` is the `i`-th row of
+/// the matrix and `decompose_poly` splits one entry into its digits. The digits of a row are
+/// kept in one arena that is reset for every row.
+pub(crate) fn decompose_rows<P: Clone>(
+    nrow: usize,
+    len: usize,
+    row: impl Fn(usize) -> Vec<P>,
+    decompose_poly: impl Fn(&P) -> Vec<P>,
+) -> Vec<Vec<P>> {
+    let mut arena = PolyArena::new();
+    let mut rows = Vec::with_capacity(nrow * len);
+    for i in 0..nrow {
+        arena.reset();
+        let entries = row(i);
+        let start = arena.alloc_extend(entries.iter().flat_map(&decompose_poly));
+        let digits = arena.slice(start, entries.len() * len);
+        rows.extend((0..len).map(|k| digits.iter().skip(k).step_by(len).cloned().collect()));
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::{
+        native::{NativePoly, NativePolyParams, NativePolyUniformSampler},
+        sampler::{DistType, PolyUniformSampler},
+        Poly, PolyMatrix, PolyParams,
+    };
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_arena_alloc_and_reset() {
+        let params = NativePolyParams::new(8, BigUint::from(65537u32), 4);
+        let sampler = NativePolyUniformSampler::new();
+        let mut arena = PolyArena::new();
+
+        // Simulate three gates, each allocating four temporaries and their sum
+        for _ in 0..3 {
+            let polys = sampler.sample_uniform(&params, 1, 4, DistType::FinRingDist).get_row(0);
+            let start = arena.alloc_extend(polys.clone());
+            assert_eq!(arena.slice(start, 4), &polys[..]);
+            let sum = arena.alloc(polys[0].clone() + &polys[1]);
+            assert_eq!(arena.get(sum), &(polys[0].clone() + &polys[1]));
+            assert_eq!(arena.len(), 5);
+            arena.reset();
+        }
+
+        // The buffer grows during the first gate only and is reused by the later ones
+        assert!(arena.is_empty());
+        assert!(arena.capacity() >= 5);
+        let stats = arena.stats();
+        assert_eq!((stats.allocations, stats.resets, stats.high_water), (15, 3, 5));
+        let first_gate_growths = stats.growths;
+        for _ in 0..100 {
+            arena.alloc_extend((0..5).map(|_| NativePoly::const_zero(&params)));
+            arena.reset();
+        }
+        assert_eq!(arena.stats().growths, first_gate_growths);
+    }
+
+    #[test]
+    fn test_decompose_rows() {
+        let params = NativePolyParams::new(8, BigUint::from(65537u32), 4);
+        let sampler = NativePolyUniformSampler::new();
+        let matrix = sampler.sample_uniform(&params, 3, 5, DistType::FinRingDist);
+
+        // The rows agree with the backend's decomposition
+        let len = params.modulus_digits();
+        let rows = decompose_rows(
+            matrix.row_size(),
+            len,
+            |i| matrix.get_row(i),
+            |poly| poly.decompose_base(&params),
+        );
+        assert_eq!(rows.len(), 3 * len);
+        let decomposed = matrix.decompose();
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(row, &decomposed.get_row(i));
+        }
+    }
+}
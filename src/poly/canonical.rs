@@ -0,0 +1,201 @@
+//! Canonical byte encoding of ring elements that is identical on every architecture.
+//!
+//! A coefficient is written as a little-endian unsigned integer of `ceil(log q / 8)` bytes and a
+//! polynomial as its coefficients in order, with no length prefix. A matrix is written as its
+//! row and column counts as little-endian `u32`s followed by its entries in row-major order.
+//! Nothing depends on `usize`, the host byte order or OpenFHE's internal layout, so encodings
+//! written on x86_64 servers decode identically on aarch64 or big-endian devices. Decoding
+//! rejects truncated input and unreduced coefficients instead of reducing them.
+use super::{Poly, PolyElem, PolyMatrix, PolyParams};
+use num_bigint::BigUint;
+
+/// Error returned when bytes are not a canonical encoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CanonicalError {
+    /// The input has `found` bytes instead of `expected`.
+    Length { expected: usize, found: usize },
+    /// The coefficient at `index` is not below the modulus.
+    Unreduced { index: usize },
+}
+
+impl std::fmt::Display for CanonicalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Length { expected, found } => {
+                write!(f, "expected {} bytes, found {}", expected, found)
+            }
+            Self::Unreduced { index } => write!(f, "coefficient {} is not reduced", index),
+        }
+    }
+}
+
+impl std::error::Error for CanonicalError {}
+
+/// The number of bytes of one coefficient modulo `modulus`.
+pub fn coeff_width(modulus: &BigUint) -> usize {
+    modulus.bits().div_ceil(8) as usize
+}
+
+pub fn encode_coeffs<E: PolyElem>(coeffs: &[E]) -> Vec<u8> {
+    coeffs.iter().flat_map(|coeff| coeff.to_bytes()).collect()
+}
+
+/// Decodes exactly `len` coefficients modulo `modulus`.
+pub fn decode_coeffs<E: PolyElem>(
+    modulus: &E::Modulus,
+    len: usize,
+    bytes: &[u8],
+) -> Result<Vec<E>, CanonicalError> {
+    let max = E::max_q(modulus).to_biguint().clone();
+    let width = coeff_width(&(&max + 1u8));
+    if bytes.len() != len * width {
+        return Err(CanonicalError::Length { expected: len * width, found: bytes.len() });
+    }
+    bytes
+        .chunks_exact(width)
+        .enumerate()
+        .map(|(index, chunk)| {
+            if BigUint::from_bytes_le(chunk) > max {
+                return Err(CanonicalError::Unreduced { index });
+            }
+            Ok(E::from_bytes(modulus, chunk))
+        })
+        .collect()
+}
+
+pub fn encode_poly<P: Poly>(poly: &P) -> Vec<u8> {
+    encode_coeffs(&poly.coeffs())
+}
+
+pub fn decode_poly<P: Poly>(params: &P::Params, bytes: &[u8]) -> Result<P, CanonicalError> {
+    let n = params.ring_dimension() as usize;
+    let coeffs = decode_coeffs::<P::Elem>(&params.modulus(), n, bytes)?;
+    Ok(P::from_coeffs(params, &coeffs))
+}
+
+pub fn encode_matrix<M: PolyMatrix>(matrix: &M) -> Vec<u8> {
+    let (nrow, ncol) = matrix.size();
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(nrow as u32).to_le_bytes());
+    bytes.extend_from_slice(&(ncol as u32).to_le_bytes());
+    for i in 0..nrow {
+        for entry in matrix.get_row(i) {
+            bytes.extend_from_slice(&encode_poly(&entry));
+        }
+    }
+    bytes
+}
+
+pub fn decode_matrix<M: PolyMatrix>(
+    params: &<M::P as Poly>::Params,
+    bytes: &[u8],
+) -> Result<M, CanonicalError> {
+    if bytes.len() < 8 {
+        return Err(CanonicalError::Length { expected: 8, found: bytes.len() });
+    }
+    let nrow = u32::from_le_bytes(bytes[0..4].try_into().unwrap()) as usize;
+    let ncol = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+    let width = params.modulus_bits().div_ceil(8) * params.ring_dimension() as usize;
+    let expected = nrow
+        .checked_mul(ncol)
+        .and_then(|entries| entries.checked_mul(width))
+        .and_then(|len| len.checked_add(8))
+        .unwrap_or(usize::MAX);
+    if bytes.len() != expected {
+        return Err(CanonicalError::Length { expected, found: bytes.len() });
+    }
+    let mut entries = bytes[8..].chunks_exact(width);
+    let rows = (0..nrow)
+        .map(|_| {
+            (0..ncol)
+                .map(|_| decode_poly::<M::P>(params, entries.next().unwrap()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(M::from_poly_vec(params, rows))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "openfhe")]
+    use crate::{
+        poly::dcrt::{DCRTPoly, DCRTPolyMatrix, DCRTPolyParams},
+        utils::{create_bit_random_poly, create_random_poly},
+    };
+
+    #[test]
+    #[cfg(feature = "openfhe")]
+    #[cfg_attr(miri, ignore)]
+    fn test_canonical_poly_round_trip() {
+        let params = DCRTPolyParams::default();
+        let poly = create_random_poly(&params);
+        let bytes = encode_poly(&poly);
+        let width = params.modulus_bits().div_ceil(8);
+        assert_eq!(bytes.len(), params.ring_dimension() as usize * width);
+        assert_eq!(bytes, poly.to_bytes());
+        assert_eq!(decode_poly::<DCRTPoly>(&params, &bytes).unwrap(), poly);
+
+        // Truncated input is rejected
+        assert_eq!(
+            decode_poly::<DCRTPoly>(&params, &bytes[1..]),
+            Err(CanonicalError::Length { expected: bytes.len(), found: bytes.len() - 1 })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "openfhe")]
+    #[cfg_attr(miri, ignore)]
+    fn test_canonical_matrix_round_trip() {
+        let params = DCRTPolyParams::default();
+        let entries = (0..2)
+            .map(|_| (0..3).map(|_| create_bit_random_poly(&params)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let matrix = DCRTPolyMatrix::from_poly_vec(&params, entries);
+        let bytes = encode_matrix(&matrix);
+        assert_eq!(&bytes[..8], &[2, 0, 0, 0, 3, 0, 0, 0]);
+        assert_eq!(decode_matrix::<DCRTPolyMatrix>(&params, &bytes).unwrap(), matrix);
+        assert!(matches!(
+            decode_matrix::<DCRTPolyMatrix>(&params, &bytes[..bytes.len() - 1]),
+            Err(CanonicalError::Length { .. })
+        ));
+    }
+
+    /// Golden vectors that only touch pure Rust code, so they also run under Miri and on
+    /// cross-compiled targets without OpenFHE, e.g. a big-endian `s390x-unknown-linux-gnu`.
+    #[cfg(feature = "cross-arch-tests")]
+    mod cross_arch {
+        use super::*;
+        use crate::poly::dcrt::FinRingElem;
+        use std::sync::Arc;
+
+        #[test]
+        fn test_canonical_coeffs_golden() {
+            // 65537 has 17 bits, so every coefficient takes 3 bytes
+            let modulus = Arc::new(BigUint::from(65537u32));
+            let coeffs = [1u32, 65536, 258]
+                .into_iter()
+                .map(|value| FinRingElem::new(value, modulus.clone()))
+                .collect::<Vec<_>>();
+            let golden = [1, 0, 0, 0, 0, 1, 2, 1, 0];
+            assert_eq!(encode_coeffs(&coeffs), golden);
+            assert_eq!(decode_coeffs::<FinRingElem>(&modulus, 3, &golden).unwrap(), coeffs);
+        }
+
+        #[test]
+        fn test_canonical_coeffs_rejects_noncanonical() {
+            let modulus = Arc::new(BigUint::from(65537u32));
+
+            // q itself and values above it are not reduced
+            let bytes = [1, 0, 0, 1, 0, 1];
+            assert_eq!(
+                decode_coeffs::<FinRingElem>(&modulus, 2, &bytes),
+                Err(CanonicalError::Unreduced { index: 1 })
+            );
+            assert_eq!(
+                decode_coeffs::<FinRingElem>(&modulus, 2, &bytes[..5]),
+                Err(CanonicalError::Length { expected: 6, found: 5 })
+            );
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Exact and approximate comparisons of matrices that report where they differ, for tests and
+//! for debugging computations whose noise rules out exact comparisons, e.g. a decrypted
+//! encoding against its expected plaintext times a scale.
+use super::{norms::centered_abs, plaintext::modulus_biguint, Poly, PolyElem, PolyMatrix};
+use itertools::Itertools;
+use num_bigint::BigUint;
+use std::fmt;
+
+/// Why two matrices are not equal, or not within the requested distance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatrixDiff {
+    Shape {
+        lhs: (usize, usize),
+        rhs: (usize, usize),
+    },
+    /// The first coefficient, in row-major order of the entries, whose centered difference
+    /// exceeds the tolerance, which is zero for [`matrix_eq`].
+    Entry {
+        row: usize,
+        col: usize,
+        coeff: usize,
+        lhs: BigUint,
+        rhs: BigUint,
+        distance: BigUint,
+    },
+}
+
+impl fmt::Display for MatrixDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Shape { lhs, rhs } => {
+                write!(f, "matrices of sizes {:?} and {:?} differ in shape", lhs, rhs)
+            }
+            Self::Entry { row, col, coeff, lhs, rhs, distance } => write!(
+                f,
+                "entry ({}, {}) differs at coefficient {}: {} vs {} (distance {})",
+                row, col, coeff, lhs, rhs, distance
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MatrixDiff {}
+
+/// Compares `lhs` and `rhs` exactly, locating the first differing coefficient if they differ.
+pub fn matrix_eq<M: PolyMatrix>(lhs: &M, rhs: &M) -> Result<(), MatrixDiff> {
+    if lhs == rhs {
+        return Ok(());
+    }
+    matrix_close(lhs, rhs, &BigUint::ZERO)
+}
+
+/// Checks that every coefficient of `lhs - rhs`, centered in `(-q/2, q/2]`, is at most
+/// `eps_norm` in absolute value.
+pub fn matrix_close<M: PolyMatrix>(lhs: &M, rhs: &M, eps_norm: &BigUint) -> Result<(), MatrixDiff> {
+    if lhs.size() != rhs.size() {
+        return Err(MatrixDiff::Shape { lhs: lhs.size(), rhs: rhs.size() });
+    }
+    let q = modulus_biguint::<M::P>(lhs.params());
+    for row in 0..lhs.row_size() {
+        let (lhs_row, rhs_row) = (lhs.get_row(row), rhs.get_row(row));
+        for (col, (lhs_entry, rhs_entry)) in lhs_row.iter().zip(rhs_row.iter()).enumerate() {
+            if lhs_entry == rhs_entry {
+                continue;
+            }
+            let (lhs_coeffs, rhs_coeffs) = (lhs_entry.coeffs(), rhs_entry.coeffs());
+            for (coeff, (a, b)) in lhs_coeffs.iter().zip(rhs_coeffs.iter()).enumerate() {
+                let (a, b) = (a.to_biguint(), b.to_biguint());
+                let distance = centered_abs(&((a + &q - b) % &q), &q);
+                if &distance > eps_norm {
+                    let (lhs, rhs) = (a.clone(), b.clone());
+                    return Err(MatrixDiff::Entry { row, col, coeff, lhs, rhs, distance });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes the size of `matrix` followed by one line per row, with each entry as its list of
+/// coefficients centered in `(-q/2, q/2]`, so that small noise reads as small numbers.
+pub fn fmt_matrix<M: PolyMatrix>(matrix: &M, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let (nrow, ncol) = matrix.size();
+    let q = modulus_biguint::<M::P>(matrix.params());
+    writeln!(f, "{}x{} matrix", nrow, ncol)?;
+    for row in 0..nrow {
+        let entries = matrix.get_row(row).iter().map(|entry| {
+            let coeffs = entry.coeffs().iter().map(|coeff| {
+                let coeff = coeff.to_biguint();
+                let abs = centered_abs(coeff, &q);
+                if &abs == coeff {
+                    abs.to_string()
+                } else {
+                    format!("-{}", abs)
+                }
+            });
+            format!("[{}]", coeffs.format(", "))
+        });
+        writeln!(f, "{}", entries.format(" "))?;
+    }
+    Ok(())
+}
+
+/// Asserts [`matrix_eq`], printing the first differing coefficient.
+#[macro_export]
+macro_rules! assert_matrix_eq {
+    ($lhs:expr, $rhs:expr $(,)?) => {
+        if let Err(diff) = $crate::poly::compare::matrix_eq(&$lhs, &$rhs) {
+            panic!("assertion `lhs == rhs` failed: {}", diff);
+        }
+    };
+}
+
+/// Asserts [`matrix_close`], printing the first coefficient out of tolerance.
+#[macro_export]
+macro_rules! assert_matrix_close {
+    ($lhs:expr, $rhs:expr, $eps_norm:expr $(,)?) => {
+        if let Err(diff) = $crate::poly::compare::matrix_close(&$lhs, &$rhs, &$eps_norm) {
+            panic!("assertion `lhs ≈ rhs` failed: {}", diff);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::{
+        native::{NativePoly, NativePolyMatrix, NativePolyParams, NativePolyUniformSampler},
+        sampler::{DistType, PolyUniformSampler},
+    };
+
+    #[test]
+    fn test_matrix_eq_and_close() {
+        let params = NativePolyParams::new(8, BigUint::from(65537u32), 4);
+        let sampler = NativePolyUniformSampler::new();
+        let matrix = sampler.sample_uniform(&params, 2, 3, DistType::FinRingDist);
+        assert_matrix_eq!(matrix, matrix.clone());
+
+        // A small error at entry (1, 2) is reported exactly but tolerated approximately
+        let mut rows = (0..2).map(|i| matrix.get_row(i)).collect::<Vec<_>>();
+        let one = NativePoly::const_one(&params);
+        rows[1][2] = rows[1][2].clone() - (one.clone() + &one + &one);
+        let noisy = NativePolyMatrix::from_poly_vec(&params, rows);
+        let diff = matrix_eq(&matrix, &noisy).unwrap_err();
+        assert!(matches!(diff, MatrixDiff::Entry { row: 1, col: 2, coeff: 0, .. }));
+        assert!(diff.to_string().starts_with("entry (1, 2) differs at coefficient 0"));
+        assert_matrix_close!(matrix, noisy, BigUint::from(3u8));
+        assert!(matrix_close(&matrix, &noisy, &BigUint::from(2u8)).is_err());
+
+        // Shapes are compared first
+        let wide = NativePolyMatrix::zero(&params, 2, 4);
+        assert!(matches!(matrix_eq(&matrix, &wide), Err(MatrixDiff::Shape { .. })));
+    }
+
+    #[test]
+    fn test_matrix_display() {
+        let params = NativePolyParams::new(2, BigUint::from(17u32), 4);
+        let one = NativePoly::const_one(&params);
+        let minus_two = -(one.clone() + &one);
+        let matrix = NativePolyMatrix::from_poly_vec(&params, vec![vec![one, minus_two]]);
+        assert_eq!(matrix.to_string(), "1x2 matrix\n[1, 0] [-2, 0]\n");
+    }
+}
@@ -0,0 +1,242 @@
+//! Chains of moduli `q_0 > q_1 > ...` for leveled evaluation, where level `i` has
+//! `crt_depth - i` CRT towers and values are rescaled from one level to a lower one by modulus
+//! switching.
+use super::{DCRTPoly, DCRTPolyMatrix, DCRTPolyParams, FinRingElem};
+use crate::{
+    bgg::circuit::{Evaluable, PolyCircuit},
+    poly::{Poly, PolyMatrix, PolyParams},
+};
+use num_bigint::BigInt;
+use num_traits::Signed;
+
+/// A value tagged with the level of the modulus chain it lives at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Leveled<T> {
+    pub level: usize,
+    pub value: T,
+}
+
+impl<T> Leveled<T> {
+    pub fn new(level: usize, value: T) -> Self {
+        Self { level, value }
+    }
+}
+
+/// Error returned when values at different levels are combined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LevelError {
+    /// The level does not exist in the chain.
+    OutOfRange { level: usize, levels: usize },
+    /// An input is at a different level than the constant-one input.
+    Mismatch { expected: usize, found: usize },
+    /// Values can only be switched down the chain.
+    NotLower { from: usize, to: usize },
+}
+
+impl std::fmt::Display for LevelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OutOfRange { level, levels } => {
+                write!(f, "level {} is out of range for a chain of {} levels", level, levels)
+            }
+            Self::Mismatch { expected, found } => {
+                write!(f, "expected inputs at level {}, found level {}", expected, found)
+            }
+            Self::NotLower { from, to } => {
+                write!(f, "cannot switch from level {} to level {}", from, to)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModulusChain {
+    levels: Vec<DCRTPolyParams>,
+}
+
+impl DCRTPolyParams {
+    /// Returns the chain of `levels` moduli starting at these parameters, dropping one CRT tower
+    /// per level. Panics unless `1 <= levels <= crt_depth`.
+    pub fn chain(&self, levels: usize) -> ModulusChain {
+        assert!(
+            (1..=self.crt_depth()).contains(&levels),
+            "levels must be in 1..={}, got {}",
+            self.crt_depth(),
+            levels
+        );
+        let levels = (0..levels)
+            .map(|level| {
+                DCRTPolyParams::new(
+                    self.ring_dimension(),
+                    self.crt_depth() - level,
+                    self.crt_bits(),
+                    self.base_bits(),
+                )
+            })
+            .collect();
+        ModulusChain { levels }
+    }
+}
+
+impl ModulusChain {
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    pub fn params(&self, level: usize) -> Result<&DCRTPolyParams, LevelError> {
+        self.levels.get(level).ok_or(LevelError::OutOfRange { level, levels: self.levels.len() })
+    }
+
+    /// Switches a polynomial from level `from` down to level `to`, mapping every coefficient
+    /// `c` (as a centered representative) to `round(c * q_to / q_from)`.
+    pub fn switch_poly(
+        &self,
+        poly: &Leveled<DCRTPoly>,
+        to: usize,
+    ) -> Result<Leveled<DCRTPoly>, LevelError> {
+        let from = poly.level;
+        if to < from {
+            return Err(LevelError::NotLower { from, to });
+        }
+        let q_from = BigInt::from(self.params(from)?.modulus().as_ref().clone());
+        let to_params = self.params(to)?;
+        let q_to = BigInt::from(to_params.modulus().as_ref().clone());
+        let half = &q_from >> 1;
+        let coeffs = poly
+            .value
+            .coeffs()
+            .iter()
+            .map(|coeff| {
+                let value = BigInt::from(coeff.value().clone());
+                let centered = if value > half { value - &q_from } else { value };
+                // round(c * q_to / q_from) = floor((2 * c * q_to + q_from) / (2 * q_from))
+                let numerator = centered * &q_to * 2 + &q_from;
+                let denominator = &q_from * 2;
+                let mut scaled = &numerator / &denominator;
+                if (&numerator % &denominator).is_negative() {
+                    scaled -= 1;
+                }
+                FinRingElem::new(scaled, to_params.modulus())
+            })
+            .collect::<Vec<_>>();
+        Ok(Leveled::new(to, DCRTPoly::from_coeffs(to_params, &coeffs)))
+    }
+
+    /// Switches every entry of a matrix, e.g. the vector of an output encoding before it is
+    /// published or decrypted, from its level down to level `to`.
+    pub fn switch_matrix(
+        &self,
+        matrix: &Leveled<DCRTPolyMatrix>,
+        to: usize,
+    ) -> Result<Leveled<DCRTPolyMatrix>, LevelError> {
+        let to_params = self.params(to)?;
+        let (nrow, ncol) = matrix.value.size();
+        let entries = (0..nrow)
+            .map(|i| {
+                (0..ncol)
+                    .map(|j| {
+                        let entry = Leveled::new(matrix.level, matrix.value.entry(i, j));
+                        self.switch_poly(&entry, to).map(|switched| switched.value)
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Leveled::new(to, DCRTPolyMatrix::from_poly_vec(to_params, entries)))
+    }
+
+    /// Evaluates `circuit` at the level of `one` after checking that every input is at the same
+    /// level.
+    pub fn eval<E: Evaluable<Params = DCRTPolyParams>>(
+        &self,
+        circuit: &PolyCircuit,
+        one: &Leveled<E>,
+        inputs: &[Leveled<E>],
+    ) -> Result<Leveled<Vec<E>>, LevelError> {
+        let params = self.params(one.level)?;
+        if let Some(input) = inputs.iter().find(|input| input.level != one.level) {
+            return Err(LevelError::Mismatch { expected: one.level, found: input.level });
+        }
+        let inputs = inputs.iter().map(|input| input.value.clone()).collect::<Vec<_>>();
+        Ok(Leveled::new(one.level, circuit.eval(params, &one.value, &inputs)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_random_poly;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_modulus_chain() {
+        let params = DCRTPolyParams::new(4, 3, 17, 1);
+        let chain = params.chain(3);
+        assert_eq!(chain.len(), 3);
+        assert_eq!(chain.params(0).unwrap(), &params);
+        assert_eq!(chain.params(2).unwrap().crt_depth(), 1);
+        assert!(chain.params(1).unwrap().modulus() < params.modulus());
+        assert_eq!(chain.params(3), Err(LevelError::OutOfRange { level: 3, levels: 3 }));
+    }
+
+    #[test]
+    fn test_modulus_chain_switch() {
+        let params = DCRTPolyParams::new(4, 2, 17, 1);
+        let chain = params.chain(2);
+        let q0 = chain.params(0).unwrap().modulus();
+        let q1 = chain.params(1).unwrap().modulus();
+
+        // Messages scaled by floor(q/4) stay scaled by about floor(q'/4)
+        let messages = [0u64, 1, 2, 3];
+        let scaled = messages
+            .iter()
+            .map(|&m| FinRingElem::new(BigUint::from(m) * (q0.as_ref() / 4u8), q0.clone()))
+            .collect::<Vec<_>>();
+        let poly = Leveled::new(0, DCRTPoly::from_coeffs(&params, &scaled));
+        let switched = chain.switch_poly(&poly, 1).unwrap();
+        assert_eq!(switched.level, 1);
+        for (coeff, &m) in switched.value.coeffs().iter().zip(messages.iter()) {
+            let expected = BigInt::from(m) * BigInt::from(q1.as_ref() / 4u8);
+            let diff = BigInt::from(coeff.value().clone()) - expected;
+            assert!(diff.magnitude() <= &BigUint::from(4u8));
+        }
+
+        // Switching to the same level is the identity and switching up is rejected
+        let poly = Leveled::new(0, create_random_poly(&params));
+        assert_eq!(chain.switch_poly(&poly, 0).unwrap(), poly);
+        let switched = chain.switch_poly(&poly, 1).unwrap();
+        assert_eq!(chain.switch_poly(&switched, 0), Err(LevelError::NotLower { from: 1, to: 0 }));
+    }
+
+    #[test]
+    fn test_modulus_chain_eval_levels() {
+        let params = DCRTPolyParams::new(4, 2, 17, 1);
+        let chain = params.chain(2);
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        let add_gate = circuit.add_gate(inputs[0], inputs[1]);
+        circuit.output(vec![add_gate]);
+
+        // Inputs at the same level evaluate at that level
+        let level1 = chain.params(1).unwrap();
+        let one = Leveled::new(1, DCRTPoly::const_one(level1));
+        let a = create_random_poly(level1);
+        let b = create_random_poly(level1);
+        let inputs = vec![Leveled::new(1, a.clone()), Leveled::new(1, b.clone())];
+        let result = chain.eval(&circuit, &one, &inputs).unwrap();
+        assert_eq!(result.level, 1);
+        assert_eq!(result.value, vec![a + b]);
+
+        // Mixed levels are rejected
+        let inputs = vec![inputs[0].clone(), Leveled::new(0, create_random_poly(&params))];
+        assert_eq!(
+            chain.eval(&circuit, &one, &inputs),
+            Err(LevelError::Mismatch { expected: 1, found: 0 })
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! The compact byte format of ring polynomials. It only needs the coefficients, so it is
+//! available without the OpenFHE backend.
+use super::element::FinRingElem;
+use crate::parallel_iter;
+use num_bigint::BigUint;
+use rayon::prelude::*;
+use std::sync::Arc;
+
+/// Encodes coefficients in `[0, modulus)` in the compact format described at
+/// [`crate::poly::Poly::to_compact_bytes`], shared with the other coefficient-based backends.
+pub(crate) fn compact_bytes(modulus: &BigUint, coeffs: &[FinRingElem]) -> Vec<u8> {
+    let q_half = modulus / 2u8;
+    let ring_dimension = coeffs.len();
+
+    // Create a bit vector of `ceil(n/8)` bytes to store flags for negative coefficients
+    let bit_vector_byte_size = ring_dimension.div_ceil(8);
+    let mut bit_vector = vec![0u8; bit_vector_byte_size];
+
+    let mut max_byte_size = 0;
+    let mut processed_values = Vec::with_capacity(ring_dimension);
+
+    // First pass: Process coefficients, fill up `bit_vector`` and calculate `max_byte_size`
+    for (i, coeff) in coeffs.iter().enumerate() {
+        // Center coefficients around 0
+        let value = if coeff.value() > &q_half {
+            let byte_idx = i / 8; // Determines which byte in the bit vector the flag for the i-th coeffs belongs to
+            let bit_idx = i % 8; // Determines the bit position within that byte
+            bit_vector[byte_idx] |= 1 << bit_idx; // Set flag for negative coefficient
+            modulus - coeff.value() // Convert to absolute value: q - coeff.value
+        } else if coeff.value() == &BigUint::ZERO {
+            BigUint::ZERO
+        } else {
+            coeff.value().clone()
+        };
+
+        processed_values.push(value.clone());
+
+        let value_bytes = value.to_bytes_le();
+        max_byte_size = std::cmp::max(max_byte_size, value_bytes.len());
+    }
+
+    let total_byte_size = 4 + bit_vector_byte_size + (ring_dimension * max_byte_size);
+    let mut result = vec![0u8; total_byte_size];
+
+    // Store max_byte_size in the first four bytes (little-endian)
+    let max_byte_size_bytes = (max_byte_size as u32).to_le_bytes();
+    result[0..4].copy_from_slice(&max_byte_size_bytes);
+
+    // Store bit vector in the next `ceil(n/8)` bytes
+    result[4..4 + bit_vector_byte_size].copy_from_slice(&bit_vector);
+
+    // Second pass: Store preprocessed coefficient values s.t. each coefficient is
+    // `max_byte_size` bytes long
+    for (i, value) in processed_values.iter().enumerate() {
+        let value_bytes = value.to_bytes_le();
+        let start_pos = 4 + bit_vector_byte_size + (i * max_byte_size);
+
+        result[start_pos..start_pos + value_bytes.len()].copy_from_slice(&value_bytes);
+    }
+
+    result
+}
+
+/// Whether `bytes` have the layout written by [`compact_bytes`] for `ring_dimension`
+/// coefficients, so that [`coeffs_from_compact_bytes`] does not read out of bounds.
+pub(crate) fn is_compact_layout(ring_dimension: usize, bytes: &[u8]) -> bool {
+    let header = 4 + ring_dimension.div_ceil(8);
+    if bytes.len() < header {
+        return false;
+    }
+    let max_byte_size = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    ring_dimension.checked_mul(max_byte_size).and_then(|len| len.checked_add(header)) ==
+        Some(bytes.len())
+}
+
+/// Decodes the coefficients encoded by [`compact_bytes`].
+pub(crate) fn coeffs_from_compact_bytes(
+    modulus: &Arc<BigUint>,
+    ring_dimension: usize,
+    bytes: &[u8],
+) -> Vec<FinRingElem> {
+    debug_assert!(is_compact_layout(ring_dimension, bytes), "malformed compact bytes");
+    // First four bytes contain the maximum byte size per coefficient
+    let max_byte_size = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+
+    // Next ceil(n/8) bytes contain the bit vector indicating if coefficients are negative
+    let bit_vector_byte_size = ring_dimension.div_ceil(8);
+    let bit_vector = &bytes[4..4 + bit_vector_byte_size];
+
+    // Remaining bytes contain coefficient values
+    parallel_iter!(0..ring_dimension)
+        .map(|i| {
+            let start = 4 + bit_vector_byte_size + (i * max_byte_size);
+            let end = start + max_byte_size;
+            let value_bytes = &bytes[start..end];
+
+            // Values are reduced first, so that bytes not written by `compact_bytes` cannot
+            // underflow below
+            let value = BigUint::from_bytes_le(value_bytes) % modulus.as_ref();
+
+            let byte_idx = i / 8;
+            let bit_idx = i % 8;
+            let is_negative = (bit_vector[byte_idx] & (1 << bit_idx)) != 0;
+
+            // Convert back from centered representation
+            let final_value = if is_negative {
+                // If negative flag is set, compute q - value
+                modulus.as_ref() - &value
+            } else {
+                // Otherwise, use value as is
+                value
+            };
+
+            FinRingElem::new(final_value, modulus.clone())
+        })
+        .collect()
+}
@@ -0,0 +1,107 @@
+//! OpenFHE keeps the `ILDCRTParams` of a polynomial behind a `shared_ptr` inside the polynomial
+//! itself, and every FFI call rebuilds them from the plain integers of [`DCRTPolyParams`], so a
+//! [`DCRTPoly`] never points into Rust-owned parameters and may outlive them on any thread.
+//! A [`Context`] makes the ownership explicit on the Rust side: it is a reference-counted handle
+//! owning one set of parameters, from which polynomials and samplers are built.
+use super::{
+    DCRTPoly, DCRTPolyParams, DCRTPolyTrapdoorSampler, DCRTPolyUniformSampler, FinRingElem,
+    ParamsError,
+};
+use crate::poly::{
+    sampler::{PolyTrapdoorSampler, PolyUniformSampler},
+    Poly,
+};
+use std::{ops::Deref, sync::Arc};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Context {
+    params: Arc<DCRTPolyParams>,
+}
+
+impl Context {
+    /// Creates a context sharing the process-wide parameters of [`DCRTPolyParams::cached`].
+    pub fn new(
+        ring_dimension: u32,
+        crt_depth: usize,
+        crt_bits: usize,
+        base_bits: u32,
+    ) -> Result<Self, ParamsError> {
+        Ok(Self { params: DCRTPolyParams::cached(ring_dimension, crt_depth, crt_bits, base_bits)? })
+    }
+
+    pub fn from_params(params: DCRTPolyParams) -> Self {
+        Self { params: Arc::new(params) }
+    }
+
+    pub fn params(&self) -> &DCRTPolyParams {
+        &self.params
+    }
+
+    /// Returns the shared parameters, e.g. to move them into another thread.
+    pub fn shared_params(&self) -> Arc<DCRTPolyParams> {
+        self.params.clone()
+    }
+
+    pub fn poly_from_coeffs(&self, coeffs: &[FinRingElem]) -> DCRTPoly {
+        DCRTPoly::from_coeffs(&self.params, coeffs)
+    }
+
+    pub fn uniform_sampler(&self) -> DCRTPolyUniformSampler {
+        DCRTPolyUniformSampler::new()
+    }
+
+    pub fn trapdoor_sampler(&self, sigma: f64) -> DCRTPolyTrapdoorSampler {
+        DCRTPolyTrapdoorSampler::new(&self.params, sigma)
+    }
+}
+
+impl Deref for Context {
+    type Target = DCRTPolyParams;
+
+    fn deref(&self) -> &DCRTPolyParams {
+        &self.params
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        poly::{sampler::DistType, PolyParams},
+        utils::create_random_poly,
+    };
+
+    #[test]
+    fn test_context_shares_params() {
+        let context = Context::new(4, 2, 17, 1).unwrap();
+        let other = context.clone();
+        assert!(Arc::ptr_eq(&context.shared_params(), &other.shared_params()));
+        assert_eq!(context.params(), &DCRTPolyParams::default());
+        assert_eq!(context.modulus(), DCRTPolyParams::default().modulus());
+        assert!(Context::new(3, 2, 17, 1).is_err());
+    }
+
+    #[test]
+    fn test_polys_outlive_context() {
+        let context = Context::from_params(DCRTPolyParams::default());
+        let a = create_random_poly(&context);
+        let b = create_random_poly(&context);
+        let matrix =
+            context.uniform_sampler().sample_uniform(&context, 2, 2, DistType::FinRingDist);
+        let expected = a.clone() * &b;
+        drop(context);
+
+        // Polynomials stay valid after the context is dropped, including on other threads
+        let handles = (0..4)
+            .map(|_| {
+                let (a, b, matrix) = (a.clone(), b.clone(), matrix.clone());
+                std::thread::spawn(move || (a * &b, matrix.entry(0, 0) + matrix.entry(1, 1)))
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            let (product, trace) = handle.join().unwrap();
+            assert_eq!(product, expected);
+            assert_eq!(trace, matrix.entry(0, 0) + matrix.entry(1, 1));
+        }
+    }
+}
@@ -3,34 +3,46 @@ use openfhe::{
     ffi::{GetMatrixCols, GetMatrixElement, GetMatrixRows, Matrix},
 };
 
-use crate::poly::Poly;
+use crate::{error::DiamondError, poly::Poly};
 
-use super::{DCRTPoly, DCRTPolyParams};
+use super::{ffi_guard, DCRTPoly, DCRTPolyParams};
 
 pub(crate) struct CppMatrix {
     pub(crate) params: DCRTPolyParams,
     pub(crate) inner: UniquePtr<Matrix>,
+    nrow: usize,
+    ncol: usize,
 }
 
 unsafe impl Send for CppMatrix {}
 unsafe impl Sync for CppMatrix {}
 
 impl CppMatrix {
-    pub fn new(params: &DCRTPolyParams, inner: UniquePtr<Matrix>) -> Self {
-        CppMatrix { params: params.clone(), inner }
+    /// Wraps a matrix returned by the OpenFHE function `call`, failing if the pointer is null.
+    pub fn new(
+        params: &DCRTPolyParams,
+        call: &'static str,
+        inner: UniquePtr<Matrix>,
+    ) -> Result<Self, DiamondError> {
+        let inner = ffi_guard::check_ptr(call, inner)?;
+        let nrow = ffi_guard::guard("GetMatrixRows", || GetMatrixRows(&inner))?;
+        let ncol = ffi_guard::guard("GetMatrixCols", || GetMatrixCols(&inner))?;
+        Ok(CppMatrix { params: params.clone(), inner, nrow, ncol })
     }
 
     pub fn nrow(&self) -> usize {
-        GetMatrixRows(&self.inner)
+        self.nrow
     }
 
     pub fn ncol(&self) -> usize {
-        GetMatrixCols(&self.inner)
+        self.ncol
     }
 
-    pub fn entry(&self, i: usize, j: usize) -> DCRTPoly {
-        let poly = DCRTPoly::new(GetMatrixElement(&self.inner, i, j));
+    pub fn entry(&self, i: usize, j: usize) -> Result<DCRTPoly, DiamondError> {
+        let call = "GetMatrixElement";
+        let ptr_poly = ffi_guard::guard(call, || GetMatrixElement(&self.inner, i, j))?;
+        let poly = DCRTPoly::from_ffi(call, ptr_poly)?;
         // This ensures that coefficients are rounded to Z_q.
-        DCRTPoly::from_coeffs(&self.params, &poly.coeffs())
+        DCRTPoly::try_from_coeffs(&self.params, &poly.coeffs())
     }
 }
@@ -51,11 +51,25 @@ impl FinRingElem {
         &self.modulus
     }
 
+    /// Rescales the value to `new_modulus`. The result keeps the original modulus, since it is
+    /// placed back into polynomials of the original parameters.
     pub fn modulus_switch(&self, new_modulus: Arc<BigUint>) -> Self {
         let value =
             ((&self.value * new_modulus.as_ref()) / self.modulus.as_ref()) % new_modulus.as_ref();
         Self { value, modulus: self.modulus.clone() }
     }
+
+    /// Checks in debug builds that both operands of an arithmetic operation share a modulus, so
+    /// that values from different levels of a modulus chain are not mixed silently.
+    #[inline]
+    fn debug_check_modulus(&self, rhs: &Self) {
+        debug_assert!(
+            Arc::ptr_eq(&self.modulus, &rhs.modulus) || self.modulus == rhs.modulus,
+            "mixed moduli {} and {}",
+            self.modulus,
+            rhs.modulus
+        );
+    }
 }
 
 impl PolyElem for FinRingElem {
@@ -129,18 +143,21 @@ impl<'a> Add<&'a FinRingElem> for FinRingElem {
     type Output = Self;
 
     fn add(self, rhs: &'a Self) -> Self::Output {
+        self.debug_check_modulus(rhs);
         Self::new(self.value + &rhs.value, self.modulus)
     }
 }
 
 impl AddAssign for FinRingElem {
     fn add_assign(&mut self, rhs: Self) {
+        self.debug_check_modulus(&rhs);
         *self = Self::new(&self.value + rhs.value, self.modulus.clone());
     }
 }
 
 impl<'a> AddAssign<&'a FinRingElem> for FinRingElem {
     fn add_assign(&mut self, rhs: &'a Self) {
+        self.debug_check_modulus(rhs);
         *self = Self::new(&self.value + &rhs.value, self.modulus.clone());
     }
 }
@@ -157,18 +174,21 @@ impl<'a> Mul<&'a FinRingElem> for FinRingElem {
     type Output = Self;
 
     fn mul(self, rhs: &'a Self) -> Self::Output {
+        self.debug_check_modulus(rhs);
         Self::new(self.value * &rhs.value, self.modulus)
     }
 }
 
 impl MulAssign for FinRingElem {
     fn mul_assign(&mut self, rhs: Self) {
+        self.debug_check_modulus(&rhs);
         *self = Self::new(&self.value * rhs.value, self.modulus.clone());
     }
 }
 
 impl<'a> MulAssign<&'a FinRingElem> for FinRingElem {
     fn mul_assign(&mut self, rhs: &'a Self) {
+        self.debug_check_modulus(rhs);
         *self = Self::new(&self.value * &rhs.value, self.modulus.clone());
     }
 }
@@ -353,4 +373,13 @@ mod tests {
         assert_eq!(neg_a.value(), &BigUint::from(9800usize)); // -200 ≡ 9800 (mod 10000)
         assert_eq!(neg_a.modulus(), modulus.as_ref());
     }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "mixed moduli")]
+    fn test_element_mixed_moduli() {
+        let a = FinRingElem::new(3, Arc::new(BigUint::from(17u8)));
+        let b = FinRingElem::new(3, Arc::new(BigUint::from(19u8)));
+        let _ = a + b;
+    }
 }
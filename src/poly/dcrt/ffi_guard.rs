@@ -0,0 +1,124 @@
+//! Checks on the results of `openfhe::ffi` calls, translating failures into
+//! [`DiamondError::Ffi`] and logging them before they reach the caller. C++ exceptions that the
+//! bindings do not declare still abort the process and cannot be recovered here.
+//!
+//! Every call goes through [`guard`] or [`guard_ptr`], and the `try_` variants of the backend
+//! pass the errors up. Operators and trait methods, whose signatures cannot carry them, panic
+//! with the error through [`expect_ffi`].
+use crate::error::DiamondError;
+use openfhe::cxx::{memory::UniquePtrTarget, UniquePtr};
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::Mutex,
+};
+use tracing::error;
+
+/// Builds and logs the error for a failed call.
+pub fn failure(call: &'static str, context: impl Into<String>) -> DiamondError {
+    let context = context.into();
+    error!(call, context = context.as_str(), "OpenFHE call failed");
+    DiamondError::Ffi { call, context }
+}
+
+/// Returns the pointer returned by `call` if it is not null.
+pub fn check_ptr<T: UniquePtrTarget>(
+    call: &'static str,
+    ptr: UniquePtr<T>,
+) -> Result<UniquePtr<T>, DiamondError> {
+    if ptr.is_null() {
+        return Err(failure(call, "returned a null pointer"));
+    }
+    Ok(ptr)
+}
+
+/// Runs `f`, turning a panic in the Rust side of the call, e.g. while converting its result,
+/// into an error.
+pub fn guard<T>(call: &'static str, f: impl FnOnce() -> T) -> Result<T, DiamondError> {
+    catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        let context = payload
+            .downcast_ref::<&str>()
+            .map(|msg| msg.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "panicked".to_string());
+        failure(call, context)
+    })
+}
+
+/// Runs `call` under [`guard`] and checks the pointer it returns.
+pub fn guard_ptr<T: UniquePtrTarget>(
+    call: &'static str,
+    f: impl FnOnce() -> UniquePtr<T>,
+) -> Result<UniquePtr<T>, DiamondError> {
+    check_ptr(call, guard(call, f)?)
+}
+
+/// Unwraps the result of a call made by an operation that cannot return an error.
+pub fn expect_ffi<T>(result: Result<T, DiamondError>) -> T {
+    result.unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Keeps the first error of calls made in callbacks that cannot return one, such as the block
+/// closures of `replace_entries`, which continue with a default value instead.
+#[derive(Debug, Default)]
+pub struct FirstFailure(Mutex<Option<DiamondError>>);
+
+impl FirstFailure {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The value of `result`, or a default after recording its error.
+    pub fn take<T: Default>(&self, result: Result<T, DiamondError>) -> T {
+        result.unwrap_or_else(|err| {
+            self.0.lock().unwrap().get_or_insert(err);
+            T::default()
+        })
+    }
+
+    /// Fails with the first recorded error.
+    pub fn into_result(self) -> Result<(), DiamondError> {
+        self.0.into_inner().unwrap().map_or(Ok(()), Err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openfhe::ffi::DCRTPoly as DCRTPolyCxx;
+
+    #[test]
+    fn test_check_ptr_null() {
+        let err = check_ptr::<DCRTPolyCxx>("DCRTPolyGenFromDug", UniquePtr::null()).unwrap_err();
+        assert_eq!(
+            err,
+            DiamondError::Ffi {
+                call: "DCRTPolyGenFromDug",
+                context: "returned a null pointer".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_guard_catches_panic() {
+        assert_eq!(guard("GenModulus", || 7), Ok(7));
+        let err = guard("GenModulus", || -> u32 { panic!("invalid string") }).unwrap_err();
+        assert_eq!(
+            err,
+            DiamondError::Ffi { call: "GenModulus", context: "invalid string".to_string() }
+        );
+    }
+
+    #[test]
+    fn test_first_failure() {
+        let failures = FirstFailure::new();
+        assert_eq!(failures.take(Ok(3)), 3);
+        assert_eq!(failures.take::<i64>(Err(failure("GenerateIntegerKarney", "first"))), 0);
+        assert_eq!(failures.take::<i64>(Err(failure("GenerateIntegerKarney", "second"))), 0);
+        let err = failures.into_result().unwrap_err();
+        assert_eq!(
+            err,
+            DiamondError::Ffi { call: "GenerateIntegerKarney", context: "first".to_string() }
+        );
+        assert_eq!(FirstFailure::new().into_result(), Ok(()));
+    }
+}
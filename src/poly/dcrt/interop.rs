@@ -0,0 +1,213 @@
+//! Conversions between [`DCRTPoly`] and the ring polynomials of external lattice libraries such
+//! as phantom-zone's `phantom-zone-math`, which is not a dependency of this crate.
+//!
+//! An external polynomial type crosses the boundary by implementing [`ForeignPoly`] in the
+//! downstream crate, exchanging its coefficients as [`Elem`]s of a single word-sized modulus.
+//! `From`/`Into` cannot be offered because converting into a [`DCRTPoly`] needs its
+//! [`DCRTPolyParams`]. Evaluation (NTT) domain forms must be converted to coefficients on the
+//! external side first, since OpenFHE's NTT layout over the CRT towers differs from other
+//! libraries'.
+use super::{DCRTPoly, DCRTPolyMatrix, DCRTPolyParams, FinRingElem};
+use crate::{
+    bgg::BggEncoding,
+    poly::{Elem, Poly, PolyMatrix, PolyParams},
+};
+use num_bigint::BigUint;
+
+/// A polynomial of an external library in coefficient form.
+pub trait ForeignPoly: Sized {
+    /// The modulus of the coefficients.
+    fn modulus(&self) -> u64;
+    /// The coefficients modulo [`Self::modulus`], one per ring dimension.
+    fn coeffs(&self) -> Vec<Elem>;
+    fn from_coeffs(modulus: u64, coeffs: Vec<Elem>) -> Self;
+}
+
+/// Error returned when a value cannot cross the boundary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InteropError {
+    /// The external polynomial has a different number of coefficients than the ring dimension.
+    RingDimension { expected: usize, found: usize },
+    /// Encoding rows are only meaningful under the modulus they were sampled with.
+    Modulus { expected: BigUint, found: u64 },
+}
+
+impl std::fmt::Display for InteropError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RingDimension { expected, found } => {
+                write!(f, "expected {} coefficients, found {}", expected, found)
+            }
+            Self::Modulus { expected, found } => {
+                write!(f, "expected modulus {}, found {}", expected, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InteropError {}
+
+/// Converts an external polynomial, e.g. an attribute, lifting each coefficient to its centered
+/// representative in `(-q'/2, q'/2]` before reducing it modulo `params.modulus()`, so that
+/// short polynomials stay short when the moduli differ.
+pub fn from_foreign<F: ForeignPoly>(
+    params: &DCRTPolyParams,
+    poly: &F,
+) -> Result<DCRTPoly, InteropError> {
+    let coeffs = poly.coeffs();
+    let n = params.ring_dimension() as usize;
+    if coeffs.len() != n {
+        return Err(InteropError::RingDimension { expected: n, found: coeffs.len() });
+    }
+    let foreign_modulus = poly.modulus();
+    let modulus = params.modulus();
+    let coeffs = coeffs
+        .into_iter()
+        .map(|coeff| {
+            debug_assert_eq!(coeff.modulus(), foreign_modulus, "mixed moduli");
+            FinRingElem::new(coeff.centered(), modulus.clone())
+        })
+        .collect::<Vec<_>>();
+    Ok(DCRTPoly::from_coeffs(params, &coeffs))
+}
+
+/// Converts a polynomial to an external polynomial modulo `foreign_modulus`, lifting each
+/// coefficient to its centered representative modulo `params.modulus()` first.
+pub fn to_foreign<F: ForeignPoly>(
+    params: &DCRTPolyParams,
+    poly: &DCRTPoly,
+    foreign_modulus: u64,
+) -> F {
+    let modulus = params.modulus();
+    let half = modulus.as_ref() >> 1;
+    let coeffs = poly
+        .coeffs()
+        .iter()
+        .map(|coeff| {
+            let value = coeff.value();
+            if value > &half {
+                let neg = (modulus.as_ref() - value) % foreign_modulus;
+                -Elem::new(u64::try_from(neg).unwrap(), foreign_modulus)
+            } else {
+                Elem::new(u64::try_from(value % foreign_modulus).unwrap(), foreign_modulus)
+            }
+        })
+        .collect();
+    F::from_coeffs(foreign_modulus, coeffs)
+}
+
+/// Exports the vector of an encoding as one external polynomial per entry. The external modulus
+/// must equal `params.modulus()`, since an encoding does not survive a change of modulus.
+pub fn encoding_row_to_foreign<F: ForeignPoly>(
+    params: &DCRTPolyParams,
+    encoding: &BggEncoding<DCRTPolyMatrix>,
+    foreign_modulus: u64,
+) -> Result<Vec<F>, InteropError> {
+    if *params.modulus() != BigUint::from(foreign_modulus) {
+        return Err(InteropError::Modulus {
+            expected: params.modulus().as_ref().clone(),
+            found: foreign_modulus,
+        });
+    }
+    Ok((0..encoding.vector.col_size())
+        .map(|j| to_foreign(params, &encoding.vector.entry(0, j), foreign_modulus))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::sampler::{BGGEncodingSampler, BGGPublicKeySampler},
+        poly::{
+            dcrt::{DCRTPolyHashSampler, DCRTPolyUniformSampler},
+            sampler::PolyUniformSampler,
+        },
+        utils::{create_bit_random_poly, create_random_poly},
+    };
+    use keccak_asm::Keccak256;
+    use num_bigint::BigInt;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct ToyPoly {
+        modulus: u64,
+        coeffs: Vec<Elem>,
+    }
+
+    impl ForeignPoly for ToyPoly {
+        fn modulus(&self) -> u64 {
+            self.modulus
+        }
+
+        fn coeffs(&self) -> Vec<Elem> {
+            self.coeffs.clone()
+        }
+
+        fn from_coeffs(modulus: u64, coeffs: Vec<Elem>) -> Self {
+            Self { modulus, coeffs }
+        }
+    }
+
+    #[test]
+    fn test_foreign_poly_round_trip() {
+        let params = DCRTPolyParams::default();
+
+        // A short signed polynomial under a different 12-bit modulus
+        let foreign_modulus = 3329;
+        let coeffs = [1, 0, 3328, 3327].map(|c| Elem::new(c, foreign_modulus)).to_vec();
+        let toy = ToyPoly { modulus: foreign_modulus, coeffs };
+        let poly = from_foreign(&params, &toy).unwrap();
+        let q = params.modulus();
+        let expected = [1u64, 0, 1, 2]
+            .iter()
+            .zip([false, false, true, true])
+            .map(|(&abs, neg)| {
+                let value = if neg { -BigInt::from(abs) } else { BigInt::from(abs) };
+                FinRingElem::new(value, q.clone())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(poly.coeffs(), expected);
+        assert_eq!(to_foreign::<ToyPoly>(&params, &poly, foreign_modulus), toy);
+
+        // Random polynomials survive a round trip under the same modulus
+        let q_u64 = u64::try_from(q.as_ref()).unwrap();
+        let poly = create_random_poly(&params);
+        let toy: ToyPoly = to_foreign(&params, &poly, q_u64);
+        assert_eq!(from_foreign(&params, &toy).unwrap(), poly);
+
+        // A wrong number of coefficients is rejected
+        let coeffs = vec![Elem::new(0, foreign_modulus); 8];
+        let toy = ToyPoly { modulus: foreign_modulus, coeffs };
+        assert_eq!(
+            from_foreign(&params, &toy),
+            Err(InteropError::RingDimension { expected: 4, found: 8 })
+        );
+    }
+
+    #[test]
+    fn test_encoding_row_to_foreign() {
+        let params = DCRTPolyParams::default();
+        let key: [u8; 32] = rand::random();
+        let d = 2;
+        let bgg_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+        let pubkeys = bgg_sampler.sample(&params, b"interop", &[true]);
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let encodings = bgg_sampler.sample(&params, &pubkeys, &[create_random_poly(&params)]);
+
+        // The row is exported entry by entry under the same modulus
+        let q_u64 = u64::try_from(params.modulus().as_ref()).unwrap();
+        let row: Vec<ToyPoly> = encoding_row_to_foreign(&params, &encodings[1], q_u64).unwrap();
+        assert_eq!(row.len(), encodings[1].vector.col_size());
+        for (j, toy) in row.iter().enumerate() {
+            assert_eq!(from_foreign(&params, toy).unwrap(), encodings[1].vector.entry(0, j));
+        }
+
+        // Any other modulus is rejected
+        assert!(matches!(
+            encoding_row_to_foreign::<ToyPoly>(&params, &encodings[1], 3329),
+            Err(InteropError::Modulus { found: 3329, .. })
+        ));
+    }
+}
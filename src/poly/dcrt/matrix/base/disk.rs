@@ -164,6 +164,14 @@ impl<T: MatrixElem> BaseMatrix<T> {
         );
     }
 
+    /// Same as [`Self::replace_entries`], which already generates the entries tile by tile.
+    pub fn replace_entries_tiled<F>(&mut self, rows: Range<usize>, cols: Range<usize>, f: F)
+    where
+        F: Fn(Range<usize>, Range<usize>) -> Vec<Vec<T>> + Send + Sync,
+    {
+        self.replace_entries(rows, cols, f)
+    }
+
     #[cfg(feature = "disk")]
     pub(crate) unsafe fn replace_block_entries(
         &self,
@@ -209,6 +217,14 @@ impl<T: MatrixElem> BaseMatrix<T> {
         (self.nrow, self.ncol)
     }
 
+    /// The entries are stored serialized in the file, so there is nothing to shrink.
+    pub fn shrink_to_fit(&mut self) {}
+
+    /// Reads the entries out of the matrix, row by row.
+    pub fn into_entries(self) -> Vec<Vec<T>> {
+        self.block_entries(0..self.nrow, 0..self.ncol)
+    }
+
     pub fn slice(
         &self,
         row_start: usize,
@@ -216,6 +232,20 @@ impl<T: MatrixElem> BaseMatrix<T> {
         col_start: usize,
         col_end: usize,
     ) -> Self {
+        debug_assert!(
+            row_start <= row_end && row_end <= self.nrow,
+            "row range {}..{} out of {} rows",
+            row_start,
+            row_end,
+            self.nrow
+        );
+        debug_assert!(
+            col_start <= col_end && col_end <= self.ncol,
+            "column range {}..{} out of {} columns",
+            col_start,
+            col_end,
+            self.ncol
+        );
         let nrow = row_end - row_start;
         let ncol = col_end - col_start;
         let mut new_matrix = Self::new_empty(&self.params, nrow, ncol);
@@ -498,6 +528,7 @@ impl<T: MatrixElem> Add<&BaseMatrix<T>> for &BaseMatrix<T> {
             rhs.nrow,
             rhs.ncol
         );
+        debug_assert_eq!(self.params, rhs.params, "matrices over different moduli");
 
         let mut new_matrix = BaseMatrix::new_empty(&self.params, self.nrow, self.ncol);
         let f = |row_offsets: Range<usize>, col_offsets: Range<usize>| -> Vec<Vec<T>> {
@@ -538,6 +569,7 @@ impl<T: MatrixElem> Sub<&BaseMatrix<T>> for &BaseMatrix<T> {
             rhs.nrow,
             rhs.ncol
         );
+        debug_assert_eq!(self.params, rhs.params, "matrices over different moduli");
 
         let mut new_matrix = BaseMatrix::new_empty(&self.params, self.nrow, self.ncol);
         let f = |row_offsets: Range<usize>, col_offsets: Range<usize>| -> Vec<Vec<T>> {
@@ -584,6 +616,7 @@ impl<T: MatrixElem> Mul<&BaseMatrix<T>> for &BaseMatrix<T> {
             self.ncol,
             rhs.nrow
         );
+        debug_assert_eq!(self.params, rhs.params, "matrices over different moduli");
 
         let mut new_matrix = BaseMatrix::new_empty(&self.params, self.nrow, rhs.ncol);
         let (_, ip_offsets) = block_offsets(0..0, 0..self.ncol);
@@ -596,7 +629,7 @@ impl<T: MatrixElem> Mul<&BaseMatrix<T>> for &BaseMatrix<T> {
                         .block_entries(row_offsets.clone(), *cur_block_ip_idx..*next_block_ip_idx);
                     let other_block_polys = rhs
                         .block_entries(*cur_block_ip_idx..*next_block_ip_idx, col_offsets.clone());
-                    mul_block_matrices(self_block_polys, other_block_polys)
+                    T::mul_block(self_block_polys, other_block_polys)
                 })
                 .reduce(|acc, muled| add_block_matrices(muled, &acc))
                 .unwrap()
@@ -736,21 +769,3 @@ fn sub_block_matrices<T: MatrixElem>(lhs: Vec<Vec<T>>, rhs: &[Vec<T>]) -> Vec<Ve
         })
         .collect::<Vec<Vec<T>>>()
 }
-
-fn mul_block_matrices<T: MatrixElem>(lhs: Vec<Vec<T>>, rhs: Vec<Vec<T>>) -> Vec<Vec<T>> {
-    let nrow = lhs.len();
-    let ncol = rhs[0].len();
-    let n_inner = lhs[0].len();
-    parallel_iter!(0..nrow)
-        .map(|i| {
-            parallel_iter!(0..ncol)
-                .map(|j: usize| {
-                    (0..n_inner)
-                        .map(|k| lhs[i][k].clone() * &rhs[k][j])
-                        .reduce(|acc, prod| acc + prod)
-                        .unwrap()
-                })
-                .collect::<Vec<T>>()
-        })
-        .collect::<Vec<Vec<T>>>()
-}
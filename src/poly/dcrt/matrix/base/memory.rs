@@ -49,6 +49,41 @@ impl<T: MatrixElem> BaseMatrix<T> {
         });
     }
 
+    /// Like [`Self::replace_entries`], but calls `f` on tiles of at most `block_size()` rows and
+    /// columns in parallel and moves every tile into place, so the entries of the whole range
+    /// are never collected at once.
+    pub fn replace_entries_tiled<F>(&mut self, rows: Range<usize>, cols: Range<usize>, f: F)
+    where
+        F: Fn(Range<usize>, Range<usize>) -> Vec<Vec<T>> + Send + Sync,
+    {
+        if rows.is_empty() || cols.is_empty() {
+            return;
+        }
+        let block_size = block_size();
+        let col_tiles = cols
+            .clone()
+            .step_by(block_size)
+            .map(|start| start..(start + block_size).min(cols.end))
+            .collect_vec();
+        self.inner[rows.start..rows.end].par_chunks_mut(block_size).enumerate().for_each(
+            |(chunk_idx, row_chunk)| {
+                let row_start = rows.start + chunk_idx * block_size;
+                let row_tile = row_start..row_start + row_chunk.len();
+                let tiles = parallel_iter!(col_tiles.clone())
+                    .map(|col_tile| f(row_tile.clone(), col_tile))
+                    .collect::<Vec<_>>();
+                for (col_tile, tile) in col_tiles.iter().zip(tiles) {
+                    debug_assert_eq!(tile.len(), row_chunk.len());
+                    for (row_data, tile_row) in row_chunk.iter_mut().zip(tile) {
+                        for (entry, value) in row_data[col_tile.clone()].iter_mut().zip(tile_row) {
+                            *entry = value;
+                        }
+                    }
+                }
+            },
+        );
+    }
+
     pub fn replace_entries_diag<F>(&mut self, diags: Range<usize>, f: F)
     where
         F: Fn(Range<usize>) -> Vec<Vec<T>> + Send + Sync,
@@ -98,6 +133,17 @@ impl<T: MatrixElem> BaseMatrix<T> {
         (self.nrow, self.ncol)
     }
 
+    /// Frees the spare capacity of the rows, e.g. left behind by entries replaced in blocks.
+    pub fn shrink_to_fit(&mut self) {
+        self.inner.shrink_to_fit();
+        self.inner.iter_mut().for_each(|row| row.shrink_to_fit());
+    }
+
+    /// Moves the entries out of the matrix, row by row.
+    pub fn into_entries(self) -> Vec<Vec<T>> {
+        self.inner
+    }
+
     pub fn slice(
         &self,
         row_start: usize,
@@ -105,6 +151,20 @@ impl<T: MatrixElem> BaseMatrix<T> {
         col_start: usize,
         col_end: usize,
     ) -> Self {
+        debug_assert!(
+            row_start <= row_end && row_end <= self.nrow,
+            "row range {}..{} out of {} rows",
+            row_start,
+            row_end,
+            self.nrow
+        );
+        debug_assert!(
+            col_start <= col_end && col_end <= self.ncol,
+            "column range {}..{} out of {} columns",
+            col_start,
+            col_end,
+            self.ncol
+        );
         let nrow = row_end - row_start;
         let ncol = col_end - col_start;
 
@@ -331,6 +391,7 @@ impl<T: MatrixElem> Add<&BaseMatrix<T>> for &BaseMatrix<T> {
             rhs.nrow,
             rhs.ncol
         );
+        debug_assert_eq!(self.params, rhs.params, "matrices over different moduli");
 
         let mut new_matrix = BaseMatrix::new_empty(&self.params, self.nrow, self.ncol);
         let f = |row_offsets: Range<usize>, col_offsets: Range<usize>| -> Vec<Vec<T>> {
@@ -371,6 +432,7 @@ impl<T: MatrixElem> Sub<&BaseMatrix<T>> for &BaseMatrix<T> {
             rhs.nrow,
             rhs.ncol
         );
+        debug_assert_eq!(self.params, rhs.params, "matrices over different moduli");
 
         let mut new_matrix = BaseMatrix::new_empty(&self.params, self.nrow, self.ncol);
         let f = |row_offsets: Range<usize>, col_offsets: Range<usize>| -> Vec<Vec<T>> {
@@ -417,6 +479,7 @@ impl<T: MatrixElem> Mul<&BaseMatrix<T>> for &BaseMatrix<T> {
             self.ncol,
             rhs.nrow
         );
+        debug_assert_eq!(self.params, rhs.params, "matrices over different moduli");
 
         let mut new_matrix = BaseMatrix::new_empty(&self.params, self.nrow, rhs.ncol);
         let (_, ip_offsets) = block_offsets(0..0, 0..self.ncol);
@@ -429,7 +492,7 @@ impl<T: MatrixElem> Mul<&BaseMatrix<T>> for &BaseMatrix<T> {
                         .block_entries(row_offsets.clone(), *cur_block_ip_idx..*next_block_ip_idx);
                     let other_block_polys = rhs
                         .block_entries(*cur_block_ip_idx..*next_block_ip_idx, col_offsets.clone());
-                    mul_block_matrices(self_block_polys, other_block_polys)
+                    T::mul_block(self_block_polys, other_block_polys)
                 })
                 .reduce(|acc, muled| add_block_matrices(muled, &acc))
                 .unwrap()
@@ -569,21 +632,3 @@ fn sub_block_matrices<T: MatrixElem>(lhs: Vec<Vec<T>>, rhs: &[Vec<T>]) -> Vec<Ve
         })
         .collect::<Vec<Vec<T>>>()
 }
-
-fn mul_block_matrices<T: MatrixElem>(lhs: Vec<Vec<T>>, rhs: Vec<Vec<T>>) -> Vec<Vec<T>> {
-    let nrow = lhs.len();
-    let ncol = rhs[0].len();
-    let n_inner = lhs[0].len();
-    parallel_iter!(0..nrow)
-        .map(|i| {
-            parallel_iter!(0..ncol)
-                .map(|j: usize| {
-                    (0..n_inner)
-                        .map(|k| lhs[i][k].clone() * &rhs[k][j])
-                        .reduce(|acc, prod| acc + prod)
-                        .unwrap()
-                })
-                .collect::<Vec<T>>()
-        })
-        .collect::<Vec<Vec<T>>>()
-}
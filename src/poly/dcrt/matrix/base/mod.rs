@@ -1,3 +1,7 @@
+use crate::{parallel_iter, poly::MatrixElem, utils::parallelism_config};
+use rayon::prelude::*;
+use std::ops::Range;
+
 #[cfg(feature = "disk")]
 pub mod disk;
 #[cfg(not(feature = "disk"))]
@@ -7,3 +11,29 @@ pub mod memory;
 pub use disk::BaseMatrix;
 #[cfg(not(feature = "disk"))]
 pub use memory::BaseMatrix;
+
+impl<T: MatrixElem> BaseMatrix<T> {
+    /// Builds a matrix whose `(i, j)` entry is `f(i, j)`.
+    /// Entries are generated tile by tile in parallel and written in place, so no full
+    /// `Vec<Vec<T>>` of the matrix is materialized beforehand.
+    pub fn from_fn<F>(params: &T::Params, nrow: usize, ncol: usize, f: F) -> Self
+    where
+        F: Fn(usize, usize) -> T + Send + Sync,
+    {
+        let mut matrix = Self::new_empty(params, nrow, ncol);
+        let parallel_columns = parallelism_config().columns;
+        let block_fn = |row_offsets: Range<usize>, col_offsets: Range<usize>| -> Vec<Vec<T>> {
+            parallel_iter!(row_offsets)
+                .map(|i| {
+                    if parallel_columns {
+                        parallel_iter!(col_offsets.clone()).map(|j| f(i, j)).collect()
+                    } else {
+                        col_offsets.clone().map(|j| f(i, j)).collect()
+                    }
+                })
+                .collect()
+        };
+        matrix.replace_entries_tiled(0..nrow, 0..ncol, block_fn);
+        matrix
+    }
+}
@@ -1,15 +1,23 @@
 use crate::{
+    error::DiamondError,
     parallel_iter,
     poly::{
-        dcrt::{cpp_matrix::CppMatrix, DCRTPoly, DCRTPolyParams},
+        compare::fmt_matrix,
+        dcrt::{
+            cpp_matrix::CppMatrix,
+            ffi_guard::{self, FirstFailure},
+            DCRTPoly, DCRTPolyParams,
+        },
+        matrix::naive_mul_block,
+        ring_matrix::mul_fixed_block,
         MatrixElem, MatrixParams, Poly, PolyMatrix, PolyParams,
     },
-    utils::{block_size, debug_mem},
+    utils::{block_size, debug_mem, trim_heap},
 };
 use itertools::Itertools;
 use openfhe::ffi::{DCRTPolyGadgetVector, MatrixGen, SetMatrixElement};
 use rayon::prelude::*;
-use std::{ops::Range, path::Path, sync::Arc};
+use std::{fmt, ops::Range, path::Path, sync::Arc};
 use tokio::fs::write;
 
 use super::base::BaseMatrix;
@@ -41,10 +49,21 @@ impl MatrixElem for DCRTPoly {
     fn as_elem_to_bytes(&self) -> Vec<u8> {
         self.to_bytes()
     }
+
+    /// Small blocks are multiplied as [`crate::poly::ring_matrix::RingMatrix`] values.
+    fn mul_block(lhs: Vec<Vec<Self>>, rhs: Vec<Vec<Self>>) -> Vec<Vec<Self>> {
+        mul_fixed_block(&lhs, &rhs).unwrap_or_else(|| naive_mul_block(lhs, rhs))
+    }
 }
 
 pub type DCRTPolyMatrix = BaseMatrix<DCRTPoly>;
 
+impl fmt::Display for DCRTPolyMatrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_matrix(self, f)
+    }
+}
+
 impl PolyMatrix for DCRTPolyMatrix {
     type P = DCRTPoly;
 
@@ -60,6 +79,10 @@ impl PolyMatrix for DCRTPolyMatrix {
         matrix
     }
 
+    fn params(&self) -> &<Self::P as Poly>::Params {
+        &self.params
+    }
+
     fn entry(&self, i: usize, j: usize) -> Self::P {
         self.entry(i, j)
     }
@@ -112,40 +135,13 @@ impl PolyMatrix for DCRTPolyMatrix {
     }
 
     fn gadget_matrix(params: &<Self::P as Poly>::Params, size: usize) -> Self {
-        let gadget_vector = Self::gadget_vector(params);
+        let gadget_vector = ffi_guard::expect_ffi(Self::gadget_vector(params));
         debug_assert_eq!(gadget_vector.col_size(), params.modulus_digits());
         gadget_vector.concat_diag(&vec![&gadget_vector; size - 1])
     }
 
     fn decompose(&self) -> Self {
-        let base_bits = self.params.base_bits();
-        let log_base_q =
-            self.params.crt_bits().div_ceil(base_bits as usize) * self.params.crt_depth();
-        let new_nrow = self.nrow * log_base_q;
-        let mut new_matrix = Self::new_empty(&self.params, new_nrow, self.ncol);
-        let f = |row_offsets: Range<usize>, col_offsets: Range<usize>| -> Vec<Vec<DCRTPoly>> {
-            let nrow = row_offsets.len();
-            let new_nrow = row_offsets.len() * log_base_q;
-            let ncol = col_offsets.len();
-            let entries = self.block_entries(row_offsets, col_offsets);
-            let decomposed_entries: Vec<Vec<Vec<DCRTPoly>>> = parallel_iter!(0..nrow)
-                .map(|i| {
-                    parallel_iter!(0..ncol)
-                        .map(|j| self.dcrt_decompose_poly(&entries[i][j], base_bits))
-                        .collect()
-                })
-                .collect();
-            parallel_iter!(0..new_nrow)
-                .map(|idx| {
-                    let i = idx / log_base_q;
-                    let k = idx % log_base_q;
-
-                    parallel_iter!(0..ncol).map(|j| decomposed_entries[i][j][k].clone()).collect()
-                })
-                .collect()
-        };
-        new_matrix.replace_entries_with_expand(0..self.nrow, 0..self.ncol, log_base_q, 1, f);
-        new_matrix
+        ffi_guard::expect_ffi(self.try_decompose())
     }
 
     fn modulus_switch(
@@ -295,51 +291,118 @@ impl PolyMatrix for DCRTPolyMatrix {
 }
 
 impl DCRTPolyMatrix {
-    pub(crate) fn to_cpp_matrix_ptr(&self) -> CppMatrix {
+    /// Drops the matrix, freeing the C++ polynomials no other value shares, and returns the freed
+    /// memory to the operating system with [`trim_heap`]. Like [`DCRTPoly::release`], returns
+    /// whether every C++ polynomial was freed, i.e. whether no value outside the matrix shares one.
+    pub fn release(self) -> bool {
+        let freed = DCRTPoly::release_all(self.into_entries().into_iter().flatten());
+        trim_heap();
+        freed
+    }
+
+    /// [`PolyMatrix::decompose`], failing if OpenFHE does.
+    pub fn try_decompose(&self) -> Result<Self, DiamondError> {
+        let base_bits = self.params.base_bits();
+        let log_base_q = self.params.modulus_digits();
+        let new_nrow = self.nrow * log_base_q;
+        let mut new_matrix = Self::new_empty(&self.params, new_nrow, self.ncol);
+        let failures = FirstFailure::new();
+        let f = |row_offsets: Range<usize>, col_offsets: Range<usize>| -> Vec<Vec<DCRTPoly>> {
+            let nrow = row_offsets.len();
+            let new_nrow = row_offsets.len() * log_base_q;
+            let ncol = col_offsets.len();
+            let entries = self.block_entries(row_offsets, col_offsets);
+            let decomposed_entries: Vec<Vec<Vec<DCRTPoly>>> = parallel_iter!(0..nrow)
+                .map(|i| {
+                    parallel_iter!(0..ncol)
+                        .map(|j| {
+                            let decomposed = self.dcrt_decompose_poly(&entries[i][j], base_bits);
+                            failures.take(decomposed.map(Some)).unwrap_or_else(|| {
+                                vec![DCRTPoly::const_zero(&self.params); log_base_q]
+                            })
+                        })
+                        .collect()
+                })
+                .collect();
+            parallel_iter!(0..new_nrow)
+                .map(|idx| {
+                    let i = idx / log_base_q;
+                    let k = idx % log_base_q;
+
+                    parallel_iter!(0..ncol).map(|j| decomposed_entries[i][j][k].clone()).collect()
+                })
+                .collect()
+        };
+        new_matrix.replace_entries_with_expand(0..self.nrow, 0..self.ncol, log_base_q, 1, f);
+        failures.into_result()?;
+        Ok(new_matrix)
+    }
+
+    pub(crate) fn to_cpp_matrix_ptr(&self) -> Result<CppMatrix, DiamondError> {
         let nrow = self.nrow;
         let ncol = self.ncol;
-        let mut matrix_ptr = MatrixGen(
-            self.params.ring_dimension(),
-            self.params.crt_depth(),
-            self.params.crt_bits(),
-            nrow,
-            ncol,
-        );
+        let mut matrix_ptr = ffi_guard::guard_ptr("MatrixGen", || {
+            MatrixGen(
+                self.params.ring_dimension(),
+                self.params.crt_depth(),
+                self.params.crt_bits(),
+                nrow,
+                ncol,
+            )
+        })?;
         debug_mem(format!("matrix_ptr MatrixGen row={}, col={}", nrow, ncol));
         for i in 0..nrow {
             for j in 0..ncol {
-                SetMatrixElement(matrix_ptr.as_mut().unwrap(), i, j, self.entry(i, j).get_poly());
+                let entry = self.entry(i, j);
+                ffi_guard::guard("SetMatrixElement", || {
+                    SetMatrixElement(matrix_ptr.pin_mut(), i, j, entry.get_poly())
+                })?;
             }
         }
         debug_mem(format!("SetMatrixElement row={}, col={}", nrow, ncol));
-        CppMatrix::new(&self.params, matrix_ptr)
+        CppMatrix::new(&self.params, "MatrixGen", matrix_ptr)
     }
 
-    pub(crate) fn from_cpp_matrix_ptr(params: &DCRTPolyParams, cpp_matrix: &CppMatrix) -> Self {
+    pub(crate) fn from_cpp_matrix_ptr(
+        params: &DCRTPolyParams,
+        cpp_matrix: &CppMatrix,
+    ) -> Result<Self, DiamondError> {
         let nrow = cpp_matrix.nrow();
         let ncol = cpp_matrix.ncol();
         let matrix_inner = parallel_iter!(0..nrow)
-            .map(|i| parallel_iter!(0..ncol).map(|j| cpp_matrix.entry(i, j)).collect::<Vec<_>>())
-            .collect::<Vec<_>>();
+            .map(|i| {
+                parallel_iter!(0..ncol)
+                    .map(|j| cpp_matrix.entry(i, j))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
         debug_mem(format!("GetMatrixElement row={}, col={}", nrow, ncol));
-        DCRTPolyMatrix::from_poly_vec(params, matrix_inner)
+        Ok(DCRTPolyMatrix::from_poly_vec(params, matrix_inner))
     }
 
-    pub(crate) fn gadget_vector(params: &DCRTPolyParams) -> DCRTPolyMatrix {
+    pub(crate) fn gadget_vector(params: &DCRTPolyParams) -> Result<DCRTPolyMatrix, DiamondError> {
         let base = 1 << params.base_bits();
-        let g_vec_cpp = DCRTPolyGadgetVector(
-            params.ring_dimension(),
-            params.crt_depth(),
-            params.crt_bits(),
-            params.modulus_digits(),
-            base,
-        );
-        DCRTPolyMatrix::from_cpp_matrix_ptr(params, &CppMatrix::new(params, g_vec_cpp))
-    }
-
-    fn dcrt_decompose_poly(&self, poly: &DCRTPoly, base_bits: u32) -> Vec<DCRTPoly> {
-        let decomposed = poly.get_poly().Decompose(base_bits);
-        let cpp_decomposed = CppMatrix::new(&self.params, decomposed);
+        let call = "DCRTPolyGadgetVector";
+        let g_vec_cpp = ffi_guard::guard(call, || {
+            DCRTPolyGadgetVector(
+                params.ring_dimension(),
+                params.crt_depth(),
+                params.crt_bits(),
+                params.modulus_digits(),
+                base,
+            )
+        })?;
+        DCRTPolyMatrix::from_cpp_matrix_ptr(params, &CppMatrix::new(params, call, g_vec_cpp)?)
+    }
+
+    fn dcrt_decompose_poly(
+        &self,
+        poly: &DCRTPoly,
+        base_bits: u32,
+    ) -> Result<Vec<DCRTPoly>, DiamondError> {
+        let call = "Decompose";
+        let decomposed = ffi_guard::guard(call, || poly.get_poly().Decompose(base_bits))?;
+        let cpp_decomposed = CppMatrix::new(&self.params, call, decomposed)?;
         parallel_iter!(0..cpp_decomposed.ncol()).map(|idx| cpp_decomposed.entry(0, idx)).collect()
     }
 }
@@ -347,15 +410,32 @@ impl DCRTPolyMatrix {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::poly::{
-        dcrt::{DCRTPolyParams, DCRTPolyUniformSampler, FinRingElem},
-        sampler::{DistType, PolyUniformSampler},
+    use crate::{
+        assert_matrix_eq,
+        poly::{
+            dcrt::{DCRTPolyParams, DCRTPolyUniformSampler, FinRingElem},
+            sampler::{DistType, PolyUniformSampler},
+        },
+        utils::create_random_poly,
     };
     use num_bigint::BigUint;
     use rand::{rng, Rng};
     use serial_test::serial;
     use std::{fs, sync::Arc};
 
+    /// The matrix of constant polynomials with the given values.
+    fn const_matrix(params: &DCRTPolyParams, values: &[&[u32]]) -> DCRTPolyMatrix {
+        let rows = values
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&v| DCRTPoly::from_const(params, &FinRingElem::new(v, params.modulus())))
+                    .collect()
+            })
+            .collect();
+        DCRTPolyMatrix::from_poly_vec(params, rows)
+    }
+
     #[test]
     fn test_matrix_gadget_matrix() {
         let params = DCRTPolyParams::default();
@@ -365,6 +445,26 @@ mod tests {
         assert_eq!(gadget_matrix.size().1, size * params.modulus_bits());
     }
 
+    #[test]
+    fn test_matrix_from_fn() {
+        let params = DCRTPolyParams::default();
+        let size = 4;
+        let identity = DCRTPolyMatrix::from_fn(&params, size, size, |i, j| {
+            if i == j {
+                DCRTPoly::const_one(&params)
+            } else {
+                DCRTPoly::const_zero(&params)
+            }
+        });
+        assert_eq!(identity, DCRTPolyMatrix::identity(&params, size, None));
+
+        let polys = (0..2 * 3).map(|_| create_random_poly(&params)).collect::<Vec<_>>();
+        let matrix = DCRTPolyMatrix::from_fn(&params, 2, 3, |i, j| polys[i * 3 + j].clone());
+        assert_eq!(matrix.size(), (2, 3));
+        let rows = polys.chunks(3).map(|row| row.to_vec()).collect();
+        crate::assert_matrix_eq!(matrix, DCRTPolyMatrix::from_poly_vec(&params, rows));
+    }
+
     #[test]
     fn test_matrix_decompose() {
         let params = DCRTPolyParams::default();
@@ -455,6 +555,22 @@ mod tests {
         assert_eq!(matrix, expected_matrix);
     }
 
+    #[test]
+    #[cfg(not(feature = "disk"))]
+    fn test_matrix_release() {
+        let params = DCRTPolyParams::default();
+        let sampler = DCRTPolyUniformSampler::new();
+        let matrix = sampler.sample_uniform(&params, 2, 2, DistType::FinRingDist);
+
+        // The entries of a zero matrix share one C++ object, which is still freed with it
+        assert!(DCRTPolyMatrix::zero(&params, 2, 2).release());
+
+        // An entry cloned out of the matrix outlives it
+        let entry = matrix.entry(1, 1);
+        assert!(!matrix.release());
+        assert!(entry.release());
+    }
+
     #[test]
     fn test_matrix_basic_operations() {
         let params = DCRTPolyParams::default();
@@ -473,25 +589,21 @@ mod tests {
         ];
 
         let matrix1 = DCRTPolyMatrix::from_poly_vec(&params, matrix_vec);
-        assert_eq!(matrix1.entry(0, 0).coeffs()[0], value);
+        assert_matrix_eq!(matrix1, const_matrix(&params, &[&[5, 0], &[0, 5]]));
         let matrix2 = matrix1.clone();
         assert_eq!(matrix1, matrix2);
 
         // Test addition
         let sum = matrix1.clone() + &matrix2;
-        let value_10 = FinRingElem::new(10u32, params.modulus());
-        assert_eq!(sum.entry(0, 0).coeffs()[0], value_10);
+        assert_matrix_eq!(sum, const_matrix(&params, &[&[10, 0], &[0, 10]]));
 
         // Test subtraction
         let diff = matrix1.clone() - &matrix2;
-        assert_eq!(diff, zero);
+        assert_matrix_eq!(diff, zero);
 
         // Test multiplication
-        let prod = matrix1 * &identity;
-        assert_eq!(prod.size(), (2, 2));
-        // Check that the product has the same values as the original matrix
-        assert_eq!(prod.entry(0, 0).coeffs()[0], value);
-        assert_eq!(prod.entry(1, 1).coeffs()[0], value);
+        let prod = matrix1.clone() * &identity;
+        assert_matrix_eq!(prod, matrix1);
     }
 
     #[test]
@@ -517,24 +629,17 @@ mod tests {
 
         // Test column concatenation
         let col_concat = matrix1.concat_columns(&[&matrix2]);
-        assert_eq!(col_concat.size().0, 2);
-        assert_eq!(col_concat.size().1, 4);
-        assert_eq!(col_concat.entry(0, 0).coeffs()[0], value);
-        assert_eq!(col_concat.entry(1, 3).coeffs()[0], value);
+        assert_matrix_eq!(col_concat, const_matrix(&params, &[&[5, 0, 0, 0], &[0, 0, 0, 5]]));
 
         // Test row concatenation
         let row_concat = matrix1.concat_rows(&[&matrix2]);
-        assert_eq!(row_concat.size().0, 4);
-        assert_eq!(row_concat.size().1, 2);
-        assert_eq!(row_concat.entry(0, 0).coeffs()[0], value);
-        assert_eq!(row_concat.entry(3, 1).coeffs()[0], value);
+        let expected = const_matrix(&params, &[&[5, 0], &[0, 0], &[0, 0], &[0, 5]]);
+        assert_matrix_eq!(row_concat, expected);
 
         // Test diagonal concatenation
         let diag_concat = matrix1.concat_diag(&[&matrix2]);
-        assert_eq!(diag_concat.size().0, 4);
-        assert_eq!(diag_concat.size().1, 4);
-        assert_eq!(diag_concat.entry(0, 0).coeffs()[0], value);
-        assert_eq!(diag_concat.entry(3, 3).coeffs()[0], value);
+        let expected = const_matrix(&params, &[&[5, 0, 0, 0], &[0; 4], &[0; 4], &[0, 0, 0, 5]]);
+        assert_matrix_eq!(diag_concat, expected);
     }
 
     #[test]
@@ -559,12 +664,10 @@ mod tests {
         let matrix2 = DCRTPolyMatrix::from_poly_vec(&params, matrix2_vec);
 
         let tensor = matrix1.tensor(&matrix2);
-        assert_eq!(tensor.size().0, 4);
-        assert_eq!(tensor.size().1, 4);
 
-        // Check that the (0,0) element is the product of the (0,0) elements
-        let value_25 = FinRingElem::new(25u32, params.modulus());
-        assert_eq!(tensor.entry(0, 0).coeffs()[0], value_25);
+        // Only the (0,0) element is nonzero, the product of the (0,0) elements
+        let expected = const_matrix(&params, &[&[25, 0, 0, 0], &[0; 4], &[0; 4], &[0; 4]]);
+        assert_matrix_eq!(tensor, expected);
     }
 
     #[test]
@@ -605,7 +708,7 @@ mod tests {
         ];
 
         let expected = DCRTPolyMatrix::from_poly_vec(&params, expected_vec);
-        assert_eq!(switched, expected);
+        assert_matrix_eq!(switched, expected);
     }
 
     #[test]
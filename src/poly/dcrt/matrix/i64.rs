@@ -1,4 +1,6 @@
 use crate::poly::{MatrixElem, MatrixParams};
+use itertools::Itertools;
+use std::fmt;
 
 use super::base::BaseMatrix;
 
@@ -33,3 +35,14 @@ impl MatrixElem for i64 {
 }
 
 pub type I64Matrix = BaseMatrix<i64>;
+
+impl fmt::Display for I64Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (nrow, ncol) = self.size();
+        writeln!(f, "{}x{} matrix", nrow, ncol)?;
+        for row in 0..nrow {
+            writeln!(f, "{}", self.get_row(row).iter().format(" "))?;
+        }
+        Ok(())
+    }
+}
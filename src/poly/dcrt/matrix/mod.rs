@@ -1,6 +1,10 @@
 pub mod base;
+#[cfg(feature = "openfhe")]
 pub mod dcrt_poly;
 pub mod i64;
+#[cfg(feature = "openfhe")]
+pub mod ring_ops;
 
+#[cfg(feature = "openfhe")]
 pub use dcrt_poly::DCRTPolyMatrix;
 pub use i64::{I64Matrix, I64MatrixParams};
@@ -0,0 +1,137 @@
+use super::{DCRTPolyMatrix, I64Matrix, I64MatrixParams};
+use crate::poly::{dcrt::DCRTPolyParams, PolyMatrix, RingMatrixOps};
+
+/// Number of binary digits of the integer gadget vector `(1, 2, ..., 2^62)`.
+pub const I64_GADGET_DIGITS: usize = 63;
+
+impl RingMatrixOps for DCRTPolyMatrix {
+    type Params = DCRTPolyParams;
+
+    fn size(&self) -> (usize, usize) {
+        self.size()
+    }
+
+    fn zero(params: &DCRTPolyParams, nrow: usize, ncol: usize) -> Self {
+        Self::zero(params, nrow, ncol)
+    }
+
+    fn identity(params: &DCRTPolyParams, size: usize) -> Self {
+        Self::identity(params, size, None)
+    }
+
+    fn add(&self, rhs: &Self) -> Self {
+        self + rhs
+    }
+
+    fn neg(&self) -> Self {
+        -self.clone()
+    }
+
+    fn mul(&self, rhs: &Self) -> Self {
+        self * rhs
+    }
+
+    fn gadget_matrix(params: &DCRTPolyParams, size: usize) -> Self {
+        <Self as PolyMatrix>::gadget_matrix(params, size)
+    }
+
+    fn gadget_decompose(&self) -> Self {
+        self.decompose()
+    }
+}
+
+impl RingMatrixOps for I64Matrix {
+    type Params = I64MatrixParams;
+
+    fn size(&self) -> (usize, usize) {
+        self.size()
+    }
+
+    fn zero(params: &I64MatrixParams, nrow: usize, ncol: usize) -> Self {
+        Self::zero(params, nrow, ncol)
+    }
+
+    fn identity(params: &I64MatrixParams, size: usize) -> Self {
+        Self::identity(params, size, None)
+    }
+
+    fn add(&self, rhs: &Self) -> Self {
+        self + rhs
+    }
+
+    fn neg(&self) -> Self {
+        -self.clone()
+    }
+
+    fn mul(&self, rhs: &Self) -> Self {
+        self * rhs
+    }
+
+    fn gadget_matrix(params: &I64MatrixParams, size: usize) -> Self {
+        Self::from_fn(params, size, size * I64_GADGET_DIGITS, |i, j| {
+            if j / I64_GADGET_DIGITS == i {
+                1 << (j % I64_GADGET_DIGITS)
+            } else {
+                0
+            }
+        })
+    }
+
+    /// Decomposes every entry into the signed binary digits of its absolute value, so that
+    /// negative entries get digits in `{-1, 0}`. Panics on `i64::MIN`, which has no 63-bit
+    /// absolute value.
+    fn gadget_decompose(&self) -> Self {
+        let (nrow, ncol) = self.size();
+        Self::from_fn(&self.params, nrow * I64_GADGET_DIGITS, ncol, |i, j| {
+            let value = self.entry(i / I64_GADGET_DIGITS, j);
+            assert_ne!(value, i64::MIN, "i64::MIN cannot be decomposed");
+            let bit = (value.unsigned_abs() >> (i % I64_GADGET_DIGITS)) & 1;
+            value.signum() * bit as i64
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        poly::{
+            dcrt::DCRTPolyUniformSampler,
+            sampler::{DistType, PolyUniformSampler},
+        },
+        ring_matrix_ops_tests,
+    };
+    use rand::Rng;
+
+    fn random_dcrt_matrix(params: &DCRTPolyParams, nrow: usize, ncol: usize) -> DCRTPolyMatrix {
+        DCRTPolyUniformSampler::new().sample_uniform(params, nrow, ncol, DistType::FinRingDist)
+    }
+
+    fn random_i64_matrix(params: &I64MatrixParams, nrow: usize, ncol: usize) -> I64Matrix {
+        // Small entries so that products do not overflow
+        I64Matrix::from_fn(params, nrow, ncol, |_, _| rand::rng().random_range(-1000..1000))
+    }
+
+    ring_matrix_ops_tests!(
+        dcrt_poly_matrix,
+        DCRTPolyMatrix,
+        DCRTPolyParams::default(),
+        random_dcrt_matrix
+    );
+    ring_matrix_ops_tests!(i64_matrix, I64Matrix, I64MatrixParams, random_i64_matrix);
+
+    #[test]
+    fn test_i64_gadget_decompose_negative() {
+        let params = I64MatrixParams;
+        let a = I64Matrix::from_fn(&params, 1, 2, |_, j| if j == 0 { -5 } else { i64::MAX });
+        let decomposed = a.gadget_decompose();
+
+        // -5 = -(1 + 4) and i64::MAX has all 63 bits set
+        assert_eq!(decomposed.entry(0, 0), -1);
+        assert_eq!(decomposed.entry(1, 0), 0);
+        assert_eq!(decomposed.entry(2, 0), -1);
+        assert!((0..I64_GADGET_DIGITS).all(|k| decomposed.entry(k, 1) == 1));
+        let gadget = <I64Matrix as RingMatrixOps>::gadget_matrix(&params, 1);
+        assert_eq!(gadget.mul(&decomposed), a);
+    }
+}
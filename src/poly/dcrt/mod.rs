@@ -1,12 +1,34 @@
+//! The DCRT backend over OpenFHE. Without the `openfhe` feature only the pieces that do not
+//! call into OpenFHE are compiled: [`FinRingElem`], the compact byte format and the generic
+//! [`matrix::base::BaseMatrix`] storage, which the native backend builds on.
+#[cfg(feature = "openfhe")]
+pub mod chain;
+pub(crate) mod compact;
+#[cfg(feature = "openfhe")]
+pub mod context;
+#[cfg(feature = "openfhe")]
 pub mod cpp_matrix;
 pub mod element;
+#[cfg(feature = "openfhe")]
+pub mod ffi_guard;
+#[cfg(feature = "openfhe")]
+pub mod interop;
 pub mod matrix;
+#[cfg(feature = "openfhe")]
 pub mod params;
+#[cfg(feature = "openfhe")]
 pub mod poly;
+#[cfg(feature = "openfhe")]
 pub mod sampler;
 
 pub use element::FinRingElem;
+#[cfg(feature = "openfhe")]
 pub use matrix::DCRTPolyMatrix;
-pub use params::DCRTPolyParams;
+#[cfg(feature = "openfhe")]
+pub use params::{DCRTPolyParams, ParamsError};
+#[cfg(feature = "openfhe")]
 pub use poly::DCRTPoly;
-pub use sampler::{DCRTPolyHashSampler, DCRTPolyTrapdoorSampler, DCRTPolyUniformSampler};
+#[cfg(feature = "openfhe")]
+pub use sampler::{
+    DCRTPolyHashSampler, DCRTPolyPrfSampler, DCRTPolyTrapdoorSampler, DCRTPolyUniformSampler,
+};
@@ -1,10 +1,46 @@
 use num_bigint::BigUint;
 use num_traits::Num;
+use once_cell::sync::Lazy;
 use openfhe::ffi;
 use serde::{Deserialize, Serialize};
-use std::{fmt::Debug, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
 
-use crate::poly::PolyParams;
+use super::ffi_guard;
+use crate::{
+    error::DiamondError,
+    poly::{dims::gadget_len, PolyParams},
+};
+
+/// Moduli generated by OpenFHE keyed by `(ring_dimension, crt_depth, crt_bits)`, since
+/// generating the CRT primes is slow.
+static MODULUS_CACHE: Lazy<Mutex<HashMap<(u32, usize, usize), Arc<BigUint>>>> =
+    Lazy::new(Default::default);
+
+/// Shared parameters keyed by `(ring_dimension, crt_depth, crt_bits, base_bits)`.
+static PARAMS_CACHE: Lazy<Mutex<HashMap<(u32, usize, usize, u32), Arc<DCRTPolyParams>>>> =
+    Lazy::new(Default::default);
+
+fn cached_modulus(
+    ring_dimension: u32,
+    crt_depth: usize,
+    crt_bits: usize,
+) -> Result<Arc<BigUint>, DiamondError> {
+    let key = (ring_dimension, crt_depth, crt_bits);
+    let mut cache = MODULUS_CACHE.lock().unwrap();
+    if let Some(modulus) = cache.get(&key) {
+        return Ok(modulus.clone());
+    }
+    let modulus = ffi_guard::guard("GenModulus", || {
+        ffi::GenModulus(ring_dimension, crt_depth, crt_bits)
+    })?;
+    let modulus = BigUint::from_str_radix(&modulus, 10)
+        .map_err(|err| ffi_guard::failure("GenModulus", format!("{}: {:?}", err, modulus)))?;
+    Ok(cache.entry(key).or_insert(Arc::new(modulus)).clone())
+}
 
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DCRTPolyParams {
@@ -53,7 +89,7 @@ impl PolyParams for DCRTPolyParams {
     }
 
     fn modulus_digits(&self) -> usize {
-        self.crt_bits.div_ceil(self.base_bits as usize) * self.crt_depth
+        gadget_len(self.crt_depth, self.crt_bits, self.base_bits)
     }
 }
 
@@ -64,18 +100,127 @@ impl Default for DCRTPolyParams {
     }
 }
 
+/// Error returned when parameters are inconsistent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamsError {
+    /// The ring dimension is not a power of 2.
+    RingDimension(u32),
+    /// The gadget base `2^base_bits` is zero bits or wider than one CRT tower.
+    BaseBits { base_bits: u32, crt_bits: usize },
+    /// The modulus is not `1 mod 2n`, so the ring does not support the NTT.
+    NotNttFriendly { ring_dimension: u32 },
+    /// The modulus has fewer bits than `crt_depth` towers of `crt_bits` bits.
+    ModulusTooSmall { modulus_bits: usize, expected_bits: usize },
+    /// The modulus is NTT-friendly but is not the product of the towers OpenFHE generates for
+    /// the given ring dimension, depth and tower size, so it cannot back the polynomials.
+    UnsupportedModulus,
+    /// OpenFHE failed to generate the modulus.
+    Ffi(DiamondError),
+}
+
+impl std::fmt::Display for ParamsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RingDimension(ring_dimension) => {
+                write!(f, "ring_dimension must be a power of 2, got {}", ring_dimension)
+            }
+            Self::BaseBits { base_bits, crt_bits } => {
+                write!(f, "base_bits must be in 1..={}, got {}", crt_bits, base_bits)
+            }
+            Self::NotNttFriendly { ring_dimension } => {
+                write!(f, "modulus must be 1 mod {} to support the NTT", 2 * *ring_dimension as u64)
+            }
+            Self::ModulusTooSmall { modulus_bits, expected_bits } => {
+                write!(f, "modulus has {} bits, expected {}", modulus_bits, expected_bits)
+            }
+            Self::UnsupportedModulus => {
+                write!(f, "modulus is not the product of the OpenFHE-generated towers")
+            }
+            Self::Ffi(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ParamsError {}
+
 impl DCRTPolyParams {
+    /// Creates parameters, panicking if they are inconsistent. See [`Self::try_new`].
     pub fn new(ring_dimension: u32, crt_depth: usize, crt_bits: usize, base_bits: u32) -> Self {
-        // assert that ring_dimension is a power of 2
-        assert!(ring_dimension.is_power_of_two(), "ring_dimension must be a power of 2");
-        let modulus = ffi::GenModulus(ring_dimension, crt_depth, crt_bits);
-        Self {
-            ring_dimension,
-            crt_depth,
-            crt_bits,
-            modulus: Arc::new(BigUint::from_str_radix(&modulus, 10).expect("invalid string")),
-            base_bits,
+        Self::try_new(ring_dimension, crt_depth, crt_bits, base_bits)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates parameters after checking that the ring dimension is a power of 2 and that the
+    /// gadget base `2^base_bits` fits in one CRT tower. Every tower has at most `crt_bits` bits,
+    /// so the gadget length recomputed from the base always covers the modulus.
+    pub fn try_new(
+        ring_dimension: u32,
+        crt_depth: usize,
+        crt_bits: usize,
+        base_bits: u32,
+    ) -> Result<Self, ParamsError> {
+        if !ring_dimension.is_power_of_two() {
+            return Err(ParamsError::RingDimension(ring_dimension));
+        }
+        if base_bits == 0 || base_bits as usize > crt_bits {
+            return Err(ParamsError::BaseBits { base_bits, crt_bits });
+        }
+        let modulus =
+            cached_modulus(ring_dimension, crt_depth, crt_bits).map_err(ParamsError::Ffi)?;
+        let params = Self { ring_dimension, crt_depth, crt_bits, modulus, base_bits };
+        debug_assert!(
+            BigUint::from(1u8) << (base_bits as usize * params.modulus_digits()) >= *params.modulus
+        );
+        Ok(params)
+    }
+
+    /// Creates parameters for a caller-provided modulus `q`, e.g. from a fixed external
+    /// parameter set, instead of taking whatever modulus is generated.
+    ///
+    /// `q` must be NTT-friendly (`q = 1 mod 2n`), have at least `crt_depth * crt_bits` bits and,
+    /// since OpenFHE regenerates the CRT towers from the ring dimension, depth and tower size,
+    /// equal the product of those towers.
+    pub fn try_new_with_modulus(
+        modulus: BigUint,
+        ring_dimension: u32,
+        crt_depth: usize,
+        crt_bits: usize,
+        base_bits: u32,
+    ) -> Result<Self, ParamsError> {
+        if !ring_dimension.is_power_of_two() {
+            return Err(ParamsError::RingDimension(ring_dimension));
+        }
+        if &modulus % (2 * ring_dimension as u64) != BigUint::from(1u8) {
+            return Err(ParamsError::NotNttFriendly { ring_dimension });
         }
+        let expected_bits = crt_depth * crt_bits;
+        if (modulus.bits() as usize) < expected_bits {
+            return Err(ParamsError::ModulusTooSmall {
+                modulus_bits: modulus.bits() as usize,
+                expected_bits,
+            });
+        }
+        let params = Self::try_new(ring_dimension, crt_depth, crt_bits, base_bits)?;
+        if *params.modulus != modulus {
+            return Err(ParamsError::UnsupportedModulus);
+        }
+        Ok(params)
+    }
+
+    /// Returns process-wide shared parameters, creating them on first use. Concurrent callers
+    /// with the same arguments get the same [`Arc`].
+    pub fn cached(
+        ring_dimension: u32,
+        crt_depth: usize,
+        crt_bits: usize,
+        base_bits: u32,
+    ) -> Result<Arc<Self>, ParamsError> {
+        let key = (ring_dimension, crt_depth, crt_bits, base_bits);
+        if let Some(params) = PARAMS_CACHE.lock().unwrap().get(&key) {
+            return Ok(params.clone());
+        }
+        let params = Arc::new(Self::try_new(ring_dimension, crt_depth, crt_bits, base_bits)?);
+        Ok(PARAMS_CACHE.lock().unwrap().entry(key).or_insert(params).clone())
     }
 
     pub fn crt_depth(&self) -> usize {
@@ -178,6 +323,81 @@ mod tests {
         assert_eq!(p.base_bits(), base_bits);
     }
 
+    #[test]
+    fn test_params_try_new_base_validation() {
+        // A valid base recomputes the gadget length from the base
+        let p = DCRTPolyParams::try_new(16, 4, 51, 17).unwrap();
+        assert_eq!(p.modulus_digits(), 12);
+        let gadget_bound = BigUint::from(1u8) << (17 * p.modulus_digits());
+        assert!(gadget_bound >= *p.modulus());
+
+        // Zero-bit and too wide bases are rejected
+        assert_eq!(
+            DCRTPolyParams::try_new(16, 4, 51, 0),
+            Err(ParamsError::BaseBits { base_bits: 0, crt_bits: 51 })
+        );
+        assert_eq!(
+            DCRTPolyParams::try_new(16, 4, 51, 52),
+            Err(ParamsError::BaseBits { base_bits: 52, crt_bits: 51 })
+        );
+
+        // The ring dimension is checked before the modulus is generated
+        assert_eq!(DCRTPolyParams::try_new(20, 4, 51, 1), Err(ParamsError::RingDimension(20)));
+    }
+
+    #[test]
+    fn test_params_try_new_with_modulus() {
+        let p = DCRTPolyParams::new(16, 4, 51, 1);
+        let q = p.modulus().as_ref().clone();
+
+        // The generated modulus is accepted
+        let explicit = DCRTPolyParams::try_new_with_modulus(q.clone(), 16, 4, 51, 1).unwrap();
+        assert_eq!(explicit, p);
+
+        // q + 1 is not 1 mod 2n
+        assert_eq!(
+            DCRTPolyParams::try_new_with_modulus(&q + 1u8, 16, 4, 51, 1),
+            Err(ParamsError::NotNttFriendly { ring_dimension: 16 })
+        );
+
+        // An NTT-friendly modulus that is too small for the towers
+        assert_eq!(
+            DCRTPolyParams::try_new_with_modulus(BigUint::from(97u32), 16, 4, 51, 1),
+            Err(ParamsError::ModulusTooSmall { modulus_bits: 7, expected_bits: 204 })
+        );
+
+        // An NTT-friendly modulus of the right size that OpenFHE would not generate
+        let other = &q + 32u8;
+        assert_eq!(
+            DCRTPolyParams::try_new_with_modulus(other, 16, 4, 51, 1),
+            Err(ParamsError::UnsupportedModulus)
+        );
+    }
+
+    #[test]
+    fn test_params_cached() {
+        // Concurrent lookups of the same parameters share one instance
+        let handles = (0..4)
+            .map(|_| std::thread::spawn(|| DCRTPolyParams::cached(8, 3, 51, 17).unwrap()))
+            .collect::<Vec<_>>();
+        let shared = handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<_>>();
+        for params in shared.iter() {
+            assert!(Arc::ptr_eq(params, &shared[0]));
+        }
+        assert_eq!(*shared[0], DCRTPolyParams::new(8, 3, 51, 17));
+
+        // A different base shares the modulus but not the parameters
+        let other = DCRTPolyParams::cached(8, 3, 51, 1).unwrap();
+        assert!(!Arc::ptr_eq(&other, &shared[0]));
+        assert!(Arc::ptr_eq(&other.modulus(), &shared[0].modulus()));
+
+        // Inconsistent parameters are rejected and not cached
+        assert_eq!(
+            DCRTPolyParams::cached(8, 3, 51, 0),
+            Err(ParamsError::BaseBits { base_bits: 0, crt_bits: 51 })
+        );
+    }
+
     #[test]
     #[should_panic(expected = "ring_dimension must be a power of 2")]
     fn test_params_initiation_non_power_of_two() {
@@ -1,9 +1,18 @@
 use rayon::prelude::*;
 
-use super::{element::FinRingElem, params::DCRTPolyParams};
+use super::{
+    compact::{coeffs_from_compact_bytes, compact_bytes},
+    element::FinRingElem,
+    ffi_guard,
+    params::DCRTPolyParams,
+};
+#[cfg(feature = "checked")]
+use crate::poly::polynomial::assert_operands;
 use crate::{
+    counters,
+    error::DiamondError,
     impl_binop_with_refs, parallel_iter,
-    poly::{element::PolyElem, Poly, PolyParams},
+    poly::{element::PolyElem, polynomial::check_operands, Poly, PolyParams},
 };
 use num_bigint::BigUint;
 use openfhe::{
@@ -13,15 +22,47 @@ use openfhe::{
 };
 
 use std::{
+    collections::HashSet,
     fmt::Debug,
-    ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+    ops::{Add, AddAssign, Deref, Mul, MulAssign, Neg, Sub, SubAssign},
     str::FromStr,
     sync::Arc,
 };
 
+/// Owns a polynomial allocated by OpenFHE, counted by [`counters::MemoryWatermark`] until the
+/// C++ object is freed.
+struct FfiPoly(UniquePtr<DCRTPolyCxx>);
+
+impl FfiPoly {
+    fn new(ptr_poly: UniquePtr<DCRTPolyCxx>) -> Self {
+        counters::record_ffi_alloc();
+        Self(ptr_poly)
+    }
+}
+
+impl Deref for FfiPoly {
+    type Target = UniquePtr<DCRTPolyCxx>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Drop for FfiPoly {
+    fn drop(&mut self) {
+        counters::record_ffi_free();
+    }
+}
+
+impl Debug for FfiPoly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct DCRTPoly {
-    ptr_poly: Arc<UniquePtr<DCRTPolyCxx>>,
+    ptr_poly: Arc<FfiPoly>,
 }
 
 // SAFETY: DCRTPoly is plain old data and is shared across threads in C++ OpenFHE as well.
@@ -29,14 +70,42 @@ unsafe impl Send for DCRTPoly {}
 unsafe impl Sync for DCRTPoly {}
 
 impl DCRTPoly {
-    pub fn new(ptr_poly: UniquePtr<DCRTPolyCxx>) -> Self {
-        Self { ptr_poly: ptr_poly.into() }
+    /// Wraps a polynomial returned by the OpenFHE function `call`, failing if the pointer is
+    /// null.
+    pub fn from_ffi(
+        call: &'static str,
+        ptr_poly: UniquePtr<DCRTPolyCxx>,
+    ) -> Result<Self, DiamondError> {
+        let ptr_poly = ffi_guard::check_ptr(call, ptr_poly)?;
+        Ok(Self { ptr_poly: Arc::new(FfiPoly::new(ptr_poly)) })
     }
 
     pub fn get_poly(&self) -> &UniquePtr<DCRTPolyCxx> {
         &self.ptr_poly
     }
 
+    /// Drops the polynomial now rather than at the end of its scope and returns whether the C++
+    /// object was freed, i.e. whether no clone shares it.
+    pub fn release(self) -> bool {
+        Arc::into_inner(self.ptr_poly).is_some()
+    }
+
+    /// Releases `polys` and returns whether every C++ object among them was freed, i.e. whether
+    /// no value outside `polys` shares one of them.
+    pub fn release_all(polys: impl IntoIterator<Item = Self>) -> bool {
+        let mut shared = HashSet::new();
+        for poly in polys {
+            let ptr = Arc::as_ptr(&poly.ptr_poly) as usize;
+            // The last release of a shared object frees it
+            if poly.release() {
+                shared.remove(&ptr);
+            } else {
+                shared.insert(ptr);
+            }
+        }
+        shared.is_empty()
+    }
+
     pub fn modulus_switch(
         &self,
         params: &DCRTPolyParams,
@@ -51,22 +120,75 @@ impl DCRTPoly {
         DCRTPoly::from_coeffs(params, &new_coeffs)
     }
 
-    fn poly_gen_from_vec(params: &DCRTPolyParams, values: Vec<String>) -> Self {
-        DCRTPoly::new(ffi::DCRTPolyGenFromVec(
-            params.ring_dimension(),
-            params.crt_depth(),
-            params.crt_bits(),
-            &values,
-        ))
+    /// [`Poly::from_coeffs`], failing if OpenFHE does.
+    pub fn try_from_coeffs(
+        params: &DCRTPolyParams,
+        coeffs: &[FinRingElem],
+    ) -> Result<Self, DiamondError> {
+        let mut coeffs_cxx = Vec::with_capacity(coeffs.len());
+        for coeff in coeffs {
+            debug_assert_eq!(coeff.modulus(), params.modulus().as_ref());
+            coeffs_cxx.push(coeff.value().to_string());
+        }
+        Self::poly_gen_from_vec(params, coeffs_cxx)
     }
 
-    fn poly_gen_from_const(params: &DCRTPolyParams, value: String) -> Self {
-        DCRTPoly::new(ffi::DCRTPolyGenFromConst(
-            params.ring_dimension(),
-            params.crt_depth(),
-            params.crt_bits(),
-            &value,
-        ))
+    /// [`Poly::from_const`], failing if OpenFHE does.
+    pub fn try_from_const(
+        params: &DCRTPolyParams,
+        constant: &FinRingElem,
+    ) -> Result<Self, DiamondError> {
+        Self::poly_gen_from_const(params, constant.value().to_string())
+    }
+
+    /// `self + rhs`, failing if OpenFHE does.
+    pub fn try_add(&self, rhs: &Self) -> Result<Self, DiamondError> {
+        let call = "DCRTPolyAdd";
+        let ptr_poly = ffi_guard::guard(call, || ffi::DCRTPolyAdd(&rhs.ptr_poly, &self.ptr_poly))?;
+        Self::from_ffi(call, ptr_poly)
+    }
+
+    /// `self * rhs`, failing if OpenFHE does.
+    pub fn try_mul(&self, rhs: &Self) -> Result<Self, DiamondError> {
+        counters::record_poly_mul();
+        let call = "DCRTPolyMul";
+        let ptr_poly = ffi_guard::guard(call, || ffi::DCRTPolyMul(&rhs.ptr_poly, &self.ptr_poly))?;
+        Self::from_ffi(call, ptr_poly)
+    }
+
+    /// `-self`, failing if OpenFHE does.
+    pub fn try_neg(&self) -> Result<Self, DiamondError> {
+        Self::from_ffi("Negate", ffi_guard::guard("Negate", || self.ptr_poly.Negate())?)
+    }
+
+    fn poly_gen_from_vec(
+        params: &DCRTPolyParams,
+        values: Vec<String>,
+    ) -> Result<Self, DiamondError> {
+        counters::record_ntt();
+        let call = "DCRTPolyGenFromVec";
+        let ptr_poly = ffi_guard::guard(call, || {
+            ffi::DCRTPolyGenFromVec(
+                params.ring_dimension(),
+                params.crt_depth(),
+                params.crt_bits(),
+                &values,
+            )
+        })?;
+        Self::from_ffi(call, ptr_poly)
+    }
+
+    fn poly_gen_from_const(params: &DCRTPolyParams, value: String) -> Result<Self, DiamondError> {
+        let call = "DCRTPolyGenFromConst";
+        let ptr_poly = ffi_guard::guard(call, || {
+            ffi::DCRTPolyGenFromConst(
+                params.ring_dimension(),
+                params.crt_depth(),
+                params.crt_bits(),
+                &value,
+            )
+        })?;
+        Self::from_ffi(call, ptr_poly)
     }
 }
 
@@ -75,6 +197,7 @@ impl Poly for DCRTPoly {
     type Params = DCRTPolyParams;
 
     fn coeffs(&self) -> Vec<Self::Elem> {
+        counters::record_ntt();
         let poly_encoding = self.ptr_poly.GetCoefficientsBytes();
         let parsed_values = parse_coefficients_bytes(&poly_encoding);
         let coeffs = parsed_values.coefficients;
@@ -83,88 +206,60 @@ impl Poly for DCRTPoly {
     }
 
     fn from_coeffs(params: &Self::Params, coeffs: &[Self::Elem]) -> Self {
-        let mut coeffs_cxx = Vec::with_capacity(coeffs.len());
-        for coeff in coeffs {
-            debug_assert_eq!(coeff.modulus(), params.modulus().as_ref());
-            coeffs_cxx.push(coeff.value().to_string());
-        }
-        Self::poly_gen_from_vec(params, coeffs_cxx)
+        ffi_guard::expect_ffi(Self::try_from_coeffs(params, coeffs))
     }
 
     fn from_const(params: &Self::Params, constant: &Self::Elem) -> Self {
-        Self::poly_gen_from_const(params, constant.value().to_string())
+        ffi_guard::expect_ffi(Self::try_from_const(params, constant))
     }
 
+    /// Inverse of [`Poly::decompose_base`]: sums the `h`-th polynomial scaled by `base^h` for the
+    /// gadget base `2^base_bits`.
     fn from_decomposed(params: &DCRTPolyParams, decomposed: &[Self]) -> Self {
+        assert!(
+            decomposed.len() <= params.modulus_digits(),
+            "expected at most {} digits, got {}",
+            params.modulus_digits(),
+            decomposed.len()
+        );
+        let base_bits = params.base_bits() as usize;
         let mut reconstructed = Self::const_zero(params);
-        for (i, bit_poly) in decomposed.iter().enumerate() {
-            let power_of_two = BigUint::from(2u32).pow(i as u32);
-            let const_poly_power_of_two =
-                Self::from_const(params, &FinRingElem::new(power_of_two, params.modulus()));
-            reconstructed += bit_poly * &const_poly_power_of_two;
+        for (i, digit_poly) in decomposed.iter().enumerate() {
+            let power_of_base = BigUint::from(1u32) << (i * base_bits);
+            let const_poly_power_of_base =
+                Self::from_const(params, &FinRingElem::new(power_of_base, params.modulus()));
+            reconstructed += digit_poly * &const_poly_power_of_base;
         }
         reconstructed
     }
 
     /// Create a polynomial from a compact byte representation based on `to_compact_bytes` encoding
     fn from_compact_bytes(params: &Self::Params, bytes: &[u8]) -> Self {
-        let ring_dimension = params.ring_dimension() as usize;
-        let modulus = params.modulus();
-
-        // First four bytes contain the maximum byte size per coefficient
-        let max_byte_size = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
-
-        // Next ceil(n/8) bytes contain the bit vector indicating if coefficients are negative
-        let bit_vector_byte_size = ring_dimension.div_ceil(8);
-        let bit_vector = &bytes[4..4 + bit_vector_byte_size];
-
-        // Remaining bytes contain coefficient values
-        let coeffs: Vec<FinRingElem> = parallel_iter!(0..ring_dimension)
-            .map(|i| {
-                let start = 4 + bit_vector_byte_size + (i * max_byte_size);
-                let end = start + max_byte_size;
-                let value_bytes = &bytes[start..end];
-
-                let value = BigUint::from_bytes_le(value_bytes);
-
-                let byte_idx = i / 8;
-                let bit_idx = i % 8;
-                let is_negative = (bit_vector[byte_idx] & (1 << bit_idx)) != 0;
-
-                // Convert back from centered representation
-                let final_value = if is_negative {
-                    // If negative flag is set, compute q - value
-                    modulus.as_ref() - &value
-                } else {
-                    // Otherwise, use value as is
-                    value
-                };
-
-                FinRingElem::new(final_value, modulus.clone())
-            })
-            .collect();
-
+        let coeffs = coeffs_from_compact_bytes(
+            &params.modulus(),
+            params.ring_dimension() as usize,
+            bytes,
+        );
         Self::from_coeffs(params, &coeffs)
     }
 
     fn const_zero(params: &Self::Params) -> Self {
-        Self::poly_gen_from_const(params, BigUint::ZERO.to_string())
+        ffi_guard::expect_ffi(Self::poly_gen_from_const(params, BigUint::ZERO.to_string()))
     }
 
     fn const_one(params: &Self::Params) -> Self {
-        Self::poly_gen_from_const(params, BigUint::from(1u32).to_string())
+        ffi_guard::expect_ffi(Self::poly_gen_from_const(params, BigUint::from(1u32).to_string()))
     }
 
     fn const_minus_one(params: &Self::Params) -> Self {
-        Self::poly_gen_from_const(
-            params,
-            (params.modulus().as_ref() - BigUint::from(1u32)).to_string(),
-        )
+        let minus_one = params.modulus().as_ref() - BigUint::from(1u32);
+        ffi_guard::expect_ffi(Self::poly_gen_from_const(params, minus_one.to_string()))
     }
 
     fn const_power_of_base(params: &Self::Params, k: usize) -> Self {
         let base = 1u32 << params.base_bits();
-        Self::poly_gen_from_const(params, BigUint::from(base).pow(k as u32).to_string())
+        let power = BigUint::from(base).pow(k as u32);
+        ffi_guard::expect_ffi(Self::poly_gen_from_const(params, power.to_string()))
     }
 
     fn const_max(params: &Self::Params) -> Self {
@@ -181,8 +276,10 @@ impl Poly for DCRTPoly {
         let log_q = params.modulus_bits();
         let base_bits = params.base_bits() as usize;
 
-        // Calculate the number of digits needed in the decomposition
+        // Calculate the number of digits needed in the decomposition. The digits are extracted
+        // from the big-integer coefficients, so moduli wider than 64 bits are supported.
         let num_digits = params.modulus_digits();
+        debug_assert!(num_digits * base_bits >= log_q, "too few digits for the modulus");
 
         // Create a mask for extracting the base_bits bits
         let base_mask = (BigUint::from(1u32) << base_bits) - BigUint::from(1u32);
@@ -227,60 +324,31 @@ impl Poly for DCRTPoly {
     ///    corresponding coefficient is negative (> `q_half`) and `n` is the ring dimension
     /// 3. The remaining `n * max_byte_size` contain the coefficient values
     fn to_compact_bytes(&self) -> Vec<u8> {
-        let modulus = self.ptr_poly.GetModulus();
-        let modulus_big: BigUint = BigUint::from_str(&modulus).unwrap();
-        let q_half = &modulus_big / 2u8;
-
-        let coeffs = self.coeffs();
-        let ring_dimension = coeffs.len();
-
-        // Create a bit vector of `ceil(n/8)` bytes to store flags for negative coefficients
-        let bit_vector_byte_size = ring_dimension.div_ceil(8);
-        let mut bit_vector = vec![0u8; bit_vector_byte_size];
-
-        let mut max_byte_size = 0;
-        let mut processed_values = Vec::with_capacity(ring_dimension);
-
-        // First pass: Process coefficients, fill up `bit_vector`` and calculate `max_byte_size`
-        for (i, coeff) in coeffs.iter().enumerate() {
-            // Center coefficients around 0
-            let value = if coeff.value() > &q_half {
-                let byte_idx = i / 8; // Determines which byte in the bit vector the flag for the i-th coeffs belongs to
-                let bit_idx = i % 8; // Determines the bit position within that byte
-                bit_vector[byte_idx] |= 1 << bit_idx; // Set flag for negative coefficient
-                &modulus_big - coeff.value() // Convert to absolute value: q - coeff.value
-            } else if coeff.value() == &BigUint::ZERO {
-                BigUint::ZERO
-            } else {
-                coeff.value().clone()
-            };
-
-            processed_values.push(value.clone());
-
-            let value_bytes = value.to_bytes_le();
-            max_byte_size = std::cmp::max(max_byte_size, value_bytes.len());
-        }
-
-        let total_byte_size = 4 + bit_vector_byte_size + (ring_dimension * max_byte_size);
-        let mut result = vec![0u8; total_byte_size];
-
-        // Store max_byte_size in the first four bytes (little-endian)
-        let max_byte_size_bytes = (max_byte_size as u32).to_le_bytes();
-        result[0..4].copy_from_slice(&max_byte_size_bytes);
+        let modulus = BigUint::from_str(&self.ptr_poly.GetModulus()).unwrap();
+        compact_bytes(&modulus, &self.coeffs())
+    }
 
-        // Store bit vector in the next `ceil(n/8)` bytes
-        result[4..4 + bit_vector_byte_size].copy_from_slice(&bit_vector);
+    /// Reports a failing OpenFHE call as an error as well.
+    fn checked_add(&self, rhs: &Self) -> Result<Self, DiamondError> {
+        check_operands("add", self, rhs)?;
+        self.try_add(rhs)
+    }
 
-        // Second pass: Store preprocessed coefficient values s.t. each coefficient is
-        // `max_byte_size` bytes long
-        for (i, value) in processed_values.iter().enumerate() {
-            let value_bytes = value.to_bytes_le();
-            let start_pos = 4 + bit_vector_byte_size + (i * max_byte_size);
+    /// Reports a failing OpenFHE call as an error as well.
+    fn checked_mul(&self, rhs: &Self) -> Result<Self, DiamondError> {
+        check_operands("mul", self, rhs)?;
+        self.try_mul(rhs)
+    }
 
-            result[start_pos..start_pos + value_bytes.len()].copy_from_slice(&value_bytes);
+    /// Compares the moduli, and only then the ring dimensions, which need the coefficients to
+    /// be read back from OpenFHE.
+    fn operand_mismatch(&self, rhs: &Self) -> Option<String> {
+        let (lhs_modulus, rhs_modulus) = (self.ptr_poly.GetModulus(), rhs.ptr_poly.GetModulus());
+        if lhs_modulus != rhs_modulus {
+            return Some(format!("moduli {} and {}", lhs_modulus, rhs_modulus));
         }
-
-        result
+        let (lhs_len, rhs_len) = (self.coeffs().len(), rhs.coeffs().len());
+        (lhs_len != rhs_len).then(|| format!("{} and {} coefficients", lhs_len, rhs_len))
     }
 
     /// Recover bits from a polynomial using decision thresholds q/4 and 3q/4
@@ -326,11 +394,15 @@ impl PartialEq for DCRTPoly {
 impl Eq for DCRTPoly {}
 
 impl_binop_with_refs!(DCRTPoly => Add::add(self, rhs: &DCRTPoly) -> DCRTPoly {
-    DCRTPoly::new(ffi::DCRTPolyAdd(&rhs.ptr_poly, &self.ptr_poly))
+    #[cfg(feature = "checked")]
+    assert_operands("add", self, rhs);
+    ffi_guard::expect_ffi(self.try_add(rhs))
 });
 
 impl_binop_with_refs!(DCRTPoly => Mul::mul(self, rhs: &DCRTPoly) -> DCRTPoly {
-    DCRTPoly::new(ffi::DCRTPolyMul(&rhs.ptr_poly, &self.ptr_poly))
+    #[cfg(feature = "checked")]
+    assert_operands("mul", self, rhs);
+    ffi_guard::expect_ffi(self.try_mul(rhs))
 });
 
 impl_binop_with_refs!(DCRTPoly => Sub::sub(self, rhs: &DCRTPoly) -> DCRTPoly {
@@ -349,7 +421,7 @@ impl Neg for &DCRTPoly {
     type Output = DCRTPoly;
 
     fn neg(self) -> Self::Output {
-        DCRTPoly::new(self.ptr_poly.Negate())
+        ffi_guard::expect_ffi(self.try_neg())
     }
 }
 
@@ -403,6 +475,58 @@ mod tests {
     };
     use rand::prelude::*;
 
+    #[test]
+    fn test_dcrtpoly_release() {
+        let params = DCRTPolyParams::default();
+        let sampler = DCRTPolyUniformSampler::new();
+        let watermark = counters::MemoryWatermark::start();
+        let polys = (0..10)
+            .map(|_| sampler.sample_poly(&params, &DistType::FinRingDist))
+            .collect::<Vec<_>>();
+        if cfg!(feature = "counters") {
+            assert!(watermark.report().peak_ffi_polys >= 10);
+        }
+
+        // Only the last of the clones sharing a C++ object frees it
+        let shared = polys[0].clone();
+        assert!(!shared.release());
+        for poly in polys {
+            assert!(poly.release());
+        }
+    }
+
+    #[test]
+    fn test_dcrtpoly_fallible_ffi() {
+        let params = DCRTPolyParams::default();
+        let sampler = DCRTPolyUniformSampler::new();
+        let a = sampler.try_sample_poly(&params, &DistType::FinRingDist).unwrap();
+        let b = sampler.try_sample_poly(&params, &DistType::BitDist).unwrap();
+
+        // The fallible variants agree with the operators
+        assert_eq!(a.try_add(&b).unwrap(), &a + &b);
+        assert_eq!(a.checked_mul(&b).unwrap(), &a * &b);
+        assert_eq!(a.try_neg().unwrap(), -&a);
+        let coeffs = a.coeffs();
+        assert_eq!(DCRTPoly::try_from_coeffs(&params, &coeffs).unwrap(), a);
+        let one = FinRingElem::new(1u32, params.modulus());
+        assert_eq!(DCRTPoly::try_from_const(&params, &one).unwrap(), DCRTPoly::const_one(&params));
+
+        // Polynomials of another ring are rejected before reaching OpenFHE
+        let other_params = DCRTPolyParams::new(8, 2, 17, 1);
+        let c = sampler.try_sample_poly(&other_params, &DistType::FinRingDist).unwrap();
+        assert!(matches!(a.checked_add(&c), Err(DiamondError::OperandMismatch { op: "add", .. })));
+    }
+
+    #[cfg(feature = "checked")]
+    #[test]
+    #[should_panic(expected = "operands of mul do not match")]
+    fn test_dcrtpoly_checked_operator() {
+        // Same ring dimension, but one more CRT tower
+        let params = DCRTPolyParams::default();
+        let other_params = DCRTPolyParams::new(4, 3, 17, 1);
+        let _ = DCRTPoly::const_one(&params) * DCRTPoly::const_one(&other_params);
+    }
+
     #[test]
     fn test_dcrtpoly_coeffs() {
         let mut rng = rand::rng();
@@ -503,6 +627,31 @@ mod tests {
         assert_eq!(decomposed.len(), { params.modulus_digits() });
     }
 
+    #[test]
+    fn test_dcrtpoly_decompose_wide_modulus() {
+        // An 80-bit modulus in base 2 and in base 2^10
+        for base_bits in [1, 10] {
+            let params = DCRTPolyParams::new(4, 2, 40, base_bits);
+            assert!(params.modulus_bits() > 64);
+            let sampler = DCRTPolyUniformSampler::new();
+            let poly = sampler.sample_poly(&params, &DistType::FinRingDist);
+            let decomposed = poly.decompose_base(&params);
+            assert_eq!(decomposed.len(), params.modulus_digits());
+
+            // Every digit is below the base, and the digits above bit 64 are extracted
+            let base = BigUint::from(1u32) << base_bits;
+            for digit_poly in decomposed.iter() {
+                assert!(digit_poly.coeffs().iter().all(|coeff| coeff.value() < &base));
+            }
+            let high_digits = &decomposed[64 / base_bits as usize + 1..];
+            let zero = DCRTPoly::const_zero(&params);
+            assert!(high_digits.iter().any(|digit_poly| digit_poly != &zero));
+
+            // Recombining the digits gives back the polynomial
+            assert_eq!(DCRTPoly::from_decomposed(&params, &decomposed), poly);
+        }
+    }
+
     #[test]
     fn test_dcrtpoly_to_compact_bytes_bit_dist() {
         let params = DCRTPolyParams::default();
@@ -668,4 +817,38 @@ mod tests {
         // Clean up
         std::fs::remove_dir_all(test_dir).unwrap();
     }
+
+    #[test]
+    fn test_dcrtpoly_automorphism() {
+        let params = DCRTPolyParams::new(8, 2, 17, 1);
+        let sampler = DCRTPolyUniformSampler::new();
+        let a = sampler.sample_poly(&params, &DistType::FinRingDist);
+        let b = sampler.sample_poly(&params, &DistType::FinRingDist);
+
+        // X -> X^3 maps X to X^3 and X^3 to X^9 = -X
+        let q = params.modulus();
+        let mut coeffs = vec![FinRingElem::new(0, q.clone()); 8];
+        coeffs[1] = FinRingElem::new(1, q.clone());
+        let x = DCRTPoly::from_coeffs(&params, &coeffs);
+        let x3 = x.automorphism(&params, 3);
+        assert_eq!(x3, x.clone() * &x * &x);
+        assert_eq!(x3.automorphism(&params, 3), -x);
+
+        // The identity, a ring homomorphism, and composition multiplies the indices
+        assert_eq!(a.automorphism(&params, 1), a);
+        assert_eq!(
+            (a.clone() * &b).automorphism(&params, 5),
+            a.automorphism(&params, 5) * b.automorphism(&params, 5)
+        );
+        assert_eq!(
+            (a.clone() + &b).automorphism(&params, 7),
+            a.automorphism(&params, 7) + b.automorphism(&params, 7)
+        );
+        assert_eq!(
+            a.automorphism(&params, 3).automorphism(&params, 5),
+            a.automorphism(&params, 15)
+        );
+        let one = DCRTPoly::const_one(&params);
+        assert_eq!(one.automorphism(&params, 11), one);
+    }
 }
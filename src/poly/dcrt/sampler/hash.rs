@@ -1,32 +1,128 @@
-use crate::{
-    parallel_iter,
-    poly::{
-        dcrt::{DCRTPoly, DCRTPolyMatrix, FinRingElem},
-        sampler::{DistType, PolyHashSampler},
-        Poly, PolyMatrix, PolyParams,
-    },
+use crate::poly::{
+    dcrt::{DCRTPoly, DCRTPolyMatrix, DCRTPolyParams, FinRingElem},
+    sampler::{DistType, PolyHashSampler},
+    sampling::{sample_coeffs, GaussianCdt},
+    Poly, PolyMatrix, PolyParams,
 };
 use bitvec::prelude::*;
 use digest::OutputSizeUser;
-use num_bigint::BigUint;
-use num_traits::Zero;
-use rayon::prelude::*;
-use std::{marker::PhantomData, ops::Range};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::marker::PhantomData;
 
 pub struct DCRTPolyHashSampler<H: OutputSizeUser + digest::Digest> {
     _h: PhantomData<H>,
 }
 
+/// Derives every entry of a hashed matrix independently from `H(key || tag || i || j)`, so that
+/// tiles of the matrix can be generated in parallel and single entries recomputed on demand.
+struct EntrySampler<'a, H> {
+    params: &'a DCRTPolyParams,
+    hasher: H,
+    dist: DistType,
+    cdt: Option<GaussianCdt>,
+}
+
+impl<'a, H> EntrySampler<'a, H>
+where
+    H: OutputSizeUser + digest::Digest + Clone,
+{
+    fn new(params: &'a DCRTPolyParams, hash_key: [u8; 32], tag: &[u8], dist: DistType) -> Self {
+        let mut hasher: H = H::new();
+        hasher.update(hash_key);
+        hasher.update(tag);
+        let cdt = match dist {
+            DistType::GaussDist { sigma } => Some(GaussianCdt::new(sigma)),
+            _ => None,
+        };
+        Self { params, hasher, dist, cdt }
+    }
+
+    /// Concatenates `H(key || tag || i || j || hash_idx)` for `hash_idx` in `0..num_hashes`.
+    fn hash_bytes(&self, i: usize, j: usize, num_hashes: usize) -> Vec<u8> {
+        let mut hasher = self.hasher.clone();
+        hasher.update(i.to_le_bytes());
+        hasher.update(j.to_le_bytes());
+        let mut bytes = Vec::with_capacity(num_hashes * <H as digest::Digest>::output_size());
+        for hash_idx in 0..num_hashes {
+            let mut hasher = hasher.clone();
+            hasher.update((hash_idx as u64).to_le_bytes());
+            bytes.extend_from_slice(&hasher.finalize());
+        }
+        bytes
+    }
+
+    fn entry(&self, i: usize, j: usize) -> DCRTPoly {
+        let params = self.params;
+        let hash_output_size = <H as digest::Digest>::output_size() * 8;
+        let n = params.ring_dimension() as usize;
+        let q = params.modulus();
+        let coeffs = match self.dist {
+            DistType::FinRingDist | DistType::TernaryDist { .. } => {
+                // The hash only seeds a ChaCha20 stream, as rejection sampling modulo q and the
+                // sorting network of the ternary distribution need a variable number of words
+                let num_hash_seed = 256usize.div_ceil(hash_output_size);
+                let seed = self.hash_bytes(i, j, num_hash_seed)[..32].try_into().unwrap();
+                sample_coeffs(&q, n, &self.dist, &mut ChaCha20Rng::from_seed(seed))
+            }
+            DistType::BitDist => {
+                let num_hash_bit_per_poly = n.div_ceil(hash_output_size);
+                let bytes = self.hash_bytes(i, j, num_hash_bit_per_poly);
+                let bits = bytes.view_bits::<Lsb0>();
+                (0..n)
+                    .map(|coeff_idx| FinRingElem::new(bits[coeff_idx] as u64, q.clone()))
+                    .collect()
+            }
+            DistType::GaussDist { .. } => {
+                // Every coefficient consumes 8 bytes for the table lookup and 1 for the sign
+                let cdt = self.cdt.as_ref().unwrap();
+                let num_hash_gauss_per_poly = (9 * 8 * n).div_ceil(hash_output_size);
+                self.hash_bytes(i, j, num_hash_gauss_per_poly)
+                    .chunks_exact(9)
+                    .take(n)
+                    .map(|chunk| {
+                        let bits = u64::from_le_bytes(chunk[..8].try_into().unwrap());
+                        let sample = cdt.sample(bits, chunk[8] & 1 == 1);
+                        FinRingElem::new(sample, q.clone())
+                    })
+                    .collect()
+            }
+        };
+        DCRTPoly::from_coeffs(params, &coeffs)
+    }
+}
+
+impl<H> DCRTPolyHashSampler<H>
+where
+    H: OutputSizeUser + digest::Digest + Clone + Send + Sync,
+{
+    /// Samples only the `(i, j)` entry of the matrix that [`PolyHashSampler::sample_hash`]
+    /// returns for the same key, tag and distribution.
+    pub fn sample_hash_entry<B: AsRef<[u8]>>(
+        &self,
+        params: &DCRTPolyParams,
+        hash_key: [u8; 32],
+        tag: B,
+        i: usize,
+        j: usize,
+        dist: DistType,
+    ) -> DCRTPoly {
+        EntrySampler::<H>::new(params, hash_key, tag.as_ref(), dist).entry(i, j)
+    }
+}
+
 impl<H> PolyHashSampler<[u8; 32]> for DCRTPolyHashSampler<H>
 where
     H: OutputSizeUser + digest::Digest + Clone + Send + Sync,
 {
     type M = DCRTPolyMatrix;
+    type Hash = H;
 
     fn new() -> Self {
         Self { _h: PhantomData }
     }
 
+    /// Generates the matrix tile by tile in parallel, writing every tile in place.
     fn sample_hash<B: AsRef<[u8]>>(
         &self,
         params: &<<Self::M as PolyMatrix>::P as Poly>::Params,
@@ -36,98 +132,17 @@ where
         ncol: usize,
         dist: DistType,
     ) -> DCRTPolyMatrix {
-        let hash_output_size = <H as digest::Digest>::output_size() * 8;
-        let n = params.ring_dimension() as usize;
-        let q = params.modulus();
-        let log_q = params.modulus_bits();
-        let num_hash_fin_per_poly = (log_q * n).div_ceil(hash_output_size);
-        let num_hash_bit_per_poly = n.div_ceil(hash_output_size);
-        let mut new_matrix = DCRTPolyMatrix::new_empty(params, nrow, ncol);
-        let mut hasher: H = H::new();
-        hasher.update(hash_key);
-        hasher.update(tag.as_ref());
-        let f = |row_offsets: Range<usize>, col_offsets: Range<usize>| -> Vec<Vec<DCRTPoly>> {
-            match dist {
-                DistType::FinRingDist => parallel_iter!(row_offsets)
-                    .map(|i| {
-                        parallel_iter!(col_offsets.clone())
-                            .map(|j| {
-                                let mut hasher = hasher.clone();
-                                hasher.update(i.to_le_bytes());
-                                hasher.update(j.to_le_bytes());
-                                let mut local_bits = bitvec![u8, Lsb0;];
-                                for hash_idx in 0..num_hash_fin_per_poly {
-                                    let mut hasher = hasher.clone();
-                                    hasher.update((hash_idx as u64).to_le_bytes());
-                                    for &byte in hasher.finalize().iter() {
-                                        for bit_index in 0..8 {
-                                            local_bits.push((byte >> bit_index) & 1 != 0);
-                                        }
-                                    }
-                                }
-                                let local_bits = local_bits.split_at(log_q * n).0;
-                                let coeffs = parallel_iter!(0..n)
-                                    .map(|coeff_idx| {
-                                        let bits =
-                                            &local_bits[coeff_idx * log_q..(coeff_idx + 1) * log_q];
-                                        let mut value = BigUint::zero();
-                                        for bit in bits.iter() {
-                                            value <<= 1;
-                                            if *bit {
-                                                value |= BigUint::from(1u32);
-                                            }
-                                        }
-                                        FinRingElem::new(value, q.clone())
-                                    })
-                                    .collect::<Vec<_>>();
-                                DCRTPoly::from_coeffs(params, &coeffs)
-                            })
-                            .collect()
-                    })
-                    .collect::<Vec<Vec<DCRTPoly>>>(),
-                DistType::BitDist => parallel_iter!(row_offsets)
-                    .map(|i| {
-                        parallel_iter!(col_offsets.clone())
-                            .map(|j| {
-                                let mut hasher = hasher.clone();
-                                hasher.update(i.to_le_bytes());
-                                hasher.update(j.to_le_bytes());
-                                let mut local_bits = bitvec![u8, Lsb0;];
-                                for hash_idx in 0..num_hash_bit_per_poly {
-                                    let mut hasher = hasher.clone();
-                                    hasher.update((hash_idx as u64).to_le_bytes());
-                                    for &byte in hasher.finalize().iter() {
-                                        for bit_index in 0..8 {
-                                            local_bits.push((byte >> bit_index) & 1 != 0);
-                                        }
-                                    }
-                                }
-                                let local_bits = local_bits.split_at(n).0;
-                                let coeffs = parallel_iter!(0..n)
-                                    .map(|coeff_idx| {
-                                        FinRingElem::new(local_bits[coeff_idx] as u64, q.clone())
-                                    })
-                                    .collect::<Vec<_>>();
-                                DCRTPoly::from_coeffs(params, &coeffs)
-                            })
-                            .collect::<Vec<DCRTPoly>>()
-                    })
-                    .collect::<Vec<Vec<DCRTPoly>>>(),
-                _ => {
-                    panic!("Unsupported distribution type")
-                }
-            }
-        };
-        new_matrix.replace_entries(0..nrow, 0..ncol, f);
-        new_matrix
+        let sampler = EntrySampler::<H>::new(params, hash_key, tag.as_ref(), dist);
+        DCRTPolyMatrix::from_fn(params, nrow, ncol, |i, j| sampler.entry(i, j))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::poly::dcrt::DCRTPolyParams;
+    use crate::poly::{dcrt::DCRTPolyParams, norms::matrix_inf_norm, sampler::many_tag};
     use keccak_asm::Keccak256;
+    use num_bigint::BigUint;
 
     #[test]
     fn test_poly_hash_sampler() {
@@ -160,4 +175,77 @@ mod tests {
         assert_eq!(matrix.row_size(), nrow, "Matrix row count mismatch");
         assert_eq!(matrix.col_size(), ncol, "Matrix column count mismatch");
     }
+
+    #[test]
+    fn test_poly_hash_sampler_gauss_dist() {
+        let key = [0u8; 32];
+        let params = DCRTPolyParams::default();
+        let sampler = DCRTPolyHashSampler::<Keccak256>::new();
+        let sigma = 3.0;
+        let dist = DistType::GaussDist { sigma };
+        let matrix = sampler.sample_hash(&params, key, b"MyTag", 10, 20, dist);
+        assert_eq!(matrix.size(), (10, 20));
+
+        // The same key and tag reproduce the matrix, another tag does not
+        assert_eq!(sampler.sample_hash(&params, key, b"MyTag", 10, 20, dist), matrix);
+        assert_ne!(sampler.sample_hash(&params, key, b"Other", 10, 20, dist), matrix);
+
+        // Every coefficient is within the tail cut
+        let tail = GaussianCdt::new(sigma).tail();
+        assert!(matrix_inf_norm(&params, &matrix) <= BigUint::from(tail));
+    }
+
+    #[test]
+    fn test_poly_hash_sampler_entry() {
+        let key: [u8; 32] = rand::random();
+        let params = DCRTPolyParams::default();
+        let sampler = DCRTPolyHashSampler::<Keccak256>::new();
+        let dists = [
+            DistType::FinRingDist,
+            DistType::BitDist,
+            DistType::GaussDist { sigma: 3.0 },
+            DistType::TernaryDist { hamming_weight: 5 },
+        ];
+        for dist in dists {
+            let matrix = sampler.sample_hash(&params, key, b"MyTag", 3, 130, dist);
+
+            // Every entry, including those of the second tile of columns, can be sampled alone
+            for (i, j) in [(0, 0), (2, 7), (1, 99), (0, 100), (2, 129)] {
+                let entry = sampler.sample_hash_entry(&params, key, b"MyTag", i, j, dist);
+                assert_eq!(entry, matrix.entry(i, j));
+            }
+        }
+    }
+
+    #[test]
+    fn test_poly_hash_sampler_many() {
+        let key: [u8; 32] = rand::random();
+        let params = DCRTPolyParams::default();
+        let sampler = DCRTPolyHashSampler::<Keccak256>::new();
+        let specs = [(b"A".to_vec(), 2, 3), (b"AB".to_vec(), 2, 3), (b"B".to_vec(), 3, 2)];
+        let matrices = sampler.sample_hash_many(&params, key, &specs, DistType::FinRingDist);
+
+        // Every matrix is the one of its derived tag
+        for ((tag, nrow, ncol), matrix) in specs.iter().zip(matrices.iter()) {
+            let derived = many_tag(tag, *nrow, *ncol);
+            let expected =
+                sampler.sample_hash(&params, key, derived, *nrow, *ncol, DistType::FinRingDist);
+            assert_eq!(matrix, &expected);
+        }
+
+        // Tags that are prefixes of each other, or dimensions alone, separate the matrices
+        assert_ne!(matrices[0], matrices[1]);
+        assert_ne!(many_tag(b"A", 2, 3), many_tag(b"A", 3, 2));
+        let plain = sampler.sample_hash(&params, key, b"A", 2, 3, DistType::FinRingDist);
+        assert_ne!(matrices[0], plain);
+    }
+
+    #[test]
+    #[should_panic(expected = "is given twice")]
+    fn test_poly_hash_sampler_many_duplicate_tag() {
+        let params = DCRTPolyParams::default();
+        let sampler = DCRTPolyHashSampler::<Keccak256>::new();
+        let specs = [(b"A", 1, 1), (b"A", 2, 2)];
+        sampler.sample_hash_many(&params, [0u8; 32], &specs, DistType::BitDist);
+    }
 }
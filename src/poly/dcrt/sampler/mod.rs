@@ -1,7 +1,10 @@
 pub mod hash;
+pub mod prf;
+pub mod registry;
 pub mod trapdoor;
 pub mod uniform;
 
 pub use hash::DCRTPolyHashSampler;
+pub use prf::DCRTPolyPrfSampler;
 pub use trapdoor::DCRTPolyTrapdoorSampler;
 pub use uniform::DCRTPolyUniformSampler;
@@ -0,0 +1,102 @@
+use crate::poly::{
+    dcrt::{DCRTPoly, DCRTPolyMatrix},
+    sampler::{DistType, PolyHashSampler},
+    sampling::sample_coeffs,
+    Poly, PolyMatrix, PolyParams,
+};
+use digest::Digest;
+use keccak_asm::Keccak256;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+/// A drop-in replacement for [`super::DCRTPolyHashSampler`] that expands the key with ChaCha20
+/// instead of hashing every chunk of every entry.
+///
+/// The ChaCha20 key is `Keccak256(key || tag)`, so different tags give independent keys, and
+/// the entry `(i, j)` is read from its own ChaCha20 stream `(i << 32) | j`. Entries can thus be
+/// sampled in parallel and the output only depends on `key`, `tag` and the position.
+pub struct DCRTPolyPrfSampler {}
+
+impl DCRTPolyPrfSampler {
+    fn entry_rng(seed: [u8; 32], i: usize, j: usize) -> ChaCha20Rng {
+        assert!(i < 1 << 32 && j < 1 << 32, "matrix index out of the stream range");
+        let mut rng = ChaCha20Rng::from_seed(seed);
+        rng.set_stream(((i as u64) << 32) | j as u64);
+        rng
+    }
+}
+
+impl PolyHashSampler<[u8; 32]> for DCRTPolyPrfSampler {
+    type M = DCRTPolyMatrix;
+    type Hash = Keccak256;
+
+    fn new() -> Self {
+        Self {}
+    }
+
+    fn sample_hash<B: AsRef<[u8]>>(
+        &self,
+        params: &<<Self::M as PolyMatrix>::P as Poly>::Params,
+        hash_key: [u8; 32],
+        tag: B,
+        nrow: usize,
+        ncol: usize,
+        dist: DistType,
+    ) -> DCRTPolyMatrix {
+        let n = params.ring_dimension() as usize;
+        let q = params.modulus();
+        let mut hasher = Keccak256::new();
+        hasher.update(hash_key);
+        hasher.update(tag.as_ref());
+        let seed: [u8; 32] = hasher.finalize().into();
+        DCRTPolyMatrix::from_fn(params, nrow, ncol, |i, j| {
+            let mut rng = Self::entry_rng(seed, i, j);
+            DCRTPoly::from_coeffs(params, &sample_coeffs(&q, n, &dist, &mut rng))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::dcrt::DCRTPolyParams;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_poly_prf_sampler_fin_ring_dist() {
+        let key = [0u8; 32];
+        let params = DCRTPolyParams::default();
+        let sampler = DCRTPolyPrfSampler::new();
+        let nrow = 10;
+        let ncol = 30;
+        let tag = b"MyTag";
+        let matrix = sampler.sample_hash(&params, key, tag, nrow, ncol, DistType::FinRingDist);
+        assert_eq!(matrix.row_size(), nrow, "Matrix row count mismatch");
+        assert_eq!(matrix.col_size(), ncol, "Matrix column count mismatch");
+
+        // The same key and tag reproduce the matrix, a different tag does not
+        let same = sampler.sample_hash(&params, key, tag, nrow, ncol, DistType::FinRingDist);
+        assert_eq!(matrix, same);
+        let other = sampler.sample_hash(&params, key, b"Other", nrow, ncol, DistType::FinRingDist);
+        assert_ne!(matrix, other);
+
+        // A sub-matrix sampled separately matches the corresponding entries
+        let small = sampler.sample_hash(&params, key, tag, 2, 3, DistType::FinRingDist);
+        assert_eq!(small, matrix.slice(0, 2, 0, 3));
+    }
+
+    #[test]
+    fn test_poly_prf_sampler_bit_dist() {
+        let key: [u8; 32] = rand::random();
+        let params = DCRTPolyParams::default();
+        let sampler = DCRTPolyPrfSampler::new();
+        let matrix = sampler.sample_hash(&params, key, b"MyTag", 10, 30, DistType::BitDist);
+        for i in 0..10 {
+            for poly in matrix.get_row(i) {
+                for coeff in poly.coeffs() {
+                    assert!(coeff.value() <= &BigUint::from(1u8));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use super::{trapdoor::DCRTTrapdoor, DCRTPolyHashSampler, DCRTPolyTrapdoorSampler};
+use crate::poly::{
+    dcrt::{DCRTPolyMatrix, DCRTPolyParams, DCRTPolyUniformSampler},
+    sampler::{DistType, PolyHashSampler, PolyTrapdoorSampler, PolyUniformSampler},
+};
+use keccak_asm::Keccak256;
+use std::collections::HashMap;
+
+/// Object-safe sampler of matrices with a distribution fixed at construction.
+pub trait DynMatrixSampler: Send + Sync {
+    fn sample(&self, params: &DCRTPolyParams, nrow: usize, ncol: usize) -> DCRTPolyMatrix;
+}
+
+/// Object-safe counterpart of [`PolyTrapdoorSampler`] for the DCRT backend.
+pub trait DynTrapdoorSampler: Send + Sync {
+    fn trapdoor(&self, params: &DCRTPolyParams, size: usize) -> (DCRTTrapdoor, DCRTPolyMatrix);
+    fn preimage(
+        &self,
+        params: &DCRTPolyParams,
+        trapdoor: &DCRTTrapdoor,
+        public_matrix: &DCRTPolyMatrix,
+        target: &DCRTPolyMatrix,
+    ) -> DCRTPolyMatrix;
+}
+
+struct UniformDynSampler {
+    dist: DistType,
+}
+
+impl DynMatrixSampler for UniformDynSampler {
+    fn sample(&self, params: &DCRTPolyParams, nrow: usize, ncol: usize) -> DCRTPolyMatrix {
+        DCRTPolyUniformSampler::new().sample_uniform(params, nrow, ncol, self.dist)
+    }
+}
+
+struct HashDynSampler {
+    hash_key: [u8; 32],
+    tag: Vec<u8>,
+    dist: DistType,
+}
+
+impl DynMatrixSampler for HashDynSampler {
+    fn sample(&self, params: &DCRTPolyParams, nrow: usize, ncol: usize) -> DCRTPolyMatrix {
+        DCRTPolyHashSampler::<Keccak256>::new().sample_hash(
+            params,
+            self.hash_key,
+            &self.tag,
+            nrow,
+            ncol,
+            self.dist,
+        )
+    }
+}
+
+impl DynTrapdoorSampler for DCRTPolyTrapdoorSampler {
+    fn trapdoor(&self, params: &DCRTPolyParams, size: usize) -> (DCRTTrapdoor, DCRTPolyMatrix) {
+        PolyTrapdoorSampler::trapdoor(self, params, size)
+    }
+
+    fn preimage(
+        &self,
+        params: &DCRTPolyParams,
+        trapdoor: &DCRTTrapdoor,
+        public_matrix: &DCRTPolyMatrix,
+        target: &DCRTPolyMatrix,
+    ) -> DCRTPolyMatrix {
+        PolyTrapdoorSampler::preimage(self, params, trapdoor, public_matrix, target)
+    }
+}
+
+/// Parses a distribution name: `fin_ring`, `bit`, `gauss=<sigma>` or `ternary=<hamming weight>`.
+fn parse_dist(spec: &str) -> Result<DistType, String> {
+    match spec {
+        "fin_ring" => Ok(DistType::FinRingDist),
+        "bit" => Ok(DistType::BitDist),
+        _ => {
+            if let Some(sigma) = spec.strip_prefix("gauss=") {
+                return sigma
+                    .parse::<f64>()
+                    .map(|sigma| DistType::GaussDist { sigma })
+                    .map_err(|e| format!("invalid gaussian sigma {sigma}: {e}"));
+            }
+            match spec.strip_prefix("ternary=") {
+                Some(weight) => weight
+                    .parse::<usize>()
+                    .map(|hamming_weight| DistType::TernaryDist { hamming_weight })
+                    .map_err(|e| format!("invalid hamming weight {weight}: {e}")),
+                None => Err(format!("unknown distribution {spec}")),
+            }
+        }
+    }
+}
+
+/// Samplers selected by name at runtime.
+///
+/// Matrix samplers are described by specs of the form
+/// * `uniform:<dist>` for the OpenFHE sampler,
+/// * `hash+keccak:<tag>:<dist>` for the hash sampler keyed by the registry hash key,
+///
+/// and trapdoor samplers by `trapdoor:<sigma>`, where the gadget base is taken from the params.
+pub struct SamplerRegistry {
+    params: DCRTPolyParams,
+    hash_key: [u8; 32],
+    matrix_samplers: HashMap<String, Box<dyn DynMatrixSampler>>,
+    trapdoor_samplers: HashMap<String, Box<dyn DynTrapdoorSampler>>,
+}
+
+impl SamplerRegistry {
+    pub fn new(params: DCRTPolyParams, hash_key: [u8; 32]) -> Self {
+        Self {
+            params,
+            hash_key,
+            matrix_samplers: HashMap::new(),
+            trapdoor_samplers: HashMap::new(),
+        }
+    }
+
+    pub fn register_matrix_sampler(&mut self, name: &str, sampler: Box<dyn DynMatrixSampler>) {
+        self.matrix_samplers.insert(name.to_string(), sampler);
+    }
+
+    pub fn register_trapdoor_sampler(&mut self, name: &str, sampler: Box<dyn DynTrapdoorSampler>) {
+        self.trapdoor_samplers.insert(name.to_string(), sampler);
+    }
+
+    /// Builds a sampler from its spec and registers it under `name`.
+    pub fn register_spec(&mut self, name: &str, spec: &str) -> Result<(), String> {
+        let parts = spec.split(':').collect::<Vec<_>>();
+        match parts.as_slice() {
+            ["uniform", dist] => {
+                let dist = parse_dist(dist)?;
+                self.register_matrix_sampler(name, Box::new(UniformDynSampler { dist }));
+            }
+            ["hash+keccak", tag, dist] => {
+                let dist = parse_dist(dist)?;
+                if matches!(dist, DistType::GaussDist { .. }) {
+                    return Err("hash sampler does not support gaussian distribution".to_string());
+                }
+                let sampler =
+                    HashDynSampler { hash_key: self.hash_key, tag: tag.as_bytes().to_vec(), dist };
+                self.register_matrix_sampler(name, Box::new(sampler));
+            }
+            ["trapdoor", sigma] => {
+                let sigma =
+                    sigma.parse::<f64>().map_err(|e| format!("invalid sigma {sigma}: {e}"))?;
+                let sampler = DCRTPolyTrapdoorSampler::new(&self.params, sigma);
+                self.register_trapdoor_sampler(name, Box::new(sampler));
+            }
+            _ => return Err(format!("unknown sampler spec {spec}")),
+        }
+        Ok(())
+    }
+
+    /// Registers every `(name, spec)` pair, e.g. read from a config file.
+    pub fn from_specs(
+        params: DCRTPolyParams,
+        hash_key: [u8; 32],
+        specs: &HashMap<String, String>,
+    ) -> Result<Self, String> {
+        let mut registry = Self::new(params, hash_key);
+        for (name, spec) in specs.iter() {
+            registry.register_spec(name, spec)?;
+        }
+        Ok(registry)
+    }
+
+    pub fn matrix_sampler(&self, name: &str) -> Option<&dyn DynMatrixSampler> {
+        self.matrix_samplers.get(name).map(|sampler| sampler.as_ref())
+    }
+
+    pub fn trapdoor_sampler(&self, name: &str) -> Option<&dyn DynTrapdoorSampler> {
+        self.trapdoor_samplers.get(name).map(|sampler| sampler.as_ref())
+    }
+
+    pub fn params(&self) -> &DCRTPolyParams {
+        &self.params
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::PolyMatrix;
+
+    #[test]
+    fn test_sampler_registry_specs() {
+        let params = DCRTPolyParams::default();
+        let specs = HashMap::from([
+            ("uniform".to_string(), "uniform:fin_ring".to_string()),
+            ("error".to_string(), "uniform:gauss=3.19".to_string()),
+            ("public".to_string(), "hash+keccak:PUBLIC:fin_ring".to_string()),
+            ("trapdoor".to_string(), "trapdoor:4.578".to_string()),
+        ]);
+        let registry = SamplerRegistry::from_specs(params.clone(), [0u8; 32], &specs).unwrap();
+
+        for name in ["uniform", "error", "public"] {
+            let matrix = registry.matrix_sampler(name).unwrap().sample(&params, 2, 3);
+            assert_eq!(matrix.size(), (2, 3));
+        }
+
+        // The hash sampler is deterministic
+        let public = registry.matrix_sampler("public").unwrap();
+        assert_eq!(public.sample(&params, 2, 3), public.sample(&params, 2, 3));
+
+        // The trapdoor sampler produces valid preimages
+        let trapdoor_sampler = registry.trapdoor_sampler("trapdoor").unwrap();
+        let (trapdoor, public_matrix) = trapdoor_sampler.trapdoor(&params, 2);
+        let target = registry.matrix_sampler("uniform").unwrap().sample(&params, 2, 2);
+        let preimage = trapdoor_sampler.preimage(&params, &trapdoor, &public_matrix, &target);
+        assert_eq!(public_matrix * preimage, target);
+    }
+
+    #[test]
+    fn test_sampler_registry_invalid_specs() {
+        let mut registry = SamplerRegistry::new(DCRTPolyParams::default(), [0u8; 32]);
+        assert!(registry.register_spec("a", "uniform:unknown").is_err());
+        assert!(registry.register_spec("b", "hash+keccak:TAG:gauss=3.0").is_err());
+        assert!(registry.register_spec("c", "trapdoor:abc").is_err());
+        assert!(registry.register_spec("d", "unknown").is_err());
+        assert!(registry.matrix_sampler("a").is_none());
+    }
+}
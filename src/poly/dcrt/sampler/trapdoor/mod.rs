@@ -1,37 +1,62 @@
 use crate::{
+    error::DiamondError,
     parallel_iter,
     poly::{
         dcrt::{
             cpp_matrix::CppMatrix,
+            ffi_guard,
             matrix::{I64Matrix, I64MatrixParams},
             sampler::DCRTPolyUniformSampler,
             DCRTPolyMatrix, DCRTPolyParams,
         },
+        rng::{default_source, CryptoRngSource},
         sampler::{DistType, PolyUniformSampler},
+        sampling::{karney, KARNEY_THRESHOLD},
         PolyMatrix, PolyParams,
     },
     utils::{block_size, debug_mem},
 };
 use openfhe::ffi::{ExtractMatrixCols, FormatMatrixCoefficient, SampleP1ForPertMat};
 use rayon::iter::ParallelIterator;
-pub use sampler::DCRTPolyTrapdoorSampler;
+pub use sampler::{DCRTPolyTrapdoorSampler, SharedAPublicMatrix};
 use std::{cmp::min, ops::Range, sync::Arc};
-use utils::{gen_dgg_int_vec, gen_int_karney, split_int64_mat_to_elems};
+use utils::{gen_dgg_int_vec, split_int64_mat_to_elems};
 
+mod perturbation;
+pub mod provider;
 pub mod sampler;
 pub mod utils;
 
-pub(crate) const KARNEY_THRESHOLD: f64 = 300.0;
-
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DCRTTrapdoor {
     pub r: DCRTPolyMatrix,
     pub e: DCRTPolyMatrix,
 }
 
+/// A trapdoor of an extension `(A | A1)` of a public matrix `A`, delegated by
+/// [`DCRTPolyTrapdoorSampler::delegate`]: a short `R` with `A * R = G - A1`, so that
+/// `(A | A1) * (R; I) = G`. It does not reveal the trapdoor of `A`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DelegatedTrapdoor {
+    pub r: DCRTPolyMatrix,
+    /// The Gaussian width of preimages sampled with the trapdoor, which dominates the
+    /// spectral norm of `R` times the width of gadget preimages.
+    pub sigma: f64,
+}
+
 impl DCRTTrapdoor {
     pub fn new(params: &DCRTPolyParams, size: usize, sigma: f64) -> Self {
-        let uniform_sampler = DCRTPolyUniformSampler::new();
+        Self::with_source(params, size, sigma, default_source())
+    }
+
+    /// Samples `R` and `E` with the randomness of `source`.
+    pub fn with_source(
+        params: &DCRTPolyParams,
+        size: usize,
+        sigma: f64,
+        source: Arc<dyn CryptoRngSource>,
+    ) -> Self {
+        let uniform_sampler = DCRTPolyUniformSampler::with_source(source);
         let log_base_q = params.modulus_digits();
         let dist = DistType::GaussDist { sigma };
         let r = uniform_sampler.sample_uniform(params, size, size * log_base_q, dist);
@@ -47,7 +72,8 @@ impl DCRTTrapdoor {
         dgg_large_params: (Option<f64>, f64, Option<&[f64]>),
         peikert: bool,
         total_ncol: usize,
-    ) -> DCRTPolyMatrix {
+        source: &dyn CryptoRngSource,
+    ) -> Result<DCRTPolyMatrix, DiamondError> {
         let r = &self.r;
         let e = &self.e;
         let params = &r.params;
@@ -62,11 +88,13 @@ impl DCRTTrapdoor {
         // the Peikert's inversion method otherwise, use Karney's method
         let p2z_vec = if sigma_large > KARNEY_THRESHOLD {
             let mut matrix = I64Matrix::new_empty(&I64MatrixParams, n * dk, padded_ncol);
+            let streams = source.streams();
             let f = |row_offsets: Range<usize>, col_offsets: Range<usize>| -> Vec<Vec<i64>> {
                 parallel_iter!(row_offsets)
-                    .map(|_| {
-                        parallel_iter!(col_offsets.clone())
-                            .map(|_| gen_int_karney(0.0, sigma_large))
+                    .map(|i| {
+                        col_offsets
+                            .clone()
+                            .map(|j| karney(&mut streams.rng(i, j), 0.0, sigma_large))
                             .collect()
                     })
                     .collect()
@@ -80,6 +108,7 @@ impl DCRTTrapdoor {
                 dgg_large_params.0.unwrap(),
                 dgg_large_params.1,
                 dgg_large_params.2.unwrap(),
+                source,
             );
             let vecs = parallel_iter!(0..n * dk)
                 .map(|i| {
@@ -105,14 +134,15 @@ impl DCRTTrapdoor {
         debug_mem("re generated");
         let tp2 = re * &p2;
         debug_mem("tp2 generated");
-        let p1 = sample_p1_for_pert_mat(a_mat, b_mat, d_mat, tp2, params, c, s, dgg, padded_ncol);
+        let p1 =
+            sample_p1_for_pert_mat(a_mat, b_mat, d_mat, tp2, params, c, s, dgg, padded_ncol)?;
         debug_mem("p1 generated");
         let mut p = p1.concat_rows(&[&p2]);
         debug_mem("p1 and p2 concatenated");
         if padding_ncol > 0 {
             p = p.slice_columns(0, total_ncol);
         }
-        p
+        Ok(p)
     }
 }
 
@@ -127,7 +157,7 @@ fn sample_p1_for_pert_mat(
     s: f64,
     dgg_stddev: f64,
     padded_ncol: usize,
-) -> DCRTPolyMatrix {
+) -> Result<DCRTPolyMatrix, DiamondError> {
     let n = params.ring_dimension();
     let depth = params.crt_depth();
     let k_res = params.crt_bits();
@@ -136,22 +166,15 @@ fn sample_p1_for_pert_mat(
     let num_threads = rayon::current_num_threads();
     let num_threads_for_cpp = num_threads.div_ceil(num_blocks);
     debug_mem("sample_p1_for_pert_square_mat parameters computed");
-    let mut a_mat = a_mat.to_cpp_matrix_ptr();
-    FormatMatrixCoefficient(a_mat.inner.as_mut().unwrap());
-    let a_mat_arc = Arc::new(a_mat);
-    let mut b_mat = b_mat.to_cpp_matrix_ptr();
-    FormatMatrixCoefficient(b_mat.inner.as_mut().unwrap());
-    let b_mat_arc = Arc::new(b_mat);
-    let mut d_mat = d_mat.to_cpp_matrix_ptr();
-    FormatMatrixCoefficient(d_mat.inner.as_mut().unwrap());
-    let d_mat_arc = Arc::new(d_mat);
+    let a_mat_arc = Arc::new(to_coefficient_format(&a_mat)?);
+    let b_mat_arc = Arc::new(to_coefficient_format(&b_mat)?);
+    let d_mat_arc = Arc::new(to_coefficient_format(&d_mat)?);
     debug_mem("a_mat, b_mat, d_mat are converted to cpp matrices");
 
     let p1_mat_blocks = parallel_iter!(0..num_blocks)
         .map(|i| {
             let end_col = min((i + 1) * block_size, padded_ncol);
-            let mut tp2 = tp2.slice_columns(i * block_size, end_col).to_cpp_matrix_ptr();
-            FormatMatrixCoefficient(tp2.inner.as_mut().unwrap());
+            let tp2 = to_coefficient_format(&tp2.slice_columns(i * block_size, end_col))?;
             let tp2_arc = Arc::new(tp2);
             debug_mem("tp2 is converted to cpp matrices");
             let ncol = end_col - i * block_size;
@@ -160,29 +183,43 @@ fn sample_p1_for_pert_mat(
                 .map(|j| {
                     let start_col = j * ncol_per_thread;
                     let end_col = min((j + 1) * ncol_per_thread, ncol);
-                    let tp2_cols =
-                        ExtractMatrixCols(&Arc::clone(&tp2_arc).as_ref().inner, start_col, end_col);
+                    let tp2_cols = ffi_guard::guard_ptr("ExtractMatrixCols", || {
+                        ExtractMatrixCols(&Arc::clone(&tp2_arc).as_ref().inner, start_col, end_col)
+                    })?;
                     debug_mem("extracting rows from tp2");
-                    let cpp_matrix = SampleP1ForPertMat(
-                        &Arc::clone(&a_mat_arc).as_ref().inner,
-                        &Arc::clone(&b_mat_arc).as_ref().inner,
-                        &Arc::clone(&d_mat_arc).as_ref().inner,
-                        &tp2_cols,
-                        n,
-                        depth,
-                        k_res,
-                        end_col - start_col,
-                        c,
-                        s,
-                        dgg_stddev,
-                    );
+                    let call = "SampleP1ForPertMat";
+                    let cpp_matrix = ffi_guard::guard(call, || {
+                        SampleP1ForPertMat(
+                            &Arc::clone(&a_mat_arc).as_ref().inner,
+                            &Arc::clone(&b_mat_arc).as_ref().inner,
+                            &Arc::clone(&d_mat_arc).as_ref().inner,
+                            &tp2_cols,
+                            n,
+                            depth,
+                            k_res,
+                            end_col - start_col,
+                            c,
+                            s,
+                            dgg_stddev,
+                        )
+                    })?;
                     debug_mem("SampleP1ForPertSquareMat called");
-                    DCRTPolyMatrix::from_cpp_matrix_ptr(params, &CppMatrix::new(params, cpp_matrix))
+                    let cpp_matrix = CppMatrix::new(params, call, cpp_matrix)?;
+                    DCRTPolyMatrix::from_cpp_matrix_ptr(params, &cpp_matrix)
                 })
-                .collect::<Vec<_>>();
-            p1_blocks[0].concat_columns(&p1_blocks[1..].iter().collect::<Vec<_>>())
+                .collect::<Result<Vec<_>, DiamondError>>()?;
+            Ok(p1_blocks[0].concat_columns(&p1_blocks[1..].iter().collect::<Vec<_>>()))
         })
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>, DiamondError>>()?;
+
+    Ok(p1_mat_blocks[0].concat_columns(&p1_mat_blocks[1..].iter().collect::<Vec<_>>()))
+}
 
-    p1_mat_blocks[0].concat_columns(&p1_mat_blocks[1..].iter().collect::<Vec<_>>())
+/// Copies `matrix` to OpenFHE and switches its entries to the coefficient representation.
+fn to_coefficient_format(matrix: &DCRTPolyMatrix) -> Result<CppMatrix, DiamondError> {
+    let mut cpp_matrix = matrix.to_cpp_matrix_ptr()?;
+    ffi_guard::guard("FormatMatrixCoefficient", || {
+        FormatMatrixCoefficient(cpp_matrix.inner.pin_mut())
+    })?;
+    Ok(cpp_matrix)
 }
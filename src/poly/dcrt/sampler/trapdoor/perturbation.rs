@@ -0,0 +1,276 @@
+//! Perturbations for preimages under a delegated trapdoor `R`.
+//!
+//! A preimage `(p - R * z; z)` is spherical, and thus independent of `R`, only if the
+//! perturbation `p` has the covariance `s^2 I - c^2 R R^*`, where `c` is the width of the gadget
+//! preimage `z` (MP12, Section 5.4). Multiplication by a ring element is diagonal in the
+//! canonical embedding, so this covariance splits into one Hermitian matrix
+//! `s^2 I - c^2 R_j R_j^*` per root `ζ_j`, where `R_j` holds the evaluations of the entries of
+//! `R` at `ζ_j`. Each is factored by Cholesky, a continuous Gaussian with that factor is mapped
+//! back to coefficients, and the coefficients are rounded by a discrete Gaussian of width
+//! [`ROUNDING_SIGMA`], whose variance is taken out of `s^2` beforehand.
+use crate::{
+    parallel_iter,
+    poly::{
+        dcrt::{DCRTPoly, DCRTPolyMatrix, DCRTPolyParams, FinRingElem},
+        norms::centered_f64,
+        plaintext::modulus_biguint,
+        rng::CryptoRngSource,
+        Poly, PolyElem, PolyMatrix, PolyParams,
+    },
+};
+use rand::Rng;
+use rand_distr::StandardNormal;
+use rayon::prelude::*;
+use std::{
+    f64::consts::{FRAC_1_SQRT_2, PI},
+    ops::{Add, Mul, Sub},
+};
+
+/// The width of the discrete Gaussian rounding the continuous perturbation to the integers.
+const ROUNDING_SIGMA: f64 = 4.578;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    fn from_polar(abs: f64, angle: f64) -> Self {
+        Self::new(abs * angle.cos(), abs * angle.sin())
+    }
+
+    fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    fn scale(self, factor: f64) -> Self {
+        Self::new(self.re * factor, self.im * factor)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(self.re * rhs.re - self.im * rhs.im, self.re * rhs.im + self.im * rhs.re)
+    }
+}
+
+/// Computes `Σ_k values[k] * exp(±2πi jk/n)` in place for a power of two `n`, with the minus
+/// sign if `inverse`.
+fn fft(values: &mut [Complex], inverse: bool) {
+    let n = values.len();
+    debug_assert!(n.is_power_of_two());
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+    let sign = if inverse { -1.0 } else { 1.0 };
+    let mut len = 2;
+    while len <= n {
+        let step = Complex::from_polar(1.0, sign * 2.0 * PI / len as f64);
+        for chunk in values.chunks_mut(len) {
+            let mut w = Complex::new(1.0, 0.0);
+            for i in 0..len / 2 {
+                let u = chunk[i];
+                let v = chunk[i + len / 2] * w;
+                chunk[i] = u + v;
+                chunk[i + len / 2] = u - v;
+                w = w * step;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// The evaluations `a(ζ_j)` at the roots `ζ_j = exp(iπ(2j + 1)/n)` of `X^n + 1`, where
+/// `ζ_{n-1-j}` is the conjugate of `ζ_j`.
+fn embed(coeffs: &[f64]) -> Vec<Complex> {
+    let n = coeffs.len();
+    let mut values = coeffs
+        .iter()
+        .enumerate()
+        .map(|(k, &coeff)| Complex::from_polar(coeff, PI * k as f64 / n as f64))
+        .collect::<Vec<_>>();
+    fft(&mut values, false);
+    values
+}
+
+/// The real coefficients of the polynomial with the evaluations `values`, which must be
+/// conjugate symmetric.
+fn unembed(mut values: Vec<Complex>) -> Vec<f64> {
+    let n = values.len();
+    fft(&mut values, true);
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(k, value)| {
+            let twist = Complex::from_polar(1.0, -PI * k as f64 / n as f64);
+            (value * twist).re / n as f64
+        })
+        .collect()
+}
+
+/// The lower triangular `L` with `L L^* = sigma`, or `None` if `sigma` is not positive definite.
+fn cholesky(sigma: &[Vec<Complex>]) -> Option<Vec<Vec<Complex>>> {
+    let m = sigma.len();
+    let mut l = vec![vec![Complex::default(); m]; m];
+    for i in 0..m {
+        let norm = (0..i).map(|t| l[i][t].re.powi(2) + l[i][t].im.powi(2)).sum::<f64>();
+        let diag = sigma[i][i].re - norm;
+        if diag <= 0.0 {
+            return None;
+        }
+        let diag = diag.sqrt();
+        l[i][i] = Complex::new(diag, 0.0);
+        for j in i + 1..m {
+            let dot = (0..i).fold(Complex::default(), |acc, t| acc + l[j][t] * l[i][t].conj());
+            l[j][i] = (sigma[j][i] - dot).scale(1.0 / diag);
+        }
+    }
+    Some(l)
+}
+
+/// Samples an integer from the discrete Gaussian of width `sigma` around `center`.
+fn round_gaussian<R: Rng>(rng: &mut R, center: f64, sigma: f64) -> i64 {
+    let tail = (12.0 * sigma).ceil();
+    let (low, high) = ((center - tail).floor() as i64, (center + tail).ceil() as i64);
+    loop {
+        let z = rng.random_range(low..=high);
+        let distance = z as f64 - center;
+        if rng.random::<f64>() < (-distance * distance / (2.0 * sigma * sigma)).exp() {
+            return z;
+        }
+    }
+}
+
+/// Samples `ncol` perturbations with the covariance `s^2 I - c^2 R R^*`, one per column.
+/// Panics unless `s` exceeds `c * s1(R)`, which the width of a delegated trapdoor ensures.
+pub(crate) fn sample_delegated_perturbation(
+    params: &DCRTPolyParams,
+    r: &DCRTPolyMatrix,
+    s: f64,
+    c: f64,
+    ncol: usize,
+    source: &dyn CryptoRngSource,
+) -> DCRTPolyMatrix {
+    let n = params.ring_dimension() as usize;
+    let (m, l) = r.size();
+    let q = modulus_biguint::<DCRTPoly>(params);
+    // r_slots[i][t][j] = R[i][t](ζ_j)
+    let r_slots = parallel_iter!(0..m)
+        .map(|i| {
+            r.get_row(i)
+                .iter()
+                .map(|entry| {
+                    let coeffs = entry.coeffs();
+                    let coeffs =
+                        coeffs.iter().map(|c| centered_f64(c.to_biguint(), &q)).collect::<Vec<_>>();
+                    embed(&coeffs)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let variance = s * s - ROUNDING_SIGMA * ROUNDING_SIGMA;
+    let factors = parallel_iter!(0..n / 2)
+        .map(|j| {
+            let sigma = (0..m)
+                .map(|a| {
+                    (0..m)
+                        .map(|b| {
+                            let dot = (0..l).fold(Complex::default(), |acc, t| {
+                                acc + r_slots[a][t][j] * r_slots[b][t][j].conj()
+                            });
+                            let diag = if a == b { variance } else { 0.0 };
+                            Complex::new(diag, 0.0) - dot.scale(c * c)
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            cholesky(&sigma).expect("the perturbation width does not dominate c * s1(R)")
+        })
+        .collect::<Vec<_>>();
+
+    // A complex Gaussian of covariance `n * Σ_j` per root maps to real coefficients of
+    // covariance `Σ`.
+    let scale = FRAC_1_SQRT_2 * (n as f64).sqrt();
+    let streams = source.streams();
+    let columns = parallel_iter!(0..ncol)
+        .map(|col| {
+            let mut rng = streams.rng(col, 0);
+            let mut slots = vec![vec![Complex::default(); n]; m];
+            for (j, factor) in factors.iter().enumerate() {
+                let w = (0..m)
+                    .map(|_| {
+                        let re: f64 = rng.sample(StandardNormal);
+                        let im: f64 = rng.sample(StandardNormal);
+                        Complex::new(re, im).scale(scale)
+                    })
+                    .collect::<Vec<_>>();
+                for i in 0..m {
+                    let y = (0..=i).fold(Complex::default(), |acc, t| acc + factor[i][t] * w[t]);
+                    slots[i][j] = y;
+                    slots[i][n - 1 - j] = y.conj();
+                }
+            }
+            slots
+                .into_iter()
+                .map(|row| {
+                    let coeffs = unembed(row)
+                        .into_iter()
+                        .map(|x| {
+                            let value = round_gaussian(&mut rng, x, ROUNDING_SIGMA);
+                            FinRingElem::from_int64(value, params.modulus())
+                        })
+                        .collect::<Vec<_>>();
+                    DCRTPoly::from_coeffs(params, &coeffs)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    DCRTPolyMatrix::from_fn(params, m, ncol, |i, j| columns[j][i].clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embed_roundtrip() {
+        // X evaluates to ζ_j, and the evaluations map back to the coefficients
+        let coeffs = [0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+        let values = embed(&coeffs);
+        for (j, value) in values.iter().enumerate() {
+            let root = Complex::from_polar(1.0, PI * (2 * j + 1) as f64 / 8.0);
+            assert!((value.re - root.re).abs() < 1e-9 && (value.im - root.im).abs() < 1e-9);
+            assert!((values[7 - j].re - value.re).abs() < 1e-9);
+        }
+        let coeffs = [3.0, -1.0, 4.0, 1.0, -5.0, 9.0, 2.0, -6.0];
+        let back = unembed(embed(&coeffs));
+        assert!(back.iter().zip(coeffs).all(|(a, b)| (a - b).abs() < 1e-9));
+    }
+}
@@ -0,0 +1,202 @@
+//! Trapdoor generation without any single party knowing the trapdoor.
+//!
+//! The trapdoor columns `G - (A_bar * R + E)` are linear in `(R, E)`, so each shareholder
+//! samples its own Gaussian share `(R_i, E_i)` and only publishes `A_bar * R_i + E_i`. The sum of
+//! the contributions is the public matrix of the trapdoor `(sum R_i, sum E_i)`, whose Gaussian
+//! width is `sqrt(shareholders)` times that of a share. Preimage sampling needs an interactive
+//! protocol between the shareholders, which deployments plug in as a [`PreimageProtocol`].
+use super::{sampler::public_matrix_from_parts, DCRTTrapdoor};
+use crate::poly::{
+    dcrt::{DCRTPolyMatrix, DCRTPolyParams, DCRTPolyUniformSampler},
+    sampler::{DistType, PolyUniformSampler, TrapdoorProvider},
+    PolyMatrix,
+};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A party holding one share of a trapdoor, e.g. a client of a remote MPC node.
+pub trait TrapdoorShareholder: Send + Sync {
+    /// Samples and keeps a share `(R_i, E_i)` of the trapdoor of `session` and returns
+    /// `A_bar * R_i + E_i`.
+    fn contribute(
+        &self,
+        params: &DCRTPolyParams,
+        session: &[u8],
+        a_bar: &DCRTPolyMatrix,
+    ) -> DCRTPolyMatrix;
+}
+
+/// Runs the interactive preimage sampling between the shareholders of a trapdoor.
+pub trait PreimageProtocol: Send + Sync {
+    fn preimage(
+        &self,
+        params: &DCRTPolyParams,
+        session: &[u8],
+        public_matrix: &DCRTPolyMatrix,
+        target: &DCRTPolyMatrix,
+    ) -> DCRTPolyMatrix;
+}
+
+/// Identifies a shared trapdoor towards its shareholders.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedTrapdoorHandle {
+    pub session: Vec<u8>,
+}
+
+/// A [`TrapdoorProvider`] whose trapdoors are the sums of the shares of its shareholders.
+pub struct SharedTrapdoorProvider {
+    shareholders: Vec<Box<dyn TrapdoorShareholder>>,
+    protocol: Box<dyn PreimageProtocol>,
+    session_prefix: Vec<u8>,
+    next_session: AtomicU64,
+}
+
+impl SharedTrapdoorProvider {
+    /// Names the trapdoors it generates `session_prefix` followed by a counter of the session.
+    pub fn new(
+        shareholders: Vec<Box<dyn TrapdoorShareholder>>,
+        protocol: Box<dyn PreimageProtocol>,
+        session_prefix: &[u8],
+    ) -> Self {
+        assert!(!shareholders.is_empty(), "at least one shareholder is required");
+        Self {
+            shareholders,
+            protocol,
+            session_prefix: session_prefix.to_vec(),
+            next_session: AtomicU64::new(0),
+        }
+    }
+
+    fn next_session(&self) -> Vec<u8> {
+        let counter = self.next_session.fetch_add(1, Ordering::Relaxed);
+        let mut session = self.session_prefix.clone();
+        session.extend_from_slice(&counter.to_le_bytes());
+        session
+    }
+}
+
+impl TrapdoorProvider for SharedTrapdoorProvider {
+    type M = DCRTPolyMatrix;
+    type Handle = SharedTrapdoorHandle;
+
+    fn trapdoor(&self, params: &DCRTPolyParams, size: usize) -> (Self::Handle, Self::M) {
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let a_bar = uniform_sampler.sample_uniform(params, size, size, DistType::FinRingDist);
+        let session = self.next_session();
+        let combined = self
+            .shareholders
+            .iter()
+            .map(|shareholder| shareholder.contribute(params, &session, &a_bar))
+            .reduce(|acc, contribution| acc + contribution)
+            .unwrap();
+        let g = DCRTPolyMatrix::gadget_matrix(params, size);
+        let a = public_matrix_from_parts(params, &a_bar, &(g - combined));
+        (SharedTrapdoorHandle { session }, a)
+    }
+
+    fn preimage(
+        &self,
+        params: &DCRTPolyParams,
+        handle: &Self::Handle,
+        public_matrix: &Self::M,
+        target: &Self::M,
+    ) -> Self::M {
+        self.protocol.preimage(params, &handle.session, public_matrix, target)
+    }
+}
+
+/// Returns `A_bar * R_i + E_i` for a share, for shareholders running in this process.
+pub fn share_contribution(share: &DCRTTrapdoor, a_bar: &DCRTPolyMatrix) -> DCRTPolyMatrix {
+    a_bar * &share.r + &share.e
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::{
+        dcrt::DCRTPolyTrapdoorSampler, dims::trapdoor_width, sampler::PolyTrapdoorSampler,
+        PolyParams,
+    };
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    const SIGMA: f64 = 4.578;
+
+    /// Keeps the shares in memory and reveals them to the test, which a real shareholder never
+    /// does.
+    #[derive(Default)]
+    struct LocalShareholder {
+        shares: Mutex<HashMap<Vec<u8>, DCRTTrapdoor>>,
+    }
+
+    impl TrapdoorShareholder for Arc<LocalShareholder> {
+        fn contribute(
+            &self,
+            params: &DCRTPolyParams,
+            session: &[u8],
+            a_bar: &DCRTPolyMatrix,
+        ) -> DCRTPolyMatrix {
+            let share = DCRTTrapdoor::new(params, a_bar.row_size(), SIGMA);
+            let contribution = share_contribution(&share, a_bar);
+            self.shares.lock().unwrap().insert(session.to_vec(), share);
+            contribution
+        }
+    }
+
+    /// Reconstructs the trapdoor, standing in for an MPC.
+    struct ReconstructingProtocol {
+        shareholders: Vec<Arc<LocalShareholder>>,
+    }
+
+    impl PreimageProtocol for ReconstructingProtocol {
+        fn preimage(
+            &self,
+            params: &DCRTPolyParams,
+            session: &[u8],
+            public_matrix: &DCRTPolyMatrix,
+            target: &DCRTPolyMatrix,
+        ) -> DCRTPolyMatrix {
+            let shares = self
+                .shareholders
+                .iter()
+                .map(|shareholder| shareholder.shares.lock().unwrap()[session].clone())
+                .collect::<Vec<_>>();
+            let trapdoor = shares[1..].iter().fold(shares[0].clone(), |acc, share| DCRTTrapdoor {
+                r: acc.r + &share.r,
+                e: acc.e + &share.e,
+            });
+            let sigma = SIGMA * (shares.len() as f64).sqrt();
+            let sampler = DCRTPolyTrapdoorSampler::new(params, sigma);
+            PolyTrapdoorSampler::preimage(&sampler, params, &trapdoor, public_matrix, target)
+        }
+    }
+
+    #[test]
+    fn test_shared_trapdoor_provider() {
+        let params = DCRTPolyParams::default();
+        let shareholders =
+            (0..3).map(|_| Arc::new(LocalShareholder::default())).collect::<Vec<_>>();
+        let boxed = shareholders
+            .iter()
+            .map(|shareholder| Box::new(shareholder.clone()) as Box<dyn TrapdoorShareholder>)
+            .collect();
+        let protocol = ReconstructingProtocol { shareholders: shareholders.clone() };
+        let provider = SharedTrapdoorProvider::new(boxed, Box::new(protocol), b"test");
+
+        // The public matrix has the shape of a local trapdoor
+        let size = 2;
+        let (handle, public_matrix) = provider.trapdoor(&params, size);
+        let k = params.modulus_digits();
+        assert_eq!(public_matrix.size(), (size, trapdoor_width(size, k)));
+        for shareholder in shareholders.iter() {
+            assert!(shareholder.shares.lock().unwrap().contains_key(&handle.session));
+        }
+
+        // Preimages of the combined trapdoor are preimages of the public matrix
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let target = uniform_sampler.sample_uniform(&params, size, 1, DistType::FinRingDist);
+        let preimage = provider.preimage(&params, &handle, &public_matrix, &target);
+        assert_eq!(public_matrix * &preimage, target);
+    }
+}
@@ -1,19 +1,27 @@
-use super::{utils::split_int64_mat_alt_to_elems, DCRTTrapdoor};
+use super::{
+    perturbation::sample_delegated_perturbation, utils::split_int64_mat_alt_to_elems, DCRTTrapdoor,
+    DelegatedTrapdoor,
+};
 use crate::{
+    error::DiamondError,
     parallel_iter,
     poly::{
         dcrt::{
-            sampler::{trapdoor::KARNEY_THRESHOLD, DCRTPolyUniformSampler},
+            ffi_guard::{self, FirstFailure},
+            sampler::DCRTPolyUniformSampler,
             DCRTPoly, DCRTPolyMatrix, DCRTPolyParams,
         },
-        sampler::{DistType, PolyTrapdoorSampler, PolyUniformSampler},
+        dims::trapdoor_width,
+        rng::{default_source, CryptoRngSource},
+        sampler::{DistType, PolyHashSampler, PolyTrapdoorSampler, PolyUniformSampler},
+        sampling::KARNEY_THRESHOLD,
         Poly, PolyMatrix, PolyParams,
     },
-    utils::{debug_mem, log_mem},
+    utils::{debug_mem, log_mem, parallelism_config},
 };
 use openfhe::ffi::DCRTGaussSampGqArbBase;
 use rayon::iter::ParallelIterator;
-use std::ops::Range;
+use std::{ops::Range, sync::Arc};
 
 const SIGMA: f64 = 4.578;
 const SPECTRAL_CONSTANT: f64 = 1.8;
@@ -22,6 +30,7 @@ pub struct DCRTPolyTrapdoorSampler {
     sigma: f64,
     base: u32,
     c: f64,
+    source: Arc<dyn CryptoRngSource>,
 }
 
 impl PolyTrapdoorSampler for DCRTPolyTrapdoorSampler {
@@ -34,7 +43,14 @@ impl PolyTrapdoorSampler for DCRTPolyTrapdoorSampler {
     ) -> Self {
         let base = 1 << params.base_bits();
         let c = (base as f64 + 1.0) * SIGMA;
-        Self { sigma, base, c }
+        Self { sigma, base, c, source: default_source() }
+    }
+
+    /// Draws trapdoors and perturbations from `source`. The perturbation `p1` and the gadget
+    /// preimages are still sampled inside OpenFHE, which keeps its own generator.
+    fn with_source(mut self, source: Arc<dyn CryptoRngSource>) -> Self {
+        self.source = source;
+        self
     }
 
     fn trapdoor(
@@ -42,13 +58,11 @@ impl PolyTrapdoorSampler for DCRTPolyTrapdoorSampler {
         params: &<<Self::M as crate::poly::PolyMatrix>::P as crate::poly::Poly>::Params,
         size: usize,
     ) -> (Self::Trapdoor, Self::M) {
-        let trapdoor = DCRTTrapdoor::new(params, size, self.sigma);
-        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let trapdoor = DCRTTrapdoor::with_source(params, size, self.sigma, self.source.clone());
+        let uniform_sampler = DCRTPolyUniformSampler::with_source(self.source.clone());
         let a_bar = uniform_sampler.sample_uniform(params, size, size, DistType::FinRingDist);
-        let g = DCRTPolyMatrix::gadget_matrix(params, size);
-        let a0 = a_bar.concat_columns(&[&DCRTPolyMatrix::identity(params, size, None)]);
-        let a1 = g - (a_bar * &trapdoor.r + &trapdoor.e);
-        let a = a0.concat_columns(&[&a1]);
+        let a1 = trapdoor_columns(params, &trapdoor, &a_bar);
+        let a = public_matrix_from_parts(params, &a_bar, &a1);
         (trapdoor, a)
     }
 
@@ -59,6 +73,61 @@ impl PolyTrapdoorSampler for DCRTPolyTrapdoorSampler {
         public_matrix: &Self::M,
         target: &Self::M,
     ) -> Self::M {
+        ffi_guard::expect_ffi(self.try_preimage(params, trapdoor, public_matrix, target))
+    }
+}
+
+/// A public matrix `(A_bar | I | G - (A_bar * R + E))` whose uniform block `A_bar` is derived
+/// from a seed by a hash sampler, so that only the trapdoor-dependent columns are stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedAPublicMatrix {
+    pub seed: [u8; 32],
+    /// The columns `G - (A_bar * R + E)`.
+    pub trapdoor_columns: DCRTPolyMatrix,
+}
+
+impl SharedAPublicMatrix {
+    const TAG: &'static [u8] = b"TRAPDOOR_SHARED_A";
+
+    pub fn size(&self) -> usize {
+        self.trapdoor_columns.row_size()
+    }
+
+    /// Re-derives `A_bar = H(seed)`.
+    pub fn a_bar<S: PolyHashSampler<[u8; 32], M = DCRTPolyMatrix>>(
+        &self,
+        params: &DCRTPolyParams,
+    ) -> DCRTPolyMatrix {
+        Self::hash_a_bar::<S>(params, self.seed, self.size())
+    }
+
+    fn hash_a_bar<S: PolyHashSampler<[u8; 32], M = DCRTPolyMatrix>>(
+        params: &DCRTPolyParams,
+        seed: [u8; 32],
+        size: usize,
+    ) -> DCRTPolyMatrix {
+        S::new().sample_hash(params, seed, Self::TAG, size, size, DistType::FinRingDist)
+    }
+
+    /// Re-derives the full public matrix, which equals the one [`PolyTrapdoorSampler::trapdoor`]
+    /// would return for the same trapdoor and `A_bar`.
+    pub fn expand<S: PolyHashSampler<[u8; 32], M = DCRTPolyMatrix>>(
+        &self,
+        params: &DCRTPolyParams,
+    ) -> DCRTPolyMatrix {
+        public_matrix_from_parts(params, &self.a_bar::<S>(params), &self.trapdoor_columns)
+    }
+}
+
+impl DCRTPolyTrapdoorSampler {
+    /// [`PolyTrapdoorSampler::preimage`], failing if OpenFHE does.
+    pub fn try_preimage(
+        &self,
+        params: &DCRTPolyParams,
+        trapdoor: &DCRTTrapdoor,
+        public_matrix: &DCRTPolyMatrix,
+        target: &DCRTPolyMatrix,
+    ) -> Result<DCRTPolyMatrix, DiamondError> {
         let d = public_matrix.row_size();
         let target_cols = target.col_size();
         assert_eq!(
@@ -67,13 +136,8 @@ impl PolyTrapdoorSampler for DCRTPolyTrapdoorSampler {
             "Target matrix should have the same number of rows as the public matrix"
         );
 
-        let n = params.ring_dimension() as usize;
         let k = params.modulus_digits();
-        let s = SPECTRAL_CONSTANT *
-            (self.base as f64 + 1.0) *
-            SIGMA *
-            SIGMA *
-            (((d * n * k) as f64).sqrt() + ((2 * n) as f64).sqrt() + 4.7);
+        let s = self.preimage_sigma(params, d);
         let dgg_large_std = (s * s - self.c * self.c).sqrt();
         let peikert = dgg_large_std < KARNEY_THRESHOLD;
         let (dgg_large_mean, dgg_large_table) = if dgg_large_std > KARNEY_THRESHOLD {
@@ -106,27 +170,212 @@ impl PolyTrapdoorSampler for DCRTPolyTrapdoorSampler {
             dgg_large_params,
             peikert,
             target_cols,
-        );
+            &*self.source,
+        )?;
         log_mem("p_hat generated");
         let perturbed_syndrome = target - &(public_matrix * &p_hat);
         debug_mem("perturbed_syndrome generated");
+        let z_hat_mat = self.gadget_preimage(params, &perturbed_syndrome)?;
+        log_mem("z_hat_mat generated");
+        let r_z_hat = &trapdoor.r * &z_hat_mat;
+        debug_mem("r_z_hat generated");
+        let e_z_hat = &trapdoor.e * &z_hat_mat;
+        debug_mem("e_z_hat generated");
+        let z_hat_former = (p_hat.slice_rows(0, d) + r_z_hat)
+            .concat_rows(&[&(p_hat.slice_rows(d, 2 * d) + e_z_hat)]);
+        let z_hat_latter = p_hat.slice_rows(2 * d, trapdoor_width(d, k)) + z_hat_mat;
+        log_mem("z_hat generated");
+        Ok(z_hat_former.concat_rows(&[&z_hat_latter]))
+    }
+
+
+    /// Samples a trapdoor like [`PolyTrapdoorSampler::trapdoor`] with `A_bar = H(seed)`, so that
+    /// the uniform `size x size` block and the identity block need not be published. The
+    /// trapdoor-dependent `size x size * k` columns still have to be stored.
+    pub fn trapdoor_with_shared_a<S: PolyHashSampler<[u8; 32], M = DCRTPolyMatrix>>(
+        &self,
+        params: &DCRTPolyParams,
+        seed: [u8; 32],
+        size: usize,
+    ) -> (DCRTTrapdoor, SharedAPublicMatrix) {
+        let trapdoor = DCRTTrapdoor::with_source(params, size, self.sigma, self.source.clone());
+        let a_bar = SharedAPublicMatrix::hash_a_bar::<S>(params, seed, size);
+        let trapdoor_columns = trapdoor_columns(params, &trapdoor, &a_bar);
+        (trapdoor, SharedAPublicMatrix { seed, trapdoor_columns })
+    }
+
+    /// Samples a short `X` with `(A | B) * X = target` given a trapdoor of `A` and an arbitrary
+    /// `B` of the same row size (SampleLeft). The rows of `X` for `B` are Gaussian with the
+    /// width of [`PolyTrapdoorSampler::preimage`] and the rows for `A` are a trapdoor preimage.
+    pub fn sample_left(
+        &self,
+        params: &DCRTPolyParams,
+        trapdoor: &DCRTTrapdoor,
+        public_matrix: &DCRTPolyMatrix,
+        b: &DCRTPolyMatrix,
+        target: &DCRTPolyMatrix,
+    ) -> Result<DCRTPolyMatrix, DiamondError> {
+        let d = public_matrix.row_size();
+        assert_eq!(b.row_size(), d, "B should have the same number of rows as the public matrix");
+        let s = self.preimage_sigma(params, d);
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let x_b = uniform_sampler.try_sample_uniform(
+            params,
+            b.col_size(),
+            target.col_size(),
+            DistType::GaussDist { sigma: s },
+        )?;
+        let x_a = self.try_preimage(params, trapdoor, public_matrix, &(target - &(b * &x_b)))?;
+        Ok(x_a.concat_rows(&[&x_b]))
+    }
+
+    /// Samples a short `X` with `(A | A * R + G) * X = target` given only the short matrix `R`
+    /// (SampleRight), where `G` is the gadget matrix of `A.row_size()` rows. `X` stacks
+    /// `p - R * z` on top of `z` for a Gaussian perturbation `p` and a gadget preimage `z` of
+    /// `target - A * p`; its distribution hides `R` only if the sampler's width dominates the
+    /// spectral norm of `R` times the width of `z`. [`Self::preimage_delegated`] corrects the
+    /// covariance of the perturbation for trapdoors that do not meet this.
+    pub fn sample_right(
+        &self,
+        params: &DCRTPolyParams,
+        a: &DCRTPolyMatrix,
+        r: &DCRTPolyMatrix,
+        target: &DCRTPolyMatrix,
+    ) -> Result<DCRTPolyMatrix, DiamondError> {
+        let d = a.row_size();
+        assert_eq!(r.row_size(), a.col_size(), "R should have as many rows as A has columns");
+        assert_eq!(r.col_size(), d * params.modulus_digits(), "R should have as many columns as G");
+        let s = self.preimage_sigma(params, d);
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let p = uniform_sampler.try_sample_uniform(
+            params,
+            a.col_size(),
+            target.col_size(),
+            DistType::GaussDist { sigma: s },
+        )?;
+        let z = self.gadget_preimage(params, &(target - &(a * &p)))?;
+        Ok((p - &(r * &z)).concat_rows(&[&z]))
+    }
+
+    /// Delegates a trapdoor of `public_matrix = A` to `(A | extension_matrix)` for an arbitrary
+    /// `extension_matrix = A1` with as many columns as the gadget matrix, e.g. the matrix of a
+    /// child identity in a hierarchy, by sampling a preimage `R` of `G - A1`.
+    pub fn delegate(
+        &self,
+        params: &DCRTPolyParams,
+        trapdoor: &DCRTTrapdoor,
+        public_matrix: &DCRTPolyMatrix,
+        extension_matrix: &DCRTPolyMatrix,
+    ) -> Result<DelegatedTrapdoor, DiamondError> {
+        let g = delegation_target(params, public_matrix, extension_matrix);
+        let r = self.try_preimage(params, trapdoor, public_matrix, &g)?;
+        let r_sigma = self.preimage_sigma(params, public_matrix.row_size());
+        let sigma = self.delegated_sigma(params, &r, r_sigma);
+        Ok(DelegatedTrapdoor { r, sigma })
+    }
+
+    /// Delegates a delegated trapdoor of `public_matrix = (A | A1)` further to
+    /// `(A | A1 | extension_matrix)`, one level down a delegation chain.
+    ///
+    /// The new `R` is a preimage of width `σ_i`, the width of the parent trapdoor, so that its
+    /// spectral norm is about `1.8 * σ_i * (sqrt(m_i * n) + sqrt(d * k * n))` for a parent of
+    /// `m_i` columns, and the width of its preimages `σ_{i+1}` is that norm times `4.578 * c`.
+    /// Every level thus multiplies the width of preimages, and of the next trapdoor, by about
+    /// `8.2 * c * (sqrt(m_i * n) + sqrt(d * k * n))`, which bounds the depth of a chain for a
+    /// given modulus.
+    pub fn delegate_further(
+        &self,
+        params: &DCRTPolyParams,
+        trapdoor: &DelegatedTrapdoor,
+        public_matrix: &DCRTPolyMatrix,
+        extension_matrix: &DCRTPolyMatrix,
+    ) -> Result<DelegatedTrapdoor, DiamondError> {
+        let g = delegation_target(params, public_matrix, extension_matrix);
+        let r = self.preimage_delegated(params, trapdoor, public_matrix, &g)?;
+        let sigma = self.delegated_sigma(params, &r, trapdoor.sigma);
+        Ok(DelegatedTrapdoor { r, sigma })
+    }
+
+    /// Samples a short `X` with `public_matrix * X = target` given a delegated trapdoor of
+    /// `public_matrix = (A | A1)`. Since `A1 = A * (-R) + G`, this is [`Self::sample_right`]
+    /// for `-R`, except that the perturbation has the covariance `σ^2 I - c^2 R R^*` for the
+    /// width `σ` of the trapdoor, so that `X` is spherical and does not leak `R`.
+    pub fn preimage_delegated(
+        &self,
+        params: &DCRTPolyParams,
+        trapdoor: &DelegatedTrapdoor,
+        public_matrix: &DCRTPolyMatrix,
+        target: &DCRTPolyMatrix,
+    ) -> Result<DCRTPolyMatrix, DiamondError> {
+        let parent_cols = trapdoor.r.row_size();
+        assert_eq!(
+            public_matrix.col_size(),
+            parent_cols + trapdoor.r.col_size(),
+            "the public matrix is not an extension the trapdoor was delegated to"
+        );
+        let a = public_matrix.slice_columns(0, parent_cols);
+        let r = -trapdoor.r.clone();
+        let p = sample_delegated_perturbation(
+            params,
+            &r,
+            trapdoor.sigma,
+            self.c,
+            target.col_size(),
+            &*self.source,
+        );
+        let z = self.gadget_preimage(params, &(target - &(&a * &p)))?;
+        Ok((p - &(&r * &z)).concat_rows(&[&z]))
+    }
+
+    /// The width of preimages under a delegated trapdoor `r` sampled with width `r_sigma`:
+    /// `4.578 * c * sqrt(s1(R)^2 + 1)`, where the spectral norm `s1(R)` is estimated like the
+    /// one of the trapdoor in [`Self::preimage_sigma`].
+    fn delegated_sigma(&self, params: &DCRTPolyParams, r: &DCRTPolyMatrix, r_sigma: f64) -> f64 {
+        let n = params.ring_dimension() as usize;
+        let (rows, cols) = r.size();
+        let s1 = SPECTRAL_CONSTANT *
+            r_sigma *
+            (((rows * n) as f64).sqrt() + ((cols * n) as f64).sqrt());
+        SIGMA * self.c * (s1 * s1 + 1.0).sqrt()
+    }
+
+    /// The Gaussian width of preimages of a public matrix with `d` rows.
+    fn preimage_sigma(&self, params: &DCRTPolyParams, d: usize) -> f64 {
+        let n = params.ring_dimension() as usize;
+        let k = params.modulus_digits();
+        SPECTRAL_CONSTANT *
+            (self.base as f64 + 1.0) *
+            SIGMA *
+            SIGMA *
+            (((d * n * k) as f64).sqrt() + ((2 * n) as f64).sqrt() + 4.7)
+    }
+
+    /// Samples a short `Z` with `G * Z = syndrome` entry by entry.
+    fn gadget_preimage(
+        &self,
+        params: &DCRTPolyParams,
+        syndrome: &DCRTPolyMatrix,
+    ) -> Result<DCRTPolyMatrix, DiamondError> {
+        let (d, target_cols) = syndrome.size();
+        let k = params.modulus_digits();
         let mut z_hat_mat = DCRTPolyMatrix::zero(params, d * k, target_cols);
+        let failures = FirstFailure::new();
         let f = |row_offsets: Range<usize>, col_offsets: Range<usize>| -> Vec<Vec<DCRTPoly>> {
             let nrow = row_offsets.len();
             let ncol = col_offsets.len();
-            let perturbed_syndromes = perturbed_syndrome.block_entries(row_offsets, col_offsets);
+            let syndromes = syndrome.block_entries(row_offsets, col_offsets);
             let decomposed_results = parallel_iter!(0..nrow)
                 .map(|i| {
                     let row_results: Vec<_> = parallel_iter!(0..ncol)
                         .map(|j| {
                             let decomposed = decompose_dcrt_gadget(
-                                &perturbed_syndromes[i][j],
+                                &syndromes[i][j],
                                 self.c,
                                 params,
                                 self.base,
                                 self.sigma,
                             );
-                            (i, j, decomposed)
+                            (i, j, failures.take(decomposed))
                         })
                         .collect();
                     row_results
@@ -136,7 +385,7 @@ impl PolyTrapdoorSampler for DCRTPolyTrapdoorSampler {
 
             let mut block_matrix = vec![vec![DCRTPoly::const_zero(params); ncol]; k * nrow];
             for (i, j, decomposed) in decomposed_results {
-                debug_assert_eq!(decomposed[0].len(), 1);
+                debug_assert!(decomposed.iter().all(|vec| vec.len() == 1));
                 for (decomposed_idx, vec) in decomposed.iter().enumerate() {
                     block_matrix[i * k + decomposed_idx][j] = vec[0].clone();
                 }
@@ -144,16 +393,8 @@ impl PolyTrapdoorSampler for DCRTPolyTrapdoorSampler {
             block_matrix
         };
         z_hat_mat.replace_entries_with_expand(0..d, 0..target_cols, k, 1, f);
-        log_mem("z_hat_mat generated");
-        let r_z_hat = &trapdoor.r * &z_hat_mat;
-        debug_mem("r_z_hat generated");
-        let e_z_hat = &trapdoor.e * &z_hat_mat;
-        debug_mem("e_z_hat generated");
-        let z_hat_former = (p_hat.slice_rows(0, d) + r_z_hat)
-            .concat_rows(&[&(p_hat.slice_rows(d, 2 * d) + e_z_hat)]);
-        let z_hat_latter = p_hat.slice_rows(2 * d, d * (k + 2)) + z_hat_mat;
-        log_mem("z_hat generated");
-        z_hat_former.concat_rows(&[&z_hat_latter])
+        failures.into_result()?;
+        Ok(z_hat_mat)
     }
 }
 
@@ -163,12 +404,17 @@ pub(crate) fn decompose_dcrt_gadget(
     params: &DCRTPolyParams,
     base: u32,
     sigma: f64,
-) -> Vec<Vec<DCRTPoly>> {
+) -> Result<Vec<Vec<DCRTPoly>>, DiamondError> {
     let depth = params.crt_depth();
-    let z_hat_bbi = parallel_iter!(0..depth)
-        .flat_map(|tower_idx| gauss_samp_gq_arb_base(syndrome, c, params, base, sigma, tower_idx))
-        .collect::<Vec<_>>();
-    split_int64_mat_alt_to_elems(&z_hat_bbi, params)
+    let sample_tower =
+        |tower_idx| gauss_samp_gq_arb_base(syndrome, c, params, base, sigma, tower_idx);
+    let towers = if parallelism_config().towers {
+        parallel_iter!(0..depth).map(sample_tower).collect::<Result<Vec<_>, _>>()?
+    } else {
+        (0..depth).map(sample_tower).collect::<Result<Vec<_>, _>>()?
+    };
+    let z_hat_bbi = towers.into_iter().flatten().collect::<Vec<_>>();
+    Ok(split_int64_mat_alt_to_elems(&z_hat_bbi, params))
 }
 
 // A function corresponding to lines 260-266 in trapdoor-dcrtpoly.cpp and the `GaussSampGqArbBase`
@@ -180,39 +426,87 @@ pub(crate) fn gauss_samp_gq_arb_base(
     base: u32,
     sigma: f64,
     tower_idx: usize,
-) -> Vec<Vec<i64>> {
+) -> Result<Vec<Vec<i64>>, DiamondError> {
     let n = params.ring_dimension();
     let depth = params.crt_depth();
     let k_res_bits = params.crt_bits();
     let k_res_digits = params.modulus_digits() / depth;
-    let result = DCRTGaussSampGqArbBase(
-        syndrome.get_poly(),
-        c,
-        n,
-        depth,
-        k_res_bits,
-        k_res_digits,
-        base as i64,
-        sigma,
-        tower_idx,
-    );
+    let result = ffi_guard::guard("DCRTGaussSampGqArbBase", || {
+        DCRTGaussSampGqArbBase(
+            syndrome.get_poly(),
+            c,
+            n,
+            depth,
+            k_res_bits,
+            k_res_digits,
+            base as i64,
+            sigma,
+            tower_idx,
+        )
+    })?;
     debug_assert_eq!(result.len(), n as usize * k_res_digits);
     // let mut matrix = I64Matrix::new_empty(&I64MatrixParams, k_res, n as usize);
-    parallel_iter!(0..k_res_digits)
+    Ok(parallel_iter!(0..k_res_digits)
         .map(|i| {
             parallel_iter!(0..n as usize).map(|j| result[i * n as usize + j]).collect::<Vec<_>>()
         })
-        .collect::<Vec<_>>()
+        .collect::<Vec<_>>())
+}
+
+/// `G - (A_bar * R + E)`
+fn trapdoor_columns(
+    params: &DCRTPolyParams,
+    trapdoor: &DCRTTrapdoor,
+    a_bar: &DCRTPolyMatrix,
+) -> DCRTPolyMatrix {
+    let g = DCRTPolyMatrix::gadget_matrix(params, a_bar.row_size());
+    g - (a_bar * &trapdoor.r + &trapdoor.e)
+}
+
+/// `G - A1` for delegating a trapdoor of `A` to `(A | A1)`.
+fn delegation_target(
+    params: &DCRTPolyParams,
+    public_matrix: &DCRTPolyMatrix,
+    extension_matrix: &DCRTPolyMatrix,
+) -> DCRTPolyMatrix {
+    let d = public_matrix.row_size();
+    assert_eq!(extension_matrix.row_size(), d, "the extension should have as many rows as A");
+    assert_eq!(
+        extension_matrix.col_size(),
+        d * params.modulus_digits(),
+        "the extension should have as many columns as G"
+    );
+    DCRTPolyMatrix::gadget_matrix(params, d) - extension_matrix
+}
+
+/// `(A_bar | I | a1)`
+pub(super) fn public_matrix_from_parts(
+    params: &DCRTPolyParams,
+    a_bar: &DCRTPolyMatrix,
+    a1: &DCRTPolyMatrix,
+) -> DCRTPolyMatrix {
+    let identity = DCRTPolyMatrix::identity(params, a_bar.row_size(), None);
+    a_bar.concat_columns(&[&identity, a1])
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::poly::{
-        dcrt::{sampler::DCRTPolyUniformSampler, DCRTPolyMatrix, DCRTPolyParams},
-        sampler::{DistType, PolyTrapdoorSampler, PolyUniformSampler},
-        PolyMatrix, PolyParams,
+    use crate::{
+        poly::{
+            dcrt::{
+                sampler::DCRTPolyUniformSampler, DCRTPolyHashSampler, DCRTPolyMatrix,
+                DCRTPolyParams,
+            },
+            norms::centered_f64,
+            plaintext::modulus_biguint,
+            rng::ChaChaSource,
+            sampler::{DistType, PolyTrapdoorSampler, PolyUniformSampler},
+            PolyElem, PolyMatrix, PolyParams,
+        },
+        utils::ThreadPoolConfig,
     };
+    use keccak_asm::Keccak256;
 
     const SIGMA: f64 = 4.578;
 
@@ -223,7 +517,7 @@ mod test {
         let target = uniform_sampler.sample_uniform(&params, 1, 1, DistType::FinRingDist);
         let decomposed = DCRTPolyMatrix::from_poly_vec(
             &params,
-            decompose_dcrt_gadget(&target.entry(0, 0), 3.0 * SIGMA, &params, 2, SIGMA),
+            decompose_dcrt_gadget(&target.entry(0, 0), 3.0 * SIGMA, &params, 2, SIGMA).unwrap(),
         );
         let gadget_vec = DCRTPolyMatrix::gadget_matrix(&params, 1);
         assert_eq!(gadget_vec * decomposed, target);
@@ -236,7 +530,8 @@ mod test {
         let target = uniform_sampler.sample_uniform(&params, 1, 1, DistType::FinRingDist);
         let decomposed = DCRTPolyMatrix::from_poly_vec(
             &params,
-            decompose_dcrt_gadget(&target.entry(0, 0), (8.0 + 1.0) * SIGMA, &params, 8, SIGMA),
+            decompose_dcrt_gadget(&target.entry(0, 0), (8.0 + 1.0) * SIGMA, &params, 8, SIGMA)
+                .unwrap(),
         );
         let gadget_vec = DCRTPolyMatrix::gadget_matrix(&params, 1);
         assert_eq!(gadget_vec * decomposed, target);
@@ -251,7 +546,7 @@ mod test {
         let (trapdoor, public_matrix) = trapdoor_sampler.trapdoor(&params, size);
 
         let expected_rows = size;
-        let expected_cols = (&params.modulus_digits() + 2) * size;
+        let expected_cols = trapdoor_width(size, params.modulus_digits());
 
         assert_eq!(
             public_matrix.row_size(),
@@ -282,6 +577,29 @@ mod test {
         assert_eq!(muled, gadget_matrix);
     }
 
+    #[test]
+    fn test_trapdoor_seeded_across_pools() {
+        let size: usize = 3;
+        let params = DCRTPolyParams::default();
+        let to_bytes = |matrix: &DCRTPolyMatrix| {
+            (0..matrix.row_size())
+                .flat_map(|i| matrix.get_row(i))
+                .flat_map(|poly| poly.to_compact_bytes())
+                .collect::<Vec<u8>>()
+        };
+
+        // Keys generated from one seed serialize to the same bytes under 1 and 4 threads
+        let keygen = |num_threads| {
+            let pool = ThreadPoolConfig::with_num_threads(num_threads).unwrap();
+            let source = Arc::new(ChaChaSource::for_test(11));
+            let trapdoor_sampler = DCRTPolyTrapdoorSampler::new(&params, SIGMA).with_source(source);
+            let (trapdoor, public_matrix) =
+                pool.install(|| trapdoor_sampler.trapdoor(&params, size));
+            [to_bytes(&trapdoor.r), to_bytes(&trapdoor.e), to_bytes(&public_matrix)]
+        };
+        assert_eq!(keygen(1), keygen(4));
+    }
+
     #[test]
     fn test_preimage_generation_square() {
         let params = DCRTPolyParams::default();
@@ -295,7 +613,7 @@ mod test {
 
         let preimage = trapdoor_sampler.preimage(&params, &trapdoor, &public_matrix, &target);
 
-        let expected_rows = size * (k + 2);
+        let expected_rows = trapdoor_width(size, k);
         let expected_cols = size;
 
         assert_eq!(
@@ -315,6 +633,30 @@ mod test {
         assert_eq!(product, target, "Product of public matrix and preimage should equal target");
     }
 
+    #[test]
+    fn test_trapdoor_with_shared_a() {
+        let params = DCRTPolyParams::default();
+        let size = 2;
+        let k = params.modulus_digits();
+        let seed: [u8; 32] = rand::random();
+        let trapdoor_sampler = DCRTPolyTrapdoorSampler::new(&params, SIGMA);
+        type HashSampler = DCRTPolyHashSampler<Keccak256>;
+        let (trapdoor, shared) =
+            trapdoor_sampler.trapdoor_with_shared_a::<HashSampler>(&params, seed, size);
+
+        // Only the trapdoor-dependent columns are stored
+        assert_eq!(shared.trapdoor_columns.size(), (size, size * k));
+        let public_matrix = shared.expand::<HashSampler>(&params);
+        assert_eq!(public_matrix.size(), (size, trapdoor_width(size, k)));
+        assert_eq!(public_matrix.slice_columns(0, size), shared.a_bar::<HashSampler>(&params));
+
+        // The expanded matrix is a valid trapdoor public matrix
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let target = uniform_sampler.sample_uniform(&params, size, size, DistType::FinRingDist);
+        let preimage = trapdoor_sampler.preimage(&params, &trapdoor, &public_matrix, &target);
+        assert_eq!(public_matrix * &preimage, target);
+    }
+
     #[test]
     fn test_preimage_generation_non_square_target_lt() {
         let params = DCRTPolyParams::default();
@@ -331,7 +673,7 @@ mod test {
 
         let preimage = trapdoor_sampler.preimage(&params, &trapdoor, &public_matrix, &target);
 
-        let expected_rows = size * (k + 2);
+        let expected_rows = trapdoor_width(size, k);
         let expected_cols = target_cols; // Preimage should be sliced to match target columns
 
         assert_eq!(
@@ -370,7 +712,7 @@ mod test {
 
         let preimage = trapdoor_sampler.preimage(&params, &trapdoor, &public_matrix, &target);
 
-        let expected_rows = size * (k + 2);
+        let expected_rows = trapdoor_width(size, k);
         let expected_cols = target_cols;
 
         assert_eq!(
@@ -408,7 +750,7 @@ mod test {
 
         let preimage = trapdoor_sampler.preimage(&params, &trapdoor, &public_matrix, &target);
 
-        let expected_rows = size * (k + 2);
+        let expected_rows = trapdoor_width(size, k);
         let expected_cols = target_cols;
 
         assert_eq!(
@@ -446,7 +788,7 @@ mod test {
 
         let preimage = trapdoor_sampler.preimage(&params, &trapdoor, &public_matrix, &target);
 
-        let expected_rows = size * (k + 2);
+        let expected_rows = trapdoor_width(size, k);
         let expected_cols = target_cols;
 
         assert_eq!(
@@ -484,7 +826,7 @@ mod test {
 
         let preimage = trapdoor_sampler.preimage(&params, &trapdoor, &public_matrix, &target);
 
-        let expected_rows = size * (k + 2);
+        let expected_rows = trapdoor_width(size, k);
         let expected_cols = target_cols;
 
         assert_eq!(
@@ -504,4 +846,109 @@ mod test {
 
         assert_eq!(product, target, "Product of public matrix and preimage should equal target");
     }
+
+    #[test]
+    fn test_sample_left() {
+        let params = DCRTPolyParams::default();
+        let size = 2;
+        let k = params.modulus_digits();
+        let trapdoor_sampler = DCRTPolyTrapdoorSampler::new(&params, SIGMA);
+        let (trapdoor, public_matrix) = trapdoor_sampler.trapdoor(&params, size);
+
+        // An arbitrary B without a known trapdoor
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let b = uniform_sampler.sample_uniform(&params, size, size * k, DistType::FinRingDist);
+        let target = uniform_sampler.sample_uniform(&params, size, 3, DistType::FinRingDist);
+
+        let preimage =
+            trapdoor_sampler.sample_left(&params, &trapdoor, &public_matrix, &b, &target).unwrap();
+        assert_eq!(preimage.row_size(), trapdoor_width(size, k) + size * k);
+        assert_eq!(preimage.col_size(), 3);
+
+        // (A | B) * preimage should be equal to target
+        let extended = public_matrix.concat_columns(&[&b]);
+        assert_eq!(extended * &preimage, target);
+    }
+
+    #[test]
+    fn test_sample_right() {
+        let params = DCRTPolyParams::default();
+        let size = 2;
+        let k = params.modulus_digits();
+        let trapdoor_sampler = DCRTPolyTrapdoorSampler::new(&params, SIGMA);
+
+        // A uniform A and a short R, so that R is a trapdoor of (A | A * R + G)
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let a = uniform_sampler.sample_uniform(&params, size, size * k, DistType::FinRingDist);
+        let r = uniform_sampler.sample_uniform(&params, size * k, size * k, DistType::BitDist);
+        let target = uniform_sampler.sample_uniform(&params, size, 3, DistType::FinRingDist);
+
+        let preimage = trapdoor_sampler.sample_right(&params, &a, &r, &target).unwrap();
+        assert_eq!(preimage.row_size(), 2 * size * k);
+        assert_eq!(preimage.col_size(), 3);
+
+        // (A | A * R + G) * preimage should be equal to target
+        let g = DCRTPolyMatrix::gadget_matrix(&params, size);
+        let extended = a.concat_columns(&[&(&a * &r + g)]);
+        assert_eq!(extended * &preimage, target);
+    }
+
+    #[test]
+    fn test_delegate() {
+        let params = DCRTPolyParams::default();
+        let size = 2;
+        let k = params.modulus_digits();
+        let trapdoor_sampler = DCRTPolyTrapdoorSampler::new(&params, SIGMA);
+        let (trapdoor, public_matrix) = trapdoor_sampler.trapdoor(&params, size);
+
+        // Delegate to (A | A1) for an arbitrary A1
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let a1 = uniform_sampler.sample_uniform(&params, size, size * k, DistType::FinRingDist);
+        let delegated =
+            trapdoor_sampler.delegate(&params, &trapdoor, &public_matrix, &a1).unwrap();
+        let extended = public_matrix.concat_columns(&[&a1]);
+        let identity = DCRTPolyMatrix::identity(&params, size * k, None);
+        let g = DCRTPolyMatrix::gadget_matrix(&params, size);
+        assert_eq!(&extended * &delegated.r.concat_rows(&[&identity]), g);
+
+        // The delegated trapdoor samples preimages of (A | A1)
+        let target = uniform_sampler.sample_uniform(&params, size, 3, DistType::FinRingDist);
+        let preimage =
+            trapdoor_sampler.preimage_delegated(&params, &delegated, &extended, &target).unwrap();
+        assert_eq!(preimage.size(), (extended.col_size(), 3));
+        assert_eq!(&extended * &preimage, target);
+
+        // The width dominates the spectral norm of R times the gadget width, and the top
+        // rows p - R * z of preimages have about that width whatever R is
+        let q = modulus_biguint::<DCRTPoly>(&params);
+        let r_sigma = trapdoor_sampler.preimage_sigma(&params, size);
+        let n = params.ring_dimension() as usize;
+        let (rows, cols) = delegated.r.size();
+        let s1 = SPECTRAL_CONSTANT *
+            r_sigma *
+            (((rows * n) as f64).sqrt() + ((cols * n) as f64).sqrt());
+        assert!(delegated.sigma > SIGMA * trapdoor_sampler.c * s1);
+        let wide_target = uniform_sampler.sample_uniform(&params, size, 8, DistType::FinRingDist);
+        let preimage = trapdoor_sampler
+            .preimage_delegated(&params, &delegated, &extended, &wide_target)
+            .unwrap();
+        let top = preimage.slice_rows(0, rows);
+        let coeffs = (0..rows)
+            .flat_map(|i| top.get_row(i))
+            .flat_map(|entry| entry.coeffs())
+            .map(|coeff| centered_f64(coeff.to_biguint(), &q))
+            .collect::<Vec<_>>();
+        let std_dev =
+            (coeffs.iter().map(|x| x * x).sum::<f64>() / coeffs.len() as f64).sqrt();
+        assert!(std_dev > 0.8 * delegated.sigma && std_dev < 1.25 * delegated.sigma);
+
+        // And delegates one level further to (A | A1 | A2), with a wider trapdoor
+        let a2 = uniform_sampler.sample_uniform(&params, size, size * k, DistType::FinRingDist);
+        let grandchild =
+            trapdoor_sampler.delegate_further(&params, &delegated, &extended, &a2).unwrap();
+        let extended = extended.concat_columns(&[&a2]);
+        assert_eq!(extended * grandchild.r.concat_rows(&[&identity]), g);
+        let growth = SPECTRAL_CONSTANT * SIGMA * trapdoor_sampler.c;
+        assert!(grandchild.sigma > growth * delegated.sigma);
+    }
 }
@@ -5,19 +5,16 @@ use crate::{
             matrix::{I64Matrix, I64MatrixParams},
             DCRTPoly, DCRTPolyMatrix, DCRTPolyParams, FinRingElem,
         },
+        rng::CryptoRngSource,
+        sampling::karney,
         Poly, PolyParams,
     },
 };
-use openfhe::ffi::GenerateIntegerKarney;
-use rand::{rng, Rng};
+use rand::Rng;
 use rand_distr::Uniform;
 use rayon::prelude::*;
 use std::ops::Range;
 
-pub(crate) fn gen_int_karney(mean: f64, stddev: f64) -> i64 {
-    GenerateIntegerKarney(mean, stddev)
-}
-
 fn find_in_vec(vec: &[f64], search: f64) -> u32 {
     // binary search to find the position of a value
     let pos = vec.partition_point(|&x| x < search);
@@ -29,19 +26,23 @@ fn find_in_vec(vec: &[f64], search: f64) -> u32 {
     }
 }
 
+/// Samples `size` integers from the discrete Gaussian of deviation `m_std`, the `i`-th one
+/// drawing from the stream `(i, 0)` of `source`.
 pub(crate) fn gen_dgg_int_vec(
     size: usize,
     peikert: bool,
     m_a: f64,
     m_std: f64,
     m_table: &[f64],
+    source: &dyn CryptoRngSource,
 ) -> I64Matrix {
     let mut vec = I64Matrix::new_empty(&I64MatrixParams, size, 1);
+    let streams = source.streams();
     if !peikert {
         // Use Karney's method
         let f = |row_offsets: Range<usize>, _: Range<usize>| -> Vec<Vec<i64>> {
             parallel_iter!(row_offsets)
-                .map(|_| vec![gen_int_karney(0.0f64, m_std)])
+                .map(|i| vec![karney(&mut streams.rng(i, 0), 0.0, m_std)])
                 .collect::<Vec<Vec<i64>>>()
         };
         vec.replace_entries(0..size, 0..1, f);
@@ -50,9 +51,8 @@ pub(crate) fn gen_dgg_int_vec(
         let distribution = Uniform::new(0.0f64, 1.0f64).unwrap();
         let f = |row_offsets: Range<usize>, _: Range<usize>| -> Vec<Vec<i64>> {
             parallel_iter!(row_offsets)
-                .map(|_| {
-                    let mut rng = rng();
-                    let seed: f64 = rng.sample(distribution) - 0.5f64;
+                .map(|i| {
+                    let seed: f64 = streams.rng(i, 0).sample(distribution) - 0.5f64;
                     let tmp = seed.abs() - m_a / 2.0f64;
                     let mut val = 0;
 
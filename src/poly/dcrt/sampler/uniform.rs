@@ -1,17 +1,25 @@
 use crate::{
-    parallel_iter,
+    error::DiamondError,
     poly::{
-        dcrt::{DCRTPoly, DCRTPolyMatrix},
+        dcrt::{
+            ffi_guard::{self, FirstFailure},
+            DCRTPoly, DCRTPolyMatrix, DCRTPolyParams,
+        },
+        rng::CryptoRngSource,
         sampler::{DistType, PolyUniformSampler},
+        sampling::sample_coeffs,
         Poly, PolyMatrix, PolyParams,
     },
 };
-use openfhe::ffi;
-use rayon::prelude::*;
-#[cfg(feature = "disk")]
-use std::ops::Range;
-
-pub struct DCRTPolyUniformSampler {}
+use rand::RngCore;
+use std::sync::Arc;
+
+/// Samples the coefficients in Rust from the randomness source it was created with, one stream
+/// of it per entry, and converts them to OpenFHE's representation.
+#[derive(Debug, Clone)]
+pub struct DCRTPolyUniformSampler {
+    source: Arc<dyn CryptoRngSource>,
+}
 
 impl Default for DCRTPolyUniformSampler {
     fn default() -> Self {
@@ -22,8 +30,8 @@ impl Default for DCRTPolyUniformSampler {
 impl PolyUniformSampler for DCRTPolyUniformSampler {
     type M = DCRTPolyMatrix;
 
-    fn new() -> Self {
-        Self {}
+    fn with_source(source: Arc<dyn CryptoRngSource>) -> Self {
+        Self { source }
     }
 
     fn sample_poly(
@@ -31,28 +39,7 @@ impl PolyUniformSampler for DCRTPolyUniformSampler {
         params: &<<Self::M as PolyMatrix>::P as Poly>::Params,
         dist: &DistType,
     ) -> <Self::M as PolyMatrix>::P {
-        let sampled_poly = match dist {
-            DistType::FinRingDist => ffi::DCRTPolyGenFromDug(
-                params.ring_dimension(),
-                params.crt_depth(),
-                params.crt_bits(),
-            ),
-            DistType::GaussDist { sigma } => ffi::DCRTPolyGenFromDgg(
-                params.ring_dimension(),
-                params.crt_depth(),
-                params.crt_bits(),
-                *sigma,
-            ),
-            DistType::BitDist => ffi::DCRTPolyGenFromBug(
-                params.ring_dimension(),
-                params.crt_depth(),
-                params.crt_bits(),
-            ),
-        };
-        if sampled_poly.is_null() {
-            panic!("Attempted to dereference a null pointer");
-        }
-        DCRTPoly::new(sampled_poly)
+        ffi_guard::expect_ffi(self.try_sample_poly(params, dist))
     }
 
     fn sample_uniform(
@@ -62,46 +49,57 @@ impl PolyUniformSampler for DCRTPolyUniformSampler {
         ncol: usize,
         dist: DistType,
     ) -> Self::M {
-        #[cfg(feature = "disk")]
-        {
-            let mut new_matrix = DCRTPolyMatrix::new_empty(params, nrow, ncol);
-            let f = |row_offsets: Range<usize>, col_offsets: Range<usize>| -> Vec<Vec<DCRTPoly>> {
-                parallel_iter!(row_offsets)
-                    .map(|_| {
-                        parallel_iter!(col_offsets.clone())
-                            .map(|_| self.sample_poly(params, &dist))
-                            .collect()
-                    })
-                    .collect()
-            };
-            new_matrix.replace_entries(0..nrow, 0..ncol, f);
-            new_matrix
-        }
-        #[cfg(not(feature = "disk"))]
-        {
-            let c: Vec<Vec<DCRTPoly>> = parallel_iter!(0..nrow)
-                .map(|_| {
-                    parallel_iter!(0..ncol)
-                        .map(|_| {
-                            let sampled_poly = self.sample_poly(params, &dist);
-                            if sampled_poly.get_poly().is_null() {
-                                panic!("Attempted to dereference a null pointer");
-                            }
-                            sampled_poly
-                        })
-                        .collect()
-                })
-                .collect();
-
-            DCRTPolyMatrix::from_poly_vec(params, c)
-        }
+        ffi_guard::expect_ffi(self.try_sample_uniform(params, nrow, ncol, dist))
+    }
+}
+
+/// Samples a polynomial from `dist` with the randomness of `rng`.
+fn try_sample_poly_with<R: RngCore>(
+    params: &DCRTPolyParams,
+    dist: &DistType,
+    rng: &mut R,
+) -> Result<DCRTPoly, DiamondError> {
+    let n = params.ring_dimension() as usize;
+    DCRTPoly::try_from_coeffs(params, &sample_coeffs(&params.modulus(), n, dist, rng))
+}
+
+impl DCRTPolyUniformSampler {
+    /// [`PolyUniformSampler::sample_poly`], failing if OpenFHE does.
+    pub fn try_sample_poly(
+        &self,
+        params: &DCRTPolyParams,
+        dist: &DistType,
+    ) -> Result<DCRTPoly, DiamondError> {
+        try_sample_poly_with(params, dist, &mut self.source.streams().rng(0, 0))
+    }
+
+    /// [`PolyUniformSampler::sample_uniform`], failing if OpenFHE does.
+    pub fn try_sample_uniform(
+        &self,
+        params: &DCRTPolyParams,
+        nrow: usize,
+        ncol: usize,
+        dist: DistType,
+    ) -> Result<DCRTPolyMatrix, DiamondError> {
+        let streams = self.source.streams();
+        let failures = FirstFailure::new();
+        let matrix = DCRTPolyMatrix::from_fn(params, nrow, ncol, |i, j| {
+            let poly = try_sample_poly_with(params, &dist, &mut streams.rng(i, j)).map(Some);
+            failures.take(poly).unwrap_or_else(|| DCRTPoly::const_zero(params))
+        });
+        failures.into_result()?;
+        Ok(matrix)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::poly::dcrt::DCRTPolyParams;
+    use crate::{
+        poly::{dcrt::DCRTPolyParams, rng::ChaChaSource},
+        utils::ThreadPoolConfig,
+    };
+    use num_bigint::BigUint;
 
     #[test]
     fn test_ring_dist() {
@@ -183,4 +181,56 @@ mod tests {
         assert_eq!(mult_matrix.row_size(), 20);
         assert_eq!(mult_matrix.col_size(), 12);
     }
+
+    #[test]
+    fn test_ternary_dist() {
+        let params = DCRTPolyParams::default();
+        let sampler = DCRTPolyUniformSampler::new();
+        let hamming_weight = 3;
+        let poly = sampler.sample_poly(&params, &DistType::TernaryDist { hamming_weight });
+
+        // Exactly hamming_weight coefficients are 1 or -1, the others zero
+        let minus_one = params.modulus().as_ref() - 1u32;
+        let coeffs = poly.coeffs();
+        let nonzero = coeffs.iter().filter(|c| c.value() != &BigUint::ZERO).collect::<Vec<_>>();
+        assert_eq!(nonzero.len(), hamming_weight);
+        let one = BigUint::from(1u8);
+        assert!(nonzero.iter().all(|c| c.value() == &one || c.value() == &minus_one));
+    }
+
+    #[test]
+    fn test_uniform_sampler_source() {
+        let params = DCRTPolyParams::default();
+
+        // Samplers over the same seeded source draw the same polynomials
+        let sample = |seed, dist| {
+            let source = Arc::new(ChaChaSource::for_test(seed));
+            DCRTPolyUniformSampler::with_source(source).sample_poly(&params, &dist)
+        };
+        for dist in [DistType::FinRingDist, DistType::GaussDist { sigma: 4.578 }] {
+            assert_eq!(sample(1, dist), sample(1, dist));
+        }
+        assert_ne!(sample(1, DistType::FinRingDist), sample(2, DistType::FinRingDist));
+
+        // Deviations above the table threshold are sampled with Karney's algorithm
+        let wide = sample(3, DistType::GaussDist { sigma: 1e6 });
+        let q = params.modulus();
+        let bound = BigUint::from(12_000_000u32);
+        assert!(wide.coeffs().iter().any(|c| c.value() != &BigUint::ZERO));
+        assert!(wide.coeffs().iter().all(|c| c.value() < &bound || q.as_ref() - c.value() < bound));
+    }
+
+    #[test]
+    fn test_uniform_sampler_source_across_pools() {
+        let params = DCRTPolyParams::default();
+
+        // A seeded source gives the same matrix whatever the number of threads sampling it
+        let sample = |num_threads| {
+            let pool = ThreadPoolConfig::with_num_threads(num_threads).unwrap();
+            let source = Arc::new(ChaChaSource::for_test(5));
+            let sampler = DCRTPolyUniformSampler::with_source(source);
+            pool.install(|| sampler.sample_uniform(&params, 12, 9, DistType::FinRingDist))
+        };
+        assert_eq!(sample(1), sample(4));
+    }
 }
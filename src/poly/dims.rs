@@ -0,0 +1,35 @@
+/// Number of base `2^base_bits` digits needed for one CRT tower of `crt_bits` bits.
+pub fn tower_gadget_len(crt_bits: usize, base_bits: u32) -> usize {
+    assert_ne!(base_bits, 0, "base_bits must be positive");
+    crt_bits.div_ceil(base_bits as usize)
+}
+
+/// Length of the gadget vector over all `crt_depth` towers, i.e. the number of columns of `G_1`.
+pub fn gadget_len(crt_depth: usize, crt_bits: usize, base_bits: u32) -> usize {
+    tower_gadget_len(crt_bits, base_bits) * crt_depth
+}
+
+/// Number of columns of a trapdoor public matrix `[A | G - A * T]` with `size` rows, where
+/// `A` has `2 * size` columns and `G` has `size * gadget_len` columns.
+pub fn trapdoor_width(size: usize, gadget_len: usize) -> usize {
+    size * (gadget_len + 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gadget_len() {
+        assert_eq!(gadget_len(2, 17, 1), 34);
+        assert_eq!(gadget_len(2, 17, 3), 12);
+        assert_eq!(gadget_len(4, 51, 17), 12);
+        assert_eq!(gadget_len(1, 20, 20), 1);
+    }
+
+    #[test]
+    fn test_trapdoor_width() {
+        assert_eq!(trapdoor_width(1, 34), 36);
+        assert_eq!(trapdoor_width(4, 12), 56);
+    }
+}
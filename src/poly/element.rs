@@ -1,5 +1,5 @@
 use std::{
-    fmt::Debug,
+    fmt::{Debug, Display},
     ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
 };
 
@@ -35,3 +35,117 @@ pub trait PolyElem:
     fn to_bytes(&self) -> Vec<u8>;
     fn to_biguint(&self) -> &num_bigint::BigUint;
 }
+
+/// A residue modulo a word-sized modulus, such as a plaintext modulus `t` or the modulus of an
+/// external library, tagged with its modulus. Arithmetic on residues of different moduli panics
+/// in debug builds instead of silently producing a residue of neither.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Elem {
+    value: u64,
+    modulus: u64,
+}
+
+impl Elem {
+    /// `value mod modulus`.
+    pub fn new(value: u64, modulus: u64) -> Self {
+        assert!(modulus > 0, "zero modulus");
+        Self { value: value % modulus, modulus }
+    }
+
+    /// The residue of the signed `value`.
+    pub fn from_i64(value: i64, modulus: u64) -> Self {
+        assert!(modulus > 0, "zero modulus");
+        Self { value: (value as i128).rem_euclid(modulus as i128) as u64, modulus }
+    }
+
+    /// The representative in `[0, modulus)`.
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn modulus(&self) -> u64 {
+        self.modulus
+    }
+
+    /// The representative in `(-modulus/2, modulus/2]`.
+    pub fn centered(&self) -> i128 {
+        if self.value > self.modulus / 2 {
+            self.value as i128 - self.modulus as i128
+        } else {
+            self.value as i128
+        }
+    }
+
+    #[inline]
+    fn debug_check_modulus(&self, rhs: &Self) {
+        debug_assert_eq!(self.modulus, rhs.modulus, "mixed moduli");
+    }
+}
+
+impl Display for Elem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+impl Add for Elem {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        self.debug_check_modulus(&rhs);
+        let value = (self.value as u128 + rhs.value as u128) % self.modulus as u128;
+        Self { value: value as u64, modulus: self.modulus }
+    }
+}
+
+impl Sub for Elem {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl Mul for Elem {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        self.debug_check_modulus(&rhs);
+        let value = (self.value as u128 * rhs.value as u128) % self.modulus as u128;
+        Self { value: value as u64, modulus: self.modulus }
+    }
+}
+
+impl Neg for Elem {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self { value: (self.modulus - self.value) % self.modulus, modulus: self.modulus }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elem_arithmetic() {
+        let (a, b) = (Elem::new(3327, 3329), Elem::new(5, 3329));
+        assert_eq!((a + b).value(), 3);
+        assert_eq!((b - a).value(), 7);
+        assert_eq!((a * b).value(), 3319);
+        assert_eq!(Elem::from_i64(-2, 3329), a);
+        assert_eq!(a.centered(), -2);
+        assert_eq!(b.centered(), 5);
+        // Moduli near 2^64 do not overflow
+        let q = u64::MAX - 58;
+        assert_eq!((Elem::new(q - 1, q) * Elem::new(q - 1, q)).value(), 1);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "mixed moduli")]
+    fn test_elem_mixed_moduli() {
+        let _ = Elem::new(1, 3329) + Elem::new(1, 7681);
+    }
+}
@@ -1,6 +1,11 @@
-use super::{sampler::PolyUniformSampler, Poly, PolyMatrix};
+use super::{
+    sampler::{PolyHashSampler, PolyUniformSampler},
+    Poly, PolyMatrix,
+};
 use crate::poly::{element::PolyElem, sampler::DistType, PolyParams};
 
+const TAG_RLWE_MASK: &[u8] = b"RLWE_MASK";
+
 pub fn rlwe_encrypt<M, SU>(
     params: &<<M as PolyMatrix>::P as Poly>::Params,
     sampler_uniform: &SU,
@@ -30,14 +35,180 @@ where
     t.clone() * a + e + &(m.clone() * &scale)
 }
 
+/// Computes the LWE sample `s * A + e` for a row vector secret `s`, where every entry of `e` is
+/// sampled from `error_dist`.
+pub fn lwe_sample<M, SU>(
+    params: &<<M as PolyMatrix>::P as Poly>::Params,
+    sampler_uniform: &SU,
+    secret: &M,
+    public_matrix: &M,
+    error_dist: DistType,
+) -> M
+where
+    M: PolyMatrix,
+    SU: PolyUniformSampler<M = M>,
+{
+    assert_eq!(secret.row_size(), 1);
+    assert_eq!(secret.col_size(), public_matrix.row_size());
+    let error = sampler_uniform.sample_uniform(params, 1, public_matrix.col_size(), error_dist);
+    secret.clone() * public_matrix + error
+}
+
+/// Expands the uniform mask `a` of an RLWE ciphertext from a 32-byte seed.
+pub fn rlwe_mask_from_seed<M, SH>(
+    params: &<<M as PolyMatrix>::P as Poly>::Params,
+    sampler_hash: &SH,
+    seed: [u8; 32],
+) -> M
+where
+    M: PolyMatrix,
+    SH: PolyHashSampler<[u8; 32], M = M>,
+{
+    sampler_hash.sample_hash(params, seed, TAG_RLWE_MASK, 1, 1, DistType::FinRingDist)
+}
+
+/// RLWE encryption whose mask `a` is derived from `seed`, so that only `(seed, b)` needs to be
+/// transmitted. The receiver recovers `a` with [`rlwe_mask_from_seed`].
+pub fn rlwe_encrypt_compressed<M, SU, SH>(
+    params: &<<M as PolyMatrix>::P as Poly>::Params,
+    sampler_uniform: &SU,
+    sampler_hash: &SH,
+    seed: [u8; 32],
+    t: &M,
+    m: &M,
+    sigma: f64,
+) -> M
+where
+    M: PolyMatrix,
+    SU: PolyUniformSampler<M = M>,
+    SH: PolyHashSampler<[u8; 32], M = M>,
+{
+    let a = rlwe_mask_from_seed(params, sampler_hash, seed);
+    rlwe_encrypt(params, sampler_uniform, t, &a, m, sigma)
+}
+
+/// RLWE public key `(a, b = t * a + e)` of a secret `t`, used to encrypt to its holder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RlwePublicKey<M: PolyMatrix> {
+    pub a: M,
+    pub b: M,
+}
+
+/// Public-key RLWE ciphertext `(u, v) = (r * a + e1, r * b + e2 + m * q/2)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RlweCiphertext<M: PolyMatrix> {
+    pub u: M,
+    pub v: M,
+}
+
+/// Samples a binary secret `t` and its public key.
+pub fn rlwe_keygen<M, SU>(
+    params: &<<M as PolyMatrix>::P as Poly>::Params,
+    sampler_uniform: &SU,
+    sigma: f64,
+) -> (M, RlwePublicKey<M>)
+where
+    M: PolyMatrix,
+    SU: PolyUniformSampler<M = M>,
+{
+    let t = sampler_uniform.sample_uniform(params, 1, 1, DistType::BitDist);
+    let a = sampler_uniform.sample_uniform(params, 1, 1, DistType::FinRingDist);
+    let b = rlwe_encrypt(params, sampler_uniform, &t, &a, &M::zero(params, 1, 1), sigma);
+    (t, RlwePublicKey { a, b })
+}
+
+/// Encrypts the binary polynomial `m` under `public_key` with a fresh binary randomness `r`.
+pub fn rlwe_pk_encrypt<M, SU>(
+    params: &<<M as PolyMatrix>::P as Poly>::Params,
+    sampler_uniform: &SU,
+    public_key: &RlwePublicKey<M>,
+    m: &M,
+    sigma: f64,
+) -> RlweCiphertext<M>
+where
+    M: PolyMatrix,
+    SU: PolyUniformSampler<M = M>,
+{
+    let r = sampler_uniform.sample_uniform(params, 1, 1, DistType::BitDist);
+    let u = rlwe_encrypt(params, sampler_uniform, &r, &public_key.a, &M::zero(params, 1, 1), sigma);
+    let v = rlwe_encrypt(params, sampler_uniform, &r, &public_key.b, m, sigma);
+    RlweCiphertext { u, v }
+}
+
+/// Recovers the bits of the message from `v - u * t`.
+pub fn rlwe_pk_decrypt<M: PolyMatrix>(
+    params: &<<M as PolyMatrix>::P as Poly>::Params,
+    t: &M,
+    ciphertext: &RlweCiphertext<M>,
+) -> Vec<bool> {
+    let noisy = ciphertext.v.clone() - ciphertext.u.clone() * t;
+    noisy.entry(0, 0).extract_bits_with_threshold(params)
+}
+
+/// Encrypts the 32-byte key of a [`PolyHashSampler`] under `public_key`, packing its bits into
+/// the coefficients of as many ciphertexts as the ring dimension requires, so that evaluators
+/// can expand the public matrices without the key being sent in the clear.
+pub fn encapsulate_hash_key<M, SU>(
+    params: &<<M as PolyMatrix>::P as Poly>::Params,
+    sampler_uniform: &SU,
+    public_key: &RlwePublicKey<M>,
+    key: &[u8; 32],
+    sigma: f64,
+) -> Vec<RlweCiphertext<M>>
+where
+    M: PolyMatrix,
+    SU: PolyUniformSampler<M = M>,
+{
+    let modulus = params.modulus();
+    let n = params.ring_dimension() as usize;
+    let bits = key.iter().flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1 == 1));
+    let bits = bits.collect::<Vec<_>>();
+    bits.chunks(n)
+        .map(|chunk| {
+            let coeffs = (0..n)
+                .map(|i| match chunk.get(i) {
+                    Some(true) => <M::P as Poly>::Elem::one(&modulus),
+                    _ => <M::P as Poly>::Elem::zero(&modulus),
+                })
+                .collect::<Vec<_>>();
+            let m = M::from_poly_vec_row(params, vec![M::P::from_coeffs(params, &coeffs)]);
+            rlwe_pk_encrypt(params, sampler_uniform, public_key, &m, sigma)
+        })
+        .collect()
+}
+
+/// Decrypts a key encapsulated by [`encapsulate_hash_key`] with the secret `t`.
+pub fn decapsulate_hash_key<M: PolyMatrix>(
+    params: &<<M as PolyMatrix>::P as Poly>::Params,
+    t: &M,
+    ciphertexts: &[RlweCiphertext<M>],
+) -> [u8; 32] {
+    let n = params.ring_dimension() as usize;
+    assert_eq!(ciphertexts.len(), 256usize.div_ceil(n), "wrong number of ciphertexts");
+    let bits = ciphertexts
+        .iter()
+        .flat_map(|ciphertext| rlwe_pk_decrypt(params, t, ciphertext))
+        .collect::<Vec<_>>();
+    let mut key = [0u8; 32];
+    for (i, bit) in bits.into_iter().take(256).enumerate() {
+        key[i / 8] |= (bit as u8) << (i % 8);
+    }
+    key
+}
+
 #[cfg(test)]
 mod tests {
     use crate::poly::{
-        dcrt::{DCRTPolyMatrix, DCRTPolyParams, DCRTPolyUniformSampler},
-        enc::rlwe_encrypt,
-        sampler::{DistType, PolyUniformSampler},
-        Poly, PolyMatrix,
+        dcrt::{DCRTPolyHashSampler, DCRTPolyMatrix, DCRTPolyParams, DCRTPolyUniformSampler},
+        enc::{
+            decapsulate_hash_key, encapsulate_hash_key, lwe_sample, rlwe_encrypt,
+            rlwe_encrypt_compressed, rlwe_keygen, rlwe_mask_from_seed,
+        },
+        sampler::{DistType, PolyHashSampler, PolyUniformSampler},
+        Poly, PolyMatrix, PolyParams,
     };
+    use keccak_asm::Keccak256;
+    use num_bigint::BigUint;
 
     #[test]
     fn test_rlwe_encrypt_decrypt() {
@@ -64,4 +235,99 @@ mod tests {
         // Verify correctness
         assert_eq!(recovered_bits, m.to_bool_vec());
     }
+
+    #[test]
+    fn test_lwe_sample() {
+        let params = DCRTPolyParams::default();
+        let sampler = DCRTPolyUniformSampler::new();
+        let secret = sampler.sample_uniform(&params, 1, 3, DistType::BitDist);
+        let public_matrix = sampler.sample_uniform(&params, 3, 5, DistType::FinRingDist);
+
+        // Without error the sample is exactly s * A
+        let sample = lwe_sample(
+            &params,
+            &sampler,
+            &secret,
+            &public_matrix,
+            DistType::GaussDist { sigma: 0.0 },
+        );
+        assert_eq!(sample.size(), (1, 5));
+        assert_eq!(sample, secret.clone() * &public_matrix);
+
+        // With error the sample differs from s * A only by the error term
+        let sample = lwe_sample(
+            &params,
+            &sampler,
+            &secret,
+            &public_matrix,
+            DistType::GaussDist { sigma: 3.0 },
+        );
+        let error = sample - secret * &public_matrix;
+        assert_eq!(error.size(), (1, 5));
+        for j in 0..5 {
+            for coeff in error.entry(0, j).coeffs() {
+                let value = coeff.value();
+                let centered = (params.modulus().as_ref() - value).min(value.clone());
+                assert!(centered < BigUint::from(100u32));
+            }
+        }
+    }
+
+    #[test]
+    fn test_rlwe_encrypt_compressed_decrypt() {
+        let params = DCRTPolyParams::default();
+        let sampler = DCRTPolyUniformSampler::new();
+        let hash_sampler = DCRTPolyHashSampler::<Keccak256>::new();
+        let sigma = 3.0;
+
+        // Generate random message bits and secret
+        let m = sampler.sample_poly(&params, &DistType::BitDist);
+        let t = sampler.sample_poly(&params, &DistType::BitDist);
+        let m_mat = DCRTPolyMatrix::from_poly_vec_row(&params, vec![m.clone()]);
+        let t_mat = DCRTPolyMatrix::from_poly_vec_row(&params, vec![t.clone()]);
+
+        // Encrypt the message, keeping only the seed and b
+        let seed: [u8; 32] = rand::random();
+        let b = rlwe_encrypt_compressed(
+            &params,
+            &sampler,
+            &hash_sampler,
+            seed,
+            &t_mat,
+            &m_mat,
+            sigma,
+        );
+
+        // Expand a from the seed and decrypt
+        let a_mat: DCRTPolyMatrix = rlwe_mask_from_seed(&params, &hash_sampler, seed);
+        let recovered = (b - (a_mat * t_mat)).entry(0, 0);
+        let recovered_bits = recovered.extract_bits_with_threshold(&params);
+
+        // Verify correctness
+        assert_eq!(recovered_bits, m.to_bool_vec());
+    }
+
+    #[test]
+    fn test_hash_key_encapsulation() {
+        let params = DCRTPolyParams::default();
+        let sampler = DCRTPolyUniformSampler::new();
+        let hash_sampler = DCRTPolyHashSampler::<Keccak256>::new();
+        let sigma = 3.0;
+
+        // The evaluator publishes a public key and the key owner encapsulates the hash key
+        let (t, public_key) = rlwe_keygen::<DCRTPolyMatrix, _>(&params, &sampler, sigma);
+        let key: [u8; 32] = rand::random();
+        let ciphertexts = encapsulate_hash_key(&params, &sampler, &public_key, &key, sigma);
+        let n = params.ring_dimension() as usize;
+        assert_eq!(ciphertexts.len(), 256usize.div_ceil(n));
+
+        // The evaluator recovers the key and expands the same matrices
+        let recovered = decapsulate_hash_key(&params, &t, &ciphertexts);
+        assert_eq!(recovered, key);
+        let expected: DCRTPolyMatrix =
+            hash_sampler.sample_hash(&params, key, b"tag", 2, 2, DistType::FinRingDist);
+        let expanded: DCRTPolyMatrix =
+            hash_sampler.sample_hash(&params, recovered, b"tag", 2, 2, DistType::FinRingDist);
+        assert_eq!(expanded, expected);
+    }
 }
@@ -0,0 +1,163 @@
+//! Gadget vectors beyond the powers of the base, e.g. ring gadgets whose entries are
+//! non-constant polynomials.
+//!
+//! A gadget is a row vector `g` together with a decomposition `g^-1` mapping every ring element
+//! `a` to short elements with `<g, g^-1(a)> = a`. The BGG+ gates only rely on this identity, so
+//! any gadget works as long as public keys and encodings are built from its matrix
+//! `G = I ⊗ g`, see [`crate::bgg::gates::GadgetGates`] and
+//! [`crate::bgg::sampler::BGGEncodingSampler::sample_with_gadget`].
+use super::{arena::decompose_rows, Poly, PolyMatrix, PolyParams};
+use std::fmt::Debug;
+
+pub trait GadgetVector<M: PolyMatrix>: Debug + Clone + Send + Sync {
+    /// The `1 x len` row vector `g`.
+    fn vector(&self, params: &<M::P as Poly>::Params) -> M;
+
+    /// Short polynomials `d` with `<g, d> = poly`, one per entry of `g`.
+    fn decompose_poly(&self, params: &<M::P as Poly>::Params, poly: &M::P) -> Vec<M::P>;
+
+    fn len(&self, params: &<M::P as Poly>::Params) -> usize {
+        self.vector(params).col_size()
+    }
+
+    /// `G = I_size ⊗ g`.
+    fn matrix(&self, params: &<M::P as Poly>::Params, size: usize) -> M {
+        let vector = self.vector(params);
+        vector.concat_diag(&vec![&vector; size - 1])
+    }
+
+    /// `G^-1(matrix)`: the digits of row `i` are the rows `i * len..(i + 1) * len`, so that
+    /// `G * G^-1(matrix) = matrix`.
+    fn decompose(&self, matrix: &M) -> M {
+        let params = matrix.params();
+        let rows = decompose_rows(
+            matrix.row_size(),
+            self.len(params),
+            |i| matrix.get_row(i),
+            |poly| self.decompose_poly(params, poly),
+        );
+        M::from_poly_vec(params, rows)
+    }
+}
+
+/// The standard gadget `(1, b, ..., b^(L - 1))` of constant polynomials, computed by the matrix
+/// backend itself.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PowersOfBase;
+
+impl<M: PolyMatrix> GadgetVector<M> for PowersOfBase {
+    fn vector(&self, params: &<M::P as Poly>::Params) -> M {
+        M::gadget_matrix(params, 1)
+    }
+
+    fn decompose_poly(&self, params: &<M::P as Poly>::Params, poly: &M::P) -> Vec<M::P> {
+        poly.decompose_base(params)
+    }
+
+    fn len(&self, params: &<M::P as Poly>::Params) -> usize {
+        params.modulus_digits()
+    }
+
+    fn matrix(&self, params: &<M::P as Poly>::Params, size: usize) -> M {
+        M::gadget_matrix(params, size)
+    }
+
+    fn decompose(&self, matrix: &M) -> M {
+        matrix.decompose()
+    }
+}
+
+/// The ring gadget `u * (1, b, ..., b^(L - 1))` for a unit `u` of the ring, decomposing `a` as
+/// the powers-of-base digits of `u^-1 * a`. Its entries are not constants unless `u` is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TwistedGadget<P: Poly> {
+    unit: P,
+    inverse: P,
+}
+
+impl<P: Poly> TwistedGadget<P> {
+    /// Panics unless `unit * inverse = 1`.
+    pub fn new(params: &P::Params, unit: P, inverse: P) -> Self {
+        assert_eq!(unit.clone() * &inverse, P::const_one(params), "the inverse is not an inverse");
+        Self { unit, inverse }
+    }
+
+    /// The gadget twisted by the monomial `X^k`, whose inverse is `-X^(n - k)` since `X^n = -1`.
+    pub fn monomial(params: &P::Params, k: usize) -> Self {
+        let n = params.ring_dimension() as usize;
+        assert!(k < n, "monomial degree {} is not below the ring dimension {}", k, n);
+        if k == 0 {
+            return Self::new(params, P::const_one(params), P::const_one(params));
+        }
+        let unit = P::const_rotate_poly(params, k);
+        let inverse = -P::const_rotate_poly(params, n - k);
+        Self::new(params, unit, inverse)
+    }
+
+    pub fn unit(&self) -> &P {
+        &self.unit
+    }
+}
+
+impl<M: PolyMatrix> GadgetVector<M> for TwistedGadget<M::P> {
+    fn vector(&self, params: &<M::P as Poly>::Params) -> M {
+        M::gadget_matrix(params, 1) * &self.unit
+    }
+
+    fn decompose_poly(&self, params: &<M::P as Poly>::Params, poly: &M::P) -> Vec<M::P> {
+        (self.inverse.clone() * poly).decompose_base(params)
+    }
+
+    fn len(&self, params: &<M::P as Poly>::Params) -> usize {
+        params.modulus_digits()
+    }
+
+    fn matrix(&self, params: &<M::P as Poly>::Params, size: usize) -> M {
+        M::gadget_matrix(params, size) * &self.unit
+    }
+
+    fn decompose(&self, matrix: &M) -> M {
+        (matrix.clone() * &self.inverse).decompose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::{
+        native::{NativePolyMatrix, NativePolyParams, NativePolyUniformSampler},
+        sampler::{DistType, PolyUniformSampler},
+    };
+    use num_bigint::BigUint;
+
+    fn check_gadget<G: GadgetVector<NativePolyMatrix>>(gadget: &G) {
+        let params = NativePolyParams::new(8, BigUint::from(65537u32), 4);
+        let sampler = NativePolyUniformSampler::new();
+        let matrix = sampler.sample_uniform(&params, 2, 3, DistType::FinRingDist);
+
+        // G * G^-1(A) = A, through the provided decomposition and the specialized one
+        let g = gadget.matrix(&params, 2);
+        assert_eq!(g.size(), (2, 2 * gadget.len(&params)));
+        assert_eq!(g.clone() * gadget.decompose(&matrix), matrix);
+        let rows = (0..2)
+            .flat_map(|i| {
+                let decomposed = matrix
+                    .get_row(i)
+                    .iter()
+                    .map(|poly| gadget.decompose_poly(&params, poly))
+                    .collect::<Vec<_>>();
+                (0..gadget.len(&params))
+                    .map(move |k| decomposed.iter().map(|d| d[k].clone()).collect())
+            })
+            .collect();
+        assert_eq!(NativePolyMatrix::from_poly_vec(&params, rows), gadget.decompose(&matrix));
+    }
+
+    #[test]
+    fn test_gadget_decompositions() {
+        check_gadget(&PowersOfBase);
+        let params = NativePolyParams::new(8, BigUint::from(65537u32), 4);
+        check_gadget(&TwistedGadget::monomial(&params, 0));
+        check_gadget(&TwistedGadget::monomial(&params, 3));
+    }
+}
@@ -0,0 +1,187 @@
+//! Row reduction and kernels of small matrices over `R_q`, for debugging trapdoors and for
+//! preprocessing steps of the encoding setup.
+//!
+//! `R_q` is not a field, so elimination only pivots on units, which are inverted by solving
+//! the linear system of the multiplication by the polynomial over `Z_q`. A column without a
+//! unit entry is skipped, and [`kernel`] reports the matrices this leaves unreduced.
+use super::{plaintext::modulus_biguint, Poly, PolyElem, PolyMatrix, PolyParams};
+use num_bigint::BigUint;
+
+/// A matrix in reduced row echelon form and the pivot column of each of its first rows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowEchelon<M: PolyMatrix> {
+    pub matrix: M,
+    pub pivots: Vec<usize>,
+}
+
+impl<M: PolyMatrix> RowEchelon<M> {
+    pub fn rank(&self) -> usize {
+        self.pivots.len()
+    }
+}
+
+/// Error returned by [`kernel`] when a row left after elimination has no unit entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoUnitPivot {
+    pub row: usize,
+}
+
+impl std::fmt::Display for NoUnitPivot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "row {} is non-zero but has no invertible entry", self.row)
+    }
+}
+
+impl std::error::Error for NoUnitPivot {}
+
+/// Solves `a * x = rhs` over `Z_q` by Gauss-Jordan elimination on unit pivots.
+fn solve_mod(mut a: Vec<Vec<BigUint>>, mut rhs: Vec<BigUint>, q: &BigUint) -> Option<Vec<BigUint>> {
+    let n = rhs.len();
+    for col in 0..n {
+        let (row, inv) = (col..n).find_map(|row| a[row][col].modinv(q).map(|inv| (row, inv)))?;
+        a.swap(col, row);
+        rhs.swap(col, row);
+        a[col].iter_mut().for_each(|value| *value = &*value * &inv % q);
+        rhs[col] = &rhs[col] * &inv % q;
+        for row in 0..n {
+            if row == col || a[row][col] == BigUint::ZERO {
+                continue;
+            }
+            let factor = a[row][col].clone();
+            for j in 0..n {
+                let scaled = &factor * &a[col][j] % q;
+                a[row][j] = (&a[row][j] + q - scaled) % q;
+            }
+            let scaled = &factor * &rhs[col] % q;
+            rhs[row] = (&rhs[row] + q - scaled) % q;
+        }
+    }
+    Some(rhs)
+}
+
+/// Returns the inverse of `poly` in `R_q`, or `None` if elimination over `Z_q` finds no unit
+/// pivot, which is always the case when `poly` is not a unit.
+pub fn invert_poly<P: Poly>(params: &P::Params, poly: &P) -> Option<P> {
+    let n = params.ring_dimension() as usize;
+    let q = modulus_biguint::<P>(params);
+    // Column j holds the coefficients of poly * x^j
+    let columns = (0..n)
+        .map(|j| (poly.clone() * P::const_rotate_poly(params, j)).coeffs())
+        .collect::<Vec<_>>();
+    let a = (0..n)
+        .map(|k| columns.iter().map(|column| column[k].to_biguint().clone()).collect())
+        .collect();
+    let mut rhs = vec![BigUint::ZERO; n];
+    rhs[0] = BigUint::from(1u8);
+    let solution = solve_mod(a, rhs, &q)?;
+    let modulus = params.modulus();
+    let coeffs = solution
+        .iter()
+        .map(|value| P::Elem::from_bytes(&modulus, &value.to_bytes_le()))
+        .collect::<Vec<_>>();
+    Some(P::from_coeffs(params, &coeffs))
+}
+
+/// Brings `matrix` into reduced row echelon form by row operations over `R_q`, pivoting on the
+/// first unit entry of each column among the rows not reduced yet.
+pub fn row_reduce<M: PolyMatrix>(params: &<M::P as Poly>::Params, matrix: &M) -> RowEchelon<M> {
+    let (nrow, ncol) = matrix.size();
+    let zero = M::P::const_zero(params);
+    let mut rows = (0..nrow).map(|i| matrix.get_row(i)).collect::<Vec<_>>();
+    let mut pivots = Vec::new();
+    for col in 0..ncol {
+        let rank = pivots.len();
+        if rank == nrow {
+            break;
+        }
+        let Some((row, inv)) = (rank..nrow).find_map(|row| {
+            (rows[row][col] != zero)
+                .then(|| invert_poly(params, &rows[row][col]))
+                .flatten()
+                .map(|inv| (row, inv))
+        }) else {
+            continue;
+        };
+        rows.swap(rank, row);
+        rows[rank].iter_mut().for_each(|entry| *entry = entry.clone() * &inv);
+        let pivot_row = rows[rank].clone();
+        for (i, row) in rows.iter_mut().enumerate() {
+            if i == rank || row[col] == zero {
+                continue;
+            }
+            let factor = row[col].clone();
+            for (entry, pivot_entry) in row.iter_mut().zip(pivot_row.iter()) {
+                *entry = entry.clone() - factor.clone() * pivot_entry;
+            }
+        }
+        pivots.push(col);
+    }
+    RowEchelon { matrix: M::from_poly_vec(params, rows), pivots }
+}
+
+/// Returns a matrix whose columns span the right kernel `{x : matrix * x = 0}`, with one
+/// column per non-pivot column of the reduced matrix, or `None` if the kernel is trivial.
+pub fn kernel<M: PolyMatrix>(
+    params: &<M::P as Poly>::Params,
+    matrix: &M,
+) -> Result<Option<M>, NoUnitPivot> {
+    let RowEchelon { matrix: reduced, pivots } = row_reduce(params, matrix);
+    let (nrow, ncol) = reduced.size();
+    let zero = M::P::const_zero(params);
+    let is_nonzero = |i: usize| reduced.get_row(i).iter().any(|entry| entry != &zero);
+    if let Some(row) = (pivots.len()..nrow).find(|&i| is_nonzero(i)) {
+        return Err(NoUnitPivot { row });
+    }
+    let free = (0..ncol).filter(|col| !pivots.contains(col)).collect::<Vec<_>>();
+    if free.is_empty() {
+        return Ok(None);
+    }
+    let mut basis = vec![vec![zero; free.len()]; ncol];
+    for (k, &col) in free.iter().enumerate() {
+        basis[col][k] = M::P::const_one(params);
+        for (i, &pivot) in pivots.iter().enumerate() {
+            basis[pivot][k] = -reduced.entry(i, col);
+        }
+    }
+    Ok(Some(M::from_poly_vec(params, basis)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::{
+        dcrt::{DCRTPoly, DCRTPolyMatrix, DCRTPolyParams, DCRTPolyUniformSampler},
+        sampler::{DistType, PolyUniformSampler},
+    };
+
+    #[test]
+    fn test_invert_poly() {
+        let params = DCRTPolyParams::default();
+        let sampler = DCRTPolyUniformSampler::new();
+        let poly = sampler.sample_poly(&params, &DistType::FinRingDist);
+        let inv = invert_poly(&params, &poly).unwrap();
+        assert_eq!(poly * inv, DCRTPoly::const_one(&params));
+        assert!(invert_poly(&params, &DCRTPoly::const_zero(&params)).is_none());
+    }
+
+    #[test]
+    fn test_row_reduce_and_kernel() {
+        let params = DCRTPolyParams::default();
+        let sampler = DCRTPolyUniformSampler::new();
+        let matrix = sampler.sample_uniform(&params, 2, 4, DistType::FinRingDist);
+
+        // A random wide matrix reduces to the identity in its first columns
+        let echelon = row_reduce(&params, &matrix);
+        assert_eq!(echelon.pivots, vec![0, 1]);
+        assert_eq!(echelon.matrix.slice_columns(0, 2), DCRTPolyMatrix::identity(&params, 2, None));
+
+        // Its kernel has one column per free column and is annihilated by the matrix
+        let basis = kernel(&params, &matrix).unwrap().unwrap();
+        assert_eq!(basis.size(), (4, 2));
+        assert_eq!(matrix * &basis, DCRTPolyMatrix::zero(&params, 2, 2));
+
+        // A square invertible matrix has a trivial kernel
+        let square = sampler.sample_uniform(&params, 2, 2, DistType::FinRingDist);
+        assert_eq!(kernel(&params, &square), Ok(None));
+    }
+}
@@ -1,3 +1,5 @@
+use crate::parallel_iter;
+use rayon::prelude::*;
 use std::{
     fmt::Debug,
     ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
@@ -31,4 +33,115 @@ pub trait MatrixElem:
     fn one(params: &Self::Params) -> Self;
     fn from_bytes_to_elem(params: &Self::Params, bytes: &[u8]) -> Self;
     fn as_elem_to_bytes(&self) -> Vec<u8>;
+    /// Multiplies two blocks of entries, the kernel behind matrix products. Backends with a
+    /// faster way to multiply many entries at once than one product per pair override it.
+    fn mul_block(lhs: Vec<Vec<Self>>, rhs: Vec<Vec<Self>>) -> Vec<Vec<Self>> {
+        naive_mul_block(lhs, rhs)
+    }
+}
+
+/// Multiplies two blocks of entries with one product per pair of entries.
+pub(crate) fn naive_mul_block<T: MatrixElem>(lhs: Vec<Vec<T>>, rhs: Vec<Vec<T>>) -> Vec<Vec<T>> {
+    let nrow = lhs.len();
+    let ncol = rhs[0].len();
+    let n_inner = lhs[0].len();
+    parallel_iter!(0..nrow)
+        .map(|i| {
+            parallel_iter!(0..ncol)
+                .map(|j: usize| {
+                    (0..n_inner)
+                        .map(|k| lhs[i][k].clone() * &rhs[k][j])
+                        .reduce(|acc, prod| acc + prod)
+                        .unwrap()
+                })
+                .collect::<Vec<T>>()
+        })
+        .collect::<Vec<Vec<T>>>()
+}
+
+/// The ring operations shared by every matrix backend, so that algebraic code and its tests can
+/// be written once and run against each backend.
+pub trait RingMatrixOps: Sized + Clone + Debug + PartialEq + Eq + Send + Sync {
+    type Params;
+    fn size(&self) -> (usize, usize);
+    fn zero(params: &Self::Params, nrow: usize, ncol: usize) -> Self;
+    fn identity(params: &Self::Params, size: usize) -> Self;
+    fn add(&self, rhs: &Self) -> Self;
+    fn neg(&self) -> Self;
+    fn mul(&self, rhs: &Self) -> Self;
+    /// Gₙ = Iₙ ⊗ gᵀ for the gadget vector g of the backend.
+    fn gadget_matrix(params: &Self::Params, size: usize) -> Self;
+    /// G⁻¹: decomposes every entry into the digits of g, so that
+    /// `G * a.gadget_decompose() == a` for `G = gadget_matrix(params, a.size().0)`.
+    fn gadget_decompose(&self) -> Self;
+}
+
+/// Generates a test module `$name` checking the ring laws of [`RingMatrixOps`] for the backend
+/// `$M`, given an expression for its params and a function `fn(&Params, nrow, ncol) -> $M`
+/// sampling random matrices.
+#[cfg(test)]
+#[macro_export]
+macro_rules! ring_matrix_ops_tests {
+    ($name:ident, $M:ty, $params:expr, $random:expr) => {
+        mod $name {
+            use super::*;
+            use $crate::poly::matrix::RingMatrixOps;
+
+            #[test]
+            fn test_add_laws() {
+                let params = $params;
+                let a: $M = $random(&params, 2, 3);
+                let b: $M = $random(&params, 2, 3);
+                let c: $M = $random(&params, 2, 3);
+                let zero = <$M as RingMatrixOps>::zero(&params, 2, 3);
+
+                // Commutativity and associativity
+                assert_eq!(a.add(&b), b.add(&a));
+                assert_eq!(a.add(&b).add(&c), a.add(&b.add(&c)));
+
+                // Zero is the neutral element and -a the inverse of a
+                assert_eq!(a.add(&zero), a);
+                assert_eq!(a.add(&a.neg()), zero);
+                assert_eq!(a.neg().neg(), a);
+            }
+
+            #[test]
+            fn test_mul_laws() {
+                let params = $params;
+                let a: $M = $random(&params, 2, 3);
+                let b: $M = $random(&params, 3, 2);
+                let c: $M = $random(&params, 3, 2);
+                let d: $M = $random(&params, 2, 4);
+
+                // Associativity
+                assert_eq!(a.mul(&b).mul(&d), a.mul(&b.mul(&d)));
+
+                // Distributivity on both sides
+                assert_eq!(a.mul(&b.add(&c)), a.mul(&b).add(&a.mul(&c)));
+                assert_eq!(b.add(&c).mul(&d), b.mul(&d).add(&c.mul(&d)));
+
+                // Identity and zero on both sides
+                let left_identity = <$M as RingMatrixOps>::identity(&params, 2);
+                let right_identity = <$M as RingMatrixOps>::identity(&params, 3);
+                assert_eq!(left_identity.mul(&a), a);
+                assert_eq!(a.mul(&right_identity), a);
+                let zero = <$M as RingMatrixOps>::zero(&params, 3, 4);
+                assert_eq!(a.mul(&zero), <$M as RingMatrixOps>::zero(&params, 2, 4));
+
+                // Negation commutes with multiplication
+                assert_eq!(a.neg().mul(&b), a.mul(&b).neg());
+            }
+
+            #[test]
+            fn test_gadget_decompose() {
+                let params = $params;
+                let a: $M = $random(&params, 2, 3);
+                let gadget = <$M as RingMatrixOps>::gadget_matrix(&params, 2);
+                let decomposed = a.gadget_decompose();
+                assert_eq!(decomposed.size().0, gadget.size().1);
+                assert_eq!(decomposed.size().1, 3);
+                assert_eq!(gadget.mul(&decomposed), a);
+            }
+        }
+    };
 }
@@ -1,15 +1,31 @@
 #![allow(clippy::needless_range_loop)]
 #![allow(clippy::suspicious_arithmetic_impl)]
 
+pub mod arena;
+pub mod canonical;
+pub mod compare;
 pub mod dcrt;
+pub mod dims;
 pub mod element;
 pub mod enc;
+pub mod gadget;
+pub mod linalg;
 pub mod matrix;
+pub mod native;
+pub mod norms;
+pub mod plaintext;
 pub mod poly_matrix;
 pub mod polynomial;
+pub mod ring_matrix;
+pub mod rng;
+pub mod rounding;
 pub mod sampler;
+pub mod sampling;
+pub mod structured;
+pub mod tag;
+pub mod zero_test;
 
-pub use element::PolyElem;
-pub use matrix::{MatrixElem, MatrixParams};
+pub use element::{Elem, PolyElem};
+pub use matrix::{MatrixElem, MatrixParams, RingMatrixOps};
 pub use poly_matrix::PolyMatrix;
 pub use polynomial::{Poly, PolyParams};
@@ -0,0 +1,331 @@
+use super::{ntt, NativePoly, NativePolyParams};
+use crate::{
+    parallel_iter,
+    poly::{
+        compare::fmt_matrix,
+        dcrt::{matrix::base::BaseMatrix, FinRingElem},
+        matrix::naive_mul_block,
+        MatrixElem, Poly, PolyMatrix, PolyParams,
+    },
+    utils::block_size,
+};
+use itertools::Itertools;
+use num_bigint::BigUint;
+use rayon::prelude::*;
+use std::{fmt, ops::Range, path::Path};
+
+#[cfg(feature = "disk")]
+use crate::poly::dcrt::matrix::base::disk::block_offsets;
+
+impl MatrixElem for NativePoly {
+    type Params = NativePolyParams;
+
+    fn zero(params: &Self::Params) -> Self {
+        <Self as Poly>::const_zero(params)
+    }
+    fn one(params: &Self::Params) -> Self {
+        <Self as Poly>::const_one(params)
+    }
+    fn from_bytes_to_elem(params: &Self::Params, bytes: &[u8]) -> Self {
+        <Self as Poly>::from_bytes(params, bytes)
+    }
+
+    fn as_elem_to_bytes(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    fn mul_block(lhs: Vec<Vec<Self>>, rhs: Vec<Vec<Self>>) -> Vec<Vec<Self>> {
+        if ntt::supports(&lhs[0][0]) {
+            ntt::mul_block(lhs, rhs)
+        } else {
+            naive_mul_block(lhs, rhs)
+        }
+    }
+}
+
+pub type NativePolyMatrix = BaseMatrix<NativePoly>;
+
+impl fmt::Display for NativePolyMatrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_matrix(self, f)
+    }
+}
+
+impl PolyMatrix for NativePolyMatrix {
+    type P = NativePoly;
+
+    fn from_poly_vec(params: &NativePolyParams, vec: Vec<Vec<NativePoly>>) -> Self {
+        let nrow = vec.len();
+        let ncol = vec[0].len();
+        let mut matrix = Self::new_empty(params, nrow, ncol);
+        let vec = &vec;
+        let f = |row_offsets: Range<usize>, col_offsets: Range<usize>| -> Vec<Vec<Self::P>> {
+            row_offsets.into_iter().map(|i| vec[i][col_offsets.clone()].to_vec()).collect()
+        };
+        matrix.replace_entries(0..nrow, 0..ncol, f);
+        matrix
+    }
+
+    fn params(&self) -> &<Self::P as Poly>::Params {
+        &self.params
+    }
+
+    fn entry(&self, i: usize, j: usize) -> Self::P {
+        self.entry(i, j)
+    }
+
+    fn get_row(&self, i: usize) -> Vec<Self::P> {
+        self.get_row(i)
+    }
+
+    fn get_column(&self, j: usize) -> Vec<Self::P> {
+        self.get_column(j)
+    }
+
+    fn size(&self) -> (usize, usize) {
+        self.size()
+    }
+
+    fn slice(&self, row_start: usize, row_end: usize, col_start: usize, col_end: usize) -> Self {
+        self.slice(row_start, row_end, col_start, col_end)
+    }
+
+    fn zero(params: &NativePolyParams, nrow: usize, ncol: usize) -> Self {
+        Self::zero(params, nrow, ncol)
+    }
+
+    fn identity(params: &NativePolyParams, size: usize, scalar: Option<Self::P>) -> Self {
+        Self::identity(params, size, scalar)
+    }
+
+    fn transpose(&self) -> Self {
+        self.transpose()
+    }
+
+    fn concat_columns(&self, others: &[&Self]) -> Self {
+        self.concat_columns(others)
+    }
+
+    fn concat_rows(&self, others: &[&Self]) -> Self {
+        self.concat_rows(others)
+    }
+
+    fn concat_diag(&self, others: &[&Self]) -> Self {
+        self.concat_diag(others)
+    }
+
+    fn tensor(&self, other: &Self) -> Self {
+        self.tensor(other)
+    }
+
+    fn gadget_matrix(params: &NativePolyParams, size: usize) -> Self {
+        let gadget_vector = Self::gadget_vector(params);
+        gadget_vector.concat_diag(&vec![&gadget_vector; size - 1])
+    }
+
+    fn decompose(&self) -> Self {
+        let log_base_q = self.params.modulus_digits();
+        let new_nrow = self.nrow * log_base_q;
+        let mut new_matrix = Self::new_empty(&self.params, new_nrow, self.ncol);
+        let f = |row_offsets: Range<usize>, col_offsets: Range<usize>| -> Vec<Vec<NativePoly>> {
+            let entries = self.block_entries(row_offsets, col_offsets);
+            let decomposed_entries: Vec<Vec<Vec<NativePoly>>> = parallel_iter!(entries)
+                .map(|row| {
+                    parallel_iter!(row).map(|poly| poly.decompose_base(&self.params)).collect()
+                })
+                .collect();
+            parallel_iter!(0..decomposed_entries.len() * log_base_q)
+                .map(|idx| {
+                    let row = &decomposed_entries[idx / log_base_q];
+                    row.iter().map(|digits| digits[idx % log_base_q].clone()).collect()
+                })
+                .collect()
+        };
+        new_matrix.replace_entries_with_expand(0..self.nrow, 0..self.ncol, log_base_q, 1, f);
+        new_matrix
+    }
+
+    fn modulus_switch(&self, new_modulus: &<NativePolyParams as PolyParams>::Modulus) -> Self {
+        let mut new_matrix = Self::new_empty(&self.params, self.nrow, self.ncol);
+        let f = |row_offsets: Range<usize>, col_offsets: Range<usize>| -> Vec<Vec<Self::P>> {
+            self.block_entries(row_offsets, col_offsets)
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|poly| poly.modulus_switch(&self.params, new_modulus.clone()))
+                        .collect_vec()
+                })
+                .collect_vec()
+        };
+        new_matrix.replace_entries(0..self.nrow, 0..self.ncol, f);
+        new_matrix
+    }
+
+    fn mul_tensor_identity(&self, other: &Self, identity_size: usize) -> Self {
+        debug_assert_eq!(self.ncol, other.nrow * identity_size);
+        let slice_width = other.nrow;
+        let slice_results = (0..identity_size)
+            .map(|i| {
+                let slice = self.slice(0, self.nrow, i * slice_width, (i + 1) * slice_width);
+                slice * other
+            })
+            .collect_vec();
+        slice_results[0].concat_columns(&slice_results[1..].iter().collect::<Vec<_>>())
+    }
+
+    fn mul_tensor_identity_decompose(&self, other: &Self, identity_size: usize) -> Self {
+        let log_base_q = self.params.modulus_digits();
+        debug_assert_eq!(self.ncol, other.nrow * identity_size * log_base_q);
+        let slice_width = other.nrow * log_base_q;
+        let output = (0..identity_size)
+            .flat_map(|i| {
+                let slice = self.slice(0, self.nrow, i * slice_width, (i + 1) * slice_width);
+                (0..other.ncol).map(move |j| &slice * &other.get_column_matrix_decompose(j))
+            })
+            .collect_vec();
+        output[0].concat_columns(&output[1..].iter().collect::<Vec<_>>())
+    }
+
+    fn get_column_matrix_decompose(&self, j: usize) -> Self {
+        Self::from_poly_vec(
+            &self.params,
+            self.get_column(j).into_iter().map(|poly| vec![poly]).collect(),
+        )
+        .decompose()
+    }
+
+    /// Reads the blocks written by [`PolyMatrix::write_to_files`], in the layout of the DCRT
+    /// backend.
+    fn read_from_files<P: AsRef<Path> + Send + Sync>(
+        params: &NativePolyParams,
+        nrow: usize,
+        ncol: usize,
+        dir_path: P,
+        id: &str,
+    ) -> Self {
+        let block_size = block_size();
+        let mut matrix = Self::new_empty(params, nrow, ncol);
+        let f = |row_range: Range<usize>, col_range: Range<usize>| -> Vec<Vec<NativePoly>> {
+            let mut path = dir_path.as_ref().to_path_buf();
+            path.push(format!(
+                "{}_{}_{}.{}_{}.{}.matrix",
+                id, block_size, row_range.start, row_range.end, col_range.start, col_range.end
+            ));
+            let bytes = std::fs::read(&path)
+                .unwrap_or_else(|_| panic!("Failed to read matrix file {:?}", path));
+            let entries_bytes: Vec<Vec<Vec<u8>>> = serde_json::from_slice(&bytes).unwrap();
+            entries_bytes
+                .iter()
+                .map(|row| {
+                    row.iter().map(|bytes| NativePoly::from_compact_bytes(params, bytes)).collect()
+                })
+                .collect()
+        };
+        matrix.replace_entries(0..nrow, 0..ncol, f);
+        matrix
+    }
+
+    async fn write_to_files<P: AsRef<Path> + Send + Sync>(&self, dir_path: P, id: &str) {
+        let block_size = block_size();
+        #[cfg(feature = "disk")]
+        let (row_offsets, col_offsets) = block_offsets(0..self.nrow, 0..self.ncol);
+        #[cfg(not(feature = "disk"))]
+        let (row_offsets, col_offsets) = (vec![0, self.nrow], vec![0, self.ncol]);
+        let dir_path = dir_path.as_ref().to_path_buf();
+        let futures = row_offsets
+            .into_iter()
+            .tuple_windows()
+            .cartesian_product(col_offsets.into_iter().tuple_windows().collect_vec())
+            .map(|((row_start, row_end), (col_start, col_end))| {
+                let entries = self.block_entries(row_start..row_end, col_start..col_end);
+                let mut path = dir_path.clone();
+                path.push(format!(
+                    "{}_{}_{}.{}_{}.{}.matrix",
+                    id, block_size, row_start, row_end, col_start, col_end
+                ));
+                let entries_bytes = entries
+                    .iter()
+                    .map(|row| row.iter().map(|poly| poly.to_compact_bytes()).collect_vec())
+                    .collect_vec();
+                async move {
+                    let serialized_data = serde_json::to_vec(&entries_bytes)?;
+                    tokio::fs::write(path, &serialized_data).await
+                }
+            })
+            .collect_vec();
+        futures::future::try_join_all(futures).await.expect("Failed to write all matrix blocks");
+    }
+}
+
+impl NativePolyMatrix {
+    /// The row vector `(1, base, ..., base^(L - 1))` of constant polynomials.
+    pub(crate) fn gadget_vector(params: &NativePolyParams) -> Self {
+        let base_bits = params.base_bits() as usize;
+        let row = (0..params.modulus_digits())
+            .map(|k| {
+                let power = BigUint::from(1u8) << (k * base_bits);
+                NativePoly::from_const(params, &FinRingElem::new(power, params.modulus()))
+            })
+            .collect();
+        Self::from_poly_vec(params, vec![row])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::{
+        native::NativePolyUniformSampler,
+        sampler::{DistType, PolyUniformSampler},
+    };
+
+    #[test]
+    fn test_native_matrix_decompose() {
+        let params = NativePolyParams::new(4, (BigUint::from(1u8) << 100) - 15u8, 8);
+        let sampler = NativePolyUniformSampler::new();
+        let matrix = sampler.sample_uniform(&params, 2, 3, DistType::FinRingDist);
+
+        // G * G^-1(A) = A with 13 digits per row
+        let decomposed = matrix.decompose();
+        assert_eq!(decomposed.size(), (2 * 13, 3));
+        let gadget_matrix = NativePolyMatrix::gadget_matrix(&params, 2);
+        assert_eq!(gadget_matrix.size(), (2, 2 * 13));
+        assert_eq!(gadget_matrix * decomposed, matrix);
+    }
+
+    #[test]
+    fn test_native_matrix_modulus_switch_and_norm() {
+        let params = NativePolyParams::new(4, (BigUint::from(1u8) << 100) - 15u8, 8);
+        let sampler = NativePolyUniformSampler::new();
+        let matrix = sampler.sample_uniform(&params, 2, 3, DistType::FinRingDist);
+
+        // Every entry is switched like NativePoly::modulus_switch
+        let new_modulus = std::sync::Arc::new((BigUint::from(1u8) << 60) - 93u8);
+        let switched = matrix.modulus_switch(&new_modulus);
+        for i in 0..2 {
+            for j in 0..3 {
+                let expected = matrix.entry(i, j).modulus_switch(&params, new_modulus.clone());
+                assert_eq!(switched.entry(i, j), expected);
+            }
+        }
+
+        // The norm is that of the centered coefficients, at most q / 2
+        let norm = matrix.max_norm();
+        assert_eq!(norm, crate::poly::norms::matrix_inf_norm(&params, &matrix));
+        assert!(norm <= BigUint::from(1u8) << 99);
+        assert_eq!(NativePolyMatrix::zero(&params, 2, 2).max_norm(), BigUint::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_native_matrix_files() {
+        let params = NativePolyParams::default();
+        let sampler = NativePolyUniformSampler::new();
+        let matrix = sampler.sample_uniform(&params, 3, 2, DistType::FinRingDist);
+        let dir = std::env::temp_dir().join(format!("diamond-native-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        matrix.write_to_files(&dir, "matrix").await;
+        let read = NativePolyMatrix::read_from_files(&params, 3, 2, &dir, "matrix");
+        assert_eq!(read, matrix);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+//! A pure-Rust backend over `Z_q[x]/(x^n + 1)`, or `Z_q[x]/(x^(p - 1) + ... + 1)` for a prime
+//! `p` (see [`RingKind`]), with multi-limb coefficients.
+//!
+//! Coefficients are big integers, so the modulus can have any number of bits (e.g. 100+ bits for
+//! circuits of medium depth) without going through OpenFHE. Multiplication of single polynomials
+//! is schoolbook, so the backend is meant for small ring dimensions, tests and cross-checking the
+//! DCRT backend; matrix products over power-of-two rings go through the NTT instead.
+pub mod matrix;
+mod ntt;
+pub mod params;
+pub mod poly;
+pub mod sampler;
+
+pub use matrix::NativePolyMatrix;
+pub use params::{NativePolyParams, RingKind};
+pub use poly::NativePoly;
+pub use sampler::{NativePolyHashSampler, NativePolyUniformSampler};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bgg::{
+            circuit::PolyCircuit,
+            sampler::{BGGEncodingSampler, BGGPublicKeySampler},
+        },
+        compat::{assert_encodings_match, reference_encode},
+        poly::{
+            sampler::{DistType, PolyUniformSampler},
+            Poly, PolyMatrix,
+        },
+    };
+    use keccak_asm::Keccak256;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_native_bgg_circuit_eval() {
+        // A 110-bit modulus, beyond what a single machine word holds
+        let params = NativePolyParams::new(4, (BigUint::from(1u8) << 110) - 1u8, 10);
+        let key: [u8; 32] = rand::random();
+        let d = 2;
+        let bgg_sampler = BGGPublicKeySampler::<_, NativePolyHashSampler<Keccak256>>::new(key, d);
+        let pubkeys = bgg_sampler.sample(&params, b"native", &[true, true]);
+        let uniform_sampler = NativePolyUniformSampler::new();
+        let secrets = (0..d)
+            .map(|_| uniform_sampler.sample_poly(&params, &DistType::BitDist))
+            .collect::<Vec<_>>();
+        let plaintexts = (0..2)
+            .map(|_| uniform_sampler.sample_poly(&params, &DistType::FinRingDist))
+            .collect::<Vec<_>>();
+        let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        let encodings = bgg_sampler.sample(&params, &pubkeys, &plaintexts);
+
+        // The encodings agree with the reference path
+        assert_encodings_match(&params, &secrets, &plaintexts, &encodings);
+
+        // Without error, the output of a multiplication exactly encodes the product
+        let mut circuit = PolyCircuit::new();
+        let inputs = circuit.input(2);
+        let mul_gate = circuit.mul_gate(inputs[0], inputs[1]);
+        circuit.output(vec![mul_gate]);
+        let output = circuit.eval(&params, &encodings[0], &encodings[1..]).remove(0);
+        let product = &plaintexts[0] * &plaintexts[1];
+        assert_eq!(output.plaintext, Some(product.clone()));
+        let expected = reference_encode(&params, &secrets, &output.pubkey.matrix, &product);
+        for (column, coeffs) in expected.into_iter().enumerate() {
+            let actual = output.vector.entry(0, column).coeffs();
+            assert_eq!(actual.iter().map(|c| c.value().clone()).collect::<Vec<_>>(), coeffs);
+        }
+    }
+}
@@ -0,0 +1,375 @@
+//! Matrix products of the native backend in the NTT domain.
+//!
+//! The exact integer product of two blocks of negacyclic polynomials with coefficients in
+//! `[0, q)` is computed modulo word-sized NTT primes whose product exceeds its range, and lifted
+//! back to `Z_q` with the CRT. Every entry is transformed once per prime, products are
+//! pointwise across the slots, and sums over the inner dimension are accumulated without
+//! reduction: the right-hand residues are kept in Montgomery form, so a single REDC reduces a
+//! whole sum of products to the residue of the sum. This replaces the `n^2` big-integer
+//! multiplications of each entry product by `O(n log n)` word operations per entry.
+use super::{NativePoly, RingKind};
+use crate::parallel_iter;
+use num_bigint::BigUint;
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// NTT primes are below `2^PRIME_BITS`, which leaves room in a `u128` accumulator.
+const PRIME_BITS: u32 = 50;
+/// Sums of this many products of residues below `2^50` stay below `p * 2^64`, the input range
+/// of REDC, so they are reduced once per chunk of the inner dimension.
+const LAZY_TERMS: usize = 1 << 14;
+
+/// NTT bases keyed by `(ring_dimension, number of primes)`, since finding primes and roots is
+/// slow compared to a block product.
+static BASIS_CACHE: Lazy<Mutex<HashMap<(usize, usize), Arc<NttBasis>>>> =
+    Lazy::new(Default::default);
+
+fn pow_mod(base: u64, mut exp: u64, p: u64) -> u64 {
+    let (mut base, mut result) = (base as u128 % p as u128, 1u128);
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result * base % p as u128;
+        }
+        base = base * base % p as u128;
+        exp >>= 1;
+    }
+    result as u64
+}
+
+/// Deterministic Miller-Rabin, exact for every `u64`.
+fn is_prime(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    if let Some(&base) = BASES.iter().find(|&&base| n % base == 0) {
+        return n == base;
+    }
+    let (s, d) = ((n - 1).trailing_zeros(), (n - 1) >> (n - 1).trailing_zeros());
+    BASES.iter().all(|&base| {
+        let mut x = pow_mod(base, d, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        (1..s).any(|_| {
+            x = (x as u128 * x as u128 % n as u128) as u64;
+            x == n - 1
+        })
+    })
+}
+
+fn bit_reverse(k: usize, bits: u32) -> usize {
+    if bits == 0 {
+        0
+    } else {
+        k.reverse_bits() >> (usize::BITS - bits)
+    }
+}
+
+/// A prime `p = 1 mod 2n` with the twiddle factors of the negacyclic NTT of dimension `n`.
+#[derive(Debug)]
+struct NttPrime {
+    p: u64,
+    /// `-p^-1 mod 2^64`.
+    p_neg_inv: u64,
+    /// `2^128 mod p`, which turns a residue into Montgomery form.
+    r2: u64,
+    /// `psi^bitrev(k)` in Montgomery form for a primitive `2n`-th root `psi`.
+    psi_rev: Vec<u64>,
+    psi_inv_rev: Vec<u64>,
+    /// `n^-1` in Montgomery form.
+    n_inv: u64,
+}
+
+impl NttPrime {
+    fn new(p: u64, n: usize) -> Self {
+        let mut inv = 1u64;
+        for _ in 0..6 {
+            inv = inv.wrapping_mul(2u64.wrapping_sub(p.wrapping_mul(inv)));
+        }
+        let r = ((1u128 << 64) % p as u128) as u64;
+        let r2 = (r as u128 * r as u128 % p as u128) as u64;
+        let p_neg_inv = inv.wrapping_neg();
+        let mut prime =
+            Self { p, p_neg_inv, r2, psi_rev: vec![], psi_inv_rev: vec![], n_inv: 0 };
+        let order = 2 * n as u64;
+        let psi = (2..p)
+            .map(|x| pow_mod(x, (p - 1) / order, p))
+            .find(|&psi| pow_mod(psi, n as u64, p) == p - 1)
+            .expect("p = 1 mod 2n has a primitive 2n-th root");
+        let psi_inv = pow_mod(psi, order - 1, p);
+        let bits = n.trailing_zeros();
+        let twiddles = |root: u64| {
+            (0..n)
+                .map(|k| prime.to_montgomery(pow_mod(root, bit_reverse(k, bits) as u64, p)))
+                .collect::<Vec<_>>()
+        };
+        let (psi_rev, psi_inv_rev) = (twiddles(psi), twiddles(psi_inv));
+        let n_inv = prime.to_montgomery(pow_mod(n as u64, p - 2, p));
+        prime.psi_rev = psi_rev;
+        prime.psi_inv_rev = psi_inv_rev;
+        prime.n_inv = n_inv;
+        prime
+    }
+
+    /// Montgomery reduction `t * 2^-64 mod p` for `t < p * 2^64`.
+    fn redc(&self, t: u128) -> u64 {
+        let m = (t as u64).wrapping_mul(self.p_neg_inv);
+        let t = ((t + m as u128 * self.p as u128) >> 64) as u64;
+        if t >= self.p {
+            t - self.p
+        } else {
+            t
+        }
+    }
+
+    /// `a * b` for `b` in Montgomery form.
+    fn mul(&self, a: u64, b: u64) -> u64 {
+        self.redc(a as u128 * b as u128)
+    }
+
+    fn to_montgomery(&self, a: u64) -> u64 {
+        self.mul(a, self.r2)
+    }
+
+    fn add(&self, a: u64, b: u64) -> u64 {
+        let sum = a + b;
+        if sum >= self.p {
+            sum - self.p
+        } else {
+            sum
+        }
+    }
+
+    fn sub(&self, a: u64, b: u64) -> u64 {
+        if a >= b {
+            a - b
+        } else {
+            a + self.p - b
+        }
+    }
+
+    /// Cooley-Tukey butterflies with the powers of `psi` merged in, from natural to
+    /// bit-reversed order.
+    fn forward(&self, a: &mut [u64]) {
+        let n = a.len();
+        let (mut t, mut m) = (n, 1);
+        while m < n {
+            t >>= 1;
+            for i in 0..m {
+                let s = self.psi_rev[m + i];
+                for j in 2 * i * t..2 * i * t + t {
+                    let (u, v) = (a[j], self.mul(a[j + t], s));
+                    a[j] = self.add(u, v);
+                    a[j + t] = self.sub(u, v);
+                }
+            }
+            m <<= 1;
+        }
+    }
+
+    /// Gentleman-Sande butterflies undoing [`Self::forward`], including the scaling by `n^-1`.
+    fn inverse(&self, a: &mut [u64]) {
+        let n = a.len();
+        let (mut t, mut m) = (1, n);
+        while m > 1 {
+            let h = m >> 1;
+            for i in 0..h {
+                let s = self.psi_inv_rev[h + i];
+                for j in 2 * i * t..2 * i * t + t {
+                    let (u, v) = (a[j], a[j + t]);
+                    a[j] = self.add(u, v);
+                    a[j + t] = self.mul(self.sub(u, v), s);
+                }
+            }
+            t <<= 1;
+            m = h;
+        }
+        a.iter_mut().for_each(|x| *x = self.mul(*x, self.n_inv));
+    }
+
+    fn residues(&self, coeffs: &[BigUint]) -> Vec<u64> {
+        let p = self.p as u128;
+        coeffs
+            .iter()
+            .map(|c| c.iter_u64_digits().rev().fold(0u128, |r, d| ((r << 64) | d as u128) % p))
+            .map(|r| r as u64)
+            .collect()
+    }
+}
+
+/// NTT primes whose product `M` bounds the integers to recover, with the CRT coefficients
+/// `(M / p) * ((M / p)^-1 mod p)` of each prime.
+#[derive(Debug)]
+struct NttBasis {
+    primes: Vec<NttPrime>,
+    crt_coeffs: Vec<BigUint>,
+    modulus: BigUint,
+}
+
+impl NttBasis {
+    fn new(n: usize, count: usize) -> Self {
+        let order = 2 * n as u64;
+        let primes = (1..(1u64 << PRIME_BITS) / order)
+            .rev()
+            .map(|k| k * order + 1)
+            .filter(|&p| is_prime(p))
+            .take(count)
+            .map(|p| NttPrime::new(p, n))
+            .collect::<Vec<_>>();
+        let modulus = primes.iter().map(|prime| BigUint::from(prime.p)).product::<BigUint>();
+        let crt_coeffs = primes
+            .iter()
+            .map(|prime| {
+                let cofactor = &modulus / prime.p;
+                let residue = (&cofactor % prime.p).iter_u64_digits().next().unwrap_or(0);
+                cofactor * pow_mod(residue, prime.p - 2, prime.p)
+            })
+            .collect();
+        Self { primes, crt_coeffs, modulus }
+    }
+
+    fn cached(n: usize, count: usize) -> Arc<Self> {
+        let mut cache = BASIS_CACHE.lock().unwrap();
+        cache.entry((n, count)).or_insert_with(|| Arc::new(Self::new(n, count))).clone()
+    }
+
+    /// Lifts the residues of an integer of absolute value below `M / 2` to `Z_q`.
+    fn lift(&self, residues: &[u64], q: &BigUint) -> BigUint {
+        let sum = residues
+            .iter()
+            .zip(self.crt_coeffs.iter())
+            .map(|(&r, coeff)| coeff * r)
+            .sum::<BigUint>() %
+            &self.modulus;
+        if sum > &self.modulus >> 1 {
+            (q - (&self.modulus - sum) % q) % q
+        } else {
+            sum % q
+        }
+    }
+}
+
+/// Whether [`mul_block`] applies to entries of this shape: negacyclic rings of power-of-two
+/// dimension, which the word-sized primes support up to `2^20`.
+pub(crate) fn supports(poly: &NativePoly) -> bool {
+    let n = poly.values().len();
+    poly.ring_kind() == RingKind::PowerOfTwo && n.is_power_of_two() && n <= 1 << 20
+}
+
+/// Multiplies two blocks of entries in the NTT domain.
+pub(crate) fn mul_block(
+    lhs: Vec<Vec<NativePoly>>,
+    rhs: Vec<Vec<NativePoly>>,
+) -> Vec<Vec<NativePoly>> {
+    let first = &lhs[0][0];
+    let q = first.modulus();
+    let n = first.values().len();
+    let (nrow, inner, ncol) = (lhs.len(), rhs.len(), rhs[0].len());
+    // Every coefficient of the exact product is below inner * n * q^2 in absolute value
+    let bound_bits = 2 * q.bits() + (inner * n).ilog2() as u64 + 3;
+    let basis = NttBasis::cached(n, bound_bits.div_ceil(PRIME_BITS as u64 - 1) as usize);
+
+    let transform = |poly: &NativePoly, montgomery: bool| {
+        basis
+            .primes
+            .iter()
+            .map(|prime| {
+                let mut slots = prime.residues(poly.values());
+                prime.forward(&mut slots);
+                if montgomery {
+                    slots.iter_mut().for_each(|x| *x = prime.to_montgomery(*x));
+                }
+                slots
+            })
+            .collect::<Vec<_>>()
+    };
+    let lhs_ntt = parallel_iter!(0..nrow)
+        .map(|i| lhs[i].iter().map(|poly| transform(poly, false)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let rhs_ntt = parallel_iter!(0..inner)
+        .map(|k| rhs[k].iter().map(|poly| transform(poly, true)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    parallel_iter!(0..nrow)
+        .map(|i| {
+            parallel_iter!(0..ncol)
+                .map(|j| {
+                    let residues = basis
+                        .primes
+                        .iter()
+                        .enumerate()
+                        .map(|(t, prime)| {
+                            let mut slots = vec![0u64; n];
+                            for chunk in (0..inner).collect::<Vec<_>>().chunks(LAZY_TERMS) {
+                                for (s, slot) in slots.iter_mut().enumerate() {
+                                    let sum = chunk
+                                        .iter()
+                                        .map(|&k| {
+                                            let (a, b) = (lhs_ntt[i][k][t][s], rhs_ntt[k][j][t][s]);
+                                            a as u128 * b as u128
+                                        })
+                                        .sum::<u128>();
+                                    *slot = prime.add(*slot, prime.redc(sum));
+                                }
+                            }
+                            prime.inverse(&mut slots);
+                            slots
+                        })
+                        .collect::<Vec<_>>();
+                    let coeffs = (0..n)
+                        .map(|s| {
+                            let slot = residues.iter().map(|r| r[s]).collect::<Vec<_>>();
+                            basis.lift(&slot, q)
+                        })
+                        .collect();
+                    first.with_coeffs(coeffs)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::{
+        matrix::naive_mul_block,
+        native::{NativePolyMatrix, NativePolyParams, NativePolyUniformSampler},
+        sampler::{DistType, PolyUniformSampler},
+        PolyMatrix,
+    };
+
+    #[test]
+    fn test_ntt_roundtrip() {
+        let basis = NttBasis::new(8, 2);
+        for prime in basis.primes.iter() {
+            assert!(is_prime(prime.p));
+            assert_eq!(prime.p % 16, 1);
+            let original = (0..8).map(|i| (i * 12345 + 7) % prime.p).collect::<Vec<_>>();
+            let mut slots = original.clone();
+            prime.forward(&mut slots);
+            prime.inverse(&mut slots);
+            assert_eq!(slots, original);
+        }
+    }
+
+    #[test]
+    fn test_ntt_mul_block_matches_naive() {
+        let params = NativePolyParams::new(16, (BigUint::from(1u8) << 100) - 15u8, 8);
+        let sampler = NativePolyUniformSampler::new();
+        let a = sampler.sample_uniform(&params, 2, 3, DistType::FinRingDist);
+        let b = sampler.sample_uniform(&params, 3, 2, DistType::FinRingDist);
+        let block = |m: &NativePolyMatrix| {
+            (0..m.row_size()).map(|i| m.get_row(i)).collect::<Vec<_>>()
+        };
+
+        // A 100-bit modulus needs five primes, and the product agrees with the schoolbook one
+        assert!(supports(&a.entry(0, 0)));
+        assert_eq!(mul_block(block(&a), block(&b)), naive_mul_block(block(&a), block(&b)));
+    }
+}
@@ -0,0 +1,114 @@
+use crate::poly::{MatrixParams, PolyParams};
+use num_bigint::BigUint;
+use std::sync::Arc;
+
+/// The cyclotomic polynomial `Φ_m(x)` the polynomials are reduced by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RingKind {
+    /// `Φ_2n(x) = x^n + 1` for a power-of-two ring dimension `n`.
+    #[default]
+    PowerOfTwo,
+    /// `Φ_p(x) = x^(p - 1) + ... + x + 1` for a prime `p = n + 1`, for ring dimensions between
+    /// the powers of two.
+    PrimeCyclotomic,
+}
+
+impl RingKind {
+    /// The order `m` of the cyclotomic polynomial, i.e. the smallest `m` with `x^m = 1` in the
+    /// ring of dimension `ring_dimension`.
+    pub fn order(self, ring_dimension: usize) -> usize {
+        match self {
+            Self::PowerOfTwo => 2 * ring_dimension,
+            Self::PrimeCyclotomic => ring_dimension + 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NativePolyParams {
+    ring_kind: RingKind,
+    /// polynomial ring dimension
+    ring_dimension: u32,
+    /// ring modulus, which need not be prime nor NTT-friendly
+    modulus: Arc<BigUint>,
+    /// bit size of the base for the gadget vector and decomposition
+    base_bits: u32,
+}
+
+impl NativePolyParams {
+    /// Parameters of the power-of-two ring `Z_q[x]/(x^n + 1)`.
+    pub fn new(ring_dimension: u32, modulus: BigUint, base_bits: u32) -> Self {
+        Self::with_ring_kind(RingKind::PowerOfTwo, ring_dimension, modulus, base_bits)
+    }
+
+    pub fn with_ring_kind(
+        ring_kind: RingKind,
+        ring_dimension: u32,
+        modulus: BigUint,
+        base_bits: u32,
+    ) -> Self {
+        match ring_kind {
+            RingKind::PowerOfTwo => assert!(
+                ring_dimension.is_power_of_two(),
+                "ring_dimension must be a power of 2, got {}",
+                ring_dimension
+            ),
+            RingKind::PrimeCyclotomic => assert!(
+                ring_dimension > 0 && is_prime(ring_dimension as u64 + 1),
+                "ring_dimension must be a prime minus 1, got {}",
+                ring_dimension
+            ),
+        }
+        assert!(modulus > BigUint::from(1u8), "modulus must be at least 2");
+        assert!(base_bits > 0, "base_bits must be positive");
+        Self { ring_kind, ring_dimension, modulus: Arc::new(modulus), base_bits }
+    }
+
+    pub fn ring_kind(&self) -> RingKind {
+        self.ring_kind
+    }
+}
+
+fn is_prime(n: u64) -> bool {
+    n >= 2 && (2..).take_while(|d| d * d <= n).all(|d| n % d != 0)
+}
+
+impl Default for NativePolyParams {
+    /// **note**  these parameters are insecure and only for test purpose
+    fn default() -> Self {
+        // The Mersenne prime 2^127 - 1, which does not fit in a u64
+        Self::new(4, (BigUint::from(1u8) << 127) - 1u8, 1)
+    }
+}
+
+impl PolyParams for NativePolyParams {
+    type Modulus = Arc<BigUint>;
+
+    fn ring_dimension(&self) -> u32 {
+        self.ring_dimension
+    }
+
+    fn modulus(&self) -> Self::Modulus {
+        self.modulus.clone()
+    }
+
+    fn base_bits(&self) -> u32 {
+        self.base_bits
+    }
+
+    fn modulus_bits(&self) -> usize {
+        self.modulus.bits() as usize
+    }
+
+    fn modulus_digits(&self) -> usize {
+        self.modulus_bits().div_ceil(self.base_bits as usize)
+    }
+}
+
+impl MatrixParams for NativePolyParams {
+    fn entry_size(&self) -> usize {
+        let log_q_bytes = self.modulus_bits().div_ceil(8);
+        let dim = self.ring_dimension() as usize;
+        dim * log_q_bytes
+    }
+}
@@ -0,0 +1,503 @@
+use super::params::{NativePolyParams, RingKind};
+#[cfg(feature = "checked")]
+use crate::poly::polynomial::assert_operands;
+use crate::{
+    counters, impl_binop_with_refs, parallel_iter,
+    poly::{
+        dcrt::{
+            compact::{coeffs_from_compact_bytes, compact_bytes},
+            FinRingElem,
+        },
+        element::PolyElem,
+        Poly, PolyParams,
+    },
+};
+use num_bigint::BigUint;
+use rayon::prelude::*;
+use std::{
+    ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+    sync::Arc,
+};
+
+/// A polynomial of the ring of [`RingKind`] stored as its coefficients in `[0, q)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NativePoly {
+    coeffs: Vec<BigUint>,
+    modulus: Arc<BigUint>,
+    ring_kind: RingKind,
+}
+
+impl NativePoly {
+    fn from_values(params: &NativePolyParams, coeffs: Vec<BigUint>) -> Self {
+        debug_assert_eq!(coeffs.len(), params.ring_dimension() as usize);
+        Self { coeffs, modulus: params.modulus(), ring_kind: params.ring_kind() }
+    }
+
+    pub(super) fn with_coeffs(&self, coeffs: Vec<BigUint>) -> Self {
+        Self { coeffs, modulus: self.modulus.clone(), ring_kind: self.ring_kind }
+    }
+
+    pub(super) fn values(&self) -> &[BigUint] {
+        &self.coeffs
+    }
+
+    pub(super) fn modulus(&self) -> &BigUint {
+        &self.modulus
+    }
+
+    pub(super) fn ring_kind(&self) -> RingKind {
+        self.ring_kind
+    }
+
+    /// Reduces the `p` coefficients of a polynomial modulo `x^p - 1` to the prime cyclotomic
+    /// ring, where `x^(p - 1) = -(x^(p - 2) + ... + 1)`.
+    fn reduce_prime_cyclotomic(&self, mut wide: Vec<BigUint>) -> Self {
+        let q = self.modulus.as_ref();
+        let top = wide.pop().expect("p is at least 2") % q;
+        let coeffs = wide.into_iter().map(|c| (c % q + q - &top) % q).collect();
+        self.with_coeffs(coeffs)
+    }
+
+    fn from_const_value(params: &NativePolyParams, value: BigUint) -> Self {
+        let mut coeffs = vec![BigUint::ZERO; params.ring_dimension() as usize];
+        coeffs[0] = value % params.modulus().as_ref();
+        Self::from_values(params, coeffs)
+    }
+
+    pub fn modulus_switch(
+        &self,
+        params: &NativePolyParams,
+        new_modulus: <NativePolyParams as PolyParams>::Modulus,
+    ) -> Self {
+        debug_assert!(new_modulus < params.modulus());
+        let coeffs = self
+            .coeffs()
+            .par_iter()
+            .map(|coeff| coeff.modulus_switch(new_modulus.clone()))
+            .collect::<Vec<FinRingElem>>();
+        NativePoly::from_coeffs(params, &coeffs)
+    }
+
+    fn debug_check_modulus(&self, other: &Self) {
+        debug_assert_eq!(self.modulus, other.modulus, "mixed moduli");
+    }
+}
+
+impl Poly for NativePoly {
+    type Elem = FinRingElem;
+    type Params = NativePolyParams;
+
+    fn coeffs(&self) -> Vec<Self::Elem> {
+        self.coeffs.iter().map(|c| FinRingElem::new(c.clone(), self.modulus.clone())).collect()
+    }
+
+    fn from_coeffs(params: &Self::Params, coeffs: &[Self::Elem]) -> Self {
+        let modulus = params.modulus();
+        let mut values = vec![BigUint::ZERO; params.ring_dimension() as usize];
+        for (value, coeff) in values.iter_mut().zip(coeffs) {
+            debug_assert_eq!(coeff.modulus(), modulus.as_ref());
+            *value = coeff.value().clone();
+        }
+        Self::from_values(params, values)
+    }
+
+    fn from_const(params: &Self::Params, constant: &Self::Elem) -> Self {
+        Self::from_const_value(params, constant.value().clone())
+    }
+
+    /// Inverse of [`Poly::decompose_base`]: sums the `h`-th polynomial scaled by `base^h` for the
+    /// gadget base `2^base_bits`.
+    fn from_decomposed(params: &Self::Params, decomposed: &[Self]) -> Self {
+        assert!(
+            decomposed.len() <= params.modulus_digits(),
+            "expected at most {} digits, got {}",
+            params.modulus_digits(),
+            decomposed.len()
+        );
+        let base_bits = params.base_bits() as usize;
+        let modulus = params.modulus();
+        let mut coeffs = vec![BigUint::ZERO; params.ring_dimension() as usize];
+        for (i, digit_poly) in decomposed.iter().enumerate() {
+            for (coeff, digit) in coeffs.iter_mut().zip(digit_poly.coeffs.iter()) {
+                *coeff = (&*coeff + (digit << (i * base_bits))) % modulus.as_ref();
+            }
+        }
+        Self::from_values(params, coeffs)
+    }
+
+    fn from_compact_bytes(params: &Self::Params, bytes: &[u8]) -> Self {
+        let coeffs = coeffs_from_compact_bytes(
+            &params.modulus(),
+            params.ring_dimension() as usize,
+            bytes,
+        );
+        Self::from_coeffs(params, &coeffs)
+    }
+
+    fn const_zero(params: &Self::Params) -> Self {
+        Self::from_const_value(params, BigUint::ZERO)
+    }
+
+    fn const_one(params: &Self::Params) -> Self {
+        Self::from_const_value(params, BigUint::from(1u32))
+    }
+
+    fn const_minus_one(params: &Self::Params) -> Self {
+        Self::from_const_value(params, params.modulus().as_ref() - BigUint::from(1u32))
+    }
+
+    fn const_power_of_base(params: &Self::Params, k: usize) -> Self {
+        Self::from_const_value(params, BigUint::from(1u32) << (params.base_bits() as usize * k))
+    }
+
+    fn const_max(params: &Self::Params) -> Self {
+        let coeffs = vec![FinRingElem::max_q(&params.modulus()); params.ring_dimension() as usize];
+        Self::from_coeffs(params, &coeffs)
+    }
+
+    /// Maps the coefficient of `x^i` to `x^(i * k mod m)` for the order `m` of the ring and
+    /// reduces the result, which also covers the prime cyclotomic rings.
+    /// `k` only matters modulo the order `m` of the cyclotomic, so it is reduced first, which
+    /// also keeps `i * k` from overflowing.
+    fn automorphism(&self, _: &Self::Params, k: usize) -> Self {
+        let n = self.coeffs.len();
+        let m = self.ring_kind.order(n);
+        let k = k % m;
+        match self.ring_kind {
+            RingKind::PowerOfTwo => {
+                assert!(k % 2 == 1, "automorphism index must be odd, got {}", k)
+            }
+            RingKind::PrimeCyclotomic => {
+                assert!(k != 0, "automorphism index must not be a multiple of {}", m)
+            }
+        }
+        let mut wide = vec![BigUint::ZERO; m];
+        for (i, coeff) in self.coeffs.iter().enumerate() {
+            wide[(i * k) % m] = coeff.clone();
+        }
+        match self.ring_kind {
+            RingKind::PowerOfTwo => {
+                let q = self.modulus.as_ref();
+                self.with_coeffs((0..n).map(|i| (&wide[i] + q - &wide[i + n]) % q).collect())
+            }
+            RingKind::PrimeCyclotomic => self.reduce_prime_cyclotomic(wide),
+        }
+    }
+
+    /// Recover bits from a polynomial using decision thresholds q/4 and 3q/4
+    fn extract_bits_with_threshold(&self, params: &Self::Params) -> Vec<bool> {
+        let modulus = params.modulus();
+        let half_q = FinRingElem::half_q(&modulus);
+        let quarter_q = half_q.value() >> 1;
+        let three_quarter_q = &quarter_q * 3u32;
+        self.coeffs.iter().map(|coeff| coeff >= &quarter_q && coeff < &three_quarter_q).collect()
+    }
+
+    /// Returns the polynomials of the `h`-th base `2^base_bits` digits of the coefficients, as
+    /// [`crate::poly::dcrt::DCRTPoly::decompose_base`] does.
+    fn decompose_base(&self, params: &Self::Params) -> Vec<Self> {
+        let base_bits = params.base_bits() as usize;
+        let base_mask = (BigUint::from(1u32) << base_bits) - BigUint::from(1u32);
+        parallel_iter!(0..params.modulus_digits())
+            .map(|digit_idx| {
+                let shift_amount = digit_idx * base_bits;
+                let digits =
+                    self.coeffs.iter().map(|coeff| (coeff >> shift_amount) & &base_mask).collect();
+                Self::from_values(params, digits)
+            })
+            .collect()
+    }
+
+    fn to_bool_vec(&self) -> Vec<bool> {
+        self.coeffs
+            .iter()
+            .map(|v| {
+                if v == &BigUint::from(0u32) {
+                    false
+                } else if v == &BigUint::from(1u32) {
+                    true
+                } else {
+                    panic!("Coefficient is not 0 or 1: {}", v);
+                }
+            })
+            .collect()
+    }
+
+    fn to_compact_bytes(&self) -> Vec<u8> {
+        compact_bytes(&self.modulus, &self.coeffs())
+    }
+
+    fn operand_mismatch(&self, rhs: &Self) -> Option<String> {
+        if self.coeffs.len() != rhs.coeffs.len() {
+            Some(format!("{} and {} coefficients", self.coeffs.len(), rhs.coeffs.len()))
+        } else if self.modulus != rhs.modulus {
+            Some(format!("moduli {} and {}", self.modulus, rhs.modulus))
+        } else if self.ring_kind != rhs.ring_kind {
+            Some(format!("rings {:?} and {:?}", self.ring_kind, rhs.ring_kind))
+        } else {
+            None
+        }
+    }
+}
+
+impl_binop_with_refs!(NativePoly => Add::add(self, rhs: &NativePoly) -> NativePoly {
+    #[cfg(feature = "checked")]
+    assert_operands("add", self, rhs);
+    self.debug_check_modulus(rhs);
+    let q = self.modulus.as_ref();
+    let coeffs = self.coeffs.iter().zip(rhs.coeffs.iter()).map(|(a, b)| (a + b) % q).collect();
+    self.with_coeffs(coeffs)
+});
+
+impl_binop_with_refs!(NativePoly => Mul::mul(self, rhs: &NativePoly) -> NativePoly {
+    counters::record_poly_mul();
+    #[cfg(feature = "checked")]
+    assert_operands("mul", self, rhs);
+    self.debug_check_modulus(rhs);
+    let q = self.modulus.as_ref();
+    let n = self.coeffs.len();
+    if self.ring_kind == RingKind::PrimeCyclotomic {
+        // Cyclic product modulo x^p - 1 for p = n + 1, reduced to the ring afterwards
+        let p = n + 1;
+        let wide = parallel_iter!(0..p)
+            .map(|k| {
+                let mut sum = BigUint::ZERO;
+                for i in 0..n {
+                    let j = (p + k - i) % p;
+                    if j < n {
+                        sum += &self.coeffs[i] * &rhs.coeffs[j];
+                    }
+                }
+                sum
+            })
+            .collect();
+        return self.reduce_prime_cyclotomic(wide);
+    }
+    // Schoolbook multiplication, keeping the terms wrapped around by x^n = -1 apart so that
+    // every output coefficient is reduced once
+    let coeffs = parallel_iter!(0..n)
+        .map(|k| {
+            let mut positive = BigUint::ZERO;
+            let mut negative = BigUint::ZERO;
+            for i in 0..n {
+                if i <= k {
+                    positive += &self.coeffs[i] * &rhs.coeffs[k - i];
+                } else {
+                    negative += &self.coeffs[i] * &rhs.coeffs[n + k - i];
+                }
+            }
+            (positive % q + q - negative % q) % q
+        })
+        .collect();
+    self.with_coeffs(coeffs)
+});
+
+impl_binop_with_refs!(NativePoly => Sub::sub(self, rhs: &NativePoly) -> NativePoly {
+    self + -rhs
+});
+
+impl Neg for NativePoly {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        -&self
+    }
+}
+
+impl Neg for &NativePoly {
+    type Output = NativePoly;
+
+    fn neg(self) -> Self::Output {
+        let q = self.modulus.as_ref();
+        let coeffs = self.coeffs.iter().map(|c| (q - c) % q).collect();
+        self.with_coeffs(coeffs)
+    }
+}
+
+impl AddAssign for NativePoly {
+    fn add_assign(&mut self, rhs: Self) {
+        *self += &rhs;
+    }
+}
+
+impl AddAssign<&NativePoly> for NativePoly {
+    fn add_assign(&mut self, rhs: &Self) {
+        #[cfg(feature = "checked")]
+        assert_operands("add", self, rhs);
+        self.debug_check_modulus(rhs);
+        let q = self.modulus.clone();
+        for (a, b) in self.coeffs.iter_mut().zip(rhs.coeffs.iter()) {
+            *a += b;
+            if &*a >= q.as_ref() {
+                *a -= q.as_ref();
+            }
+        }
+    }
+}
+
+impl MulAssign for NativePoly {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self *= &rhs;
+    }
+}
+
+impl MulAssign<&NativePoly> for NativePoly {
+    fn mul_assign(&mut self, rhs: &Self) {
+        *self = &*self * rhs;
+    }
+}
+
+impl SubAssign for NativePoly {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self -= &rhs;
+    }
+}
+
+impl SubAssign<&NativePoly> for NativePoly {
+    fn sub_assign(&mut self, rhs: &Self) {
+        *self += -rhs;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        compat::negacyclic_mul,
+        error::DiamondError,
+        poly::{
+            native::NativePolyUniformSampler,
+            sampler::{DistType, PolyUniformSampler},
+        },
+    };
+
+    fn values(poly: &NativePoly) -> Vec<BigUint> {
+        poly.coeffs().iter().map(|c| c.value().clone()).collect()
+    }
+
+    #[test]
+    fn test_native_poly_arithmetic() {
+        let params = NativePolyParams::new(8, (BigUint::from(1u8) << 120) + 451u32, 4);
+        let q = params.modulus();
+        assert_eq!(params.modulus_bits(), 121);
+        let sampler = NativePolyUniformSampler::new();
+        let a = sampler.sample_poly(&params, &DistType::FinRingDist);
+        let b = sampler.sample_poly(&params, &DistType::FinRingDist);
+
+        // Multiplication agrees with the reference negacyclic product
+        assert_eq!(values(&(&a * &b)), negacyclic_mul(&q, &values(&a), &values(&b)));
+
+        // Addition, subtraction and negation are consistent
+        assert_eq!(&a + &b - &b, a);
+        assert_eq!(&a + -&a, NativePoly::const_zero(&params));
+        assert_eq!(NativePoly::const_minus_one(&params) * &a, -a.clone());
+        let mut c = a.clone();
+        c += &b;
+        c -= &a;
+        assert_eq!(c, b);
+    }
+
+    #[test]
+    fn test_native_prime_cyclotomic() {
+        // Dimension 6 for the 7th cyclotomic polynomial
+        let params = NativePolyParams::with_ring_kind(
+            RingKind::PrimeCyclotomic,
+            6,
+            (BigUint::from(1u8) << 100) - 15u8,
+            4,
+        );
+        let q = params.modulus();
+        let sampler = NativePolyUniformSampler::new();
+        let a = sampler.sample_poly(&params, &DistType::FinRingDist);
+        let b = sampler.sample_poly(&params, &DistType::FinRingDist);
+
+        // Multiplication agrees with the full product reduced by x^6 = -(x^5 + ... + 1)
+        let mut expected = vec![BigUint::ZERO; 11];
+        for (i, a_i) in values(&a).iter().enumerate() {
+            for (j, b_j) in values(&b).iter().enumerate() {
+                expected[i + j] = (&expected[i + j] + a_i * b_j) % q.as_ref();
+            }
+        }
+        for degree in (6..11).rev() {
+            let top = expected.pop().unwrap();
+            for coeff in expected[degree - 6..degree].iter_mut() {
+                *coeff = (&*coeff + q.as_ref() - &top) % q.as_ref();
+            }
+        }
+        assert_eq!(values(&(&a * &b)), expected);
+
+        // Automorphisms are ring homomorphisms and x * x^5 wraps around
+        for k in 1..7 {
+            let product = a.automorphism(&params, k) * b.automorphism(&params, k);
+            assert_eq!((&a * &b).automorphism(&params, k), product);
+        }
+        let x = NativePoly::const_rotate_poly(&params, 1);
+        let x5 = NativePoly::const_rotate_poly(&params, 5);
+        let minus_one = q.as_ref() - 1u8;
+        assert_eq!(values(&(x * x5)), vec![minus_one; 6]);
+    }
+
+    #[test]
+    fn test_native_poly_automorphism() {
+        let params = NativePolyParams::new(8, (BigUint::from(1u8) << 120) + 451u32, 4);
+        let sampler = NativePolyUniformSampler::new();
+        let poly = sampler.sample_poly(&params, &DistType::FinRingDist);
+
+        // The index is taken modulo 2n, also when i * k would overflow
+        assert_eq!(poly.automorphism(&params, 3 + 16), poly.automorphism(&params, 3));
+        assert_eq!(poly.automorphism(&params, usize::MAX), poly.automorphism(&params, 15));
+        assert_eq!(poly.automorphism(&params, 17), poly);
+    }
+
+    #[test]
+    #[should_panic(expected = "automorphism index must be odd")]
+    fn test_native_poly_automorphism_even() {
+        let params = NativePolyParams::new(8, (BigUint::from(1u8) << 120) + 451u32, 4);
+        let _ = NativePoly::const_one(&params).automorphism(&params, 16 + 2);
+    }
+
+    #[test]
+    fn test_native_poly_decompose_and_bytes() {
+        let params = NativePolyParams::new(8, (BigUint::from(1u8) << 120) + 451u32, 4);
+        let sampler = NativePolyUniformSampler::new();
+        let poly = sampler.sample_poly(&params, &DistType::FinRingDist);
+
+        // The digits recompose to the polynomial
+        let decomposed = poly.decompose_base(&params);
+        assert_eq!(decomposed.len(), 31);
+        assert_eq!(NativePoly::from_decomposed(&params, &decomposed), poly);
+
+        // The compact encoding round-trips
+        let bytes = poly.to_compact_bytes();
+        assert_eq!(NativePoly::from_compact_bytes(&params, &bytes), poly);
+        assert_eq!(NativePoly::from_bytes(&params, &poly.to_bytes()), poly);
+    }
+
+    #[test]
+    fn test_native_poly_checked_ops() {
+        let params = NativePolyParams::new(8, (BigUint::from(1u8) << 120) + 451u32, 4);
+        let other_params = NativePolyParams::new(8, BigUint::from(65537u32), 4);
+        let sampler = NativePolyUniformSampler::new();
+        let a = sampler.sample_poly(&params, &DistType::FinRingDist);
+        let b = sampler.sample_poly(&params, &DistType::FinRingDist);
+        let c = sampler.sample_poly(&other_params, &DistType::FinRingDist);
+
+        // Operands of the same ring give the unchecked results
+        assert_eq!(a.checked_add(&b).unwrap(), &a + &b);
+        assert_eq!(a.checked_mul(&b).unwrap(), &a * &b);
+
+        // Mixed moduli are reported instead of reduced modulo one of them
+        assert!(a.operand_mismatch(&c).unwrap().contains("moduli"));
+        assert!(matches!(a.checked_add(&c), Err(DiamondError::OperandMismatch { op: "add", .. })));
+        assert!(matches!(c.checked_mul(&a), Err(DiamondError::OperandMismatch { op: "mul", .. })));
+    }
+
+    #[cfg(feature = "checked")]
+    #[test]
+    #[should_panic(expected = "operands of add do not match")]
+    fn test_native_poly_checked_operator() {
+        let params = NativePolyParams::new(8, (BigUint::from(1u8) << 120) + 451u32, 4);
+        let other_params = NativePolyParams::new(8, BigUint::from(65537u32), 4);
+        let _ = NativePoly::const_one(&params) + NativePoly::const_one(&other_params);
+    }
+}
@@ -0,0 +1,151 @@
+use super::{NativePoly, NativePolyMatrix, NativePolyParams};
+use crate::poly::{
+    rng::CryptoRngSource,
+    sampler::{DistType, PolyHashSampler, PolyUniformSampler},
+    sampling::sample_coeffs,
+    Poly, PolyMatrix, PolyParams,
+};
+use digest::Digest;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::{marker::PhantomData, sync::Arc};
+
+/// Samples a polynomial from `dist` with the randomness of `rng`.
+fn sample_poly_with<R: RngCore>(
+    params: &NativePolyParams,
+    dist: &DistType,
+    rng: &mut R,
+) -> NativePoly {
+    let n = params.ring_dimension() as usize;
+    NativePoly::from_coeffs(params, &sample_coeffs(&params.modulus(), n, dist, rng))
+}
+
+fn sample_matrix_with<R: RngCore>(
+    params: &NativePolyParams,
+    nrow: usize,
+    ncol: usize,
+    dist: &DistType,
+    rng: &mut R,
+) -> NativePolyMatrix {
+    let entries = (0..nrow)
+        .map(|_| (0..ncol).map(|_| sample_poly_with(params, dist, rng)).collect())
+        .collect();
+    NativePolyMatrix::from_poly_vec(params, entries)
+}
+
+/// Samples from the randomness source it was created with, one stream of it per entry.
+#[derive(Debug, Clone)]
+pub struct NativePolyUniformSampler {
+    source: Arc<dyn CryptoRngSource>,
+}
+
+impl Default for NativePolyUniformSampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PolyUniformSampler for NativePolyUniformSampler {
+    type M = NativePolyMatrix;
+
+    fn with_source(source: Arc<dyn CryptoRngSource>) -> Self {
+        Self { source }
+    }
+
+    fn sample_poly(&self, params: &NativePolyParams, dist: &DistType) -> NativePoly {
+        sample_poly_with(params, dist, &mut self.source.streams().rng(0, 0))
+    }
+
+    fn sample_uniform(
+        &self,
+        params: &NativePolyParams,
+        nrow: usize,
+        ncol: usize,
+        dist: DistType,
+    ) -> NativePolyMatrix {
+        let streams = self.source.streams();
+        let entries = (0..nrow)
+            .map(|i| {
+                (0..ncol).map(|j| sample_poly_with(params, &dist, &mut streams.rng(i, j))).collect()
+            })
+            .collect();
+        NativePolyMatrix::from_poly_vec(params, entries)
+    }
+}
+
+/// Expands `H(key || tag)` with ChaCha20 into the entries of the matrix, row by row.
+pub struct NativePolyHashSampler<H: Digest> {
+    _h: PhantomData<H>,
+}
+
+impl<H: Digest> PolyHashSampler<[u8; 32]> for NativePolyHashSampler<H> {
+    type M = NativePolyMatrix;
+    type Hash = H;
+
+    fn new() -> Self {
+        Self { _h: PhantomData }
+    }
+
+    fn sample_hash<B: AsRef<[u8]>>(
+        &self,
+        params: &NativePolyParams,
+        hash_key: [u8; 32],
+        tag: B,
+        nrow: usize,
+        ncol: usize,
+        dist: DistType,
+    ) -> NativePolyMatrix {
+        let mut hasher = H::new();
+        hasher.update(hash_key);
+        hasher.update(tag.as_ref());
+        hasher.update((nrow as u64).to_le_bytes());
+        hasher.update((ncol as u64).to_le_bytes());
+        let digest = hasher.finalize();
+        let mut seed = [0u8; 32];
+        let len = digest.len().min(32);
+        seed[..len].copy_from_slice(&digest[..len]);
+        sample_matrix_with(params, nrow, ncol, &dist, &mut ChaCha20Rng::from_seed(seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::rng::ChaChaSource;
+    use keccak_asm::Keccak256;
+
+    #[test]
+    fn test_native_hash_sampler() {
+        let params = NativePolyParams::default();
+        let key: [u8; 32] = rand::random();
+        let sampler = NativePolyHashSampler::<Keccak256>::new();
+
+        // The same key and tag give the same matrix, another tag another one
+        let a = sampler.sample_hash(&params, key, b"tag", 2, 3, DistType::FinRingDist);
+        assert_eq!(a.size(), (2, 3));
+        assert_eq!(a, sampler.sample_hash(&params, key, b"tag", 2, 3, DistType::FinRingDist));
+        assert_ne!(a, sampler.sample_hash(&params, key, b"other", 2, 3, DistType::FinRingDist));
+
+        // Bit matrices only have binary coefficients
+        let bits = sampler.sample_hash(&params, key, b"tag", 2, 3, DistType::BitDist);
+        for i in 0..2 {
+            for j in 0..3 {
+                bits.entry(i, j).to_bool_vec();
+            }
+        }
+    }
+
+    #[test]
+    fn test_native_uniform_sampler_source() {
+        let params = NativePolyParams::default();
+
+        // Samplers over the same seeded source draw the same matrices
+        let sample = |seed| {
+            let source = Arc::new(ChaChaSource::for_test(seed));
+            let sampler = NativePolyUniformSampler::with_source(source);
+            sampler.sample_uniform(&params, 2, 2, DistType::GaussDist { sigma: 3.0 })
+        };
+        assert_eq!(sample(1), sample(1));
+        assert_ne!(sample(1), sample(2));
+    }
+}
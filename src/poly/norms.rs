@@ -0,0 +1,166 @@
+//! Norms of ring elements and matrices over the centered representatives of their coefficients
+//! in `(-q/2, q/2]`, to quantify noise growth. For [`crate::poly::dcrt::DCRTPoly`] the
+//! coefficients are the CRT interpolation of the towers, as returned by [`Poly::coeffs`].
+use super::{plaintext::modulus_biguint, Poly, PolyElem, PolyMatrix};
+use num_bigint::BigUint;
+use num_traits::{ToPrimitive, Zero};
+use std::f64::consts::PI;
+
+/// Returns `|c|` for the centered representative of `c mod q`.
+pub fn centered_abs(c: &BigUint, q: &BigUint) -> BigUint {
+    let neg = q - c;
+    if c > &neg {
+        neg
+    } else {
+        c.clone()
+    }
+}
+
+pub(crate) fn centered_f64(c: &BigUint, q: &BigUint) -> f64 {
+    let abs = centered_abs(c, q).to_f64().unwrap_or(f64::INFINITY);
+    if c > &(q >> 1) {
+        -abs
+    } else {
+        abs
+    }
+}
+
+/// The largest absolute value of a coefficient.
+pub fn inf_norm<P: Poly>(params: &P::Params, poly: &P) -> BigUint {
+    let q = modulus_biguint::<P>(params);
+    poly.coeffs()
+        .iter()
+        .map(|coeff| centered_abs(coeff.to_biguint(), &q))
+        .max()
+        .unwrap_or_else(BigUint::zero)
+}
+
+/// The Euclidean norm of the coefficient vector.
+pub fn l2_norm<P: Poly>(params: &P::Params, poly: &P) -> f64 {
+    sum_of_squares(params, poly).to_f64().unwrap_or(f64::INFINITY).sqrt()
+}
+
+fn sum_of_squares<P: Poly>(params: &P::Params, poly: &P) -> BigUint {
+    let q = modulus_biguint::<P>(params);
+    poly.coeffs()
+        .iter()
+        .map(|coeff| {
+            let abs = centered_abs(coeff.to_biguint(), &q);
+            &abs * &abs
+        })
+        .sum()
+}
+
+/// The infinity norm of the canonical embedding of `Z[X]/(X^n + 1)`, i.e. the largest `|p(ζ)|`
+/// over the primitive `2n`-th roots of unity `ζ`, computed in floating point.
+pub fn canonical_norm<P: Poly>(params: &P::Params, poly: &P) -> f64 {
+    let q = modulus_biguint::<P>(params);
+    let coeffs =
+        poly.coeffs().iter().map(|coeff| centered_f64(coeff.to_biguint(), &q)).collect::<Vec<_>>();
+    let n = coeffs.len();
+    (0..n)
+        .map(|j| {
+            let (mut re, mut im) = (0.0, 0.0);
+            for (k, coeff) in coeffs.iter().enumerate() {
+                let angle = PI * ((2 * j + 1) * k % (2 * n)) as f64 / n as f64;
+                re += coeff * angle.cos();
+                im += coeff * angle.sin();
+            }
+            (re * re + im * im).sqrt()
+        })
+        .fold(0.0, f64::max)
+}
+
+fn entries<M: PolyMatrix>(matrix: &M) -> impl Iterator<Item = M::P> + '_ {
+    (0..matrix.row_size()).flat_map(move |i| matrix.get_row(i))
+}
+
+/// The largest absolute value of a coefficient of any entry.
+pub fn matrix_inf_norm<M: PolyMatrix>(params: &<M::P as Poly>::Params, matrix: &M) -> BigUint {
+    entries(matrix).map(|entry| inf_norm(params, &entry)).max().unwrap_or_else(BigUint::zero)
+}
+
+/// The Euclidean norm of all coefficients of all entries.
+pub fn matrix_l2_norm<M: PolyMatrix>(params: &<M::P as Poly>::Params, matrix: &M) -> f64 {
+    let sum: BigUint = entries(matrix).map(|entry| sum_of_squares(params, &entry)).sum();
+    sum.to_f64().unwrap_or(f64::INFINITY).sqrt()
+}
+
+/// The largest [`canonical_norm`] of any entry.
+pub fn matrix_canonical_norm<M: PolyMatrix>(params: &<M::P as Poly>::Params, matrix: &M) -> f64 {
+    entries(matrix).map(|entry| canonical_norm(params, &entry)).fold(0.0, f64::max)
+}
+
+/// Returns `after / before`, e.g. by how much an error grew through an operation.
+pub fn growth_factor(before: &BigUint, after: &BigUint) -> f64 {
+    let before = before.to_f64().unwrap_or(f64::INFINITY);
+    let after = after.to_f64().unwrap_or(f64::INFINITY);
+    after / before
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        poly::{
+            dcrt::{DCRTPoly, DCRTPolyMatrix, DCRTPolyParams, FinRingElem},
+            PolyParams,
+        },
+        utils::create_bit_random_poly,
+    };
+    use num_bigint::BigInt;
+
+    fn poly_from_signed(params: &DCRTPolyParams, coeffs: &[i64]) -> DCRTPoly {
+        let mut coeffs = coeffs
+            .iter()
+            .map(|&c| FinRingElem::new(BigInt::from(c), params.modulus()))
+            .collect::<Vec<_>>();
+        coeffs.resize(params.ring_dimension() as usize, FinRingElem::new(0u64, params.modulus()));
+        DCRTPoly::from_coeffs(params, &coeffs)
+    }
+
+    #[test]
+    fn test_poly_norms() {
+        let params = DCRTPolyParams::default();
+
+        // 3 - 4X has infinity norm 4 and L2 norm 5
+        let poly = poly_from_signed(&params, &[3, -4]);
+        assert_eq!(inf_norm(&params, &poly), BigUint::from(4u8));
+        assert!((l2_norm(&params, &poly) - 5.0).abs() < 1e-9);
+
+        // Constants embed to themselves and monomials to roots of unity
+        let constant = poly_from_signed(&params, &[-7]);
+        assert!((canonical_norm(&params, &constant) - 7.0).abs() < 1e-9);
+        let monomial = poly_from_signed(&params, &[0, 0, 1]);
+        assert!((canonical_norm(&params, &monomial) - 1.0).abs() < 1e-9);
+
+        // The embedding scales the L2 norm by sqrt(n) on average, so l2 <= canonical <= sqrt(n) l2
+        let n = params.ring_dimension() as f64;
+        for _ in 0..10 {
+            let poly = create_bit_random_poly(&params);
+            let l2 = l2_norm(&params, &poly);
+            let canonical = canonical_norm(&params, &poly);
+            assert!(l2 <= canonical + 1e-9);
+            assert!(canonical <= n.sqrt() * l2 + 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_matrix_norms() {
+        let params = DCRTPolyParams::default();
+        let entries = vec![
+            vec![poly_from_signed(&params, &[1, -2]), poly_from_signed(&params, &[0])],
+            vec![poly_from_signed(&params, &[0, 0, 2]), poly_from_signed(&params, &[-6])],
+        ];
+        let matrix = DCRTPolyMatrix::from_poly_vec(&params, entries);
+        assert_eq!(matrix_inf_norm(&params, &matrix), BigUint::from(6u8));
+        assert!((matrix_l2_norm(&params, &matrix) - 45f64.sqrt()).abs() < 1e-9);
+        assert!((matrix_canonical_norm(&params, &matrix) - 6.0).abs() < 1e-9);
+
+        // Doubling the matrix doubles the norm
+        let doubled = matrix.clone() + &matrix;
+        let factor =
+            growth_factor(&matrix_inf_norm(&params, &matrix), &matrix_inf_norm(&params, &doubled));
+        assert_eq!(factor, 2.0);
+    }
+}
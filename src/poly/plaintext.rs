@@ -0,0 +1,100 @@
+use super::{Elem, Poly, PolyElem, PolyParams};
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+
+/// Returns the modulus `q` as an integer.
+pub(crate) fn modulus_biguint<P: Poly>(params: &P::Params) -> BigUint {
+    <P::Elem as PolyElem>::max_q(&params.modulus()).to_biguint() + BigUint::from(1u8)
+}
+
+/// Returns the scaling factor `Δ = ⌊q / t⌋` for the plaintext modulus `t`.
+pub fn scaling_factor(q: &BigUint, t: u64) -> BigUint {
+    assert!(t >= 2, "plaintext modulus must be at least 2");
+    assert!(q >= &BigUint::from(t), "plaintext modulus must not exceed q");
+    q / t
+}
+
+/// Encodes messages in `Z_t`, which must share the plaintext modulus `t`, into the coefficients
+/// of a polynomial as `m_i * Δ`. Missing coefficients are set to zero.
+pub fn encode<P: Poly>(params: &P::Params, msgs: &[Elem]) -> P {
+    let n = params.ring_dimension() as usize;
+    assert!(msgs.len() <= n, "too many messages for the ring dimension");
+    let modulus = params.modulus();
+    let mut coeffs = vec![<P::Elem as PolyElem>::zero(&modulus); n];
+    if let Some(first) = msgs.first() {
+        let t = first.modulus();
+        let delta = scaling_factor(&modulus_biguint::<P>(params), t);
+        for (coeff, msg) in coeffs.iter_mut().zip(msgs.iter()) {
+            assert_eq!(msg.modulus(), t, "messages of different plaintext moduli");
+            let value = &delta * msg.value();
+            *coeff = <P::Elem as PolyElem>::from_bytes(&modulus, &value.to_bytes_le());
+        }
+    }
+    P::from_coeffs(params, &coeffs)
+}
+
+/// Decodes every coefficient `c` of the polynomial to `⌊t * c / q⌉ mod t`.
+pub fn decode<P: Poly>(params: &P::Params, poly: &P, t: u64) -> Vec<Elem> {
+    let q = modulus_biguint::<P>(params);
+    let half_q = &q >> 1;
+    poly.coeffs()
+        .iter()
+        .map(|coeff| {
+            let scaled = (coeff.to_biguint() * t + &half_q) / &q;
+            Elem::new((scaled % t).to_u64().expect("decoded value must fit in u64"), t)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::{
+        dcrt::{DCRTPoly, DCRTPolyParams, DCRTPolyUniformSampler},
+        sampler::{DistType, PolyUniformSampler},
+    };
+    use rand::Rng;
+
+    #[test]
+    fn test_plaintext_encode_decode() {
+        let params = DCRTPolyParams::default();
+        let n = params.ring_dimension() as usize;
+        let mut rng = rand::rng();
+        for t in [2u64, 3, 16] {
+            let msgs = (0..n).map(|_| Elem::new(rng.random_range(0..t), t)).collect::<Vec<_>>();
+            let encoded: DCRTPoly = encode(&params, &msgs);
+            assert_eq!(decode(&params, &encoded, t), msgs);
+        }
+    }
+
+    #[test]
+    fn test_plaintext_decode_with_error() {
+        let params = DCRTPolyParams::default();
+        let sampler = DCRTPolyUniformSampler::new();
+        let n = params.ring_dimension() as usize;
+        let t = 4;
+        let msgs = (0..n as u64).map(|i| Elem::new(i, t)).collect::<Vec<_>>();
+        let encoded: DCRTPoly = encode(&params, &msgs);
+
+        // Small Gaussian noise must be rounded away
+        let error = sampler.sample_poly(&params, &DistType::GaussDist { sigma: 3.0 });
+        assert_eq!(decode(&params, &(encoded + error), t), msgs);
+    }
+
+    #[test]
+    fn test_plaintext_partial_message() {
+        let params = DCRTPolyParams::default();
+        let n = params.ring_dimension() as usize;
+        let encoded: DCRTPoly = encode(&params, &[Elem::new(1, 2)]);
+        let mut expected = vec![Elem::new(0, 2); n];
+        expected[0] = Elem::new(1, 2);
+        assert_eq!(decode(&params, &encoded, 2), expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "messages of different plaintext moduli")]
+    fn test_plaintext_mixed_moduli() {
+        let params = DCRTPolyParams::default();
+        let _: DCRTPoly = encode(&params, &[Elem::new(1, 2), Elem::new(1, 3)]);
+    }
+}
@@ -1,4 +1,8 @@
-use super::{Poly, PolyParams};
+use super::{
+    arena::decompose_rows, norms::matrix_inf_norm, plaintext::modulus_biguint, Poly, PolyElem,
+    PolyParams,
+};
+use num_bigint::BigUint;
 use std::{
     fmt::Debug,
     ops::{Add, Mul, Neg, Sub},
@@ -38,6 +42,7 @@ pub trait PolyMatrix:
         let wrapped_vec = vec.into_iter().map(|elem| vec![elem]).collect();
         Self::from_poly_vec(params, wrapped_vec)
     }
+    fn params(&self) -> &<Self::P as Poly>::Params;
     fn entry(&self, i: usize, j: usize) -> Self::P;
     fn get_row(&self, i: usize) -> Vec<Self::P>;
     fn get_column(&self, j: usize) -> Vec<Self::P>;
@@ -91,11 +96,59 @@ pub trait PolyMatrix:
     /// A matrix of dimension n×(n·log_b(q)), in which each block row is a scaled identity
     /// under the ring modulus.
     fn gadget_matrix(params: &<Self::P as Poly>::Params, size: usize) -> Self;
-    fn decompose(&self) -> Self;
+    /// Applies [`Poly::automorphism`] to every entry. Since the gadget matrix has constant
+    /// entries it is fixed by every automorphism.
+    fn automorphism(&self, params: &<Self::P as Poly>::Params, k: usize) -> Self {
+        let entries = (0..self.row_size())
+            .map(|i| self.get_row(i).iter().map(|poly| poly.automorphism(params, k)).collect())
+            .collect();
+        Self::from_poly_vec(params, entries)
+    }
+    /// Computes `G^-1(self)` with [`Poly::decompose_base`]: the digits of row `i` are the rows
+    /// `i * k..(i + 1) * k` for `k = modulus_digits()`, so that `G * G^-1(self) = self`. The
+    /// default works row by row; the built-in backends override it with a block-wise parallel one.
+    fn decompose(&self) -> Self {
+        let params = self.params();
+        let rows = decompose_rows(
+            self.row_size(),
+            params.modulus_digits(),
+            |i| self.get_row(i),
+            |poly| poly.decompose_base(params),
+        );
+        Self::from_poly_vec(params, rows)
+    }
+    /// Rescales every coefficient `c` to `floor(c * q' / q) mod q'`. The entries keep their
+    /// parameters, so the result is read as a matrix modulo `q'` by the caller. Overridden by the
+    /// built-in backends like [`Self::decompose`].
     fn modulus_switch(
         &self,
         new_modulus: &<<Self::P as Poly>::Params as PolyParams>::Modulus,
-    ) -> Self;
+    ) -> Self {
+        let params = self.params();
+        let modulus = params.modulus();
+        let q = modulus_biguint::<Self::P>(params);
+        let new_q = <<Self::P as Poly>::Elem as PolyElem>::max_q(new_modulus).to_biguint() + 1u8;
+        let switch = |poly: &Self::P| {
+            let coeffs = poly
+                .coeffs()
+                .iter()
+                .map(|coeff| {
+                    let value = (coeff.to_biguint() * &new_q / &q) % &new_q;
+                    <Self::P as Poly>::Elem::from_bytes(&modulus, &value.to_bytes_le())
+                })
+                .collect::<Vec<_>>();
+            Self::P::from_coeffs(params, &coeffs)
+        };
+        let rows = (0..self.row_size())
+            .map(|i| self.get_row(i).iter().map(switch).collect())
+            .collect();
+        Self::from_poly_vec(params, rows)
+    }
+    /// The largest absolute value of a centered coefficient of any entry, see
+    /// [`crate::poly::norms::matrix_inf_norm`].
+    fn max_norm(&self) -> BigUint {
+        matrix_inf_norm(self.params(), self)
+    }
     /// Performs the operation S * (identity ⊗ other)
     fn mul_tensor_identity(&self, other: &Self, identity_size: usize) -> Self;
     /// Performs the operation S * (identity ⊗ G^-1(other)),
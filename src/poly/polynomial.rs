@@ -6,7 +6,8 @@ use std::{
 };
 use tokio;
 
-use super::element::PolyElem;
+use super::{dcrt::compact::is_compact_layout, element::PolyElem};
+use crate::error::DiamondError;
 
 pub trait PolyParams: Clone + Debug + PartialEq + Eq + Send + Sync {
     type Modulus: Debug + Clone;
@@ -60,6 +61,12 @@ pub trait Poly:
         Self::from_coeffs(params, &coeffs)
     }
     fn from_compact_bytes(params: &Self::Params, bytes: &[u8]) -> Self;
+    /// Like [`Self::from_compact_bytes`], returning `None` for bytes that do not have the compact
+    /// layout of a polynomial of these parameters instead of panicking.
+    fn try_from_compact_bytes(params: &Self::Params, bytes: &[u8]) -> Option<Self> {
+        is_compact_layout(params.ring_dimension() as usize, bytes)
+            .then(|| Self::from_compact_bytes(params, bytes))
+    }
     fn coeffs(&self) -> Vec<Self::Elem>;
     fn coeffs_digits(&self) -> Vec<u32> {
         self.coeffs()
@@ -86,6 +93,23 @@ pub trait Poly:
         Self::from_coeffs(params, &coeffs)
     }
     fn const_max(params: &Self::Params) -> Self;
+    /// Applies the ring automorphism `X -> X^k` for a `k` that is odd modulo `2n`, using
+    /// `X^n = -1` to map the coefficient of `X^i` to `X^(i * k mod 2n)`.
+    fn automorphism(&self, params: &Self::Params, k: usize) -> Self {
+        let n = params.ring_dimension() as usize;
+        let k = k % (2 * n);
+        assert!(k % 2 == 1, "automorphism index must be odd, got {}", k);
+        let mut coeffs = Self::const_zero(params).coeffs();
+        for (i, coeff) in self.coeffs().into_iter().enumerate() {
+            let j = (i * k) % (2 * n);
+            if j < n {
+                coeffs[j] = coeff;
+            } else {
+                coeffs[j - n] = -coeff;
+            }
+        }
+        Self::from_coeffs(params, &coeffs)
+    }
     fn extract_bits_with_threshold(&self, params: &Self::Params) -> Vec<bool>;
     fn decompose_base(&self, params: &Self::Params) -> Vec<Self>;
     fn to_bytes(&self) -> Vec<u8> {
@@ -98,6 +122,20 @@ pub trait Poly:
     fn to_bool_vec(&self) -> Vec<bool>;
     fn to_compact_bytes(&self) -> Vec<u8>;
 
+    /// Describes why `self` and `rhs` do not belong to the same ring, e.g. because their moduli
+    /// differ, or returns `None` if they can be combined.
+    fn operand_mismatch(&self, rhs: &Self) -> Option<String>;
+    /// `self + rhs`, failing instead of mixing polynomials of different rings.
+    fn checked_add(&self, rhs: &Self) -> Result<Self, DiamondError> {
+        check_operands("add", self, rhs)?;
+        Ok(self.clone() + rhs)
+    }
+    /// `self * rhs`, failing instead of mixing polynomials of different rings.
+    fn checked_mul(&self, rhs: &Self) -> Result<Self, DiamondError> {
+        check_operands("mul", self, rhs)?;
+        Ok(self.clone() * rhs)
+    }
+
     /// Reads a polynomial with id from files under the given directory.
     fn read_from_file<P: AsRef<Path> + Send + Sync>(
         params: &Self::Params,
@@ -130,3 +168,20 @@ pub trait Poly:
         }
     }
 }
+
+/// Fails with [`DiamondError::OperandMismatch`] if `lhs` and `rhs` belong to different rings.
+pub fn check_operands<P: Poly>(op: &'static str, lhs: &P, rhs: &P) -> Result<(), DiamondError> {
+    match lhs.operand_mismatch(rhs) {
+        Some(reason) => Err(DiamondError::OperandMismatch { op, reason }),
+        None => Ok(()),
+    }
+}
+
+/// Checks the operands of every ring operation of the backends under the `checked` feature, so
+/// that mixed-up moduli panic at the operation instead of producing garbage further on.
+#[cfg(feature = "checked")]
+pub(crate) fn assert_operands<P: Poly>(op: &'static str, lhs: &P, rhs: &P) {
+    if let Err(err) = check_operands(op, lhs, rhs) {
+        panic!("{}", err);
+    }
+}
@@ -0,0 +1,236 @@
+use super::{Poly, PolyMatrix};
+use std::{
+    array,
+    ops::{Add, Mul, Neg, Sub},
+};
+
+/// A matrix over the ring with dimensions fixed at compile time.
+///
+/// Entries are stored inline in nested arrays, which avoids the heap allocations and the parallel
+/// dispatch of [`PolyMatrix`] implementations for the tiny matrices used in tests and small
+/// parameter sets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RingMatrix<P: Poly, const R: usize, const C: usize> {
+    entries: [[P; C]; R],
+}
+
+impl<P: Poly, const R: usize, const C: usize> RingMatrix<P, R, C> {
+    pub fn from_fn<F: FnMut(usize, usize) -> P>(mut f: F) -> Self {
+        Self { entries: array::from_fn(|i| array::from_fn(|j| f(i, j))) }
+    }
+
+    pub fn zero(params: &P::Params) -> Self {
+        Self::from_fn(|_, _| P::const_zero(params))
+    }
+
+    pub fn entry(&self, i: usize, j: usize) -> &P {
+        &self.entries[i][j]
+    }
+
+    pub fn transpose(&self) -> RingMatrix<P, C, R> {
+        RingMatrix::from_fn(|i, j| self.entries[j][i].clone())
+    }
+
+    /// Converts a dynamically sized matrix, panicking if its size is not `R x C`.
+    pub fn from_poly_matrix<M: PolyMatrix<P = P>>(matrix: &M) -> Self {
+        assert_eq!(matrix.size(), (R, C), "matrix size mismatch");
+        Self::from_fn(|i, j| matrix.entry(i, j))
+    }
+
+    pub fn to_poly_matrix<M: PolyMatrix<P = P>>(&self, params: &P::Params) -> M {
+        M::from_poly_vec(params, self.entries.iter().map(|row| row.to_vec()).collect())
+    }
+}
+
+impl<P: Poly, const N: usize> RingMatrix<P, N, N> {
+    pub fn identity(params: &P::Params) -> Self {
+        Self::from_fn(|i, j| if i == j { P::const_one(params) } else { P::const_zero(params) })
+    }
+}
+
+impl<P: Poly, const R: usize, const C: usize> Add<&Self> for RingMatrix<P, R, C> {
+    type Output = Self;
+    fn add(self, rhs: &Self) -> Self {
+        let mut entries = self.entries;
+        for i in 0..R {
+            for j in 0..C {
+                entries[i][j] += rhs.entries[i][j].clone();
+            }
+        }
+        Self { entries }
+    }
+}
+
+impl<P: Poly, const R: usize, const C: usize> Add for RingMatrix<P, R, C> {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        self + &rhs
+    }
+}
+
+impl<P: Poly, const R: usize, const C: usize> Sub<&Self> for RingMatrix<P, R, C> {
+    type Output = Self;
+    fn sub(self, rhs: &Self) -> Self {
+        let mut entries = self.entries;
+        for i in 0..R {
+            for j in 0..C {
+                entries[i][j] -= rhs.entries[i][j].clone();
+            }
+        }
+        Self { entries }
+    }
+}
+
+impl<P: Poly, const R: usize, const C: usize> Sub for RingMatrix<P, R, C> {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        self - &rhs
+    }
+}
+
+impl<P: Poly, const R: usize, const C: usize> Neg for RingMatrix<P, R, C> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        let mut entries = self.entries;
+        for row in entries.iter_mut() {
+            for entry in row.iter_mut() {
+                *entry = -entry.clone();
+            }
+        }
+        Self { entries }
+    }
+}
+
+impl<P: Poly, const R: usize, const C: usize, const K: usize> Mul<&RingMatrix<P, C, K>>
+    for &RingMatrix<P, R, C>
+{
+    type Output = RingMatrix<P, R, K>;
+    fn mul(self, rhs: &RingMatrix<P, C, K>) -> RingMatrix<P, R, K> {
+        assert!(C > 0, "inner dimension must be positive");
+        RingMatrix::from_fn(|i, j| {
+            let mut acc = self.entries[i][0].clone() * &rhs.entries[0][j];
+            for k in 1..C {
+                acc += self.entries[i][k].clone() * &rhs.entries[k][j];
+            }
+            acc
+        })
+    }
+}
+
+impl<P: Poly, const R: usize, const C: usize, const K: usize> Mul<RingMatrix<P, C, K>>
+    for RingMatrix<P, R, C>
+{
+    type Output = RingMatrix<P, R, K>;
+    fn mul(self, rhs: RingMatrix<P, C, K>) -> RingMatrix<P, R, K> {
+        &self * &rhs
+    }
+}
+
+/// The largest dimension [`mul_fixed_block`] multiplies through a [`RingMatrix`].
+pub const MAX_FIXED_DIM: usize = 4;
+
+/// Multiplies two blocks of entries as [`RingMatrix`] values of the matching dimensions when
+/// none exceeds [`MAX_FIXED_DIM`], and returns `None` otherwise. Matrix products whose blocks are
+/// this small thus use the fixed-size product without callers naming the dimensions.
+pub fn mul_fixed_block<P: Poly>(lhs: &[Vec<P>], rhs: &[Vec<P>]) -> Option<Vec<Vec<P>>> {
+    if lhs.is_empty() || lhs[0].len() != rhs.len() {
+        return None;
+    }
+    match lhs.len() {
+        1 => mul_fixed_rows::<P, 1>(lhs, rhs),
+        2 => mul_fixed_rows::<P, 2>(lhs, rhs),
+        3 => mul_fixed_rows::<P, 3>(lhs, rhs),
+        4 => mul_fixed_rows::<P, 4>(lhs, rhs),
+        _ => None,
+    }
+}
+
+fn mul_fixed_rows<P: Poly, const R: usize>(lhs: &[Vec<P>], rhs: &[Vec<P>]) -> Option<Vec<Vec<P>>> {
+    match rhs.len() {
+        1 => mul_fixed_inner::<P, R, 1>(lhs, rhs),
+        2 => mul_fixed_inner::<P, R, 2>(lhs, rhs),
+        3 => mul_fixed_inner::<P, R, 3>(lhs, rhs),
+        4 => mul_fixed_inner::<P, R, 4>(lhs, rhs),
+        _ => None,
+    }
+}
+
+fn mul_fixed_inner<P: Poly, const R: usize, const C: usize>(
+    lhs: &[Vec<P>],
+    rhs: &[Vec<P>],
+) -> Option<Vec<Vec<P>>> {
+    match rhs[0].len() {
+        1 => Some(mul_fixed::<P, R, C, 1>(lhs, rhs)),
+        2 => Some(mul_fixed::<P, R, C, 2>(lhs, rhs)),
+        3 => Some(mul_fixed::<P, R, C, 3>(lhs, rhs)),
+        4 => Some(mul_fixed::<P, R, C, 4>(lhs, rhs)),
+        _ => None,
+    }
+}
+
+fn mul_fixed<P: Poly, const R: usize, const C: usize, const K: usize>(
+    lhs: &[Vec<P>],
+    rhs: &[Vec<P>],
+) -> Vec<Vec<P>> {
+    let lhs = RingMatrix::<P, R, C>::from_fn(|i, j| lhs[i][j].clone());
+    let rhs = RingMatrix::<P, C, K>::from_fn(|i, j| rhs[i][j].clone());
+    (&lhs * &rhs).entries.into_iter().map(Vec::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        poly::{
+            dcrt::{DCRTPoly, DCRTPolyMatrix, DCRTPolyParams},
+            matrix::naive_mul_block,
+        },
+        utils::create_random_poly,
+    };
+
+    #[test]
+    fn test_ring_matrix_mul_matches_poly_matrix() {
+        let params = DCRTPolyParams::default();
+        let a = RingMatrix::<DCRTPoly, 2, 3>::from_fn(|_, _| create_random_poly(&params));
+        let b = RingMatrix::<DCRTPoly, 3, 2>::from_fn(|_, _| create_random_poly(&params));
+        let product = &a * &b;
+
+        let a_dyn: DCRTPolyMatrix = a.to_poly_matrix(&params);
+        let b_dyn: DCRTPolyMatrix = b.to_poly_matrix(&params);
+        let expected = RingMatrix::<DCRTPoly, 2, 2>::from_poly_matrix(&(a_dyn * b_dyn));
+        assert_eq!(product, expected);
+    }
+
+    #[test]
+    fn test_ring_matrix_add_sub_identity() {
+        let params = DCRTPolyParams::default();
+        let a = RingMatrix::<DCRTPoly, 3, 3>::from_fn(|_, _| create_random_poly(&params));
+        let b = RingMatrix::<DCRTPoly, 3, 3>::from_fn(|_, _| create_random_poly(&params));
+        let identity = RingMatrix::<DCRTPoly, 3, 3>::identity(&params);
+
+        assert_eq!(a.clone() + &b - &b, a);
+        assert_eq!(a.clone() - &a, RingMatrix::zero(&params));
+        assert_eq!(-a.clone() + &a, RingMatrix::zero(&params));
+        assert_eq!(&a * &identity, a);
+        assert_eq!(a.transpose().transpose(), a);
+    }
+
+    #[test]
+    fn test_mul_fixed_block() {
+        let params = DCRTPolyParams::default();
+        let block = |nrow: usize, ncol: usize| {
+            (0..nrow)
+                .map(|_| (0..ncol).map(|_| create_random_poly(&params)).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        };
+
+        // Blocks within the fixed dimensions agree with the naive product
+        let (lhs, rhs) = (block(2, 3), block(3, 4));
+        let product = mul_fixed_block(&lhs, &rhs).unwrap();
+        assert_eq!(product, naive_mul_block(lhs, rhs));
+
+        // Larger blocks are left to the caller
+        let (lhs, rhs) = (block(MAX_FIXED_DIM + 1, 2), block(2, 2));
+        assert!(mul_fixed_block(&lhs, &rhs).is_none());
+    }
+}
@@ -0,0 +1,186 @@
+//! Sources of the randomness used to sample secrets, errors, trapdoors and keys.
+//!
+//! Samplers hold an `Arc<dyn CryptoRngSource>` instead of reaching for a global generator, so
+//! that an application can draw from the OS, a seeded ChaCha stream, a hardware generator or a
+//! deterministic stream in tests. Sources are shared across rayon workers and therefore fill
+//! buffers through `&self`. A sampling call draws one [`RngStreams`] key from the source and
+//! derives the generator of every entry or parallel task from its position, so that a seeded
+//! source gives the same values whatever the number of threads and their scheduling.
+//!
+//! The only randomness still drawn inside OpenFHE, from its own generator, is that of the
+//! perturbation `p1` and the gadget preimages of trapdoor preimages.
+use rand::{RngCore, SeedableRng, TryRngCore};
+use rand_chacha::ChaCha20Rng;
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+
+pub trait CryptoRngSource: Debug + Send + Sync {
+    fn fill_bytes(&self, dest: &mut [u8]);
+
+    /// Draws the key of the generators of one sampling call.
+    fn streams(&self) -> RngStreams {
+        let mut seed = [0u8; 32];
+        self.fill_bytes(&mut seed);
+        RngStreams { seed }
+    }
+}
+
+/// The generators of one sampling call, one ChaCha20 stream per position `(i, j)` under a key
+/// drawn from a [`CryptoRngSource`].
+#[derive(Clone, Copy)]
+pub struct RngStreams {
+    seed: [u8; 32],
+}
+
+impl RngStreams {
+    /// The generator of position `(i, j)`, e.g. of the entry `(i, j)` of a sampled matrix.
+    pub fn rng(&self, i: usize, j: usize) -> ChaCha20Rng {
+        assert!(i < 1 << 32 && j < 1 << 32, "position out of the stream range");
+        let mut rng = ChaCha20Rng::from_seed(self.seed);
+        rng.set_stream(((i as u64) << 32) | j as u64);
+        rng
+    }
+}
+
+/// Returns the source samplers use unless they are given another one.
+pub fn default_source() -> Arc<dyn CryptoRngSource> {
+    Arc::new(ThreadRngSource)
+}
+
+/// Adapts a source to [`RngCore`], for code written against `rand`.
+#[derive(Debug, Clone, Copy)]
+pub struct SourceRng<'a>(pub &'a dyn CryptoRngSource);
+
+impl RngCore for SourceRng<'_> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.0.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.0.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+}
+
+/// The thread-local ChaCha generator of `rand`, reseeded from the OS.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThreadRngSource;
+
+impl CryptoRngSource for ThreadRngSource {
+    fn fill_bytes(&self, dest: &mut [u8]) {
+        rand::rng().fill_bytes(dest)
+    }
+}
+
+/// Reads every byte from the operating system, at the cost of a system call per fill.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsRngSource;
+
+impl CryptoRngSource for OsRngSource {
+    fn fill_bytes(&self, dest: &mut [u8]) {
+        rand::rngs::OsRng.try_fill_bytes(dest).expect("the OS random generator failed")
+    }
+}
+
+/// A ChaCha20 stream from a 32-byte seed. The same sequence of sampling calls reproduces the
+/// same values from the same seed.
+#[derive(Debug)]
+pub struct ChaChaSource {
+    rng: Mutex<ChaCha20Rng>,
+}
+
+impl ChaChaSource {
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        Self { rng: Mutex::new(ChaCha20Rng::from_seed(seed)) }
+    }
+
+    /// A deterministic source for tests, which must never be used for real keys.
+    pub fn for_test(seed: u64) -> Self {
+        Self { rng: Mutex::new(ChaCha20Rng::seed_from_u64(seed)) }
+    }
+}
+
+impl CryptoRngSource for ChaChaSource {
+    fn fill_bytes(&self, dest: &mut [u8]) {
+        self.rng.lock().unwrap().fill_bytes(dest)
+    }
+}
+
+/// The `RDRAND` instruction of x86-64 processors.
+#[cfg(target_arch = "x86_64")]
+#[derive(Debug, Clone, Copy)]
+pub struct RdRandSource {
+    _private: (),
+}
+
+#[cfg(target_arch = "x86_64")]
+impl RdRandSource {
+    /// Returns `None` if the processor does not support `RDRAND`.
+    pub fn new() -> Option<Self> {
+        std::is_x86_feature_detected!("rdrand").then_some(Self { _private: () })
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+impl CryptoRngSource for RdRandSource {
+    fn fill_bytes(&self, dest: &mut [u8]) {
+        // Intel recommends giving up after 10 consecutive failures
+        const RETRIES: usize = 10;
+        for chunk in dest.chunks_mut(8) {
+            let mut value = 0u64;
+            let ok = (0..RETRIES).any(|_| {
+                // SAFETY: `new` checked that the processor supports RDRAND.
+                unsafe { std::arch::x86_64::_rdrand64_step(&mut value) == 1 }
+            });
+            assert!(ok, "RDRAND failed {} times in a row", RETRIES);
+            chunk.copy_from_slice(&value.to_le_bytes()[..chunk.len()]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sources() {
+        // Seeded sources repeat their stream, the others do not
+        let mut left = [0u8; 40];
+        let mut right = [0u8; 40];
+        ChaChaSource::for_test(7).fill_bytes(&mut left);
+        ChaChaSource::for_test(7).fill_bytes(&mut right);
+        assert_eq!(left, right);
+        #[cfg_attr(not(target_arch = "x86_64"), allow(unused_mut))]
+        let mut sources: Vec<Arc<dyn CryptoRngSource>> =
+            vec![default_source(), Arc::new(OsRngSource), Arc::new(ChaChaSource::for_test(8))];
+        #[cfg(target_arch = "x86_64")]
+        sources.extend(RdRandSource::new().map(|source| Arc::new(source) as _));
+        for source in sources {
+            source.fill_bytes(&mut right);
+            assert_ne!(left, right);
+        }
+
+        // The adapter draws from the source it wraps
+        let source = ChaChaSource::for_test(7);
+        let value = SourceRng(&source).next_u64();
+        assert_eq!(value.to_le_bytes(), left[..8]);
+
+        // Streams are keyed from the source, so they repeat with it, and every position and every
+        // call gets its own stream
+        let source = ChaChaSource::for_test(7);
+        let (first, second) = (source.streams(), source.streams());
+        let value = first.rng(1, 2).next_u64();
+        assert_eq!(value, ChaChaSource::for_test(7).streams().rng(1, 2).next_u64());
+        assert_ne!(value, first.rng(2, 1).next_u64());
+        assert_ne!(value, second.rng(1, 2).next_u64());
+    }
+}
@@ -0,0 +1,148 @@
+use super::{plaintext::modulus_biguint, sampling::uniform_mod_q, Poly, PolyElem, PolyMatrix};
+use num_bigint::BigUint;
+use rand::RngCore;
+
+/// Rounds `c ∈ Z_q` to `⌊2c / q⌉ mod 2`, i.e. returns whether `c` is closer to `q/2` than
+/// to 0.
+pub fn round_to_bit(c: &BigUint, q: &BigUint) -> bool {
+    let doubled: BigUint = c << 1;
+    let (floor, rem) = (&doubled / q, &doubled % q);
+    // Round up when the remainder is at least q/2.
+    let rounded = if (rem << 1) >= *q { floor + 1u8 } else { floor };
+    rounded.bit(0)
+}
+
+/// Randomized rounding of `c ∈ Z_q`: with `2c = f·q + r`, returns `(f + 1) mod 2` with
+/// probability `r / q` and `f mod 2` otherwise, so that the expectation of the output equals
+/// `2c / q` before reduction mod 2.
+pub fn round_to_bit_randomized<R: RngCore + ?Sized>(
+    c: &BigUint,
+    q: &BigUint,
+    rng: &mut R,
+) -> bool {
+    let doubled: BigUint = c << 1;
+    let (floor, rem) = (&doubled / q, &doubled % q);
+    let u = uniform_mod_q(rng, q, 1).pop().expect("one sample");
+    let rounded = if u < rem { floor + 1u8 } else { floor };
+    rounded.bit(0)
+}
+
+/// Rounds every coefficient of the polynomial with [`round_to_bit`].
+pub fn round_poly<P: Poly>(params: &P::Params, poly: &P) -> Vec<bool> {
+    let q = modulus_biguint::<P>(params);
+    poly.coeffs().iter().map(|coeff| round_to_bit(coeff.to_biguint(), &q)).collect()
+}
+
+/// Rounds every coefficient of the polynomial with [`round_to_bit_randomized`].
+pub fn round_poly_randomized<P: Poly, R: RngCore + ?Sized>(
+    params: &P::Params,
+    poly: &P,
+    rng: &mut R,
+) -> Vec<bool> {
+    let q = modulus_biguint::<P>(params);
+    poly.coeffs()
+        .iter()
+        .map(|coeff| round_to_bit_randomized(coeff.to_biguint(), &q, rng))
+        .collect()
+}
+
+/// Rounds every entry of the matrix with [`round_poly`]. The output is indexed by row, column
+/// and coefficient.
+pub fn round_matrix<M: PolyMatrix>(
+    params: &<M::P as Poly>::Params,
+    matrix: &M,
+) -> Vec<Vec<Vec<bool>>> {
+    (0..matrix.row_size())
+        .map(|i| matrix.get_row(i).iter().map(|poly| round_poly(params, poly)).collect())
+        .collect()
+}
+
+/// Rounds every entry of the matrix with [`round_poly_randomized`].
+pub fn round_matrix_randomized<M: PolyMatrix, R: RngCore + ?Sized>(
+    params: &<M::P as Poly>::Params,
+    matrix: &M,
+    rng: &mut R,
+) -> Vec<Vec<Vec<bool>>> {
+    (0..matrix.row_size())
+        .map(|i| {
+            matrix.get_row(i).iter().map(|poly| round_poly_randomized(params, poly, rng)).collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::{
+        dcrt::{DCRTPoly, DCRTPolyMatrix, DCRTPolyParams, FinRingElem},
+        PolyParams,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_round_to_bit_edges() {
+        // For an odd q, values in [⌈q/4⌉, ⌊3q/4⌋] round to 1
+        let q = BigUint::from(17u32);
+        let bits = (0u32..17).map(|c| round_to_bit(&BigUint::from(c), &q)).collect::<Vec<_>>();
+        let expected = (0u32..17).map(|c| (5..=12).contains(&c)).collect::<Vec<_>>();
+        assert_eq!(bits, expected);
+
+        // For an even q, exactly q/4 rounds up and exactly 3q/4 rounds up to 2 = 0 mod 2
+        let q = BigUint::from(16u32);
+        assert!(!round_to_bit(&BigUint::from(3u32), &q));
+        assert!(round_to_bit(&BigUint::from(4u32), &q));
+        assert!(round_to_bit(&BigUint::from(11u32), &q));
+        assert!(!round_to_bit(&BigUint::from(12u32), &q));
+    }
+
+    #[test]
+    fn test_round_to_bit_randomized() {
+        let q = BigUint::from(1000u32);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // Values exactly at 0 and q/2 are rounded deterministically
+        for _ in 0..100 {
+            assert!(!round_to_bit_randomized(&BigUint::from(0u32), &q, &mut rng));
+            assert!(round_to_bit_randomized(&BigUint::from(500u32), &q, &mut rng));
+        }
+
+        // c = q/8 gives 2c/q = 1/4, so about a quarter of the outputs are 1
+        let ones = (0..4000)
+            .filter(|_| round_to_bit_randomized(&BigUint::from(125u32), &q, &mut rng))
+            .count();
+        assert!((800..1200).contains(&ones), "unexpected number of ones: {}", ones);
+    }
+
+    #[test]
+    fn test_round_poly_and_matrix() {
+        let params = DCRTPolyParams::default();
+        let q = params.modulus();
+        let q_big = modulus_biguint::<DCRTPoly>(&params);
+        let n = params.ring_dimension() as usize;
+
+        // Coefficients near 0 and near q/2
+        let small = BigUint::from(3u32);
+        let half = &q_big / 2u32 + 2u32;
+        let coeffs = (0..n)
+            .map(|i| {
+                let value = if i % 2 == 0 { small.clone() } else { half.clone() };
+                FinRingElem::new(value, q.clone())
+            })
+            .collect::<Vec<_>>();
+        let poly = DCRTPoly::from_coeffs(&params, &coeffs);
+        let expected = (0..n).map(|i| i % 2 == 1).collect::<Vec<_>>();
+        assert_eq!(round_poly(&params, &poly), expected);
+
+        let matrix = DCRTPolyMatrix::from_poly_vec(&params, vec![vec![poly.clone(), -poly]]);
+        let rounded = round_matrix(&params, &matrix);
+        assert_eq!(rounded.len(), 1);
+        assert_eq!(rounded[0][0], expected);
+        // Negation maps q/2 + 2 to q/2 - 2 and 3 to q - 3, which round to the same bits
+        assert_eq!(rounded[0][1], expected);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let randomized = round_matrix_randomized(&params, &matrix, &mut rng);
+        assert_eq!(randomized[0].len(), 2);
+        assert_eq!(randomized[0][0].len(), n);
+    }
+}
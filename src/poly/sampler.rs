@@ -1,4 +1,26 @@
-use super::{Poly, PolyMatrix};
+use super::{
+    rng::{default_source, CryptoRngSource},
+    Poly, PolyMatrix,
+};
+use crate::utils::parallelism_config;
+use rayon::prelude::*;
+use std::sync::Arc;
+
+const MANY_DOMAIN: &[u8; 4] = b"DIOH";
+
+/// The tag from which [`PolyHashSampler::sample_hash_many`] derives the matrix of `tag` with
+/// `nrow` rows and `ncol` columns: `DIOH || len || tag || nrow || ncol`, where the length and
+/// dimensions are `u64` little-endian. Distinct specifications never give the same tag, even if
+/// one tag is a prefix of another.
+pub fn many_tag(tag: &[u8], nrow: usize, ncol: usize) -> Vec<u8> {
+    let mut derived = Vec::with_capacity(MANY_DOMAIN.len() + 24 + tag.len());
+    derived.extend_from_slice(MANY_DOMAIN);
+    derived.extend_from_slice(&(tag.len() as u64).to_le_bytes());
+    derived.extend_from_slice(tag);
+    derived.extend_from_slice(&(nrow as u64).to_le_bytes());
+    derived.extend_from_slice(&(ncol as u64).to_le_bytes());
+    derived
+}
 
 #[derive(Debug, Clone, Copy)]
 /// Enum representing different types of distributions for random sampling.
@@ -14,11 +36,17 @@ pub enum DistType {
     GaussDist { sigma: f64 },
     /// Distribution that produces random bits (0 or 1).
     BitDist,
+    /// Ternary polynomials with exactly `hamming_weight` coefficients in `{-1, 1}` and all others
+    /// zero, the sparse secrets of many practical parameter sets. The positions of the nonzeros
+    /// are sampled in constant time by [`crate::poly::sampling::ternary_hamming`].
+    TernaryDist { hamming_weight: usize },
 }
 
 /// Trait for sampling a polynomial based on a hash function.
 pub trait PolyHashSampler<K: AsRef<[u8]>> {
     type M: PolyMatrix;
+    /// The hash keys are derived with, e.g. by [`crate::bgg::epoch::epoch_hash_key`].
+    type Hash: digest::Digest;
 
     fn new() -> Self;
 
@@ -35,12 +63,48 @@ pub trait PolyHashSampler<K: AsRef<[u8]>> {
         ncol: usize,
         dist: DistType,
     ) -> Self::M;
+
+    /// Samples one matrix per `(tag, nrow, ncol)` in `specs` from the same key, the `k`-th being
+    /// [`Self::sample_hash`] with the tag [`many_tag`] of `specs[k]`. The matrices are derived in
+    /// parallel unless `PARALLEL_MATRICES` is `false`. Panics if two specifications share a tag.
+    fn sample_hash_many<B: AsRef<[u8]> + Sync>(
+        &self,
+        params: &<<Self::M as PolyMatrix>::P as Poly>::Params,
+        key: [u8; 32],
+        specs: &[(B, usize, usize)],
+        dist: DistType,
+    ) -> Vec<Self::M>
+    where
+        Self: Sync,
+    {
+        let mut tags = specs.iter().map(|(tag, _, _)| tag.as_ref()).collect::<Vec<_>>();
+        tags.sort_unstable();
+        if let Some(pair) = tags.windows(2).find(|pair| pair[0] == pair[1]) {
+            panic!("tag {:?} is given twice", pair[0]);
+        }
+        let sample = |(tag, nrow, ncol): &(B, usize, usize)| {
+            self.sample_hash(params, key, many_tag(tag.as_ref(), *nrow, *ncol), *nrow, *ncol, dist)
+        };
+        if parallelism_config().matrices {
+            specs.par_iter().map(sample).collect()
+        } else {
+            specs.iter().map(sample).collect()
+        }
+    }
 }
 
 pub trait PolyUniformSampler {
     type M: PolyMatrix;
 
-    fn new() -> Self;
+    /// A sampler drawing from [`default_source`].
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self::with_source(default_source())
+    }
+
+    fn with_source(source: Arc<dyn CryptoRngSource>) -> Self;
 
     fn sample_poly(
         &self,
@@ -63,6 +127,9 @@ pub trait PolyTrapdoorSampler {
 
     fn new(params: &<<Self::M as PolyMatrix>::P as Poly>::Params, sigma: f64) -> Self;
 
+    /// Replaces the randomness source, [`default_source`] after [`Self::new`].
+    fn with_source(self, source: Arc<dyn CryptoRngSource>) -> Self;
+
     fn trapdoor(
         &self,
         params: &<<Self::M as PolyMatrix>::P as Poly>::Params,
@@ -77,3 +144,49 @@ pub trait PolyTrapdoorSampler {
         target: &Self::M,
     ) -> Self::M;
 }
+
+/// Source of trapdoors and preimages, so that deployments can generate the trapdoor outside the
+/// process, e.g. in an MPC where no single party learns it. Every [`PolyTrapdoorSampler`] is a
+/// provider holding the whole trapdoor locally.
+pub trait TrapdoorProvider {
+    type M: PolyMatrix;
+    /// The trapdoor itself, or a handle to it for providers that do not hold it.
+    type Handle;
+
+    fn trapdoor(
+        &self,
+        params: &<<Self::M as PolyMatrix>::P as Poly>::Params,
+        size: usize,
+    ) -> (Self::Handle, Self::M);
+
+    fn preimage(
+        &self,
+        params: &<<Self::M as PolyMatrix>::P as Poly>::Params,
+        handle: &Self::Handle,
+        public_matrix: &Self::M,
+        target: &Self::M,
+    ) -> Self::M;
+}
+
+impl<S: PolyTrapdoorSampler> TrapdoorProvider for S {
+    type M = S::M;
+    type Handle = S::Trapdoor;
+
+    fn trapdoor(
+        &self,
+        params: &<<Self::M as PolyMatrix>::P as Poly>::Params,
+        size: usize,
+    ) -> (Self::Handle, Self::M) {
+        PolyTrapdoorSampler::trapdoor(self, params, size)
+    }
+
+    fn preimage(
+        &self,
+        params: &<<Self::M as PolyMatrix>::P as Poly>::Params,
+        handle: &Self::Handle,
+        public_matrix: &Self::M,
+        target: &Self::M,
+    ) -> Self::M {
+        PolyTrapdoorSampler::preimage(self, params, handle, public_matrix, target)
+    }
+}
@@ -0,0 +1,368 @@
+use super::{dcrt::FinRingElem, sampler::DistType};
+use num_bigint::BigUint;
+use num_traits::Zero;
+use rand::{Rng, RngCore};
+use std::sync::Arc;
+use subtle::{ConditionallySelectable, ConstantTimeGreater};
+
+/// Above this deviation Gaussians are sampled with [`karney`] instead of a [`GaussianCdt`],
+/// whose table grows with the deviation.
+pub const KARNEY_THRESHOLD: f64 = 300.0;
+
+/// Samples `count` integers uniformly from `[0, q)` by rejection sampling.
+///
+/// Each candidate is read as `ceil(log2(q) / 8)` bytes from `rng` in little-endian order and the
+/// bits above `q.bits()` are masked off before comparing against `q`. The output therefore depends
+/// only on the byte stream of `rng`, so a seeded RNG (or any XOF exposed through `RngCore`) yields
+/// the same values on every platform. Every sampler draws uniform coefficients through it, via
+/// [`sample_coeffs`].
+pub fn uniform_mod_q<R: RngCore + ?Sized>(
+    rng: &mut R,
+    q: &BigUint,
+    count: usize,
+) -> Vec<BigUint> {
+    assert!(!q.is_zero(), "modulus must be non-zero");
+    let bits = q.bits() as usize;
+    let num_bytes = bits.div_ceil(8);
+    let top_mask = match bits % 8 {
+        0 => 0xffu8,
+        r => (1u8 << r) - 1,
+    };
+    let mut bytes = vec![0u8; num_bytes];
+    let mut samples = Vec::with_capacity(count);
+    while samples.len() < count {
+        rng.fill_bytes(&mut bytes);
+        bytes[num_bytes - 1] &= top_mask;
+        let candidate = BigUint::from_bytes_le(&bytes);
+        if &candidate < q {
+            samples.push(candidate);
+        }
+    }
+    samples
+}
+
+/// Cumulative distribution table of `|x|` for the discrete Gaussian over the integers with
+/// standard deviation `sigma`, tail-cut at `12 * sigma`, for turning uniform bits such as hash
+/// output into Gaussian samples deterministically. Entries are fractions of `2^64` computed in
+/// `f64`, so the distance to the exact distribution is about `2^-53`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GaussianCdt {
+    table: Vec<u64>,
+}
+
+impl GaussianCdt {
+    pub fn new(sigma: f64) -> Self {
+        assert!(sigma >= 0.0, "sigma must be non-negative, got {}", sigma);
+        let tail = (12.0 * sigma).ceil() as i64;
+        // |x| = 0 has weight 1 and every other |x| = k weight 2 rho(k), one for each sign
+        let weights = (0..=tail)
+            .map(|k| {
+                let rho = (-((k * k) as f64) / (2.0 * sigma * sigma)).exp();
+                if k == 0 {
+                    1.0
+                } else {
+                    2.0 * rho
+                }
+            })
+            .collect::<Vec<_>>();
+        let total: f64 = weights.iter().sum();
+        let mut cumulative = 0.0;
+        let mut table = weights
+            .iter()
+            .map(|weight| {
+                cumulative += weight;
+                (cumulative / total * 2f64.powi(64)).min(u64::MAX as f64) as u64
+            })
+            .collect::<Vec<_>>();
+        *table.last_mut().unwrap() = u64::MAX;
+        Self { table }
+    }
+
+    /// The largest absolute value of a sample.
+    pub fn tail(&self) -> u64 {
+        self.table.len() as u64 - 1
+    }
+
+    /// Maps 64 uniform bits to `|x|` by table lookup and applies `negative` as the sign.
+    pub fn sample(&self, bits: u64, negative: bool) -> i64 {
+        let abs = self.table.partition_point(|&c| c <= bits).min(self.table.len() - 1) as i64;
+        if negative {
+            -abs
+        } else {
+            abs
+        }
+    }
+}
+
+/// Samples `n` ternary coefficients with exactly `hamming_weight` of them nonzero, each `1` or
+/// `-1` with equal probability, drawing all randomness from `rng`.
+///
+/// The nonzeros start in the first positions and are moved to uniformly random ones by sorting
+/// all positions by random 63-bit keys with a bitonic network of constant-time compare-and-swaps,
+/// so that neither branches nor memory accesses depend on where the nonzeros end up.
+pub fn ternary_hamming<R: RngCore + ?Sized>(
+    rng: &mut R,
+    n: usize,
+    hamming_weight: usize,
+) -> Vec<i8> {
+    assert!(hamming_weight <= n, "hamming weight {} exceeds {} coefficients", hamming_weight, n);
+    let len = n.next_power_of_two();
+    // Padding sorts after every real position, whose keys have the top bit cleared
+    let mut keys = (0..len)
+        .map(|idx| if idx < n { rng.next_u64() >> 1 } else { u64::MAX })
+        .collect::<Vec<_>>();
+    let mut signs = vec![0u8; hamming_weight.div_ceil(8)];
+    rng.fill_bytes(&mut signs);
+    let mut values = (0..len)
+        .map(|idx| {
+            if idx < hamming_weight {
+                1 - 2 * ((signs[idx / 8] >> (idx % 8)) & 1) as i8
+            } else {
+                0
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut k = 2;
+    while k <= len {
+        let mut j = k / 2;
+        while j > 0 {
+            for i in 0..len {
+                let l = i ^ j;
+                if l <= i {
+                    continue;
+                }
+                // Ascending in blocks whose bit k is clear, descending in the others
+                let swap = if i & k == 0 {
+                    keys[i].ct_gt(&keys[l])
+                } else {
+                    keys[l].ct_gt(&keys[i])
+                };
+                let (head, tail) = keys.split_at_mut(l);
+                u64::conditional_swap(&mut head[i], &mut tail[0], swap);
+                let (head, tail) = values.split_at_mut(l);
+                i8::conditional_swap(&mut head[i], &mut tail[0], swap);
+            }
+            j /= 2;
+        }
+        k *= 2;
+    }
+    values.truncate(n);
+    values
+}
+
+/// Samples `n` coefficients modulo `q` from `dist` with the randomness of `rng`.
+pub fn sample_coeffs<R: RngCore + ?Sized>(
+    q: &Arc<BigUint>,
+    n: usize,
+    dist: &DistType,
+    rng: &mut R,
+) -> Vec<FinRingElem> {
+    match dist {
+        DistType::FinRingDist => uniform_mod_q(rng, q, n)
+            .into_iter()
+            .map(|value| FinRingElem::new(value, q.clone()))
+            .collect(),
+        DistType::BitDist => {
+            (0..n).map(|_| FinRingElem::new(rng.next_u32() & 1, q.clone())).collect()
+        }
+        DistType::TernaryDist { hamming_weight } => ternary_hamming(rng, n, *hamming_weight)
+            .into_iter()
+            .map(|coeff| FinRingElem::from_int64(coeff as i64, q.clone()))
+            .collect(),
+        DistType::GaussDist { sigma } if *sigma > KARNEY_THRESHOLD => {
+            (0..n).map(|_| FinRingElem::from_int64(karney(rng, 0.0, *sigma), q.clone())).collect()
+        }
+        DistType::GaussDist { sigma } => {
+            let cdt = GaussianCdt::new(*sigma);
+            (0..n)
+                .map(|_| {
+                    let sample = cdt.sample(rng.next_u64(), rng.next_u32() & 1 == 1);
+                    FinRingElem::from_int64(sample, q.clone())
+                })
+                .collect()
+        }
+    }
+}
+
+/// Samples an integer from the discrete Gaussian of standard deviation `stddev` around `mean`
+/// with Karney's algorithm D ("Sampling exactly from the normal distribution", 2016), as
+/// OpenFHE's `GenerateIntegerKarney` does, in time independent of `stddev`.
+pub fn karney<R: RngCore + ?Sized>(rng: &mut R, mean: f64, stddev: f64) -> i64 {
+    assert!(stddev > 0.0, "stddev must be positive, got {}", stddev);
+    let width = stddev.ceil() as i64;
+    loop {
+        // D1 and D2: k with probability exp(-k/2) (1 - exp(-1/2)), kept w.p. exp(-k(k-1)/2)
+        let mut k = 0i64;
+        while bernoulli_exp_minus_half(rng) {
+            k += 1;
+        }
+        if !(0..k * (k - 1)).all(|_| bernoulli_exp_minus_half(rng)) {
+            continue;
+        }
+        // D3 and D4: a sign and a candidate to the right of the scaled k
+        let sign = if rng.next_u32() & 1 == 1 { 1 } else { -1 };
+        let di0 = stddev * k as f64 + sign as f64 * mean;
+        let i0 = di0.ceil();
+        let j = rng.random_range(0..width);
+        let x = (i0 - di0) / stddev + j as f64 / stddev;
+        // D5 and D6
+        if x >= 1.0 || (x == 0.0 && sign < 0 && k == 0) {
+            continue;
+        }
+        // D7: kept w.p. exp(-x(2k + x)/2)
+        if !(0..=k).all(|_| bernoulli_exp_x(rng, k, x)) {
+            continue;
+        }
+        return sign * (i0 as i64 + j);
+    }
+}
+
+/// True with probability `exp(-1/2)`, using only uniform comparisons.
+fn bernoulli_exp_minus_half<R: RngCore + ?Sized>(rng: &mut R) -> bool {
+    let mut a: f64 = rng.random();
+    if a >= 0.5 {
+        return true;
+    }
+    loop {
+        let b: f64 = rng.random();
+        if b >= a {
+            return false;
+        }
+        a = rng.random();
+        if a >= b {
+            return true;
+        }
+    }
+}
+
+/// True with probability `exp(-x(2k + x)/(2k + 2))`, Karney's algorithm B.
+fn bernoulli_exp_x<R: RngCore + ?Sized>(rng: &mut R, k: i64, x: f64) -> bool {
+    let bound = (2.0 * k as f64 + x) / (2 * k + 2) as f64;
+    let mut y = x;
+    let mut n = 0;
+    loop {
+        let z: f64 = rng.random();
+        if z >= y {
+            break;
+        }
+        let r: f64 = rng.random();
+        if r >= bound {
+            break;
+        }
+        y = z;
+        n += 1;
+    }
+    n % 2 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_uniform_mod_q_range() {
+        let q = BigUint::from(1000003u64);
+        let mut rng = StdRng::seed_from_u64(7);
+        let samples = uniform_mod_q(&mut rng, &q, 1000);
+        assert_eq!(samples.len(), 1000);
+        assert!(samples.iter().all(|x| x < &q));
+    }
+
+    #[test]
+    fn test_uniform_mod_q_deterministic() {
+        let q = (BigUint::from(1u8) << 100) - BigUint::from(3u8);
+        let samples1 = uniform_mod_q(&mut StdRng::from_seed([1u8; 32]), &q, 64);
+        let samples2 = uniform_mod_q(&mut StdRng::from_seed([1u8; 32]), &q, 64);
+        assert_eq!(samples1, samples2);
+        let samples3 = uniform_mod_q(&mut StdRng::from_seed([2u8; 32]), &q, 64);
+        assert_ne!(samples1, samples3);
+    }
+
+    #[test]
+    fn test_uniform_mod_q_power_of_two() {
+        // q = 2^16 has 17 bits, so about half of the candidates are rejected
+        let q = BigUint::from(1u32 << 16);
+        let mut rng = StdRng::seed_from_u64(0);
+        let samples = uniform_mod_q(&mut rng, &q, 256);
+        assert!(samples.iter().all(|x| x < &q));
+    }
+
+    #[test]
+    fn test_sample_coeffs_uniform_mod_q() {
+        // Uniform coefficients are exactly the samples of uniform_mod_q from the same stream
+        let q = Arc::new(BigUint::from(1000003u64));
+        let coeffs = sample_coeffs(&q, 16, &DistType::FinRingDist, &mut StdRng::seed_from_u64(5));
+        let samples = uniform_mod_q(&mut StdRng::seed_from_u64(5), &q, 16);
+        assert_eq!(coeffs.iter().map(|c| c.value().clone()).collect::<Vec<_>>(), samples);
+    }
+
+    #[test]
+    fn test_gaussian_cdt_moments() {
+        let sigma = 3.2;
+        let cdt = GaussianCdt::new(sigma);
+        assert_eq!(cdt.tail(), 39);
+
+        // The empirical mean and standard deviation are close to 0 and sigma
+        let mut rng = StdRng::seed_from_u64(3);
+        let count = 100000;
+        let samples = (0..count)
+            .map(|_| cdt.sample(rng.next_u64(), rng.next_u32() & 1 == 1))
+            .collect::<Vec<_>>();
+        let mean = samples.iter().sum::<i64>() as f64 / count as f64;
+        let variance =
+            samples.iter().map(|&x| (x as f64 - mean).powi(2)).sum::<f64>() / count as f64;
+        assert!(mean.abs() < 0.1, "mean {}", mean);
+        assert!((variance.sqrt() - sigma).abs() < 0.1, "standard deviation {}", variance.sqrt());
+        assert!(samples.iter().all(|x| x.unsigned_abs() <= cdt.tail()));
+
+        // Sigma zero always yields zero
+        let cdt = GaussianCdt::new(0.0);
+        assert_eq!(cdt.sample(u64::MAX, true), 0);
+        assert_eq!(cdt.sample(0, false), 0);
+    }
+
+    #[test]
+    fn test_karney_moments() {
+        // Centered and off-center, above and below the CDT threshold
+        let mut rng = StdRng::seed_from_u64(11);
+        for (mean, stddev) in [(0.0, 3.2), (0.5, 10.0), (-7.25, 1000.0)] {
+            let count = 50000;
+            let samples = (0..count).map(|_| karney(&mut rng, mean, stddev)).collect::<Vec<_>>();
+            let empirical = samples.iter().sum::<i64>() as f64 / count as f64;
+            let variance =
+                samples.iter().map(|&x| (x as f64 - empirical).powi(2)).sum::<f64>() / count as f64;
+            assert!((empirical - mean).abs() < 0.05 * stddev, "mean {}", empirical);
+            assert!((variance.sqrt() / stddev - 1.0).abs() < 0.05, "stddev {}", variance.sqrt());
+        }
+    }
+
+    #[test]
+    fn test_ternary_hamming() {
+        // Exactly h nonzeros in {-1, 1}, also for a length that is not a power of two
+        let mut rng = StdRng::seed_from_u64(5);
+        for (n, h) in [(64, 16), (100, 37), (8, 8), (8, 0)] {
+            let coeffs = ternary_hamming(&mut rng, n, h);
+            assert_eq!(coeffs.len(), n);
+            assert_eq!(coeffs.iter().filter(|&&c| c != 0).count(), h);
+            assert!(coeffs.iter().all(|&c| (-1..=1).contains(&c)));
+        }
+
+        // Every position is hit and both signs occur
+        let mut hits = [0usize; 16];
+        let mut negative = 0;
+        for _ in 0..1000 {
+            for (idx, &c) in ternary_hamming(&mut rng, 16, 2).iter().enumerate() {
+                hits[idx] += (c != 0) as usize;
+                negative += (c < 0) as usize;
+            }
+        }
+        assert!(hits.iter().all(|&count| (75..=175).contains(&count)), "{:?}", hits);
+        assert!((850..=1150).contains(&negative), "{} negative coefficients", negative);
+
+        // The output only depends on the seed
+        let first = ternary_hamming(&mut StdRng::seed_from_u64(9), 128, 40);
+        assert_eq!(first, ternary_hamming(&mut StdRng::seed_from_u64(9), 128, 40));
+    }
+}
@@ -0,0 +1,159 @@
+//! Constructors for the structured matrices of BGG+ gate identities, such as the
+//! `A_f = A * H_f` of a homomorphism, so that new gates can be written as products of matrices
+//! rather than loops over entries.
+//!
+//! Selections and permutations act on columns by right multiplication: column `j` of
+//! `matrix * permutation_matrix(params, perm)` is column `perm[j]` of `matrix`. Their block
+//! variants move blocks of `block` columns at once, e.g. whole public keys of a concatenation
+//! `(A_1 | ... | A_k)`, each `A_i` having `block` columns.
+use super::{Poly, PolyMatrix};
+
+/// The 0/1 matrix whose `(i, j)` entry is one if `one(i, j)` holds.
+fn indicator<M: PolyMatrix>(
+    params: &<M::P as Poly>::Params,
+    nrow: usize,
+    ncol: usize,
+    one: impl Fn(usize, usize) -> bool,
+) -> M {
+    let entries = (0..nrow)
+        .map(|i| {
+            (0..ncol)
+                .map(|j| if one(i, j) { M::P::const_one(params) } else { M::P::const_zero(params) })
+                .collect()
+        })
+        .collect();
+    M::from_poly_vec(params, entries)
+}
+
+/// `I_copies ⊗ matrix`, the block diagonal matrix with `copies` copies of `matrix`.
+pub fn identity_tensor<M: PolyMatrix>(matrix: &M, copies: usize) -> M {
+    assert!(copies > 0, "tensor with an empty identity");
+    matrix.concat_diag(&vec![matrix; copies - 1])
+}
+
+/// `matrix ⊗ I_size`, which replaces every entry `x` of `matrix` by `x * I_size`.
+pub fn tensor_identity<M: PolyMatrix>(
+    params: &<M::P as Poly>::Params,
+    matrix: &M,
+    size: usize,
+) -> M {
+    matrix.tensor(&M::identity(params, size, None))
+}
+
+/// `I_copies ⊗ G_size`, i.e. `G_{copies * size}` seen as `copies` diagonal blocks.
+pub fn gadget_tensor<M: PolyMatrix>(
+    params: &<M::P as Poly>::Params,
+    copies: usize,
+    size: usize,
+) -> M {
+    identity_tensor(&M::gadget_matrix(params, size), copies)
+}
+
+/// `copies` identities of `size` stacked vertically, so that `stacked * x` repeats the
+/// `size`-row vector `x`, and `x^T * stacked` sums the blocks of `x^T`.
+pub fn stacked_identity<M: PolyMatrix>(
+    params: &<M::P as Poly>::Params,
+    size: usize,
+    copies: usize,
+) -> M {
+    indicator(params, size * copies, size, |i, j| i % size == j)
+}
+
+/// The `size x indices.len()` matrix selecting the columns `indices` of a matrix with `size`
+/// columns, in the order given. Indices may repeat.
+pub fn selection_matrix<M: PolyMatrix>(
+    params: &<M::P as Poly>::Params,
+    size: usize,
+    indices: &[usize],
+) -> M {
+    assert!(indices.iter().all(|&index| index < size), "selected column out of range");
+    indicator(params, size, indices.len(), |i, j| indices[j] == i)
+}
+
+/// Like [`selection_matrix`], selecting the column blocks `indices` of a matrix with
+/// `num_blocks` blocks of `block` columns.
+pub fn block_selection_matrix<M: PolyMatrix>(
+    params: &<M::P as Poly>::Params,
+    num_blocks: usize,
+    block: usize,
+    indices: &[usize],
+) -> M {
+    tensor_identity(params, &selection_matrix::<M>(params, num_blocks, indices), block)
+}
+
+/// The permutation matrix reordering the columns of a matrix by `perm`, which must be a
+/// permutation of `0..perm.len()`. Its transpose applies the inverse permutation.
+pub fn permutation_matrix<M: PolyMatrix>(params: &<M::P as Poly>::Params, perm: &[usize]) -> M {
+    let mut seen = vec![false; perm.len()];
+    for &index in perm {
+        assert!(index < perm.len() && !seen[index], "{:?} is not a permutation", perm);
+        seen[index] = true;
+    }
+    selection_matrix(params, perm.len(), perm)
+}
+
+/// Like [`permutation_matrix`], reordering blocks of `block` columns.
+pub fn block_permutation_matrix<M: PolyMatrix>(
+    params: &<M::P as Poly>::Params,
+    perm: &[usize],
+    block: usize,
+) -> M {
+    tensor_identity(params, &permutation_matrix::<M>(params, perm), block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::{
+        dcrt::{DCRTPoly, DCRTPolyMatrix, DCRTPolyParams, DCRTPolyUniformSampler},
+        sampler::{DistType, PolyUniformSampler},
+        PolyParams,
+    };
+
+    #[test]
+    fn test_structured_matrices() {
+        let params = DCRTPolyParams::default();
+        let sampler = DCRTPolyUniformSampler::new();
+        let log_base_q = params.modulus_digits();
+
+        // I ⊗ G is the gadget matrix of the stacked rows
+        let gadget = gadget_tensor::<DCRTPolyMatrix>(&params, 3, 2);
+        assert_eq!(gadget, DCRTPolyMatrix::gadget_matrix(&params, 6));
+        assert_eq!(gadget.size(), (6, 6 * log_base_q));
+
+        // Columns and blocks of columns are selected and permuted by right multiplication
+        let matrix = sampler.sample_uniform(&params, 2, 6, DistType::FinRingDist);
+        let selected = matrix.clone() * selection_matrix::<DCRTPolyMatrix>(&params, 6, &[4, 1, 4]);
+        assert_eq!(selected.get_column(0), matrix.get_column(4));
+        assert_eq!(selected.get_column(1), matrix.get_column(1));
+        assert_eq!(selected.get_column(2), matrix.get_column(4));
+        let perm = permutation_matrix::<DCRTPolyMatrix>(&params, &[2, 0, 1]);
+        let block_perm = block_permutation_matrix::<DCRTPolyMatrix>(&params, &[2, 0, 1], 2);
+        let blocks = matrix.clone() * block_perm;
+        assert_eq!(blocks.slice_columns(0, 2), matrix.slice_columns(4, 6));
+        assert_eq!(blocks.slice_columns(2, 4), matrix.slice_columns(0, 2));
+        assert_eq!(
+            matrix.clone() * block_selection_matrix::<DCRTPolyMatrix>(&params, 3, 2, &[1]),
+            matrix.slice_columns(2, 4)
+        );
+        assert_eq!(perm.clone() * perm.transpose(), DCRTPolyMatrix::identity(&params, 3, None));
+
+        // Stacked identities repeat vectors and sum blocks
+        let vector = sampler.sample_uniform(&params, 2, 1, DistType::FinRingDist);
+        let stacked = stacked_identity::<DCRTPolyMatrix>(&params, 2, 3);
+        assert_eq!(stacked.clone() * &vector, vector.concat_rows(&[&vector, &vector]));
+        let row = sampler.sample_uniform(&params, 1, 6, DistType::FinRingDist);
+        let sum = row.slice_columns(0, 2) + row.slice_columns(2, 4) + row.slice_columns(4, 6);
+        assert_eq!(row * stacked, sum);
+
+        // Tensoring with identities on either side
+        let small = sampler.sample_uniform(&params, 2, 2, DistType::FinRingDist);
+        let left = identity_tensor(&small, 2);
+        assert_eq!(left.slice(2, 4, 2, 4), small);
+        assert_eq!(left.slice(0, 2, 2, 4), DCRTPolyMatrix::zero(&params, 2, 2));
+        let right = tensor_identity(&params, &small, 2);
+        assert_eq!(right.entry(2, 0), small.entry(1, 0));
+        assert_eq!(right.entry(3, 1), small.entry(1, 0));
+        assert_eq!(right.entry(2, 1), DCRTPoly::const_zero(&params));
+    }
+}
@@ -0,0 +1,109 @@
+use super::{Poly, PolyElem, PolyParams};
+use num_bigint::BigUint;
+use subtle::Choice;
+
+/// How [`zero_test`] compares the coefficients against the thresholds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZeroTestMode {
+    /// Compares fixed-width byte encodings of the coefficients without data-dependent branches
+    /// or early exits, for servers whose decryption timing must not leak the attributes.
+    ConstantTime,
+    /// Compares the coefficients as big integers, which exits on the first differing limb.
+    Fast,
+}
+
+/// Returns `a >= b` for little-endian unsigned integers of the same width, computing the borrow
+/// of `a - b` over every byte.
+pub fn ct_ge(a: &[u8], b: &[u8]) -> Choice {
+    assert_eq!(a.len(), b.len(), "operands must have the same width");
+    let mut borrow = 0u8;
+    for (&x, &y) in a.iter().zip(b.iter()) {
+        let diff = x as i16 - y as i16 - borrow as i16;
+        borrow = ((diff >> 15) & 1) as u8;
+    }
+    Choice::from(1 - borrow)
+}
+
+/// Returns `low <= c < high` for little-endian unsigned integers of the same width.
+pub fn ct_in_range(c: &[u8], low: &[u8], high: &[u8]) -> Choice {
+    ct_ge(c, low) & !ct_ge(c, high)
+}
+
+/// Rounds every coefficient to the bit of `q/4 <= c < 3q/4` like
+/// [`Poly::extract_bits_with_threshold`]. In [`ZeroTestMode::ConstantTime`] the comparisons run
+/// in constant time; the conversion of the coefficients to bytes is left to the polynomial
+/// backend.
+pub fn zero_test<P: Poly>(params: &P::Params, poly: &P, mode: ZeroTestMode) -> Vec<bool> {
+    if mode == ZeroTestMode::Fast {
+        return poly.extract_bits_with_threshold(params);
+    }
+    let modulus = params.modulus();
+    let width = params.modulus_bits().div_ceil(8);
+    let quarter_q = <P::Elem as PolyElem>::half_q(&modulus).to_biguint() >> 1;
+    let low = to_fixed_bytes(&quarter_q, width);
+    let high = to_fixed_bytes(&(&quarter_q * 3u32), width);
+    poly.coeffs()
+        .iter()
+        .map(|coeff| {
+            let c = to_fixed_bytes(coeff.to_biguint(), width);
+            bool::from(ct_in_range(&c, &low, &high))
+        })
+        .collect()
+}
+
+fn to_fixed_bytes(value: &BigUint, width: usize) -> Vec<u8> {
+    let mut bytes = value.to_bytes_le();
+    bytes.resize(width, 0);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        poly::dcrt::{DCRTPoly, DCRTPolyParams, FinRingElem},
+        utils::create_random_poly,
+    };
+
+    #[test]
+    fn test_ct_ge() {
+        // Every pair of single bytes
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                assert_eq!(bool::from(ct_ge(&[a], &[b])), a >= b);
+            }
+        }
+
+        // Multi-byte operands where only the high or the low byte differs
+        let pairs = [(0x0100u16, 0x00ffu16), (0x00ff, 0x0100), (0x1234, 0x1234), (0x1235, 0x1234)];
+        for (a, b) in pairs {
+            assert_eq!(bool::from(ct_ge(&a.to_le_bytes(), &b.to_le_bytes())), a >= b);
+        }
+    }
+
+    #[test]
+    fn test_zero_test_matches_fast_path() {
+        let params = DCRTPolyParams::default();
+        for _ in 0..10 {
+            let poly = create_random_poly(&params);
+            assert_eq!(
+                zero_test(&params, &poly, ZeroTestMode::ConstantTime),
+                zero_test(&params, &poly, ZeroTestMode::Fast)
+            );
+        }
+
+        // Coefficients on both sides of both thresholds
+        let modulus = params.modulus();
+        let quarter_q = FinRingElem::half_q(&modulus).value() >> 1;
+        let three_quarter_q = &quarter_q * 3u32;
+        let values = [&quarter_q - 1u8, quarter_q.clone(), &three_quarter_q - 1u8, three_quarter_q];
+        let coeffs = values
+            .into_iter()
+            .map(|value| FinRingElem::new(value, modulus.clone()))
+            .collect::<Vec<_>>();
+        let poly = DCRTPoly::from_coeffs(&params, &coeffs);
+        let bits = zero_test(&params, &poly, ZeroTestMode::ConstantTime);
+        assert_eq!(bits, vec![false, true, true, false]);
+        assert_eq!(bits, zero_test(&params, &poly, ZeroTestMode::Fast));
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{
+    bgg::{
+        circuit::PolyCircuit,
+        sampler::{BGGEncodingSampler, BGGPublicKeySampler},
+    },
+    poly::{
+        dcrt::{
+            DCRTPoly, DCRTPolyHashSampler, DCRTPolyMatrix, DCRTPolyParams, DCRTPolyUniformSampler,
+            FinRingElem,
+        },
+        sampler::{DistType, PolyUniformSampler},
+        Poly, PolyElem, PolyMatrix, PolyParams,
+    },
+};
+use keccak_asm::Keccak256;
+use num_bigint::BigUint;
+use proptest::{prelude::*, test_runner::TestCaseError};
+
+/// Strategy generating small parameters accepted by OpenFHE.
+pub fn arb_params() -> impl Strategy<Value = DCRTPolyParams> {
+    (prop::sample::select(vec![4u32, 8, 16]), 1usize..=2, 1u32..=3).prop_map(
+        |(ring_dim, crt_depth, base_bits)| DCRTPolyParams::new(ring_dim, crt_depth, 17, base_bits),
+    )
+}
+
+/// Strategy generating `len` random bits used as packed attribute values.
+pub fn arb_bits(len: usize) -> impl Strategy<Value = Vec<bool>> {
+    prop::collection::vec(any::<bool>(), len)
+}
+
+/// Strategy generating a random circuit of `num_input` inputs and between 1 and `max_gates`
+/// add/sub/mul gates. The last gate is the only output.
+pub fn arb_circuit(num_input: usize, max_gates: usize) -> impl Strategy<Value = PolyCircuit> {
+    prop::collection::vec((0u8..3, any::<usize>(), any::<usize>()), 1..=max_gates).prop_map(
+        move |gates| {
+            let mut circuit = PolyCircuit::new();
+            let mut wires = vec![0];
+            wires.extend(circuit.input(num_input));
+            for (op, left, right) in gates {
+                let left = wires[left % wires.len()];
+                let right = wires[right % wires.len()];
+                let gate = match op {
+                    0 => circuit.add_gate(left, right),
+                    1 => circuit.sub_gate(left, right),
+                    _ => circuit.mul_gate(left, right),
+                };
+                wires.push(gate);
+            }
+            circuit.output(vec![*wires.last().unwrap()]);
+            circuit
+        },
+    )
+}
+
+/// Returns the infinity norm of the centered coefficients of every entry of the matrix.
+fn centered_inf_norm(params: &DCRTPolyParams, matrix: &DCRTPolyMatrix) -> BigUint {
+    let q = params.modulus();
+    let (nrow, ncol) = matrix.size();
+    let mut norm = BigUint::ZERO;
+    for i in 0..nrow {
+        for j in 0..ncol {
+            for coeff in matrix.entry(i, j).coeffs() {
+                let value = coeff.value();
+                let centered = (q.as_ref() - value).min(value.clone());
+                norm = norm.max(centered);
+            }
+        }
+    }
+    norm
+}
+
+/// Evaluates `circuit` over plaintext polynomials, BGG+ public keys and BGG+ encodings of the
+/// given bits and checks that
+/// 1. every output encoding carries the output public key and plaintext, and
+/// 2. its error `c - s * (A - x * G)` has an infinity norm at most `error_bound`.
+pub fn check_bgg_homomorphism(
+    params: &DCRTPolyParams,
+    circuit: &PolyCircuit,
+    bits: &[bool],
+    d: usize,
+    encoding_sigma: f64,
+    error_bound: &BigUint,
+) -> Result<(), TestCaseError> {
+    prop_assert_eq!(circuit.num_input(), bits.len());
+
+    // Sample public keys and encodings of constant polynomials holding the bits
+    let key: [u8; 32] = rand::random();
+    let tag: u64 = rand::random();
+    let pubkey_sampler = BGGPublicKeySampler::<_, DCRTPolyHashSampler<Keccak256>>::new(key, d);
+    let reveal_plaintexts = vec![true; bits.len()];
+    let pubkeys = pubkey_sampler.sample(params, &tag.to_le_bytes(), &reveal_plaintexts);
+    let uniform_sampler = DCRTPolyUniformSampler::new();
+    let secrets = uniform_sampler.sample_uniform(params, 1, d, DistType::BitDist).get_row(0);
+    let plaintexts = bits
+        .iter()
+        .map(|&bit| {
+            DCRTPoly::from_const(params, &FinRingElem::constant(&params.modulus(), bit as u64))
+        })
+        .collect::<Vec<_>>();
+    let encoding_sampler =
+        BGGEncodingSampler::new(params, &secrets, uniform_sampler, encoding_sigma);
+    let encodings = encoding_sampler.sample(params, &pubkeys, &plaintexts);
+
+    // Evaluate the circuit on every representation
+    let one = DCRTPoly::const_one(params);
+    let poly_outputs = circuit.eval(params, &one, &plaintexts);
+    let pubkey_outputs = circuit.eval(params, &pubkeys[0], &pubkeys[1..]);
+    let encoding_outputs = circuit.eval(params, &encodings[0], &encodings[1..]);
+
+    let secret_vec = &encoding_sampler.secret_vec;
+    let s_g = secret_vec.clone() * DCRTPolyMatrix::gadget_matrix(params, secret_vec.col_size());
+    for ((poly, pubkey), encoding) in
+        poly_outputs.iter().zip(pubkey_outputs.iter()).zip(encoding_outputs.iter())
+    {
+        prop_assert_eq!(&encoding.pubkey, pubkey);
+        prop_assert_eq!(encoding.plaintext.as_ref(), Some(poly));
+        let error = encoding.vector.clone() - secret_vec.clone() * &pubkey.matrix +
+            s_g.clone() * poly;
+        let norm = centered_inf_norm(params, &error);
+        prop_assert!(&norm <= error_bound, "error norm {} exceeds bound {}", norm, error_bound);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_traits::One;
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(4))]
+
+        #[test]
+        fn test_bgg_homomorphism_random_circuits(
+            params in arb_params(),
+            circuit in arb_circuit(3, 4),
+            bits in arb_bits(3),
+        ) {
+            // Without encoding noise the output error must vanish
+            check_bgg_homomorphism(&params, &circuit, &bits, 2, 0.0, &BigUint::ZERO)?;
+
+            // With noise it must stay within the norm simulator's bound for the circuit, where
+            // fresh errors are below 6 sigma and the plaintexts are bits
+            let sigma = 4.578;
+            let fresh_error = BigUint::from((6.0 * sigma).ceil() as u64);
+            let d = 2;
+            let m = (d + 1) * params.modulus_digits();
+            let norms = circuit.simulate_bgg_norm(
+                params.ring_dimension(),
+                params.base_bits(),
+                vec![BigUint::one(); bits.len()],
+            );
+            let error_bound = norms.error_bound(m, &fresh_error);
+            check_bgg_homomorphism(&params, &circuit, &bits, d, sigma, &error_bound)?;
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use crate::poly::PolyParams;
+
+/// Core-SVP cost exponent per BKZ block size for classical sieving.
+const SIEVE_EXPONENT: f64 = 0.292;
+/// Statistical security targeted by noise flooding.
+const FLOODING_TARGET_BITS: f64 = 40.0;
+/// Computational security below which the report warns.
+const SECURITY_TARGET_BITS: f64 = 128.0;
+
+/// Inputs of [`audit`] beyond the ring parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditConfig {
+    /// Number of secret polynomials `d`.
+    pub secret_size: usize,
+    /// Standard deviation of the fresh encoding error.
+    pub error_sigma: f64,
+    /// Standard deviation of the flooding noise, if any.
+    pub flooding_sigma: Option<f64>,
+    /// Multiplicative depth of the evaluated circuit.
+    pub circuit_depth: usize,
+}
+
+/// Estimates produced by [`audit`]. All sizes are in bits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditReport {
+    pub log_q: usize,
+    /// Root Hermite factor a lattice reduction needs to distinguish the LWE samples.
+    pub root_hermite_factor: f64,
+    /// Classical core-SVP cost of the BKZ block size achieving that root Hermite factor.
+    pub security_bits: f64,
+    /// `log2(flooding_sigma / error bound)`, the statistical distance being about `2^-margin`.
+    pub flooding_margin_bits: Option<f64>,
+    /// `log2(q/4) - log2(error bound after evaluation)`; negative means decryption fails.
+    pub correctness_slack_bits: f64,
+    pub warnings: Vec<String>,
+}
+
+impl AuditReport {
+    pub fn is_ok(&self) -> bool {
+        self.warnings.is_empty()
+    }
+}
+
+/// Root Hermite factor reached by BKZ with block size `beta`.
+fn bkz_root_hermite_factor(beta: f64) -> f64 {
+    use std::f64::consts::{E, PI};
+    ((beta / (2.0 * PI * E)) * (PI * beta).powf(1.0 / beta)).powf(1.0 / (2.0 * (beta - 1.0)))
+}
+
+/// `log2` of the fresh error bound, 6 standard deviations per coefficient.
+fn fresh_error_bits(config: &AuditConfig) -> f64 {
+    (6.0 * config.error_sigma.max(1.0)).log2()
+}
+
+/// `log2` of the error bound after `config.circuit_depth` multiplicative levels with a gadget of
+/// `digits` digits in base `2^base_bits`, and the slack `log2(q/4)` leaves above it.
+fn error_budget(
+    n: f64,
+    log_q: usize,
+    base_bits: u32,
+    digits: usize,
+    config: &AuditConfig,
+) -> (f64, f64) {
+    let m = (config.secret_size + 1) * digits;
+    let growth_bits = (n * m as f64).log2() + base_bits as f64;
+    let error_bits = fresh_error_bits(config) + config.circuit_depth as f64 * growth_bits;
+    (error_bits, (log_q as f64 - 2.0) - error_bits)
+}
+
+/// Estimates the hardness of the RLWE instance and the error budget of a circuit evaluation.
+///
+/// The lattice dimension is `n * d`. The root Hermite factor is the one needed for the
+/// distinguishing attack, `log2(δ) = log2(q/σ)^2 / (4 * n * d * log2(q))`, and it is converted
+/// into the smallest BKZ block size reaching it, priced at `0.292 * β` bits. The error bound
+/// grows by a factor `n * m * 2^base_bits` per multiplicative level, where `m` is the width
+/// of a public key. These are rough estimates meant to catch toy parameters, not a replacement
+/// for the lattice estimator.
+pub fn audit<P: PolyParams>(params: &P, config: &AuditConfig) -> AuditReport {
+    let n = params.ring_dimension() as f64;
+    let log_q = params.modulus_bits();
+    let lattice_dim = n * config.secret_size as f64;
+    let sigma = config.error_sigma.max(1.0);
+    let mut warnings = Vec::new();
+
+    let log_delta = (log_q as f64 - sigma.log2()).powi(2) / (4.0 * lattice_dim * log_q as f64);
+    let root_hermite_factor = 2f64.powf(log_delta);
+    // If no block size up to the full dimension is enough, the attack costs at least the largest
+    let max_beta = lattice_dim.max(50.0) as usize;
+    let beta = (50..=max_beta)
+        .find(|&beta| bkz_root_hermite_factor(beta as f64) <= root_hermite_factor)
+        .unwrap_or(max_beta);
+    let security_bits = SIEVE_EXPONENT * beta as f64;
+    if security_bits < SECURITY_TARGET_BITS {
+        warnings.push(format!(
+            "ring dimension {} with a {}-bit modulus gives about {:.0} bits of security",
+            params.ring_dimension(),
+            log_q,
+            security_bits
+        ));
+    }
+
+    let flooding_margin_bits = config.flooding_sigma.map(|flooding_sigma| {
+        let margin = flooding_sigma.log2() - fresh_error_bits(config);
+        if margin < FLOODING_TARGET_BITS {
+            warnings.push(format!(
+                "flooding noise only hides the error up to a statistical distance of 2^-{:.0}",
+                margin.max(0.0)
+            ));
+        }
+        margin
+    });
+
+    let (_, correctness_slack_bits) =
+        error_budget(n, log_q, params.base_bits(), params.modulus_digits(), config);
+    if correctness_slack_bits < 0.0 {
+        warnings.push(format!(
+            "modulus only {} bits with depth {} muls: the error exceeds q/4 by 2^{:.0}, so \
+             decryption fails",
+            log_q, config.circuit_depth, -correctness_slack_bits
+        ));
+    } else if correctness_slack_bits < 10.0 {
+        warnings.push(format!(
+            "modulus only {} bits with depth {} muls: decryption failure probability ≥ 2^-10",
+            log_q, config.circuit_depth
+        ));
+    }
+
+    AuditReport {
+        log_q,
+        root_hermite_factor,
+        security_bits,
+        flooding_margin_bits,
+        correctness_slack_bits,
+        warnings,
+    }
+}
+
+/// Gadget base picked by [`Gadget::optimal_for`].
+///
+/// A larger base shortens the gadget vector, so public keys have fewer columns and every
+/// multiplication is cheaper, but decomposed digits are larger and each multiplicative level
+/// grows the error by about `n * m * 2^base_bits`, where `m` is the width of a public key.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gadget {
+    pub base_bits: u32,
+    /// Number of digits `ceil(log2(q) / base_bits)` of the gadget vector.
+    pub digits: usize,
+    /// Estimated size of the error after evaluating the circuit.
+    pub error_bits: f64,
+    /// `log2(q/4) - error_bits`, the budget left for decryption.
+    pub correctness_slack_bits: f64,
+}
+
+impl Gadget {
+    /// Returns the largest base whose error after `config.circuit_depth` multiplicative levels
+    /// stays below `q/4` under the model of [`audit`], or `None` if even a binary gadget
+    /// exceeds the budget. The base of `params` itself is ignored.
+    pub fn optimal_for<P: PolyParams>(params: &P, config: &AuditConfig) -> Option<Self> {
+        let n = params.ring_dimension() as f64;
+        let log_q = params.modulus_bits();
+        (1..=log_q as u32)
+            .map(|base_bits| {
+                let digits = log_q.div_ceil(base_bits as usize);
+                let (error_bits, correctness_slack_bits) =
+                    error_budget(n, log_q, base_bits, digits, config);
+                Self { base_bits, digits, error_bits, correctness_slack_bits }
+            })
+            .filter(|gadget| gadget.correctness_slack_bits >= 0.0)
+            .last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly::{dcrt::DCRTPolyParams, native::NativePolyParams};
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_audit_toy_params() {
+        // The default test parameters are insecure
+        let params = DCRTPolyParams::default();
+        let config = AuditConfig {
+            secret_size: 1,
+            error_sigma: 4.578,
+            flooding_sigma: Some(100.0),
+            circuit_depth: 4,
+        };
+        let report = audit(&params, &config);
+        assert_eq!(report.log_q, params.modulus_bits());
+        assert!(report.security_bits < SECURITY_TARGET_BITS);
+        assert!(report.flooding_margin_bits.unwrap() < FLOODING_TARGET_BITS);
+        assert!(report.correctness_slack_bits < 0.0);
+        assert!(!report.is_ok());
+        assert_eq!(report.warnings.len(), 3);
+    }
+
+    #[test]
+    fn test_audit_monotonicity() {
+        let config = AuditConfig {
+            secret_size: 1,
+            error_sigma: 3.2,
+            flooding_sigma: None,
+            circuit_depth: 1,
+        };
+
+        // A larger ring with the same modulus is harder
+        let small = audit(&DCRTPolyParams::new(1024, 2, 51, 17), &config);
+        let large = audit(&DCRTPolyParams::new(4096, 2, 51, 17), &config);
+        assert!(large.root_hermite_factor < small.root_hermite_factor);
+        assert!(large.security_bits > small.security_bits);
+        assert!(large.flooding_margin_bits.is_none());
+
+        // A deeper circuit leaves less correctness slack
+        let deep_config = AuditConfig { circuit_depth: 3, ..config };
+        let deep = audit(&DCRTPolyParams::new(4096, 2, 51, 17), &deep_config);
+        assert!(deep.correctness_slack_bits < large.correctness_slack_bits);
+    }
+
+    #[test]
+    fn test_audit_unreachable_block_size() {
+        // No block size reaches the root Hermite factor of a 17-bit modulus in dimension 4096
+        let params = NativePolyParams::new(4096, BigUint::from(65537u32), 1);
+        let config = AuditConfig {
+            secret_size: 1,
+            error_sigma: 3.2,
+            flooding_sigma: None,
+            circuit_depth: 1,
+        };
+        let report = audit(&params, &config);
+        assert_eq!(report.security_bits, SIEVE_EXPONENT * 4096.0);
+        assert!(report.security_bits >= SECURITY_TARGET_BITS);
+    }
+
+    #[test]
+    fn test_gadget_optimal_for() {
+        let modulus = (BigUint::from(1u8) << 204) - 3u8;
+        let params = NativePolyParams::new(4096, modulus.clone(), 17);
+        let config = AuditConfig {
+            secret_size: 1,
+            error_sigma: 3.2,
+            flooding_sigma: None,
+            circuit_depth: 2,
+        };
+
+        // The chosen base fits the budget and agrees with the audit of parameters using it
+        let gadget = Gadget::optimal_for(&params, &config).unwrap();
+        assert!(gadget.correctness_slack_bits >= 0.0);
+        let chosen = NativePolyParams::new(4096, modulus, gadget.base_bits);
+        assert_eq!(gadget.digits, chosen.modulus_digits());
+        let report = audit(&chosen, &config);
+        assert_eq!(report.correctness_slack_bits, gadget.correctness_slack_bits);
+
+        // Deeper circuits force smaller bases, until no base is small enough
+        let deeper = Gadget::optimal_for(&params, &AuditConfig { circuit_depth: 4, ..config });
+        assert!(deeper.unwrap().base_bits < gadget.base_bits);
+        let too_deep = AuditConfig { circuit_depth: 100, ..config };
+        assert!(Gadget::optimal_for(&params, &too_deep).is_none());
+    }
+}
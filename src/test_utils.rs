@@ -2,16 +2,21 @@
 use crate::utils::calculate_tmp_size;
 use crate::{
     bgg::circuit::PolyCircuit,
-    io::{obf::obfuscate, params::ObfuscationParams, Obfuscation},
+    io::{
+        obf::obfuscate,
+        params::{KeygenConfig, ObfuscationParams},
+        Obfuscation,
+    },
     poly::{
         dcrt::{
             DCRTPoly, DCRTPolyHashSampler, DCRTPolyMatrix, DCRTPolyParams, DCRTPolyTrapdoorSampler,
             DCRTPolyUniformSampler, FinRingElem,
         },
+        rng::default_source,
         sampler::{DistType, PolyUniformSampler},
         Poly, PolyElem, PolyParams,
     },
-    utils::{calculate_directory_size, init_tracing, log_mem},
+    utils::{calculate_directory_size, init_tracing, log_mem, ThreadPoolConfig},
 };
 use keccak_asm::Keccak256;
 use num_bigint::BigUint;
@@ -76,6 +81,8 @@ pub async fn test_io_common(
         hardcoded_key_sigma,
         p_sigma,
         trapdoor_sigma: SIGMA,
+        keygen: KeygenConfig::default(),
+        key_cache: None,
     };
 
     let sampler_uniform = DCRTPolyUniformSampler::new();
@@ -88,8 +95,13 @@ pub async fn test_io_common(
         DCRTPolyHashSampler<Keccak256>,
         DCRTPolyTrapdoorSampler,
         _,
-        _,
-    >(obf_params.clone(), hardcoded_key.clone(), &mut rng, &dir_path)
+    >(
+        obf_params.clone(),
+        hardcoded_key.clone(),
+        default_source(),
+        &dir_path,
+        &ThreadPoolConfig::global(),
+    )
     .await;
     let obfuscation_time = start_time.elapsed();
     info!("Time to obfuscate: {:?}", obfuscation_time);
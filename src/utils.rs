@@ -1,16 +1,32 @@
-use std::{env, path::Path};
+use std::{
+    env, fmt,
+    path::Path,
+    sync::{Arc, RwLock},
+};
 #[cfg(feature = "cpu")]
 use std::{thread, time};
 
+use crate::poly::Elem;
+#[cfg(feature = "openfhe")]
 use crate::poly::{
     dcrt::{DCRTPoly, DCRTPolyParams, DCRTPolyUniformSampler},
     sampler::{DistType, PolyUniformSampler},
     Poly,
 };
+#[cfg(all(test, feature = "openfhe"))]
+use crate::{
+    bgg::{
+        sampler::{BGGEncodingSampler, BGGPublicKeySampler},
+        BggEncoding, BggPublicKey,
+    },
+    poly::dcrt::{DCRTPolyHashSampler, DCRTPolyMatrix},
+};
+#[cfg(all(test, feature = "openfhe"))]
+use keccak_asm::Keccak256;
 use memory_stats::memory_stats;
 use num_bigint::BigUint;
 use num_traits::{One, Zero};
-use rayon::prelude::*;
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
 #[cfg(feature = "cpu")]
 use sysinfo::{CpuRefreshKind, RefreshKind, System};
 #[cfg(feature = "disk")]
@@ -22,17 +38,49 @@ pub fn ceil_log2(q: &BigUint) -> usize {
     assert!(!q.is_zero(), "log2 is undefined for zero");
 
     let bits = q.bits() as usize;
-    if q & (q - BigUint::one()) == BigUint::zero() {
+    if is_power_of_two(q) {
         bits - 1
     } else {
         bits
     }
 }
 
+/// Returns `⌊log2(q)⌋`.
+pub fn floor_log2(q: &BigUint) -> usize {
+    assert!(!q.is_zero(), "log2 is undefined for zero");
+    bit_length(q) - 1
+}
+
+/// Number of bits needed to represent `q`, which is 0 for `q = 0`.
+pub fn bit_length(q: &BigUint) -> usize {
+    q.bits() as usize
+}
+
+pub fn is_power_of_two(q: &BigUint) -> bool {
+    !q.is_zero() && (q & (q - BigUint::one())).is_zero()
+}
+
+/// Number of base-`b` digits needed to represent every value in `[0, q)`, i.e. the smallest `k`
+/// with `b^k >= q`. For `q = b^k` exactly this is `k`.
+pub fn digits_base_b(q: &BigUint, b: u32) -> usize {
+    assert!(b >= 2, "base must be at least 2");
+    assert!(!q.is_zero(), "digit count is undefined for zero");
+    if b.is_power_of_two() {
+        return ceil_log2(q).div_ceil(b.trailing_zeros() as usize);
+    }
+    let mut digits = 0;
+    let mut power = BigUint::one();
+    while &power < q {
+        power *= b;
+        digits += 1;
+    }
+    digits
+}
+
 /// Print a ring element
-pub fn print_ring_element(label: &str, ring_el: &[u64]) {
+pub fn print_ring_element(label: &str, ring_el: &[Elem]) {
     print!("{} [", label);
-    for (k, &val) in ring_el.iter().enumerate() {
+    for (k, val) in ring_el.iter().enumerate() {
         if k > 0 {
             print!(", ");
         }
@@ -42,7 +90,7 @@ pub fn print_ring_element(label: &str, ring_el: &[u64]) {
 }
 
 /// Print a matrix of ring elements
-pub fn print_matrix_ring(label: &str, matrix: &[Vec<Vec<u64>>]) {
+pub fn print_matrix_ring(label: &str, matrix: &[Vec<Vec<Elem>>]) {
     println!("\n{}", label,);
 
     for (i, row) in matrix.iter().enumerate() {
@@ -54,7 +102,7 @@ pub fn print_matrix_ring(label: &str, matrix: &[Vec<Vec<u64>>]) {
 }
 
 /// Print a vector of ring elements
-pub fn print_vector_ring(label: &str, vec: &[Vec<u64>]) {
+pub fn print_vector_ring(label: &str, vec: &[Vec<Elem>]) {
     println!("\n{}", label);
     for (i, inner_vec) in vec.iter().enumerate() {
         print!("{}[{}]: ", label, i);
@@ -63,17 +111,20 @@ pub fn print_vector_ring(label: &str, vec: &[Vec<u64>]) {
 }
 
 // Helper function to create a random polynomial using UniformSampler
+#[cfg(feature = "openfhe")]
 pub fn create_random_poly(params: &DCRTPolyParams) -> DCRTPoly {
     let sampler = DCRTPolyUniformSampler::new();
     sampler.sample_poly(params, &DistType::FinRingDist)
 }
 
+#[cfg(feature = "openfhe")]
 pub fn create_bit_random_poly(params: &DCRTPolyParams) -> DCRTPoly {
     let sampler = DCRTPolyUniformSampler::new();
     sampler.sample_poly(params, &DistType::BitDist)
 }
 
 // Helper function to create a bit polynomial (0 or 1)
+#[cfg(feature = "openfhe")]
 pub fn create_bit_poly(params: &DCRTPolyParams, bit: bool) -> DCRTPoly {
     if bit {
         DCRTPoly::const_one(params)
@@ -82,6 +133,41 @@ pub fn create_bit_poly(params: &DCRTPolyParams, bit: bool) -> DCRTPoly {
     }
 }
 
+/// The samplers the tests of attribute encodings share: default parameters, a random hash key
+/// and a random bit secret of `d` polynomials, encoding without noise.
+#[cfg(all(test, feature = "openfhe"))]
+pub(crate) struct TestBggSetup {
+    pub params: DCRTPolyParams,
+    pub pubkey_sampler: BGGPublicKeySampler<[u8; 32], DCRTPolyHashSampler<Keccak256>>,
+    pub encoding_sampler: BGGEncodingSampler<DCRTPolyUniformSampler>,
+}
+
+#[cfg(all(test, feature = "openfhe"))]
+impl TestBggSetup {
+    pub fn new(d: usize) -> Self {
+        let params = DCRTPolyParams::default();
+        let key: [u8; 32] = rand::random();
+        let pubkey_sampler = BGGPublicKeySampler::new(key, d);
+        let secrets = vec![create_bit_random_poly(&params); d];
+        let uniform_sampler = DCRTPolyUniformSampler::new();
+        let encoding_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+        Self { params, pubkey_sampler, encoding_sampler }
+    }
+
+    /// Samples the public keys for `tag` and encodings of random plaintexts in every slot.
+    pub fn sample(
+        &self,
+        tag: &[u8],
+        reveal_plaintexts: &[bool],
+    ) -> (Vec<BggPublicKey<DCRTPolyMatrix>>, Vec<BggEncoding<DCRTPolyMatrix>>) {
+        let pubkeys = self.pubkey_sampler.sample(&self.params, tag, reveal_plaintexts);
+        let plaintexts =
+            reveal_plaintexts.iter().map(|_| create_random_poly(&self.params)).collect::<Vec<_>>();
+        let encodings = self.encoding_sampler.sample(&self.params, &pubkeys, &plaintexts);
+        (pubkeys, encodings)
+    }
+}
+
 pub fn log_mem<T: Into<String>>(tag: T) {
     if let Some(usage) = memory_stats() {
         info!(
@@ -132,6 +218,21 @@ pub fn debug_mem<T: Into<String>>(tag: T) {
     }
 }
 
+/// Returns the memory freed by the C++ side to the operating system. glibc keeps freed memory
+/// for reuse, so without it a long-running service stays at the peak of its largest request.
+/// Returns whether any memory was released, which is always false on other platforms.
+pub fn trim_heap() -> bool {
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    {
+        // SAFETY: malloc_trim only releases free pages of the allocator.
+        unsafe { libc::malloc_trim(0) == 1 }
+    }
+    #[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+    {
+        false
+    }
+}
+
 pub fn init_tracing() {
     tracing_subscriber::fmt::init();
 }
@@ -140,6 +241,134 @@ pub fn block_size() -> usize {
     env::var("BLOCK_SIZE").map(|str| str.parse::<usize>().unwrap()).unwrap_or(100)
 }
 
+/// The number of columns of `G^-1(B)` that a multiplication by a public key `B` materializes at
+/// once, set by `MUL_BLOCK_SIZE`. Unset keeps the whole decomposition.
+pub fn mul_block_size() -> Option<usize> {
+    env::var("MUL_BLOCK_SIZE").ok().map(|str| str.parse::<usize>().unwrap())
+}
+
+/// Switches for the nested levels of parallelism, so that users can tune the nesting for their
+/// core counts. Installed with [`set_parallelism_config`], or else read once from the
+/// environment by [`ParallelismConfig::from_env`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParallelismConfig {
+    /// Process the RNS towers of a DCRT polynomial in parallel.
+    pub towers: bool,
+    /// Generate the columns of a matrix row in parallel.
+    pub columns: bool,
+    /// Evaluate the gates of the same circuit level in parallel.
+    pub gates: bool,
+    /// Derive the matrices of [`crate::poly::sampler::PolyHashSampler::sample_hash_many`] in
+    /// parallel.
+    pub matrices: bool,
+}
+
+impl Default for ParallelismConfig {
+    fn default() -> Self {
+        Self::new(true, true, true, true)
+    }
+}
+
+/// An environment variable read by [`ParallelismConfig::from_env`] that is neither `true` nor
+/// `false`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParallelismConfigError {
+    pub var: &'static str,
+    pub value: String,
+}
+
+impl fmt::Display for ParallelismConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} must be `true` or `false`, got `{}`", self.var, self.value)
+    }
+}
+
+impl std::error::Error for ParallelismConfigError {}
+
+impl ParallelismConfig {
+    pub fn new(towers: bool, columns: bool, gates: bool, matrices: bool) -> Self {
+        Self { towers, columns, gates, matrices }
+    }
+
+    /// Enables each level unless the corresponding environment variable (`PARALLEL_TOWERS`,
+    /// `PARALLEL_COLUMNS`, `PARALLEL_GATES`, `PARALLEL_MATRICES`) is set to `false`.
+    pub fn from_env() -> Result<Self, ParallelismConfigError> {
+        Self::from_vars(|var| env::var(var).ok())
+    }
+
+    fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ParallelismConfigError> {
+        let flag = |var: &'static str| match lookup(var) {
+            None => Ok(true),
+            Some(value) => value.parse::<bool>().map_err(|_| ParallelismConfigError { var, value }),
+        };
+        Ok(Self::new(
+            flag("PARALLEL_TOWERS")?,
+            flag("PARALLEL_COLUMNS")?,
+            flag("PARALLEL_GATES")?,
+            flag("PARALLEL_MATRICES")?,
+        ))
+    }
+}
+
+static PARALLELISM_CONFIG: RwLock<Option<ParallelismConfig>> = RwLock::new(None);
+
+/// Installs `config` for all later operations in the process, in place of the environment.
+pub fn set_parallelism_config(config: ParallelismConfig) {
+    *PARALLELISM_CONFIG.write().unwrap() = Some(config);
+}
+
+/// The installed [`ParallelismConfig`], read from the environment on first use if none was
+/// installed. Panics if an environment variable is not a boolean.
+pub fn parallelism_config() -> ParallelismConfig {
+    if let Some(config) = *PARALLELISM_CONFIG.read().unwrap() {
+        return config;
+    }
+    let config = ParallelismConfig::from_env().unwrap_or_else(|err| panic!("{}", err));
+    *PARALLELISM_CONFIG.write().unwrap().get_or_insert(config)
+}
+
+/// The rayon pool the parallel work of an entry point such as
+/// [`crate::bgg::circuit::PolyCircuit::eval_streaming`] runs in, so that applications embedding
+/// the library, e.g. in an async runtime, can keep it off the global pool. Results do not depend
+/// on the pool or its number of threads, since arithmetic mod `q` is exact.
+#[derive(Debug, Clone, Default)]
+pub struct ThreadPoolConfig {
+    pool: Option<Arc<ThreadPool>>,
+}
+
+impl ThreadPoolConfig {
+    /// Runs on the global rayon pool.
+    pub fn global() -> Self {
+        Self { pool: None }
+    }
+
+    /// Runs on a pool supplied by the application.
+    pub fn with_pool(pool: Arc<ThreadPool>) -> Self {
+        Self { pool: Some(pool) }
+    }
+
+    /// Runs on a dedicated pool of `num_threads` threads.
+    pub fn with_num_threads(num_threads: usize) -> Result<Self, ThreadPoolBuildError> {
+        let pool = ThreadPoolBuilder::new().num_threads(num_threads).build()?;
+        Ok(Self::with_pool(Arc::new(pool)))
+    }
+
+    pub fn current_num_threads(&self) -> usize {
+        match &self.pool {
+            Some(pool) => pool.current_num_threads(),
+            None => rayon::current_num_threads(),
+        }
+    }
+
+    /// Runs `op` in the pool, blocking the calling thread until it returns.
+    pub fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        match &self.pool {
+            Some(pool) => pool.install(op),
+            None => op(),
+        }
+    }
+}
+
 /// Calculate the total size of a directory in bytes
 pub fn calculate_directory_size<P: AsRef<Path>>(path: P) -> u64 {
     WalkDir::new(path)
@@ -204,3 +433,97 @@ macro_rules! impl_binop_with_refs {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Smallest `k` with `b^k >= q`, computed on `u128`.
+    fn digits_naive(q: u128, b: u128) -> usize {
+        let mut digits = 0;
+        let mut power = 1u128;
+        while power < q {
+            power *= b;
+            digits += 1;
+        }
+        digits
+    }
+
+    #[test]
+    fn test_log2_helpers() {
+        for q in 1u64..=1025 {
+            let big = BigUint::from(q);
+            assert_eq!(bit_length(&big), 64 - q.leading_zeros() as usize);
+            assert_eq!(floor_log2(&big), 63 - q.leading_zeros() as usize);
+            assert_eq!(ceil_log2(&big), digits_naive(q as u128, 2));
+            assert_eq!(is_power_of_two(&big), q.is_power_of_two());
+        }
+        assert_eq!(bit_length(&BigUint::zero()), 0);
+        assert!(!is_power_of_two(&BigUint::zero()));
+
+        // Exact powers of two and their neighbours far beyond u64
+        let power = BigUint::one() << 200;
+        assert_eq!(ceil_log2(&power), 200);
+        assert_eq!(floor_log2(&power), 200);
+        assert_eq!(ceil_log2(&(&power + 1u8)), 201);
+        assert_eq!(floor_log2(&(&power - 1u8)), 199);
+        assert!(is_power_of_two(&power));
+        assert!(!is_power_of_two(&(&power + 1u8)));
+    }
+
+    #[test]
+    fn test_digits_base_b() {
+        for b in 2u32..=17 {
+            for q in 1u128..=2000 {
+                assert_eq!(
+                    digits_base_b(&BigUint::from(q), b),
+                    digits_naive(q, b as u128),
+                    "q = {}, b = {}",
+                    q,
+                    b
+                );
+            }
+            // q exactly a power of the base and one above it
+            for k in 1..=20u32 {
+                let q = BigUint::from(b).pow(k);
+                assert_eq!(digits_base_b(&q, b), k as usize);
+                assert_eq!(digits_base_b(&(&q + 1u8), b), k as usize + 1);
+                if k > 1 || b > 2 {
+                    assert_eq!(digits_base_b(&(&q - 1u8), b), k as usize);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_thread_pool_config() {
+        // A dedicated pool runs the work on its own threads
+        let pool = ThreadPoolConfig::with_num_threads(3).unwrap();
+        assert_eq!(pool.current_num_threads(), 3);
+        assert_eq!(pool.install(rayon::current_num_threads), 3);
+        assert!(pool.install(rayon::current_thread_index).is_some());
+
+        // The global pool runs it on the calling thread
+        let global = ThreadPoolConfig::global();
+        assert_eq!(global.current_num_threads(), rayon::current_num_threads());
+        assert_eq!(global.install(rayon::current_thread_index), None);
+    }
+
+    #[test]
+    fn test_parallelism_config() {
+        // Unset variables enable their level
+        let vars = [("PARALLEL_GATES", "false")];
+        let lookup = |var: &str| vars.iter().find(|(v, _)| *v == var).map(|(_, x)| x.to_string());
+        let config = ParallelismConfig::from_vars(lookup).unwrap();
+        assert_eq!(config, ParallelismConfig::new(true, true, false, true));
+
+        // Values other than `true` and `false` are reported with their variable
+        let err = ParallelismConfig::from_vars(|_| Some("yes".to_string())).unwrap_err();
+        assert_eq!(err, ParallelismConfigError { var: "PARALLEL_TOWERS", value: "yes".into() });
+        assert_eq!(err.to_string(), "PARALLEL_TOWERS must be `true` or `false`, got `yes`");
+
+        // An installed config takes precedence over the environment
+        set_parallelism_config(ParallelismConfig::default());
+        assert_eq!(parallelism_config(), ParallelismConfig::default());
+    }
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "keygen")]
+
 use diamond_io::{
     bgg::{circuit::PolyCircuit, sampler::BGGPublicKeySampler, BggPublicKey, DigitsToInt},
     io::utils::build_final_digits_circuit,
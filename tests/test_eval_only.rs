@@ -0,0 +1,68 @@
+#![cfg(feature = "eval-only")]
+
+//! The attribute-side evaluator and the zero test over the native backend, built without
+//! OpenFHE: `cargo test --no-default-features --features eval-only --test test_eval_only`.
+
+use diamond_io::{
+    bgg::{
+        circuit::PolyCircuit,
+        sampler::{BGGEncodingSampler, BGGPublicKeySampler},
+    },
+    poly::{
+        dcrt::FinRingElem,
+        native::{NativePoly, NativePolyHashSampler, NativePolyParams, NativePolyUniformSampler},
+        sampler::{DistType, PolyUniformSampler},
+        zero_test::{zero_test, ZeroTestMode},
+        Poly, PolyElem, PolyParams,
+    },
+};
+use keccak_asm::Keccak256;
+use num_bigint::BigUint;
+
+#[test]
+fn test_native_eval_only_circuit() {
+    let params = NativePolyParams::new(4, (BigUint::from(1u8) << 110) - 1u8, 10);
+    let key: [u8; 32] = rand::random();
+    let d = 2;
+    let bgg_sampler = BGGPublicKeySampler::<_, NativePolyHashSampler<Keccak256>>::new(key, d);
+    let pubkeys = bgg_sampler.sample_attributes(&params, b"eval-only", &[true, true]);
+    let uniform_sampler = NativePolyUniformSampler::new();
+    let secrets = (0..d)
+        .map(|_| uniform_sampler.sample_poly(&params, &DistType::BitDist))
+        .collect::<Vec<_>>();
+    let plaintexts = (0..2)
+        .map(|_| uniform_sampler.sample_poly(&params, &DistType::BitDist))
+        .collect::<Vec<_>>();
+    let bgg_sampler = BGGEncodingSampler::new(&params, &secrets, uniform_sampler, 0.0);
+    let encodings = bgg_sampler.sample_attributes(&params, &pubkeys, &plaintexts);
+
+    // f(x) = x1 * x2 + x1 is evaluated on the attribute side under the key-side output
+    let mut circuit = PolyCircuit::new();
+    let inputs = circuit.input(2);
+    let mul_gate = circuit.mul_gate(inputs[0], inputs[1]);
+    let add_gate = circuit.add_gate(mul_gate, inputs[0]);
+    circuit.output(vec![add_gate]);
+    let output = encodings.eval(&params, &circuit).remove(0);
+    let expected = &plaintexts[0] * &plaintexts[1] + &plaintexts[0];
+    assert_eq!(output.plaintext, Some(expected));
+    assert_eq!(output.pubkey, pubkeys.eval(&params, &circuit).remove(0));
+}
+
+#[test]
+fn test_native_eval_only_zero_test() {
+    let params = NativePolyParams::new(4, (BigUint::from(1u8) << 110) - 1u8, 10);
+    let modulus = params.modulus();
+    let quarter_q = FinRingElem::half_q(&modulus).value() >> 1;
+    let three_quarter_q = &quarter_q * 3u32;
+
+    // Coefficients on both sides of both thresholds
+    let values = [&quarter_q - 1u8, quarter_q.clone(), &three_quarter_q - 1u8, three_quarter_q];
+    let coeffs = values
+        .into_iter()
+        .map(|value| FinRingElem::new(value, modulus.clone()))
+        .collect::<Vec<_>>();
+    let poly = NativePoly::from_coeffs(&params, &coeffs);
+    let bits = zero_test(&params, &poly, ZeroTestMode::ConstantTime);
+    assert_eq!(bits, vec![false, true, true, false]);
+    assert_eq!(bits, zero_test(&params, &poly, ZeroTestMode::Fast));
+}
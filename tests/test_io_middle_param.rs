@@ -1,3 +1,5 @@
+#![cfg(feature = "keygen")]
+
 #[cfg(test)]
 mod test {
     use diamond_io::test_utils::test_io_common;